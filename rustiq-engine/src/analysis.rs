@@ -0,0 +1,225 @@
+//! Offline analysis of IQ recordings, for command-line tools that use the
+//! engine's DSP without running the engine, and for the overview of a file
+//! the engine plays.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use rustiq_messages::{Decibels, Hertz, IqFormat, RecordingOverview, RustIqError, SignalRegion};
+use rustradio::Complex;
+
+use super::dsp::{SpectrumSurvey, find_signals, noise_floor};
+use super::sources::IqFileSource;
+
+/// Samples read from the file at a time.
+const CHUNK: usize = 65_536;
+
+/// Settings for `analyze_file`.
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Sample rate of the recording.
+    pub sample_rate: Hertz,
+    /// Frequency the recording was centered on, added to signal offsets.
+    pub center_frequency: Hertz,
+    /// FFT size; sets the frequency resolution.
+    pub fft_size: usize,
+    /// Rise above the noise floor that counts as a signal.
+    pub threshold: Decibels,
+    /// Most spectrogram rows to produce. Blocks are averaged to fit.
+    pub max_rows: usize,
+    /// Regions left out of the noise floor and the signals found, e.g. a
+    /// strong local transmitter.
+    pub excluded: Vec<SignalRegion>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            sample_rate: Hertz(3_200_000),
+            center_frequency: Hertz(0),
+            fft_size: 4096,
+            threshold: Decibels(10.0),
+            max_rows: 1024,
+            excluded: Vec::new(),
+        }
+    }
+}
+
+/// A signal found in a recording.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Signal {
+    /// Frequency of the signal's strongest bin.
+    pub frequency: Hertz,
+    /// Width down to 20 dB below the peak, in whole bins.
+    pub bandwidth: Hertz,
+    /// Width down to 3 dB below the peak.
+    pub half_power_bandwidth: Hertz,
+    /// Width holding 99% of the signal's power above the noise floor.
+    pub occupied_bandwidth: Hertz,
+    /// Total power of the signal relative to a full-scale tone.
+    pub power: Decibels,
+    /// Peak power over the noise floor.
+    pub snr: Decibels,
+}
+
+/// Summary of a recording from `analyze_file`.
+#[derive(Debug, Clone)]
+pub struct FileAnalysis {
+    pub duration: Duration,
+    /// Median bin power relative to a full-scale tone, of the bins not
+    /// excluded.
+    pub noise_floor: Decibels,
+    /// Signals above the threshold, strongest first.
+    pub signals: Vec<Signal>,
+    /// Width of one spectrogram bin.
+    pub resolution: f64,
+    /// Spectrogram rows in dB, oldest first, lowest frequency first.
+    pub spectrogram: Vec<Vec<Decibels>>,
+    /// Whether each bin lies in an excluded region.
+    pub excluded: Vec<bool>,
+}
+
+/// Analyze an IQ recording in the engine's file format (interleaved
+/// native-endian `f32` I and Q).
+pub fn analyze_file(path: &Path, options: &AnalysisOptions) -> io::Result<FileAnalysis> {
+    let file = File::open(path)?;
+    let samples = file.metadata()?.len() as usize / size_of::<Complex>();
+    let frames = samples / options.fft_size;
+    if frames == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} holds fewer than {} samples",
+                path.display(),
+                options.fft_size
+            ),
+        ));
+    }
+    let frames_per_row = frames.div_ceil(options.max_rows.max(1));
+    let mut survey = SpectrumSurvey::new(options.fft_size, frames_per_row);
+
+    let mut reader = BufReader::new(file);
+    let mut bytes = vec![0; CHUNK * size_of::<Complex>()];
+    loop {
+        let n = read_up_to(&mut reader, &mut bytes)?;
+        let chunk: Vec<Complex> = bytes[..n - n % size_of::<Complex>()]
+            .chunks_exact(size_of::<Complex>())
+            .map(|sample| {
+                let re = f32::from_ne_bytes(sample[..4].try_into().expect("4 bytes"));
+                let im = f32::from_ne_bytes(sample[4..].try_into().expect("4 bytes"));
+                Complex::new(re, im)
+            })
+            .collect();
+        survey.process(&chunk);
+        if n < bytes.len() {
+            break;
+        }
+    }
+
+    let sample_rate = options.sample_rate.as_hz() as f64;
+    let resolution = sample_rate / options.fft_size as f64;
+    let excluded: Vec<bool> = (0..options.fft_size)
+        .map(|bin| {
+            let offset = (bin as f64 - (options.fft_size / 2) as f64) * resolution;
+            let frequency = options.center_frequency.as_hz() as f64 + offset;
+            options
+                .excluded
+                .iter()
+                .any(|region| region.contains(frequency))
+        })
+        .collect();
+    let mut average = survey.average();
+    let included: Vec<f32> = average
+        .iter()
+        .zip(&excluded)
+        .filter(|(_, excluded)| !**excluded)
+        .map(|(&power, _)| power)
+        .collect();
+    let floor = noise_floor(&included);
+    // Excluded bins sit at the floor, so nothing is found in them
+    for (power, _) in average.iter_mut().zip(&excluded).filter(|(_, e)| **e) {
+        *power = floor;
+    }
+    let signals = find_signals(&average, floor, options.threshold.to_power())
+        .into_iter()
+        .map(|signal| {
+            let offset = (signal.peak_bin as f64 - (options.fft_size / 2) as f64) * resolution;
+            let frequency = (options.center_frequency.as_hz() as f64 + offset).max(0.0);
+            let width = |bins: f64| Hertz((bins * resolution).round() as u64);
+            Signal {
+                frequency: Hertz(frequency.round() as u64),
+                bandwidth: width(signal.width_bins as f64),
+                half_power_bandwidth: width(signal.half_power_bins as f64),
+                occupied_bandwidth: width(signal.occupied_bins as f64),
+                power: Decibels::from_power(signal.power),
+                snr: Decibels::from_power(signal.peak / floor),
+            }
+        })
+        .collect();
+
+    Ok(FileAnalysis {
+        duration: Duration::from_secs_f64(samples as f64 / sample_rate),
+        noise_floor: Decibels::from_power(floor),
+        signals,
+        resolution,
+        spectrogram: survey
+            .rows()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&power| Decibels::from_power(power))
+                    .collect()
+            })
+            .collect(),
+        excluded,
+    })
+}
+
+/// Spectrogram of a whole recording in `format`, of at most `max_rows`
+/// rows of `fft_size` bins. A WAV header's sample rate is taken over
+/// `sample_rate`.
+pub fn overview(
+    path: &Path,
+    format: IqFormat,
+    sample_rate: Hertz,
+    fft_size: usize,
+    max_rows: usize,
+) -> Result<RecordingOverview, RustIqError> {
+    let (mut source, _) = IqFileSource::new(path, format)?;
+    let sample_rate = source.sample_rate().unwrap_or(sample_rate);
+    let samples = source.samples();
+    let frames = (samples / fft_size as u64) as usize;
+    let frames_per_row = frames.div_ceil(max_rows.max(1)).max(1);
+    let mut survey = SpectrumSurvey::new(fft_size, frames_per_row);
+    while let Some(chunk) = source
+        .read_samples(CHUNK)
+        .map_err(|e| RustIqError::Format {
+            path: path.to_path_buf(),
+            detail: e.to_string(),
+        })?
+    {
+        survey.process(&chunk);
+    }
+    let rate = sample_rate.as_hz().max(1) as f64;
+    Ok(RecordingOverview {
+        path: path.to_path_buf(),
+        sample_rate,
+        duration: Duration::from_secs_f64(samples as f64 / rate),
+        row_duration: Duration::from_secs_f64((frames_per_row * fft_size) as f64 / rate),
+        rows: survey.rows().to_vec(),
+    })
+}
+
+/// Fill `buf` from `reader`, returning fewer bytes only at end of file.
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,107 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+
+use rustiq_messages::{AntennaSwitchConfig, AntennaSwitchLink};
+
+/// Where Linux exposes GPIO pins exported through sysfs.
+const GPIO_ROOT: &str = "/sys/class/gpio";
+
+/// How long to wait for a network relay to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Switch to antenna `index` of `config`.
+pub fn select(config: &AntennaSwitchConfig, index: usize) -> io::Result<()> {
+    let antenna = config.antennas.get(index).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No antenna {index} on the switch"),
+        )
+    })?;
+    let command = unescape(&antenna.command);
+    match &config.link {
+        AntennaSwitchLink::Serial { path } => {
+            let mut port = OpenOptions::new().write(true).open(path)?;
+            port.write_all(&command)?;
+            port.flush()
+        }
+        AntennaSwitchLink::Network { address } => {
+            let addresses = std::net::ToSocketAddrs::to_socket_addrs(address.as_str())?;
+            let mut last_error = io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{address} did not resolve"),
+            );
+            for address in addresses {
+                match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                    Ok(mut stream) => return stream.write_all(&command),
+                    Err(e) => last_error = e,
+                }
+            }
+            Err(last_error)
+        }
+        AntennaSwitchLink::Gpio { pins } => {
+            for (bit, pin) in pins.iter().enumerate() {
+                let value = if index >> bit & 1 == 1 { b"1" } else { b"0" };
+                let path = Path::new(GPIO_ROOT).join(format!("gpio{pin}/value"));
+                std::fs::write(path, value)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Expand `\r`, `\n`, `\t`, `\\` and `\xNN` escapes in a switch command.
+/// Anything else after a backslash is kept as is.
+fn unescape(command: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.clone().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => {
+                        bytes.push(byte);
+                        chars.nth(1);
+                    }
+                    _ => bytes.extend_from_slice(b"\\x"),
+                }
+            }
+            Some(other) => {
+                bytes.push(b'\\');
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => bytes.push(b'\\'),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_escapes() {
+        assert_eq!(unescape(r"ANT 2\r\n"), b"ANT 2\r\n");
+        assert_eq!(unescape(r"\xA0\x01\x01\xA2"), [0xA0, 0x01, 0x01, 0xA2]);
+        assert_eq!(unescape(r"a\\b"), b"a\\b");
+    }
+
+    #[test]
+    fn keeps_unknown_escapes() {
+        assert_eq!(unescape(r"\q\xZZ\"), b"\\q\\xZZ\\");
+    }
+}
@@ -0,0 +1,173 @@
+//! Where the listening channel's audio goes besides, or instead of, the UI:
+//! a WAV recording and UDP listeners. Set from `AudioRouting`, and shared
+//! by the engine with the audio sink of each graph, so a change takes effect
+//! without a rebuild and a recording carries on across rebuilds.
+
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Context;
+use log::warn;
+use rustiq_messages::AudioRouting;
+
+/// Length of the header `WavRecording` writes.
+const HEADER_BYTES: u32 = 44;
+
+/// The audio outputs for a routing, shared between the engine and the
+/// graphs it builds.
+#[derive(Clone, Default)]
+pub struct AudioRoutes(Arc<Mutex<Routes>>);
+
+#[derive(Default)]
+struct Routes {
+    routing: AudioRouting,
+    recording: Option<WavRecording>,
+    network: Option<NetworkAudio>,
+}
+
+impl AudioRoutes {
+    pub fn routing(&self) -> AudioRouting {
+        self.lock().routing.clone()
+    }
+
+    /// Route the audio as `routing` says. A recording to the same path as
+    /// before carries on; on an error the routing is left as it was.
+    pub fn set(&self, routing: AudioRouting) -> anyhow::Result<()> {
+        let mut routes = self.lock();
+        let recording = match &routing.recording {
+            Some(path) if routes.routing.recording.as_ref() == Some(path) => {
+                routes.recording.take()
+            }
+            Some(path) => Some(WavRecording::create(path)?),
+            None => None,
+        };
+        let network = match routing.network.as_slice() {
+            [] => None,
+            addresses => Some(NetworkAudio::connect(addresses)?),
+        };
+        *routes = Routes {
+            routing,
+            recording,
+            network,
+        };
+        Ok(())
+    }
+
+    /// Send `samples` at `sample_rate` to the recording and the network
+    /// listeners. Returns whether they also go to the speakers.
+    pub fn deliver(&self, sample_rate: f64, samples: &[f32]) -> bool {
+        let mut routes = self.lock();
+        let pcm: Vec<i16> = samples
+            .iter()
+            .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .collect();
+        if let Some(recording) = &mut routes.recording
+            && let Err(e) = recording.write(sample_rate, &pcm)
+        {
+            warn!("Stopped recording audio: {:#}", e);
+            routes.recording = None;
+            routes.routing.recording = None;
+        }
+        if let Some(network) = &routes.network {
+            network.send(&pcm);
+        }
+        routes.routing.speakers
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Routes> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A 16-bit mono WAV file, its header rewritten after each write so it is
+/// valid however the recording ends. The sample rate is that of the first
+/// audio written.
+struct WavRecording {
+    file: File,
+    sample_rate: Option<u32>,
+    data_bytes: u32,
+}
+
+impl WavRecording {
+    fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        Ok(Self {
+            file,
+            sample_rate: None,
+            data_bytes: 0,
+        })
+    }
+
+    fn write(&mut self, sample_rate: f64, pcm: &[i16]) -> anyhow::Result<()> {
+        let rate = sample_rate.round() as u32;
+        match self.sample_rate {
+            None => self.sample_rate = Some(rate),
+            // Audio at another rate would play at the wrong speed
+            Some(recorded) if recorded != rate => return Ok(()),
+            Some(_) => {}
+        }
+        let bytes: Vec<u8> = pcm.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        self.file
+            .seek(SeekFrom::Start((HEADER_BYTES + self.data_bytes) as u64))?;
+        self.file.write_all(&bytes)?;
+        self.data_bytes += bytes.len() as u32;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header(rate, self.data_bytes))?;
+        Ok(())
+    }
+}
+
+/// Header of a 16-bit mono PCM WAV file at `sample_rate` with `data_bytes`
+/// of samples.
+fn header(sample_rate: u32, data_bytes: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_BYTES as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_BYTES - 8 + data_bytes).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    // Bytes per frame, bits per sample
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_bytes.to_le_bytes());
+    header
+}
+
+/// Listeners sent the audio over UDP, a datagram for each chunk.
+struct NetworkAudio {
+    socket: UdpSocket,
+    addresses: Vec<SocketAddr>,
+}
+
+impl NetworkAudio {
+    fn connect(addresses: &[String]) -> anyhow::Result<Self> {
+        let addresses = addresses
+            .iter()
+            .map(|address| {
+                address
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut resolved| resolved.next())
+                    .with_context(|| format!("resolving {address}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let socket = UdpSocket::bind("0.0.0.0:0").context("binding a UDP socket")?;
+        Ok(Self { socket, addresses })
+    }
+
+    fn send(&self, pcm: &[i16]) {
+        let bytes: Vec<u8> = pcm.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        for address in &self.addresses {
+            // A listener that isn't there yet is no reason to stop
+            let _ = self.socket.send_to(&bytes, address);
+        }
+    }
+}
@@ -0,0 +1,728 @@
+//! Typed builder for chains of rustradio blocks. Each stage adds its block
+//! to the graph and hands on the block's output stream, so the stream types
+//! are checked from source to sink and optional stages are a method call
+//! rather than another round of manual wiring. Reusable groups of stages,
+//! like a channel's demodulator and decoder, are `SubGraph`s. A chain can
+//! end by fanning out to `Domain`s, pipelines each built in a graph of its
+//! own, which run side by side on threads of their own.
+
+use std::borrow::Cow;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use flume::Sender;
+use log::warn;
+use rustradio::block::Block;
+use rustradio::blocks::{FftStream, Map, MultiplyConst, Tee};
+use rustradio::graph::{CancellationToken, Graph, GraphRunner};
+use rustradio::stream::ReadStream;
+use rustradio::{Complex, Float, Sample};
+
+use rustiq_messages::{Decibels, Event, Hertz, RustIqError, SourceConfig, SourceKind};
+
+use super::audio_routing::AudioRoutes;
+use super::config::Overflow;
+use super::dsp::{HannWindow, Resample};
+use super::fanout::{self, FanOut};
+use super::playback::Playback;
+use super::sinks::{ChannelPassband, ListeningChannel, SquelchThreshold};
+use super::sources::{
+    IqFileSource, RTL_SDR_RATES, RtlTcpSource, SignalGenerator, SpyServerSource, hardware_rate,
+};
+use super::tuner::{LoShift, Tuner, lo_mixer};
+
+/// How long the domains that are waited for get to finish what is queued
+/// for them once the sources' graph stops, before they are stopped too.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Graphs under construction: the sources', and one for each domain
+/// fanned out to. The outline of their blocks indents branches, sub-graphs
+/// and domains under where they attach.
+pub struct Pipeline {
+    graphs: Vec<Graph>,
+    /// How each domain's graph, after the sources', is ended
+    ends: Vec<DomainEnd>,
+    outline: Vec<String>,
+    /// Rate the first source's hardware runs at, when its stream is
+    /// resampled from it
+    device_sample_rate: Option<Hertz>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self {
+            graphs: vec![Graph::new()],
+            ends: Vec::new(),
+            outline: Vec::new(),
+            device_sample_rate: None,
+        }
+    }
+
+    /// Rate the first source's hardware runs at, if its stream is resampled
+    /// to another.
+    pub fn device_sample_rate(&self) -> Option<Hertz> {
+        self.device_sample_rate
+    }
+
+    /// Token that stops all the graphs.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.graphs[0].cancel_token()
+    }
+
+    /// One line per block, sub-graph or domain, in the order added.
+    pub fn outline(&self) -> String {
+        self.outline.join("\n")
+    }
+
+    pub fn into_graph(self) -> Graphs {
+        Graphs {
+            graphs: self.graphs,
+            ends: self.ends,
+        }
+    }
+
+    fn add(&mut self, block: Box<dyn Block + Send>, graph: usize, depth: usize) {
+        self.note(block.block_name(), depth);
+        self.graphs[graph].add(block);
+    }
+
+    fn note(&mut self, line: &str, depth: usize) {
+        self.outline.push(format!("{}{}", "  ".repeat(depth), line));
+    }
+}
+
+/// The graphs of a pipeline, run together as one: each on a thread of its
+/// own, ending when the sources' graph does. Domains that are waited for
+/// finish what is queued for them first, so a recording keeps its last
+/// samples; the rest are stopped. A graph that fails stops the rest, and
+/// its error is the pipeline's.
+pub struct Graphs {
+    graphs: Vec<Graph>,
+    ends: Vec<DomainEnd>,
+}
+
+/// What ends a domain's graph: closing its input, so it finishes once it
+/// has used up what is queued, or, if it may drop samples, stopping it.
+struct DomainEnd {
+    name: &'static str,
+    overflow: Overflow,
+    close: CancellationToken,
+}
+
+impl Graphs {
+    pub fn run(&mut self) -> rustradio::Result<()> {
+        let (sources, domains) = self
+            .graphs
+            .split_first_mut()
+            .expect("a pipeline has the sources' graph");
+        let stop_sources = sources.cancel_token();
+        thread::scope(|scope| {
+            let running: Vec<_> = domains
+                .iter_mut()
+                .zip(&self.ends)
+                .map(|(graph, end)| {
+                    let stop = graph.cancel_token();
+                    let stop_sources = stop_sources.clone();
+                    let handle = scope.spawn(move || {
+                        let result = graph.run();
+                        if result.is_err() {
+                            stop_sources.cancel();
+                        }
+                        result
+                    });
+                    (end, stop, handle)
+                })
+                .collect();
+            let result = sources.run();
+            for (end, stop, _) in &running {
+                end.close.cancel();
+                if end.overflow == Overflow::Drop {
+                    stop.cancel();
+                }
+            }
+            let deadline = Instant::now() + DRAIN_TIMEOUT;
+            while running.iter().any(|(.., handle)| !handle.is_finished())
+                && Instant::now() < deadline
+            {
+                thread::sleep(Duration::from_millis(5));
+            }
+            running
+                .into_iter()
+                .fold(result, |result, (end, stop, handle)| {
+                    if !handle.is_finished() {
+                        warn!("The {} didn't finish in time; stopping it", end.name);
+                        stop.cancel();
+                    }
+                    let domain = handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    result.and(domain)
+                })
+        })
+    }
+
+    /// Token that stops all the graphs.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.graphs[0].cancel_token()
+    }
+}
+
+/// Adds a domain's stages to the chain starting its graph.
+type BuildDomain<'a> = Box<dyn for<'p> FnOnce(ChainBuilder<'p, Complex>) + 'a>;
+
+/// One of the pipelines a chain fans out to, run in a graph of its own so
+/// it keeps its own pace. When it falls behind, the fan-out does
+/// `overflow`: waits for it, holding up the source and every other domain,
+/// or drops what doesn't fit its queue.
+pub struct Domain<'a> {
+    name: &'static str,
+    overflow: Overflow,
+    build: BuildDomain<'a>,
+}
+
+impl<'a> Domain<'a> {
+    /// A domain named `name` in the outline and logs, its stages added by
+    /// `build`.
+    pub fn new(
+        name: &'static str,
+        overflow: Overflow,
+        build: impl for<'p> FnOnce(ChainBuilder<'p, Complex>) + 'a,
+    ) -> Self {
+        Self {
+            name,
+            overflow,
+            build: Box::new(build),
+        }
+    }
+}
+
+/// What a `SubGraph` is connected to besides its input stream.
+pub struct Ports {
+    /// Where the sub-graph's results go
+    pub event_tx: Sender<Event>,
+    /// Frequency of the input stream's DC
+    pub center_frequency: Hertz,
+    /// Where the listening channel's audio goes
+    pub audio_routes: AudioRoutes,
+    /// Level the listening channel is squelched at
+    pub squelch: SquelchThreshold,
+    /// Where the scanner has hopped the listening channel to
+    pub listening: ListeningChannel,
+    /// Passband the listening channel is filtered through, if not its
+    /// mode's
+    pub passband: ChannelPassband,
+    /// How the stream is played back, if it is from a file
+    pub playback: Option<Playback>,
+}
+
+/// A source of `kind` failing to open with `error`. A source that found
+/// its server speaking another protocol says so itself.
+fn source_error(kind: SourceKind, error: anyhow::Error) -> RustIqError {
+    error
+        .downcast::<RustIqError>()
+        .unwrap_or_else(|error| RustIqError::Source {
+            kind,
+            detail: format!("{error:#}"),
+        })
+}
+
+/// A reusable group of stages fed from the IQ stream, such as a channel's
+/// demodulator and decoder. All sub-graphs have the same ports: IQ samples
+/// in, events out.
+pub trait SubGraph {
+    /// Label for the sub-graph in the pipeline outline.
+    fn name(&self) -> String;
+
+    /// Add the sub-graph's stages, starting from `input`.
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports);
+}
+
+/// A chain of blocks in a `Pipeline`, so far ending in a stream of `T`.
+/// Finish it with `sink`; a chain dropped unfinished leaves its stream
+/// unread.
+#[must_use = "a chain must end in a sink"]
+pub struct ChainBuilder<'g, T> {
+    pipeline: &'g mut Pipeline,
+    stream: ReadStream<T>,
+    sample_rate: u64,
+    /// Graph the chain's blocks go in
+    graph: usize,
+    /// Nesting in the outline
+    depth: usize,
+}
+
+impl<'g> ChainBuilder<'g, Complex> {
+    /// Start a chain at the source block for `source_config`. Hardware
+    /// sources are tuned to the tuner's frequency, and follow it if they can.
+    pub fn source(
+        pipeline: &'g mut Pipeline,
+        source_config: SourceConfig,
+        tuner: &mut Tuner,
+    ) -> Result<Self, RustIqError> {
+        Self::source_from(pipeline, source_config, tuner, Duration::ZERO)
+    }
+
+    /// As `source`, with a file read from `start` into it. A device that
+    /// can't run at the rate asked for runs at the nearest above it, and
+    /// its stream is resampled down.
+    pub fn source_from(
+        pipeline: &'g mut Pipeline,
+        source_config: SourceConfig,
+        tuner: &mut Tuner,
+        start: Duration,
+    ) -> Result<Self, RustIqError> {
+        let kind = SourceKind::of(&source_config);
+        let first = pipeline.outline.is_empty();
+        let (stream, sample_rate, lo_offset, requested) = match source_config {
+            SourceConfig::SignalGenerator {
+                sample_rate,
+                tones,
+                snr,
+                sweep,
+            } => {
+                let (generator, stream) = SignalGenerator::new(sample_rate, &tones, snr, sweep);
+                pipeline.add(Box::new(generator), 0, 0);
+                (stream, sample_rate, Hertz(0), sample_rate)
+            }
+            SourceConfig::File {
+                path,
+                sample_rate,
+                format,
+            } => {
+                let (mut file_source, stream) = IqFileSource::new(&path, format)?;
+                // A WAV header knows better than the configured rate
+                let sample_rate = file_source.sample_rate().unwrap_or(sample_rate);
+                file_source
+                    .skip((start.as_secs_f64() * sample_rate.as_hz() as f64) as u64)
+                    .map_err(|e| RustIqError::Source {
+                        kind,
+                        detail: e.to_string(),
+                    })?;
+                pipeline.add(Box::new(file_source), 0, 0);
+                (stream, sample_rate, Hertz(0), sample_rate)
+            }
+            #[cfg(feature = "rtlsdr")]
+            SourceConfig::RtlSdr { sample_rate, gain } => {
+                use rustradio::blocks::{RtlSdrDecode, RtlSdrSource};
+
+                // Only reopening the dongle changes its frequency
+                tuner.mark_fixed();
+                let device_rate = hardware_rate(&RTL_SDR_RATES, sample_rate);
+                let lo_offset = tuner.lo_offset(device_rate);
+                let (rtlsdr_source, bytes) = RtlSdrSource::new(
+                    tuner.frequency().as_hz() + lo_offset.as_hz(),
+                    device_rate.as_hz() as u32,
+                    gain.0.round() as i32,
+                )
+                .map_err(|e| RustIqError::Source {
+                    kind,
+                    detail: e.to_string(),
+                })?;
+                pipeline.add(Box::new(rtlsdr_source), 0, 0);
+                let (decode, stream) = RtlSdrDecode::new(bytes);
+                pipeline.add(Box::new(decode), 0, 0);
+                (stream, device_rate, lo_offset, sample_rate)
+            }
+            #[cfg(not(feature = "rtlsdr"))]
+            SourceConfig::RtlSdr { .. } => {
+                unreachable!("the engine rejects RTL-SDR sources when built without them")
+            }
+            #[cfg(feature = "soapysdr")]
+            SourceConfig::SoapySdr {
+                device,
+                sample_rate,
+                antenna,
+                bandwidth,
+                gains,
+            } => {
+                let (soapy_source, stream, device_rate, lo_offset) =
+                    crate::soapy::open(&device, tuner, sample_rate, antenna, bandwidth, &gains)
+                        .map_err(|e| source_error(kind, e))?;
+                pipeline.add(Box::new(soapy_source), 0, 0);
+                (stream, device_rate, lo_offset, sample_rate)
+            }
+            #[cfg(not(feature = "soapysdr"))]
+            SourceConfig::SoapySdr { .. } => {
+                unreachable!("the engine rejects SoapySDR sources when built without them")
+            }
+            SourceConfig::RtlTcp {
+                host,
+                port,
+                sample_rate,
+                gain,
+            } => {
+                let device_rate = hardware_rate(&RTL_SDR_RATES, sample_rate);
+                let lo_offset = tuner.lo_offset(device_rate);
+                let (rtl_tcp_source, stream, mut control) = RtlTcpSource::connect(
+                    &format!("{host}:{port}"),
+                    Hertz(tuner.frequency().as_hz() + lo_offset.as_hz()),
+                    device_rate,
+                    gain,
+                )
+                .map_err(|e| source_error(kind, e))?;
+                tuner.follow(move |frequency| {
+                    control.set_frequency(Hertz(frequency.as_hz() + lo_offset.as_hz()))?;
+                    Ok(())
+                });
+                pipeline.add(Box::new(rtl_tcp_source), 0, 0);
+                (stream, device_rate, lo_offset, sample_rate)
+            }
+            SourceConfig::SpyServer {
+                host,
+                port,
+                sample_rate,
+                gain,
+            } => {
+                let lo_offset = tuner.lo_offset(sample_rate);
+                let (spyserver_source, stream, sample_rate, mut control) =
+                    SpyServerSource::connect(
+                        &format!("{host}:{port}"),
+                        Hertz(tuner.frequency().as_hz() + lo_offset.as_hz()),
+                        sample_rate,
+                        gain,
+                    )
+                    .map_err(|e| source_error(kind, e))?;
+                tuner.follow(move |frequency| {
+                    control.set_frequency(Hertz(frequency.as_hz() + lo_offset.as_hz()))?;
+                    Ok(())
+                });
+                pipeline.add(Box::new(spyserver_source), 0, 0);
+                (stream, sample_rate, lo_offset, sample_rate)
+            }
+        };
+        let mut chain = Self {
+            pipeline,
+            stream,
+            sample_rate: sample_rate.as_hz(),
+            graph: 0,
+            depth: 0,
+        };
+        // A hardware source tuned off the center is shifted back onto it
+        if lo_offset != Hertz(0) {
+            chain = chain.then(|src| LoShift::new(src, lo_mixer(sample_rate, lo_offset)));
+        }
+        if requested >= sample_rate {
+            return Ok(chain);
+        }
+        if first {
+            chain.pipeline.device_sample_rate = Some(sample_rate);
+        }
+        Ok(chain.resample(requested))
+    }
+
+    /// Branch off into `sub_graph`, as `branch` does.
+    pub fn attach(self, sub_graph: Box<dyn SubGraph>, ports: &Ports) -> Self {
+        self.branch(|chain| chain.end_in(sub_graph, ports))
+    }
+
+    /// End the chain in `sub_graph`.
+    pub fn end_in(self, sub_graph: Box<dyn SubGraph>, ports: &Ports) {
+        self.pipeline.note(&sub_graph.name(), self.depth);
+        let input = ChainBuilder {
+            depth: self.depth + 1,
+            ..self
+        };
+        sub_graph.build(input, ports);
+    }
+
+    /// End the chain in a fan-out to `domains`, each built in a graph of
+    /// its own.
+    pub fn fan_out(self, domains: Vec<Domain<'_>>) {
+        self.pipeline.note("FanOut", self.depth);
+        let mut ports = Vec::new();
+        for domain in domains {
+            let (port, source, stream) = fanout::domain(domain.name, domain.overflow);
+            let behind = match domain.overflow {
+                Overflow::Block => "waited for",
+                Overflow::Drop => "dropping",
+            };
+            self.pipeline.note(
+                &format!("{}, {} when behind", domain.name, behind),
+                self.depth + 1,
+            );
+            let graph = self.pipeline.graphs.len();
+            self.pipeline.graphs.push(Graph::new());
+            self.pipeline.ends.push(DomainEnd {
+                name: domain.name,
+                overflow: domain.overflow,
+                close: source.close_token(),
+            });
+            self.pipeline.graphs[graph].add(Box::new(source));
+            (domain.build)(ChainBuilder {
+                pipeline: &mut *self.pipeline,
+                stream,
+                sample_rate: self.sample_rate,
+                graph,
+                depth: self.depth + 2,
+            });
+            ports.push(port);
+        }
+        self.pipeline.graphs[self.graph].add(Box::new(FanOut::new(
+            self.stream,
+            ports,
+            self.sample_rate,
+        )));
+    }
+
+    /// Resample the stream down to `sample_rate`.
+    pub fn resample(self, sample_rate: Hertz) -> Self {
+        let input_rate = self.sample_rate as f64;
+        let mut chain = self.then(|src| Resample::new(src, input_rate, sample_rate.as_hz() as f64));
+        chain.sample_rate = sample_rate.as_hz();
+        chain
+    }
+
+    /// Scale the samples by `gain`. Unity gain adds no block.
+    pub fn gain(self, gain: Decibels) -> Self {
+        if gain == Decibels(0.0) {
+            return self;
+        }
+        let factor = Complex::new(gain.to_linear(), 0.0);
+        self.then(|src| MultiplyConst::new(src, factor))
+    }
+
+    /// Weight blocks of `size` samples by a Hann window, ahead of an FFT
+    /// of that size.
+    pub fn window(self, size: usize) -> Self {
+        self.then(|src| HannWindow::new(src, size))
+    }
+
+    /// Transform blocks of `size` samples.
+    pub fn fft(self, size: usize) -> Self {
+        self.then(|src| FftStream::new(src, size))
+    }
+
+    /// Take the magnitude of each sample.
+    pub fn magnitude(self) -> ChainBuilder<'g, Float> {
+        self.then(|src| {
+            Map::new(src, "MapMagnitude", |sample: Complex, tags| {
+                (sample.norm(), Cow::Borrowed(tags))
+            })
+        })
+    }
+}
+
+impl<'g, T: Sample> ChainBuilder<'g, T> {
+    /// Sample rate of the chain's source, in Hz.
+    pub fn sample_rate(&self) -> u64 {
+        self.sample_rate
+    }
+
+    /// Add a block built by `stage` from the chain's stream, continuing from
+    /// the block's output.
+    pub fn then<U, B>(
+        self,
+        stage: impl FnOnce(ReadStream<T>) -> (B, ReadStream<U>),
+    ) -> ChainBuilder<'g, U>
+    where
+        B: Block + 'static,
+    {
+        let (block, stream) = stage(self.stream);
+        self.pipeline.add(Box::new(block), self.graph, self.depth);
+        ChainBuilder {
+            pipeline: self.pipeline,
+            stream,
+            sample_rate: self.sample_rate,
+            graph: self.graph,
+            depth: self.depth,
+        }
+    }
+
+    /// Tee the stream off into a branch built by `build`; the chain carries
+    /// on with the other copy.
+    pub fn branch(self, build: impl FnOnce(ChainBuilder<'_, T>)) -> Self {
+        let (tee, main, branch) = Tee::new(self.stream);
+        self.pipeline.add(Box::new(tee), self.graph, self.depth);
+        build(ChainBuilder {
+            pipeline: &mut *self.pipeline,
+            stream: branch,
+            sample_rate: self.sample_rate,
+            graph: self.graph,
+            depth: self.depth + 1,
+        });
+        Self {
+            pipeline: self.pipeline,
+            stream: main,
+            sample_rate: self.sample_rate,
+            graph: self.graph,
+            depth: self.depth,
+        }
+    }
+
+    /// End the chain in the block built by `sink` from its stream.
+    pub fn sink<B>(self, sink: impl FnOnce(ReadStream<T>) -> B)
+    where
+        B: Block + 'static,
+    {
+        self.pipeline
+            .add(Box::new(sink(self.stream)), self.graph, self.depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use rustiq_messages::IqFormat;
+    use rustradio::block::BlockRet;
+    use rustradio::blocks::NullSink;
+    use rustradio::rustradio_macros;
+
+    struct Doubler;
+
+    impl SubGraph for Doubler {
+        fn name(&self) -> String {
+            "Doubler".to_string()
+        }
+
+        fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, _ports: &Ports) {
+            input
+                .then(|src| MultiplyConst::new(src, Complex::new(2.0, 0.0)))
+                .sink(NullSink::new);
+        }
+    }
+
+    #[test]
+    fn outlines_sub_graphs_under_their_branch() {
+        let (event_tx, _event_rx) = flume::unbounded();
+        let ports = Ports {
+            event_tx,
+            center_frequency: Hertz(0),
+            audio_routes: AudioRoutes::default(),
+            squelch: SquelchThreshold::default(),
+            listening: ListeningChannel::default(),
+            passband: ChannelPassband::default(),
+            playback: None,
+        };
+        let mut pipeline = Pipeline::new();
+        ChainBuilder::source(
+            &mut pipeline,
+            SourceConfig::default(),
+            &mut Tuner::new(Hertz(0)),
+        )
+        .unwrap()
+        .attach(Box::new(Doubler), &ports)
+        .fft(16)
+        .magnitude()
+        .sink(NullSink::new);
+
+        let expected = [
+            "SignalGenerator",
+            "Tee",
+            "  Doubler",
+            "    MultiplyConst",
+            "    NullSink",
+            "FftStream",
+            "MapMagnitude",
+            "NullSink",
+        ];
+        assert_eq!(pipeline.outline(), expected.join("\n"));
+    }
+
+    #[test]
+    fn outlines_domains_under_their_fan_out() {
+        let mut pipeline = Pipeline::new();
+        let domains = vec![
+            Domain::new("Spectrum", Overflow::Drop, |chain| {
+                chain.window(16).fft(16).magnitude().sink(NullSink::new)
+            }),
+            Domain::new("Recording", Overflow::Block, |chain| {
+                chain.sink(NullSink::new)
+            }),
+        ];
+        ChainBuilder::source(
+            &mut pipeline,
+            SourceConfig::default(),
+            &mut Tuner::new(Hertz(0)),
+        )
+        .unwrap()
+        .fan_out(domains);
+
+        let expected = [
+            "SignalGenerator",
+            "FanOut",
+            "  Spectrum, dropping when behind",
+            "    HannWindow",
+            "    FftStream",
+            "    MapMagnitude",
+            "    NullSink",
+            "  Recording, waited for when behind",
+            "    NullSink",
+        ];
+        assert_eq!(pipeline.outline(), expected.join("\n"));
+        // The sources' graph and one for each domain
+        assert_eq!(pipeline.graphs.len(), 3);
+    }
+
+    /// Counts the samples reaching it, ten at a time and each ten `delay`
+    /// after the last, as a busy disk might take them.
+    #[derive(rustradio_macros::Block)]
+    #[rustradio(new)]
+    struct Count {
+        #[rustradio(in)]
+        src: ReadStream<Complex>,
+        count: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl Block for Count {
+        fn work(&mut self) -> rustradio::Result<BlockRet<'_>> {
+            let (input, _tags) = self.src.read_buf()?;
+            if input.is_empty() {
+                return Ok(BlockRet::WaitForStream(&self.src, 1));
+            }
+            thread::sleep(self.delay);
+            let n = input.len().min(10);
+            input.consume(n);
+            self.count.fetch_add(n, Ordering::SeqCst);
+            Ok(BlockRet::Again)
+        }
+    }
+
+    #[test]
+    fn domains_waited_for_finish_what_is_queued() {
+        // Eight chunks of ten samples, all queued for the domains at once
+        let path = std::env::temp_dir().join(format!("rustiq-drain-{}.cf32", std::process::id()));
+        let bytes: Vec<u8> = std::iter::repeat_n(0.5f32, 2 * 80)
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        std::fs::write(&path, bytes).unwrap();
+        let source = SourceConfig::File {
+            path: path.clone(),
+            sample_rate: Hertz(1_000),
+            format: IqFormat::Cf32,
+        };
+
+        let shown = Arc::new(AtomicUsize::new(0));
+        let recorded = Arc::new(AtomicUsize::new(0));
+        let mut pipeline = Pipeline::new();
+        let domains = vec![
+            Domain::new("Spectrum", Overflow::Drop, |chain| {
+                chain.sink(|src| Count::new(src, shown.clone(), Duration::ZERO))
+            }),
+            Domain::new("Recording", Overflow::Block, |chain| {
+                chain.sink(|src| Count::new(src, recorded.clone(), Duration::from_millis(20)))
+            }),
+        ];
+        ChainBuilder::source(&mut pipeline, source, &mut Tuner::new(Hertz(0)))
+            .unwrap()
+            .fan_out(domains);
+        let mut graphs = pipeline.into_graph();
+        let stop = graphs.cancel_token();
+        let running = thread::spawn(move || graphs.run());
+        // Stopped once all is fanned out, well before the recording has
+        // taken it
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while shown.load(Ordering::SeqCst) < 80 {
+            assert!(Instant::now() < deadline, "the spectrum never got it all");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(recorded.load(Ordering::SeqCst) < 80);
+        stop.cancel();
+        running.join().unwrap().unwrap();
+        assert_eq!(recorded.load(Ordering::SeqCst), 80);
+        std::fs::remove_file(path).unwrap();
+    }
+}
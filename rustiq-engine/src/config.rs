@@ -0,0 +1,130 @@
+use flume::{Receiver, Sender};
+use rustiq_messages::{Command, Event};
+
+use crate::SpectrumTx;
+
+/// Bytes a bin takes in a spectrum frame: its magnitude, and its peak and
+/// min hold levels.
+const BIN_BYTES: usize = 12;
+
+/// Spectrum frames in flight for each one queued: the main source's and
+/// the second source's.
+const SOURCES: usize = 2;
+
+/// Smallest FFT size the engine takes, however tight the memory.
+const MIN_FFT_SIZE: usize = 16;
+
+/// What a queue does when it is full: the spectrum path's channel to the
+/// UI, or a domain's queue from the source it shares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wait for room, holding up whatever feeds the queue until its reader
+    /// catches up. Nothing is lost, but a slow reader slows everything.
+    Block,
+    /// Drop what doesn't fit and carry on. The spectrum channel drops its
+    /// oldest frame to make room for the newest, and the UI sees dropped
+    /// frames as gaps in the sequence numbers and marks them in the
+    /// waterfall.
+    #[default]
+    Drop,
+}
+
+/// Caps on what the engine may use, so it can run on a single-board
+/// computer alongside other services. The engine reports an
+/// `RustIqError::Limit` whenever it holds back to stay within one. `None`
+/// is no cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceLimits {
+    /// Bytes for spectrum frames: those queued for the UI and the one
+    /// being worked out, from each source. Caps the FFT size.
+    pub memory: Option<usize>,
+    /// Threads for work in the background, such as the overview of a file
+    /// played or the self test. Work that finds none free isn't done.
+    pub threads: Option<usize>,
+    /// Bytes of samples a recording may take on disk; it is stopped once
+    /// it has.
+    pub disk: Option<u64>,
+}
+
+/// Capacities of the channels between the engine and the UI, and the
+/// limits of the engine. Spectrum frames get their own channel so the
+/// high-rate waterfall feed can't crowd out state snapshots, measurements
+/// and decodes, which the UI also handles first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Spectrum frames buffered for the UI. Small keeps the waterfall
+    /// current; large rides out UI stalls at the cost of latency.
+    pub spectrum_capacity: usize,
+    pub spectrum_overflow: Overflow,
+    /// Other events buffered for the UI. Senders wait when it's full.
+    pub event_capacity: usize,
+    /// Commands buffered for the engine, or `None` for no limit.
+    pub command_capacity: Option<usize>,
+    pub limits: ResourceLimits,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            spectrum_capacity: 1,
+            spectrum_overflow: Overflow::Drop,
+            event_capacity: 1,
+            command_capacity: None,
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn command_channel(&self) -> (Sender<Command>, Receiver<Command>) {
+        match self.command_capacity {
+            Some(capacity) => flume::bounded(capacity),
+            None => flume::unbounded(),
+        }
+    }
+
+    pub fn event_channel(&self) -> (Sender<Event>, Receiver<Event>) {
+        flume::bounded(self.event_capacity)
+    }
+
+    /// Channel for `Event::SpectrumData` only, handling a full channel as
+    /// `spectrum_overflow` says.
+    pub fn spectrum_channel(&self) -> (SpectrumTx, Receiver<Event>) {
+        let (tx, rx) = flume::bounded(self.spectrum_capacity);
+        (SpectrumTx::new(tx, &rx, self.spectrum_overflow), rx)
+    }
+
+    /// Largest FFT size whose spectrum frames fit the memory limit, if
+    /// there is one.
+    pub fn max_fft_size(&self) -> Option<usize> {
+        let memory = self.limits.memory?;
+        let frames = (self.spectrum_capacity + 1) * SOURCES;
+        let bins = memory / (frames * BIN_BYTES);
+        Some(match bins.checked_ilog2() {
+            Some(log) => (1 << log).max(MIN_FFT_SIZE),
+            None => MIN_FFT_SIZE,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_the_fft_size_to_the_memory_limit() {
+        let config = |memory| EngineConfig {
+            spectrum_capacity: 3,
+            limits: ResourceLimits {
+                memory,
+                ..ResourceLimits::default()
+            },
+            ..EngineConfig::default()
+        };
+        assert_eq!(config(None).max_fft_size(), None);
+        // Eight frames of 12 bytes a bin
+        assert_eq!(config(Some(8 * 12 * 4096)).max_fft_size(), Some(4096));
+        assert_eq!(config(Some(8 * 12 * 4095)).max_fft_size(), Some(2048));
+        assert_eq!(config(Some(1)).max_fft_size(), Some(MIN_FFT_SIZE));
+    }
+}
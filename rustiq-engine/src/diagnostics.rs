@@ -0,0 +1,175 @@
+//! Self test of the receive chain: a reference tone of known level and
+//! frequency from the signal generator, measured after the source, the FFT
+//! and the USB demodulator. Each stage has to show the tone at the level and
+//! frequency it went in at, so a calibration change that shifts one of them
+//! shows up as a failed stage.
+
+use std::f64::consts::{SQRT_2, TAU};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use rustiq_messages::{
+    Decibels, DemodMode, Hertz, SelfTestReport, SourceConfig, Stage, StageCheck, Tone,
+};
+use rustradio::Complex;
+
+use super::chain::{ChainBuilder, Pipeline};
+use super::dsp::AudioDemodulator;
+use super::sinks::CaptureSink;
+use super::tuner::Tuner;
+
+const SAMPLE_RATE: u64 = 48_000;
+const FFT_SIZE: usize = 4096;
+
+/// The tone sits on FFT bin 128, so all its energy lands in that bin, and
+/// in the middle of the USB passband.
+const TONE_FREQUENCY: Hertz = Hertz(128 * SAMPLE_RATE / FFT_SIZE as u64);
+const TONE_LEVEL: Decibels = Decibels(-20.0);
+
+/// Allowed deviation of a stage's level.
+const TOLERANCE: Decibels = Decibels(0.5);
+
+/// Allowed deviation of a stage's frequency: one FFT bin.
+const FREQUENCY_TOLERANCE: u64 = SAMPLE_RATE / FFT_SIZE as u64 + 1;
+
+/// IQ samples captured, enough for the demodulator's filters to settle.
+const IQ_SAMPLES: usize = SAMPLE_RATE as usize / 2;
+
+/// FFT frames averaged for the spectrum level.
+const FFT_FRAMES: usize = 4;
+
+/// Longest the captures may take before the stages count as dead.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run the reference tone through a graph of its own and check each stage.
+pub fn self_test() -> SelfTestReport {
+    let (iq_tx, iq_rx) = flume::bounded(1);
+    let (spectrum_tx, spectrum_rx) = flume::bounded(1);
+
+    let mut pipeline = Pipeline::new();
+    let cancel_token = pipeline.cancel_token();
+    let source = SourceConfig::SignalGenerator {
+        sample_rate: Hertz(SAMPLE_RATE),
+        tones: vec![Tone {
+            frequency: TONE_FREQUENCY,
+            amplitude: TONE_LEVEL,
+        }],
+        snr: None,
+        sweep: None,
+    };
+    ChainBuilder::source(&mut pipeline, source, &mut Tuner::new(Hertz(0)))
+        .expect("the signal generator always opens")
+        .branch(|chain| chain.sink(|src| CaptureSink::new(src, IQ_SAMPLES, iq_tx)))
+        .fft(FFT_SIZE)
+        .magnitude()
+        .sink(|src| CaptureSink::new(src, FFT_SIZE * FFT_FRAMES, spectrum_tx));
+
+    let mut graph = pipeline.into_graph();
+    let graph_handle = thread::spawn(move || graph.run());
+    let iq = iq_rx.recv_timeout(TIMEOUT).unwrap_or_default();
+    let magnitudes = spectrum_rx.recv_timeout(TIMEOUT).unwrap_or_default();
+    cancel_token.cancel();
+    match graph_handle.join() {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("Self test graph failed: {}", e),
+        Err(_) => warn!("Self test graph panicked"),
+    }
+
+    SelfTestReport {
+        tone_frequency: TONE_FREQUENCY,
+        tone_level: TONE_LEVEL,
+        tolerance: TOLERANCE,
+        checks: vec![
+            check(Stage::Source, source_tone(&iq)),
+            check(Stage::Spectrum, spectrum_tone(&magnitudes)),
+            check(Stage::Audio, audio_tone(&iq)),
+        ],
+    }
+}
+
+fn check(stage: Stage, tone: Option<(Decibels, Hertz)>) -> StageCheck {
+    let passed = tone.is_some_and(|(level, frequency)| {
+        (level.0 - TONE_LEVEL.0).abs() <= TOLERANCE.0
+            && frequency.as_hz().abs_diff(TONE_FREQUENCY.as_hz()) <= FREQUENCY_TOLERANCE
+    });
+    StageCheck {
+        stage,
+        level: tone.map(|(level, _)| level),
+        frequency: tone.map(|(_, frequency)| frequency),
+        passed,
+    }
+}
+
+/// Level and frequency of the tone in the IQ stream, from its RMS and mean
+/// phase step.
+fn source_tone(iq: &[Complex]) -> Option<(Decibels, Hertz)> {
+    if iq.len() < 2 {
+        return None;
+    }
+    let power = iq.iter().map(|s| s.norm_sqr() as f64).sum::<f64>() / iq.len() as f64;
+    let rotation: Complex = iq.windows(2).map(|w| w[1] * w[0].conj()).sum();
+    let frequency = rotation.arg() as f64 / TAU * SAMPLE_RATE as f64;
+    Some((
+        Decibels::from_linear(power.sqrt() as f32),
+        hertz(frequency)?,
+    ))
+}
+
+/// Level and frequency of the strongest FFT bin, averaged over the frames.
+/// The FFT is unnormalized, so a full-scale tone on a bin has a magnitude
+/// of the FFT size.
+fn spectrum_tone(magnitudes: &[f32]) -> Option<(Decibels, Hertz)> {
+    let frames = magnitudes.len() / FFT_SIZE;
+    if frames == 0 {
+        return None;
+    }
+    let mean: Vec<f32> = (0..FFT_SIZE)
+        .map(|bin| {
+            magnitudes[bin..]
+                .iter()
+                .step_by(FFT_SIZE)
+                .take(frames)
+                .sum::<f32>()
+                / frames as f32
+        })
+        .collect();
+    let (peak, magnitude) = mean.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+    // Bins past the middle are negative frequencies
+    let bin = if peak < FFT_SIZE / 2 {
+        peak as f64
+    } else {
+        peak as f64 - FFT_SIZE as f64
+    };
+    Some((
+        Decibels::from_linear(magnitude / FFT_SIZE as f32),
+        hertz(bin * SAMPLE_RATE as f64 / FFT_SIZE as f64)?,
+    ))
+}
+
+/// Level and frequency of the USB-demodulated tone, dialled to DC so the
+/// tone comes out at its own frequency, from the settled audio's RMS and
+/// zero crossings.
+fn audio_tone(iq: &[Complex]) -> Option<(Decibels, Hertz)> {
+    let mut demodulator = AudioDemodulator::new(SAMPLE_RATE as f64, 0.0, DemodMode::Usb);
+    let audio = demodulator.process(iq);
+    let settled = &audio[audio.len() / 4..];
+    if settled.len() < 2 {
+        return None;
+    }
+    let power = settled.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / settled.len() as f64;
+    let crossings = settled
+        .windows(2)
+        .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+        .count();
+    let duration = settled.len() as f64 / demodulator.output_rate();
+    Some((
+        Decibels::from_linear((power.sqrt() * SQRT_2) as f32),
+        hertz(crossings as f64 / 2.0 / duration)?,
+    ))
+}
+
+/// A measured frequency, if it's one `Hertz` can hold.
+fn hertz(frequency: f64) -> Option<Hertz> {
+    (frequency >= 0.0).then(|| Hertz(frequency.round() as u64))
+}
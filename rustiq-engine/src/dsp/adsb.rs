@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use rustradio::Complex;
+
+use rustiq_messages::{GeoPosition, TrackKind, TrackReport};
+
+/// Half-microsecond chips per second: Mode S uses pulse position modulation
+/// with two chips per 1 µs bit.
+const CHIP_RATE: f64 = 2e6;
+
+/// Preamble length in chips (8 µs), and the chips carrying its four pulses.
+const PREAMBLE_CHIPS: usize = 16;
+const PREAMBLE_PULSES: [usize; 4] = [0, 2, 7, 9];
+
+/// Extended squitter length in bits.
+const LONG_BITS: usize = 112;
+
+/// Mode S parity generator polynomial (the x^24 term is implicit).
+const GENERATOR: u32 = 0xFF_F409;
+
+/// Even and odd position frames further apart than this aren't combined.
+const CPR_MAX_AGE: f64 = 10.0;
+
+/// Callsign character set for identification messages.
+const CALLSIGN_CHARS: &[u8; 64] =
+    b"#ABCDEFGHIJKLMNOPQRSTUVWXYZ##### ###############0123456789######";
+
+/// One half of a CPR-encoded position.
+#[derive(Clone, Copy)]
+struct CprFrame {
+    lat: u32,
+    lon: u32,
+    /// Sample time the frame was received, in seconds
+    time: f64,
+}
+
+/// Most recent even and odd position frames from one aircraft.
+#[derive(Default)]
+struct CprPair {
+    even: Option<CprFrame>,
+    odd: Option<CprFrame>,
+}
+
+/// Decodes 1090 MHz ADS-B extended squitters from IQ centered on 1090 MHz.
+///
+/// Pulses are found in the signal magnitude, so no channel filtering is
+/// needed, but the sample rate must be a multiple of 2 MHz. Only DF17/18
+/// messages that pass the parity check are decoded; airborne positions need
+/// an even and an odd frame from the same aircraft to resolve.
+pub struct AdsbDecoder {
+    rate: f64,
+    /// Samples per half-microsecond chip
+    chip_len: usize,
+    /// Magnitudes not yet searched, kept across calls so that messages can
+    /// span input buffers
+    magnitudes: Vec<f32>,
+    /// Sample position of `magnitudes[0]`
+    position: u64,
+    positions: HashMap<u32, CprPair>,
+}
+
+impl AdsbDecoder {
+    /// Create a decoder, or `None` if the sample rate isn't a multiple of 2 MHz.
+    pub fn new(sample_rate: f64) -> Option<Self> {
+        let chip_len = (sample_rate / CHIP_RATE).round() as usize;
+        if chip_len == 0 || (chip_len as f64 * CHIP_RATE - sample_rate).abs() > 0.01 * sample_rate {
+            return None;
+        }
+        Some(Self {
+            rate: sample_rate,
+            chip_len,
+            magnitudes: Vec::new(),
+            position: 0,
+            positions: HashMap::new(),
+        })
+    }
+
+    /// Feed IQ samples, returning a report for every message decoded in them.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<TrackReport> {
+        self.magnitudes.extend(input.iter().map(|c| c.norm()));
+        let frame_len = (PREAMBLE_CHIPS + 2 * LONG_BITS) * self.chip_len;
+
+        let mut reports = Vec::new();
+        let mut start = 0;
+        while start + frame_len <= self.magnitudes.len() {
+            let report = self
+                .preamble_at(start)
+                .then(|| self.bits_at(start))
+                .filter(|bits| valid_extended_squitter(bits))
+                .and_then(|bits| {
+                    let time = (self.position + start as u64) as f64 / self.rate;
+                    self.decode(&bits, time)
+                });
+            match report {
+                Some(report) => {
+                    reports.push(report);
+                    start += frame_len;
+                }
+                None => start += 1,
+            }
+        }
+        self.magnitudes.drain(..start);
+        self.position += start as u64;
+        reports
+    }
+
+    /// Mean magnitude of one chip of a frame starting at `start`.
+    fn chip(&self, start: usize, chip: usize) -> f32 {
+        let from = start + chip * self.chip_len;
+        self.magnitudes[from..from + self.chip_len]
+            .iter()
+            .sum::<f32>()
+            / self.chip_len as f32
+    }
+
+    /// Whether the four preamble pulses stand clearly above the gaps between them.
+    fn preamble_at(&self, start: usize) -> bool {
+        let (mut pulse_min, mut gap_max, mut gap_sum) = (f32::MAX, 0.0f32, 0.0f32);
+        for chip in 0..PREAMBLE_CHIPS {
+            let level = self.chip(start, chip);
+            if PREAMBLE_PULSES.contains(&chip) {
+                pulse_min = pulse_min.min(level);
+            } else {
+                gap_max = gap_max.max(level);
+                gap_sum += level;
+            }
+        }
+        let gap_mean = gap_sum / (PREAMBLE_CHIPS - PREAMBLE_PULSES.len()) as f32;
+        pulse_min > gap_max && pulse_min > 2.0 * gap_mean
+    }
+
+    /// Slice the bits following a preamble: a pulse in the first chip is a 1.
+    fn bits_at(&self, start: usize) -> Vec<u8> {
+        (0..LONG_BITS)
+            .map(|bit| {
+                let chip = PREAMBLE_CHIPS + 2 * bit;
+                u8::from(self.chip(start, chip) > self.chip(start, chip + 1))
+            })
+            .collect()
+    }
+
+    fn decode(&mut self, bits: &[u8], time: f64) -> Option<TrackReport> {
+        let icao = field(bits, 8, 24);
+        let mut report = TrackReport::new(TrackKind::Aircraft, format!("{icao:06X}"));
+        match field(bits, 32, 5) {
+            1..=4 => {
+                let name: String = (0..8)
+                    .map(|i| CALLSIGN_CHARS[field(bits, 40 + 6 * i, 6) as usize] as char)
+                    .filter(|&c| c != '#')
+                    .collect();
+                report.name = Some(name.trim().to_string());
+            }
+            9..=18 => {
+                report.altitude = altitude(field(bits, 40, 12));
+                let frame = CprFrame {
+                    lat: field(bits, 54, 17),
+                    lon: field(bits, 71, 17),
+                    time,
+                };
+                let odd = bits[53] == 1;
+                let pair = self.positions.entry(icao).or_default();
+                if odd {
+                    pair.odd = Some(frame);
+                } else {
+                    pair.even = Some(frame);
+                }
+                if let (Some(even), Some(odd_frame)) = (pair.even, pair.odd)
+                    && (even.time - odd_frame.time).abs() <= CPR_MAX_AGE
+                {
+                    report.position = global_position(even, odd_frame, odd);
+                }
+            }
+            19 => {
+                let subtype = field(bits, 37, 3);
+                if !matches!(subtype, 1 | 2) {
+                    return None;
+                }
+                let (east, north) = (field(bits, 46, 10), field(bits, 57, 10));
+                if east == 0 || north == 0 {
+                    return None;
+                }
+                let scale = if subtype == 2 { 4.0 } else { 1.0 };
+                let sign = |bit: u8| if bit == 1 { -1.0 } else { 1.0 };
+                let vx = sign(bits[45]) * (east - 1) as f64 * scale;
+                let vy = sign(bits[56]) * (north - 1) as f64 * scale;
+                report.speed = Some(vx.hypot(vy) as f32);
+                report.course = Some(vx.atan2(vy).to_degrees().rem_euclid(360.0) as f32);
+            }
+            _ => return None,
+        }
+        Some(report)
+    }
+}
+
+/// Whether the bits are a DF17/18 extended squitter with correct parity.
+fn valid_extended_squitter(bits: &[u8]) -> bool {
+    matches!(field(bits, 0, 5), 17 | 18) && parity(&bits[..LONG_BITS - 24]) == field(bits, 88, 24)
+}
+
+/// Mode S parity: the remainder of the data bits times x^24 divided by the generator.
+fn parity(data: &[u8]) -> u32 {
+    let mut remainder = 0u32;
+    for &bit in data {
+        let top = (remainder >> 23) & 1 ^ u32::from(bit);
+        remainder = (remainder << 1) & 0xFF_FFFF;
+        if top == 1 {
+            remainder ^= GENERATOR;
+        }
+    }
+    remainder
+}
+
+/// Read `len` bits starting at `start`, most significant first.
+fn field(bits: &[u8], start: usize, len: usize) -> u32 {
+    bits[start..start + len]
+        .iter()
+        .fold(0, |acc, &bit| (acc << 1) | u32::from(bit))
+}
+
+/// Altitude in feet from the 12-bit altitude field, if it uses 25 ft encoding.
+fn altitude(code: u32) -> Option<f32> {
+    // The Q bit selects 25 ft increments; Gillham-coded altitudes aren't decoded
+    if code == 0 || code & 0x10 == 0 {
+        return None;
+    }
+    let n = ((code & 0xFE0) >> 1) | (code & 0xF);
+    Some(n as f32 * 25.0 - 1_000.0)
+}
+
+/// Number of longitude zones at a latitude (the CPR "NL" function).
+fn longitude_zones(lat: f64) -> u32 {
+    let lat = lat.abs();
+    if lat < 1e-9 {
+        return 59;
+    }
+    if lat >= 87.0 {
+        return if lat > 87.0 { 1 } else { 2 };
+    }
+    let a = 1.0 - (PI / 30.0).cos();
+    let b = lat.to_radians().cos().powi(2);
+    (2.0 * PI / (1.0 - a / b).acos()).floor() as u32
+}
+
+/// Resolve an airborne position from an even and an odd CPR frame.
+/// `latest_odd` says which frame arrived last, and so which one the result
+/// describes.
+fn global_position(even: CprFrame, odd: CprFrame, latest_odd: bool) -> Option<GeoPosition> {
+    const SCALE: f64 = 131_072.0;
+    let (lat_even, lon_even) = (even.lat as f64 / SCALE, even.lon as f64 / SCALE);
+    let (lat_odd, lon_odd) = (odd.lat as f64 / SCALE, odd.lon as f64 / SCALE);
+
+    let j = (59.0 * lat_even - 60.0 * lat_odd + 0.5).floor();
+    let wrap = |lat: f64| if lat >= 270.0 { lat - 360.0 } else { lat };
+    let lat_even = wrap(360.0 / 60.0 * (j.rem_euclid(60.0) + lat_even));
+    let lat_odd = wrap(360.0 / 59.0 * (j.rem_euclid(59.0) + lat_odd));
+    let zones = longitude_zones(lat_even);
+    if zones != longitude_zones(lat_odd) {
+        // The frames straddle a zone boundary; wait for the next pair
+        return None;
+    }
+
+    let m = (lon_even * (zones - 1) as f64 - lon_odd * zones as f64 + 0.5).floor();
+    let (latitude, n, lon) = if latest_odd {
+        (lat_odd, (zones - 1).max(1) as f64, lon_odd)
+    } else {
+        (lat_even, zones.max(1) as f64, lon_even)
+    };
+    let mut longitude = 360.0 / n * (m.rem_euclid(n) + lon);
+    if longitude >= 180.0 {
+        longitude -= 360.0;
+    }
+    Some(GeoPosition {
+        latitude,
+        longitude,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 2e6;
+
+    /// IQ at 2 MS/s for a message given in hex: preamble, then a pulse in the
+    /// first or second half of each bit period, followed by some silence.
+    fn squitter(hex: &str) -> Vec<Complex> {
+        let mut chips = vec![false; PREAMBLE_CHIPS];
+        for pulse in PREAMBLE_PULSES {
+            chips[pulse] = true;
+        }
+        for i in (0..hex.len()).step_by(2) {
+            let byte = u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+            for bit in (0..8).rev() {
+                let one = byte >> bit & 1 == 1;
+                chips.extend([one, !one]);
+            }
+        }
+        chips.extend([false; 64]);
+        chips
+            .into_iter()
+            .map(|on| Complex::new(if on { 0.5 } else { 0.01 }, 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_identification() {
+        let mut decoder = AdsbDecoder::new(RATE).unwrap();
+        let reports = decoder.process(&squitter("8D4840D6202CC371C32CE0576098"));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].id, "4840D6");
+        assert_eq!(reports[0].name.as_deref(), Some("KLM1023"));
+    }
+
+    #[test]
+    fn decodes_position_from_even_and_odd_frames() {
+        let mut decoder = AdsbDecoder::new(RATE).unwrap();
+        let mut input = squitter("8D40621D58C386435CC412692AD6");
+        input.extend(squitter("8D40621D58C382D690C8AC2863A7"));
+
+        let reports = decoder.process(&input);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].position, None, "one frame can't be resolved");
+        assert_eq!(reports[1].altitude, Some(38_000.0));
+        let position = reports[1].position.unwrap();
+        assert!((position.latitude - 52.2572).abs() < 1e-3, "{position:?}");
+        assert!((position.longitude - 3.9194).abs() < 1e-3, "{position:?}");
+    }
+
+    #[test]
+    fn decodes_velocity() {
+        let mut decoder = AdsbDecoder::new(RATE).unwrap();
+        let reports = decoder.process(&squitter("8D485020994409940838175B284F"));
+        assert_eq!(reports.len(), 1);
+        let (speed, course) = (reports[0].speed.unwrap(), reports[0].course.unwrap());
+        assert!((speed - 159.2).abs() < 0.1, "speed {speed}");
+        assert!((course - 182.88).abs() < 0.1, "course {course}");
+    }
+
+    #[test]
+    fn rejects_corrupted_messages_and_unsupported_rates() {
+        let mut decoder = AdsbDecoder::new(RATE).unwrap();
+        assert!(
+            decoder
+                .process(&squitter("8D4840D6202CC371C32CE0576099"))
+                .is_empty()
+        );
+        assert!(AdsbDecoder::new(2.4e6).is_none());
+        assert!(AdsbDecoder::new(4e6).is_some());
+    }
+
+    #[test]
+    fn messages_may_span_input_buffers() {
+        let mut decoder = AdsbDecoder::new(RATE).unwrap();
+        let input = squitter("8D4840D6202CC371C32CE0576098");
+        let (first, second) = input.split_at(100);
+        assert!(decoder.process(first).is_empty());
+        assert_eq!(decoder.process(second).len(), 1);
+    }
+}
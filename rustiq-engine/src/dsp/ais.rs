@@ -0,0 +1,385 @@
+use rustradio::Complex;
+
+use super::channel::Channelizer;
+use super::fir::{lowpass_taps, shift_taps};
+use rustiq_messages::{GeoPosition, TrackKind, TrackReport};
+
+/// AIS symbol rate.
+const BAUD: f64 = 9_600.0;
+
+/// Rate the channel is decimated to; five samples per symbol.
+const CHANNEL_RATE: f64 = 48_000.0;
+
+/// Half-width of the channel filter. AIS channels are 25 kHz apart.
+const CHANNEL_BANDWIDTH: f64 = 8_000.0;
+
+/// Width of the channel filter's transition band in Hz.
+const TRANSITION: f64 = 2_000.0;
+
+/// How far each symbol transition pulls the symbol clock towards it.
+const CLOCK_GAIN: f64 = 0.2;
+
+/// HDLC flag byte, which starts and ends every frame.
+const FLAG: u8 = 0x7E;
+
+/// Longest frame accepted, in received bits: five slots of 256 bits.
+const MAX_FRAME_BITS: usize = 1_280;
+
+/// Decodes AIS position and static data reports from one 9600 baud GMSK channel.
+///
+/// The channel is selected and FM-demodulated like `AudioDemodulator` does, a
+/// symbol clock locks onto the zero crossings, and NRZI-decoded bits are
+/// searched for HDLC frames with a valid CRC.
+pub struct AisDecoder {
+    channel: Channelizer,
+    /// Previous channel sample, for the FM discriminator
+    previous: Complex,
+    samples_per_symbol: f64,
+    /// Symbol clock phase in symbols; a symbol is sampled when it wraps
+    clock: f64,
+    /// Previous discriminator output, for finding zero crossings
+    last_frequency: f32,
+    /// Previous symbol, for NRZI decoding
+    last_symbol: bool,
+    /// The last eight bits received, newest in the lowest bit
+    shift: u8,
+    /// Raw (still bit-stuffed) bits since the last flag, if a frame is open
+    frame: Option<Vec<u8>>,
+}
+
+impl AisDecoder {
+    /// Create a decoder for the channel `offset` Hz from the input's DC.
+    pub fn new(sample_rate: f64, offset: f64) -> Self {
+        let channel = Channelizer::new(
+            sample_rate,
+            offset,
+            CHANNEL_RATE,
+            |filter_rate, output_rate| {
+                let len = (4.0 * filter_rate / TRANSITION).ceil() as usize;
+                let cutoff = CHANNEL_BANDWIDTH.min(0.45 * output_rate);
+                shift_taps(&lowpass_taps(cutoff / filter_rate, len), 0.0)
+            },
+        );
+
+        Self {
+            samples_per_symbol: channel.output_rate() / BAUD,
+            channel,
+            previous: Complex::new(0.0, 0.0),
+            clock: 0.0,
+            last_frequency: 0.0,
+            last_symbol: false,
+            shift: 0,
+            frame: None,
+        }
+    }
+
+    /// Feed IQ samples, returning a report for every message decoded in them.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<TrackReport> {
+        let channel = self.channel.process(input);
+
+        let mut reports = Vec::new();
+        for c in channel {
+            let rotation = c * self.previous.conj();
+            self.previous = c;
+            let frequency = rotation.im.atan2(rotation.re);
+            if let Some(symbol) = self.recover_symbol(frequency) {
+                let bit = u8::from(symbol == self.last_symbol);
+                self.last_symbol = symbol;
+                if let Some(bytes) = self.push_bit(bit) {
+                    reports.extend(decode(&bytes));
+                }
+            }
+        }
+        reports
+    }
+
+    /// Advance the symbol clock by one sample, returning a symbol when one is due.
+    fn recover_symbol(&mut self, frequency: f32) -> Option<bool> {
+        let step = 1.0 / self.samples_per_symbol;
+        self.clock += step;
+        if (frequency > 0.0) != (self.last_frequency > 0.0) {
+            // Transitions fall between symbols, half a symbol from the sampling point
+            let fraction = self.last_frequency / (self.last_frequency - frequency);
+            let crossing = self.clock - (1.0 - fraction as f64) * step;
+            self.clock += (0.5 - crossing) * CLOCK_GAIN;
+        }
+        self.last_frequency = frequency;
+        if self.clock < 1.0 {
+            return None;
+        }
+        self.clock -= 1.0;
+        Some(frequency > 0.0)
+    }
+
+    /// Add one NRZI-decoded bit, returning the frame bytes when a valid frame ends.
+    fn push_bit(&mut self, bit: u8) -> Option<Vec<u8>> {
+        self.shift = (self.shift << 1) | bit;
+        if self.shift == FLAG {
+            let raw = self.frame.replace(Vec::new())?;
+            // The flag's first seven bits were taken for frame data
+            return unstuff(&raw[..raw.len().saturating_sub(7)]);
+        }
+        if self.shift & 0x7F == 0x7F {
+            // Seven ones in a row abort the frame
+            self.frame = None;
+            return None;
+        }
+        if let Some(raw) = &mut self.frame {
+            raw.push(bit);
+            if raw.len() > MAX_FRAME_BITS {
+                self.frame = None;
+            }
+        }
+        None
+    }
+}
+
+/// Remove HDLC bit stuffing and pack the bits (sent LSB first) into bytes,
+/// returning the data bytes if the frame check sequence matches.
+fn unstuff(raw: &[u8]) -> Option<Vec<u8>> {
+    let mut bits = Vec::with_capacity(raw.len());
+    let mut ones = 0;
+    for &bit in raw {
+        if ones == 5 && bit == 0 {
+            ones = 0;
+            continue;
+        }
+        ones = if bit == 1 { ones + 1 } else { 0 };
+        bits.push(bit);
+    }
+    if bits.len() < 24 || bits.len() % 8 != 0 {
+        return None;
+    }
+    let mut bytes: Vec<u8> = bits
+        .chunks(8)
+        .map(|chunk| chunk.iter().rev().fold(0, |acc, &bit| (acc << 1) | bit))
+        .collect();
+    let fcs = u16::from_le_bytes([bytes[bytes.len() - 2], bytes[bytes.len() - 1]]);
+    bytes.truncate(bytes.len() - 2);
+    (crc16(&bytes) == fcs).then_some(bytes)
+}
+
+/// CRC-16/X.25, the HDLC frame check sequence.
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in bytes {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x8408
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Read `len` bits starting at `start` of the message, most significant first.
+fn field(bytes: &[u8], start: usize, len: usize) -> u32 {
+    (start..start + len).fold(0, |acc, i| {
+        (acc << 1) | u32::from(bytes[i / 8] >> (7 - i % 8) & 1)
+    })
+}
+
+/// Read a two's complement field.
+fn signed_field(bytes: &[u8], start: usize, len: usize) -> i32 {
+    let value = field(bytes, start, len) as i32;
+    (value << (32 - len)) >> (32 - len)
+}
+
+/// Read `chars` characters of AIS 6-bit text, trimming `@` padding and spaces.
+fn text_field(bytes: &[u8], start: usize, chars: usize) -> String {
+    let text: String = (0..chars)
+        .map(|i| {
+            let code = field(bytes, start + 6 * i, 6) as u8;
+            (if code < 32 { code + 64 } else { code }) as char
+        })
+        .collect();
+    text.trim_end_matches(['@', ' ']).to_string()
+}
+
+/// Decode a message's payload, if it's one of the types reported on the map.
+fn decode(bytes: &[u8]) -> Option<TrackReport> {
+    let len = bytes.len() * 8;
+    let message_type = field(bytes, 0, 6);
+    let mmsi = field(bytes, 8, 30);
+    let mut report = TrackReport::new(TrackKind::Vessel, format!("{mmsi:09}"));
+    match message_type {
+        // Class A position report
+        1..=3 if len >= 168 => {
+            set_motion(&mut report, bytes, 50, 61, 116, 128);
+        }
+        // Class B position report
+        18 if len >= 168 => {
+            set_motion(&mut report, bytes, 46, 57, 112, 124);
+        }
+        // Class A static and voyage data
+        5 if len >= 232 => {
+            report.name = Some(text_field(bytes, 112, 20));
+        }
+        // Class B static data, part A
+        24 if len >= 160 && field(bytes, 38, 2) == 0 => {
+            report.name = Some(text_field(bytes, 40, 20));
+        }
+        _ => return None,
+    }
+    Some(report)
+}
+
+/// Fill in speed, position and course from a position report, given the
+/// bit offsets of its fields.
+fn set_motion(
+    report: &mut TrackReport,
+    bytes: &[u8],
+    speed: usize,
+    longitude: usize,
+    course: usize,
+    heading: usize,
+) {
+    let speed = field(bytes, speed, 10);
+    report.speed = (speed != 1023).then_some(speed as f32 / 10.0);
+
+    // Positions are in 1/10000 minute; 181° and 91° mean "not available"
+    let lon = signed_field(bytes, longitude, 28) as f64 / 600_000.0;
+    let lat = signed_field(bytes, longitude + 28, 27) as f64 / 600_000.0;
+    report.position = (lon.abs() <= 180.0 && lat.abs() <= 90.0).then_some(GeoPosition {
+        latitude: lat,
+        longitude: lon,
+    });
+
+    let course = field(bytes, course, 12);
+    let heading = field(bytes, heading, 9);
+    report.course = if course < 3600 {
+        Some(course as f32 / 10.0)
+    } else {
+        (heading < 360).then_some(heading as f32)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    /// Bit writer for building message payloads, most significant bit first.
+    struct Payload(Vec<u8>);
+
+    impl Payload {
+        fn put(&mut self, value: i64, len: usize) -> &mut Self {
+            self.0
+                .extend((0..len).rev().map(|i| (value >> i & 1) as u8));
+            self
+        }
+    }
+
+    /// IQ for an AIS burst carrying `payload`, FSK modulated at `offset` Hz.
+    fn burst(payload: &[u8], offset: f64, sample_rate: f64) -> Vec<Complex> {
+        let bytes: Vec<u8> = payload
+            .chunks(8)
+            .map(|chunk| chunk.iter().fold(0, |acc, &bit| (acc << 1) | bit))
+            .collect();
+        let fcs = crc16(&bytes).to_le_bytes();
+
+        // Training sequence and opening flag, then stuffed data and FCS (LSB
+        // first), then the closing flag and enough idle bits to flush the filter
+        let mut bits: Vec<u8> = (0..24).map(|i| i % 2).collect();
+        let flag: Vec<u8> = (0..8).map(|i| FLAG >> i & 1).collect();
+        bits.extend(&flag);
+        let mut ones = 0;
+        for byte in bytes.iter().chain(&fcs) {
+            for i in 0..8 {
+                let bit = byte >> i & 1;
+                bits.push(bit);
+                ones = if bit == 1 { ones + 1 } else { 0 };
+                if ones == 5 {
+                    bits.push(0);
+                    ones = 0;
+                }
+            }
+        }
+        bits.extend(&flag);
+        bits.extend([0; 64]);
+
+        // NRZI: a 0 is sent as a change of frequency
+        let mut level = true;
+        let samples_per_bit = sample_rate / BAUD;
+        let mut phase = 0.0f64;
+        let mut iq = Vec::new();
+        for (i, bit) in bits.iter().enumerate() {
+            if *bit == 0 {
+                level = !level;
+            }
+            let deviation = if level { 2_400.0 } else { -2_400.0 };
+            let end = ((i + 1) as f64 * samples_per_bit) as usize;
+            while iq.len() < end {
+                phase += TAU * (offset + deviation) / sample_rate;
+                iq.push(Complex::new(phase.cos() as f32, phase.sin() as f32));
+            }
+        }
+        iq
+    }
+
+    #[test]
+    fn decodes_class_a_position_report() {
+        let mut payload = Payload(Vec::new());
+        payload
+            .put(1, 6)
+            .put(0, 2)
+            .put(244_670_316, 30)
+            .put(0, 4)
+            .put(0, 8)
+            .put(123, 10)
+            .put(0, 1)
+            .put((4.8012 * 600_000.0) as i64, 28)
+            .put((-52.3712 * 600_000.0) as i64, 27)
+            .put(2_705, 12)
+            .put(270, 9)
+            .put(0, 6 + 2 + 3 + 1 + 19);
+        assert_eq!(payload.0.len(), 168);
+
+        let sample_rate = 192_000.0;
+        let mut decoder = AisDecoder::new(sample_rate, 25_000.0);
+        let reports = decoder.process(&burst(&payload.0, 25_000.0, sample_rate));
+
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.kind, TrackKind::Vessel);
+        assert_eq!(report.id, "244670316");
+        assert_eq!(report.speed, Some(12.3));
+        assert_eq!(report.course, Some(270.5));
+        let position = report.position.unwrap();
+        assert!((position.latitude + 52.3712).abs() < 1e-5, "{position:?}");
+        assert!((position.longitude - 4.8012).abs() < 1e-5, "{position:?}");
+    }
+
+    #[test]
+    fn decodes_static_data_name() {
+        let mut payload = Payload(Vec::new());
+        payload.put(24, 6).put(0, 2).put(123_456_789, 30).put(0, 2);
+        for c in "PILOT BOAT".chars().chain(std::iter::repeat_n('@', 10)) {
+            let code = c as i64;
+            payload.put(if code >= 64 { code - 64 } else { code }, 6);
+        }
+        payload.put(0, 8);
+
+        let mut decoder = AisDecoder::new(CHANNEL_RATE, 0.0);
+        let reports = decoder.process(&burst(&payload.0, 0.0, CHANNEL_RATE));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].id, "123456789");
+        assert_eq!(reports[0].name.as_deref(), Some("PILOT BOAT"));
+        assert_eq!(reports[0].position, None);
+    }
+
+    #[test]
+    fn rejects_frames_with_bad_crc() {
+        let payload: Vec<u8> = (0..168).map(|i| u8::from(i % 3 == 0)).collect();
+        let mut iq = burst(&payload, 0.0, CHANNEL_RATE);
+        // Invert the frequency over a few symbols in the middle of the frame
+        for sample in &mut iq[500..520] {
+            *sample = sample.conj();
+        }
+        let mut decoder = AisDecoder::new(CHANNEL_RATE, 0.0);
+        assert!(decoder.process(&iq).is_empty());
+    }
+}
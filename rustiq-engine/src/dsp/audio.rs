@@ -0,0 +1,478 @@
+use std::f64::consts::TAU;
+
+use rustradio::Complex;
+
+use super::channel::Channelizer;
+use super::classify::ChannelStats;
+use super::fir::{lowpass_taps, shift_taps};
+use super::nco::Nco;
+use super::rds::{self, RdsDecoder};
+use super::squelch::PowerSquelch;
+use super::stereo::{Audio, StereoDecoder};
+use rustiq_messages::{Decibels, DemodMode, Passband, RdsData};
+
+/// Rate the channel is decimated to before demodulation.
+/// Wide enough for a narrowband FM channel.
+pub const AUDIO_RATE: f64 = 24_000.0;
+
+/// Width of the channel filter's transition band in Hz.
+const TRANSITION: f64 = 1_000.0;
+
+/// Peak deviation that demodulates to full-scale audio.
+const FM_DEVIATION: f64 = 5_000.0;
+
+/// Rate a broadcast FM channel is decimated to, wide enough for the whole
+/// multiplex signal up to its 57 kHz RDS subcarrier.
+const WFM_RATE: f64 = 240_000.0;
+
+/// Width of the broadcast FM channel filter's transition band.
+const WFM_TRANSITION: f64 = 20_000.0;
+
+/// Peak deviation of broadcast FM.
+const WFM_DEVIATION: f64 = 75_000.0;
+
+/// Time constant an AM carrier's level is followed over, in seconds: long
+/// against the lowest audio, short against fading.
+const AM_CARRIER_SMOOTHING: f64 = 0.1;
+
+/// Pitch a CW carrier at the dial frequency is heard at.
+const CW_PITCH: f64 = 700.0;
+
+/// Turns one narrow channel of the IQ stream into real audio samples.
+///
+/// The channel is selected and decimated to roughly `AUDIO_RATE`, through a
+/// filter of the mode's passband or another, then demodulated. Broadcast FM is decimated to
+/// `WFM_RATE` instead, and its multiplex signal decoded to stereo or mono
+/// and for RDS.
+/// A squelch, if set, mutes the audio while the channel is quiet.
+pub struct AudioDemodulator {
+    /// Rate of the IQ stream the channel is selected from
+    input_rate: f64,
+    /// Offset of the dial frequency from the input's DC
+    offset: f64,
+    channel: Channelizer,
+    demod: DemodMode,
+    /// Passband of the channel filter
+    passband: Passband,
+    /// Previous channel sample, for the FM discriminator
+    previous: Complex,
+    /// Smoothed envelope, taken for the carrier level in AM
+    carrier: f32,
+    /// Weight of each new sample in the carrier level
+    carrier_alpha: f32,
+    /// Beat oscillator turning a CW carrier into a tone
+    bfo: Nco,
+    /// Decoder of the multiplex signal, for broadcast FM
+    stereo: Option<StereoDecoder>,
+    /// Decoder of RDS, for broadcast FM at a rate that holds it
+    rds: Option<RdsDecoder>,
+    /// What RDS last brought that was not yet taken
+    rds_data: Option<RdsData>,
+    squelch: PowerSquelch,
+    /// Channel power the squelch opens at, if it is set
+    threshold: Option<f32>,
+    /// Channel samples the squelch let through since last taken
+    heard: ChannelStats,
+}
+
+impl AudioDemodulator {
+    /// Create a demodulator for the channel `offset` Hz from the input's DC.
+    pub fn new(sample_rate: f64, offset: f64, demod: DemodMode) -> Self {
+        Self::with_passband(sample_rate, offset, demod, demod.passband())
+    }
+
+    /// Create a demodulator whose channel filter lets through `passband`
+    /// rather than the mode's, as far as the channel's rate allows.
+    pub fn with_passband(
+        sample_rate: f64,
+        offset: f64,
+        demod: DemodMode,
+        passband: Passband,
+    ) -> Self {
+        let target_rate = match demod {
+            DemodMode::Wfm => WFM_RATE,
+            _ => AUDIO_RATE,
+        };
+        let channel = Channelizer::new(
+            sample_rate,
+            offset,
+            target_rate,
+            |filter_rate, output_rate| {
+                let transition = match demod {
+                    DemodMode::Wfm => WFM_TRANSITION,
+                    _ => TRANSITION,
+                };
+                let len = (4.0 * filter_rate / transition).ceil() as usize;
+                // Within the channel, clear of its aliases
+                let edge = 0.45 * output_rate;
+                let low = (passband.low as f64).clamp(-edge, edge);
+                let high = (passband.high as f64).clamp(-edge, edge);
+                let center = (low + high) / 2.0;
+                let cutoff = (high - low) / 2.0;
+                shift_taps(
+                    &lowpass_taps(cutoff / filter_rate, len),
+                    center / filter_rate,
+                )
+            },
+        );
+        let stereo = (demod == DemodMode::Wfm).then(|| StereoDecoder::new(channel.output_rate()));
+        let rds = (demod == DemodMode::Wfm && channel.output_rate() >= rds::MIN_RATE)
+            .then(|| RdsDecoder::new(channel.output_rate()));
+
+        let rate = channel.output_rate();
+        Self {
+            input_rate: sample_rate,
+            offset,
+            passband,
+            squelch: PowerSquelch::new(rate),
+            carrier: 0.0,
+            carrier_alpha: (1.0 - (-1.0 / (AM_CARRIER_SMOOTHING * rate)).exp()) as f32,
+            // Mixing "down" from minus the pitch moves the carrier up to it
+            bfo: Nco::new(rate, -CW_PITCH),
+            channel,
+            demod,
+            previous: Complex::new(0.0, 0.0),
+            stereo,
+            rds,
+            rds_data: None,
+            threshold: None,
+            heard: ChannelStats::default(),
+        }
+    }
+
+    /// Move to the channel `offset` Hz from the input's DC, demodulating it
+    /// as `demod` through its own passband, starting over as a new
+    /// demodulator with the same squelch.
+    pub fn retune(&mut self, offset: f64, demod: DemodMode) {
+        self.rebuild(offset, demod, demod.passband());
+    }
+
+    /// Filter the channel through `passband`, or the mode's, starting over
+    /// if that changes it.
+    pub fn set_passband(&mut self, passband: Option<Passband>) {
+        let passband = passband.unwrap_or(self.demod.passband());
+        if passband != self.passband {
+            self.rebuild(self.offset, self.demod, passband);
+        }
+    }
+
+    fn rebuild(&mut self, offset: f64, demod: DemodMode, passband: Passband) {
+        let threshold = self.threshold;
+        *self = Self::with_passband(self.input_rate, offset, demod, passband);
+        self.threshold = threshold;
+    }
+
+    /// Mute the audio while the channel's power is below `threshold`, or
+    /// never.
+    pub fn set_squelch(&mut self, threshold: Option<Decibels>) {
+        self.threshold = threshold.map(Decibels::to_power);
+    }
+
+    /// Whether the squelch let the last audio through.
+    pub fn squelch_open(&self) -> bool {
+        self.squelch.is_open()
+    }
+
+    /// What the squelch has let through since last taken, to classify it.
+    pub fn take_heard(&mut self) -> ChannelStats {
+        std::mem::take(&mut self.heard)
+    }
+
+    /// What the station last sent over RDS, if it changed since last taken.
+    pub fn take_rds(&mut self) -> Option<RdsData> {
+        self.rds_data.take()
+    }
+
+    /// Sample rate of the demodulated audio in Hz.
+    pub fn output_rate(&self) -> f64 {
+        match &self.stereo {
+            Some(stereo) => stereo.output_rate(),
+            None => self.channel.output_rate(),
+        }
+    }
+
+    /// Demodulate IQ samples, returning the mono audio produced from them.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<f32> {
+        self.process_audio(input).into_mono()
+    }
+
+    /// Demodulate IQ samples, returning the audio produced from them in
+    /// stereo if the station sends it.
+    pub fn process_audio(&mut self, input: &[Complex]) -> Audio {
+        let channel = self.channel.process(input);
+        let gate = self.squelch.process(&channel, self.threshold);
+        for (&sample, _) in channel.iter().zip(&gate).filter(|(_, open)| **open) {
+            self.heard.push(sample);
+        }
+        let mut audio = self.demodulate(&channel);
+        if self.threshold.is_some() && !gate.is_empty() {
+            // The audio may be at another rate than the channel
+            let channels = if audio.stereo { 2 } else { 1 };
+            let frames = audio.samples.len() / channels;
+            for (i, frame) in audio.samples.chunks_mut(channels).enumerate() {
+                if !gate[i * gate.len() / frames.max(1)] {
+                    frame.fill(0.0);
+                }
+            }
+        }
+        audio
+    }
+
+    /// Demodulate the channel's samples as the mode says.
+    fn demodulate(&mut self, channel: &[Complex]) -> Audio {
+        let samples = match self.demod {
+            DemodMode::Usb | DemodMode::Lsb => channel.iter().map(|c| c.re).collect(),
+            DemodMode::Am => self.envelope(channel),
+            DemodMode::Cw => channel.iter().map(|&c| self.bfo.mix(c).re).collect(),
+            DemodMode::Fm => self.discriminate(channel, FM_DEVIATION),
+            DemodMode::Wfm => {
+                let multiplex = self.discriminate(channel, WFM_DEVIATION);
+                if let Some(data) = self.rds.as_mut().and_then(|rds| rds.process(&multiplex)) {
+                    self.rds_data = Some(data);
+                }
+                if let Some(stereo) = &mut self.stereo {
+                    return stereo.process(&multiplex);
+                }
+                multiplex
+            }
+        };
+        Audio {
+            samples,
+            stereo: false,
+        }
+    }
+
+    /// AM-demodulate channel samples: the envelope's swing about the
+    /// carrier level, so full modulation is full scale however strong the
+    /// carrier.
+    fn envelope(&mut self, channel: &[Complex]) -> Vec<f32> {
+        channel
+            .iter()
+            .map(|c| {
+                let level = c.norm();
+                self.carrier += self.carrier_alpha * (level - self.carrier);
+                if self.carrier > 0.0 {
+                    (level - self.carrier) / self.carrier
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// FM-demodulate channel samples, scaled so `deviation` is full scale.
+    fn discriminate(&mut self, channel: &[Complex], deviation: f64) -> Vec<f32> {
+        let gain = self.channel.output_rate() / (TAU * deviation);
+        channel
+            .iter()
+            .map(|&c| {
+                let rotation = c * self.previous.conj();
+                self.previous = c;
+                (rotation.im.atan2(rotation.re) as f64 * gain) as f32
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Estimate the dominant frequency of real audio from its zero crossings.
+    fn zero_crossing_frequency(audio: &[f32], rate: f64) -> f64 {
+        let settled = &audio[audio.len() / 4..];
+        let crossings = settled
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        crossings as f64 / 2.0 / (settled.len() as f64 / rate)
+    }
+
+    #[test]
+    fn usb_recovers_tone_above_dial() {
+        let sample_rate = 48_000.0;
+        let (dial, tone) = (10_000.0, 1_500.0);
+        let input: Vec<Complex> = (0..48_000)
+            .map(|i| {
+                let phase = TAU * (dial + tone) * i as f64 / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let mut demod = AudioDemodulator::new(sample_rate, dial, DemodMode::Usb);
+        let audio = demod.process(&input);
+
+        assert_eq!(demod.output_rate(), 24_000.0);
+        let freq = zero_crossing_frequency(&audio, demod.output_rate());
+        assert!((freq - tone).abs() < 5.0, "recovered {freq} Hz");
+    }
+
+    #[test]
+    fn usb_rejects_lower_sideband() {
+        let sample_rate = 48_000.0;
+        let input: Vec<Complex> = (0..48_000)
+            .map(|i| {
+                let phase = TAU * (10_000.0 - 1_500.0) * i as f64 / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let mut demod = AudioDemodulator::new(sample_rate, 10_000.0, DemodMode::Usb);
+        let audio = demod.process(&input);
+        let settled = &audio[audio.len() / 2..];
+        assert!(settled.iter().all(|s| s.abs() < 0.01));
+    }
+
+    #[test]
+    fn lsb_recovers_tone_below_dial_and_rejects_upper_sideband() {
+        let sample_rate = 48_000.0;
+        let tone_at = |freq: f64| -> Vec<Complex> {
+            (0..48_000)
+                .map(|i| {
+                    let phase = TAU * freq * i as f64 / sample_rate;
+                    Complex::new(phase.cos() as f32, phase.sin() as f32)
+                })
+                .collect()
+        };
+        let mut demod = AudioDemodulator::new(sample_rate, 10_000.0, DemodMode::Lsb);
+        let audio = demod.process(&tone_at(10_000.0 - 1_500.0));
+        let freq = zero_crossing_frequency(&audio, demod.output_rate());
+        assert!((freq - 1_500.0).abs() < 5.0, "recovered {freq} Hz");
+
+        let mut demod = AudioDemodulator::new(sample_rate, 10_000.0, DemodMode::Lsb);
+        let audio = demod.process(&tone_at(10_000.0 + 1_500.0));
+        let settled = &audio[audio.len() / 2..];
+        assert!(settled.iter().all(|s| s.abs() < 0.01));
+    }
+
+    #[test]
+    fn narrowed_passband_rejects_tone_it_no_longer_covers() {
+        let sample_rate = 48_000.0;
+        let (dial, tone) = (10_000.0, 2_500.0);
+        let input: Vec<Complex> = (0..48_000)
+            .map(|i| {
+                let phase = TAU * (dial + tone) * i as f64 / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let mut demod = AudioDemodulator::new(sample_rate, dial, DemodMode::Usb);
+        let audio = demod.process(&input);
+        let settled = &audio[audio.len() / 2..];
+        assert!(settled.iter().any(|s| s.abs() > 0.5));
+
+        demod.set_passband(Some(Passband {
+            low: 300,
+            high: 1_800,
+        }));
+        let audio = demod.process(&input);
+        let settled = &audio[audio.len() / 2..];
+        assert!(settled.iter().all(|s| s.abs() < 0.01));
+
+        // Back to the mode's own
+        demod.set_passband(None);
+        let audio = demod.process(&input);
+        let settled = &audio[audio.len() / 2..];
+        assert!(settled.iter().any(|s| s.abs() > 0.5));
+    }
+
+    #[test]
+    fn am_recovers_modulating_tone_at_any_carrier_level() {
+        let sample_rate = 96_000.0;
+        let (carrier, tone) = (20_000.0, 1_000.0);
+        for level in [0.01, 1.0] {
+            let input: Vec<Complex> = (0..96_000)
+                .map(|i| {
+                    let t = i as f64 / sample_rate;
+                    let envelope = level * (1.0 + 0.5 * (TAU * tone * t).sin());
+                    let phase = TAU * carrier * t;
+                    Complex::new(
+                        (envelope * phase.cos()) as f32,
+                        (envelope * phase.sin()) as f32,
+                    )
+                })
+                .collect();
+            let mut demod = AudioDemodulator::new(sample_rate, carrier, DemodMode::Am);
+            let audio = demod.process(&input);
+
+            let freq = zero_crossing_frequency(&audio, demod.output_rate());
+            assert!((freq - tone).abs() < 5.0, "recovered {freq} Hz");
+            let peak = audio[audio.len() / 2..]
+                .iter()
+                .fold(0f32, |m, s| m.max(s.abs()));
+            assert!((peak - 0.5).abs() < 0.05, "peak {peak} at {level}");
+        }
+    }
+
+    #[test]
+    fn cw_carrier_is_heard_as_a_tone() {
+        let sample_rate = 48_000.0;
+        let input: Vec<Complex> = (0..48_000)
+            .map(|i| {
+                let phase = TAU * 10_000.0 * i as f64 / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let mut demod = AudioDemodulator::new(sample_rate, 10_000.0, DemodMode::Cw);
+        let audio = demod.process(&input);
+        let freq = zero_crossing_frequency(&audio, demod.output_rate());
+        assert!((freq - CW_PITCH).abs() < 5.0, "heard {freq} Hz");
+    }
+
+    #[test]
+    fn fm_recovers_modulating_tone() {
+        let sample_rate = 96_000.0;
+        let (carrier, tone, deviation) = (20_000.0, 1_000.0, 2_500.0);
+        let mut phase = 0.0;
+        let input: Vec<Complex> = (0..96_000)
+            .map(|i| {
+                let t = i as f64 / sample_rate;
+                let freq = carrier + deviation * (TAU * tone * t).sin();
+                phase += TAU * freq / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let mut demod = AudioDemodulator::new(sample_rate, carrier, DemodMode::Fm);
+        let audio = demod.process(&input);
+
+        let freq = zero_crossing_frequency(&audio, demod.output_rate());
+        assert!((freq - tone).abs() < 5.0, "recovered {freq} Hz");
+        let peak = audio[audio.len() / 2..]
+            .iter()
+            .fold(0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.5).abs() < 0.05, "peak {peak}");
+    }
+
+    #[test]
+    fn wfm_decodes_stereo_broadcast() {
+        let sample_rate = 480_000.0;
+        let (left, right) = (1_000.0, 0.0);
+        let mut phase = 0.0;
+        let input: Vec<Complex> = (0..480_000)
+            .map(|i| {
+                let t = i as f64 / sample_rate;
+                let l = 0.5 * (TAU * left * t).sin();
+                let r = right;
+                let pilot = TAU * 19_000.0 * t;
+                let multiplex =
+                    0.45 * (l + r) + 0.45 * (l - r) * (2.0 * pilot).sin() + 0.09 * pilot.sin();
+                phase += TAU * WFM_DEVIATION * multiplex / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        let mut demod = AudioDemodulator::new(sample_rate, 0.0, DemodMode::Wfm);
+        assert_eq!(demod.output_rate(), 48_000.0);
+        assert!(!demod.process_audio(&input[..48_000]).stereo);
+
+        let audio = demod.process_audio(&input[48_000..]);
+        assert!(audio.stereo);
+        let settled = &audio.samples[audio.samples.len() / 2..];
+        let peak = |channel: usize| {
+            settled
+                .iter()
+                .skip(channel)
+                .step_by(2)
+                .fold(0f32, |m, s| m.max(s.abs()))
+        };
+        let (left_peak, right_peak) = (peak(0), peak(1));
+        assert!(left_peak > 0.4, "left {left_peak}");
+        assert!(right_peak < left_peak / 10.0, "right {right_peak}");
+    }
+}
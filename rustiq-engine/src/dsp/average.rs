@@ -0,0 +1,177 @@
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Error, rustradio_macros};
+
+use crate::tuner::CenterFrequency;
+use rustiq_messages::{Averaging, Hertz};
+
+/// Averages magnitude frames in power, so noise settles towards its mean
+/// while a steady carrier keeps its level.
+pub struct SpectrumAverager {
+    averaging: Averaging,
+    /// Running power of each bin
+    power: Vec<f32>,
+    /// Frames in `power` so far: those of the current block, or whether
+    /// the moving average has started
+    frames: usize,
+    output: Vec<f32>,
+}
+
+impl SpectrumAverager {
+    pub fn new(averaging: Averaging, bins: usize) -> Self {
+        Self {
+            averaging,
+            power: vec![0.0; bins],
+            frames: 0,
+            output: vec![0.0; bins],
+        }
+    }
+
+    /// Frames averaged into each one that comes out: 1 except for blocks.
+    pub fn stride(&self) -> usize {
+        match self.averaging {
+            Averaging::Block(frames) => frames.max(1),
+            Averaging::Off | Averaging::Exponential(_) => 1,
+        }
+    }
+
+    /// Start over, forgetting the frames so far.
+    pub fn reset(&mut self) {
+        self.frames = 0;
+    }
+
+    /// Add a frame of magnitudes, returning the averaged frame if one is
+    /// due.
+    pub fn push(&mut self, magnitudes: &[f32]) -> Option<&[f32]> {
+        match self.averaging {
+            Averaging::Off => {
+                self.output.copy_from_slice(magnitudes);
+            }
+            Averaging::Exponential(factor) => {
+                if self.frames == 0 {
+                    self.load(magnitudes);
+                    self.frames = 1;
+                } else {
+                    for (power, magnitude) in self.power.iter_mut().zip(magnitudes) {
+                        *power += factor * (magnitude * magnitude - *power);
+                    }
+                }
+                self.emit(1.0);
+            }
+            Averaging::Block(frames) => {
+                if self.frames == 0 {
+                    self.load(magnitudes);
+                } else {
+                    for (power, magnitude) in self.power.iter_mut().zip(magnitudes) {
+                        *power += magnitude * magnitude;
+                    }
+                }
+                self.frames += 1;
+                if self.frames < frames {
+                    return None;
+                }
+                self.emit(1.0 / self.frames as f32);
+                self.frames = 0;
+            }
+        }
+        Some(&self.output)
+    }
+
+    fn load(&mut self, magnitudes: &[f32]) {
+        for (power, magnitude) in self.power.iter_mut().zip(magnitudes) {
+            *power = magnitude * magnitude;
+        }
+    }
+
+    /// Write the magnitudes of `power` scaled by `scale` to the output.
+    fn emit(&mut self, scale: f32) {
+        for (output, power) in self.output.iter_mut().zip(&self.power) {
+            *output = (power * scale).sqrt();
+        }
+    }
+}
+
+/// Averages the frames of a magnitude stream on their way to the spectrum
+/// sink. Starts over on a retune, so frames of two tunings don't blend.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct AverageSpectrum {
+    #[rustradio(in)]
+    src: ReadStream<f32>,
+    #[rustradio(out)]
+    dst: WriteStream<f32>,
+    averager: SpectrumAverager,
+    fft_size: usize,
+    center_frequency: CenterFrequency,
+    /// Center frequency of the frames being averaged
+    #[rustradio(default)]
+    tuned: Option<Hertz>,
+}
+
+impl Block for AverageSpectrum {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.len() < self.fft_size {
+            return Ok(BlockRet::WaitForStream(&self.src, self.fft_size));
+        }
+        let mut o = self.dst.write_buf()?;
+        if o.len() < self.fft_size {
+            return Ok(BlockRet::WaitForStream(&self.dst, self.fft_size));
+        }
+
+        let center_frequency = self.center_frequency.get();
+        if self.tuned != Some(center_frequency) {
+            self.tuned = Some(center_frequency);
+            self.averager.reset();
+        }
+        let produced = match self.averager.push(&input.slice()[..self.fft_size]) {
+            Some(frame) => {
+                o.slice()[..frame.len()].copy_from_slice(frame);
+                frame.len()
+            }
+            None => 0,
+        };
+        input.consume(self.fft_size);
+        o.produce(produced, &[]);
+        Ok(BlockRet::Again)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn off_passes_frames_through() {
+        let mut averager = SpectrumAverager::new(Averaging::Off, 2);
+        assert_eq!(averager.push(&[1.0, 2.0]), Some(&[1.0, 2.0][..]));
+        assert_eq!(averager.push(&[3.0, 4.0]), Some(&[3.0, 4.0][..]));
+    }
+
+    #[test]
+    fn block_averages_power_once_per_block() {
+        let mut averager = SpectrumAverager::new(Averaging::Block(2), 2);
+        assert_eq!(averager.stride(), 2);
+        assert_eq!(averager.push(&[3.0, 1.0]), None);
+        let frame = averager.push(&[4.0, 1.0]).unwrap().to_vec();
+        assert!((frame[0] - 12.5f32.sqrt()).abs() < 1e-6, "{frame:?}");
+        assert!((frame[1] - 1.0).abs() < 1e-6, "{frame:?}");
+        // The next block starts afresh
+        assert_eq!(averager.push(&[2.0, 2.0]), None);
+        assert_eq!(averager.push(&[2.0, 2.0]), Some(&[2.0, 2.0][..]));
+    }
+
+    #[test]
+    fn exponential_settles_towards_new_level() {
+        let mut averager = SpectrumAverager::new(Averaging::Exponential(0.5), 1);
+        assert_eq!(averager.push(&[0.0]), Some(&[0.0][..]));
+        let levels: Vec<f32> = (0..20).map(|_| averager.push(&[1.0]).unwrap()[0]).collect();
+        assert!((levels[0] - 0.5f32.sqrt()).abs() < 1e-6, "{levels:?}");
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((levels[19] - 1.0).abs() < 1e-3, "{levels:?}");
+
+        // A reset takes the next frame as it is
+        averager.reset();
+        assert_eq!(averager.push(&[0.0]), Some(&[0.0][..]));
+    }
+}
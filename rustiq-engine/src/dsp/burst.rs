@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use rustradio::Complex;
+
+use rustiq_messages::{Burst, Decibels};
+
+/// Smoothing factor of the power envelope. Averages over roughly 16 samples,
+/// enough to stop single noise samples from triggering a burst.
+const ENVELOPE_ALPHA: f32 = 1.0 / 16.0;
+
+/// Adaptation rate of the noise floor while no burst is active.
+/// The floor falls faster than it rises so it recovers quickly after a burst.
+const FLOOR_RISE: f32 = 1e-4;
+const FLOOR_FALL: f32 = 1e-3;
+
+/// Power must drop this far below the threshold (as a power ratio) to end a burst.
+const HYSTERESIS: f32 = 0.5;
+
+/// Floor used in place of a perfectly silent input, so digital silence never triggers.
+const MIN_FLOOR: f32 = 1e-12;
+
+/// How long power must stay below the threshold before a burst ends, by default.
+const HANG_TIME: f64 = 0.001;
+
+/// One burst found by `BurstDetector`.
+#[derive(Debug, Clone, Copy)]
+pub struct DetectedBurst {
+    /// Seconds since detection started
+    pub start: f64,
+    /// Seconds from the first to the last sample above the threshold
+    pub duration: f64,
+    /// Seconds since the previous burst started
+    pub interval: Option<f64>,
+    /// Peak envelope power over the noise floor (linear)
+    pub peak_snr: f32,
+}
+
+impl DetectedBurst {
+    /// Convert to the message sent to the UI.
+    pub fn to_message(self) -> Burst {
+        Burst {
+            start: Duration::from_secs_f64(self.start),
+            duration: Duration::from_secs_f64(self.duration),
+            interval: self.interval.map(Duration::from_secs_f64),
+            peak_snr: Decibels::from_power(self.peak_snr),
+        }
+    }
+}
+
+struct ActiveBurst {
+    start: u64,
+    last_above: u64,
+    peak: f32,
+}
+
+/// Finds bursts of energy in an IQ stream by comparing a smoothed power
+/// envelope against a tracked noise floor.
+pub struct BurstDetector {
+    sample_rate: f64,
+    /// Power ratio over the noise floor that starts a burst
+    threshold: f32,
+    hang_samples: u64,
+    envelope: f32,
+    noise_floor: Option<f32>,
+    position: u64,
+    active: Option<ActiveBurst>,
+    previous_start: Option<u64>,
+}
+
+impl BurstDetector {
+    pub fn new(sample_rate: f64, threshold: f32) -> Self {
+        Self {
+            sample_rate,
+            threshold,
+            hang_samples: (HANG_TIME * sample_rate).ceil() as u64,
+            envelope: 0.0,
+            noise_floor: None,
+            position: 0,
+            active: None,
+            previous_start: None,
+        }
+    }
+
+    /// Set how long power must stay below the threshold before a burst ends.
+    /// Slowly fading signals need longer than the default to stay in one piece.
+    pub fn with_hang_time(mut self, seconds: f64) -> Self {
+        self.hang_samples = (seconds * self.sample_rate).ceil() as u64;
+        self
+    }
+
+    /// Feed IQ samples, returning every burst that ended within them.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<DetectedBurst> {
+        let mut bursts = Vec::new();
+        for sample in input {
+            let power = sample.norm_sqr();
+            // Start the envelope and floor at the first sample rather than zero
+            let floor = *self.noise_floor.get_or_insert_with(|| {
+                self.envelope = power;
+                power
+            });
+            self.envelope += ENVELOPE_ALPHA * (power - self.envelope);
+            let floor = floor.max(MIN_FLOOR);
+            let snr = self.envelope / floor;
+
+            match &mut self.active {
+                Some(burst) => {
+                    burst.peak = burst.peak.max(snr);
+                    let release = self.threshold * HYSTERESIS;
+                    // The raw power marks the end, so the envelope's decay
+                    // doesn't stretch the burst
+                    if power / floor > release {
+                        burst.last_above = self.position;
+                    }
+                    if snr <= release && self.position - burst.last_above > self.hang_samples {
+                        bursts.push(self.finish_burst());
+                    }
+                }
+                None if snr > self.threshold => {
+                    self.active = Some(ActiveBurst {
+                        start: self.position,
+                        last_above: self.position,
+                        peak: snr,
+                    });
+                }
+                None => {
+                    let floor = self.noise_floor.as_mut().unwrap();
+                    let rate = if self.envelope < *floor {
+                        FLOOR_FALL
+                    } else {
+                        FLOOR_RISE
+                    };
+                    *floor += rate * (self.envelope - *floor);
+                }
+            }
+            self.position += 1;
+        }
+        bursts
+    }
+
+    fn finish_burst(&mut self) -> DetectedBurst {
+        let burst = self.active.take().expect("no active burst");
+        let interval = self
+            .previous_start
+            .map(|previous| (burst.start - previous) as f64 / self.sample_rate);
+        self.previous_start = Some(burst.start);
+        DetectedBurst {
+            start: burst.start as f64 / self.sample_rate,
+            duration: (burst.last_above - burst.start + 1) as f64 / self.sample_rate,
+            interval,
+            peak_snr: burst.peak,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pulses of `on` samples ending every `period` samples, over a weak constant floor.
+    fn pulse_train(on: usize, period: usize, pulses: usize) -> Vec<Complex> {
+        (0..period * pulses + period / 2)
+            .map(|i| {
+                if i % period >= period - on {
+                    Complex::new(1.0, 0.0)
+                } else {
+                    Complex::new(0.01, 0.0)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measures_pulse_timing() {
+        let sample_rate = 48_000.0;
+        // 5 ms pulses every 20 ms (50 Hz PRF)
+        let input = pulse_train(240, 960, 5);
+        let mut detector = BurstDetector::new(sample_rate, 10.0);
+
+        let bursts = detector.process(&input);
+        assert_eq!(bursts.len(), 5);
+        assert!(bursts[0].interval.is_none());
+        for burst in &bursts {
+            assert!((burst.duration - 0.005).abs() < 0.0005, "{burst:?}");
+            assert!(burst.peak_snr > 1000.0);
+        }
+        for burst in &bursts[1..] {
+            let interval = burst.interval.unwrap();
+            assert!((interval - 0.020).abs() < 1e-9, "{burst:?}");
+        }
+    }
+
+    #[test]
+    fn ignores_steady_signal() {
+        let input = vec![Complex::new(0.5, 0.5); 48_000];
+        let mut detector = BurstDetector::new(48_000.0, 10.0);
+        assert!(detector.process(&input).is_empty());
+    }
+}
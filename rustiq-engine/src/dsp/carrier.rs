@@ -0,0 +1,152 @@
+use std::f64::consts::TAU;
+
+use rustradio::Complex;
+
+use super::zoom::{ZoomFft, ZoomFrame};
+
+/// Rate the input is decimated to before the zoom FFT.
+/// Sets the lock range: carriers within ±ZOOM_RATE/2 of the target are found.
+const ZOOM_RATE: f64 = 2_000.0;
+
+/// Decimated samples per estimate. At `ZOOM_RATE` this is roughly two estimates per second.
+const ZOOM_FFT_SIZE: usize = 1024;
+
+/// One carrier estimate produced by `CarrierMeter`.
+#[derive(Debug, Clone, Copy)]
+pub struct CarrierEstimate {
+    /// Seconds of input consumed when the estimate was made
+    pub elapsed: f64,
+    /// Carrier offset in Hz from the input's DC
+    pub offset: f64,
+    /// Mean carrier power (linear, amplitude squared)
+    pub power: f32,
+}
+
+/// Measures the frequency of a single carrier near a target offset.
+///
+/// The zoom FFT finds the strongest bin near the target, parabolic interpolation
+/// refines it to a fraction of a bin, and the average phase rotation of the
+/// decimated samples (after removing that coarse estimate) gives the residual.
+pub struct CarrierMeter {
+    zoom: ZoomFft,
+    target_offset: f64,
+    frames: u64,
+}
+
+impl CarrierMeter {
+    pub fn new(sample_rate: f64, target_offset: f64) -> Self {
+        let decimation = (sample_rate / ZOOM_RATE).round() as usize;
+        Self {
+            zoom: ZoomFft::new(sample_rate, target_offset, decimation, ZOOM_FFT_SIZE),
+            target_offset,
+            frames: 0,
+        }
+    }
+
+    /// Feed IQ samples, returning an estimate for every completed zoom frame.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<CarrierEstimate> {
+        let rate = self.zoom.output_rate();
+        let frame_duration = self.zoom.fft_size() as f64 / rate;
+        self.zoom
+            .process(input)
+            .into_iter()
+            .map(|frame| {
+                self.frames += 1;
+                let (residual, power) = estimate_frame(&frame, rate);
+                CarrierEstimate {
+                    elapsed: self.frames as f64 * frame_duration,
+                    offset: self.target_offset + residual,
+                    power,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Estimate the strongest carrier in a zoom frame.
+/// Returns (frequency relative to the zoom center in Hz, mean power).
+fn estimate_frame(frame: &ZoomFrame, sample_rate: f64) -> (f64, f32) {
+    let n = frame.spectrum.len();
+    let mags: Vec<f32> = frame.spectrum.iter().map(|c| c.norm()).collect();
+    let (peak, _) = mags
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .expect("zoom frame should not be empty");
+
+    // Parabolic interpolation on log magnitude around the peak bin
+    let log_mag = |i: usize| (mags[i % n].max(f32::MIN_POSITIVE) as f64).ln();
+    let (left, center, right) = (log_mag(peak + n - 1), log_mag(peak), log_mag(peak + 1));
+    let denominator = left - 2.0 * center + right;
+    let delta = if denominator.abs() > f64::EPSILON {
+        (0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let signed_bin = if peak > n / 2 {
+        peak as f64 - n as f64
+    } else {
+        peak as f64
+    };
+    let coarse = (signed_bin + delta) * sample_rate / n as f64;
+
+    // Average phase rotation per sample after removing the coarse estimate
+    let step = coarse / sample_rate;
+    let mut rotation = Complex::new(0.0, 0.0);
+    let mut previous: Option<Complex> = None;
+    for (i, &sample) in frame.samples.iter().enumerate() {
+        let (sin, cos) = (-TAU * (step * i as f64).fract()).sin_cos();
+        let derotated = sample * Complex::new(cos as f32, sin as f32);
+        if let Some(previous) = previous {
+            rotation += derotated * previous.conj();
+        }
+        previous = Some(derotated);
+    }
+    let residual = (rotation.im as f64).atan2(rotation.re as f64) * sample_rate / TAU;
+
+    let power = frame.samples.iter().map(|c| c.norm_sqr()).sum::<f32>() / n as f32;
+    (coarse + residual, power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(sample_rate: f64, freq: f64, len: usize) -> Vec<Complex> {
+        (0..len)
+            .map(|i| {
+                let phase = TAU * freq * i as f64 / sample_rate;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measures_offset_carrier_to_sub_hz() {
+        let sample_rate = 48_000.0;
+        let carrier = 10_003.37;
+        let mut meter = CarrierMeter::new(sample_rate, 10_000.0);
+
+        let estimates = meter.process(&tone(sample_rate, carrier, 48_000 * 2));
+        assert!(!estimates.is_empty());
+        for estimate in estimates {
+            assert!(
+                (estimate.offset - carrier).abs() < 0.05,
+                "estimated {} Hz, expected {} Hz",
+                estimate.offset,
+                carrier
+            );
+            assert!((estimate.power - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn finds_carrier_below_target() {
+        let sample_rate = 48_000.0;
+        let carrier = 9_650.8;
+        let mut meter = CarrierMeter::new(sample_rate, 10_000.0);
+
+        let estimate = meter.process(&tone(sample_rate, carrier, 48_000))[0];
+        assert!((estimate.offset - carrier).abs() < 0.05);
+    }
+}
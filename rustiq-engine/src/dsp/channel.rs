@@ -0,0 +1,73 @@
+use rustradio::Complex;
+
+use super::fir::{FirDecimator, IntegrateDump};
+use super::nco::Nco;
+
+/// Selects one narrow channel of the IQ stream and decimates it.
+///
+/// The channel is mixed to DC, integrate-and-dump decimated to a few times the
+/// target rate (for wideband sources), then filtered and decimated to roughly
+/// the target rate by an FIR filter supplied by the caller.
+pub struct Channelizer {
+    mixer: Nco,
+    pre_decimator: IntegrateDump,
+    filter: FirDecimator,
+    output_rate: f64,
+    /// Output samples until the filter's history is full of real input
+    settling: usize,
+    /// Group delay of the channel filter in seconds
+    delay: f64,
+}
+
+impl Channelizer {
+    /// Create a channelizer for the channel `offset` Hz from the input's DC.
+    ///
+    /// `taps` builds the channel filter given the rate it runs at and the
+    /// output rate, both in Hz.
+    pub fn new(
+        sample_rate: f64,
+        offset: f64,
+        target_rate: f64,
+        taps: impl FnOnce(f64, f64) -> Vec<Complex>,
+    ) -> Self {
+        let pre_decimation = ((sample_rate / (4.0 * target_rate)).floor() as usize).max(1);
+        let filter_rate = sample_rate / pre_decimation as f64;
+        let decimation = ((filter_rate / target_rate).round() as usize).max(1);
+        let output_rate = filter_rate / decimation as f64;
+
+        let taps = taps(filter_rate, output_rate);
+        Self {
+            mixer: Nco::new(sample_rate, offset),
+            pre_decimator: IntegrateDump::new(pre_decimation),
+            settling: taps.len().div_ceil(decimation),
+            delay: (taps.len() / 2) as f64 / filter_rate,
+            filter: FirDecimator::new(taps, decimation),
+            output_rate,
+        }
+    }
+
+    /// Sample rate of the channel in Hz.
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    /// Number of initial output samples affected by the filter starting from
+    /// silence. Level-sensitive consumers should skip them.
+    pub fn settling_samples(&self) -> usize {
+        self.settling
+    }
+
+    /// How far the channel lags the input, in seconds.
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Select the channel from IQ samples, returning the channel samples produced.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<Complex> {
+        let mixed: Vec<Complex> = input
+            .iter()
+            .filter_map(|&sample| self.pre_decimator.push(self.mixer.mix(sample)))
+            .collect();
+        self.filter.process(&mixed)
+    }
+}
@@ -0,0 +1,131 @@
+//! Telling apart the kinds of signal heard on a channel, from the shape of
+//! its samples at the dial frequency.
+
+use rustradio::Complex;
+
+use rustiq_messages::{Decibels, SignalClass};
+
+/// Share of the power in a steady component at the dial frequency above
+/// which the signal is taken for a carrier.
+const CARRIER_SHARE: f64 = 0.3;
+
+/// Spread of the envelope, relative to its mean, below which the signal is
+/// taken for a constant envelope one.
+const STEADY_ENVELOPE: f64 = 0.2;
+
+/// Running sums over a channel's samples, enough to classify the signal in
+/// them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChannelStats {
+    samples: u64,
+    /// Sum of the samples, for the carrier on the dial
+    sum: (f64, f64),
+    /// Sum of the envelope
+    envelope: f64,
+    /// Sum of the power
+    power: f64,
+}
+
+impl ChannelStats {
+    pub fn push(&mut self, sample: Complex) {
+        let (re, im) = (f64::from(sample.re), f64::from(sample.im));
+        self.samples += 1;
+        self.sum.0 += re;
+        self.sum.1 += im;
+        self.power += re * re + im * im;
+        self.envelope += (re * re + im * im).sqrt();
+    }
+
+    /// Add in `other`'s samples.
+    pub fn merge(&mut self, other: &ChannelStats) {
+        self.samples += other.samples;
+        self.sum.0 += other.sum.0;
+        self.sum.1 += other.sum.1;
+        self.envelope += other.envelope;
+        self.power += other.power;
+    }
+
+    /// Average power of the samples, relative to full scale.
+    pub fn level(&self) -> Decibels {
+        let power = self.power / self.samples.max(1) as f64;
+        Decibels((10.0 * power.max(1e-20).log10()) as f32)
+    }
+
+    /// What the samples look like, if there are any: a carrier if much of
+    /// their power is steady on the dial, otherwise by how much their
+    /// envelope spreads.
+    pub fn class(&self) -> Option<SignalClass> {
+        if self.samples == 0 || self.power <= 0.0 {
+            return None;
+        }
+        let n = self.samples as f64;
+        let power = self.power / n;
+        let carrier = ((self.sum.0 / n).powi(2) + (self.sum.1 / n).powi(2)) / power;
+        let mean = self.envelope / n;
+        let spread = (power - mean * mean).max(0.0).sqrt() / mean;
+        Some(if carrier > CARRIER_SHARE {
+            SignalClass::Carrier
+        } else if spread < STEADY_ENVELOPE {
+            SignalClass::ConstantEnvelope
+        } else {
+            SignalClass::VaryingEnvelope
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const RATE: f64 = 24_000.0;
+
+    fn stats(samples: impl Iterator<Item = Complex>) -> ChannelStats {
+        let mut stats = ChannelStats::default();
+        samples.for_each(|sample| stats.push(sample));
+        stats
+    }
+
+    /// A second of `signal(t)` at `RATE`.
+    fn second(signal: impl Fn(f64) -> Complex) -> impl Iterator<Item = Complex> {
+        (0..RATE as usize).map(move |i| signal(i as f64 / RATE))
+    }
+
+    #[test]
+    fn tells_carriers_from_fm_and_ssb() {
+        // AM, half modulated by a 1 kHz tone
+        let am = stats(second(|t| {
+            Complex::new((0.5 * (1.0 + 0.5 * (TAU * 1e3 * t).sin())) as f32, 0.0)
+        }));
+        assert_eq!(am.class(), Some(SignalClass::Carrier));
+        // FM, 3 kHz deviation by a 1 kHz tone
+        let fm = stats(second(|t| {
+            let phase = 3.0 * (TAU * 1e3 * t).sin();
+            Complex::new(phase.cos() as f32, phase.sin() as f32)
+        }));
+        assert_eq!(fm.class(), Some(SignalClass::ConstantEnvelope));
+        assert!((fm.level().0).abs() < 0.1, "{:?}", fm.level());
+        // SSB of two tones, beating
+        let ssb = stats(second(|t| {
+            let (a, b) = (TAU * 700.0 * t, TAU * 1_900.0 * t);
+            Complex::new(
+                (0.3 * (a.cos() + b.cos())) as f32,
+                (0.3 * (a.sin() + b.sin())) as f32,
+            )
+        }));
+        assert_eq!(ssb.class(), Some(SignalClass::VaryingEnvelope));
+        assert_eq!(ChannelStats::default().class(), None);
+    }
+
+    #[test]
+    fn merges_sums_of_parts() {
+        let samples: Vec<_> =
+            second(|t| Complex::new((TAU * 50.0 * t).cos() as f32, 0.1)).collect();
+        let whole = stats(samples.iter().copied());
+        let (first, last) = samples.split_at(1_000);
+        let mut parts = stats(first.iter().copied());
+        parts.merge(&stats(last.iter().copied()));
+        assert_eq!(parts.class(), whole.class());
+        assert!((parts.level().0 - whole.level().0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,183 @@
+use rustiq_messages::{Decibels, Hertz};
+
+use super::noise_floor;
+
+/// Frames left out at the start of a capture, as they may still hold
+/// samples from before the input was switched to the termination.
+const SETTLE_FRAMES: usize = 4;
+
+/// Averages magnitude frames of the terminated input, in power, into a
+/// dark frame.
+pub struct DarkFrameCapture {
+    /// Frames to average
+    frames: usize,
+    /// Frames still to leave out before averaging
+    settle: usize,
+    /// Tuning the frames so far were taken at
+    center_frequency: Option<Hertz>,
+    /// Summed power of each bin
+    power: Vec<f32>,
+    /// Frames in `power` so far
+    count: usize,
+}
+
+impl DarkFrameCapture {
+    pub fn new(frames: usize) -> Self {
+        Self {
+            frames: frames.max(1),
+            settle: SETTLE_FRAMES,
+            center_frequency: None,
+            power: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Add a frame taken at `center_frequency`, returning the dark frame
+    /// once enough are in. A frame from another tuning, or of another
+    /// size, starts the average over.
+    pub fn push(&mut self, center_frequency: Hertz, magnitudes: &[f32]) -> Option<DarkFrame> {
+        if self.settle > 0 {
+            self.settle -= 1;
+            return None;
+        }
+        if self.center_frequency != Some(center_frequency) || self.power.len() != magnitudes.len() {
+            self.center_frequency = Some(center_frequency);
+            self.power = vec![0.0; magnitudes.len()];
+            self.count = 0;
+        }
+        for (power, magnitude) in self.power.iter_mut().zip(magnitudes) {
+            *power += magnitude * magnitude;
+        }
+        self.count += 1;
+        if self.count < self.frames {
+            return None;
+        }
+        let power: Vec<f32> = self
+            .power
+            .iter()
+            .map(|power| power / self.count as f32)
+            .collect();
+        self.count = 0;
+        Some(DarkFrame::new(center_frequency, power))
+    }
+}
+
+/// The front end's own spectrum at one tuning, taken with its input
+/// terminated: its noise floor, and the spurs, DC spike and ripple above it.
+pub struct DarkFrame {
+    center_frequency: Hertz,
+    /// Power of each bin above the floor
+    excess: Vec<f32>,
+    /// Median bin power
+    floor: f32,
+    /// Mean bin power
+    mean: f32,
+}
+
+impl DarkFrame {
+    fn new(center_frequency: Hertz, power: Vec<f32>) -> Self {
+        let floor = noise_floor(&power);
+        let mean = power.iter().sum::<f32>() / power.len().max(1) as f32;
+        Self {
+            center_frequency,
+            excess: power.iter().map(|power| (power - floor).max(0.0)).collect(),
+            floor,
+            mean,
+        }
+    }
+
+    /// Mean level of the dark frame, as a magnitude in dB.
+    pub fn level(&self) -> Decibels {
+        Decibels::from_linear(self.mean.sqrt())
+    }
+
+    /// Whether the dark frame was taken at `center_frequency` with `bins`
+    /// bins, so it fits frames taken there.
+    pub fn fits(&self, center_frequency: Hertz, bins: usize) -> bool {
+        self.center_frequency == center_frequency && self.excess.len() == bins
+    }
+
+    /// Take the front end's artifacts out of a frame of magnitudes that
+    /// fits. Only their excess over the floor is subtracted, so the noise
+    /// floor stays where it is, and no bin is taken below the floor, which
+    /// the front end can't measure under.
+    pub fn subtract(&self, magnitudes: &mut [f32]) {
+        for (magnitude, excess) in magnitudes.iter_mut().zip(&self.excess) {
+            let power = *magnitude * *magnitude;
+            *magnitude = (power - excess).max(power.min(self.floor)).sqrt();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: Hertz = Hertz(100_000_000);
+
+    /// A flat floor of magnitude 0.01 with a spur of 0.1 in bin 2.
+    fn terminated() -> Vec<f32> {
+        let mut magnitudes = vec![0.01; 8];
+        magnitudes[2] = 0.1;
+        magnitudes
+    }
+
+    fn capture(frames: usize) -> DarkFrame {
+        let mut capture = DarkFrameCapture::new(frames);
+        for _ in 0..SETTLE_FRAMES + frames - 1 {
+            assert!(capture.push(CENTER, &terminated()).is_none());
+        }
+        capture.push(CENTER, &terminated()).unwrap()
+    }
+
+    #[test]
+    fn averages_frames_after_settling() {
+        let mut capture = DarkFrameCapture::new(2);
+        // Still the antenna's signal before the switch took effect
+        for _ in 0..SETTLE_FRAMES {
+            assert!(capture.push(CENTER, &[1.0; 8]).is_none());
+        }
+        assert!(capture.push(CENTER, &terminated()).is_none());
+        let dark_frame = capture.push(CENTER, &terminated()).unwrap();
+        assert!(dark_frame.fits(CENTER, 8));
+        assert!(!dark_frame.fits(Hertz(CENTER.0 + 1), 8));
+        assert!(!dark_frame.fits(CENTER, 16));
+        // Mean power of (7 * 1e-4 + 1e-2) / 8
+        assert!((dark_frame.level().0 - -28.737).abs() < 0.01);
+    }
+
+    #[test]
+    fn starts_over_on_another_tuning() {
+        let mut capture = DarkFrameCapture::new(2);
+        for _ in 0..SETTLE_FRAMES + 1 {
+            capture.push(CENTER, &terminated());
+        }
+        assert!(capture.push(Hertz(CENTER.0 * 2), &terminated()).is_none());
+        let dark_frame = capture.push(Hertz(CENTER.0 * 2), &terminated()).unwrap();
+        assert!(dark_frame.fits(Hertz(CENTER.0 * 2), 8));
+    }
+
+    #[test]
+    fn subtracts_the_spur_and_keeps_the_floor() {
+        let dark_frame = capture(3);
+        // A signal in bin 5 on the antenna, which adds noise of its own,
+        // and the spur over that noise rather than the front end's
+        let mut magnitudes = vec![0.02; 8];
+        magnitudes[2] = (0.02f32.powi(2) + 0.1f32.powi(2) - 0.01f32.powi(2)).sqrt();
+        magnitudes[5] = 0.5;
+        dark_frame.subtract(&mut magnitudes);
+
+        for (bin, magnitude) in magnitudes.iter().enumerate() {
+            let expected = if bin == 5 { 0.5 } else { 0.02 };
+            assert!((magnitude - expected).abs() < 1e-4, "{bin}: {magnitude}");
+        }
+
+        // With the spur stronger in the dark frame than now, the bin stops
+        // at the floor; a bin already under it stays where it is
+        let mut magnitudes = vec![0.005; 8];
+        magnitudes[2] = 0.05;
+        dark_frame.subtract(&mut magnitudes);
+        assert!((magnitudes[2] - 0.01).abs() < 1e-6);
+        assert_eq!(magnitudes[0], 0.005);
+    }
+}
@@ -0,0 +1,107 @@
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+use super::fir::{FirDecimator, lowpass_taps, shift_taps};
+
+/// Taps of the anti-alias filter per unit of decimation, giving a
+/// transition band a tenth of the output rate wide.
+const TAPS_PER_DECIMATION: usize = 40;
+
+/// Passband edge as a fraction of the output rate, leaving the edges of the
+/// decimated band, where the filter rolls off, to alias.
+const PASSBAND: f64 = 0.45;
+
+/// Most outputs filtered per call, so the FFT after the decimator gets
+/// samples as they are filtered rather than in one late burst.
+const CHUNK: usize = 8192;
+
+/// Keeps every `decimation`th sample after low-pass filtering, narrowing
+/// the stream to the slice of the band around DC.
+#[derive(rustradio_macros::Block)]
+pub struct Decimate {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+    filter: FirDecimator,
+    decimation: usize,
+}
+
+impl Decimate {
+    pub fn new(src: ReadStream<Complex>, decimation: usize) -> (Self, ReadStream<Complex>) {
+        let decimation = decimation.max(1);
+        let (dst, dr) = rustradio::stream::new_stream();
+        (
+            Self {
+                src,
+                dst,
+                filter: anti_alias(decimation),
+                decimation,
+            },
+            dr,
+        )
+    }
+}
+
+/// The filter and decimator for `decimation`.
+fn anti_alias(decimation: usize) -> FirDecimator {
+    let taps = lowpass_taps(
+        PASSBAND / decimation as f64,
+        TAPS_PER_DECIMATION * decimation + 1,
+    );
+    FirDecimator::new(shift_taps(&taps, 0.0), decimation)
+}
+
+impl Block for Decimate {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+        let mut o = self.dst.write_buf()?;
+        // Room for every output the input could complete
+        let n = input
+            .len()
+            .min(o.len().saturating_sub(1).min(CHUNK) * self.decimation);
+        if n == 0 {
+            return Ok(BlockRet::WaitForStream(&self.dst, 2));
+        }
+        let output = self.filter.process(&input.slice()[..n]);
+        o.slice()[..output.len()].copy_from_slice(&output);
+        o.produce(output.len(), &[]);
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    fn tone(frequency: f64, len: usize) -> Vec<Complex> {
+        (0..len)
+            .map(|i| {
+                let phase = TAU * frequency * i as f64;
+                Complex::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect()
+    }
+
+    /// Mean power of the second half of `samples`, once the filter is full.
+    fn settled_power(samples: &[Complex]) -> f32 {
+        let settled = &samples[samples.len() / 2..];
+        settled.iter().map(|s| s.norm_sqr()).sum::<f32>() / settled.len() as f32
+    }
+
+    #[test]
+    fn keeps_the_slice_around_dc_and_rejects_aliases() {
+        let filter = |frequency| settled_power(&anti_alias(8).process(&tone(frequency, 16_384)));
+        // Inside the decimated band, cycles per input sample
+        assert!((filter(0.03) - 1.0).abs() < 0.05);
+        // Would alias onto it
+        assert!(filter(0.3) < 1e-4);
+        assert!(filter(-0.1) < 1e-4);
+    }
+}
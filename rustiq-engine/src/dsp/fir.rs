@@ -0,0 +1,151 @@
+use std::f64::consts::{PI, TAU};
+
+use rustradio::Complex;
+
+/// Windowed-sinc low-pass taps with unity gain at DC.
+///
+/// `cutoff` is in cycles per sample (0.5 is Nyquist). `len` is rounded up to
+/// an odd number so the filter has an integer group delay.
+pub fn lowpass_taps(cutoff: f64, len: usize) -> Vec<f32> {
+    let len = len | 1;
+    let middle = (len / 2) as f64;
+    let taps: Vec<f64> = (0..len)
+        .map(|i| {
+            let t = i as f64 - middle;
+            let sinc = if t == 0.0 {
+                2.0 * cutoff
+            } else {
+                (TAU * cutoff * t).sin() / (PI * t)
+            };
+            // Blackman window
+            let w = TAU * i as f64 / (len - 1).max(1) as f64;
+            sinc * (0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos())
+        })
+        .collect();
+    let gain: f64 = taps.iter().sum();
+    taps.iter().map(|t| (t / gain) as f32).collect()
+}
+
+/// Shift real low-pass taps up by `offset` cycles per sample, making a
+/// complex band-pass filter centered on `offset`.
+pub fn shift_taps(taps: &[f32], offset: f64) -> Vec<Complex> {
+    let middle = (taps.len() / 2) as f64;
+    taps.iter()
+        .enumerate()
+        .map(|(i, &tap)| {
+            let (sin, cos) = (TAU * offset * (i as f64 - middle)).sin_cos();
+            Complex::new(tap * cos as f32, tap * sin as f32)
+        })
+        .collect()
+}
+
+/// FIR filter that keeps every `decimation`th output.
+pub struct FirDecimator {
+    /// Taps in reverse order, so they line up with the oldest-first history
+    reversed: Vec<Complex>,
+    decimation: usize,
+    history: Vec<Complex>,
+    /// Index in `history` where the next output's window starts
+    next: usize,
+}
+
+impl FirDecimator {
+    pub fn new(taps: Vec<Complex>, decimation: usize) -> Self {
+        let mut reversed = taps;
+        reversed.reverse();
+        Self {
+            history: vec![Complex::new(0.0, 0.0); reversed.len() - 1],
+            reversed,
+            decimation: decimation.max(1),
+            next: 0,
+        }
+    }
+
+    /// Filter and decimate the input, returning every output completed by it.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<Complex> {
+        self.history.extend_from_slice(input);
+        let len = self.reversed.len();
+        let mut output = Vec::with_capacity(input.len() / self.decimation + 1);
+        while self.next + len <= self.history.len() {
+            let window = &self.history[self.next..self.next + len];
+            output.push(window.iter().zip(&self.reversed).map(|(x, t)| x * t).sum());
+            self.next += self.decimation;
+        }
+        let consumed = self.next.min(self.history.len());
+        self.history.drain(..consumed);
+        self.next -= consumed;
+        output
+    }
+}
+
+/// Integrate-and-dump decimator: averages each block of `decimation` samples.
+///
+/// Cheap, with nulls on the alias frequencies closest to DC, but a poor
+/// anti-alias filter on its own. Used ahead of a proper FIR stage.
+pub struct IntegrateDump {
+    decimation: usize,
+    accumulator: Complex,
+    accumulated: usize,
+}
+
+impl IntegrateDump {
+    pub fn new(decimation: usize) -> Self {
+        Self {
+            decimation: decimation.max(1),
+            accumulator: Complex::new(0.0, 0.0),
+            accumulated: 0,
+        }
+    }
+
+    /// Add one sample, returning the block average when a block completes.
+    pub fn push(&mut self, sample: Complex) -> Option<Complex> {
+        self.accumulator += sample;
+        self.accumulated += 1;
+        if self.accumulated < self.decimation {
+            return None;
+        }
+        let average = self.accumulator / self.decimation as f32;
+        self.accumulator = Complex::new(0.0, 0.0);
+        self.accumulated = 0;
+        Some(average)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f64, len: usize) -> Vec<Complex> {
+        (0..len)
+            .map(|i| {
+                let (sin, cos) = (TAU * freq * i as f64).sin_cos();
+                Complex::new(cos as f32, sin as f32)
+            })
+            .collect()
+    }
+
+    fn output_level(filter: &mut FirDecimator, input: &[Complex]) -> f32 {
+        let output = filter.process(input);
+        // Skip the filter's start-up transient
+        let settled = &output[output.len() / 2..];
+        settled.iter().map(|c| c.norm()).sum::<f32>() / settled.len() as f32
+    }
+
+    #[test]
+    fn lowpass_passes_dc_and_rejects_stopband() {
+        let taps = shift_taps(&lowpass_taps(0.05, 101), 0.0);
+        let pass = output_level(&mut FirDecimator::new(taps.clone(), 1), &tone(0.01, 2000));
+        let stop = output_level(&mut FirDecimator::new(taps, 1), &tone(0.2, 2000));
+        assert!((pass - 1.0).abs() < 0.01, "passband gain {pass}");
+        assert!(stop < 1e-3, "stopband gain {stop}");
+    }
+
+    #[test]
+    fn shifted_taps_select_one_side() {
+        let taps = shift_taps(&lowpass_taps(0.05, 101), 0.1);
+        let upper = output_level(&mut FirDecimator::new(taps.clone(), 4), &tone(0.1, 4000));
+        let lower = output_level(&mut FirDecimator::new(taps, 4), &tone(-0.1, 4000));
+        assert!((upper - 1.0).abs() < 0.01, "upper gain {upper}");
+        assert!(lower < 1e-3, "lower gain {lower}");
+    }
+}
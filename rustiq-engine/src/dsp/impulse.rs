@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustfft::{Fft, FftPlanner};
+use rustradio::Complex;
+
+use rustiq_messages::{Decibels, Impulse};
+
+/// Samples per analysis block.
+const BLOCK_SIZE: usize = 64;
+
+/// Sub-bands the spectrum of each block is divided into.
+const BANDS: usize = 8;
+
+/// Sub-bands that must rise together for a block to count as an impulse.
+/// Carriers keying on and off raise only one or two.
+const MIN_BANDS: usize = 6;
+
+/// How many times more the strongest sub-band may rise than the others that
+/// count towards `MIN_BANDS`. Impulses have a roughly flat spectrum.
+const MAX_SPREAD: f32 = 10.0;
+
+/// Adaptation rates of the per-band noise floors, per block. The floors fall
+/// faster than they rise so they recover quickly after a crash.
+const FLOOR_RISE: f32 = 1e-3;
+const FLOOR_FALL: f32 = 1e-2;
+
+/// Floor used in place of a perfectly silent band.
+const MIN_FLOOR: f32 = 1e-12;
+
+/// Impulses closer together than this are counted as one crash.
+const DEAD_TIME: f64 = 0.01;
+
+/// One static crash found by `ImpulseDetector`.
+#[derive(Debug, Clone, Copy)]
+pub struct DetectedImpulse {
+    /// Seconds since detection started
+    pub time: f64,
+    /// Peak mean power over the noise floor across the sub-bands (linear)
+    pub peak_snr: f32,
+}
+
+impl DetectedImpulse {
+    /// Convert to the message sent to the UI.
+    pub fn to_message(self) -> Impulse {
+        Impulse {
+            time: Duration::from_secs_f64(self.time),
+            peak_snr: Decibels::from_power(self.peak_snr),
+        }
+    }
+}
+
+struct ActiveImpulse {
+    start: u64,
+    last_above: u64,
+    peak: f32,
+}
+
+/// Counts broadband impulses such as lightning static crashes and
+/// electrical interference.
+///
+/// Each short block of samples is split into sub-bands with an FFT, and every
+/// sub-band's power is compared against its own slowly tracked noise floor.
+/// A block where most sub-bands jump at once is impulsive; narrowband signals
+/// switching on don't qualify however strong they are.
+pub struct ImpulseDetector {
+    sample_rate: f64,
+    /// Power ratio over the floor a sub-band must exceed
+    threshold: f32,
+    fft: Arc<dyn Fft<f32>>,
+    block: Vec<Complex>,
+    floors: Option<[f32; BANDS]>,
+    /// Index of the current block
+    position: u64,
+    dead_blocks: u64,
+    active: Option<ActiveImpulse>,
+}
+
+impl ImpulseDetector {
+    pub fn new(sample_rate: f64, threshold: f32) -> Self {
+        Self {
+            sample_rate,
+            threshold,
+            fft: FftPlanner::new().plan_fft_forward(BLOCK_SIZE),
+            block: Vec::with_capacity(BLOCK_SIZE),
+            floors: None,
+            position: 0,
+            dead_blocks: (DEAD_TIME * sample_rate / BLOCK_SIZE as f64).ceil() as u64,
+            active: None,
+        }
+    }
+
+    /// Feed IQ samples, returning every impulse that ended within them.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<DetectedImpulse> {
+        let mut impulses = Vec::new();
+        for &sample in input {
+            self.block.push(sample);
+            if self.block.len() < BLOCK_SIZE {
+                continue;
+            }
+            let powers = self.band_powers();
+            impulses.extend(self.advance(powers));
+            self.block.clear();
+            self.position += 1;
+        }
+        impulses
+    }
+
+    /// Power in each sub-band of the current block.
+    fn band_powers(&mut self) -> [f32; BANDS] {
+        self.fft.process(&mut self.block);
+        let mut powers = [0.0; BANDS];
+        for (bin, value) in self.block.iter().enumerate() {
+            powers[bin * BANDS / BLOCK_SIZE] += value.norm_sqr();
+        }
+        powers
+    }
+
+    fn advance(&mut self, powers: [f32; BANDS]) -> Option<DetectedImpulse> {
+        let floors = *self.floors.get_or_insert(powers);
+        let ratios: [f32; BANDS] =
+            std::array::from_fn(|band| powers[band] / floors[band].max(MIN_FLOOR));
+        // A band only counts if its rise is comparable to the strongest one,
+        // which rules out the spectral leakage of a strong carrier switching on
+        let excess: [f32; BANDS] = std::array::from_fn(|band| powers[band] - floors[band]);
+        let max_excess = excess.iter().fold(0.0f32, |max, &e| max.max(e));
+        let rising = (0..BANDS)
+            .filter(|&band| {
+                ratios[band] > self.threshold && excess[band] * MAX_SPREAD >= max_excess
+            })
+            .count();
+
+        if rising >= MIN_BANDS {
+            let snr = ratios.iter().sum::<f32>() / BANDS as f32;
+            match &mut self.active {
+                Some(impulse) => {
+                    impulse.peak = impulse.peak.max(snr);
+                    impulse.last_above = self.position;
+                }
+                None => {
+                    self.active = Some(ActiveImpulse {
+                        start: self.position,
+                        last_above: self.position,
+                        peak: snr,
+                    });
+                }
+            }
+            return None;
+        }
+
+        let floors = self.floors.as_mut().expect("floors set above");
+        for (floor, power) in floors.iter_mut().zip(powers) {
+            let rate = if power < *floor {
+                FLOOR_FALL
+            } else {
+                FLOOR_RISE
+            };
+            *floor += rate * (power - *floor);
+        }
+
+        let impulse = self
+            .active
+            .take_if(|impulse| self.position - impulse.last_above > self.dead_blocks)?;
+        Some(DetectedImpulse {
+            time: (impulse.start * BLOCK_SIZE as u64) as f64 / self.sample_rate,
+            peak_snr: impulse.peak,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const RATE: f64 = 48_000.0;
+
+    /// Low-level pseudo-random noise.
+    fn noise(len: usize) -> Vec<Complex> {
+        let mut state = 1u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 8) as f32 / (1 << 24) as f32 - 0.5) * 0.01
+        };
+        (0..len).map(|_| Complex::new(next(), next())).collect()
+    }
+
+    #[test]
+    fn counts_clicks_once_each() {
+        let mut input = noise(RATE as usize * 2);
+        let clicks = [0.5, 0.8, 1.5];
+        for t in clicks {
+            // A few samples of a decaying spike, like a crash through the front end
+            let at = (t * RATE) as usize;
+            for (i, sample) in input[at..at + 4].iter_mut().enumerate() {
+                *sample += Complex::new(2.0 / (i + 1) as f32, 0.0);
+            }
+        }
+        let mut detector = ImpulseDetector::new(RATE, 10.0);
+        let impulses = detector.process(&input);
+
+        assert_eq!(impulses.len(), clicks.len(), "{impulses:?}");
+        for (impulse, t) in impulses.iter().zip(clicks) {
+            assert!((impulse.time - t).abs() < 0.01, "{impulse:?}");
+            assert!(impulse.peak_snr > 10.0, "{impulse:?}");
+        }
+    }
+
+    #[test]
+    fn ignores_narrowband_bursts() {
+        let mut input = noise(RATE as usize * 2);
+        // Switching on mid-block, so the leakage reaches every sub-band
+        let start = RATE as usize / 2 + 10;
+        for (i, sample) in input[start..start + 4_800].iter_mut().enumerate() {
+            let phase = TAU * 3_000.0 * i as f64 / RATE;
+            *sample += Complex::new(phase.cos() as f32, phase.sin() as f32);
+        }
+        let mut detector = ImpulseDetector::new(RATE, 10.0);
+        assert!(detector.process(&input).is_empty());
+    }
+}
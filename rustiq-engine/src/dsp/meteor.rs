@@ -0,0 +1,118 @@
+use rustradio::Complex;
+
+use super::burst::{BurstDetector, DetectedBurst};
+use super::channel::Channelizer;
+use super::fir::{lowpass_taps, shift_taps};
+
+/// Gaps shorter than this don't split a ping. Meteor reflections flutter as
+/// the trail distorts, and Es openings fade in and out.
+const HANG_TIME: f64 = 0.05;
+
+/// Lowest channel rate, so narrow channels still get a usable filter.
+const MIN_RATE: f64 = 200.0;
+
+/// Finds meteor scatter and sporadic-E pings on one narrow channel.
+///
+/// The channel around a beacon or broadcast carrier is selected and decimated
+/// to about twice its bandwidth, and the burst detector looks for brief rises
+/// of the channel power above its noise floor.
+pub struct PingDetector {
+    channel: Channelizer,
+    detector: BurstDetector,
+    /// Channel samples still to skip while the filter settles; its startup
+    /// ramp would otherwise set the noise floor near zero
+    settling: usize,
+}
+
+impl PingDetector {
+    /// Create a detector for the channel `offset` Hz from the input's DC.
+    /// `threshold` is the power ratio over the noise floor that starts a ping.
+    pub fn new(sample_rate: f64, offset: f64, bandwidth: f64, threshold: f32) -> Self {
+        let target_rate = (2.0 * bandwidth).max(MIN_RATE);
+        let channel = Channelizer::new(
+            sample_rate,
+            offset,
+            target_rate,
+            |filter_rate, output_rate| {
+                let cutoff = (bandwidth / 2.0).min(0.45 * output_rate);
+                let len = (16.0 * filter_rate / cutoff).ceil() as usize;
+                shift_taps(&lowpass_taps(cutoff / filter_rate, len), 0.0)
+            },
+        );
+        let detector =
+            BurstDetector::new(channel.output_rate(), threshold).with_hang_time(HANG_TIME);
+        Self {
+            settling: channel.settling_samples(),
+            channel,
+            detector,
+        }
+    }
+
+    /// Feed IQ samples, returning every ping that ended within them.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<DetectedBurst> {
+        let channel = self.channel.process(input);
+        let skip = self.settling.min(channel.len());
+        self.settling -= skip;
+        // Report times from the start of the input, not from the end of
+        // settling, and take out the filter's delay
+        let settle_time = self.channel.settling_samples() as f64 / self.channel.output_rate();
+        let mut pings = self.detector.process(&channel[skip..]);
+        for ping in &mut pings {
+            ping.start += settle_time - self.channel.delay();
+        }
+        pings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const RATE: f64 = 48_000.0;
+
+    /// A weak carrier at `freq` that rises by 30 dB for each (start, length)
+    /// in seconds, over low-level noise.
+    fn beacon(freq: f64, seconds: f64, pings: &[(f64, f64)]) -> Vec<Complex> {
+        let mut state = 1u32;
+        let mut noise = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 8) as f32 / (1 << 24) as f32 - 0.5) * 1e-3
+        };
+        (0..(seconds * RATE) as usize)
+            .map(|i| {
+                let t = i as f64 / RATE;
+                let loud = pings.iter().any(|&(s, len)| t >= s && t < s + len);
+                let amplitude = if loud { 0.1 } else { 0.003 };
+                let phase = TAU * freq * t;
+                Complex::new(
+                    (amplitude * phase.cos()) as f32 + noise(),
+                    (amplitude * phase.sin()) as f32 + noise(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_ping_timing() {
+        let pings = [(2.0, 0.2), (3.5, 0.05)];
+        let input = beacon(5_000.0, 5.0, &pings);
+        let mut detector = PingDetector::new(RATE, 5_000.0, 500.0, 10.0);
+        let detected = detector.process(&input);
+
+        assert_eq!(detected.len(), 2, "{detected:?}");
+        for (ping, &(start, length)) in detected.iter().zip(&pings) {
+            assert!((ping.start - start).abs() < 0.02, "{ping:?}");
+            assert!((ping.duration - length).abs() < 0.02, "{ping:?}");
+            assert!(ping.peak_snr > 100.0, "{ping:?}");
+        }
+        assert!((detected[1].interval.unwrap() - 1.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn ignores_signals_outside_the_channel() {
+        let input = beacon(8_000.0, 4.0, &[(2.0, 0.2)]);
+        let mut detector = PingDetector::new(RATE, 5_000.0, 500.0, 10.0);
+        assert!(detector.process(&input).is_empty());
+    }
+}
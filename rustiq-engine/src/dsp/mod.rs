@@ -0,0 +1,54 @@
+#[cfg(feature = "adsb")]
+mod adsb;
+#[cfg(feature = "ais")]
+mod ais;
+mod audio;
+mod average;
+mod burst;
+mod carrier;
+mod channel;
+mod classify;
+mod dark_frame;
+mod decimate;
+mod fir;
+mod impulse;
+mod meteor;
+mod nco;
+mod rds;
+mod resample;
+#[cfg(feature = "selcall")]
+mod selcall;
+mod squelch;
+#[cfg(feature = "sstv")]
+mod sstv;
+mod stereo;
+mod stretch;
+mod survey;
+mod symbol_rate;
+mod window;
+mod zoom;
+
+#[cfg(feature = "adsb")]
+pub use adsb::AdsbDecoder;
+#[cfg(feature = "ais")]
+pub use ais::AisDecoder;
+pub use audio::AudioDemodulator;
+pub use average::{AverageSpectrum, SpectrumAverager};
+pub use burst::BurstDetector;
+pub use carrier::CarrierMeter;
+pub use classify::ChannelStats;
+pub use dark_frame::{DarkFrame, DarkFrameCapture};
+pub use decimate::Decimate;
+pub use impulse::ImpulseDetector;
+pub use meteor::PingDetector;
+pub use nco::Nco;
+pub use resample::Resample;
+#[cfg(feature = "selcall")]
+pub use selcall::SelCallDecoder;
+#[cfg(feature = "sstv")]
+pub use sstv::SstvDecoder;
+pub use stereo::Audio;
+pub use stretch::TimeStretch;
+pub use survey::{SpectrumSurvey, find_signals, noise_floor};
+pub use symbol_rate::SymbolRateEstimator;
+pub use window::HannWindow;
@@ -0,0 +1,28 @@
+use std::f64::consts::TAU;
+
+use rustradio::Complex;
+
+/// Numerically controlled oscillator that mixes a frequency down to DC.
+pub struct Nco {
+    /// Frequency in cycles per sample
+    step: f64,
+    /// Phase in cycles, kept in [0, 1)
+    phase: f64,
+}
+
+impl Nco {
+    /// Create a mixer that moves `frequency` Hz to DC.
+    pub fn new(sample_rate: f64, frequency: f64) -> Self {
+        Self {
+            step: frequency / sample_rate,
+            phase: 0.0,
+        }
+    }
+
+    /// Mix one sample, advancing the oscillator.
+    pub fn mix(&mut self, sample: Complex) -> Complex {
+        let (sin, cos) = (-TAU * self.phase).sin_cos();
+        self.phase = (self.phase + self.step).rem_euclid(1.0);
+        sample * Complex::new(cos as f32, sin as f32)
+    }
+}
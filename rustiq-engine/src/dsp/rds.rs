@@ -0,0 +1,507 @@
+//! Radio Data System: the station's name, radiotext and identity, sent at
+//! 1187.5 bit/s on a 57 kHz subcarrier of the broadcast FM multiplex.
+
+use rustradio::Complex;
+
+use rustiq_messages::RdsData;
+
+use super::fir::{FirDecimator, lowpass_taps, shift_taps};
+use super::nco::Nco;
+
+/// Frequency of the RDS subcarrier, three times the pilot's.
+const SUBCARRIER: f64 = 57_000.0;
+
+/// Bit rate, the subcarrier's over 48.
+const BIT_RATE: f64 = SUBCARRIER / 48.0;
+
+/// Lowest multiplex rate that holds the RDS band, up to 59.4 kHz.
+pub const MIN_RATE: f64 = 120_000.0;
+
+/// Half the width of the RDS band around the subcarrier.
+const BANDWIDTH: f64 = 2_400.0;
+
+/// Width of the RDS filter's transition band in Hz.
+const TRANSITION: f64 = 1_500.0;
+
+/// Positions in a bit the clock is recovered among.
+const PHASES: usize = 16;
+
+/// Weight of each bit's energy in the clock recovery.
+const CLOCK_ALPHA: f32 = 1.0 / 64.0;
+
+/// Bits in a block: 16 of data and a 10-bit checkword.
+const BLOCK_BITS: u32 = 26;
+
+/// Generator polynomial of the checkword, x^10 + x^8 + x^7 + x^5 + x^4 + x^3 + 1.
+const POLYNOMIAL: u32 = 0x5B9;
+
+/// Blocks in a row that fail their check before sync is taken for lost.
+const MAX_BAD_BLOCKS: u32 = 20;
+
+/// The four blocks of a group, C' standing in for C in version B groups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    A,
+    B,
+    C,
+    CPrime,
+    D,
+}
+
+impl Block {
+    const ALL: [Block; 5] = [Self::A, Self::B, Self::C, Self::CPrime, Self::D];
+
+    /// Offset word added to the block's checkword.
+    fn offset(self) -> u32 {
+        match self {
+            Self::A => 0x0FC,
+            Self::B => 0x198,
+            Self::C => 0x168,
+            Self::CPrime => 0x350,
+            Self::D => 0x1B4,
+        }
+    }
+
+    /// Index of the block in its group.
+    fn index(self) -> usize {
+        match self {
+            Self::A => 0,
+            Self::B => 1,
+            Self::C | Self::CPrime => 2,
+            Self::D => 3,
+        }
+    }
+
+    /// The block a 26-bit word is, by its offset, if it checks out.
+    fn of(word: u32) -> Option<Block> {
+        let offset = (word & 0x3FF) ^ checkword((word >> 10) as u16);
+        Self::ALL.into_iter().find(|block| block.offset() == offset)
+    }
+}
+
+/// Checkword of 16 data bits, before the offset: the remainder of their
+/// division by the generator.
+fn checkword(data: u16) -> u32 {
+    let mut remainder = u32::from(data) << 10;
+    for bit in (10..26).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= POLYNOMIAL << (bit - 10);
+        }
+    }
+    remainder
+}
+
+/// Decodes RDS from the multiplex signal of broadcast FM, at a rate of at
+/// least `MIN_RATE`.
+///
+/// The subcarrier is mixed to DC and filtered, and each bit's biphase
+/// symbol matched over the last bit's worth of samples. The bit clock is
+/// the position in the bit where that matches strongest on average, and
+/// the bits are told apart differentially, so the subcarrier's phase never
+/// needs locking. Groups are found by their blocks' checkwords.
+pub struct RdsDecoder {
+    mixer: Nco,
+    filter: FirDecimator,
+    /// Samples per bit, after the filter
+    samples_per_bit: f64,
+    /// Last bit's worth of filtered samples, oldest first
+    window: Vec<Complex>,
+    /// Position in the current bit, in bits
+    clock: f64,
+    /// Average energy of the matched symbol at each position in a bit
+    energy: [f32; PHASES],
+    /// Position in a bit the symbols are taken at
+    strobe: usize,
+    /// Phase the clock was at for the previous sample
+    last_phase: usize,
+    /// Symbol taken for the previous bit
+    previous: Complex,
+    /// Last 26 bits
+    register: u32,
+    /// Bits received, for the spacing of blocks before sync
+    bits: u64,
+    /// Bit each kind of block was last seen ending at, before sync, and
+    /// its data
+    seen: [Option<(u64, u16)>; 5],
+    /// Once in sync: the block expected next, and bits left until it ends
+    sync: Option<(usize, u32)>,
+    bad_blocks: u32,
+    /// Blocks of the group being received, those that checked out
+    group: [Option<u16>; 4],
+    text: Text,
+    /// What was last returned
+    sent: Option<RdsData>,
+}
+
+impl RdsDecoder {
+    /// A decoder for a multiplex signal at `sample_rate`.
+    pub fn new(sample_rate: f64) -> Self {
+        let decimation = ((sample_rate / (PHASES as f64 * BIT_RATE)) as usize).max(1);
+        let len = (4.0 * sample_rate / TRANSITION).ceil() as usize;
+        let taps = shift_taps(&lowpass_taps(BANDWIDTH / sample_rate, len), 0.0);
+        let samples_per_bit = sample_rate / decimation as f64 / BIT_RATE;
+        Self {
+            mixer: Nco::new(sample_rate, SUBCARRIER),
+            filter: FirDecimator::new(taps, decimation),
+            samples_per_bit,
+            window: vec![Complex::new(0.0, 0.0); samples_per_bit.round() as usize],
+            clock: 0.0,
+            energy: [0.0; PHASES],
+            strobe: 0,
+            last_phase: 0,
+            previous: Complex::new(0.0, 0.0),
+            register: 0,
+            bits: 0,
+            seen: [None; 5],
+            sync: None,
+            bad_blocks: 0,
+            group: [None; 4],
+            text: Text::default(),
+            sent: None,
+        }
+    }
+
+    /// Decode multiplex samples, returning what the station sends if it
+    /// changed with them.
+    pub fn process(&mut self, mpx: &[f32]) -> Option<RdsData> {
+        let mixed: Vec<Complex> = mpx
+            .iter()
+            .map(|&sample| self.mixer.mix(Complex::new(sample, 0.0)))
+            .collect();
+        for sample in self.filter.process(&mixed) {
+            self.window.rotate_left(1);
+            *self.window.last_mut().expect("a bit is a few samples") = sample;
+            self.clock = (self.clock + 1.0 / self.samples_per_bit).fract();
+            let phase = (self.clock * PHASES as f64) as usize % PHASES;
+
+            // Biphase: the first half of the bit against the second
+            let half = self.window.len() / 2;
+            let first: Complex = self.window[..half].iter().sum();
+            let second: Complex = self.window[half..].iter().sum();
+            let symbol = first - second;
+            self.energy[phase] += CLOCK_ALPHA * (symbol.norm_sqr() - self.energy[phase]);
+
+            if phase == self.strobe && phase != self.last_phase {
+                // Differentially coded: a 1 turns the symbol over
+                let bit = (symbol * self.previous.conj()).re < 0.0;
+                self.previous = symbol;
+                self.receive(bit);
+                self.strobe = (0..PHASES)
+                    .max_by(|&a, &b| self.energy[a].total_cmp(&self.energy[b]))
+                    .unwrap_or(0);
+            }
+            self.last_phase = phase;
+        }
+
+        let data = self.text.data();
+        if data.is_some() && data != self.sent {
+            self.sent = data.clone();
+            return data;
+        }
+        None
+    }
+
+    /// Take in one bit, looking for blocks.
+    fn receive(&mut self, bit: bool) {
+        self.register = ((self.register << 1) | u32::from(bit)) & ((1 << BLOCK_BITS) - 1);
+        self.bits += 1;
+        let Some((expected, left)) = self.sync else {
+            // Sync is two blocks in order, one right after the other
+            if let Some(block) = Block::of(self.register) {
+                let data = (self.register >> 10) as u16;
+                let before = (block.index() + 3) % 4;
+                let start = self.bits.checked_sub(u64::from(BLOCK_BITS));
+                let previous = Block::ALL
+                    .into_iter()
+                    .filter(|b| b.index() == before)
+                    .find_map(|b| self.seen[b as usize].filter(|&(at, _)| Some(at) == start));
+                self.seen[block as usize] = Some((self.bits, data));
+                if let Some((_, previous)) = previous {
+                    self.sync = Some(((block.index() + 1) % 4, BLOCK_BITS));
+                    self.bad_blocks = 0;
+                    self.group = [None; 4];
+                    // Unless it ended a group, the one before is of this one
+                    if block.index() > 0 {
+                        self.group[before] = Some(previous);
+                    }
+                    self.group[block.index()] = Some(data);
+                }
+            }
+            return;
+        };
+        if left > 1 {
+            self.sync = Some((expected, left - 1));
+            return;
+        }
+
+        let data = (self.register >> 10) as u16;
+        match Block::of(self.register) {
+            Some(block) if block.index() == expected => {
+                self.bad_blocks = 0;
+                self.group[expected] = Some(data);
+            }
+            _ => {
+                self.bad_blocks += 1;
+                self.group[expected] = None;
+            }
+        }
+        if expected == 3 {
+            let group = std::mem::take(&mut self.group);
+            self.text.decode(group);
+        }
+        if self.bad_blocks >= MAX_BAD_BLOCKS {
+            self.sync = None;
+            self.seen = [None; 5];
+        } else {
+            self.sync = Some(((expected + 1) % 4, BLOCK_BITS));
+        }
+    }
+}
+
+/// The station's identity and texts, as put together from its groups.
+struct Text {
+    pi: Option<u16>,
+    /// Programme service name as received so far, and which of its four
+    /// segments are in
+    station: [u8; 8],
+    station_segments: u8,
+    /// Last programme service name received whole
+    station_whole: Option<String>,
+    /// Radiotext as received so far, which of its sixteen segments are in,
+    /// and the A/B flag that clears it
+    radiotext: [u8; 64],
+    radiotext_segments: u16,
+    radiotext_flag: Option<bool>,
+    /// Last radiotext received whole
+    radiotext_whole: Option<String>,
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Self {
+            pi: None,
+            station: [b' '; 8],
+            station_segments: 0,
+            station_whole: None,
+            radiotext: [b' '; 64],
+            radiotext_segments: 0,
+            radiotext_flag: None,
+            radiotext_whole: None,
+        }
+    }
+}
+
+impl Text {
+    fn data(&self) -> Option<RdsData> {
+        Some(RdsData {
+            pi: self.pi?,
+            station: self.station_whole.clone(),
+            radiotext: self.radiotext_whole.clone(),
+        })
+    }
+
+    /// Take in a group's blocks, those that checked out.
+    fn decode(&mut self, [a, b, c, d]: [Option<u16>; 4]) {
+        if let Some(pi) = a {
+            if self.pi.is_some_and(|known| known != pi) {
+                // Another station
+                *self = Self::default();
+            }
+            self.pi = Some(pi);
+        }
+        let Some(b) = b else {
+            return;
+        };
+        let group_type = b >> 12;
+        let version_b = b & 0x0800 != 0;
+        match group_type {
+            0 => {
+                let Some(d) = d else {
+                    return;
+                };
+                let segment = usize::from(b & 0x3);
+                self.station[segment * 2..segment * 2 + 2].copy_from_slice(&d.to_be_bytes());
+                self.station_segments |= 1 << segment;
+                if self.station_segments == 0xF {
+                    self.station_whole = Some(printable(&self.station).trim_end().to_string());
+                    self.station_segments = 0;
+                }
+            }
+            2 => {
+                let flag = b & 0x10 != 0;
+                if self.radiotext_flag != Some(flag) {
+                    // A new text
+                    self.radiotext_flag = Some(flag);
+                    self.radiotext = [b' '; 64];
+                    self.radiotext_segments = 0;
+                }
+                let segment = usize::from(b & 0xF);
+                let (chars, at) = if version_b {
+                    let Some(d) = d else {
+                        return;
+                    };
+                    (d.to_be_bytes().to_vec(), segment * 2)
+                } else {
+                    let (Some(c), Some(d)) = (c, d) else {
+                        return;
+                    };
+                    let mut chars = c.to_be_bytes().to_vec();
+                    chars.extend(d.to_be_bytes());
+                    (chars, segment * 4)
+                };
+                let len = if version_b { 32 } else { 64 };
+                if at + chars.len() > len {
+                    return;
+                }
+                self.radiotext[at..at + chars.len()].copy_from_slice(&chars);
+                self.radiotext_segments |= 1 << segment;
+
+                // Whole once every segment up to its end is in
+                let text = &self.radiotext[..len];
+                let end = text.iter().position(|&c| c == b'\r').unwrap_or(len);
+                let per_segment = if version_b { 2 } else { 4 };
+                let needed = end.saturating_sub(1) / per_segment + 1;
+                let all = ((1u32 << needed) - 1) as u16;
+                if self.radiotext_segments & all == all {
+                    self.radiotext_whole = Some(printable(&text[..end]).trim_end().to_string());
+                    self.radiotext_segments = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Characters as sent, those outside printable ASCII shown as '?'.
+fn printable(chars: &[u8]) -> String {
+    chars
+        .iter()
+        .map(|&c| {
+            if (0x20..0x7F).contains(&c) {
+                c as char
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const RATE: f64 = 240_000.0;
+
+    /// Bits of a block of `data`, with its checkword and offset.
+    fn block(data: u16, block: Block) -> Vec<bool> {
+        let word = (u32::from(data) << 10) | (checkword(data) ^ block.offset());
+        (0..BLOCK_BITS)
+            .rev()
+            .map(|bit| word & (1 << bit) != 0)
+            .collect()
+    }
+
+    fn group(pi: u16, b: u16, c: u16, d: u16) -> Vec<bool> {
+        let mut bits = block(pi, Block::A);
+        bits.extend(block(b, Block::B));
+        bits.extend(block(c, Block::C));
+        bits.extend(block(d, Block::D));
+        bits
+    }
+
+    /// Groups 0A sending `station` and 2A sending `radiotext`.
+    fn groups(pi: u16, station: &str, radiotext: &str) -> Vec<bool> {
+        let mut bits = Vec::new();
+        for (segment, pair) in station.as_bytes().chunks(2).enumerate() {
+            let d = u16::from_be_bytes([pair[0], pair[1]]);
+            bits.extend(group(pi, segment as u16, 0, d));
+        }
+        let mut text = radiotext.as_bytes().to_vec();
+        text.push(b'\r');
+        text.resize(text.len().div_ceil(4) * 4, b' ');
+        for (segment, quad) in text.chunks(4).enumerate() {
+            let b = (2 << 12) | segment as u16;
+            let c = u16::from_be_bytes([quad[0], quad[1]]);
+            let d = u16::from_be_bytes([quad[2], quad[3]]);
+            bits.extend(group(pi, b, c, d));
+        }
+        bits
+    }
+
+    /// Multiplex signal carrying `bits` on the subcarrier, with a pilot and
+    /// a tone.
+    fn multiplex(bits: &[bool]) -> Vec<f32> {
+        // Differentially coded, then each bit a biphase symbol
+        let mut level = false;
+        let coded: Vec<bool> = bits
+            .iter()
+            .map(|&bit| {
+                level ^= bit;
+                level
+            })
+            .collect();
+        let len = (bits.len() as f64 / BIT_RATE * RATE) as usize;
+        (0..len)
+            .map(|i| {
+                let t = i as f64 / RATE;
+                let position = t * BIT_RATE;
+                let bit = coded[position as usize];
+                let first_half = position.fract() < 0.5;
+                let symbol = if bit == first_half { 1.0 } else { -1.0 };
+                let pilot = TAU * 19_000.0 * t;
+                (0.4 * (TAU * 1_000.0 * t).sin()
+                    + 0.09 * pilot.sin()
+                    + 0.04 * symbol * (3.0 * pilot + 0.7).cos()) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checks_blocks_by_their_offsets() {
+        let bits = block(0x1234, Block::CPrime);
+        let word = bits
+            .iter()
+            .fold(0, |word, &bit| (word << 1) | u32::from(bit));
+        assert_eq!(Block::of(word), Some(Block::CPrime));
+        assert_eq!(Block::of(word ^ 0x40), None);
+    }
+
+    #[test]
+    fn decodes_station_name_and_radiotext() {
+        let mut bits = Vec::new();
+        for _ in 0..3 {
+            bits.extend(groups(0xC201, "RUSTIQ  ", "Now playing: tests"));
+        }
+        let mpx = multiplex(&bits);
+        let mut decoder = RdsDecoder::new(RATE);
+        let mut last = None;
+        for chunk in mpx.chunks(4_800) {
+            if let Some(data) = decoder.process(chunk) {
+                last = Some(data);
+            }
+        }
+        assert_eq!(
+            last,
+            Some(RdsData {
+                pi: 0xC201,
+                station: Some("RUSTIQ".to_string()),
+                radiotext: Some("Now playing: tests".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn finds_nothing_in_a_multiplex_without_rds() {
+        let mpx: Vec<f32> = (0..240_000)
+            .map(|i| (0.4 * (TAU * 1_000.0 * i as f64 / RATE).sin()) as f32)
+            .collect();
+        let mut decoder = RdsDecoder::new(RATE);
+        assert_eq!(decoder.process(&mpx), None);
+    }
+
+    #[test]
+    fn shows_unprintable_characters_as_question_marks() {
+        assert_eq!(printable(b"A\x01B\xC3"), "A?B?");
+    }
+}
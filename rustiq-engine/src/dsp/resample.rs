@@ -0,0 +1,156 @@
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+use super::fir::{FirDecimator, lowpass_taps, shift_taps};
+
+/// Taps of the anti-alias filter per whole step of input between outputs.
+const TAPS_PER_STEP: usize = 40;
+
+/// Passband edge as a fraction of the output rate.
+const PASSBAND: f64 = 0.45;
+
+/// Most inputs filtered per call.
+const CHUNK: usize = 8192;
+
+/// Lowers the rate of the stream by any factor, low-pass filtering it and
+/// interpolating between the filtered samples. For a source whose hardware
+/// can't run at the rate asked for, only at one above it.
+#[derive(rustradio_macros::Block)]
+pub struct Resample {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+    filter: FirDecimator,
+    /// Input samples per output sample
+    step: f64,
+    /// Position of the next output, in filtered samples after `previous`
+    position: f64,
+    /// Last filtered sample of the previous call
+    previous: Complex,
+}
+
+impl Resample {
+    pub fn new(
+        src: ReadStream<Complex>,
+        input_rate: f64,
+        output_rate: f64,
+    ) -> (Self, ReadStream<Complex>) {
+        let step = (input_rate / output_rate).max(1.0);
+        let taps = lowpass_taps(PASSBAND / step, TAPS_PER_STEP * step.ceil() as usize + 1);
+        let (dst, dr) = rustradio::stream::new_stream();
+        (
+            Self {
+                src,
+                dst,
+                filter: FirDecimator::new(shift_taps(&taps, 0.0), 1),
+                step,
+                position: 0.0,
+                previous: Complex::new(0.0, 0.0),
+            },
+            dr,
+        )
+    }
+
+    /// Interpolate the outputs that fall within `filtered`.
+    fn interpolate(&mut self, filtered: &[Complex]) -> Vec<Complex> {
+        let Some(&last) = filtered.last() else {
+            return Vec::new();
+        };
+        let mut output = Vec::with_capacity((filtered.len() as f64 / self.step) as usize + 1);
+        while self.position < filtered.len() as f64 {
+            let index = self.position as usize;
+            let fraction = (self.position - index as f64) as f32;
+            let before = if index == 0 {
+                self.previous
+            } else {
+                filtered[index - 1]
+            };
+            output.push(before + (filtered[index] - before) * fraction);
+            self.position += self.step;
+        }
+        self.position -= filtered.len() as f64;
+        self.previous = last;
+        output
+    }
+}
+
+impl Block for Resample {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+        let mut o = self.dst.write_buf()?;
+        // Room for every output the input could complete
+        let room = (o.len().saturating_sub(1) as f64 * self.step) as usize;
+        let n = input.len().min(room).min(CHUNK);
+        if n == 0 {
+            return Ok(BlockRet::WaitForStream(&self.dst, 2));
+        }
+        let filtered = self.filter.process(&input.slice()[..n]);
+        let output = self.interpolate(&filtered);
+        o.slice()[..output.len()].copy_from_slice(&output);
+        o.produce(output.len(), &[]);
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    fn tone(freq: f64, rate: f64, len: usize) -> Vec<Complex> {
+        (0..len)
+            .map(|i| {
+                let (sin, cos) = (TAU * freq * i as f64 / rate).sin_cos();
+                Complex::new(cos as f32, sin as f32)
+            })
+            .collect()
+    }
+
+    fn resample(input: &[Complex], input_rate: f64, output_rate: f64) -> Vec<Complex> {
+        let (_, src) = rustradio::stream::new_stream();
+        let (mut block, _) = Resample::new(src, input_rate, output_rate);
+        let filtered = block.filter.process(input);
+        block.interpolate(&filtered)
+    }
+
+    /// Frequency of a tone, from the phase steps between its samples.
+    fn frequency(samples: &[Complex], rate: f64) -> f64 {
+        let steps: f64 = samples
+            .windows(2)
+            .map(|pair| (pair[1] * pair[0].conj()).arg() as f64)
+            .sum();
+        steps / (samples.len() - 1) as f64 * rate / TAU
+    }
+
+    #[test]
+    fn keeps_a_tone_in_the_band_at_the_new_rate() {
+        let input = tone(100_000.0, 2_400_000.0, 240_000);
+        let output = resample(&input, 2_400_000.0, 2_000_000.0);
+        assert!(
+            (output.len() as i64 - 200_000).abs() <= 1,
+            "{}",
+            output.len()
+        );
+        // Past the filter's start
+        let settled = &output[1_000..];
+        assert!((frequency(settled, 2_000_000.0) - 100_000.0).abs() < 1.0);
+        let level = settled.iter().map(|s| s.norm()).sum::<f32>() / settled.len() as f32;
+        assert!((level - 1.0).abs() < 0.01, "{level}");
+    }
+
+    #[test]
+    fn filters_out_what_would_alias() {
+        // Above the new Nyquist, so it would fold back to -800 kHz
+        let input = tone(1_200_000.0 - 1.0, 3_200_000.0, 32_000);
+        let output = resample(&input, 3_200_000.0, 2_000_000.0);
+        let settled = &output[1_000..];
+        let level = settled.iter().map(|s| s.norm()).sum::<f32>() / settled.len() as f32;
+        assert!(level < 0.01, "{level}");
+    }
+}
@@ -0,0 +1,225 @@
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+use rustiq_messages::{SelCall, SelCallStandard};
+
+/// Detection blocks per nominal tone. Three leaves at least two blocks
+/// fully inside each tone, whatever the alignment.
+const BLOCKS_PER_TONE: f64 = 3.0;
+
+/// Consecutive blocks a tone must win to count as a digit.
+const MIN_BLOCKS: usize = 2;
+
+/// Share of the block's energy the strongest tone must hold.
+const MIN_PURITY: f32 = 0.5;
+
+/// How much stronger (power ratio) the strongest tone must be than the runner-up.
+const MIN_DOMINANCE: f32 = 4.0;
+
+/// Shortest sequence reported as a call.
+const MIN_DIGITS: usize = 5;
+
+/// Index of the repeat tone in `SelCallStandard::tones()`.
+const REPEAT: usize = 10;
+
+/// Decodes 5-tone selective calls from demodulated audio.
+///
+/// Each block of audio is classified as one of the standard's tones (or none)
+/// with a bank of Goertzel filters. A tone that wins enough consecutive
+/// blocks becomes a digit, and a tone-length gap ends the sequence.
+pub struct SelCallDecoder {
+    rate: f64,
+    standard: SelCallStandard,
+    /// Goertzel coefficients (2cos(ω)) for each tone
+    coefficients: [f32; 11],
+    block_len: usize,
+    block: Vec<f32>,
+    /// Sample position of the start of `block`
+    position: u64,
+    /// Tone winning the recent blocks, and for how many
+    candidate: Option<(usize, usize)>,
+    /// Tones accepted in the current sequence
+    symbols: Vec<usize>,
+    /// Sample position where the current sequence started
+    sequence_start: u64,
+    /// Blocks since the last tone
+    silence: usize,
+}
+
+impl SelCallDecoder {
+    pub fn new(audio_rate: f64, standard: SelCallStandard) -> Self {
+        let tone = standard.tone_duration().as_secs_f64();
+        let block_len = ((tone / BLOCKS_PER_TONE) * audio_rate).round() as usize;
+        Self {
+            rate: audio_rate,
+            standard,
+            coefficients: standard
+                .tones()
+                .map(|f| (2.0 * (TAU * f as f64 / audio_rate).cos()) as f32),
+            block_len: block_len.max(1),
+            block: Vec::with_capacity(block_len),
+            position: 0,
+            candidate: None,
+            symbols: Vec::new(),
+            sequence_start: 0,
+            silence: 0,
+        }
+    }
+
+    /// Feed audio samples, returning every call completed within them.
+    pub fn process(&mut self, audio: &[f32]) -> Vec<SelCall> {
+        let mut calls = Vec::new();
+        for &sample in audio {
+            self.block.push(sample);
+            if self.block.len() < self.block_len {
+                continue;
+            }
+            let tone = self.classify();
+            calls.extend(self.advance(tone));
+            self.position += self.block_len as u64;
+            self.block.clear();
+        }
+        calls
+    }
+
+    /// Find which tone, if any, clearly dominates the current block.
+    fn classify(&self) -> Option<usize> {
+        let energy: f32 = self.block.iter().map(|s| s * s).sum();
+        if energy <= f32::MIN_POSITIVE {
+            return None;
+        }
+        let mut powers: Vec<(usize, f32)> = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| (i, goertzel_power(&self.block, c)))
+            .collect();
+        powers.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let (best, power) = powers[0];
+        let runner_up = powers[1].1;
+
+        // A full-scale tone at a bin frequency has power (N/2)^2 * A^2 = energy * N / 2
+        let purity = power / (energy * self.block_len as f32 / 2.0);
+        (purity >= MIN_PURITY && power >= MIN_DOMINANCE * runner_up).then_some(best)
+    }
+
+    fn advance(&mut self, tone: Option<usize>) -> Option<SelCall> {
+        let Some(tone) = tone else {
+            self.candidate = None;
+            self.silence += 1;
+            if self.silence as f64 >= BLOCKS_PER_TONE {
+                return self.finish();
+            }
+            return None;
+        };
+        self.silence = 0;
+
+        let count = match self.candidate {
+            Some((candidate, count)) if candidate == tone => count + 1,
+            _ => 1,
+        };
+        self.candidate = Some((tone, count));
+        // The standard never sends the same tone twice in a row (it uses the
+        // repeat tone instead), so a tone interrupted by a bad block is one digit
+        if count == MIN_BLOCKS && self.symbols.last() != Some(&tone) {
+            if self.symbols.is_empty() {
+                self.sequence_start = self.position - ((MIN_BLOCKS - 1) * self.block_len) as u64;
+            }
+            self.symbols.push(tone);
+        }
+        None
+    }
+
+    /// End the current sequence, returning it if it decodes to a call.
+    fn finish(&mut self) -> Option<SelCall> {
+        let symbols = std::mem::take(&mut self.symbols);
+        let mut id = String::new();
+        let mut previous = None;
+        for symbol in symbols {
+            let digit = if symbol == REPEAT { previous? } else { symbol };
+            id.push(char::from_digit(digit as u32, 10).unwrap());
+            previous = Some(digit);
+        }
+        (id.len() >= MIN_DIGITS).then(|| SelCall {
+            start: Duration::from_secs_f64(self.sequence_start as f64 / self.rate),
+            standard: self.standard,
+            id,
+        })
+    }
+}
+
+/// Power of one frequency in a block of samples, via the Goertzel algorithm.
+/// `coefficient` is 2cos(ω) for the frequency ω in radians per sample.
+fn goertzel_power(block: &[f32], coefficient: f32) -> f32 {
+    let (mut s1, mut s2) = (0.0f32, 0.0f32);
+    for &x in block {
+        let s0 = x + coefficient * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    s1 * s1 + s2 * s2 - coefficient * s1 * s2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 24_000.0;
+
+    /// Audio for a call: silence, the tones for `digits` (with repeats), silence.
+    fn call_audio(standard: SelCallStandard, digits: &str) -> Vec<f32> {
+        let tones = standard.tones();
+        let tone_len = (standard.tone_duration().as_secs_f64() * RATE) as usize;
+        let mut audio = vec![0.0; 3_000];
+        let mut previous = None;
+        for digit in digits.chars().map(|c| c.to_digit(10).unwrap() as usize) {
+            let symbol = if previous == Some(digit) {
+                REPEAT
+            } else {
+                digit
+            };
+            previous = Some(digit);
+            let freq = tones[symbol] as f64;
+            let start = audio.len();
+            audio.extend(
+                (0..tone_len)
+                    .map(|i| (0.5 * (TAU * freq * (start + i) as f64 / RATE).sin()) as f32),
+            );
+        }
+        audio.extend(vec![0.0; 6_000]);
+        audio
+    }
+
+    #[test]
+    fn decodes_every_standard_with_repeats() {
+        for standard in SelCallStandard::ALL {
+            let mut decoder = SelCallDecoder::new(RATE, standard);
+            let calls = decoder.process(&call_audio(standard, "12234"));
+            assert_eq!(calls.len(), 1, "{}", standard.label());
+            assert_eq!(calls[0].id, "12234", "{}", standard.label());
+            assert_eq!(calls[0].standard, standard);
+            let start = calls[0].start.as_secs_f64();
+            assert!((start - 3_000.0 / RATE).abs() < 0.02, "start {start}");
+        }
+    }
+
+    #[test]
+    fn ignores_short_sequences_and_noise() {
+        let mut decoder = SelCallDecoder::new(RATE, SelCallStandard::Ccir);
+        assert!(
+            decoder
+                .process(&call_audio(SelCallStandard::Ccir, "123"))
+                .is_empty()
+        );
+
+        // Deterministic pseudo-random noise
+        let mut state = 1u32;
+        let noise: Vec<f32> = (0..RATE as usize)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect();
+        assert!(decoder.process(&noise).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Muting a channel's audio while nothing is being sent on it, judged by
+//! the channel's power ahead of demodulation.
+
+use rustradio::Complex;
+
+/// Time constant the channel power is smoothed over, in seconds: short
+/// enough not to clip the first syllable, long enough to ride over the
+/// dips of a fading signal.
+const SMOOTHING: f64 = 0.01;
+
+/// How far below the threshold the power must fall for an open squelch to
+/// close, so a signal right at it doesn't chatter.
+const HYSTERESIS: f32 = 1.995; // 3 dB
+
+/// Opens while the smoothed power of a channel is above a threshold.
+pub struct PowerSquelch {
+    /// Weight of each new sample in the smoothed power
+    alpha: f32,
+    /// Smoothed power of the channel
+    power: f32,
+    open: bool,
+}
+
+impl PowerSquelch {
+    /// A squelch for a channel at `sample_rate`, closed until it hears
+    /// something.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            alpha: (1.0 - (-1.0 / (SMOOTHING * sample_rate)).exp()) as f32,
+            power: 0.0,
+            open: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Whether the squelch is open at each of `channel`'s samples, against
+    /// a `threshold` power; always open without one.
+    pub fn process(&mut self, channel: &[Complex], threshold: Option<f32>) -> Vec<bool> {
+        channel
+            .iter()
+            .map(|sample| {
+                self.power += self.alpha * (sample.norm_sqr() - self.power);
+                self.open = match threshold {
+                    None => true,
+                    Some(threshold) if self.open => self.power * HYSTERESIS >= threshold,
+                    Some(threshold) => self.power >= threshold,
+                };
+                self.open
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 24_000.0;
+
+    fn carrier(amplitude: f32, len: usize) -> Vec<Complex> {
+        vec![Complex::new(amplitude, 0.0); len]
+    }
+
+    #[test]
+    fn opens_on_a_signal_and_closes_after_it() {
+        let mut squelch = PowerSquelch::new(RATE);
+        // -40 dB of power
+        let threshold = Some(1e-4);
+        let quiet = squelch.process(&carrier(1e-3, 2_400), threshold);
+        assert!(quiet.iter().all(|&open| !open));
+
+        let signal = squelch.process(&carrier(0.1, 2_400), threshold);
+        // Opens within a few milliseconds
+        let opened = signal.iter().position(|&open| open).unwrap();
+        assert!(opened < 100, "opened after {opened} samples");
+        assert!(squelch.is_open());
+
+        squelch.process(&carrier(1e-3, 2_400), threshold);
+        assert!(!squelch.is_open());
+    }
+
+    #[test]
+    fn holds_open_just_below_the_threshold() {
+        let mut squelch = PowerSquelch::new(RATE);
+        let threshold = Some(1e-4);
+        squelch.process(&carrier(0.1, 2_400), threshold);
+        // 1 dB below
+        let gate = squelch.process(&carrier(0.0089, 2_400), threshold);
+        assert!(gate.iter().all(|&open| open));
+        // Without a threshold it never closes
+        let gate = squelch.process(&carrier(0.0, 2_400), None);
+        assert!(gate.iter().all(|&open| open));
+    }
+}
@@ -0,0 +1,585 @@
+use std::f64::consts::TAU;
+
+use rustradio::Complex;
+
+use super::fir::{FirDecimator, lowpass_taps, shift_taps};
+use super::nco::Nco;
+use rustiq_messages::{SstvEvent, SstvMode};
+
+/// Tone frequencies in Hz.
+const SYNC: f32 = 1_200.0;
+const LEADER: f32 = 1_900.0;
+const BLACK: f32 = 1_500.0;
+const WHITE: f32 = 2_300.0;
+const VIS_ONE: f32 = 1_100.0;
+const VIS_ZERO: f32 = 1_300.0;
+
+/// How far a VIS tone may be from nominal and still count.
+const TONE_TOLERANCE: f32 = 60.0;
+
+/// Duration of one VIS bit in seconds.
+const VIS_BIT: f64 = 0.030;
+
+/// Length of leader tone required before the VIS start bit, in seconds.
+const LEADER_CHECK: f64 = 0.100;
+
+/// Width of each side of the step detector that finds the leader-to-start-bit edge, in seconds.
+const EDGE_WINDOW: f64 = 0.002;
+
+/// Where one scan sits within a line, in milliseconds from the line start.
+#[derive(Clone, Copy)]
+struct Scan {
+    start: f64,
+    duration: f64,
+}
+
+#[derive(Clone, Copy)]
+enum Layout {
+    /// Separate green, blue and red scans on every line
+    Rgb { green: Scan, blue: Scan, red: Scan },
+    /// Luma on every line, with R-Y on even lines and B-Y on odd lines
+    Robot36 { luma: Scan, chroma: Scan },
+}
+
+struct ModeSpec {
+    mode: SstvMode,
+    vis: u8,
+    /// Time from the end of the VIS code to the first line, in milliseconds
+    first_line: f64,
+    /// Line period in milliseconds
+    line: f64,
+    layout: Layout,
+}
+
+const fn martin(mode: SstvMode, vis: u8, scan: f64) -> ModeSpec {
+    // sync 4.862, then porch/separator of 0.572 around each scan
+    let green = 4.862 + 0.572;
+    let blue = green + scan + 0.572;
+    let red = blue + scan + 0.572;
+    ModeSpec {
+        mode,
+        vis,
+        first_line: 0.0,
+        line: red + scan + 0.572,
+        layout: Layout::Rgb {
+            green: Scan {
+                start: green,
+                duration: scan,
+            },
+            blue: Scan {
+                start: blue,
+                duration: scan,
+            },
+            red: Scan {
+                start: red,
+                duration: scan,
+            },
+        },
+    }
+}
+
+const fn scottie(mode: SstvMode, vis: u8, scan: f64) -> ModeSpec {
+    // Separators of 1.5 before green and blue; the 9 ms sync and a 1.5 ms porch
+    // sit mid-line before red. A lone sync precedes the first line.
+    let green = 1.5;
+    let blue = green + scan + 1.5;
+    let red = blue + scan + 9.0 + 1.5;
+    ModeSpec {
+        mode,
+        vis,
+        first_line: 9.0,
+        line: red + scan,
+        layout: Layout::Rgb {
+            green: Scan {
+                start: green,
+                duration: scan,
+            },
+            blue: Scan {
+                start: blue,
+                duration: scan,
+            },
+            red: Scan {
+                start: red,
+                duration: scan,
+            },
+        },
+    }
+}
+
+const MODES: [ModeSpec; 6] = [
+    martin(SstvMode::Martin1, 44, 146.432),
+    martin(SstvMode::Martin2, 40, 73.216),
+    scottie(SstvMode::Scottie1, 60, 138.240),
+    scottie(SstvMode::Scottie2, 56, 88.064),
+    scottie(SstvMode::ScottieDx, 76, 345.600),
+    ModeSpec {
+        mode: SstvMode::Robot36,
+        vis: 8,
+        first_line: 0.0,
+        // sync 9, porch 3, luma 88, separator 4.5, porch 1.5, chroma 44
+        line: 150.0,
+        layout: Layout::Robot36 {
+            luma: Scan {
+                start: 12.0,
+                duration: 88.0,
+            },
+            chroma: Scan {
+                start: 106.0,
+                duration: 44.0,
+            },
+        },
+    },
+];
+
+/// Tracks the instantaneous frequency of the audio around the SSTV tone range.
+///
+/// The audio is mixed so the leader tone sits at DC, low-pass filtered to drop
+/// the mirror image, and the phase step between samples gives the frequency.
+struct ToneTracker {
+    mixer: Nco,
+    filter: FirDecimator,
+    previous: Complex,
+    rate: f64,
+}
+
+impl ToneTracker {
+    fn new(rate: f64) -> Self {
+        // Passes 1100-2300 Hz (-800..+400 after mixing); the image sits below -2900 Hz
+        let len = (4.0 * rate / 1_800.0).ceil() as usize;
+        Self {
+            mixer: Nco::new(rate, LEADER as f64),
+            filter: FirDecimator::new(shift_taps(&lowpass_taps(1_200.0 / rate, len), 0.0), 1),
+            previous: Complex::new(0.0, 0.0),
+            rate,
+        }
+    }
+
+    fn process(&mut self, audio: &[f32]) -> Vec<f32> {
+        let mixed: Vec<Complex> = audio
+            .iter()
+            .map(|&s| self.mixer.mix(Complex::new(s, 0.0)))
+            .collect();
+        self.filter
+            .process(&mixed)
+            .into_iter()
+            .map(|c| {
+                let rotation = c * self.previous.conj();
+                self.previous = c;
+                let offset = rotation.im.atan2(rotation.re) as f64 * self.rate / TAU;
+                LEADER + offset as f32
+            })
+            .collect()
+    }
+}
+
+enum State {
+    Hunting,
+    /// Decoding lines at nominal timing from `start` (a sample position)
+    Receiving {
+        spec: &'static ModeSpec,
+        start: f64,
+        line: usize,
+    },
+}
+
+/// Decodes SSTV images from demodulated audio.
+///
+/// Waits for a VIS code, then samples each line at the mode's nominal timing.
+/// There is no slant correction, so the audio's sample rate must be accurate.
+pub struct SstvDecoder {
+    rate: f64,
+    tracker: ToneTracker,
+    /// Instantaneous frequency of recent audio
+    freqs: Vec<f32>,
+    /// Running sums of `freqs`; `prefix[i]` is the sum of everything before `freqs[i]`
+    prefix: Vec<f64>,
+    /// Sample position of `freqs[0]`
+    base: usize,
+    state: State,
+}
+
+impl SstvDecoder {
+    pub fn new(audio_rate: f64) -> Self {
+        Self {
+            rate: audio_rate,
+            tracker: ToneTracker::new(audio_rate),
+            freqs: Vec::new(),
+            prefix: vec![0.0],
+            base: 0,
+            state: State::Hunting,
+        }
+    }
+
+    /// Feed audio samples, returning the image progress they complete.
+    pub fn process(&mut self, audio: &[f32]) -> Vec<SstvEvent> {
+        let mut events = Vec::new();
+        for freq in self.tracker.process(audio) {
+            self.freqs.push(freq);
+            self.prefix.push(self.prefix.last().unwrap() + freq as f64);
+            self.step(&mut events);
+        }
+        events
+    }
+
+    /// Sample position just past the newest frequency sample.
+    fn position(&self) -> usize {
+        self.base + self.freqs.len()
+    }
+
+    fn step(&mut self, events: &mut Vec<SstvEvent>) {
+        let position = self.position();
+        match self.state {
+            State::Hunting => {
+                if let Some(spec) = self.match_vis(position) {
+                    // The VIS windows ignore bit edges, so the match is only
+                    // roughly aligned; time the image from the start bit's edge
+                    let end = self.leader_edge(position) + 10.0 * VIS_BIT * self.rate;
+                    events.push(SstvEvent::Started(spec.mode));
+                    self.state = State::Receiving {
+                        spec,
+                        start: end + spec.first_line * self.rate / 1_000.0,
+                        line: 0,
+                    };
+                } else if self.freqs.len() > 2 * self.rate as usize {
+                    self.trim(position - self.rate as usize);
+                }
+            }
+            State::Receiving { spec, start, line } => {
+                // Robot 36 shares chroma between line pairs, so decode two at a time
+                let lines = match spec.layout {
+                    Layout::Rgb { .. } => 1,
+                    Layout::Robot36 { .. } => 2,
+                };
+                let line_samples = spec.line * self.rate / 1_000.0;
+                let line_end = start + (line + lines) as f64 * line_samples;
+                if (position as f64) < line_end.ceil() {
+                    return;
+                }
+
+                let line_start = |l: usize| start + l as f64 * line_samples;
+                match spec.layout {
+                    Layout::Rgb { green, blue, red } => {
+                        let (r, g, b) = (
+                            self.scan(line_start(line), red, spec.mode),
+                            self.scan(line_start(line), green, spec.mode),
+                            self.scan(line_start(line), blue, spec.mode),
+                        );
+                        let pixels = (0..r.len())
+                            .map(|x| [r[x] as u8, g[x] as u8, b[x] as u8])
+                            .collect();
+                        events.push(SstvEvent::Line { line, pixels });
+                    }
+                    Layout::Robot36 { luma, chroma } => {
+                        let red_diff = self.scan(line_start(line), chroma, spec.mode);
+                        let blue_diff = self.scan(line_start(line + 1), chroma, spec.mode);
+                        for l in [line, line + 1] {
+                            let y = self.scan(line_start(l), luma, spec.mode);
+                            let pixels = (0..y.len())
+                                .map(|x| ycrcb_to_rgb(y[x], red_diff[x], blue_diff[x]))
+                                .collect();
+                            events.push(SstvEvent::Line { line: l, pixels });
+                        }
+                    }
+                }
+
+                let next = line + lines;
+                if next >= spec.mode.height() {
+                    events.push(SstvEvent::Finished);
+                    self.state = State::Hunting;
+                } else {
+                    self.trim(line_start(next).floor() as usize);
+                    self.state = State::Receiving {
+                        spec,
+                        start,
+                        line: next,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Check whether a VIS code ends at `end`, returning the mode it names.
+    fn match_vis(&self, end: usize) -> Option<&'static ModeSpec> {
+        let bit = VIS_BIT * self.rate;
+        let end = end as f64;
+        let leader_end = end - 10.0 * bit;
+        let leader_start = leader_end - LEADER_CHECK * self.rate;
+        if leader_start < self.base as f64 {
+            return None;
+        }
+        // Mean of the k-th bit counting back from the stop bit, ignoring its edges
+        let bit_mean = |k: f64| {
+            let bit_end = end - k * bit;
+            self.mean(bit_end - 0.9 * bit, bit_end - 0.1 * bit)
+        };
+
+        for (freq, expected) in [
+            (self.mean(leader_start, leader_end), LEADER),
+            (bit_mean(9.0), SYNC),
+            (bit_mean(0.0), SYNC),
+        ] {
+            if (freq - expected).abs() > TONE_TOLERANCE {
+                return None;
+            }
+        }
+
+        // 7 data bits, LSB first, then even parity
+        let mut code = 0u8;
+        let mut ones = 0;
+        for i in 0..8 {
+            let freq = bit_mean(8.0 - i as f64);
+            let one = freq < SYNC;
+            if (freq - if one { VIS_ONE } else { VIS_ZERO }).abs() > TONE_TOLERANCE {
+                return None;
+            }
+            if one {
+                ones += 1;
+                if i < 7 {
+                    code |= 1 << i;
+                }
+            }
+        }
+        if ones % 2 != 0 {
+            return None;
+        }
+
+        MODES.iter().find(|spec| spec.vis == code)
+    }
+
+    /// Find the leader-to-start-bit edge of the VIS code matched at `end`.
+    /// Returns the sample position of the steepest drop within half a bit of nominal.
+    fn leader_edge(&self, end: usize) -> f64 {
+        let bit = VIS_BIT * self.rate;
+        let window = EDGE_WINDOW * self.rate;
+        let nominal = end as f64 - 10.0 * bit;
+        let first = (nominal - bit / 2.0).max(self.base as f64 + window) as usize;
+        let last = (nominal + bit / 2.0) as usize;
+        (first..=last)
+            .map(|t| {
+                let t = t as f64;
+                let step = self.mean(t - window, t) - self.mean(t, t + window);
+                (t, step)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(nominal, |(t, _)| t)
+    }
+
+    /// Pixel values (0-255) across one scan of the line starting at `line_start`.
+    fn scan(&self, line_start: f64, scan: Scan, mode: SstvMode) -> Vec<f32> {
+        let width = mode.width();
+        let ms = self.rate / 1_000.0;
+        let pixel = scan.duration * ms / width as f64;
+        let scan_start = line_start + scan.start * ms;
+        (0..width)
+            .map(|x| {
+                let start = scan_start + x as f64 * pixel;
+                let freq = self.mean(start, start + pixel);
+                ((freq - BLACK) / (WHITE - BLACK) * 255.0).clamp(0.0, 255.0)
+            })
+            .collect()
+    }
+
+    /// Mean frequency over sample positions [start, end).
+    /// Always covers at least one sample.
+    fn mean(&self, start: f64, end: f64) -> f32 {
+        let last = self.freqs.len();
+        let from = (start.floor() as usize)
+            .saturating_sub(self.base)
+            .min(last - 1);
+        let to = (end.floor() as usize)
+            .saturating_sub(self.base)
+            .clamp(from + 1, last);
+        ((self.prefix[to] - self.prefix[from]) / (to - from) as f64) as f32
+    }
+
+    /// Drop history before sample position `keep_from`.
+    fn trim(&mut self, keep_from: usize) {
+        let count = keep_from.saturating_sub(self.base).min(self.freqs.len());
+        self.freqs.drain(..count);
+        self.prefix.drain(..count);
+        self.base += count;
+    }
+}
+
+fn ycrcb_to_rgb(y: f32, red_diff: f32, blue_diff: f32) -> [u8; 3] {
+    let (cr, cb) = (red_diff - 128.0, blue_diff - 128.0);
+    [
+        (y + 1.402 * cr).clamp(0.0, 255.0) as u8,
+        (y - 0.344 * cb - 0.714 * cr).clamp(0.0, 255.0) as u8,
+        (y + 1.772 * cb).clamp(0.0, 255.0) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 24_000.0;
+
+    /// Phase-continuous tone sequence of (frequency Hz, duration ms).
+    struct Encoder {
+        audio: Vec<f32>,
+        phase: f64,
+        /// Time written so far, in samples, kept fractional so lines don't drift
+        time: f64,
+    }
+
+    impl Encoder {
+        fn new() -> Self {
+            Self {
+                audio: Vec::new(),
+                phase: 0.0,
+                time: 0.0,
+            }
+        }
+
+        fn tone(&mut self, freq: f32, ms: f64) {
+            self.time += ms * RATE / 1_000.0;
+            while (self.audio.len() as f64) < self.time {
+                self.phase = (self.phase + freq as f64 / RATE).fract();
+                self.audio.push((TAU * self.phase).sin() as f32);
+            }
+        }
+
+        fn vis(&mut self, code: u8) {
+            self.tone(LEADER, 300.0);
+            self.tone(SYNC, 10.0);
+            self.tone(LEADER, 300.0);
+            self.tone(SYNC, 30.0);
+            let mut ones = 0;
+            for i in 0..7 {
+                let one = code >> i & 1 == 1;
+                ones += one as u32;
+                self.tone(if one { VIS_ONE } else { VIS_ZERO }, 30.0);
+            }
+            self.tone(if ones % 2 == 1 { VIS_ONE } else { VIS_ZERO }, 30.0);
+            self.tone(SYNC, 30.0);
+        }
+
+        fn scan(&mut self, values: impl Iterator<Item = f32>, ms: f64) {
+            let values: Vec<f32> = values.collect();
+            let pixel = ms / values.len() as f64;
+            for v in values {
+                self.tone(BLACK + v / 255.0 * (WHITE - BLACK), pixel);
+            }
+        }
+    }
+
+    fn test_pixel(mode: SstvMode, x: usize, line: usize) -> [f32; 3] {
+        let across = x as f32 / mode.width() as f32 * 255.0;
+        let down = line as f32 / mode.height() as f32 * 255.0;
+        [across, 255.0 - across, down]
+    }
+
+    fn encode(spec: &ModeSpec) -> Vec<f32> {
+        let mode = spec.mode;
+        let row = |line: usize, channel: usize| {
+            (0..mode.width()).map(move |x| test_pixel(mode, x, line)[channel])
+        };
+        let mut encoder = Encoder::new();
+        encoder.tone(BLACK, 100.0);
+        encoder.vis(spec.vis);
+        for line in 0..mode.height() {
+            match spec.layout {
+                Layout::Rgb { green, blue, red }
+                    if matches!(mode, SstvMode::Martin1 | SstvMode::Martin2) =>
+                {
+                    encoder.tone(SYNC, 4.862);
+                    encoder.tone(BLACK, 0.572);
+                    encoder.scan(row(line, 1), green.duration);
+                    encoder.tone(BLACK, 0.572);
+                    encoder.scan(row(line, 2), blue.duration);
+                    encoder.tone(BLACK, 0.572);
+                    encoder.scan(row(line, 0), red.duration);
+                    encoder.tone(BLACK, 0.572);
+                }
+                Layout::Rgb { green, blue, red } => {
+                    if line == 0 {
+                        encoder.tone(SYNC, 9.0);
+                    }
+                    encoder.tone(BLACK, 1.5);
+                    encoder.scan(row(line, 1), green.duration);
+                    encoder.tone(BLACK, 1.5);
+                    encoder.scan(row(line, 2), blue.duration);
+                    encoder.tone(SYNC, 9.0);
+                    encoder.tone(BLACK, 1.5);
+                    encoder.scan(row(line, 0), red.duration);
+                }
+                Layout::Robot36 { luma, chroma } => {
+                    let ycrcb: Vec<[f32; 3]> = (0..mode.width())
+                        .map(|x| {
+                            let [r, g, b] = test_pixel(mode, x, line);
+                            let y = 0.299 * r + 0.587 * g + 0.114 * b;
+                            [y, (r - y) * 0.713 + 128.0, (b - y) * 0.564 + 128.0]
+                        })
+                        .collect();
+                    let even = line % 2 == 0;
+                    encoder.tone(SYNC, 9.0);
+                    encoder.tone(BLACK, 3.0);
+                    encoder.scan(ycrcb.iter().map(|p| p[0]), luma.duration);
+                    encoder.tone(if even { BLACK } else { WHITE }, 4.5);
+                    encoder.tone(LEADER, 1.5);
+                    let c = if even { 1 } else { 2 };
+                    encoder.scan(ycrcb.iter().map(|p| p[c]), chroma.duration);
+                }
+            }
+        }
+        encoder.tone(BLACK, 100.0);
+        encoder.audio
+    }
+
+    fn check_decode(mode: SstvMode, tolerance: f32) {
+        let spec = MODES.iter().find(|s| s.mode == mode).unwrap();
+        let mut decoder = SstvDecoder::new(RATE);
+        // Feed in chunks as the sink would
+        let events: Vec<SstvEvent> = encode(spec)
+            .chunks(4096)
+            .flat_map(|chunk| decoder.process(chunk))
+            .collect();
+
+        assert_eq!(events.first(), Some(&SstvEvent::Started(mode)));
+        assert_eq!(events.last(), Some(&SstvEvent::Finished));
+        let lines: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                SstvEvent::Line { line, pixels } => Some((*line, pixels)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines.len(), mode.height());
+
+        for (line, pixels) in lines {
+            assert_eq!(pixels.len(), mode.width());
+            // Skip the scan edges, which the tone filter blurs into their neighbours
+            for x in (16..mode.width() - 16).step_by(8) {
+                let expected = test_pixel(mode, x, line);
+                for c in 0..3 {
+                    let error = (pixels[x][c] as f32 - expected[c]).abs();
+                    assert!(
+                        error <= tolerance,
+                        "{} line {line} x {x}: got {:?}, expected {:?}",
+                        mode.label(),
+                        pixels[x],
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_martin2() {
+        check_decode(SstvMode::Martin2, 6.0);
+    }
+
+    #[test]
+    fn decodes_scottie2() {
+        check_decode(SstvMode::Scottie2, 6.0);
+    }
+
+    #[test]
+    fn decodes_robot36() {
+        // Chroma is shared between line pairs, so allow for the vertical gradient
+        check_decode(SstvMode::Robot36, 10.0);
+    }
+}
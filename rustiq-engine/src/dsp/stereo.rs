@@ -0,0 +1,248 @@
+use rustradio::Complex;
+
+use super::fir::{FirDecimator, lowpass_taps, shift_taps};
+use super::nco::Nco;
+
+/// Frequency of the stereo pilot tone; the L-R subcarrier is at twice it.
+const PILOT: f64 = 19_000.0;
+
+/// Lowest multiplex rate that holds the L-R subband, up to 53 kHz.
+const MIN_STEREO_RATE: f64 = 106_000.0;
+
+/// Rate the audio is decimated to.
+const AUDIO_RATE: f64 = 48_000.0;
+
+/// Top of the broadcast audio band.
+const AUDIO_CUTOFF: f64 = 15_000.0;
+
+/// Width of the audio filter's transition band in Hz, ending short of the
+/// pilot.
+const TRANSITION: f64 = 4_000.0;
+
+/// Bandwidth in Hz of the filter tracking the pilot: narrow enough to pick
+/// it out of the noise, wide enough to follow a sample clock a little off.
+const PILOT_BANDWIDTH: f64 = 20.0;
+
+/// Tracked pilot level at which stereo starts, and below which it stops
+/// again. A pilot at the usual 9% of the deviation tracks at 0.045.
+const PILOT_ON: f32 = 0.02;
+const PILOT_OFF: f32 = 0.012;
+
+/// De-emphasis time constant, as in Europe; the Americas use 75 µs.
+const DEEMPHASIS: f64 = 50e-6;
+
+/// Demodulated audio, mono or stereo.
+pub struct Audio {
+    /// Mono samples, or left and right interleaved if `stereo`
+    pub samples: Vec<f32>,
+    pub stereo: bool,
+}
+
+impl Audio {
+    /// The audio as mono samples, mixing down stereo.
+    pub fn into_mono(self) -> Vec<f32> {
+        if !self.stereo {
+            return self.samples;
+        }
+        self.samples
+            .chunks_exact(2)
+            .map(|pair| (pair[0] + pair[1]) / 2.0)
+            .collect()
+    }
+}
+
+/// Decodes the multiplex signal of broadcast FM: L+R at baseband, a 19 kHz
+/// pilot, and L-R on a 38 kHz subcarrier locked to twice the pilot's phase.
+///
+/// The pilot is tracked by mixing it to DC and smoothing it into a phasor,
+/// whose phase gives the subcarrier's and whose size tells whether the
+/// station is sending stereo at all. Without a pilot, or at a rate too low
+/// to hold the subcarrier, the audio is mono.
+pub struct StereoDecoder {
+    pilot_mixer: Nco,
+    /// The pilot, mixed to DC and smoothed
+    pilot: Complex,
+    /// Smoothing factor of the pilot tracking
+    pilot_alpha: f32,
+    /// Whether the multiplex rate holds the subcarrier
+    capable: bool,
+    stereo: bool,
+    /// Low-pass filter and decimator for L+R in the real part and L-R in
+    /// the imaginary
+    filter: FirDecimator,
+    output_rate: f64,
+    deemphasis_alpha: f32,
+    /// De-emphasis filter outputs, left and right
+    deemphasized: [f32; 2],
+}
+
+impl StereoDecoder {
+    /// A decoder for a multiplex signal at `sample_rate`, scaled so full
+    /// deviation is 1.
+    pub fn new(sample_rate: f64) -> Self {
+        let decimation = ((sample_rate / AUDIO_RATE).round() as usize).max(1);
+        let output_rate = sample_rate / decimation as f64;
+        let cutoff = AUDIO_CUTOFF.min(0.45 * output_rate);
+        let len = (4.0 * sample_rate / TRANSITION).ceil() as usize;
+        let taps = shift_taps(&lowpass_taps(cutoff / sample_rate, len), 0.0);
+        Self {
+            pilot_mixer: Nco::new(sample_rate, PILOT),
+            pilot: Complex::new(0.0, 0.0),
+            pilot_alpha: (1.0 - (-std::f64::consts::TAU * PILOT_BANDWIDTH / sample_rate).exp())
+                as f32,
+            capable: sample_rate >= MIN_STEREO_RATE,
+            stereo: false,
+            filter: FirDecimator::new(taps, decimation),
+            output_rate,
+            deemphasis_alpha: (1.0 - (-1.0 / (output_rate * DEEMPHASIS)).exp()) as f32,
+            deemphasized: [0.0; 2],
+        }
+    }
+
+    /// Sample rate of the audio in Hz.
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    /// Decode multiplex samples. The audio is stereo or mono for the whole
+    /// of one call, as the pilot was when it started.
+    pub fn process(&mut self, mpx: &[f32]) -> Audio {
+        let stereo = self.stereo;
+        let mut baseband = Vec::with_capacity(mpx.len());
+        for &sample in mpx {
+            // The pilot's oscillator turning the other way, e^-jωt
+            let oscillator = self.pilot_mixer.mix(Complex::new(1.0, 0.0));
+            self.pilot += (oscillator * sample - self.pilot) * self.pilot_alpha;
+
+            let mut difference = 0.0;
+            if stereo {
+                // The pilot rebuilt at unit size; a pilot of sin φ comes
+                // with a subcarrier of sin 2φ, and this is cos φ + j sin φ
+                // turned back a quarter cycle
+                let pilot = oscillator.conj() * self.pilot / self.pilot.norm().max(f32::EPSILON);
+                let subcarrier = -2.0 * pilot.re * pilot.im;
+                difference = 2.0 * sample * subcarrier;
+            }
+            baseband.push(Complex::new(sample, difference));
+        }
+
+        let level = self.pilot.norm();
+        if self.capable && level >= PILOT_ON {
+            self.stereo = true;
+        } else if !self.capable || level < PILOT_OFF {
+            self.stereo = false;
+        }
+
+        let filtered = self.filter.process(&baseband);
+        let alpha = self.deemphasis_alpha;
+        let mut samples = Vec::with_capacity(filtered.len() * if stereo { 2 } else { 1 });
+        for sample in filtered {
+            if stereo {
+                let channels = [sample.re + sample.im, sample.re - sample.im];
+                for (state, channel) in self.deemphasized.iter_mut().zip(channels) {
+                    *state += alpha * (channel - *state);
+                    samples.push(*state);
+                }
+            } else {
+                let [mono, _] = &mut self.deemphasized;
+                *mono += alpha * (sample.re - *mono);
+                samples.push(*mono);
+            }
+        }
+        Audio { samples, stereo }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const RATE: f64 = 240_000.0;
+
+    /// A second of multiplex signal with `left` and `right` tones, and the
+    /// pilot if `pilot`.
+    fn multiplex(left: f64, right: f64, pilot: bool) -> Vec<f32> {
+        (0..RATE as usize)
+            .map(|i| {
+                let t = i as f64 / RATE;
+                let l = 0.5 * (TAU * left * t).sin();
+                let r = 0.5 * (TAU * right * t).sin();
+                let pilot_phase = TAU * PILOT * t;
+                let mut sample = 0.45 * (l + r);
+                if pilot {
+                    sample += 0.45 * (l - r) * (2.0 * pilot_phase).sin() + 0.09 * pilot_phase.sin();
+                }
+                sample as f32
+            })
+            .collect()
+    }
+
+    /// Level of `tone` in `audio`, by correlation.
+    fn tone_level(audio: &[f32], rate: f64, tone: f64) -> f64 {
+        let settled = &audio[audio.len() / 2..];
+        let (mut i, mut q) = (0.0, 0.0);
+        for (n, &sample) in settled.iter().enumerate() {
+            let phase = TAU * tone * n as f64 / rate;
+            i += sample as f64 * phase.cos();
+            q += sample as f64 * phase.sin();
+        }
+        2.0 * (i * i + q * q).sqrt() / settled.len() as f64
+    }
+
+    #[test]
+    fn separates_left_and_right_with_a_pilot() {
+        let mut decoder = StereoDecoder::new(RATE);
+        assert_eq!(decoder.output_rate(), 48_000.0);
+        let input = multiplex(1_000.0, 400.0, true);
+        // The pilot is picked up over the first part
+        let first = decoder.process(&input[..24_000]);
+        assert!(!first.stereo);
+
+        let audio = decoder.process(&input[24_000..]);
+        assert!(audio.stereo);
+        let left: Vec<f32> = audio.samples.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = audio.samples.iter().skip(1).step_by(2).copied().collect();
+        let rate = decoder.output_rate();
+        let (left_wanted, left_leak) = (
+            tone_level(&left, rate, 1_000.0),
+            tone_level(&left, rate, 400.0),
+        );
+        let (right_wanted, right_leak) = (
+            tone_level(&right, rate, 400.0),
+            tone_level(&right, rate, 1_000.0),
+        );
+        assert!(left_wanted > 0.3, "left {left_wanted}");
+        assert!(right_wanted > 0.3, "right {right_wanted}");
+        // At least 20 dB of separation
+        assert!(
+            left_leak < left_wanted / 10.0,
+            "{left_leak} of {left_wanted}"
+        );
+        assert!(
+            right_leak < right_wanted / 10.0,
+            "{right_leak} of {right_wanted}"
+        );
+    }
+
+    #[test]
+    fn stays_mono_without_a_pilot() {
+        let mut decoder = StereoDecoder::new(RATE);
+        let input = multiplex(1_000.0, 1_000.0, false);
+        decoder.process(&input[..24_000]);
+        let audio = decoder.process(&input[24_000..]);
+        assert!(!audio.stereo);
+        assert_eq!(audio.samples.len(), 43_200);
+        assert!(tone_level(&audio.samples, decoder.output_rate(), 1_000.0) > 0.3);
+    }
+
+    #[test]
+    fn stays_mono_at_a_rate_too_low_for_the_subcarrier() {
+        let mut decoder = StereoDecoder::new(48_000.0);
+        let pilot: Vec<f32> = (0..48_000)
+            .map(|i| (0.09 * (TAU * PILOT * i as f64 / 48_000.0).sin()) as f32)
+            .collect();
+        decoder.process(&pilot[..24_000]);
+        assert!(!decoder.process(&pilot[24_000..]).stereo);
+    }
+}
@@ -0,0 +1,187 @@
+//! Changing the length of audio without changing its pitch, by WSOLA:
+//! segments are overlapped and added at a steady pace while being taken
+//! from the input faster or slower, each nudged to where it best lines up
+//! with the audio before it.
+
+use std::f64::consts::TAU;
+
+/// Length of the segments overlapped, in seconds: longer than a pitch
+/// period of speech, shorter than a syllable.
+const SEGMENT: f64 = 0.03;
+
+/// How far a segment may be nudged either way from where it is due.
+const TOLERANCE: f64 = 0.005;
+
+/// Stretches mono or interleaved stereo audio to a multiple of its length.
+pub struct TimeStretch {
+    stereo: bool,
+    /// Frames in each segment; segments overlap by half
+    segment: usize,
+    tolerance: usize,
+    /// Periodic Hann window, which sums to one at half overlap
+    window: Vec<f32>,
+    /// Input frames not yet stretched, interleaved if stereo
+    input: Vec<f32>,
+    /// Frame of `input` the next segment is due at
+    due: f64,
+    /// Frame of `input` that carries on from the last segment, which the
+    /// next is lined up with
+    natural: usize,
+    /// Second half of the last segment, windowed, for the next to overlap
+    tail: Vec<f32>,
+}
+
+impl TimeStretch {
+    pub fn new(sample_rate: f64, stereo: bool) -> Self {
+        let hop = ((SEGMENT * sample_rate) as usize / 2).max(1);
+        let segment = 2 * hop;
+        let channels = if stereo { 2 } else { 1 };
+        Self {
+            stereo,
+            segment,
+            tolerance: (TOLERANCE * sample_rate) as usize,
+            window: (0..segment)
+                .map(|i| (0.5 - 0.5 * (TAU * i as f64 / segment as f64).cos()) as f32)
+                .collect(),
+            input: Vec::new(),
+            due: 0.0,
+            natural: 0,
+            tail: vec![0.0; hop * channels],
+        }
+    }
+
+    /// Whether this stretches audio that is stereo as `stereo` says.
+    pub fn is_for(&self, stereo: bool) -> bool {
+        self.stereo == stereo
+    }
+
+    /// Stretch `samples` to `1 / speed` times their length, returning what
+    /// is ready. The last few hundredths of a second are held back until
+    /// more audio follows.
+    pub fn process(&mut self, samples: &[f32], speed: f32) -> Vec<f32> {
+        let channels = if self.stereo { 2 } else { 1 };
+        let hop = self.segment / 2;
+        self.input.extend_from_slice(samples);
+        let mut output = Vec::new();
+        loop {
+            let frames = self.input.len() / channels;
+            let earliest = (self.due as usize).saturating_sub(self.tolerance);
+            let latest = self.due as usize + self.tolerance;
+            if latest + self.segment > frames || self.natural + hop > frames {
+                break;
+            }
+            let start = self.best_start(earliest, latest);
+            let segment = &self.input[start * channels..(start + self.segment) * channels];
+            for (i, (sample, tail)) in segment.iter().zip(&mut self.tail).enumerate() {
+                output.push(tail.to_owned() + sample * self.window[i / channels]);
+            }
+            for (i, tail) in self.tail.iter_mut().enumerate() {
+                *tail = segment[hop * channels + i] * self.window[hop + i / channels];
+            }
+            self.natural = start + hop;
+            self.due += hop as f64 * f64::from(speed);
+        }
+        // Drop what no segment can start at any more
+        let keep = ((self.due as usize).saturating_sub(self.tolerance)).min(self.natural);
+        self.input.drain(..keep * channels);
+        self.due -= keep as f64;
+        self.natural -= keep;
+        output
+    }
+
+    /// Start of the segment between `earliest` and `latest` that best
+    /// carries on from the last one.
+    fn best_start(&self, earliest: usize, latest: usize) -> usize {
+        let channels = if self.stereo { 2 } else { 1 };
+        let hop = self.segment / 2;
+        let mono = |frame: usize| -> f32 {
+            self.input[frame * channels..(frame + 1) * channels]
+                .iter()
+                .sum()
+        };
+        // Every other frame is plenty to line up audio-band waveforms
+        let natural: Vec<f32> = (0..hop)
+            .step_by(2)
+            .map(|i| mono(self.natural + i))
+            .collect();
+        (earliest..=latest)
+            .map(|start| {
+                let (correlation, energy) = (0..hop).step_by(2).zip(&natural).fold(
+                    (0.0, 0.0),
+                    |(correlation, energy), (i, natural)| {
+                        let sample = mono(start + i);
+                        (correlation + sample * natural, energy + sample * sample)
+                    },
+                );
+                (start, correlation / energy.sqrt().max(f32::EPSILON))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(earliest, |(start, _)| start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 8_000.0;
+
+    fn tone(frequency: f64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (TAU * frequency * i as f64 / RATE).sin() as f32)
+            .collect()
+    }
+
+    /// Frequency of `samples` from their rising zero crossings.
+    fn frequency(samples: &[f32]) -> f64 {
+        let crossings: Vec<usize> = samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+            .map(|(i, _)| i)
+            .collect();
+        let cycles = (crossings.len() - 1) as f64;
+        cycles * RATE / (crossings[crossings.len() - 1] - crossings[0]) as f64
+    }
+
+    #[test]
+    fn stretches_without_changing_pitch() {
+        for speed in [0.5, 2.0] {
+            let mut stretch = TimeStretch::new(RATE, false);
+            let input = tone(440.0, RATE as usize * 2);
+            // Fed in pieces, as the audio sink does
+            let output: Vec<f32> = input
+                .chunks(160)
+                .flat_map(|chunk| stretch.process(chunk, speed))
+                .collect();
+            let expected = input.len() as f64 / f64::from(speed);
+            let length = output.len() as f64;
+            assert!(
+                (length - expected).abs() < 0.05 * expected,
+                "{length} samples at {speed}×"
+            );
+            let settled = &output[output.len() / 4..output.len() * 3 / 4];
+            let pitch = frequency(settled);
+            assert!((pitch - 440.0).abs() < 5.0, "{pitch} Hz at {speed}×");
+            // Segments line up, leaving no dips where they overlap
+            let peak = settled.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+            assert!(peak < 1.05, "{peak}");
+            for window in settled.chunks(RATE as usize / 100) {
+                let peak = window.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+                assert!(peak > 0.9, "{peak} at {speed}×");
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_stereo_channels_apart() {
+        let mut stretch = TimeStretch::new(RATE, true);
+        let input: Vec<f32> = tone(300.0, RATE as usize)
+            .into_iter()
+            .flat_map(|left| [left, 0.0])
+            .collect();
+        let output = stretch.process(&input, 0.5);
+        assert!(output.len() > input.len() * 3 / 2);
+        assert!(output.iter().skip(1).step_by(2).all(|&right| right == 0.0));
+    }
+}
@@ -0,0 +1,307 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use rustfft::{Fft, FftPlanner};
+use rustradio::Complex;
+
+/// Signals are measured out to where they fall this far below their peak
+/// (a linear power ratio, 20 dB).
+const EDGE_RATIO: f32 = 0.01;
+
+/// Bins below the threshold that may separate parts of one signal.
+const MAX_GAP: usize = 2;
+
+/// Share of a signal's power inside its occupied bandwidth.
+const OCCUPIED_FRACTION: f32 = 0.99;
+
+/// Averaged spectrum and spectrogram of a whole recording.
+///
+/// Blocks of `fft_size` samples are Hann windowed and transformed; their
+/// powers are averaged into one spectrum (Welch's method) and, in groups of
+/// `frames_per_row`, into the rows of a spectrogram. Powers are relative to
+/// a full-scale tone, with DC in the middle bin.
+pub struct SpectrumSurvey {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// Power of a full-scale tone, to normalise to
+    full_scale: f32,
+    block: Vec<Complex>,
+    total: Vec<f32>,
+    frames: usize,
+    row: Vec<f32>,
+    row_frames: usize,
+    frames_per_row: usize,
+    rows: Vec<Vec<f32>>,
+}
+
+/// A signal found in an averaged spectrum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurveySignal {
+    /// Bin of the strongest power, with DC in the middle bin
+    pub peak_bin: usize,
+    /// Number of bins down to `EDGE_RATIO` of the peak
+    pub width_bins: usize,
+    /// Width down to half the peak power (-3 dB), in bins, interpolated
+    pub half_power_bins: f32,
+    /// Width holding `OCCUPIED_FRACTION` of the power above the floor, in
+    /// bins, interpolated
+    pub occupied_bins: f32,
+    /// Peak power (linear)
+    pub peak: f32,
+    /// Total power across the signal's bins (linear)
+    pub power: f32,
+}
+
+impl SpectrumSurvey {
+    pub fn new(fft_size: usize, frames_per_row: usize) -> Self {
+        let window: Vec<f32> = (0..fft_size)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f32 / fft_size as f32).cos())
+            .collect();
+        let gain: f32 = window.iter().sum();
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(fft_size),
+            full_scale: gain * gain,
+            window,
+            block: Vec::with_capacity(fft_size),
+            total: vec![0.0; fft_size],
+            frames: 0,
+            row: vec![0.0; fft_size],
+            row_frames: 0,
+            frames_per_row: frames_per_row.max(1),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn process(&mut self, input: &[Complex]) {
+        for &sample in input {
+            self.block.push(sample * self.window[self.block.len()]);
+            if self.block.len() == self.window.len() {
+                self.add_frame();
+            }
+        }
+    }
+
+    fn add_frame(&mut self) {
+        self.fft.process(&mut self.block);
+        let half = self.block.len() / 2;
+        for (bin, value) in self.block.iter().enumerate() {
+            // Shift so DC lands in the middle bin
+            let shifted = (bin + half) % self.block.len();
+            let power = value.norm_sqr() / self.full_scale;
+            self.total[shifted] += power;
+            self.row[shifted] += power;
+        }
+        self.block.clear();
+        self.frames += 1;
+        self.row_frames += 1;
+        if self.row_frames == self.frames_per_row {
+            let scale = 1.0 / self.row_frames as f32;
+            self.rows
+                .push(self.row.iter().map(|&power| power * scale).collect());
+            self.row.fill(0.0);
+            self.row_frames = 0;
+        }
+    }
+
+    /// The average power in each bin.
+    pub fn average(&self) -> Vec<f32> {
+        let scale = 1.0 / self.frames.max(1) as f32;
+        self.total.iter().map(|&power| power * scale).collect()
+    }
+
+    /// The completed spectrogram rows, oldest first.
+    pub fn rows(&self) -> &[Vec<f32>] {
+        &self.rows
+    }
+}
+
+/// Median bin power, a robust estimate of the noise floor while signals
+/// occupy under half the band.
+pub fn noise_floor(spectrum: &[f32]) -> f32 {
+    let mut sorted = spectrum.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted.get(sorted.len() / 2).copied().unwrap_or(0.0)
+}
+
+/// Signals rising `threshold` (a power ratio) above `floor`, strongest first.
+pub fn find_signals(spectrum: &[f32], floor: f32, threshold: f32) -> Vec<SurveySignal> {
+    let level = floor * threshold;
+    let mut signals = Vec::new();
+    let mut bin = 0;
+    while bin < spectrum.len() {
+        if spectrum[bin] <= level {
+            bin += 1;
+            continue;
+        }
+        // Extend over the run of bins above the level, bridging short gaps
+        let start = bin;
+        let mut end = bin;
+        let mut gap = 0;
+        bin += 1;
+        while bin < spectrum.len() && gap <= MAX_GAP {
+            if spectrum[bin] > level {
+                end = bin;
+                gap = 0;
+            } else {
+                gap += 1;
+            }
+            bin += 1;
+        }
+        bin = end + 1;
+
+        let run = &spectrum[start..=end];
+        let (offset, &peak) = run
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .expect("run is not empty");
+        // Measure the width around the peak, so strong signals' window
+        // leakage doesn't count towards their bandwidth
+        let edge = peak * EDGE_RATIO;
+        let low = run[..offset]
+            .iter()
+            .rposition(|&power| power < edge)
+            .map_or(0, |i| i + 1);
+        let high = run[offset..]
+            .iter()
+            .position(|&power| power < edge)
+            .map_or(run.len(), |i| offset + i);
+        let above_floor: Vec<f32> = run.iter().map(|&power| (power - floor).max(0.0)).collect();
+        signals.push(SurveySignal {
+            peak_bin: start + offset,
+            width_bins: high - low,
+            half_power_bins: half_power_width(spectrum, start + offset),
+            occupied_bins: occupied_width(&above_floor, OCCUPIED_FRACTION),
+            peak,
+            power: run[low..high].iter().sum(),
+        });
+    }
+    signals.sort_by(|a, b| b.peak.total_cmp(&a.peak));
+    signals
+}
+
+/// Width in bins between where the power falls to half that of the peak
+/// bin, interpolating between the bins either side of each crossing. A
+/// spectrum edge counts as a crossing.
+fn half_power_width(spectrum: &[f32], peak: usize) -> f32 {
+    let half = spectrum[peak] / 2.0;
+    // Position of the crossing between a bin above half and one below
+    let crossing = |above: usize, below: usize| {
+        let (a, b) = (spectrum[above], spectrum[below]);
+        above as f32 + (below as f32 - above as f32) * (a - half) / (a - b)
+    };
+    let low = spectrum[..peak]
+        .iter()
+        .rposition(|&power| power < half)
+        .map_or(-0.5, |below| crossing(below + 1, below));
+    let high = spectrum[peak..]
+        .iter()
+        .position(|&power| power < half)
+        .map_or(spectrum.len() as f32 - 0.5, |i| {
+            crossing(peak + i - 1, peak + i)
+        });
+    high - low
+}
+
+/// Width in bins holding `fraction` of the total power, leaving an equal
+/// share out on either side. Each bin's power is spread evenly across it.
+fn occupied_width(powers: &[f32], fraction: f32) -> f32 {
+    let total: f32 = powers.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    // Where the running total reaches `target`
+    let position = |target: f32| {
+        let mut sum = 0.0;
+        for (bin, &power) in powers.iter().enumerate() {
+            if sum + power >= target && power > 0.0 {
+                return bin as f32 + (target - sum) / power;
+            }
+            sum += power;
+        }
+        powers.len() as f32
+    };
+    let tail = total * (1.0 - fraction) / 2.0;
+    position(total - tail) - position(tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FFT_SIZE: usize = 1024;
+
+    /// Low-level noise plus a tone in bin `tone_bin` of an unshifted FFT, at
+    /// amplitude `amplitude`.
+    fn tone(len: usize, tone_bin: f32, amplitude: f32) -> Vec<Complex> {
+        let mut state = 1u32;
+        let mut noise = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 8) as f32 / (1 << 24) as f32 - 0.5) * 1e-3
+        };
+        (0..len)
+            .map(|i| {
+                let phase = TAU * tone_bin * i as f32 / FFT_SIZE as f32;
+                Complex::new(
+                    amplitude * phase.cos() + noise(),
+                    amplitude * phase.sin() + noise(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_scale_tone_reads_zero_db() {
+        let mut survey = SpectrumSurvey::new(FFT_SIZE, 4);
+        survey.process(&tone(FFT_SIZE * 8, 100.0, 1.0));
+        let average = survey.average();
+
+        assert_eq!(survey.rows().len(), 2);
+        let peak = average[FFT_SIZE / 2 + 100];
+        assert!((peak - 1.0).abs() < 0.01, "peak {peak}");
+    }
+
+    #[test]
+    fn finds_tones_strongest_first() {
+        let mut input = tone(FFT_SIZE * 16, -200.0, 0.01);
+        for (sample, strong) in input.iter_mut().zip(tone(FFT_SIZE * 16, 50.0, 0.5)) {
+            *sample += strong;
+        }
+        let mut survey = SpectrumSurvey::new(FFT_SIZE, 16);
+        survey.process(&input);
+        let average = survey.average();
+        let floor = noise_floor(&average);
+        let signals = find_signals(&average, floor, 10.0);
+
+        assert_eq!(signals.len(), 2, "{signals:?}");
+        assert_eq!(signals[0].peak_bin, FFT_SIZE / 2 + 50);
+        assert_eq!(signals[1].peak_bin, FFT_SIZE / 2 - 200);
+        // A Hann-windowed tone's main lobe, measured to 20 dB down
+        for signal in &signals {
+            assert!((2..=4).contains(&signal.width_bins), "{signal:?}");
+        }
+        // 34 dB apart in amplitude
+        assert!(signals[0].peak > 1_000.0 * signals[1].peak);
+        // A Hann window's main lobe is 1.44 bins wide at -3 dB, and holds
+        // over 99% of the tone's power
+        for signal in &signals {
+            assert!((signal.half_power_bins - 1.44).abs() < 0.15, "{signal:?}");
+            assert!((1.0..3.0).contains(&signal.occupied_bins), "{signal:?}");
+        }
+    }
+
+    #[test]
+    fn measures_bandwidth_of_flat_signal() {
+        // A flat 100-bin signal over the floor, with sloped skirts
+        let mut spectrum = vec![1e-6; FFT_SIZE];
+        spectrum[400..500].fill(1e-3);
+        spectrum[399] = 1e-4;
+        spectrum[500] = 1e-4;
+        let signals = find_signals(&spectrum, noise_floor(&spectrum), 10.0);
+
+        assert_eq!(signals.len(), 1, "{signals:?}");
+        let signal = signals[0];
+        assert!((signal.half_power_bins - 100.1).abs() < 0.05, "{signal:?}");
+        assert!((signal.occupied_bins - 99.2).abs() < 0.2, "{signal:?}");
+    }
+}
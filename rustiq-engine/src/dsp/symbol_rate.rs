@@ -0,0 +1,327 @@
+use std::f32::consts::{PI, TAU};
+
+use rustfft::FftPlanner;
+use rustiq_messages::{Decibels, SymbolRateCandidate};
+use rustradio::Complex;
+
+use super::channel::Channelizer;
+use super::fir::{lowpass_taps, shift_taps};
+
+/// Seconds of the channel an estimate is made from.
+const CAPTURE: f64 = 1.0;
+
+/// Most channel samples an estimate is made from, bounding the FFT.
+const MAX_SAMPLES: usize = 1 << 17;
+
+/// Lowest channel rate, so narrow regions still get a usable filter.
+const MIN_RATE: f64 = 200.0;
+
+/// Lines below this many bins are taken for the signal's slow fading.
+const MIN_BIN: usize = 4;
+
+/// Rise of a line above the median of the bins around it (a linear power
+/// ratio, 20 dB) to count as a candidate. Noise alone rarely tops 13 dB.
+const MIN_STRENGTH: f32 = 100.0;
+
+/// Bins either side of a line that its background is taken from, so the
+/// broad hump of random data under the lines doesn't count as one.
+const BACKGROUND_BINS: usize = 32;
+
+/// Highest multiple of a line that is taken for its harmonic.
+const MAX_HARMONIC: f64 = 8.0;
+
+/// Weaker peaks this close to a stronger one are its window's sidelobes.
+const MIN_SEPARATION: usize = 3;
+
+/// Candidates reported.
+const MAX_CANDIDATES: usize = 3;
+
+/// Estimates the symbol rate of one signal from spectral lines.
+///
+/// The region is selected and decimated to four times its bandwidth, and a
+/// second of it collected. Symbol timing makes the signal cyclostationary:
+/// the envelope of a filtered PSK or QAM signal dips at each transition, and
+/// an FSK signal's frequency jumps, so the squared envelope and the size of
+/// the frequency steps both repeat at the symbol rate. Their spectra then
+/// show a line at the rate, which is read off to a fraction of a bin.
+pub struct SymbolRateEstimator {
+    channel: Channelizer,
+    bandwidth: f64,
+    /// Channel samples still to skip while the filter settles
+    settling: usize,
+    samples: Vec<Complex>,
+    /// Channel samples to collect
+    wanted: usize,
+    done: bool,
+}
+
+impl SymbolRateEstimator {
+    /// Create an estimator for the region `bandwidth` Hz wide, `offset` Hz
+    /// from the input's DC.
+    pub fn new(sample_rate: f64, offset: f64, bandwidth: f64) -> Self {
+        let target_rate = (4.0 * bandwidth).max(MIN_RATE);
+        let channel = Channelizer::new(
+            sample_rate,
+            offset,
+            target_rate,
+            |filter_rate, output_rate| {
+                let cutoff = (bandwidth / 2.0).min(0.45 * output_rate);
+                let len = (16.0 * filter_rate / cutoff).ceil() as usize;
+                shift_taps(&lowpass_taps(cutoff / filter_rate, len), 0.0)
+            },
+        );
+        let wanted = ((CAPTURE * channel.output_rate()) as usize).min(MAX_SAMPLES);
+        Self {
+            settling: channel.settling_samples(),
+            channel,
+            bandwidth,
+            samples: Vec::with_capacity(wanted),
+            wanted,
+            done: false,
+        }
+    }
+
+    /// Feed IQ samples. Returns the candidates, strongest first, once enough
+    /// of the channel is in, and nothing before or after.
+    pub fn process(&mut self, input: &[Complex]) -> Option<Vec<SymbolRateCandidate>> {
+        if self.done {
+            return None;
+        }
+        let channel = self.channel.process(input);
+        let skip = self.settling.min(channel.len());
+        self.settling -= skip;
+        let take = (self.wanted - self.samples.len()).min(channel.len() - skip);
+        self.samples.extend_from_slice(&channel[skip..skip + take]);
+        if self.samples.len() < self.wanted {
+            return None;
+        }
+        self.done = true;
+        Some(self.estimate())
+    }
+
+    fn estimate(&self) -> Vec<SymbolRateCandidate> {
+        let samples = &self.samples;
+        let envelope: Vec<f32> = samples.iter().map(|s| s.norm_sqr()).collect();
+        let steps: Vec<f32> = samples
+            .windows(2)
+            .map(|w| (w[1] * w[0].conj()).arg())
+            .collect();
+        let transitions: Vec<f32> = steps.windows(2).map(|w| wrap(w[1] - w[0]).abs()).collect();
+
+        let size = samples.len().next_power_of_two();
+        let bin_rate = self.channel.output_rate() / size as f64;
+        let last = ((self.bandwidth / bin_rate) as usize).min(size / 2 - 2);
+        if last < MIN_BIN + 2 {
+            return Vec::new();
+        }
+
+        // Each spectrum relative to its background, taking the stronger
+        let spectra = [envelope, transitions]
+            .map(|feature| above_background(&line_spectrum(&feature, size), last + 2));
+        let lines: Vec<f32> = spectra[0]
+            .iter()
+            .zip(&spectra[1])
+            .map(|(a, b)| a.max(*b))
+            .collect();
+
+        let mut peaks: Vec<usize> = (MIN_BIN..=last)
+            .filter(|&bin| {
+                lines[bin] >= MIN_STRENGTH
+                    && lines[bin] > lines[bin - 1]
+                    && lines[bin] >= lines[bin + 1]
+            })
+            .collect();
+        peaks.sort_by(|&a, &b| lines[b].total_cmp(&lines[a]));
+        let mut chosen: Vec<usize> = Vec::new();
+        for bin in peaks {
+            if chosen.iter().all(|&c| c.abs_diff(bin) > MIN_SEPARATION) {
+                chosen.push(bin);
+            }
+        }
+        let rates: Vec<f64> = chosen
+            .iter()
+            .map(|&bin| bin as f64 + refine(&lines[bin - 1..=bin + 1]))
+            .collect();
+
+        // A line at a multiple of another is its harmonic, however strong,
+        // so the fundamentals come first
+        let mut candidates: Vec<(bool, SymbolRateCandidate)> = chosen
+            .iter()
+            .zip(&rates)
+            .map(|(&bin, &rate)| {
+                let harmonic = rates.iter().any(|&other| {
+                    let multiple = (rate / other).round();
+                    (2.0..=MAX_HARMONIC).contains(&multiple)
+                        && (rate - multiple * other).abs() <= 1.5
+                });
+                let candidate = SymbolRateCandidate {
+                    rate: rate * bin_rate,
+                    strength: Decibels::from_power(lines[bin]),
+                };
+                (harmonic, candidate)
+            })
+            .collect();
+        candidates.sort_by_key(|&(harmonic, _)| harmonic);
+        candidates
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+}
+
+/// Power spectrum of a real feature, mean removed and Hann windowed,
+/// zero-padded to `size`. Only the positive half is returned.
+fn line_spectrum(feature: &[f32], size: usize) -> Vec<f32> {
+    let mean = feature.iter().sum::<f32>() / feature.len() as f32;
+    let len = feature.len() as f32;
+    let mut buffer: Vec<Complex> = feature
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            let window = 0.5 - 0.5 * (TAU * i as f32 / len).cos();
+            Complex::new((value - mean) * window, 0.0)
+        })
+        .collect();
+    buffer.resize(size, Complex::new(0.0, 0.0));
+    FftPlanner::new()
+        .plan_fft_forward(size)
+        .process(&mut buffer);
+    buffer[..size / 2].iter().map(|c| c.norm_sqr()).collect()
+}
+
+/// Each of the first `len` bins of `spectrum` over the median of the bins
+/// around it.
+fn above_background(spectrum: &[f32], len: usize) -> Vec<f32> {
+    let mut window = Vec::with_capacity(2 * BACKGROUND_BINS + 1);
+    (0..len)
+        .map(|bin| {
+            let start = bin.saturating_sub(BACKGROUND_BINS);
+            let end = (bin + BACKGROUND_BINS + 1).min(spectrum.len());
+            window.clear();
+            window.extend_from_slice(&spectrum[start..end]);
+            let middle = window.len() / 2;
+            let (_, median, _) = window.select_nth_unstable_by(middle, f32::total_cmp);
+            spectrum[bin] / median.max(f32::MIN_POSITIVE)
+        })
+        .collect()
+}
+
+/// Offset of a peak from its middle bin, from a parabola through the log
+/// powers of it and its neighbours.
+fn refine(powers: &[f32]) -> f64 {
+    let [a, b, c] = [powers[0], powers[1], powers[2]].map(|p| p.max(f32::MIN_POSITIVE).ln());
+    let curvature = a - 2.0 * b + c;
+    if curvature >= 0.0 {
+        return 0.0;
+    }
+    (0.5 * (a - c) / curvature) as f64
+}
+
+/// Wrap a phase into (-π, π].
+fn wrap(phase: f32) -> f32 {
+    if phase > PI {
+        phase - TAU
+    } else if phase <= -PI {
+        phase + TAU
+    } else {
+        phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const RATE: f64 = 48_000.0;
+
+    /// Repeatable random bits and noise.
+    struct Lcg(u32);
+
+    impl Lcg {
+        fn next(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (self.0 >> 8) as f32 / (1 << 24) as f32 - 0.5
+        }
+    }
+
+    /// `seconds` of a signal at `freq` keyed by random bits at `baud`, its
+    /// phase advancing by `step(bit)` radians each sample, plus a little
+    /// noise.
+    fn keyed(
+        freq: f64,
+        baud: f64,
+        seconds: f64,
+        mut step: impl FnMut(bool) -> f64,
+    ) -> Vec<Complex> {
+        let mut random = Lcg(7);
+        let mut bit = false;
+        let mut symbol = usize::MAX;
+        let mut phase = 0.0;
+        (0..(seconds * RATE) as usize)
+            .map(|i| {
+                let t = i as f64 / RATE;
+                if (t * baud) as usize != symbol {
+                    symbol = (t * baud) as usize;
+                    bit = random.next() > 0.0;
+                }
+                phase += step(bit);
+                let carrier = TAU * freq * t + phase;
+                Complex::new(
+                    0.1 * carrier.cos() as f32 + 1e-3 * random.next(),
+                    0.1 * carrier.sin() as f32 + 1e-3 * random.next(),
+                )
+            })
+            .collect()
+    }
+
+    fn estimate(input: &[Complex], offset: f64, bandwidth: f64) -> Vec<SymbolRateCandidate> {
+        let mut estimator = SymbolRateEstimator::new(RATE, offset, bandwidth);
+        let mut result = None;
+        for chunk in input.chunks(4096) {
+            if let Some(candidates) = estimator.process(chunk) {
+                assert!(result.is_none(), "reported twice");
+                result = Some(candidates);
+            }
+        }
+        result.expect("no estimate")
+    }
+
+    #[test]
+    fn finds_psk_symbol_rate() {
+        // NRZ BPSK: the phase flips by π on a 1, so its envelope dips at
+        // the flips once the region's filter band-limits it
+        let mut last = false;
+        let input = keyed(6_000.0, 1_200.0, 1.5, |bit| {
+            let flip = bit != last;
+            last = bit;
+            if flip { std::f64::consts::PI } else { 0.0 }
+        });
+        let candidates = estimate(&input, 6_000.0, 2_400.0);
+        assert!(!candidates.is_empty());
+        assert!((candidates[0].rate - 1_200.0).abs() < 2.0, "{candidates:?}");
+        assert!(candidates[0].strength > Decibels(20.0));
+    }
+
+    #[test]
+    fn finds_fsk_symbol_rate() {
+        // Continuous-phase FSK, ±425 Hz
+        let input = keyed(-3_000.0, 300.0, 1.5, |bit| {
+            let deviation = if bit { 425.0 } else { -425.0 };
+            TAU * deviation / RATE
+        });
+        let candidates = estimate(&input, -3_000.0, 1_500.0);
+        assert!(!candidates.is_empty());
+        assert!((candidates[0].rate - 300.0).abs() < 1.0, "{candidates:?}");
+    }
+
+    #[test]
+    fn finds_nothing_in_noise() {
+        let mut random = Lcg(3);
+        let input: Vec<Complex> = (0..(1.5 * RATE) as usize)
+            .map(|_| Complex::new(random.next(), random.next()))
+            .collect();
+        assert!(estimate(&input, 0.0, 5_000.0).is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+use std::f64::consts::TAU;
+
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+/// Weights each block of `size` samples by a Hann window ahead of an FFT of
+/// that size, so a signal between two bins doesn't leak across the whole
+/// spectrum. Scaled so a tone on a bin keeps its level.
+#[derive(rustradio_macros::Block)]
+pub struct HannWindow {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+    weights: Vec<f32>,
+    /// Where in its block the next sample falls
+    position: usize,
+}
+
+impl HannWindow {
+    pub fn new(src: ReadStream<Complex>, size: usize) -> (Self, ReadStream<Complex>) {
+        let (dst, dr) = rustradio::stream::new_stream();
+        (
+            Self {
+                src,
+                dst,
+                weights: hann(size.max(1)),
+                position: 0,
+            },
+            dr,
+        )
+    }
+}
+
+/// The periodic Hann window of `size` samples, divided by its mean of 1/2.
+fn hann(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| (1.0 - (TAU * i as f64 / size as f64).cos()) as f32)
+        .collect()
+}
+
+impl Block for HannWindow {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+        let mut o = self.dst.write_buf()?;
+        let n = input.len().min(o.len());
+        if n == 0 {
+            return Ok(BlockRet::WaitForStream(&self.dst, 1));
+        }
+        for (out, &sample) in o.slice()[..n].iter_mut().zip(&input.slice()[..n]) {
+            *out = sample * self.weights[self.position];
+            self.position = (self.position + 1) % self.weights.len();
+        }
+        o.produce(n, &[]);
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use rustfft::{Fft, FftPlanner};
+use rustradio::Complex;
+
+use super::fir::IntegrateDump;
+use super::nco::Nco;
+
+/// One block of decimated samples and its spectrum.
+pub struct ZoomFrame {
+    /// Decimated time-domain samples, centered on the zoom frequency.
+    pub samples: Vec<Complex>,
+    /// FFT of `samples` (not shifted: DC is bin 0).
+    pub spectrum: Vec<Complex>,
+}
+
+/// Zoom-FFT: mixes a narrow slice of the band down to DC, decimates it, and
+/// runs a long FFT over the decimated samples for fine frequency resolution.
+///
+/// Decimation is integrate-and-dump, which is cheap and places nulls on the
+/// alias frequencies closest to DC. That is good enough for measuring
+/// isolated carriers but is not a general-purpose channel filter.
+pub struct ZoomFft {
+    mixer: Nco,
+    decimator: IntegrateDump,
+    samples: Vec<Complex>,
+    fft: Arc<dyn Fft<f32>>,
+    fft_size: usize,
+    output_rate: f64,
+}
+
+impl ZoomFft {
+    /// Create a zoom-FFT centered `offset` Hz from the input's DC.
+    pub fn new(sample_rate: f64, offset: f64, decimation: usize, fft_size: usize) -> Self {
+        let decimation = decimation.max(1);
+        Self {
+            mixer: Nco::new(sample_rate, offset),
+            decimator: IntegrateDump::new(decimation),
+            samples: Vec::with_capacity(fft_size),
+            fft: FftPlanner::new().plan_fft_forward(fft_size),
+            fft_size,
+            output_rate: sample_rate / decimation as f64,
+        }
+    }
+
+    /// Sample rate of the decimated samples in Hz.
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    /// Number of decimated samples per frame.
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Mix, decimate and buffer the input, returning every frame completed by it.
+    pub fn process(&mut self, input: &[Complex]) -> Vec<ZoomFrame> {
+        let mut frames = Vec::new();
+        for &sample in input {
+            let Some(decimated) = self.decimator.push(self.mixer.mix(sample)) else {
+                continue;
+            };
+            self.samples.push(decimated);
+
+            if self.samples.len() == self.fft_size {
+                let samples =
+                    std::mem::replace(&mut self.samples, Vec::with_capacity(self.fft_size));
+                let mut spectrum = samples.clone();
+                self.fft.process(&mut spectrum);
+                frames.push(ZoomFrame { samples, spectrum });
+            }
+        }
+        frames
+    }
+}
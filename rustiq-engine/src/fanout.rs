@@ -0,0 +1,234 @@
+//! Fan a source's stream out to pipelines that each run in a graph of
+//! their own, such as the spectrum, the channels and the recording. Every
+//! domain gets its own queue, so one falling behind only holds up the
+//! others if it was asked to wait for room rather than drop.
+
+use std::thread;
+use std::time::Duration;
+
+use flume::{Receiver, Sender, TryRecvError, TrySendError};
+use log::warn;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::graph::CancellationToken;
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Error, rustradio_macros};
+
+use super::config::Overflow;
+
+/// Chunks handed to the domains per second of samples.
+const CHUNKS_PER_SECOND: u64 = 100;
+
+/// Chunks queued for each domain. A domain is never more than these
+/// behind the source, so it hears of a hop within a sixth of a second.
+const QUEUE_CHUNKS: usize = 16;
+
+/// How long the fan-out sleeps when a domain it waits for has no room.
+const WAIT: Duration = Duration::from_millis(1);
+
+/// The fan-out's end of a domain's queue.
+pub struct DomainPort {
+    name: String,
+    tx: Sender<Vec<Complex>>,
+    overflow: Overflow,
+    /// Samples the domain missed because its queue was full
+    dropped: u64,
+}
+
+/// A queue into the domain `name`, doing `overflow` when full: the
+/// fan-out's end, and the source block reading it at the domain's end with
+/// its stream.
+pub fn domain(name: &str, overflow: Overflow) -> (DomainPort, DomainSource, ReadStream<Complex>) {
+    let (tx, rx) = flume::bounded(QUEUE_CHUNKS);
+    let (source, stream) = DomainSource::new(rx);
+    let port = DomainPort {
+        name: name.to_string(),
+        tx,
+        overflow,
+        dropped: 0,
+    };
+    (port, source, stream)
+}
+
+/// A sink block that hands its stream of `sample_rate` samples a second to
+/// each domain in chunks. It waits for room in the queues of the domains
+/// that block; the others miss what doesn't fit. Domains whose graph has
+/// ended are left out.
+#[derive(rustradio_macros::Block)]
+pub struct FanOut {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    ports: Vec<DomainPort>,
+    chunk: usize,
+}
+
+impl FanOut {
+    pub fn new(src: ReadStream<Complex>, ports: Vec<DomainPort>, sample_rate: u64) -> Self {
+        Self {
+            src,
+            ports,
+            chunk: (sample_rate / CHUNKS_PER_SECOND).max(1) as usize,
+        }
+    }
+}
+
+impl Block for FanOut {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        // Not waiting on the stream: a graph whose blocks all wait ends, and
+        // this one runs on after a file ends, until the engine cancels it
+        if input.is_empty() {
+            return Ok(BlockRet::Pending);
+        }
+        // This block is the only sender, so room now is room when sending
+        let waiting = self
+            .ports
+            .iter()
+            .any(|port| port.overflow == Overflow::Block && port.tx.is_full());
+        if waiting {
+            // Sleeping rather than spinning, as a source such as the
+            // generator makes samples as fast as it is asked to
+            thread::sleep(WAIT);
+            return Ok(BlockRet::Pending);
+        }
+
+        let n = input.len().min(self.chunk);
+        let chunk = &input.slice()[..n];
+        self.ports
+            .retain_mut(|port| match port.tx.try_send(chunk.to_vec()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    if port.dropped == 0 {
+                        warn!("The {} fell behind the source; dropping samples", port.name);
+                    }
+                    port.dropped += n as u64;
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        input.consume(n);
+        if self.ports.is_empty() {
+            return Ok(BlockRet::EOF);
+        }
+        Ok(BlockRet::Again)
+    }
+}
+
+/// A source block starting a domain's graph with the samples fanned out to
+/// it. It ends when the fan-out does, or once what is queued is used up
+/// after its input is closed.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct DomainSource {
+    rx: Receiver<Vec<Complex>>,
+    /// Chunk received that didn't fit the output yet
+    #[rustradio(default)]
+    pending: Vec<Complex>,
+    /// Set once nothing more is sent
+    #[rustradio(default)]
+    closed: CancellationToken,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+}
+
+impl DomainSource {
+    /// Token to cancel once the fan-out has sent its last.
+    pub fn close_token(&self) -> CancellationToken {
+        self.closed.clone()
+    }
+}
+
+impl Block for DomainSource {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        // Taken before receiving, so a chunk sent just ahead of closing
+        // isn't missed
+        let closed = self.closed.is_canceled();
+        let mut o = self.dst.write_buf()?;
+        // All that is queued, so small chunks from a paced source don't
+        // leave it behind
+        let mut written = 0;
+        while written < o.len() {
+            if self.pending.is_empty() {
+                self.pending = match self.rx.try_recv() {
+                    Ok(chunk) => chunk,
+                    Err(TryRecvError::Empty) if !closed => break,
+                    Err(_) if written == 0 => return Ok(BlockRet::EOF),
+                    Err(_) => break,
+                };
+            }
+            let n = (o.len() - written).min(self.pending.len());
+            o.slice()[written..written + n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            written += n;
+        }
+        if written == 0 {
+            if o.is_empty() {
+                return Ok(BlockRet::WaitForStream(&self.dst, 1));
+            }
+            return Ok(BlockRet::Pending);
+        }
+        o.produce(written, &[]);
+        Ok(BlockRet::Again)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustradio::stream::new_stream;
+
+    /// Write `n` samples into `stream`.
+    fn write(stream: &WriteStream<Complex>, n: usize) {
+        let mut o = stream.write_buf().unwrap();
+        o.fill_from_slice(&vec![Complex::new(1.0, 0.0); n]);
+        o.produce(n, &[]);
+    }
+
+    #[test]
+    fn drops_for_a_domain_behind_without_holding_up_the_others() {
+        let (tx, src) = new_stream();
+        let (behind, _never_run, _) = domain("behind", Overflow::Drop);
+        let (keeping_up, mut source, stream) = domain("keeping up", Overflow::Block);
+        // Chunks of ten samples
+        let mut fan_out = FanOut::new(src, vec![behind, keeping_up], 1_000);
+        let rounds = QUEUE_CHUNKS + 4;
+        for _ in 0..rounds {
+            write(&tx, 10);
+            assert!(matches!(fan_out.work().unwrap(), BlockRet::Again));
+            assert!(matches!(source.work().unwrap(), BlockRet::Again));
+        }
+        assert_eq!(fan_out.ports[0].dropped, 40);
+        assert_eq!(stream.read_buf().unwrap().0.len(), rounds * 10);
+    }
+
+    #[test]
+    fn holds_up_the_source_for_a_domain_that_blocks() {
+        let (tx, src) = new_stream();
+        let (behind, _never_run, _) = domain("behind", Overflow::Block);
+        let mut fan_out = FanOut::new(src, vec![behind], 1_000);
+        write(&tx, 10 * (QUEUE_CHUNKS + 1));
+        for _ in 0..QUEUE_CHUNKS {
+            assert!(matches!(fan_out.work().unwrap(), BlockRet::Again));
+        }
+        assert!(matches!(fan_out.work().unwrap(), BlockRet::Pending));
+        assert_eq!(fan_out.src.read_buf().unwrap().0.len(), 10);
+        assert_eq!(fan_out.ports[0].dropped, 0);
+    }
+
+    #[test]
+    fn ends_once_closed_and_drained() {
+        let (tx, src) = new_stream();
+        let (port, mut source, stream) = domain("draining", Overflow::Block);
+        let close = source.close_token();
+        let mut fan_out = FanOut::new(src, vec![port], 1_000);
+        write(&tx, 20);
+        for _ in 0..2 {
+            assert!(matches!(fan_out.work().unwrap(), BlockRet::Again));
+        }
+        // The fan-out lives on in the sources' graph, but sends no more
+        close.cancel();
+        assert!(matches!(source.work().unwrap(), BlockRet::Again));
+        assert_eq!(stream.read_buf().unwrap().0.len(), 20);
+        assert!(matches!(source.work().unwrap(), BlockRet::EOF));
+    }
+}
@@ -1,56 +1,390 @@
-use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use flume::Sender;
 use rustradio::Complex;
-use rustradio::blocks::{FftStream, FileSource, Map, SignalSourceComplex};
-use rustradio::graph::{Graph, GraphRunner};
-
-use super::sinks::SpectrumSink;
-use rustiq_messages::{Event, SourceConfig};
-
-/// Build the DSP graph for the engine.
-/// Returns (Graph, sample_rate_hz).
-pub fn build_graph(event_tx: Sender<Event>, source_config: SourceConfig) -> (Graph, u64) {
-    let (prev, sample_rate, mut graph) = match source_config {
-        SourceConfig::SignalGenerator {
-            sample_rate,
-            signal_freq,
-            amplitude,
-        } => {
-            let (signal_source, prev) = SignalSourceComplex::new(
-                sample_rate.as_hz() as f32,
-                signal_freq.as_hz() as f32,
-                amplitude.to_linear(),
-            );
-            let mut g = Graph::new();
-            g.add(Box::new(signal_source));
-            (prev, sample_rate.as_hz(), g)
-        }
-        SourceConfig::File { path, sample_rate } => {
-            let (file_source, prev) =
-                FileSource::<Complex>::new(path).expect("Failed to open IQ file");
-            let mut g = Graph::new();
-            g.add(Box::new(file_source));
-            (prev, sample_rate.as_hz(), g)
+
+use log::debug;
+
+use super::audio_routing::AudioRoutes;
+use super::chain::{ChainBuilder, Domain, Graphs, Pipeline, Ports, SubGraph};
+use super::dsp::{AverageSpectrum, Decimate, Nco, SpectrumAverager};
+use super::playback::{Pace, Playback};
+use super::recording::RecordingTap;
+use super::sinks::{
+    ChannelPassband, DarkFrames, ListeningChannel, SpectrumSettings, SpectrumSink, SquelchThreshold,
+};
+use super::subgraphs::{
+    BurstDetection, CarrierMeasurement, Demodulator, ImpulseCounter, MeteorDetection,
+    SymbolRateEstimation, VfoReceiver,
+};
+use super::tuner::{CenterFrequency, LoShift, Tuner};
+use super::{Overflow, SpectrumTx};
+use rustiq_messages::{
+    AudioChannel, Averaging, CalibrationPoint, Decibels, Discontinuity, Event, Hertz, MeteorConfig,
+    RustIqError, SelCallConfig, SignalRegion, SourceConfig, Vfo,
+};
+
+/// Where spectrum frames go, their size and how they are numbered.
+#[derive(Clone)]
+pub struct SpectrumOutput {
+    pub tx: SpectrumTx,
+    /// Bins in each frame
+    pub fft_size: usize,
+    /// Factor the stream is decimated by ahead of the FFT
+    pub decimation: usize,
+    /// How far off the center frequency the frames are centered, in Hz; the
+    /// stream is shifted by it ahead of the decimation
+    pub frequency_offset: i64,
+    /// Front-end response the levels are corrected by, empty if none
+    pub calibration: Vec<CalibrationPoint>,
+    /// How frames are averaged before they are sent
+    pub averaging: Averaging,
+    /// Whether frames carry the highest level of each bin so far
+    pub peak_hold: bool,
+    /// Whether frames carry the lowest level of each bin so far
+    pub min_hold: bool,
+    /// Sequence number of the next frame, carried across graph rebuilds
+    pub sequence: Arc<AtomicU64>,
+    /// Front-end artifacts subtracted from the frames
+    pub dark_frames: DarkFrames,
+}
+
+/// Optional analyses that get their own branch of the IQ stream.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Analysis {
+    /// Target frequency of the carrier measurement
+    pub carrier_target: Option<Hertz>,
+    /// Threshold above the noise floor for burst detection
+    pub burst_threshold: Option<Decibels>,
+    /// Channel and threshold of the meteor scatter detection
+    pub meteor: Option<MeteorConfig>,
+    /// Threshold above the noise floor for impulse counting
+    pub impulse_threshold: Option<Decibels>,
+    /// Channel feeding the SSTV decoder
+    pub sstv_channel: Option<AudioChannel>,
+    /// Channel and tone set for the SelCall decoder
+    pub selcall: Option<SelCallConfig>,
+    /// Channel frequency of the AIS decoder
+    pub ais_channel: Option<Hertz>,
+    /// Whether to decode ADS-B
+    pub adsb: bool,
+    /// Channel demodulated for listening
+    pub demodulator: Option<AudioChannel>,
+    /// Region to estimate the symbol rate of. A one-off: it only runs on
+    /// the next graph.
+    pub symbol_rate: Option<SignalRegion>,
+}
+
+impl Analysis {
+    /// A sub-graph for each enabled analysis, for a stream at `sample_rate`.
+    /// Decoders left out of the build are never enabled, as the engine
+    /// rejects their commands.
+    #[cfg_attr(not(feature = "adsb"), allow(unused_variables))]
+    fn sub_graphs(&self, sample_rate: u64) -> Vec<Box<dyn SubGraph>> {
+        let mut sub_graphs: Vec<Box<dyn SubGraph>> = Vec::new();
+        if let Some(target) = self.carrier_target {
+            sub_graphs.push(Box::new(CarrierMeasurement(target)));
         }
-    };
+        if let Some(threshold) = self.burst_threshold {
+            sub_graphs.push(Box::new(BurstDetection(threshold)));
+        }
+        if let Some(config) = self.meteor {
+            sub_graphs.push(Box::new(MeteorDetection(config)));
+        }
+        if let Some(threshold) = self.impulse_threshold {
+            sub_graphs.push(Box::new(ImpulseCounter(threshold)));
+        }
+        if let Some(region) = self.symbol_rate {
+            sub_graphs.push(Box::new(SymbolRateEstimation(region)));
+        }
+        if let Some(channel) = self.demodulator {
+            sub_graphs.push(Box::new(Demodulator(channel)));
+        }
+        #[cfg(feature = "sstv")]
+        if let Some(channel) = self.sstv_channel {
+            sub_graphs.push(Box::new(super::subgraphs::SstvChannel(channel)));
+        }
+        #[cfg(feature = "selcall")]
+        if let Some(config) = self.selcall {
+            sub_graphs.push(Box::new(super::subgraphs::SelCallChannel(config)));
+        }
+        #[cfg(feature = "ais")]
+        if let Some(frequency) = self.ais_channel {
+            sub_graphs.push(Box::new(super::subgraphs::AisChannel(frequency)));
+        }
+        #[cfg(feature = "adsb")]
+        if self.adsb {
+            match super::dsp::AdsbDecoder::new(sample_rate as f64) {
+                Some(decoder) => sub_graphs.push(Box::new(super::subgraphs::AdsbReceiver(decoder))),
+                None => log::warn!(
+                    "ADS-B needs a sample rate that is a multiple of 2 MHz, not {} Hz",
+                    sample_rate
+                ),
+            }
+        }
+        sub_graphs
+    }
 
-    // Create fft block
-    let fft_size = 4096;
-    let (fft, prev) = FftStream::new(prev, fft_size);
+    /// Whether an analysis listens on a channel placed by its offset from
+    /// the center frequency, which a retune in place would move.
+    pub fn has_channels(&self) -> bool {
+        self.carrier_target.is_some()
+            || self.meteor.is_some()
+            || self.symbol_rate.is_some()
+            || self.sstv_channel.is_some()
+            || self.selcall.is_some()
+            || self.ais_channel.is_some()
+            || self.demodulator.is_some()
+    }
+}
 
-    // Compute magnitude from complex FFT output
-    let (map_magnitude, prev) = Map::new(prev, "MapMagnitude", |sample, tags| {
-        (sample.norm(), Cow::Borrowed(tags))
-    });
+/// A source of the graph that couldn't be opened.
+#[derive(Debug)]
+pub struct SourceFailure {
+    /// Whether it was the second source, without which the graph can run
+    pub second: bool,
+    pub error: RustIqError,
+}
+
+/// Size of the spectrum FFT until the UI picks another.
+pub const DEFAULT_FFT_SIZE: usize = 4096;
+
+/// Largest spectrum FFT accepted, which at 65536 bins is already slow to
+/// draw.
+pub const MAX_FFT_SIZE: usize = 65_536;
+
+/// Largest decimation ahead of the spectrum FFT accepted; the anti-alias
+/// filter grows with it.
+pub const MAX_DECIMATION: usize = 256;
+
+/// What a graph is built from, as `build_graph` describes.
+pub struct GraphConfig {
+    /// Where events other than spectrum frames go
+    pub event_tx: Sender<Event>,
+    /// The main source
+    pub source_config: SourceConfig,
+    pub tuner: Tuner,
+    /// Applied to the sources ahead of every consumer
+    pub gain: Decibels,
+    pub analysis: Analysis,
+    /// Where the listening channel's audio goes
+    pub audio_routes: AudioRoutes,
+    pub squelch: SquelchThreshold,
+    pub listening: ListeningChannel,
+    pub passband: ChannelPassband,
+    /// Where the main source's spectrum goes
+    pub spectrum: SpectrumOutput,
+    /// A second source and where its spectrum goes
+    pub second: Option<(SourceConfig, SpectrumOutput)>,
+    pub recording: Option<RecordingTap>,
+    /// Receivers with their audio routes
+    pub vfos: Vec<(Vfo, AudioRoutes)>,
+    pub playback: Playback,
+    /// Where a file starts playing from
+    pub start: Duration,
+}
 
-    // Create spectrum sink
-    let spectrum_sink = SpectrumSink::new(prev, event_tx.clone(), fft_size);
+/// Build the DSP graph for the engine from `config`, its sources tuned by
+/// `tuner`.
+/// The main source fans out to domains, pipelines that each run in a graph
+/// of their own: the spectrum, the channels and the recording. A file or
+/// the generator waits for the slowest of them; a device only waits for the
+/// recording.
+/// Each analysis enabled in `analysis` is a sub-graph of the channels;
+/// the listening channel's audio goes where `audio_routes` says, muted
+/// below the `squelch` level, hops where `listening` says and is filtered
+/// through `passband`.
+/// The source is scaled by `gain` before any consumer sees it.
+/// Spectrum frames go to `spectrum`, other events to `event_tx`.
+/// A `second` source, with its own spectrum output, gets only a spectrum.
+/// A `recording` tap is fed the main source's stream.
+/// Each of the `vfos` gets its own receiver, its audio going to its routes.
+/// A file as the main source plays from `start` at the `playback` speed.
+/// Returns (Graphs, sample_rate_hz, device_sample_rate, Tuner) of the main
+/// source, the device's rate only if the stream is resampled from it; the
+/// tuner retunes both sources. Fails with the first source that doesn't
+/// open.
+pub fn build_graph(
+    config: GraphConfig,
+) -> Result<(Graphs, u64, Option<Hertz>, Tuner), SourceFailure> {
+    let GraphConfig {
+        event_tx,
+        source_config,
+        mut tuner,
+        gain,
+        analysis,
+        audio_routes,
+        squelch,
+        listening,
+        passband,
+        spectrum,
+        second,
+        recording,
+        vfos,
+        playback,
+        start,
+    } = config;
+    let mut pipeline = Pipeline::new();
+    let from_file = matches!(source_config, SourceConfig::File { .. });
+    let generated = matches!(source_config, SourceConfig::SignalGenerator { .. });
+    let mut chain = ChainBuilder::source_from(&mut pipeline, source_config, &mut tuner, start)
+        .map_err(|error| SourceFailure {
+            second: false,
+            error,
+        })?;
+    let sample_rate = chain.sample_rate();
+    if from_file {
+        let playback = playback.clone();
+        chain = chain.then(|src| Pace::new(src, sample_rate as f64, playback));
+    }
+    // Apply the source gain ahead of every consumer
+    let chain = chain.gain(gain);
 
-    // Add blocks to graph
-    graph.add(Box::new(fft));
-    graph.add(Box::new(map_magnitude));
-    graph.add(Box::new(spectrum_sink));
+    let ports = Ports {
+        event_tx,
+        center_frequency: tuner.frequency(),
+        audio_routes,
+        squelch,
+        listening,
+        passband,
+        playback: from_file.then_some(playback),
+    };
+    let vfos = vfos
+        .into_iter()
+        .map(|(vfo, routes)| Box::new(VfoReceiver(vfo, routes)) as Box<dyn SubGraph>);
+    let mut channels: Vec<Box<dyn SubGraph>> = analysis
+        .sub_graphs(sample_rate)
+        .into_iter()
+        .chain(vfos)
+        .collect();
+    // A file or the generator waits for whichever domain is slowest; a
+    // device can't, so the domains that can do without some samples drop
+    // them instead
+    let overflow = if from_file || generated {
+        Overflow::Block
+    } else {
+        Overflow::Drop
+    };
+    let center_frequency = tuner.center_frequency();
+    let mut domains = vec![Domain::new("Spectrum", overflow, |chain| {
+        add_spectrum(chain, spectrum, 0, center_frequency)
+    })];
+    if let Some(last) = channels.pop() {
+        let ports = &ports;
+        domains.push(Domain::new("Channels", overflow, move |chain| {
+            channels
+                .into_iter()
+                .fold(chain, |chain, sub_graph| chain.attach(sub_graph, ports))
+                .end_in(last, ports)
+        }));
+    }
+    // Every sample is recorded, however long the disk takes
+    if let Some(tap) = recording {
+        domains.push(Domain::new("Recording", Overflow::Block, |chain| {
+            chain.sink(|src| tap.sink(src))
+        }));
+    }
+    chain.fan_out(domains);
+
+    if let Some((second_config, second_spectrum)) = second {
+        let chain = ChainBuilder::source(&mut pipeline, second_config, &mut tuner)
+            .map_err(|error| SourceFailure {
+                second: true,
+                error,
+            })?
+            .gain(gain);
+        add_spectrum(chain, second_spectrum, 1, tuner.center_frequency());
+    }
+
+    debug!("Pipeline:\n{}", pipeline.outline());
+    let device_sample_rate = pipeline.device_sample_rate();
+    Ok((
+        pipeline.into_graph(),
+        sample_rate,
+        device_sample_rate,
+        tuner,
+    ))
+}
+
+/// End `chain` in the spectrum of source number `source`.
+fn add_spectrum(
+    chain: ChainBuilder<'_, Complex>,
+    spectrum: SpectrumOutput,
+    source: usize,
+    center_frequency: CenterFrequency,
+) {
+    let mut chain = chain;
+    if spectrum.frequency_offset != 0 {
+        // Moves the offset down to DC, for the decimation to keep
+        let mixer = Nco::new(chain.sample_rate() as f64, spectrum.frequency_offset as f64);
+        chain = chain.then(|src| LoShift::new(src, mixer));
+    }
+    if spectrum.decimation > 1 {
+        chain = chain.then(|src| Decimate::new(src, spectrum.decimation));
+    }
+    let sample_rate = chain.sample_rate() as f64 / spectrum.decimation as f64;
+    // Frames from an earlier graph mean this one restarts the stream
+    let restart = (spectrum.sequence.load(Ordering::Relaxed) > 0).then_some(Discontinuity::Restart);
+    let mut chain = chain
+        .window(spectrum.fft_size)
+        .fft(spectrum.fft_size)
+        .magnitude();
+    let mut stride = 1;
+    if spectrum.averaging != Averaging::Off {
+        let averager = SpectrumAverager::new(spectrum.averaging, spectrum.fft_size);
+        stride = averager.stride();
+        let center_frequency = center_frequency.clone();
+        chain = chain
+            .then(|src| AverageSpectrum::new(src, averager, spectrum.fft_size, center_frequency));
+    }
+    chain.sink(|src| {
+        SpectrumSink::new(
+            src,
+            spectrum.tx,
+            SpectrumSettings {
+                fft_size: spectrum.fft_size,
+                stride,
+                sample_rate,
+                correction: correction(
+                    &spectrum.calibration,
+                    spectrum.fft_size,
+                    sample_rate,
+                    spectrum.frequency_offset,
+                ),
+                center_frequency,
+                frequency_offset: spectrum.frequency_offset,
+                source,
+                sequence: spectrum.sequence,
+                peak_hold: spectrum.peak_hold.then(Vec::new),
+                min_hold: spectrum.min_hold.then(Vec::new),
+                discontinuity: restart,
+                dark_frames: spectrum.dark_frames,
+            },
+        )
+    });
+}
 
-    (graph, sample_rate)
+/// Factor each bin of an FFT-shifted frame is scaled by, undoing the gain
+/// `calibration` gives at the bin's offset from the center; the frame is
+/// centered `frequency_offset` off it. Empty without a calibration, leaving
+/// the frames alone.
+fn correction(
+    calibration: &[CalibrationPoint],
+    fft_size: usize,
+    sample_rate: f64,
+    frequency_offset: i64,
+) -> Vec<f32> {
+    if calibration.is_empty() {
+        return Vec::new();
+    }
+    (0..fft_size)
+        .map(|bin| {
+            let offset = (bin as f64 - (fft_size / 2) as f64) * sample_rate / fft_size as f64
+                + frequency_offset as f64;
+            let gain = CalibrationPoint::gain_at(calibration, offset);
+            10f32.powf(-gain.0 / 20.0)
+        })
+        .collect()
 }
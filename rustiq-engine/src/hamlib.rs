@@ -0,0 +1,67 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long to wait for a daemon to accept a connection or answer.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A connection to a hamlib network daemon (`rigctld` or `rotctld`), using
+/// the default (non-extended) response protocol.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn open(address: &str) -> io::Result<Self> {
+        let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{address} did not resolve"),
+            )
+        })?;
+        let writer = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        writer.set_read_timeout(Some(TIMEOUT))?;
+        Ok(Self {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+        })
+    }
+
+    /// Send a set command, returning the daemon's result code (negative
+    /// hamlib error codes on failure).
+    pub fn set(&mut self, command: &str) -> io::Result<i32> {
+        writeln!(self.writer, "{command}")?;
+        let reply = self.read_line()?;
+        reply
+            .strip_prefix("RPRT ")
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| unexpected(&reply))
+    }
+
+    /// Send a get command, returning its reply lines.
+    pub fn get(&mut self, command: &str, lines: usize) -> io::Result<Vec<String>> {
+        writeln!(self.writer, "{command}")?;
+        (0..lines).map(|_| self.read_line()).collect()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line.trim().to_string())
+    }
+}
+
+/// Parse one reply line, e.g. a frequency or an angle.
+pub fn parse<T: std::str::FromStr>(reply: &str) -> io::Result<T> {
+    reply.parse().map_err(|_| unexpected(reply))
+}
+
+fn unexpected(reply: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected reply {reply:?}"),
+    )
+}
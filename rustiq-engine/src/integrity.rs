@@ -0,0 +1,216 @@
+//! Checksums of a recording's data, for telling later that a capture is
+//! complete and unaltered. The data is hashed in fixed-size chunks, each
+//! chunk's SHA-256 appended to a manifest beside the data as soon as the
+//! chunk is written, so damage can be placed within a recording and a
+//! capture cut short by a crash still checks up to where it stopped.
+//!
+//! The manifest is text:
+//!
+//! ```text
+//! rustiq-manifest 1
+//! chunk_bytes 8388608
+//! chunk 0 <sha256>
+//! chunk 1 <sha256>
+//! total_bytes 12582912
+//! ```
+//!
+//! The last chunk may be short, and `total_bytes` is only written once the
+//! recording has finished.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Bytes hashed per chunk: a million `cf32` samples.
+pub const CHUNK_BYTES: u64 = 8 << 20;
+
+const HEADER: &str = "rustiq-manifest 1";
+
+/// Where the manifest of the data file at `data_path` goes.
+pub fn manifest_path(data_path: &Path) -> PathBuf {
+    data_path.with_extension("sigmf-sha256")
+}
+
+/// Writes the manifest of a recording as its data is written.
+pub struct Checksummer {
+    manifest: File,
+    chunk_bytes: u64,
+    hasher: Sha256,
+    /// Bytes in the current chunk
+    in_chunk: u64,
+    chunks: u64,
+    total: u64,
+}
+
+impl Checksummer {
+    pub fn create(manifest_path: &Path, chunk_bytes: u64) -> io::Result<Self> {
+        let mut manifest = File::create(manifest_path)?;
+        writeln!(manifest, "{HEADER}\nchunk_bytes {chunk_bytes}")?;
+        Ok(Self {
+            manifest,
+            chunk_bytes,
+            hasher: Sha256::new(),
+            in_chunk: 0,
+            chunks: 0,
+            total: 0,
+        })
+    }
+
+    /// Hash `bytes` just written to the data, recording each chunk they
+    /// complete.
+    pub fn update(&mut self, mut bytes: &[u8]) -> io::Result<()> {
+        while !bytes.is_empty() {
+            let n = bytes
+                .len()
+                .min(usize::try_from(self.chunk_bytes - self.in_chunk).unwrap_or(usize::MAX));
+            self.hasher.update(&bytes[..n]);
+            self.in_chunk += n as u64;
+            self.total += n as u64;
+            bytes = &bytes[n..];
+            if self.in_chunk == self.chunk_bytes {
+                self.end_chunk()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the short last chunk, if any, and the length of the data.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.in_chunk > 0 {
+            self.end_chunk()?;
+        }
+        writeln!(self.manifest, "total_bytes {}", self.total)?;
+        self.manifest.sync_all()
+    }
+
+    fn end_chunk(&mut self) -> io::Result<()> {
+        let digest = std::mem::take(&mut self.hasher).finalize();
+        // Written unbuffered, like the data, so a crash keeps every chunk
+        writeln!(self.manifest, "chunk {} {}", self.chunks, hex(&digest))?;
+        self.chunks += 1;
+        self.in_chunk = 0;
+        Ok(())
+    }
+}
+
+/// Result of checking a recording's data against its manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    /// Bytes per chunk, from the manifest
+    pub chunk_bytes: u64,
+    /// Chunks whose data matches its checksum
+    pub good_chunks: u64,
+    /// Indices of the chunks whose data doesn't
+    pub corrupt_chunks: Vec<u64>,
+    /// Chunks in the manifest the data ends before, as when it is truncated
+    pub missing_chunks: u64,
+    /// Bytes of data the manifest doesn't cover
+    pub unchecked_bytes: u64,
+    /// Whether the recorder finished the manifest, rather than being cut
+    /// short by a crash
+    pub finished: bool,
+}
+
+impl Verification {
+    /// The data is all there, unaltered and finished.
+    pub fn passed(&self) -> bool {
+        self.corrupt_chunks.is_empty()
+            && self.missing_chunks == 0
+            && self.unchecked_bytes == 0
+            && self.finished
+    }
+}
+
+/// Check the data file at `data_path` against its manifest.
+pub fn verify(data_path: &Path) -> io::Result<Verification> {
+    let manifest = read_manifest(&manifest_path(data_path))?;
+    let mut data = BufReader::new(File::open(data_path)?);
+    let mut verification = Verification {
+        chunk_bytes: manifest.chunk_bytes,
+        good_chunks: 0,
+        corrupt_chunks: Vec::new(),
+        missing_chunks: 0,
+        unchecked_bytes: 0,
+        finished: manifest.total_bytes.is_some(),
+    };
+
+    let last = manifest.chunks.len().saturating_sub(1);
+    for (index, checksum) in manifest.chunks.iter().enumerate() {
+        // Only the last chunk of a finished recording may be short
+        let expected = match manifest.total_bytes {
+            Some(total) if index == last => {
+                total.saturating_sub(index as u64 * manifest.chunk_bytes)
+            }
+            _ => manifest.chunk_bytes,
+        };
+        let mut hasher = Sha256::new();
+        let read = io::copy(&mut (&mut data).take(expected), &mut hasher)?;
+        if read < expected {
+            verification.missing_chunks = (manifest.chunks.len() - index) as u64;
+            return Ok(verification);
+        }
+        if hex(&hasher.finalize()) == *checksum {
+            verification.good_chunks += 1;
+        } else {
+            verification.corrupt_chunks.push(index as u64);
+        }
+    }
+    verification.unchecked_bytes = io::copy(&mut data, &mut io::sink())?;
+    Ok(verification)
+}
+
+struct Manifest {
+    chunk_bytes: u64,
+    /// Checksum of each chunk, in hex
+    chunks: Vec<String>,
+    total_bytes: Option<u64>,
+}
+
+fn read_manifest(path: &Path) -> io::Result<Manifest> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected line in manifest: {line:?}"),
+        )
+    };
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    // A line cut off by a crash while it was written doesn't count
+    let complete = text.rfind('\n').map_or("", |end| &text[..=end]);
+    let mut lines = complete.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a RustIQ recording manifest",
+        ));
+    }
+    let mut manifest = Manifest {
+        chunk_bytes: 0,
+        chunks: Vec::new(),
+        total_bytes: None,
+    };
+    for line in lines {
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["chunk_bytes", bytes] => {
+                manifest.chunk_bytes = bytes.parse().map_err(|_| invalid(line))?;
+            }
+            ["chunk", index, checksum] if index.parse() == Ok(manifest.chunks.len()) => {
+                manifest.chunks.push(checksum.to_string());
+            }
+            ["total_bytes", bytes] => {
+                manifest.total_bytes = Some(bytes.parse().map_err(|_| invalid(line))?);
+            }
+            _ => return Err(invalid(line)),
+        }
+    }
+    if manifest.chunk_bytes == 0 {
+        return Err(invalid("no chunk_bytes"));
+    }
+    Ok(manifest)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,113 @@
+//! Session journal for crash recovery. The journal holds the latest
+//! `SessionRecord` in wire format and is locked while its session runs; one
+//! left behind unlocked belongs to a session that didn't exit cleanly.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use rustiq_messages::{SessionRecord, read_frame, write_frame};
+
+/// Size of one `cf32_le` sample in a recording's data file.
+const SAMPLE_BYTES: u64 = 8;
+
+/// The journal of the running session.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Start journaling to `path`, replacing a previous session's journal.
+    /// Fails if another running session holds the journal.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ResourceBusy,
+                    format!("{} is in use by another session", path.display()),
+                ));
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+        file.set_len(0)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Replace the journaled record, synced so it survives a power loss.
+    pub fn write(&mut self, record: &SessionRecord) -> io::Result<()> {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, record)?;
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.file.write_all(&frame)?;
+        self.file.sync_data()
+    }
+
+    /// End the session cleanly, removing the journal.
+    pub fn close(self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Failed to remove journal {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// The record of a previous session that didn't exit cleanly, if any.
+/// A recording it left in progress is finalized first.
+pub fn recover(path: &Path) -> io::Result<Option<SessionRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match file.try_lock_shared() {
+        Ok(()) => {}
+        // Still running
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => return Err(e),
+    }
+    let record: SessionRecord = match read_frame(&mut io::BufReader::new(&file)) {
+        Ok(record) => record,
+        // Cut off while the journal was rewritten
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if let Some(recording) = &record.recording {
+        match finalize_recording(recording) {
+            Ok(samples) => info!(
+                "Finalized interrupted recording {} ({} samples)",
+                recording.display(),
+                samples
+            ),
+            Err(e) => warn!(
+                "Failed to finalize recording {}: {}",
+                recording.display(),
+                e
+            ),
+        }
+    }
+    Ok(Some(record))
+}
+
+/// Close out a recording's data file after an interruption by dropping a
+/// trailing partial sample. Returns the number of whole samples kept.
+pub fn finalize_recording(data_path: &Path) -> io::Result<u64> {
+    let file = OpenOptions::new().write(true).open(data_path)?;
+    let len = file.metadata()?.len();
+    let samples = len / SAMPLE_BYTES;
+    if len % SAMPLE_BYTES != 0 {
+        file.set_len(samples * SAMPLE_BYTES)?;
+    }
+    file.sync_all()?;
+    Ok(samples)
+}
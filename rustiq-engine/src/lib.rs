@@ -1,13 +1,198 @@
+pub mod analysis;
+#[cfg(feature = "antenna-switch")]
+mod antenna;
+mod audio_routing;
+mod chain;
+mod config;
+mod diagnostics;
+mod dsp;
+mod fanout;
 mod graph;
+#[cfg(any(feature = "rig", feature = "rotator"))]
+mod hamlib;
+pub mod integrity;
+pub mod journal;
+mod playback;
+pub mod recording;
+#[cfg(feature = "rig")]
+mod rig;
+#[cfg(feature = "rotator")]
+mod rotator;
+mod scanner;
 mod sinks;
+#[cfg(feature = "soapysdr")]
+mod soapy;
+mod sources;
+mod spectrum_channel;
+mod subgraphs;
+mod tuner;
+mod workers;
 
-use anyhow::Result;
-use flume::{Receiver, Sender};
-use log::debug;
-use rustiq_messages::{Command, EngineState, Event, Hertz, SourceConfig};
-use rustradio::graph::{CancellationToken, GraphRunner};
+pub use config::{EngineConfig, Overflow, ResourceLimits};
+pub use spectrum_channel::SpectrumTx;
+
+use flume::{Receiver, SendError, Sender};
+use log::{debug, info, warn};
+#[cfg(feature = "rig")]
+use rustiq_messages::RigConfig;
+use rustiq_messages::{
+    AntennaRule, AntennaSwitchConfig, Averaging, Capabilities, Command, DarkFrameConfig, Decibels,
+    DemodMode, EngineState, Event, Feature, GainProfile, Hertz, IqFormat, Resource, RustIqError,
+    ScanHit, SessionRecord, SourceCapability, SourceConfig, SourceKind, Vfo,
+};
+use rustradio::graph::CancellationToken;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Bins in each row of a file's overview.
+const OVERVIEW_FFT_SIZE: usize = 512;
+
+/// Most rows in a file's overview; longer files are averaged to fit.
+const OVERVIEW_ROWS: usize = 1024;
+
+/// A dark frame capture that gets no frames for this long, e.g. as the
+/// source failed, is given up on so the antenna is switched back.
+const DARK_FRAME_STALL: Duration = Duration::from_secs(10);
+
+/// How often the flow of spectrum frames is reported.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the position of a file source is reported while it plays.
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Stands in for the antenna switch drivers when they are left out of the
+/// build. The engine never has a switch configured then.
+#[cfg(not(feature = "antenna-switch"))]
+mod antenna {
+    use rustiq_messages::AntennaSwitchConfig;
+
+    pub fn select(_config: &AntennaSwitchConfig, _index: usize) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Whether `feature` was compiled into this build. The analyses are always
+/// there; decoders and hardware control each have a cargo feature.
+pub fn compiled_in(feature: Feature) -> bool {
+    match feature {
+        Feature::CarrierMeasurement
+        | Feature::BurstDetection
+        | Feature::MeteorDetection
+        | Feature::ImpulseCounter
+        | Feature::SymbolRateEstimation => true,
+        Feature::SstvDecoder => cfg!(feature = "sstv"),
+        Feature::SelCallDecoder => cfg!(feature = "selcall"),
+        Feature::AisDecoder => cfg!(feature = "ais"),
+        Feature::AdsbDecoder => cfg!(feature = "adsb"),
+        Feature::AntennaSwitch => cfg!(feature = "antenna-switch"),
+        Feature::Rig => cfg!(feature = "rig"),
+        Feature::Rotator => cfg!(feature = "rotator"),
+    }
+}
+
+/// Whether sources of `kind` were compiled into this build. Hardware
+/// sources each have a cargo feature, as they need the device's library.
+fn source_compiled_in(kind: SourceKind) -> bool {
+    match kind {
+        SourceKind::SignalGenerator
+        | SourceKind::File
+        | SourceKind::RtlTcp
+        | SourceKind::SpyServer => true,
+        SourceKind::RtlSdr => cfg!(feature = "rtlsdr"),
+        SourceKind::SoapySdr => cfg!(feature = "soapysdr"),
+    }
+}
+
+/// The feature a command needs, if it needs one.
+/// The UI's end of the event channel was dropped.
+fn ui_gone(_: SendError<Event>) -> RustIqError {
+    RustIqError::Protocol {
+        peer: "UI".to_string(),
+        detail: "stopped taking events".to_string(),
+    }
+}
+
+fn required_feature(command: &Command) -> Option<Feature> {
+    match command {
+        Command::StartSstvDecoder(_) | Command::StopSstvDecoder => Some(Feature::SstvDecoder),
+        Command::StartSelCallDecoder(_) | Command::StopSelCallDecoder => {
+            Some(Feature::SelCallDecoder)
+        }
+        Command::StartAisDecoder(_) | Command::StopAisDecoder => Some(Feature::AisDecoder),
+        Command::StartAdsbDecoder | Command::StopAdsbDecoder => Some(Feature::AdsbDecoder),
+        Command::SetAntennaSwitch(_) | Command::SelectAntenna(_) | Command::SetDarkFrame(_) => {
+            Some(Feature::AntennaSwitch)
+        }
+        Command::ConnectRig(_) | Command::DisconnectRig | Command::TuneRig(_) => Some(Feature::Rig),
+        Command::ConnectRotator(_) | Command::DisconnectRotator | Command::PointRotator(_) => {
+            Some(Feature::Rotator)
+        }
+        _ => None,
+    }
+}
+
+/// What this engine build supports.
+pub fn capabilities() -> Capabilities {
+    let features: Vec<Feature> = Feature::ALL
+        .into_iter()
+        .filter(|&feature| compiled_in(feature))
+        .collect();
+    // Both run as fast as the graph consumes them
+    let mut sources = vec![
+        SourceCapability {
+            kind: SourceKind::SignalGenerator,
+            max_sample_rate: None,
+            sample_rates: Vec::new(),
+            devices: Vec::new(),
+        },
+        SourceCapability {
+            kind: SourceKind::File,
+            max_sample_rate: None,
+            sample_rates: Vec::new(),
+            devices: Vec::new(),
+        },
+    ];
+    if source_compiled_in(SourceKind::RtlSdr) {
+        sources.push(SourceCapability {
+            kind: SourceKind::RtlSdr,
+            max_sample_rate: Some(Hertz(3_200_000)),
+            sample_rates: sources::RTL_SDR_RATES.to_vec(),
+            devices: Vec::new(),
+        });
+    }
+    // A remote dongle, so nothing to build in
+    sources.push(SourceCapability {
+        kind: SourceKind::RtlTcp,
+        max_sample_rate: Some(Hertz(3_200_000)),
+        sample_rates: sources::RTL_SDR_RATES.to_vec(),
+        devices: Vec::new(),
+    });
+    // The server's device sets the limit; an Airspy's is 10 MHz
+    sources.push(SourceCapability {
+        kind: SourceKind::SpyServer,
+        max_sample_rate: None,
+        sample_rates: Vec::new(),
+        devices: Vec::new(),
+    });
+    // Rates vary too much between devices to give one limit
+    #[cfg(feature = "soapysdr")]
+    sources.push(SourceCapability {
+        kind: SourceKind::SoapySdr,
+        max_sample_rate: None,
+        sample_rates: Vec::new(),
+        devices: soapy::devices(),
+    });
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        sources,
+        // The demodulator for listening is always built
+        demod_modes: DemodMode::ALL.to_vec(),
+        features,
+    }
+}
 
 /// The SDR engine backend.
 /// Owns the rustradio graph and processes commands from the UI.
@@ -15,6 +200,89 @@ pub struct Engine {
     cmd_rx: Receiver<Command>,
     event_tx: Sender<Event>,
     current_config: SourceConfig,
+    /// Source that last opened, to fall back on if another fails to
+    working_config: Option<SourceConfig>,
+    /// Source shown next to the main one
+    second_config: Option<SourceConfig>,
+    center_frequency: Hertz,
+    gain: Decibels,
+    gain_profiles: Vec<GainProfile>,
+    /// Tune hardware sources off the center to keep their DC spike out of it
+    offset_tuning: bool,
+    antenna_switch: Option<AntennaSwitchConfig>,
+    /// Antenna last selected successfully
+    antenna: Option<usize>,
+    /// Schedule dark frames are captured on
+    dark_frame: Option<DarkFrameConfig>,
+    /// When the next dark frame is due
+    next_dark_frame: Instant,
+    /// Whether a dark frame is being captured, with the input terminated
+    capturing_dark_frame: bool,
+    /// When the flow of spectrum frames was last reported, and the frames
+    /// sent by then
+    last_stats: (Instant, u64),
+    /// Transceiver the center frequency follows
+    #[cfg(feature = "rig")]
+    rig: Option<(RigConfig, rig::Rig)>,
+    /// Connected rotator and its address
+    #[cfg(feature = "rotator")]
+    rotator: Option<(String, rotator::Rotator)>,
+    /// Analyses to run alongside the spectrum
+    analysis: graph::Analysis,
+    /// Where the listening channel's audio goes, carried across graphs
+    audio_routes: audio_routing::AudioRoutes,
+    /// Level the listening channel is squelched at
+    squelch: sinks::SquelchThreshold,
+    /// Where the scan has hopped the listening channel to
+    listening: sinks::ListeningChannel,
+    /// Passband the listening channel is filtered through, if not its
+    /// mode's
+    passband: sinks::ChannelPassband,
+    /// VFOs demodulating other channels, with where their audio goes,
+    /// carried across graphs
+    vfos: Vec<(Vfo, audio_routing::AudioRoutes)>,
+    /// Id the next VFO added gets
+    next_vfo: u32,
+    /// Scan stepping the listening channel, if one is running
+    scanner: Option<scanner::Scanner>,
+    /// Channel the scan is on and whether it is active, as last told to
+    /// the UI
+    scan_status: Option<(usize, bool)>,
+    /// How a file source plays, shared with the graphs
+    playback: playback::Playback,
+    /// How far into a file source the running graph started playing it
+    playback_start: Duration,
+    /// Where a file source plays from once the graph is rebuilt, if not on
+    /// from where it had got to
+    next_start: Option<Duration>,
+    /// When the position of a file source was last reported, and what it
+    /// was
+    last_position: Option<(Instant, Duration)>,
+    /// Rate the source's hardware runs at, when resampled to another
+    device_sample_rate: Option<Hertz>,
+    /// File the overview was last worked out for, and its format
+    overview_of: Option<(PathBuf, IqFormat)>,
+    /// Where to journal the session for crash recovery
+    journal_path: Option<PathBuf>,
+    journal: Option<journal::Journal>,
+    /// Session that crashed, to offer for restoring with the first snapshot
+    previous_session: Option<SessionRecord>,
+    /// Where spectrum frames go, by default along with the other events
+    spectrum: graph::SpectrumOutput,
+    /// Sequence number of the second source's next spectrum frame
+    second_sequence: Arc<AtomicU64>,
+    /// Capabilities yet to be announced to the UI
+    capabilities: Option<Capabilities>,
+    /// Recording of the main source's stream, carried across graphs
+    recording: Option<recording::LiveRecording>,
+    /// End the recording along with the current graph
+    stop_recording: bool,
+    /// Largest FFT size within the memory limit
+    max_fft_size: usize,
+    /// Bytes a recording may take on disk
+    max_recording_bytes: Option<u64>,
+    /// Threads for work in the background
+    workers: workers::Workers,
     should_exit: bool,
 }
 
@@ -28,52 +296,590 @@ impl Engine {
         debug!("Constructing a new engine");
         Self {
             cmd_rx,
-            event_tx,
+            event_tx: event_tx.clone(),
             current_config: source_config,
+            working_config: None,
+            second_config: None,
+            center_frequency: Hertz(0),
+            gain: Decibels(0.0),
+            gain_profiles: Vec::new(),
+            offset_tuning: false,
+            antenna_switch: None,
+            antenna: None,
+            dark_frame: None,
+            next_dark_frame: Instant::now(),
+            capturing_dark_frame: false,
+            last_stats: (Instant::now(), 0),
+            #[cfg(feature = "rig")]
+            rig: None,
+            #[cfg(feature = "rotator")]
+            rotator: None,
+            analysis: graph::Analysis::default(),
+            audio_routes: audio_routing::AudioRoutes::default(),
+            squelch: sinks::SquelchThreshold::default(),
+            listening: sinks::ListeningChannel::default(),
+            passband: sinks::ChannelPassband::default(),
+            vfos: Vec::new(),
+            next_vfo: 1,
+            scanner: None,
+            scan_status: None,
+            playback: playback::Playback::default(),
+            playback_start: Duration::ZERO,
+            next_start: None,
+            last_position: None,
+            device_sample_rate: None,
+            overview_of: None,
+            journal_path: None,
+            journal: None,
+            previous_session: None,
+            spectrum: graph::SpectrumOutput {
+                tx: SpectrumTx::shared(event_tx, Overflow::Drop),
+                fft_size: graph::DEFAULT_FFT_SIZE,
+                decimation: 1,
+                frequency_offset: 0,
+                calibration: Vec::new(),
+                averaging: Averaging::Off,
+                peak_hold: false,
+                min_hold: false,
+                sequence: Arc::new(AtomicU64::new(0)),
+                dark_frames: sinks::DarkFrames::default(),
+            },
+            second_sequence: Arc::new(AtomicU64::new(0)),
+            capabilities: Some(capabilities()),
+            recording: None,
+            stop_recording: false,
+            max_fft_size: graph::MAX_FFT_SIZE,
+            max_recording_bytes: None,
+            workers: workers::Workers::default(),
             should_exit: false,
         }
     }
 
+    /// Journal the session to `path`, and offer to restore the session
+    /// journaled there if it didn't exit cleanly.
+    pub fn with_journal(mut self, path: PathBuf) -> Self {
+        self.journal_path = Some(path);
+        self
+    }
+
+    /// Send spectrum frames on their own channel.
+    pub fn with_spectrum_channel(mut self, spectrum_tx: SpectrumTx) -> Self {
+        self.spectrum.tx = spectrum_tx;
+        self
+    }
+
+    /// Hold the engine to the resource limits of `config`, counting the
+    /// spectrum frames its channel queues toward the memory limit.
+    pub fn with_limits(mut self, config: &EngineConfig) -> Self {
+        if let Some(max_fft_size) = config.max_fft_size() {
+            self.max_fft_size = max_fft_size.min(graph::MAX_FFT_SIZE);
+            self.spectrum.fft_size = self.spectrum.fft_size.min(self.max_fft_size);
+        }
+        self.max_recording_bytes = config.limits.disk;
+        self.workers = workers::Workers::new(config.limits.threads);
+        self
+    }
+
     /// Run the engine (blocking).
     /// Runs in a loop that can restart the DSP graph when source changes.
-    pub fn run(mut self) -> Result<()> {
+    /// Fails only if the UI goes away without stopping it; what goes wrong
+    /// meanwhile is reported to the UI as `Event::Error`.
+    pub fn run(mut self) -> Result<(), RustIqError> {
+        if let Some(path) = &self.journal_path {
+            match journal::recover(path) {
+                Ok(previous) => self.previous_session = previous,
+                Err(e) => warn!("Failed to read journal {}: {}", path.display(), e),
+            }
+            match journal::Journal::create(path) {
+                Ok(journal) => self.journal = Some(journal),
+                Err(e) => warn!("Not journaling the session: {}", e),
+            }
+        }
+
         while !self.should_exit {
             self.run_graph_iteration()?;
         }
+
+        // Only a clean exit removes the journal
+        if let Some(journal) = self.journal.take() {
+            journal.close();
+        }
         Ok(())
     }
 
-    fn run_graph_iteration(&mut self) -> Result<()> {
-        let (graph, sample_rate_hz) =
-            graph::build_graph(self.event_tx.clone(), self.current_config.clone());
-        let cancel_token = graph.cancel_token();
-
-        let state = EngineState {
-            center_frequency: Hertz(0),
-            sample_rate: Hertz(sample_rate_hz),
-            fft_size: 4096,
+    fn run_graph_iteration(&mut self) -> Result<(), RustIqError> {
+        let tap = self.recording_tap();
+        // The demodulator is built on the channel the scan is on
+        self.listening.reset();
+        let built = graph::build_graph(graph::GraphConfig {
+            event_tx: self.event_tx.clone(),
             source_config: self.current_config.clone(),
+            tuner: tuner::Tuner::new(self.center_frequency).with_offset_tuning(self.offset_tuning),
+            gain: self.gain,
+            analysis: self.analysis,
+            audio_routes: self.audio_routes.clone(),
+            squelch: self.squelch.clone(),
+            listening: self.listening.clone(),
+            passband: self.passband.clone(),
+            spectrum: self.spectrum.clone(),
+            second: self.second_config.clone().map(|config| {
+                // The calibration is the main source's front end's
+                let spectrum = graph::SpectrumOutput {
+                    calibration: Vec::new(),
+                    sequence: self.second_sequence.clone(),
+                    dark_frames: sinks::DarkFrames::default(),
+                    ..self.spectrum.clone()
+                };
+                (config, spectrum)
+            }),
+            recording: tap,
+            vfos: self.vfos.clone(),
+            playback: self.playback.clone(),
+            start: self.playback_start,
+        });
+        let (graph, sample_rate_hz, device_sample_rate, mut tuner) = match built {
+            Ok(built) => built,
+            Err(failure) => {
+                self.report(failure.error);
+                // Carry on without the source that failed, for the user to
+                // pick another
+                if failure.second {
+                    self.second_config = None;
+                } else {
+                    // Taken, so a source that stopped opening since
+                    // doesn't fail over and over
+                    self.current_config = self.working_config.take().unwrap_or_default();
+                    self.playback_start = Duration::ZERO;
+                }
+                return Ok(());
+            }
         };
-        self.event_tx.send(Event::StateSnapshot(state))?;
+        let cancel_token = graph.cancel_token();
+        self.device_sample_rate = device_sample_rate;
+        if let Some(rate) = device_sample_rate {
+            info!(
+                "The source runs at {} Hz; resampling to {} Hz",
+                rate.as_hz(),
+                sample_rate_hz
+            );
+        }
+        self.analysis.symbol_rate = None;
+        let sample_rate = Hertz(sample_rate_hz);
+        // The file's own rate, as from a WAV header, or the rate a server
+        // settled on, shows in the UI
+        if let SourceConfig::File {
+            sample_rate: source_rate,
+            ..
+        }
+        | SourceConfig::SpyServer {
+            sample_rate: source_rate,
+            ..
+        } = &mut self.current_config
+        {
+            *source_rate = sample_rate;
+        }
+        self.working_config = Some(self.current_config.clone());
+
+        self.event_tx
+            .send(Event::StateSnapshot(Box::new(self.state(sample_rate))))
+            .map_err(ui_gone)?;
+        if let Some(capabilities) = self.capabilities.take() {
+            self.event_tx
+                .send(Event::Capabilities(capabilities))
+                .map_err(ui_gone)?;
+        }
+        if let Some(previous) = self.previous_session.take() {
+            self.event_tx
+                .send(Event::PreviousSession(previous))
+                .map_err(ui_gone)?;
+        }
+        self.journal_session();
+        self.send_overview(sample_rate);
+        // Reported straight away from where the new graph plays
+        self.last_position = None;
 
         let mut graph = graph;
         let graph_handle = thread::spawn(move || graph.run());
 
-        self.process_commands(&cancel_token, &graph_handle);
+        self.process_commands(&cancel_token, &graph_handle, &mut tuner, sample_rate);
 
         let _ = graph_handle.join();
+        // A rebuild carries a paced file on from where it had got to; one
+        // read as fast as it can be is far ahead of what was made of it, so
+        // plays again from where it started
+        let position = self.playback_position(sample_rate);
+        self.playback.take_played();
+        self.playback_start = self
+            .next_start
+            .take()
+            .unwrap_or(match self.playback.speed() {
+                Some(_) => position,
+                None => self.playback_start,
+            });
+        if self.should_exit
+            || self.stop_recording
+            || self
+                .recording
+                .as_ref()
+                .is_some_and(|recording| *recording.source_config() != self.current_config)
+        {
+            self.finish_recording();
+        }
         Ok(())
     }
 
+    /// Move the scan on if it is time to, hopping the listening channel in
+    /// the running graph, and tell the UI where it is.
+    fn step_scan(&mut self, tuner: &tuner::Tuner) {
+        let Some(scanner) = &mut self.scanner else {
+            return;
+        };
+        if scanner
+            .tick(Instant::now(), self.listening.open())
+            .is_some()
+        {
+            if let Some((index, duration)) = scanner.take_hit() {
+                let stats = self.listening.stats();
+                let scan_channel = &scanner.list().channels[index];
+                let hit = ScanHit {
+                    channel: index,
+                    name: scan_channel.name.clone(),
+                    frequency: scan_channel.channel.frequency,
+                    demod: scan_channel.channel.demod,
+                    duration,
+                    level: stats.level(),
+                    class: stats.class(),
+                };
+                info!("Heard {} for {:?}", hit.name, hit.duration);
+                let _ = self.event_tx.send(Event::ScanHit(hit));
+            }
+            let channel = scanner.channel().channel;
+            debug!("Scanning {}", scanner.channel().name);
+            let offset = channel.frequency.as_hz() as f64 - tuner.frequency().as_hz() as f64;
+            self.listening.hop(offset, channel.demod);
+            self.analysis.demodulator = Some(channel);
+        }
+        let status = (scanner.at(), scanner.is_active());
+        if self.scan_status != Some(status) {
+            self.scan_status = Some(status);
+            let _ = self.event_tx.send(Event::Scanning {
+                channel: status.0,
+                active: status.1,
+            });
+        }
+    }
+
+    /// Report how far a file source has played if it is time to and it has
+    /// moved since.
+    fn step_position(&mut self, sample_rate: Hertz) {
+        if !matches!(self.current_config, SourceConfig::File { .. }) {
+            return;
+        }
+        let position = self.playback_position(sample_rate);
+        if let Some((at, reported)) = self.last_position
+            && (at.elapsed() < POSITION_INTERVAL || reported == position)
+        {
+            return;
+        }
+        self.last_position = Some((Instant::now(), position));
+        let _ = self.event_tx.try_send(Event::PlaybackPosition(position));
+    }
+
+    /// How far into a file source the running graph, at `sample_rate`, has
+    /// played it.
+    fn playback_position(&self, sample_rate: Hertz) -> Duration {
+        let played = self.playback.played() as f64 / sample_rate.as_hz().max(1) as f64;
+        self.playback_start + Duration::from_secs_f64(played)
+    }
+
+    /// Report the flow of spectrum frames to the UI if it is time to.
+    fn step_stats(&mut self) {
+        let (at, sent_then) = self.last_stats;
+        let elapsed = at.elapsed();
+        if elapsed < STATS_INTERVAL {
+            return;
+        }
+        let (sent, dropped) = self.spectrum.tx.counts();
+        self.last_stats = (Instant::now(), sent);
+        let fps = (sent - sent_then) as f64 / elapsed.as_secs_f64();
+        // Another comes soon, so one that doesn't fit isn't missed
+        let _ = self.event_tx.try_send(Event::Stats {
+            frames_dropped: dropped,
+            fps: fps as f32,
+        });
+    }
+
+    /// Capture a dark frame if one is due, or the last no longer fits the
+    /// spectrum, switching the input to the termination while it is taken
+    /// and back once it is in.
+    fn step_dark_frame(&mut self) {
+        let Some(config) = self.dark_frame else {
+            return;
+        };
+        let dark_frames = &self.spectrum.dark_frames;
+        if self.capturing_dark_frame {
+            if let Some(level) = dark_frames.take_captured() {
+                info!("Captured a dark frame at {:.1} dB", level.0);
+                let _ = self.event_tx.send(Event::DarkFrameCaptured(level));
+            } else if dark_frames.stalled(DARK_FRAME_STALL) {
+                warn!("Giving up on the dark frame, no spectrum frames came");
+                dark_frames.cancel();
+            } else {
+                return;
+            }
+            self.capturing_dark_frame = false;
+            self.next_dark_frame = Instant::now() + config.interval;
+            self.reselect_antenna();
+            return;
+        }
+        if !dark_frames.take_stale() && Instant::now() < self.next_dark_frame {
+            return;
+        }
+        let Some(switch) = &self.antenna_switch else {
+            return;
+        };
+        if self.antenna.is_none() {
+            warn!("No antenna selected to switch back to after a dark frame");
+            self.next_dark_frame = Instant::now() + config.interval;
+            return;
+        }
+        match antenna::select(switch, config.termination) {
+            Ok(()) => {
+                debug!("Capturing a dark frame on antenna {}", config.termination);
+                dark_frames.capture(config.frames as usize);
+                self.capturing_dark_frame = true;
+            }
+            Err(e) => {
+                self.report(RustIqError::Device {
+                    device: format!("{} antenna switch", switch.link.label()),
+                    detail: format!(
+                        "can't select the termination on antenna {}: {e}",
+                        config.termination
+                    ),
+                });
+                self.next_dark_frame = Instant::now() + config.interval;
+            }
+        }
+    }
+
+    /// Stop capturing dark frames and subtracting them, switching back to
+    /// the antenna if one is being captured.
+    fn stop_dark_frames(&mut self) {
+        self.dark_frame = None;
+        self.spectrum.dark_frames.clear();
+        if std::mem::take(&mut self.capturing_dark_frame) {
+            self.reselect_antenna();
+        }
+    }
+
+    /// Switch back to the antenna selected before the input was terminated.
+    fn reselect_antenna(&mut self) {
+        if let Some(index) = self.antenna {
+            self.select_antenna(index);
+        }
+    }
+
+    /// Tell the user about `error`, as well as logging it.
+    fn report(&self, error: RustIqError) {
+        warn!("{}", error);
+        let _ = self.event_tx.send(Event::Error(error));
+    }
+
+    /// Work out the overview of a newly opened file source in the
+    /// background, and send it once it is done.
+    fn send_overview(&mut self, sample_rate: Hertz) {
+        let SourceConfig::File { path, format, .. } = &self.current_config else {
+            self.overview_of = None;
+            return;
+        };
+        let file = (path.clone(), *format);
+        if self.overview_of.as_ref() == Some(&file) {
+            return;
+        }
+        self.overview_of = Some(file.clone());
+        let event_tx = self.event_tx.clone();
+        let started = self.workers.spawn(move || {
+            let (path, format) = file;
+            match analysis::overview(&path, format, sample_rate, OVERVIEW_FFT_SIZE, OVERVIEW_ROWS) {
+                Ok(overview) => {
+                    debug!("Worked out the overview of {}", path.display());
+                    let _ = event_tx.send(Event::RecordingOverview(overview));
+                }
+                Err(e) => warn!("No overview of the recording: {}", e),
+            }
+        });
+        if !started {
+            // Tried again with the next graph
+            self.overview_of = None;
+            self.report_busy("skipped the overview of the file");
+        }
+    }
+
+    /// Tell the UI background work wasn't done, with all the threads
+    /// allowed for it taken.
+    fn report_busy(&self, what: &str) {
+        self.report(RustIqError::Limit {
+            resource: Resource::Threads,
+            detail: format!(
+                "{what}, as all {} threads are busy",
+                self.workers.limit().unwrap_or_default()
+            ),
+        });
+    }
+
+    /// Stop the recording once it takes up the disk space allowed, telling
+    /// the UI. Returns whether it was stopped.
+    fn check_recording_size(&mut self) -> bool {
+        let (Some(max), Some(recording)) = (self.max_recording_bytes, &self.recording) else {
+            return false;
+        };
+        if self.stop_recording || !recording.is_full() {
+            return false;
+        }
+        self.report(RustIqError::Limit {
+            resource: Resource::Disk,
+            detail: format!(
+                "stopped recording to {} at {} MB",
+                recording.data_path().display(),
+                max / 1_000_000
+            ),
+        });
+        self.stop_recording = true;
+        true
+    }
+
+    /// Tap for the next graph to record into, noting its tuning first.
+    fn recording_tap(&mut self) -> Option<recording::RecordingTap> {
+        let recording = self.recording.as_mut()?;
+        let tap = recording
+            .retune(self.center_frequency)
+            .and_then(|()| recording.tap());
+        match tap {
+            Ok(tap) => Some(tap),
+            Err(e) => {
+                warn!("Ending the recording: {:#}", e);
+                self.finish_recording();
+                None
+            }
+        }
+    }
+
+    /// End the recording, once no graph writes to it.
+    fn finish_recording(&mut self) {
+        self.stop_recording = false;
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        let path = recording.data_path().to_path_buf();
+        let status = match recording.finish() {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("Failed to finish recording {}: {:#}", path.display(), e);
+                return;
+            }
+        };
+        info!(
+            "Recorded {} bytes to {}",
+            status.bytes_written,
+            path.display()
+        );
+        let _ = self.event_tx.send(Event::RecordingStatus(status));
+    }
+
+    /// The engine's state, running a graph at `sample_rate`.
+    fn state(&self, sample_rate: Hertz) -> EngineState {
+        EngineState {
+            center_frequency: self.center_frequency,
+            gain: self.gain,
+            gain_profiles: self.gain_profiles.clone(),
+            antenna_switch: self.antenna_switch.clone(),
+            antenna: self.antenna,
+            dark_frame: self.dark_frame,
+            rig: self.rig_config(),
+            rotator: self.rotator_address(),
+            sample_rate,
+            device_sample_rate: self.device_sample_rate,
+            fft_size: self.spectrum.fft_size,
+            decimation: self.spectrum.decimation,
+            spectrum_span: Hertz(sample_rate.as_hz() / self.spectrum.decimation as u64),
+            frequency_offset: self.spectrum.frequency_offset,
+            calibration: self.spectrum.calibration.clone(),
+            averaging: self.spectrum.averaging,
+            peak_hold: self.spectrum.peak_hold,
+            min_hold: self.spectrum.min_hold,
+            offset_tuning: self.offset_tuning,
+            source_config: self.current_config.clone(),
+            second_source: self.second_config.clone(),
+            playback_speed: self.playback.speed(),
+            playback_start: self.playback_start,
+            playback_paused: self.playback.is_paused(),
+            carrier_measurement: self.analysis.carrier_target,
+            burst_detection: self.analysis.burst_threshold,
+            meteor_detection: self.analysis.meteor,
+            impulse_counter: self.analysis.impulse_threshold,
+            sstv_decoder: self.analysis.sstv_channel,
+            selcall_decoder: self.analysis.selcall,
+            ais_decoder: self.analysis.ais_channel,
+            adsb_decoder: self.analysis.adsb,
+            demodulator: self.analysis.demodulator,
+            squelch: self.squelch.get(),
+            passband: self.passband.get(),
+            scan: self.scanner.as_ref().map(|scanner| scanner.list().clone()),
+            audio_routing: self.audio_routes.routing(),
+            vfos: self.vfos.iter().map(|(vfo, _)| vfo.clone()).collect(),
+        }
+    }
+
+    /// Journal the source and tuning, for restoring after a crash.
+    fn journal_session(&mut self) {
+        if let Some(journal) = &mut self.journal {
+            let record = SessionRecord {
+                source_config: self.current_config.clone(),
+                center_frequency: self.center_frequency,
+                gain: self.gain,
+                recording: self
+                    .recording
+                    .as_ref()
+                    .map(|recording| recording.data_path().to_path_buf()),
+            };
+            if let Err(e) = journal.write(&record) {
+                warn!("Failed to journal the session: {}", e);
+            }
+        }
+    }
+
     fn process_commands(
         &mut self,
         cancel_token: &CancellationToken,
-        graph_handle: &thread::JoinHandle<std::result::Result<(), rustradio::Error>>,
+        graph_handle: &thread::JoinHandle<Result<(), rustradio::Error>>,
+        tuner: &mut tuner::Tuner,
+        sample_rate: Hertz,
     ) {
         loop {
+            self.step_scan(tuner);
+            self.step_dark_frame();
+            self.step_stats();
+            self.step_position(sample_rate);
             let msg = self.cmd_rx.recv_timeout(Duration::from_millis(100));
             debug!("Engine received message: {:?}", msg);
 
+            if let Ok(command) = &msg
+                && let Some(feature) = required_feature(command)
+                && !compiled_in(feature)
+            {
+                warn!("{} isn't built into this engine", feature.label());
+                continue;
+            }
+            if let Ok(Command::ChangeSource(config) | Command::SetSecondSource(Some(config))) = &msg
+                && !source_compiled_in(SourceKind::of(config))
+            {
+                warn!(
+                    "{} sources aren't built into this engine",
+                    SourceKind::of(config).label()
+                );
+                continue;
+            }
+
             match msg {
                 Ok(Command::Stop) | Err(flume::RecvTimeoutError::Disconnected) => {
                     self.should_exit = true;
@@ -82,16 +888,677 @@ impl Engine {
                 }
                 Ok(Command::ChangeSource(new_config)) => {
                     self.current_config = new_config;
+                    self.next_start = Some(Duration::ZERO);
+                    self.playback.set_paused(false);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetSecondSource(config)) => {
+                    self.second_config = config;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetCenterFrequency(frequency)) => {
+                    let gain = self.gain;
+                    self.center_frequency = frequency;
+                    self.apply_gain_profile();
+                    self.apply_antenna_rule();
+                    // A new gain, or a channel placed from the old center,
+                    // needs the graph rebuilt
+                    if self.gain == gain
+                        && !self.analysis.has_channels()
+                        && self.vfos.is_empty()
+                        && tuner.retune(frequency)
+                    {
+                        debug!("Retuned to {} in place", frequency);
+                        if let Some(recording) = &mut self.recording
+                            && let Err(e) = recording.retune(frequency)
+                        {
+                            warn!("Failed to note the retune in the recording: {:#}", e);
+                        }
+                        let state = self.state(sample_rate);
+                        let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                        self.journal_session();
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetGain(gain)) => {
+                    self.gain = gain;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetFftSize(size)) => {
+                    if !size.is_power_of_two() || !(16..=graph::MAX_FFT_SIZE).contains(&size) {
+                        warn!(
+                            "Ignoring FFT size {}, not a power of two up to {}",
+                            size,
+                            graph::MAX_FFT_SIZE
+                        );
+                        continue;
+                    }
+                    if size > self.max_fft_size {
+                        self.report(RustIqError::Limit {
+                            resource: Resource::Memory,
+                            detail: format!(
+                                "FFT size {} doesn't fit, {} is the largest that does",
+                                size, self.max_fft_size
+                            ),
+                        });
+                        if self.spectrum.fft_size == self.max_fft_size {
+                            continue;
+                        }
+                    }
+                    self.spectrum.fft_size = size.min(self.max_fft_size);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetDecimation(decimation)) => {
+                    if !decimation.is_power_of_two() || decimation > graph::MAX_DECIMATION {
+                        warn!(
+                            "Ignoring decimation {}, not a power of two up to {}",
+                            decimation,
+                            graph::MAX_DECIMATION
+                        );
+                        continue;
+                    }
+                    self.spectrum.decimation = decimation;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetFrequencyOffset(offset)) => {
+                    if offset.unsigned_abs() >= sample_rate.as_hz() / 2 {
+                        warn!(
+                            "Ignoring frequency offset {} Hz, outside the {} band",
+                            offset, sample_rate
+                        );
+                        continue;
+                    }
+                    self.spectrum.frequency_offset = offset;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetCalibration(points)) => {
+                    self.spectrum.calibration = points;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetAveraging(averaging)) => {
+                    if !averaging.is_valid() {
+                        warn!("Ignoring invalid spectrum averaging {:?}", averaging);
+                        continue;
+                    }
+                    self.spectrum.averaging = averaging;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetPeakHold(enabled)) => {
+                    self.spectrum.peak_hold = enabled;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetMinHold(enabled)) => {
+                    self.spectrum.min_hold = enabled;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetOffsetTuning(enabled)) => {
+                    self.offset_tuning = enabled;
                     cancel_token.cancel();
                     break;
                 }
+                Ok(Command::SetGainProfiles(profiles)) => {
+                    self.gain_profiles = profiles;
+                    self.apply_gain_profile();
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetAntennaSwitch(config)) => {
+                    // Dark frames are taken through the switch
+                    if config.is_none() {
+                        self.stop_dark_frames();
+                    }
+                    self.antenna_switch = config;
+                    // The switch's position is unknown until we set it
+                    self.antenna = None;
+                    self.apply_antenna_rule();
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SelectAntenna(index)) => {
+                    if self.antenna_switch.is_none() {
+                        warn!("No antenna switch to select antenna {} on", index);
+                        continue;
+                    }
+                    self.select_antenna(index);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetDarkFrame(config)) => {
+                    match config {
+                        Some(_) if self.antenna_switch.is_none() => {
+                            warn!("No antenna switch to terminate the input with");
+                            continue;
+                        }
+                        Some(config) => {
+                            self.dark_frame = Some(config);
+                            self.next_dark_frame = Instant::now();
+                        }
+                        None => self.stop_dark_frames(),
+                    }
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                #[cfg(feature = "rotator")]
+                Ok(Command::ConnectRotator(address)) => {
+                    let rotator = rotator::Rotator::connect(address.clone(), self.event_tx.clone());
+                    self.rotator = Some((address, rotator));
+                    // The rotator isn't part of the graph, which carries on
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                #[cfg(feature = "rotator")]
+                Ok(Command::DisconnectRotator) => {
+                    if self.rotator.take().is_none() {
+                        warn!("No rotator to disconnect");
+                        continue;
+                    }
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                #[cfg(feature = "rotator")]
+                Ok(Command::PointRotator(position)) => match &self.rotator {
+                    Some((_, rotator)) => rotator.point(position),
+                    None => warn!("No rotator to point"),
+                },
+                #[cfg(feature = "rig")]
+                Ok(Command::ConnectRig(config)) => {
+                    let rig = rig::Rig::connect(config.address.clone());
+                    self.rig = Some((config, rig));
+                    // The rig is polled between commands, not by the graph
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                #[cfg(feature = "rig")]
+                Ok(Command::DisconnectRig) => {
+                    if self.rig.take().is_none() {
+                        warn!("No rig to disconnect");
+                        continue;
+                    }
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                #[cfg(feature = "rig")]
+                Ok(Command::TuneRig(frequency)) => match &self.rig {
+                    Some((_, rig)) => rig.tune(frequency),
+                    None => warn!("No rig to tune"),
+                },
+                Ok(Command::RestoreSession(session)) => {
+                    info!("Restoring previous session");
+                    self.current_config = session.source_config;
+                    self.next_start = Some(Duration::ZERO);
+                    self.playback.set_paused(false);
+                    self.center_frequency = session.center_frequency;
+                    self.gain = session.gain;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartCarrierMeasurement(target)) => {
+                    self.analysis.carrier_target = Some(target);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopCarrierMeasurement) => {
+                    if self.analysis.carrier_target.take().is_none() {
+                        warn!("No carrier measurement to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartBurstDetection(threshold)) => {
+                    self.analysis.burst_threshold = Some(threshold);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopBurstDetection) => {
+                    if self.analysis.burst_threshold.take().is_none() {
+                        warn!("No burst detection to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartMeteorDetection(config)) => {
+                    self.analysis.meteor = Some(config);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopMeteorDetection) => {
+                    if self.analysis.meteor.take().is_none() {
+                        warn!("No meteor detection to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartImpulseCounter(threshold)) => {
+                    self.analysis.impulse_threshold = Some(threshold);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopImpulseCounter) => {
+                    if self.analysis.impulse_threshold.take().is_none() {
+                        warn!("No impulse counter to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartSstvDecoder(channel)) => {
+                    self.analysis.sstv_channel = Some(channel);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopSstvDecoder) => {
+                    if self.analysis.sstv_channel.take().is_none() {
+                        warn!("No SSTV decoder to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartSelCallDecoder(config)) => {
+                    self.analysis.selcall = Some(config);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopSelCallDecoder) => {
+                    if self.analysis.selcall.take().is_none() {
+                        warn!("No SelCall decoder to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartAisDecoder(frequency)) => {
+                    self.analysis.ais_channel = Some(frequency);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopAisDecoder) => {
+                    if self.analysis.ais_channel.take().is_none() {
+                        warn!("No AIS decoder to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartAdsbDecoder) => {
+                    self.analysis.adsb = true;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopAdsbDecoder) => {
+                    if !std::mem::take(&mut self.analysis.adsb) {
+                        warn!("No ADS-B decoder to stop");
+                        continue;
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetDemodulator(channel)) => {
+                    if channel.is_none() && self.analysis.demodulator.is_none() {
+                        warn!("No demodulator to stop");
+                        continue;
+                    }
+                    // Listening somewhere else ends the scan
+                    self.scanner = None;
+                    // Another mode starts from its own passband
+                    if channel.map(|channel| channel.demod)
+                        != self.analysis.demodulator.map(|channel| channel.demod)
+                    {
+                        self.passband.set(None);
+                    }
+                    self.analysis.demodulator = channel;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::SetAudioRouting(routing)) => {
+                    if let Err(e) = self.audio_routes.set(routing) {
+                        self.report(RustIqError::Device {
+                            device: "Audio routing".into(),
+                            detail: format!("{e:#}"),
+                        });
+                        continue;
+                    }
+                    info!("Routing audio to {:?}", self.audio_routes.routing());
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::SetPlaybackSpeed(speed)) => {
+                    if let Some(speed) = speed
+                        && !playback::SPEEDS.contains(&speed)
+                    {
+                        warn!(
+                            "Ignoring playback speed {}, not from {} to {}",
+                            speed,
+                            playback::SPEEDS.start(),
+                            playback::SPEEDS.end()
+                        );
+                        continue;
+                    }
+                    self.playback.set_speed(speed);
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::Pause | Command::Resume)
+                    if !matches!(self.current_config, SourceConfig::File { .. }) =>
+                {
+                    warn!("Only a file source can be paused");
+                }
+                Ok(Command::Pause) => {
+                    self.playback.set_paused(true);
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::Resume) => {
+                    self.playback.set_paused(false);
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::SetSquelch(threshold)) => {
+                    self.squelch.set(threshold);
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::SetPassband(passband)) => {
+                    if self.analysis.demodulator.is_none() {
+                        warn!("No listening channel to set the passband of");
+                        continue;
+                    }
+                    if let Some(passband) = passband
+                        && passband.low >= passband.high
+                    {
+                        warn!(
+                            "Ignoring passband from {} to {} Hz, not from low to high",
+                            passband.low, passband.high
+                        );
+                        continue;
+                    }
+                    self.passband.set(passband);
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::AddVfo(config)) => {
+                    let routes = audio_routing::AudioRoutes::default();
+                    if let Err(e) = routes.set(config.routing()) {
+                        self.report(RustIqError::Device {
+                            device: "VFO audio routing".into(),
+                            detail: format!("{e:#}"),
+                        });
+                        continue;
+                    }
+                    let id = self.next_vfo;
+                    self.next_vfo += 1;
+                    info!("Adding VFO {} at {}", id, config.channel.frequency);
+                    self.vfos.push((Vfo { id, config }, routes));
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::RemoveVfo(id)) => {
+                    let Some(index) = self.vfos.iter().position(|(vfo, _)| vfo.id == id) else {
+                        warn!("No VFO {} to remove", id);
+                        continue;
+                    };
+                    self.vfos.remove(index);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::ConfigureVfo { id, config }) => {
+                    let Some((vfo, routes)) = self.vfos.iter_mut().find(|(vfo, _)| vfo.id == id)
+                    else {
+                        warn!("No VFO {} to configure", id);
+                        continue;
+                    };
+                    if let Err(e) = routes.set(config.routing()) {
+                        self.report(RustIqError::Device {
+                            device: "VFO audio routing".into(),
+                            detail: format!("{e:#}"),
+                        });
+                        continue;
+                    }
+                    vfo.config = config;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartScan(list)) => {
+                    if self.squelch.get().is_none() {
+                        warn!("Scanning needs the squelch set, to tell active channels");
+                        continue;
+                    }
+                    let len = list.channels.len();
+                    let Some(scanner) = scanner::Scanner::new(list, Instant::now()) else {
+                        warn!("No channels to scan that aren't locked out");
+                        continue;
+                    };
+                    info!("Scanning {} channels", len);
+                    self.passband.set(None);
+                    self.analysis.demodulator = Some(scanner.channel().channel);
+                    self.scanner = Some(scanner);
+                    self.scan_status = None;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopScan) => {
+                    if self.scanner.take().is_none() {
+                        warn!("No scan to stop");
+                        continue;
+                    }
+                    // Listening carries on where the scan got to
+                    self.scan_status = None;
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::SetLockout { channel, lockout }) => {
+                    let Some(scanner) = &mut self.scanner else {
+                        warn!("No scan to lock channel {} out of", channel);
+                        continue;
+                    };
+                    if !scanner.set_lockout(channel, lockout) {
+                        warn!("No channel {} in the scan list", channel);
+                        continue;
+                    }
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                }
+                Ok(Command::Seek(position)) => {
+                    if !matches!(self.current_config, SourceConfig::File { .. }) {
+                        warn!("Only a file source can be played from another point");
+                        continue;
+                    }
+                    // A paused file stays paused, held at the new position
+                    info!("Playing from {:?} in", position);
+                    self.next_start = Some(position);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::EstimateSymbolRate(region)) => {
+                    self.analysis.symbol_rate = Some(region);
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StartRecording { path, format }) => {
+                    if let Some(recording) = &self.recording {
+                        warn!("Already recording to {}", recording.data_path().display());
+                        continue;
+                    }
+                    match recording::LiveRecording::create(
+                        &path,
+                        format,
+                        self.current_config.clone(),
+                        sample_rate,
+                        self.center_frequency,
+                        self.max_recording_bytes,
+                    ) {
+                        Ok(recording) => {
+                            info!("Recording to {}", recording.data_path().display());
+                            self.recording = Some(recording);
+                        }
+                        Err(e) => {
+                            self.report(RustIqError::Format {
+                                path,
+                                detail: format!("can't start recording: {e:#}"),
+                            });
+                            continue;
+                        }
+                    }
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::StopRecording) => {
+                    if self.recording.is_none() {
+                        warn!("No recording to stop");
+                        continue;
+                    }
+                    self.stop_recording = true;
+                    cancel_token.cancel();
+                    break;
+                }
+                Ok(Command::Resync) => {
+                    let state = self.state(sample_rate);
+                    let _ = self.event_tx.send(Event::StateSnapshot(Box::new(state)));
+                    let _ = self.event_tx.send(Event::Capabilities(capabilities()));
+                }
+                Ok(Command::RunSelfTest) => {
+                    // Off the command loop; the test takes a moment
+                    let event_tx = self.event_tx.clone();
+                    let started = self.workers.spawn(move || {
+                        let report = diagnostics::self_test();
+                        info!(
+                            "Self test {}",
+                            if report.passed() { "passed" } else { "failed" }
+                        );
+                        let _ = event_tx.send(Event::SelfTest(report));
+                    });
+                    if !started {
+                        self.report_busy("skipped the self test");
+                    }
+                }
+                #[cfg(not(all(feature = "rig", feature = "rotator")))]
+                Ok(command) => unreachable!("{:?} was rejected as not built in", command),
                 Err(flume::RecvTimeoutError::Timeout) => {
                     if graph_handle.is_finished() {
                         self.should_exit = true;
                         break;
                     }
+                    if let Some(status) = self
+                        .recording
+                        .as_mut()
+                        .and_then(|recording| recording.poll_status())
+                    {
+                        let _ = self.event_tx.send(Event::RecordingStatus(status));
+                    }
+                    if self.check_recording_size() {
+                        cancel_token.cancel();
+                        break;
+                    }
+                    #[cfg(feature = "rig")]
+                    if let Some(frequency) = self
+                        .rig
+                        .as_ref()
+                        .and_then(|(_, rig)| rig.frequency_change())
+                    {
+                        self.follow_rig(frequency);
+                        cancel_token.cancel();
+                        break;
+                    }
                 }
             }
         }
     }
+
+    #[cfg(feature = "rig")]
+    fn rig_config(&self) -> Option<RigConfig> {
+        self.rig.as_ref().map(|(config, _)| config.clone())
+    }
+
+    #[cfg(not(feature = "rig"))]
+    fn rig_config(&self) -> Option<rustiq_messages::RigConfig> {
+        None
+    }
+
+    #[cfg(feature = "rotator")]
+    fn rotator_address(&self) -> Option<String> {
+        self.rotator.as_ref().map(|(address, _)| address.clone())
+    }
+
+    #[cfg(not(feature = "rotator"))]
+    fn rotator_address(&self) -> Option<String> {
+        None
+    }
+
+    /// Center on the rig's new frequency. From an IF tap the front end
+    /// stays on the IF, so only the displayed frequency moves.
+    #[cfg(feature = "rig")]
+    fn follow_rig(&mut self, frequency: Hertz) {
+        debug!("Rig tuned to {}", frequency);
+        self.center_frequency = frequency;
+        if self
+            .rig
+            .as_ref()
+            .is_some_and(|(config, _)| config.if_frequency.is_none())
+        {
+            self.apply_gain_profile();
+            self.apply_antenna_rule();
+        }
+    }
+
+    /// Switch to the gain of the profile covering the center frequency.
+    /// Outside every profile the current gain is kept.
+    fn apply_gain_profile(&mut self) {
+        if let Some(profile) = GainProfile::find(&self.gain_profiles, self.center_frequency) {
+            debug!("Applying gain profile {:?}", profile);
+            self.gain = profile.gain;
+        }
+    }
+
+    /// Switch to the antenna of the rule covering the center frequency.
+    /// Outside every rule the current antenna is kept.
+    fn apply_antenna_rule(&mut self) {
+        let Some(config) = &self.antenna_switch else {
+            return;
+        };
+        if let Some(rule) = AntennaRule::find(&config.rules, self.center_frequency)
+            && self.antenna != Some(rule.antenna)
+        {
+            self.select_antenna(rule.antenna);
+        }
+    }
+
+    fn select_antenna(&mut self, index: usize) {
+        let Some(config) = &self.antenna_switch else {
+            return;
+        };
+        // Frames from the antenna don't belong in a dark frame; it is
+        // taken again once the input can be terminated
+        if std::mem::take(&mut self.capturing_dark_frame) {
+            self.spectrum.dark_frames.cancel();
+            self.next_dark_frame = Instant::now();
+        }
+        match antenna::select(config, index) {
+            Ok(()) => {
+                info!("Selected antenna {}", index);
+                self.antenna = Some(index);
+            }
+            Err(e) => {
+                self.report(RustIqError::Device {
+                    device: format!("{} antenna switch", config.link.label()),
+                    detail: format!("can't select antenna {index}: {e}"),
+                });
+                self.antenna = None;
+            }
+        }
+    }
 }
@@ -0,0 +1,149 @@
+//! Playing a file source back in step with the wall clock. The speed and
+//! the pause are shared by the engine with the blocks that follow it, so
+//! they change without a rebuild; the blocks share back how far they have
+//! played, for the engine to carry a paced file on from there when it does
+//! rebuild.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Instant;
+
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+/// Slowest and fastest playback accepted.
+pub const SPEEDS: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+
+/// How a file source plays, shared between the engine and the graphs it
+/// builds.
+#[derive(Debug, Clone, Default)]
+pub struct Playback(Arc<Shared>);
+
+#[derive(Debug, Default)]
+struct Shared {
+    /// Times real time, as f32 bits; zero, the default, stands for unpaced
+    speed: AtomicU32,
+    paused: AtomicBool,
+    /// Samples passed on since the engine last took the count
+    played: AtomicU64,
+}
+
+impl Playback {
+    /// Times real time the file plays at, `None` if it is read as fast as
+    /// it can be.
+    pub fn speed(&self) -> Option<f32> {
+        let speed = f32::from_bits(self.0.speed.load(Ordering::Relaxed));
+        (speed > 0.0).then_some(speed)
+    }
+
+    pub fn set_speed(&self, speed: Option<f32>) {
+        self.0
+            .speed
+            .store(speed.unwrap_or(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.0.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Samples played since the count was last taken.
+    pub fn played(&self) -> u64 {
+        self.0.played.load(Ordering::Relaxed)
+    }
+
+    /// Samples played since the count was last taken, starting it over.
+    pub fn take_played(&self) -> u64 {
+        self.0.played.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// Passes samples on no faster than `sample_rate` times the playback speed
+/// per second of wall clock, all at once while unpaced, and none while
+/// paused.
+#[derive(rustradio_macros::Block)]
+pub struct Pace {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+    sample_rate: f64,
+    playback: Playback,
+    /// Samples passed on so far
+    passed: u64,
+    /// The speed being kept to, with when and after how many samples it
+    /// took effect
+    since: Option<(f32, Instant, u64)>,
+}
+
+impl Pace {
+    /// Pace `src` for `playback`, adding what it plays to its count.
+    pub fn new(
+        src: ReadStream<Complex>,
+        sample_rate: f64,
+        playback: Playback,
+    ) -> (Self, ReadStream<Complex>) {
+        let (dst, dr) = rustradio::stream::new_stream();
+        (
+            Self {
+                src,
+                dst,
+                sample_rate,
+                playback,
+                passed: 0,
+                since: None,
+            },
+            dr,
+        )
+    }
+}
+
+impl Block for Pace {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+        let mut o = self.dst.write_buf()?;
+        if o.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.dst, 1));
+        }
+        if self.playback.is_paused() {
+            // Pacing starts over on resuming
+            self.since = None;
+            return Ok(BlockRet::Pending);
+        }
+        let mut n = input.len().min(o.len());
+        match self.playback.speed() {
+            Some(speed) => {
+                // A new speed counts from now, not from the start
+                let (speed, start, start_passed) = *self
+                    .since
+                    .take()
+                    .filter(|(since, ..)| *since == speed)
+                    .get_or_insert((speed, Instant::now(), self.passed));
+                self.since = Some((speed, start, start_passed));
+                let due = start_passed
+                    + (start.elapsed().as_secs_f64() * self.sample_rate * f64::from(speed)) as u64;
+                n = n.min(due.saturating_sub(self.passed) as usize);
+                if n == 0 {
+                    return Ok(BlockRet::Pending);
+                }
+            }
+            None => self.since = None,
+        }
+        o.slice()[..n].copy_from_slice(&input.slice()[..n]);
+        o.produce(n, &[]);
+        input.consume(n);
+        self.passed += n as u64;
+        self.playback
+            .0
+            .played
+            .fetch_add(n as u64, Ordering::Relaxed);
+        Ok(BlockRet::Again)
+    }
+}
@@ -0,0 +1,325 @@
+//! Captures to SigMF recordings: headless ones, for command-line tools that
+//! record without running the engine, and live ones of the engine's stream.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Context;
+use rustiq_messages::{Hertz, RecordingFormat, RecordingStatus, SourceConfig, UtcTime};
+use rustradio::Complex;
+use rustradio::graph::CancellationToken;
+use rustradio::sigmf::{Capture, SigMF};
+use rustradio::stream::ReadStream;
+
+use super::chain::{ChainBuilder, Graphs, Pipeline};
+use super::integrity::{self, CHUNK_BYTES, Checksummer};
+use super::sinks::IqFileSink;
+use super::tuner::Tuner;
+
+/// SigMF datatype of the samples recordings are written in.
+const DATATYPE: &str = "cf32_le";
+
+/// Size of one `cf32_le` sample.
+const SAMPLE_BYTES: u64 = 8;
+
+/// Time between progress reports of a live recording.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings for a `Recording`.
+#[derive(Debug, Clone)]
+pub struct RecordingOptions {
+    /// Frequency the source is tuned to, for the metadata.
+    pub center_frequency: Hertz,
+    /// Stop after this long; record until cancelled if `None`.
+    pub duration: Option<Duration>,
+    /// Free-text description for the metadata.
+    pub description: Option<String>,
+    /// Name of the device recorded from, for the metadata.
+    pub hardware: Option<String>,
+}
+
+/// A capture from a source into a SigMF recording: `<base>.sigmf-data` and
+/// `<base>.sigmf-meta`, with the data's checksums in `<base>.sigmf-sha256`
+/// for `integrity::verify`. The metadata is written before any samples, so a
+/// capture cut short still leaves a valid recording.
+pub struct Recording {
+    graph: Graphs,
+    data_path: PathBuf,
+    written: Arc<AtomicU64>,
+    checksums: Arc<Mutex<Checksummer>>,
+    sample_rate: Hertz,
+}
+
+impl Recording {
+    pub fn new(
+        source_config: SourceConfig,
+        base: &Path,
+        options: &RecordingOptions,
+    ) -> anyhow::Result<Self> {
+        let data_path = base.with_extension("sigmf-data");
+        let meta_path = base.with_extension("sigmf-meta");
+
+        let mut pipeline = Pipeline::new();
+        let cancel = pipeline.cancel_token();
+        let chain = ChainBuilder::source(
+            &mut pipeline,
+            source_config,
+            &mut Tuner::new(options.center_frequency),
+        )?;
+        let sample_rate = chain.sample_rate();
+        let limit = options
+            .duration
+            .map(|duration| (duration.as_secs_f64() * sample_rate as f64).round() as u64);
+
+        let mut meta = metadata(Hertz(sample_rate), options.center_frequency);
+        meta.global.core_description = options.description.clone();
+        meta.global.core_hw = options.hardware.clone();
+        write_metadata(&meta_path, &meta)?;
+
+        let data_file = File::create(&data_path)
+            .with_context(|| format!("creating {}", data_path.display()))?;
+        let checksums = create_checksums(&data_path)?;
+        let written = Arc::new(AtomicU64::new(0));
+        chain.sink(|src| {
+            IqFileSink::new(
+                src,
+                data_file,
+                data_path.clone(),
+                limit,
+                Some(cancel),
+                written.clone(),
+                Some(checksums.clone()),
+            )
+        });
+
+        Ok(Self {
+            graph: pipeline.into_graph(),
+            data_path,
+            written,
+            checksums,
+            sample_rate: Hertz(sample_rate),
+        })
+    }
+
+    /// Token that stops the recording early, e.g. on Ctrl-C.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.graph.cancel_token()
+    }
+
+    pub fn data_path(&self) -> &Path {
+        &self.data_path
+    }
+
+    pub fn sample_rate(&self) -> Hertz {
+        self.sample_rate
+    }
+
+    /// Capture until the duration is reached, the source ends or the
+    /// recording is cancelled. Returns the number of samples written.
+    pub fn run(mut self) -> anyhow::Result<u64> {
+        self.graph.run()?;
+        self.checksums
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .finish()
+            .context("finishing the checksum manifest")?;
+        Ok(self.written.load(Ordering::Relaxed))
+    }
+}
+
+/// A recording of the engine's live IQ stream. It outlives the graphs it
+/// records: each graph built while it runs tees the main source's stream
+/// into a sink appending to the same file, so the recording carries on
+/// across rebuilds. A SigMF recording gets a capture for each tuning.
+pub struct LiveRecording {
+    data_path: PathBuf,
+    file: File,
+    /// Metadata file and contents, rewritten as captures are added; `None`
+    /// for a raw recording
+    meta: Option<(PathBuf, SigMF)>,
+    checksums: Option<Arc<Mutex<Checksummer>>>,
+    /// Samples written so far
+    written: Arc<AtomicU64>,
+    /// Most samples to write, to keep within the disk space allowed
+    limit: Option<u64>,
+    /// Source recorded from; the stream of another source doesn't belong
+    /// in the same recording
+    source_config: SourceConfig,
+    center_frequency: Hertz,
+    last_status: Instant,
+}
+
+impl LiveRecording {
+    /// Start a recording at `path` of the stream from `source_config`,
+    /// running at `sample_rate` and tuned to `center_frequency`. Samples
+    /// past `max_bytes` of data, if given, are dropped.
+    pub fn create(
+        path: &Path,
+        format: RecordingFormat,
+        source_config: SourceConfig,
+        sample_rate: Hertz,
+        center_frequency: Hertz,
+        max_bytes: Option<u64>,
+    ) -> anyhow::Result<Self> {
+        let (data_path, meta, checksums) = match format {
+            RecordingFormat::SigMf => {
+                let data_path = path.with_extension("sigmf-data");
+                let meta_path = path.with_extension("sigmf-meta");
+                let meta = metadata(sample_rate, center_frequency);
+                write_metadata(&meta_path, &meta)?;
+                let checksums = create_checksums(&data_path)?;
+                (data_path, Some((meta_path, meta)), Some(checksums))
+            }
+            RecordingFormat::Raw => (path.to_path_buf(), None, None),
+        };
+        let file = File::create(&data_path)
+            .with_context(|| format!("creating {}", data_path.display()))?;
+        Ok(Self {
+            data_path,
+            file,
+            meta,
+            checksums,
+            written: Arc::new(AtomicU64::new(0)),
+            limit: max_bytes.map(|bytes| bytes / SAMPLE_BYTES),
+            source_config,
+            center_frequency,
+            last_status: Instant::now(),
+        })
+    }
+
+    pub fn data_path(&self) -> &Path {
+        &self.data_path
+    }
+
+    pub fn source_config(&self) -> &SourceConfig {
+        &self.source_config
+    }
+
+    /// Tap for a new graph to record into.
+    pub fn tap(&self) -> anyhow::Result<RecordingTap> {
+        let file = self
+            .file
+            .try_clone()
+            .with_context(|| format!("reopening {}", self.data_path.display()))?;
+        Ok(RecordingTap {
+            file,
+            path: self.data_path.clone(),
+            written: self.written.clone(),
+            limit: self.limit,
+            checksums: self.checksums.clone(),
+        })
+    }
+
+    /// Whether the recording has taken up all the disk space allowed.
+    pub fn is_full(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.written.load(Ordering::Relaxed) >= limit)
+    }
+
+    /// Note a retune to `frequency`, starting a new capture from the next
+    /// sample.
+    pub fn retune(&mut self, frequency: Hertz) -> anyhow::Result<()> {
+        if frequency == self.center_frequency {
+            return Ok(());
+        }
+        self.center_frequency = frequency;
+        let Some((meta_path, meta)) = &mut self.meta else {
+            return Ok(());
+        };
+        meta.captures.push(Capture {
+            core_frequency: Some(frequency.as_hz() as f64),
+            core_datetime: Some(UtcTime::from(SystemTime::now()).to_string()),
+            ..Capture::new(self.written.load(Ordering::Relaxed))
+        });
+        write_metadata(meta_path, meta)
+    }
+
+    pub fn status(&self, active: bool) -> RecordingStatus {
+        RecordingStatus {
+            path: self.data_path.clone(),
+            bytes_written: self.written.load(Ordering::Relaxed) * SAMPLE_BYTES,
+            active,
+        }
+    }
+
+    /// Progress to report, if it is time for another report.
+    pub fn poll_status(&mut self) -> Option<RecordingStatus> {
+        if self.last_status.elapsed() < STATUS_INTERVAL {
+            return None;
+        }
+        self.last_status = Instant::now();
+        Some(self.status(true))
+    }
+
+    /// End the recording, once no graph is writing to it any more.
+    /// Returns its final status.
+    pub fn finish(self) -> anyhow::Result<RecordingStatus> {
+        if let Some(checksums) = &self.checksums {
+            checksums
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .finish()
+                .context("finishing the checksum manifest")?;
+        }
+        self.file
+            .sync_all()
+            .with_context(|| format!("syncing {}", self.data_path.display()))?;
+        Ok(self.status(false))
+    }
+}
+
+/// A graph's share of a live recording.
+pub struct RecordingTap {
+    file: File,
+    path: PathBuf,
+    written: Arc<AtomicU64>,
+    limit: Option<u64>,
+    checksums: Option<Arc<Mutex<Checksummer>>>,
+}
+
+impl RecordingTap {
+    /// Sink appending `src` to the recording.
+    pub(crate) fn sink(self, src: ReadStream<Complex>) -> IqFileSink {
+        IqFileSink::new(
+            src,
+            self.file,
+            self.path,
+            self.limit,
+            None,
+            self.written,
+            self.checksums,
+        )
+    }
+}
+
+/// SigMF metadata for a recording at `sample_rate`, with a first capture
+/// tuned to `center_frequency` starting now.
+fn metadata(sample_rate: Hertz, center_frequency: Hertz) -> SigMF {
+    let mut meta = SigMF::new(DATATYPE.to_string());
+    meta.global.core_sample_rate = Some(sample_rate.as_hz() as f64);
+    meta.global.core_recorder = Some(format!("rustiq {}", env!("CARGO_PKG_VERSION")));
+    meta.captures.push(Capture {
+        core_frequency: Some(center_frequency.as_hz() as f64),
+        core_datetime: Some(UtcTime::from(SystemTime::now()).to_string()),
+        ..Capture::new(0)
+    });
+    meta
+}
+
+fn write_metadata(meta_path: &Path, meta: &SigMF) -> anyhow::Result<()> {
+    let meta_file =
+        File::create(meta_path).with_context(|| format!("creating {}", meta_path.display()))?;
+    serde_json::to_writer_pretty(meta_file, meta)
+        .with_context(|| format!("writing {}", meta_path.display()))
+}
+
+/// Checksums of the data at `data_path`, for `integrity::verify`.
+fn create_checksums(data_path: &Path) -> anyhow::Result<Arc<Mutex<Checksummer>>> {
+    let manifest_path = integrity::manifest_path(data_path);
+    let checksums = Checksummer::create(&manifest_path, CHUNK_BYTES)
+        .with_context(|| format!("creating {}", manifest_path.display()))?;
+    Ok(Arc::new(Mutex::new(checksums)))
+}
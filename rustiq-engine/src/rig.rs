@@ -0,0 +1,105 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use flume::{Receiver, RecvTimeoutError, Sender};
+use log::{debug, warn};
+use rustiq_messages::Hertz;
+
+use super::hamlib::{self, Connection};
+
+/// How often the rig's frequency is read back.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Client for a hamlib `rigctld`, talking its network protocol on a worker
+/// thread. The worker polls the rig's VFO frequency and reconnects by itself
+/// if the daemon goes away.
+///
+/// Dropping the handle stops the worker.
+pub struct Rig {
+    tune_tx: Sender<Hertz>,
+    frequency_rx: Receiver<Hertz>,
+}
+
+impl Rig {
+    pub fn connect(address: String) -> Self {
+        let (tune_tx, tune_rx) = flume::unbounded();
+        let (frequency_tx, frequency_rx) = flume::unbounded();
+        thread::spawn(move || run(&address, &tune_rx, &frequency_tx));
+        Self {
+            tune_tx,
+            frequency_rx,
+        }
+    }
+
+    /// Tune the rig to `frequency`.
+    pub fn tune(&self, frequency: Hertz) {
+        let _ = self.tune_tx.send(frequency);
+    }
+
+    /// The rig's frequency, if it has changed since the last call.
+    pub fn frequency_change(&self) -> Option<Hertz> {
+        self.frequency_rx.try_iter().last()
+    }
+}
+
+fn run(address: &str, tune_rx: &Receiver<Hertz>, frequency_tx: &Sender<Hertz>) {
+    let mut connection: Option<Connection> = None;
+    let mut last_frequency = None;
+    loop {
+        let target = match tune_rx.recv_timeout(POLL_INTERVAL) {
+            Ok(target) => Some(target),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+
+        if connection.is_none() {
+            match Connection::open(address) {
+                Ok(opened) => {
+                    debug!("Connected to rigctld at {}", address);
+                    connection = Some(opened);
+                    last_frequency = None;
+                }
+                Err(e) => {
+                    warn!("Failed to connect to rigctld at {}: {}", address, e);
+                    continue;
+                }
+            }
+        }
+        let Some(rigctld) = &mut connection else {
+            continue;
+        };
+
+        let result = target
+            .map_or(Ok(()), |target| set_frequency(rigctld, target))
+            .and_then(|()| frequency(rigctld));
+        match result {
+            Ok(frequency) if last_frequency != Some(frequency) => {
+                last_frequency = Some(frequency);
+                if frequency_tx.send(frequency).is_err() {
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Lost rigctld at {}: {}", address, e);
+                connection = None;
+            }
+        }
+    }
+}
+
+fn set_frequency(rigctld: &mut Connection, target: Hertz) -> io::Result<()> {
+    match rigctld.set(&format!("F {}", target.as_hz()))? {
+        0 => {}
+        code => warn!("rigctld refused frequency {}: error {}", target, code),
+    }
+    Ok(())
+}
+
+fn frequency(rigctld: &mut Connection) -> io::Result<Hertz> {
+    let reply = rigctld.get("f", 1)?;
+    // rigctld prints the frequency as an integer, some backends as a float
+    let hz: f64 = hamlib::parse(&reply[0])?;
+    Ok(Hertz(hz.round() as u64))
+}
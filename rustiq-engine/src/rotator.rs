@@ -0,0 +1,93 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use flume::{Receiver, RecvTimeoutError, Sender};
+use log::{debug, warn};
+use rustiq_messages::{Event, RotatorPosition};
+
+use super::hamlib::{self, Connection};
+
+/// How often the rotator's position is read back.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Client for a hamlib `rotctld`, talking its network protocol on a worker
+/// thread. The position is polled and sent as `Event::RotatorPosition`; the
+/// worker reconnects by itself if the daemon goes away.
+///
+/// Dropping the handle stops the worker.
+pub struct Rotator {
+    targets: Sender<RotatorPosition>,
+}
+
+impl Rotator {
+    pub fn connect(address: String, event_tx: Sender<Event>) -> Self {
+        let (targets, target_rx) = flume::unbounded();
+        thread::spawn(move || run(&address, &target_rx, &event_tx));
+        Self { targets }
+    }
+
+    /// Turn the rotator to `position`.
+    pub fn point(&self, position: RotatorPosition) {
+        let _ = self.targets.send(position);
+    }
+}
+
+fn run(address: &str, targets: &Receiver<RotatorPosition>, event_tx: &Sender<Event>) {
+    let mut connection: Option<Connection> = None;
+    loop {
+        let target = match targets.recv_timeout(POLL_INTERVAL) {
+            Ok(target) => Some(target),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+
+        if connection.is_none() {
+            match Connection::open(address) {
+                Ok(opened) => {
+                    debug!("Connected to rotctld at {}", address);
+                    connection = Some(opened);
+                }
+                Err(e) => {
+                    warn!("Failed to connect to rotctld at {}: {}", address, e);
+                    continue;
+                }
+            }
+        }
+        let Some(rotctld) = &mut connection else {
+            continue;
+        };
+
+        let result = target
+            .map_or(Ok(()), |target| set_position(rotctld, target))
+            .and_then(|()| position(rotctld));
+        match result {
+            Ok(position) => {
+                if event_tx.send(Event::RotatorPosition(position)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!("Lost rotctld at {}: {}", address, e);
+                connection = None;
+            }
+        }
+    }
+}
+
+fn set_position(rotctld: &mut Connection, target: RotatorPosition) -> io::Result<()> {
+    let command = format!("P {:.1} {:.1}", target.azimuth, target.elevation);
+    match rotctld.set(&command)? {
+        0 => {}
+        code => warn!("rotctld refused position {:?}: error {}", target, code),
+    }
+    Ok(())
+}
+
+fn position(rotctld: &mut Connection) -> io::Result<RotatorPosition> {
+    let reply = rotctld.get("p", 2)?;
+    Ok(RotatorPosition {
+        azimuth: hamlib::parse(&reply[0])?,
+        elevation: hamlib::parse(&reply[1])?,
+    })
+}
@@ -0,0 +1,276 @@
+//! Stepping the listening channel through a scan list, stopping where the
+//! squelch opens.
+
+use std::time::{Duration, Instant};
+
+use rustiq_messages::{Lockout, ScanChannel, ScanList};
+
+/// How long each channel is listened to before the scan judges whether it
+/// is active: long enough for the channel filter and squelch to settle.
+pub const STEP: Duration = Duration::from_millis(150);
+
+/// Steps through a scan list, given the squelch on each channel it hops to.
+/// Where the squelch opens it stays, until the channel has been quiet for
+/// its delay or held for its hold, or the list's dwell; every so often it
+/// looks in on the priority channels.
+pub struct Scanner {
+    list: ScanList,
+    /// Index of the channel being listened to
+    at: usize,
+    /// When the scan hopped to it
+    arrived: Instant,
+    /// When the squelch opened on it, while it has been active
+    active_since: Option<Instant>,
+    /// When the squelch was first and last heard open on it
+    first_heard: Option<Instant>,
+    heard: Option<Instant>,
+    /// Channel last moved on from after hearing it, and for how long it
+    /// was heard, until taken
+    hit: Option<(usize, Duration)>,
+    /// Ordinary channels stepped past since the priority channels were
+    /// last looked in on
+    since_priority: u32,
+    /// Channel to carry on from after looking in on a priority channel
+    resume: Option<usize>,
+    /// Priority channel to look in on next
+    next_priority: usize,
+}
+
+impl Scanner {
+    /// A scan starting on the first channel not locked out, or none if all
+    /// are.
+    pub fn new(list: ScanList, now: Instant) -> Option<Self> {
+        let at = list
+            .channels
+            .iter()
+            .position(|channel| channel.lockout == Lockout::None)?;
+        Some(Self {
+            list,
+            at,
+            arrived: now,
+            active_since: None,
+            first_heard: None,
+            heard: None,
+            hit: None,
+            since_priority: 0,
+            resume: None,
+            next_priority: 0,
+        })
+    }
+
+    pub fn list(&self) -> &ScanList {
+        &self.list
+    }
+
+    /// Index of the channel being listened to.
+    pub fn at(&self) -> usize {
+        self.at
+    }
+
+    pub fn channel(&self) -> &ScanChannel {
+        &self.list.channels[self.at]
+    }
+
+    /// Whether the scan is stopped on an active channel.
+    pub fn is_active(&self) -> bool {
+        self.active_since.is_some()
+    }
+
+    /// The channel the scan last moved on from after hearing activity on
+    /// it, and for how long it was heard, once.
+    pub fn take_hit(&mut self) -> Option<(usize, Duration)> {
+        self.hit.take()
+    }
+
+    /// Lock `channel` out of the scan or let it back in. Returns false if
+    /// the list has no such channel.
+    pub fn set_lockout(&mut self, channel: usize, lockout: Lockout) -> bool {
+        match self.list.channels.get_mut(channel) {
+            Some(scan_channel) => {
+                scan_channel.lockout = lockout;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Given whether the squelch is open on the channel, if it has been
+    /// heard since the hop, the index of the channel to hop to, if the scan
+    /// moves on.
+    pub fn tick(&mut self, now: Instant, open: Option<bool>) -> Option<usize> {
+        let locked_out = self.channel().lockout != Lockout::None;
+        if !locked_out && now.duration_since(self.arrived) < STEP {
+            return None;
+        }
+        if !locked_out && open == Some(true) {
+            let since = *self.active_since.get_or_insert(now);
+            self.first_heard.get_or_insert(now);
+            self.heard = Some(now);
+            match self.list.dwell.or(self.channel().hold) {
+                Some(hold) if now.duration_since(since) >= hold => {}
+                _ => return None,
+            }
+        } else if !locked_out
+            && let Some(heard) = self.heard
+            && now.duration_since(heard) < self.channel().delay
+        {
+            // Waiting for a reply
+            self.active_since = None;
+            return None;
+        }
+        let next = self.next()?;
+        if let (Some(first), Some(last)) = (self.first_heard, self.heard) {
+            self.hit = Some((self.at, last - first));
+        }
+        self.at = next;
+        self.arrived = now;
+        self.active_since = None;
+        self.first_heard = None;
+        self.heard = None;
+        Some(next)
+    }
+
+    /// The channel to hop to from this one: back to the ordinary channels
+    /// after looking in on a priority one, a priority channel when it is its
+    /// turn, otherwise the next channel not locked out. None if this is the
+    /// only one left.
+    fn next(&mut self) -> Option<usize> {
+        let from = match self.resume.take() {
+            Some(resume) => resume,
+            None => {
+                self.since_priority += 1;
+                if self.list.priority_every > 0
+                    && self.since_priority >= self.list.priority_every
+                    && let Some(priority) = self.next_priority()
+                {
+                    self.since_priority = 0;
+                    self.resume = Some(self.at);
+                    return Some(priority);
+                }
+                self.at
+            }
+        };
+        let len = self.list.channels.len();
+        (1..=len)
+            .map(|step| (from + step) % len)
+            .find(|&index| self.unlocked(index) && index != self.at)
+    }
+
+    /// The priority channel to look in on, in turn, other than the one the
+    /// scan is on.
+    fn next_priority(&mut self) -> Option<usize> {
+        let len = self.list.channels.len();
+        let priority = (0..len)
+            .map(|step| (self.next_priority + step) % len)
+            .find(|&index| {
+                index != self.at && self.unlocked(index) && self.list.channels[index].priority
+            })?;
+        self.next_priority = priority + 1;
+        Some(priority)
+    }
+
+    fn unlocked(&self, index: usize) -> bool {
+        self.list.channels[index].lockout == Lockout::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustiq_messages::{AudioChannel, DemodMode, Hertz};
+
+    fn list(len: u64) -> ScanList {
+        ScanList {
+            channels: (0..len)
+                .map(|i| {
+                    let channel = AudioChannel {
+                        frequency: Hertz(156_000_000 + 25_000 * i),
+                        demod: DemodMode::Fm,
+                    };
+                    ScanChannel {
+                        delay: Duration::from_secs(1),
+                        ..ScanChannel::new(format!("Ch {i}"), channel)
+                    }
+                })
+                .collect(),
+            priority_every: 0,
+            dwell: None,
+        }
+    }
+
+    /// Channels visited over `steps` quiet steps.
+    fn visits(scanner: &mut Scanner, start: Instant, steps: u32) -> Vec<usize> {
+        (1..=steps)
+            .filter_map(|step| scanner.tick(start + STEP * step, Some(false)))
+            .collect()
+    }
+
+    #[test]
+    fn steps_past_quiet_and_locked_out_channels() {
+        let mut list = list(4);
+        list.channels[0].lockout = Lockout::Permanent;
+        let start = Instant::now();
+        let mut scanner = Scanner::new(list, start).unwrap();
+        assert_eq!(scanner.at(), 1);
+        // Not until the channel has had time to settle
+        assert_eq!(scanner.tick(start + STEP / 2, Some(false)), None);
+        assert_eq!(visits(&mut scanner, start, 4), [2, 3, 1, 2]);
+
+        scanner.set_lockout(3, Lockout::Temporary);
+        assert_eq!(visits(&mut scanner, start + STEP * 4, 2), [1, 2]);
+    }
+
+    #[test]
+    fn stays_while_active_and_for_the_delay_after() {
+        let start = Instant::now();
+        let mut scanner = Scanner::new(list(3), start).unwrap();
+        assert_eq!(scanner.tick(start + STEP, Some(true)), None);
+        assert!(scanner.is_active());
+        assert_eq!(scanner.tick(start + STEP * 20, Some(true)), None);
+        // Quiet, but within a second of being heard
+        assert_eq!(scanner.tick(start + STEP * 21, Some(false)), None);
+        assert!(!scanner.is_active());
+        let quiet = start + STEP * 20 + Duration::from_secs(1);
+        assert_eq!(scanner.tick(quiet, Some(false)), Some(1));
+    }
+
+    #[test]
+    fn moves_on_from_an_active_channel_after_its_hold() {
+        let mut list = list(3);
+        list.channels[0].hold = Some(Duration::from_secs(2));
+        let start = Instant::now();
+        let mut scanner = Scanner::new(list, start).unwrap();
+        assert_eq!(scanner.tick(start + STEP, Some(true)), None);
+        assert_eq!(
+            scanner.tick(start + STEP + Duration::from_secs(2), Some(true)),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn moves_on_after_the_dwell_and_tells_of_the_hit() {
+        let mut list = list(3);
+        list.dwell = Some(Duration::from_secs(5));
+        let start = Instant::now();
+        let mut scanner = Scanner::new(list, start).unwrap();
+        assert_eq!(scanner.tick(start + STEP, Some(true)), None);
+        assert_eq!(scanner.take_hit(), None);
+        let dwelt = start + STEP + Duration::from_secs(5);
+        assert_eq!(scanner.tick(dwelt, Some(true)), Some(1));
+        assert_eq!(scanner.take_hit(), Some((0, Duration::from_secs(5))));
+        assert_eq!(scanner.take_hit(), None);
+        // Nothing heard, nothing to tell
+        assert_eq!(visits(&mut scanner, dwelt, 1), [2]);
+        assert_eq!(scanner.take_hit(), None);
+    }
+
+    #[test]
+    fn looks_in_on_priority_channels_every_few_steps() {
+        let mut list = list(5);
+        list.channels[4].priority = true;
+        list.priority_every = 2;
+        let start = Instant::now();
+        let mut scanner = Scanner::new(list, start).unwrap();
+        assert_eq!(visits(&mut scanner, start, 7), [1, 4, 2, 3, 4, 0, 1]);
+    }
+}
@@ -0,0 +1,37 @@
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::dsp::AdsbDecoder;
+use rustiq_messages::Event;
+
+/// A sink block that decodes ADS-B messages and reports the targets in them.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct AdsbSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    decoder: AdsbDecoder,
+}
+
+impl Block for AdsbSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        for report in self.decoder.process(input.slice()) {
+            if self.event_tx.send(Event::Track(report)).is_err() {
+                return Ok(BlockRet::EOF);
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
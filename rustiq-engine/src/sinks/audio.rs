@@ -0,0 +1,331 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::audio_routing::AudioRoutes;
+use crate::dsp::{Audio, AudioDemodulator, ChannelStats, TimeStretch};
+use crate::playback::Playback;
+use rustiq_messages::{AudioChunk, Decibels, DemodMode, Event, Passband};
+
+/// Seconds of audio sent per event, so a fast stream doesn't flood the UI
+/// with tiny chunks.
+const CHUNK: f64 = 0.02;
+
+/// Level the listening channel is squelched at, if it is, shared between
+/// the engine and the graphs it builds so it changes without a rebuild.
+#[derive(Debug, Clone)]
+pub struct SquelchThreshold(Arc<AtomicU32>);
+
+impl Default for SquelchThreshold {
+    fn default() -> Self {
+        // NaN stands for no squelch
+        Self(Arc::new(AtomicU32::new(f32::NAN.to_bits())))
+    }
+}
+
+impl SquelchThreshold {
+    pub fn get(&self) -> Option<Decibels> {
+        let threshold = f32::from_bits(self.0.load(Ordering::Relaxed));
+        (!threshold.is_nan()).then_some(Decibels(threshold))
+    }
+
+    pub fn set(&self, threshold: Option<Decibels>) {
+        let threshold = threshold.map_or(f32::NAN, |threshold| threshold.0);
+        self.0.store(threshold.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Passband the listening channel is filtered through, if not its mode's,
+/// shared between the engine and the graphs it builds so it changes
+/// without a rebuild.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelPassband(Arc<Mutex<Option<Passband>>>);
+
+impl ChannelPassband {
+    pub fn get(&self) -> Option<Passband> {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn set(&self, passband: Option<Passband>) {
+        *self.0.lock().unwrap() = passband;
+    }
+}
+
+/// Where the scanner has hopped the listening channel to, shared between
+/// the engine and the graphs it builds so it hops without a rebuild, and
+/// whether the squelch is open there and what it let through.
+#[derive(Debug, Clone, Default)]
+pub struct ListeningChannel(Arc<Mutex<Hops>>);
+
+#[derive(Debug, Default)]
+struct Hops {
+    /// Number of the latest hop, 0 before any
+    hop: u64,
+    /// Offset from the stream's DC and mode of the latest hop
+    to: Option<(f64, DemodMode)>,
+    /// Whether the squelch was open, and on which hop
+    heard: Option<(u64, bool)>,
+    /// What the squelch let through on the latest hop
+    stats: ChannelStats,
+}
+
+impl ListeningChannel {
+    /// Hop the demodulator to the channel `offset` Hz from the stream's DC,
+    /// demodulating it as `demod`.
+    pub fn hop(&self, offset: f64, demod: DemodMode) {
+        let mut hops = self.0.lock().unwrap();
+        hops.hop += 1;
+        hops.to = Some((offset, demod));
+        hops.stats = ChannelStats::default();
+    }
+
+    /// Forget the hops, for a graph built on the channel listened to.
+    pub fn reset(&self) {
+        *self.0.lock().unwrap() = Hops::default();
+    }
+
+    /// Whether the squelch is open on the latest hop, once the demodulator
+    /// has heard anything there.
+    pub fn open(&self) -> Option<bool> {
+        let hops = self.0.lock().unwrap();
+        hops.heard
+            .filter(|&(hop, _)| hop == hops.hop)
+            .map(|(_, open)| open)
+    }
+
+    /// What the squelch has let through on the latest hop.
+    pub fn stats(&self) -> ChannelStats {
+        self.0.lock().unwrap().stats
+    }
+
+    /// A hop later than `hop`, if there is one.
+    fn since(&self, hop: u64) -> Option<(u64, f64, DemodMode)> {
+        let hops = self.0.lock().unwrap();
+        let (offset, demod) = hops.to?;
+        (hops.hop != hop).then_some((hops.hop, offset, demod))
+    }
+
+    fn hear(&self, hop: u64, open: bool, stats: &ChannelStats) {
+        let mut hops = self.0.lock().unwrap();
+        hops.heard = Some((hop, open));
+        if hop == hops.hop {
+            hops.stats.merge(stats);
+        }
+    }
+}
+
+/// A sink block that demodulates one channel and sends its audio to the UI,
+/// and wherever else `routes` says. The UI hears when the squelch opens or
+/// closes. The channel hops where `listening` says, filtered through
+/// `passband`.
+#[derive(rustradio_macros::Block)]
+pub struct AudioSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    demodulator: AudioDemodulator,
+    routes: AudioRoutes,
+    /// How the stream is played back, if it is a file
+    playback: Option<Playback>,
+    squelch: SquelchThreshold,
+    listening: ListeningChannel,
+    passband: ChannelPassband,
+    /// Hop the demodulator is on, 0 for the channel it was built on
+    hop: u64,
+    /// Whether the squelch was open, as last told to the UI
+    squelch_open: Option<bool>,
+    /// Stretches the audio while played back off real time
+    stretch: Option<TimeStretch>,
+    /// Audio not yet sent
+    pending: Vec<f32>,
+    /// Whether `pending` is interleaved stereo
+    stereo: bool,
+}
+
+/// Where an `AudioSink`'s audio goes, and the controls it follows while it
+/// runs.
+pub struct AudioSettings {
+    pub routes: AudioRoutes,
+    /// How the stream is played back, if it is a file
+    pub playback: Option<Playback>,
+    pub squelch: SquelchThreshold,
+    pub listening: ListeningChannel,
+    pub passband: ChannelPassband,
+}
+
+impl AudioSink {
+    pub fn new(
+        src: ReadStream<Complex>,
+        event_tx: Sender<Event>,
+        demodulator: AudioDemodulator,
+        settings: AudioSettings,
+    ) -> Self {
+        let AudioSettings {
+            routes,
+            playback,
+            squelch,
+            listening,
+            passband,
+        } = settings;
+        Self {
+            src,
+            event_tx,
+            demodulator,
+            routes,
+            playback,
+            squelch,
+            listening,
+            passband,
+            hop: 0,
+            squelch_open: None,
+            stretch: None,
+            pending: Vec::new(),
+            stereo: false,
+        }
+    }
+}
+
+impl Block for AudioSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        if let Some((hop, offset, demod)) = self.listening.since(self.hop) {
+            // Audio at the old channel's rate goes out first
+            if !self.flush(self.demodulator.output_rate()) {
+                return Ok(BlockRet::EOF);
+            }
+            self.hop = hop;
+            self.demodulator.retune(offset, demod);
+            self.stretch = None;
+        }
+        let sample_rate = self.demodulator.output_rate();
+        self.demodulator.set_passband(self.passband.get());
+        let threshold = self.squelch.get();
+        self.demodulator.set_squelch(threshold);
+        let mut audio = self.demodulator.process_audio(input.slice());
+        let open = threshold.is_none() || self.demodulator.squelch_open();
+        let heard = self.demodulator.take_heard();
+        self.listening.hear(self.hop, open, &heard);
+        if self.squelch_open != Some(open) {
+            self.squelch_open = Some(open);
+            if self.event_tx.send(Event::SquelchState(open)).is_err() {
+                return Ok(BlockRet::EOF);
+            }
+        }
+        if let Some(data) = self.demodulator.take_rds()
+            && self.event_tx.send(Event::RdsData(data)).is_err()
+        {
+            return Ok(BlockRet::EOF);
+        }
+        // Keeps pace with a file played slower or faster, at the same pitch
+        match self.playback.as_ref().and_then(Playback::speed) {
+            Some(speed) if speed != 1.0 => {
+                let stretch = match &mut self.stretch {
+                    Some(stretch) if stretch.is_for(audio.stereo) => stretch,
+                    _ => self
+                        .stretch
+                        .insert(TimeStretch::new(sample_rate, audio.stereo)),
+                };
+                audio.samples = stretch.process(&audio.samples, speed);
+            }
+            _ => self.stretch = None,
+        }
+        // A chunk is all stereo or all mono
+        if audio.stereo != self.stereo && !self.flush(sample_rate) {
+            return Ok(BlockRet::EOF);
+        }
+        self.stereo = audio.stereo;
+        self.pending.extend(audio.samples);
+        let channels = if self.stereo { 2.0 } else { 1.0 };
+        if self.pending.len() as f64 >= CHUNK * sample_rate * channels && !self.flush(sample_rate) {
+            return Ok(BlockRet::EOF);
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
+
+impl AudioSink {
+    /// Send the pending audio on. Returns false if the UI has gone.
+    fn flush(&mut self, sample_rate: f64) -> bool {
+        let samples = std::mem::take(&mut self.pending);
+        if samples.is_empty() {
+            return true;
+        }
+        // Recordings and listeners get mono
+        let mono = Audio {
+            samples: samples.clone(),
+            stereo: self.stereo,
+        }
+        .into_mono();
+        if !self.routes.deliver(sample_rate, &mono) {
+            return true;
+        }
+        let chunk = AudioChunk {
+            sample_rate,
+            samples,
+            stereo: self.stereo,
+        };
+        self.event_tx.send(Event::AudioChunk(chunk)).is_ok()
+    }
+}
+
+/// A sink block that demodulates a VFO's channel and sends its audio where
+/// `routes` says, but not to the UI, which hears when its squelch opens or
+/// closes.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct VfoSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    id: u32,
+    /// Squelched and filtered as the VFO is set up
+    demodulator: AudioDemodulator,
+    routes: AudioRoutes,
+    /// Whether the squelch was open, as last told to the UI
+    #[rustradio(default)]
+    squelch_open: Option<bool>,
+    /// Audio not yet sent
+    #[rustradio(default)]
+    pending: Vec<f32>,
+}
+
+impl Block for VfoSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        let audio = self.demodulator.process_audio(input.slice());
+        let open = self.demodulator.squelch_open();
+        if self.squelch_open != Some(open) {
+            self.squelch_open = Some(open);
+            let event = Event::VfoSquelch { id: self.id, open };
+            if self.event_tx.send(event).is_err() {
+                return Ok(BlockRet::EOF);
+            }
+        }
+        self.pending.extend(audio.into_mono());
+        let sample_rate = self.demodulator.output_rate();
+        if self.pending.len() as f64 >= CHUNK * sample_rate {
+            self.routes
+                .deliver(sample_rate, &std::mem::take(&mut self.pending));
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
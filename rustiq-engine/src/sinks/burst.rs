@@ -0,0 +1,41 @@
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::dsp::BurstDetector;
+use rustiq_messages::Event;
+
+/// A sink block that detects bursts in the IQ stream and emits one event per burst.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct BurstSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    detector: BurstDetector,
+}
+
+impl Block for BurstSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        for detected in self.detector.process(input.slice()) {
+            if self
+                .event_tx
+                .send(Event::Burst(detected.to_message()))
+                .is_err()
+            {
+                return Ok(BlockRet::EOF);
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
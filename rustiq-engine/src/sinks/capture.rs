@@ -0,0 +1,42 @@
+use flume::Sender;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, Sample, rustradio_macros};
+
+/// A sink block that sends on the first `len` samples of its stream, then
+/// discards the rest so a branch it shares a tee with keeps flowing.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct CaptureSink<T: Sample> {
+    #[rustradio(in)]
+    src: ReadStream<T>,
+    len: usize,
+    tx: Sender<Vec<T>>,
+    #[rustradio(default)]
+    captured: Vec<T>,
+    #[rustradio(default)]
+    sent: bool,
+}
+
+impl<T: Sample> Block for CaptureSink<T> {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        if !self.sent {
+            let n = input.len().min(self.len - self.captured.len());
+            self.captured.extend_from_slice(&input.slice()[..n]);
+            if self.captured.len() == self.len {
+                self.sent = true;
+                // Nobody waiting for the capture is no reason to stop the graph
+                let _ = self.tx.send(std::mem::take(&mut self.captured));
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::dsp::CarrierMeter;
+use rustiq_messages::{CarrierMeasurement, Decibels, Event};
+
+/// A sink block that measures a carrier in the IQ stream and emits its frequency.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct CarrierSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    meter: CarrierMeter,
+    /// Absolute frequency of the IQ stream's DC, added to the meter's offsets
+    center_frequency: f64,
+}
+
+impl Block for CarrierSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        for estimate in self.meter.process(input.slice()) {
+            let measurement = CarrierMeasurement {
+                elapsed: Duration::from_secs_f64(estimate.elapsed),
+                frequency: self.center_frequency + estimate.offset,
+                power: Decibels::from_linear(estimate.power.sqrt()),
+            };
+            if self
+                .event_tx
+                .send(Event::CarrierMeasurement(measurement))
+                .is_err()
+            {
+                return Ok(BlockRet::EOF);
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
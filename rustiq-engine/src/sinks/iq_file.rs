@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::graph::CancellationToken;
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::integrity::{self, Checksummer};
+
+/// A sink block that writes samples to a file as little-endian `f32` I/Q
+/// pairs (SigMF `cf32_le`), checksumming them as they are written. After
+/// `limit` samples, if given, it cancels the graph if it has `cancel`, as
+/// live sources never run out; otherwise it drops the rest, leaving the
+/// graph running for its other sinks.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct IqFileSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    file: File,
+    path: PathBuf,
+    limit: Option<u64>,
+    cancel: Option<CancellationToken>,
+    /// Samples written so far, readable while the graph runs
+    written: Arc<AtomicU64>,
+    /// Shared with the recording, which finishes the manifest; `None` for
+    /// a recording without one
+    checksums: Option<Arc<Mutex<Checksummer>>>,
+}
+
+impl Block for IqFileSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let written = self.written.load(Ordering::Relaxed);
+        let full = self.limit.is_some_and(|limit| written >= limit);
+        if let (true, Some(cancel)) = (full, &self.cancel) {
+            cancel.cancel();
+            return Ok(BlockRet::EOF);
+        }
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+        if full {
+            let n = input.len();
+            input.consume(n);
+            return Ok(BlockRet::Again);
+        }
+
+        let remaining = self.limit.map_or(u64::MAX, |limit| limit - written);
+        let n = input
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let bytes: Vec<u8> = input.slice()[..n]
+            .iter()
+            .flat_map(|sample| [sample.re, sample.im])
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        // Written unbuffered, so nothing is lost when the graph is cancelled
+        self.file
+            .write_all(&bytes)
+            .map_err(|e| Error::file_io(e, &self.path))?;
+        if let Some(checksums) = &self.checksums {
+            checksums
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .update(&bytes)
+                .map_err(|e| Error::file_io(e, integrity::manifest_path(&self.path)))?;
+        }
+        self.written.fetch_add(n as u64, Ordering::Relaxed);
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
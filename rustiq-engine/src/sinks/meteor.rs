@@ -0,0 +1,41 @@
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::dsp::PingDetector;
+use rustiq_messages::Event;
+
+/// A sink block that watches one channel for meteor scatter pings and emits one event per ping.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct MeteorSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    detector: PingDetector,
+}
+
+impl Block for MeteorSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        for detected in self.detector.process(input.slice()) {
+            if self
+                .event_tx
+                .send(Event::MeteorPing(detected.to_message()))
+                .is_err()
+            {
+                return Ok(BlockRet::EOF);
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
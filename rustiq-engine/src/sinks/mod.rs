@@ -1,3 +1,37 @@
+#[cfg(feature = "adsb")]
+mod adsb;
+#[cfg(feature = "ais")]
+mod ais;
+mod audio;
+mod burst;
+mod capture;
+mod carrier;
+mod impulse;
+mod iq_file;
+mod meteor;
+#[cfg(feature = "selcall")]
+mod selcall;
 mod spectrum;
+#[cfg(feature = "sstv")]
+mod sstv;
+mod symbol_rate;
 
-pub use spectrum::SpectrumSink;
+#[cfg(feature = "adsb")]
+pub use adsb::AdsbSink;
+#[cfg(feature = "ais")]
+pub use ais::AisSink;
+pub use audio::{
+    AudioSettings, AudioSink, ChannelPassband, ListeningChannel, SquelchThreshold, VfoSink,
+};
+pub use burst::BurstSink;
+pub use capture::CaptureSink;
+pub use carrier::CarrierSink;
+pub use impulse::ImpulseSink;
+pub use iq_file::IqFileSink;
+pub use meteor::MeteorSink;
+#[cfg(feature = "selcall")]
+pub use selcall::SelCallSink;
+pub use spectrum::{DarkFrames, SpectrumSettings, SpectrumSink};
+#[cfg(feature = "sstv")]
+pub use sstv::SstvSink;
+pub use symbol_rate::SymbolRateSink;
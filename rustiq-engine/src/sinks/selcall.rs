@@ -0,0 +1,39 @@
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::dsp::{AudioDemodulator, SelCallDecoder};
+use rustiq_messages::Event;
+
+/// A sink block that demodulates one channel and decodes selective calls from its audio.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct SelCallSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    demodulator: AudioDemodulator,
+    decoder: SelCallDecoder,
+}
+
+impl Block for SelCallSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        let audio = self.demodulator.process(input.slice());
+        for call in self.decoder.process(&audio) {
+            if self.event_tx.send(Event::SelCall(call)).is_err() {
+                return Ok(BlockRet::EOF);
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
@@ -1,18 +1,207 @@
-use flume::Sender;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
 use rustradio::block::{Block, BlockRet};
 use rustradio::stream::ReadStream;
 use rustradio::{Error, rustradio_macros};
 
-use rustiq_messages::Event;
+use crate::SpectrumTx;
+use crate::dsp::{DarkFrame, DarkFrameCapture};
+use crate::tuner::CenterFrequency;
+use rustiq_messages::{Decibels, Discontinuity, Event, Hertz, SpectrumFrame};
+
+/// Starved of samples this long, the source counts as stalled.
+const STALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The dark frame subtracted from the spectrum and any being captured,
+/// shared between the engine and the graphs it builds so a capture carries
+/// on across rebuilds.
+#[derive(Clone, Default)]
+pub struct DarkFrames(Arc<Mutex<DarkFrameState>>);
+
+#[derive(Default)]
+struct DarkFrameState {
+    capture: Option<DarkFrameCapture>,
+    latest: Option<DarkFrame>,
+    /// Level of a dark frame captured since the engine last asked
+    captured: Option<Decibels>,
+    /// Whether the latest dark frame was dropped for not fitting the
+    /// frames, since the engine last asked
+    stale: bool,
+    /// When the capture last took a frame, or started
+    progress: Option<Instant>,
+}
+
+impl DarkFrames {
+    /// Average the next `frames` frames into a dark frame, instead of
+    /// sending them.
+    pub fn capture(&self, frames: usize) {
+        let mut state = self.0.lock().unwrap();
+        state.capture = Some(DarkFrameCapture::new(frames));
+        state.progress = Some(Instant::now());
+    }
+
+    /// Stop a capture, if one is running, sending frames again.
+    pub fn cancel(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.capture = None;
+        state.progress = None;
+    }
+
+    /// Stop subtracting and capturing.
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = DarkFrameState::default();
+    }
+
+    /// Level of the dark frame captured since this was last called.
+    pub fn take_captured(&self) -> Option<Decibels> {
+        self.0.lock().unwrap().captured.take()
+    }
+
+    /// Whether the dark frame stopped fitting the spectrum, retuned or
+    /// resized, since this was last called, so another is due.
+    pub fn take_stale(&self) -> bool {
+        std::mem::take(&mut self.0.lock().unwrap().stale)
+    }
+
+    /// Whether a capture is running but hasn't had a frame for `timeout`.
+    pub fn stalled(&self, timeout: Duration) -> bool {
+        let state = self.0.lock().unwrap();
+        state.capture.is_some() && state.progress.is_some_and(|at| at.elapsed() > timeout)
+    }
+
+    /// Subtract the dark frame from a frame of `magnitudes` taken at
+    /// `center_frequency`, or take the frame into the capture. Returns
+    /// whether the frame is to be sent.
+    fn apply(&self, center_frequency: Hertz, magnitudes: &mut [f32]) -> bool {
+        let mut state = self.0.lock().unwrap();
+        if let Some(capture) = &mut state.capture {
+            let finished = capture.push(center_frequency, magnitudes);
+            state.progress = Some(Instant::now());
+            if let Some(dark_frame) = finished {
+                state.captured = Some(dark_frame.level());
+                state.latest = Some(dark_frame);
+                state.capture = None;
+            }
+            return false;
+        }
+        match &state.latest {
+            Some(dark_frame) if dark_frame.fits(center_frequency, magnitudes.len()) => {
+                dark_frame.subtract(magnitudes);
+            }
+            Some(_) => {
+                state.latest = None;
+                state.stale = true;
+            }
+            None => {}
+        }
+        true
+    }
+}
+
+/// What a spectrum sink makes frames of, and how it numbers them.
+pub struct SpectrumSettings {
+    pub fft_size: usize,
+    /// FFT frames averaged into each frame the sink gets
+    pub stride: usize,
+    pub sample_rate: f64,
+    /// Factor to scale each bin by, for a calibrated front end; empty if none
+    pub correction: Vec<f32>,
+    /// Frequency the frames are centered on, for the UI to line up frames
+    /// from different tunings. Followed as the graph is retuned.
+    pub center_frequency: CenterFrequency,
+    /// How far off the center frequency the frames are centered
+    pub frequency_offset: i64,
+    /// Index of the source the frames are from
+    pub source: usize,
+    /// Next frame's sequence number, shared across graph rebuilds
+    pub sequence: Arc<AtomicU64>,
+    /// Highest level of each bin so far, if peak hold is on; empty until
+    /// the first frame
+    pub peak_hold: Option<Vec<f32>>,
+    /// Lowest level of each bin so far, if min hold is on
+    pub min_hold: Option<Vec<f32>>,
+    /// Flag for the first frame
+    pub discontinuity: Option<Discontinuity>,
+    /// Front-end artifacts subtracted from the frames
+    pub dark_frames: DarkFrames,
+}
 
 /// A sink block that consumes f32 spectrum data and sends it via flume channel.
 #[derive(rustradio_macros::Block)]
-#[rustradio(new)]
 pub struct SpectrumSink {
     #[rustradio(in)]
     src: ReadStream<f32>,
-    event_tx: Sender<Event>,
+    tx: SpectrumTx,
     fft_size: usize,
+    /// FFT frames averaged into each frame the sink gets
+    stride: usize,
+    sample_rate: f64,
+    /// Factor to scale each bin by, for a calibrated front end; empty if none
+    correction: Vec<f32>,
+    /// Frequency the frames are centered on, for the UI to line up frames
+    /// from different tunings. Followed as the graph is retuned.
+    center_frequency: CenterFrequency,
+    /// How far off the center frequency the frames are centered
+    frequency_offset: i64,
+    /// Index of the source the frames are from
+    source: usize,
+    /// Next frame's sequence number, shared across graph rebuilds
+    sequence: Arc<AtomicU64>,
+    /// Highest level of each bin so far, if peak hold is on; empty until
+    /// the first frame
+    peak_hold: Option<Vec<f32>>,
+    /// Lowest level of each bin so far, if min hold is on
+    min_hold: Option<Vec<f32>>,
+    /// Flag for the next frame
+    discontinuity: Option<Discontinuity>,
+    /// Front-end artifacts subtracted from the frames
+    dark_frames: DarkFrames,
+    /// When the sink last ran out of samples
+    starved_since: Option<Instant>,
+    /// Samples consumed by this graph's sink
+    samples: u64,
+    /// Center frequency the holds were taken at
+    held_at: Option<Hertz>,
+}
+
+impl SpectrumSink {
+    pub fn new(src: ReadStream<f32>, tx: SpectrumTx, settings: SpectrumSettings) -> Self {
+        let SpectrumSettings {
+            fft_size,
+            stride,
+            sample_rate,
+            correction,
+            center_frequency,
+            frequency_offset,
+            source,
+            sequence,
+            peak_hold,
+            min_hold,
+            discontinuity,
+            dark_frames,
+        } = settings;
+        Self {
+            src,
+            tx,
+            fft_size,
+            stride,
+            sample_rate,
+            correction,
+            center_frequency,
+            frequency_offset,
+            source,
+            sequence,
+            peak_hold,
+            min_hold,
+            discontinuity,
+            dark_frames,
+            starved_since: None,
+            samples: 0,
+            held_at: None,
+        }
+    }
 }
 
 impl Block for SpectrumSink {
@@ -23,9 +212,16 @@ impl Block for SpectrumSink {
 
         let (input, _tags) = self.src.read_buf()?;
 
-        // Wait until we have at least one FFT frame
+        // Wait until we have at least one FFT frame. Waiting on the stream
+        // lets a graph whose input has ended finish
         if input.len() < self.fft_size {
-            return Ok(BlockRet::Pending);
+            self.starved_since.get_or_insert_with(Instant::now);
+            return Ok(BlockRet::WaitForStream(&self.src, self.fft_size));
+        }
+        if let Some(since) = self.starved_since.take()
+            && since.elapsed() > STALL_TIMEOUT
+        {
+            self.discontinuity.get_or_insert(Discontinuity::SourceStall);
         }
 
         // Only process one FFT frame at a time
@@ -37,19 +233,70 @@ impl Block for SpectrumSink {
         // FFT shift: move DC from edges to center
         // This rearranges [DC, positive, negative] -> [negative, DC, positive]
         spectrum_data.rotate_left(n / 2);
+        for (magnitude, factor) in spectrum_data.iter_mut().zip(&self.correction) {
+            *magnitude *= factor;
+        }
 
-        // Block the pipeline to provide backpressure if the UI is behind
-        if self
-            .event_tx
-            .send(Event::SpectrumData(spectrum_data))
-            .is_err()
-        {
+        // The terminated input's frames go into the dark frame, not the UI
+        let center_frequency = self.center_frequency.get();
+        if !self.dark_frames.apply(center_frequency, &mut spectrum_data) {
+            input.consume(n);
+            self.samples += (n * self.stride) as u64;
+            return Ok(BlockRet::Again);
+        }
+
+        // Levels from another tuning don't belong in the holds
+        if self.held_at != Some(center_frequency) {
+            self.held_at = Some(center_frequency);
+            self.peak_hold.iter_mut().for_each(Vec::clear);
+            self.min_hold.iter_mut().for_each(Vec::clear);
+        }
+        if let Some(peak_hold) = &mut self.peak_hold {
+            hold(peak_hold, &spectrum_data, f32::max);
+        }
+        if let Some(min_hold) = &mut self.min_hold {
+            hold(min_hold, &spectrum_data, f32::min);
+        }
+
+        let frame = SpectrumFrame {
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed),
+            sample_time: Duration::from_secs_f64(self.samples as f64 / self.sample_rate),
+            timestamp: SystemTime::now(),
+            source: self.source,
+            discontinuity: self.discontinuity.take(),
+            center_frequency: Hertz(
+                center_frequency
+                    .0
+                    .saturating_add_signed(self.frequency_offset),
+            ),
+            sample_rate: Hertz(self.sample_rate as u64),
+            magnitudes: spectrum_data,
+            peak_hold: self.peak_hold.clone(),
+            min_hold: self.min_hold.clone(),
+        };
+
+        // Waits for the UI if it is behind, or drops the oldest frame, the
+        // skipped sequence number telling the UI a frame is missing
+        if !self.tx.send(Event::SpectrumData(frame)) {
             return Ok(BlockRet::EOF);
         }
 
         // Consume the FFT frame
         input.consume(n);
+        self.samples += (n * self.stride) as u64;
 
         Ok(BlockRet::Again)
     }
 }
+
+/// Fold `frame` into the held levels by `keep`, which picks the level to
+/// keep of the held one and the new one. An empty hold takes the frame.
+fn hold(held: &mut Vec<f32>, frame: &[f32], keep: fn(f32, f32) -> f32) {
+    if held.len() != frame.len() {
+        *held = frame.to_vec();
+        return;
+    }
+    for (held, &level) in held.iter_mut().zip(frame) {
+        *held = keep(*held, level);
+    }
+}
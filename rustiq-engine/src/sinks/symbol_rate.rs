@@ -0,0 +1,43 @@
+use flume::Sender;
+use rustradio::Complex;
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::ReadStream;
+use rustradio::{Error, rustradio_macros};
+
+use crate::dsp::SymbolRateEstimator;
+use rustiq_messages::{Event, SignalRegion, SymbolRateEstimate};
+
+/// A sink block that estimates the symbol rate of one region once, emits the
+/// estimate, then discards the rest of its stream.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new)]
+pub struct SymbolRateSink {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    event_tx: Sender<Event>,
+    region: SignalRegion,
+    estimator: SymbolRateEstimator,
+}
+
+impl Block for SymbolRateSink {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let (input, _tags) = self.src.read_buf()?;
+        if input.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.src, 1));
+        }
+
+        if let Some(candidates) = self.estimator.process(input.slice()) {
+            let estimate = SymbolRateEstimate {
+                region: self.region,
+                candidates,
+            };
+            if self.event_tx.send(Event::SymbolRate(estimate)).is_err() {
+                return Ok(BlockRet::EOF);
+            }
+        }
+
+        let n = input.len();
+        input.consume(n);
+        Ok(BlockRet::Again)
+    }
+}
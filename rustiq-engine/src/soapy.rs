@@ -0,0 +1,167 @@
+//! SoapySDR devices: finding them for the capabilities, and opening one as
+//! the source. Only the first receive channel of a device is used.
+
+use log::warn;
+use rustiq_messages::{Decibels, FrequencyRange, GainStage, Hertz, SourceDevice, StageGain};
+use rustradio::Complex;
+use rustradio::blocks::SoapySdrSource;
+use rustradio::stream::ReadStream;
+use soapysdr::{Args, Device, Direction, Range};
+
+use crate::sources::hardware_rate;
+use crate::tuner::Tuner;
+
+const CHANNEL: usize = 0;
+
+/// Rates offered within a range a device runs at anything in.
+const COMMON_RATES: [u64; 11] = [
+    250_000, 1_000_000, 2_000_000, 2_400_000, 2_500_000, 5_000_000, 8_000_000, 10_000_000,
+    20_000_000, 40_000_000, 56_000_000,
+];
+
+/// The devices SoapySDR finds, with the settings their drivers offer.
+/// Devices that fail to open are left out.
+pub fn devices() -> Vec<SourceDevice> {
+    let found = match soapysdr::enumerate("") {
+        Ok(found) => found,
+        Err(e) => {
+            warn!("Failed to list SoapySDR devices: {}", e);
+            return Vec::new();
+        }
+    };
+    found
+        .iter()
+        .filter_map(|args| match describe(args) {
+            Ok(device) => Some(device),
+            Err(e) => {
+                warn!(
+                    "Failed to query SoapySDR device {}: {}",
+                    args_string(args),
+                    e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+fn describe(args: &Args) -> Result<SourceDevice, soapysdr::Error> {
+    let args_string = args_string(args);
+    let device = Device::new(args_string.as_str())?;
+    let gain_stages = device
+        .list_gains(Direction::Rx, CHANNEL)?
+        .into_iter()
+        .map(|name| {
+            let range = device.gain_element_range(Direction::Rx, CHANNEL, name.as_str())?;
+            Ok(GainStage {
+                name,
+                min: Decibels(range.minimum as f32),
+                max: Decibels(range.maximum as f32),
+            })
+        })
+        .collect::<Result<Vec<_>, soapysdr::Error>>()?;
+    let label = args
+        .get("label")
+        .or_else(|| args.get("driver"))
+        .unwrap_or(args_string.as_str())
+        .to_string();
+    Ok(SourceDevice {
+        label,
+        antennas: device.antennas(Direction::Rx, CHANNEL)?,
+        gain_stages,
+        bandwidth: span(&device.bandwidth_range(Direction::Rx, CHANNEL)?),
+        sample_rates: sample_rates(&device.get_sample_rate_range(Direction::Rx, CHANNEL)?),
+        args: args_string,
+    })
+}
+
+/// Open the device `args` names, following `tuner`, with the stage gains
+/// in `gains` and the driver's choice of anything left unset. It runs at
+/// `sample_rate` if it can, else at the nearest rate above it. Returns the
+/// source and its stream with the rate it runs at and how far above the
+/// tuner's frequency it is tuned.
+pub fn open(
+    args: &str,
+    tuner: &mut Tuner,
+    sample_rate: Hertz,
+    antenna: Option<String>,
+    bandwidth: Option<Hertz>,
+    gains: &[StageGain],
+) -> anyhow::Result<(SoapySdrSource, ReadStream<Complex>, Hertz, Hertz)> {
+    let device = Device::new(args)?;
+    let ranges = device.get_sample_rate_range(Direction::Rx, CHANNEL)?;
+    let rate = sample_rate.as_hz() as f64;
+    let device_rate = if ranges
+        .iter()
+        .any(|range| range.minimum <= rate && rate <= range.maximum)
+    {
+        sample_rate
+    } else {
+        hardware_rate(&sample_rates(&ranges), sample_rate)
+    };
+    let lo_offset = tuner.lo_offset(device_rate);
+    let mut builder = SoapySdrSource::builder(
+        &device,
+        (tuner.frequency().as_hz() + lo_offset.as_hz()) as f64,
+        device_rate.as_hz() as f64,
+    );
+    if let Some(antenna) = antenna {
+        builder = builder.antenna(antenna);
+    }
+    let (source, stream) = builder.build()?;
+    // After building, which sets the overall gain to the middle of its range
+    for StageGain { stage, gain } in gains {
+        device.set_gain_element(Direction::Rx, CHANNEL, stage.as_str(), gain.0 as f64)?;
+    }
+    if let Some(bandwidth) = bandwidth {
+        device.set_bandwidth(Direction::Rx, CHANNEL, bandwidth.as_hz() as f64)?;
+    }
+    tuner.follow(move |frequency| {
+        device.set_frequency(
+            Direction::Rx,
+            CHANNEL,
+            (frequency.as_hz() + lo_offset.as_hz()) as f64,
+            Args::new(),
+        )?;
+        Ok(())
+    });
+    Ok((source, stream, device_rate, lo_offset))
+}
+
+/// Arguments in the `key=value,...` form that open the device again.
+fn args_string(args: &Args) -> String {
+    args.iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Rates to offer from the ranges a device runs at, lowest first: each
+/// discrete one, and the ends of each range with the common rates within it.
+fn sample_rates(ranges: &[Range]) -> Vec<Hertz> {
+    let mut rates: Vec<Hertz> = ranges
+        .iter()
+        .flat_map(|range| {
+            let within = COMMON_RATES
+                .iter()
+                .map(|&rate| rate as f64)
+                .filter(|&rate| range.minimum < rate && rate < range.maximum);
+            [range.minimum, range.maximum].into_iter().chain(within)
+        })
+        .filter(|&rate| rate > 0.0)
+        .map(|rate| Hertz(rate.round() as u64))
+        .collect();
+    rates.sort();
+    rates.dedup();
+    rates
+}
+
+/// The whole span of a list of ranges, if there is one.
+fn span(ranges: &[Range]) -> Option<FrequencyRange> {
+    let start = ranges.iter().map(|range| range.minimum).reduce(f64::min)?;
+    let end = ranges.iter().map(|range| range.maximum).reduce(f64::max)?;
+    (end > 0.0).then(|| FrequencyRange {
+        start: Hertz(start.round() as u64),
+        end: Hertz(end.round() as u64),
+    })
+}
@@ -0,0 +1,213 @@
+use std::f64::consts::TAU;
+
+use rustiq_messages::{Decibels, Hertz, Sweep, Tone};
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+/// Most samples generated per call.
+const CHUNK: usize = 8192;
+
+/// A tone being generated.
+struct Oscillator {
+    /// Frequency, in cycles per sample
+    frequency: f64,
+    amplitude: f64,
+    /// Phase, in cycles
+    phase: f64,
+}
+
+/// Repeatable white Gaussian noise.
+struct Noise {
+    /// Standard deviation of I and of Q
+    deviation: f64,
+    state: u64,
+}
+
+impl Noise {
+    /// A uniform value in (0, 1], by xorshift.
+    fn uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        ((self.state >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A sample of the noise, by the Box-Muller transform.
+    fn sample(&mut self) -> Complex {
+        let radius = self.deviation * (-2.0 * self.uniform().ln()).sqrt();
+        let (sin, cos) = (TAU * self.uniform()).sin_cos();
+        Complex::new((radius * cos) as f32, (radius * sin) as f32)
+    }
+}
+
+/// A source block that generates the sum of tones, swept up together if
+/// asked, with white Gaussian noise added if asked.
+#[derive(rustradio_macros::Block)]
+pub struct SignalGenerator {
+    oscillators: Vec<Oscillator>,
+    noise: Option<Noise>,
+    /// Rise of the tones per sample through the sweep, in cycles per
+    /// sample, and the sweep's length in samples
+    sweep: Option<(f64, u64)>,
+    /// Samples generated
+    position: u64,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+}
+
+impl SignalGenerator {
+    /// Generate `tones` at `sample_rate`, with noise `snr` below the
+    /// strongest of them, or below full scale without any.
+    pub fn new(
+        sample_rate: Hertz,
+        tones: &[Tone],
+        snr: Option<Decibels>,
+        sweep: Option<Sweep>,
+    ) -> (Self, ReadStream<Complex>) {
+        let rate = sample_rate.as_hz().max(1) as f64;
+        let oscillators: Vec<Oscillator> = tones
+            .iter()
+            .map(|tone| Oscillator {
+                frequency: tone.frequency.as_hz() as f64 / rate,
+                amplitude: f64::from(tone.amplitude.to_linear()),
+                phase: 0.0,
+            })
+            .collect();
+        let noise = snr.map(|snr| {
+            let strongest = oscillators
+                .iter()
+                .map(|oscillator| oscillator.amplitude)
+                .reduce(f64::max)
+                .unwrap_or(1.0);
+            let power = strongest * strongest / 10f64.powf(f64::from(snr.0) / 10.0);
+            Noise {
+                deviation: (power / 2.0).sqrt(),
+                state: 0x2545_f491_4f6c_dd1d,
+            }
+        });
+        let sweep = sweep.map(|sweep| {
+            let samples = (sweep.period.as_secs_f64() * rate).round().max(1.0) as u64;
+            (sweep.span.as_hz() as f64 / rate / samples as f64, samples)
+        });
+        let (dst, dr) = rustradio::stream::new_stream();
+        (
+            Self {
+                oscillators,
+                noise,
+                sweep,
+                position: 0,
+                dst,
+            },
+            dr,
+        )
+    }
+
+    fn next(&mut self) -> Complex {
+        let rise = self.sweep.map_or(0.0, |(step, samples)| {
+            step * (self.position % samples) as f64
+        });
+        self.position += 1;
+        let mut sample = self
+            .noise
+            .as_mut()
+            .map_or(Complex::new(0.0, 0.0), Noise::sample);
+        for oscillator in &mut self.oscillators {
+            let (sin, cos) = (TAU * oscillator.phase).sin_cos();
+            sample += Complex::new(
+                (oscillator.amplitude * cos) as f32,
+                (oscillator.amplitude * sin) as f32,
+            );
+            oscillator.phase = (oscillator.phase + oscillator.frequency + rise).fract();
+        }
+        sample
+    }
+}
+
+impl Block for SignalGenerator {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let mut o = self.dst.write_buf()?;
+        let n = o.len().min(CHUNK);
+        if n == 0 {
+            return Ok(BlockRet::WaitForStream(&self.dst, 1));
+        }
+        let samples: Vec<Complex> = (0..n).map(|_| self.next()).collect();
+        o.slice()[..n].copy_from_slice(&samples);
+        o.produce(n, &[]);
+        Ok(BlockRet::Again)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn generate(
+        tones: &[Tone],
+        snr: Option<Decibels>,
+        sweep: Option<Sweep>,
+        len: usize,
+    ) -> Vec<Complex> {
+        let (mut generator, _) = SignalGenerator::new(Hertz(48_000), tones, snr, sweep);
+        (0..len).map(|_| generator.next()).collect()
+    }
+
+    fn tone(frequency: u64, amplitude: f32) -> Tone {
+        Tone {
+            frequency: Hertz(frequency),
+            amplitude: Decibels(amplitude),
+        }
+    }
+
+    /// Level of `samples` at `frequency`, by correlating with it.
+    fn level_at(samples: &[Complex], frequency: f64) -> f64 {
+        let sum: Complex = samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                let (sin, cos) = (-TAU * frequency * i as f64 / 48_000.0).sin_cos();
+                sample * Complex::new(cos as f32, sin as f32)
+            })
+            .sum();
+        f64::from(sum.norm()) / samples.len() as f64
+    }
+
+    #[test]
+    fn sums_its_tones() {
+        let samples = generate(&[tone(1_000, 0.0), tone(6_000, -20.0)], None, None, 48_000);
+        assert!((level_at(&samples, 1_000.0) - 1.0).abs() < 1e-3);
+        assert!((level_at(&samples, 6_000.0) - 0.1).abs() < 1e-3);
+        assert!(level_at(&samples, 3_000.0) < 1e-3);
+    }
+
+    #[test]
+    fn adds_noise_below_the_strongest_tone() {
+        let samples = generate(&[tone(1_000, -6.0)], Some(Decibels(20.0)), None, 48_000);
+        let tone_power = level_at(&samples, 1_000.0).powi(2);
+        let total: f64 = samples
+            .iter()
+            .map(|sample| f64::from(sample.norm_sqr()))
+            .sum::<f64>()
+            / samples.len() as f64;
+        let snr = 10.0 * (tone_power / (total - tone_power)).log10();
+        assert!((snr - 20.0).abs() < 0.2, "{snr}");
+    }
+
+    #[test]
+    fn sweeps_its_tones_up_and_starts_again() {
+        // From 1 kHz to 11 kHz over a second
+        let sweep = Sweep {
+            span: Hertz(10_000),
+            period: Duration::from_secs(1),
+        };
+        let samples = generate(&[tone(1_000, 0.0)], None, Some(sweep), 96_000);
+        let frequency = |at: usize| {
+            let step = (samples[at + 1] * samples[at].conj()).arg();
+            f64::from(step) * 48_000.0 / TAU
+        };
+        assert!((frequency(0) - 1_000.0).abs() < 1.0);
+        assert!((frequency(24_000) - 6_000.0).abs() < 1.0);
+        assert!((frequency(48_000) - 1_000.0).abs() < 1.0);
+    }
+}
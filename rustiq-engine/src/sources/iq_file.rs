@@ -0,0 +1,321 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use rustiq_messages::{Hertz, IqFormat, RustIqError, SourceKind};
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+use super::wav::{self, WavEncoding};
+
+/// How each I and Q value is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    F32,
+    U8,
+    I8,
+    I16,
+}
+
+impl Encoding {
+    /// Bytes of one I or Q value.
+    fn value_size(self) -> usize {
+        match self {
+            Encoding::F32 => 4,
+            Encoding::U8 | Encoding::I8 => 1,
+            Encoding::I16 => 2,
+        }
+    }
+
+    /// The value in `bytes`, scaled to a full scale of 1.
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Encoding::F32 => f32::from_le_bytes(bytes[..4].try_into().unwrap()),
+            Encoding::U8 => (f32::from(bytes[0]) - 127.5) / 127.5,
+            Encoding::I8 => f32::from(bytes[0] as i8) / 128.0,
+            Encoding::I16 => f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32768.0,
+        }
+    }
+}
+
+/// A source block that reads an IQ file stored in any of the `IqFormat`s,
+/// converting its samples to `Complex`.
+#[derive(rustradio_macros::Block)]
+pub struct IqFileSource {
+    path: PathBuf,
+    file: BufReader<File>,
+    encoding: Encoding,
+    /// Values per sample: 2 for I and Q, 1 for a real signal
+    channels: usize,
+    /// Sample rate the file's header gives
+    sample_rate: Option<Hertz>,
+    /// Samples in the file
+    len: u64,
+    /// Bytes of samples left to read; a WAV file may carry other chunks
+    /// after its samples
+    remaining: u64,
+    /// Bytes of a sample cut off by the last read
+    partial: Vec<u8>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+}
+
+impl IqFileSource {
+    pub fn new(path: &Path, format: IqFormat) -> Result<(Self, ReadStream<Complex>), RustIqError> {
+        let unreadable = |e: std::io::Error| RustIqError::Source {
+            kind: SourceKind::File,
+            detail: format!("can't read {}: {}", path.display(), e),
+        };
+        let malformed = |detail: String| RustIqError::Format {
+            path: path.to_path_buf(),
+            detail,
+        };
+        let mut file = File::open(path).map_err(unreadable)?;
+        let file_len = file.metadata().map_err(unreadable)?.len();
+        let mut header_rate = None;
+        let (encoding, channels, remaining) = match format {
+            IqFormat::Cf32 => (Encoding::F32, 2, u64::MAX),
+            IqFormat::Cu8 => (Encoding::U8, 2, u64::MAX),
+            IqFormat::Cs8 => (Encoding::I8, 2, u64::MAX),
+            IqFormat::Cs16 => (Encoding::I16, 2, u64::MAX),
+            IqFormat::Wav => {
+                let header = wav::read_header(&mut file).map_err(|e| malformed(e.to_string()))?;
+                // Two channels are I and Q; one is a real signal, as from
+                // a receiver's audio output
+                if !matches!(header.channels, 1 | 2) {
+                    return Err(malformed(format!(
+                        "{} channels, not I and Q",
+                        header.channels
+                    )));
+                }
+                let encoding = match header.encoding {
+                    WavEncoding::U8 => Encoding::U8,
+                    WavEncoding::I16 => Encoding::I16,
+                    WavEncoding::F32 => Encoding::F32,
+                };
+                file.seek(SeekFrom::Start(header.data_start))
+                    .map_err(unreadable)?;
+                header_rate = Some(Hertz(u64::from(header.sample_rate)));
+                (encoding, usize::from(header.channels), header.data_len)
+            }
+        };
+        let size = (encoding.value_size() * channels) as u64;
+        let len = remaining.min(file_len) / size;
+        debug!("Reading {} as {:?}", path.display(), format);
+        let (dst, dr) = rustradio::stream::new_stream();
+        Ok((
+            Self {
+                path: path.to_path_buf(),
+                file: BufReader::new(file),
+                encoding,
+                channels,
+                sample_rate: header_rate,
+                len,
+                remaining,
+                partial: Vec::new(),
+                dst,
+            },
+            dr,
+        ))
+    }
+
+    /// Sample rate the file says it was recorded at, for formats that
+    /// carry one.
+    pub fn sample_rate(&self) -> Option<Hertz> {
+        self.sample_rate
+    }
+
+    /// Samples in the file, from where its samples start.
+    pub fn samples(&self) -> u64 {
+        self.len
+    }
+
+    /// Start reading `samples` in, rather than from the first sample.
+    /// Must be called before anything is read.
+    pub fn skip(&mut self, samples: u64) -> Result<(), Error> {
+        let size = (self.encoding.value_size() * self.channels) as u64;
+        let bytes = samples.min(self.len) * size;
+        self.file
+            .seek_relative(bytes as i64)
+            .map_err(|e| Error::file_io(e, &self.path))?;
+        self.remaining -= bytes;
+        Ok(())
+    }
+
+    /// Read up to `max` samples, or `None` at the end of the file. Fewer
+    /// samples, even none, may be read before the end.
+    pub fn read_samples(&mut self, max: usize) -> Result<Option<Vec<Complex>>, Error> {
+        let value_size = self.encoding.value_size();
+        let size = value_size * self.channels;
+        let want = (max * size - self.partial.len())
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let mut bytes = std::mem::take(&mut self.partial);
+        let start = bytes.len();
+        bytes.resize(start + want, 0);
+        let read = self
+            .file
+            .read(&mut bytes[start..])
+            .map_err(|e| Error::file_io(e, &self.path))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.remaining -= read as u64;
+        bytes.truncate(start + read);
+
+        let samples = bytes
+            .chunks_exact(size)
+            .map(|sample| {
+                let q = if self.channels == 2 {
+                    self.encoding.decode(&sample[value_size..])
+                } else {
+                    0.0
+                };
+                Complex::new(self.encoding.decode(sample), q)
+            })
+            .collect();
+        self.partial = bytes.split_off(bytes.len() - bytes.len() % size);
+        Ok(Some(samples))
+    }
+}
+
+impl Block for IqFileSource {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let mut output = self.dst.write_buf()?;
+        if output.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.dst, 1));
+        }
+        let Some(samples) = self.read_samples(output.len())? else {
+            debug!("End of {}", self.path.display());
+            return Ok(BlockRet::EOF);
+        };
+        output.slice()[..samples.len()].copy_from_slice(&samples);
+        output.produce(samples.len(), &[]);
+        Ok(BlockRet::Again)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every sample `format` stored as `bytes` reads as.
+    fn read(format: IqFormat, bytes: &[u8]) -> Vec<Complex> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, bytes).unwrap();
+        let (mut source, stream) = IqFileSource::new(file.path(), format).unwrap();
+        while !matches!(source.work().unwrap(), BlockRet::EOF) {}
+        let (samples, _) = stream.read_buf().unwrap();
+        samples.slice().to_vec()
+    }
+
+    /// A 48 kHz WAV file of `samples` in `channels` channels, with `bits`
+    /// per value in format `tag`, followed by a chunk of other data.
+    fn wav(tag: u16, channels: u16, bits: u16, samples: &[u8]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend(tag.to_le_bytes());
+        fmt.extend(channels.to_le_bytes());
+        fmt.extend(48_000u32.to_le_bytes());
+        fmt.extend((48_000 * u32::from(channels * bits) / 8).to_le_bytes());
+        fmt.extend((channels * bits / 8).to_le_bytes());
+        fmt.extend(bits.to_le_bytes());
+        let mut file = b"RIFF\0\0\0\0WAVE".to_vec();
+        for (id, chunk) in [(b"fmt ", &fmt[..]), (b"data", samples), (b"LIST", b"info")] {
+            file.extend(id);
+            file.extend((chunk.len() as u32).to_le_bytes());
+            file.extend(chunk);
+        }
+        file
+    }
+
+    #[test]
+    fn converts_integer_formats_to_full_scale() {
+        assert_eq!(
+            read(IqFormat::Cu8, &[255, 0, 127, 128]),
+            [
+                Complex::new(1.0, -1.0),
+                Complex::new(-0.5 / 127.5, 0.5 / 127.5)
+            ]
+        );
+        assert_eq!(read(IqFormat::Cs8, &[0x80, 64]), [Complex::new(-1.0, 0.5)]);
+        let cs16: Vec<u8> = [i16::MIN, 16_384]
+            .into_iter()
+            .flat_map(i16::to_le_bytes)
+            .collect();
+        assert_eq!(read(IqFormat::Cs16, &cs16), [Complex::new(-1.0, 0.5)]);
+    }
+
+    #[test]
+    fn reads_cf32_and_drops_a_trailing_partial_sample() {
+        let mut bytes: Vec<u8> = [0.25f32, -0.75]
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        bytes.extend([1, 2, 3]);
+        assert_eq!(read(IqFormat::Cf32, &bytes), [Complex::new(0.25, -0.75)]);
+    }
+
+    #[test]
+    fn reads_only_the_samples_of_a_wav_file() {
+        let samples: Vec<u8> = [i16::MAX, i16::MIN, 0, 16_384]
+            .into_iter()
+            .flat_map(i16::to_le_bytes)
+            .collect();
+        let iq = read(IqFormat::Wav, &wav(1, 2, 16, &samples));
+        assert_eq!(iq.len(), 2);
+        assert!((iq[0].re - 1.0).abs() < 1e-4);
+        assert_eq!(iq[0].im, -1.0);
+        assert_eq!(iq[1], Complex::new(0.0, 0.5));
+
+        let floats: Vec<u8> = [0.5f32, -0.5]
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        assert_eq!(
+            read(IqFormat::Wav, &wav(3, 2, 32, &floats)),
+            [Complex::new(0.5, -0.5)]
+        );
+    }
+
+    #[test]
+    fn reads_mono_wav_files_as_real_signals() {
+        let samples: Vec<u8> = [16_384i16, -16_384]
+            .into_iter()
+            .flat_map(i16::to_le_bytes)
+            .collect();
+        assert_eq!(
+            read(IqFormat::Wav, &wav(1, 1, 16, &samples)),
+            [Complex::new(0.5, 0.0), Complex::new(-0.5, 0.0)]
+        );
+    }
+
+    #[test]
+    fn takes_the_sample_rate_from_a_wav_header() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &wav(1, 2, 16, &[0; 8])).unwrap();
+        let (source, _) = IqFileSource::new(file.path(), IqFormat::Wav).unwrap();
+        assert_eq!(source.sample_rate(), Some(Hertz(48_000)));
+        let (source, _) = IqFileSource::new(file.path(), IqFormat::Cs16).unwrap();
+        assert_eq!(source.sample_rate(), None);
+    }
+
+    #[test]
+    fn skips_to_a_later_sample() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &wav(1, 2, 16, &[0, 0, 0, 0, 0, 64, 0, 192])).unwrap();
+        let (mut source, stream) = IqFileSource::new(file.path(), IqFormat::Wav).unwrap();
+        assert_eq!(source.samples(), 2);
+        source.skip(1).unwrap();
+        while !matches!(source.work().unwrap(), BlockRet::EOF) {}
+        let (samples, _) = stream.read_buf().unwrap();
+        assert_eq!(samples.slice(), [Complex::new(0.5, -0.5)]);
+    }
+
+    #[test]
+    fn rejects_wav_files_with_more_channels_than_iq() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &wav(1, 4, 16, &[0; 8])).unwrap();
+        assert!(IqFileSource::new(file.path(), IqFormat::Wav).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+mod generator;
+mod iq_file;
+mod rates;
+mod rtl_tcp;
+mod spyserver;
+mod wav;
+
+pub use generator::SignalGenerator;
+pub use iq_file::IqFileSource;
+pub use rates::{RTL_SDR_RATES, hardware_rate};
+pub use rtl_tcp::RtlTcpSource;
+pub use spyserver::SpyServerSource;
@@ -0,0 +1,64 @@
+//! Sample rates sources run at, and picking one for a rate asked for that
+//! the hardware doesn't support.
+
+use rustiq_messages::Hertz;
+
+/// Rates an RTL-SDR dongle runs at without dropping samples. It takes
+/// others, but with its resampler's artifacts.
+pub const RTL_SDR_RATES: [Hertz; 11] = [
+    Hertz(250_000),
+    Hertz(1_024_000),
+    Hertz(1_536_000),
+    Hertz(1_792_000),
+    Hertz(1_920_000),
+    Hertz(2_048_000),
+    Hertz(2_160_000),
+    Hertz(2_400_000),
+    Hertz(2_560_000),
+    Hertz(2_880_000),
+    Hertz(3_200_000),
+];
+
+/// The rate of `rates` to run hardware at for a stream of `requested`:
+/// that rate if it is one, else the nearest above it to resample down
+/// from, or the highest if none is. Any rate goes for an empty list.
+pub fn hardware_rate(rates: &[Hertz], requested: Hertz) -> Hertz {
+    if rates.is_empty() || rates.contains(&requested) {
+        return requested;
+    }
+    rates
+        .iter()
+        .copied()
+        .filter(|&rate| rate >= requested)
+        .min()
+        .or_else(|| rates.iter().copied().max())
+        .unwrap_or(requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_at_a_supported_rate_as_asked() {
+        assert_eq!(
+            hardware_rate(&RTL_SDR_RATES, Hertz(2_048_000)),
+            Hertz(2_048_000)
+        );
+        assert_eq!(hardware_rate(&[], Hertz(123)), Hertz(123));
+    }
+
+    #[test]
+    fn resamples_from_the_nearest_rate_above() {
+        assert_eq!(
+            hardware_rate(&RTL_SDR_RATES, Hertz::mhz(2)),
+            Hertz(2_048_000)
+        );
+        assert_eq!(hardware_rate(&RTL_SDR_RATES, Hertz(48_000)), Hertz(250_000));
+        // Above them all, the highest is as near as it gets
+        assert_eq!(
+            hardware_rate(&RTL_SDR_RATES, Hertz::mhz(10)),
+            Hertz(3_200_000)
+        );
+    }
+}
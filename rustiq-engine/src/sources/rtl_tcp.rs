@@ -0,0 +1,173 @@
+//! A client of `rtl_tcp`, which serves an RTL-SDR dongle over TCP: a
+//! 12-byte header, then unsigned 8-bit I/Q pairs for as long as the
+//! connection lasts. The dongle is controlled with 5-byte commands sent
+//! back, so it can be retuned while streaming.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use anyhow::Context;
+use log::{debug, warn};
+use rustiq_messages::{Decibels, Hertz, RustIqError};
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+/// Start of the header `rtl_tcp` sends on connecting.
+const MAGIC: &[u8; 4] = b"RTL0";
+
+/// Bytes read from the connection at a time.
+const READ_SIZE: usize = 16 * 1024;
+
+/// Commands `rtl_tcp` takes, each followed by a big-endian `u32`.
+const SET_FREQUENCY: u8 = 0x01;
+const SET_SAMPLE_RATE: u8 = 0x02;
+/// 0 for the tuner's automatic gain, 1 for the gain set
+const SET_GAIN_MODE: u8 = 0x03;
+/// In tenths of a dB
+const SET_GAIN: u8 = 0x04;
+
+/// Sends control commands to an `rtl_tcp` server.
+pub struct RtlTcpControl(TcpStream);
+
+impl RtlTcpControl {
+    pub fn set_frequency(&mut self, frequency: Hertz) -> io::Result<()> {
+        let frequency = u32::try_from(frequency.as_hz()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{frequency} is out of rtl_tcp's range"),
+            )
+        })?;
+        self.send(SET_FREQUENCY, frequency)
+    }
+
+    fn send(&mut self, command: u8, param: u32) -> io::Result<()> {
+        let mut message = [command, 0, 0, 0, 0];
+        message[1..].copy_from_slice(&param.to_be_bytes());
+        self.0.write_all(&message)
+    }
+}
+
+/// A source block streaming from an `rtl_tcp` server. A thread reads the
+/// connection, so a slow network doesn't hold up the graph.
+#[derive(rustradio_macros::Block)]
+pub struct RtlTcpSource {
+    rx: Receiver<Vec<u8>>,
+    /// Shuts the connection down when the graph is done with it
+    stream: TcpStream,
+    /// Bytes received but not yet converted, at most half a sample
+    partial: Option<u8>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+}
+
+impl RtlTcpSource {
+    /// Connect to `address` and set the dongle to `frequency`,
+    /// `sample_rate` and `gain`. Returns the source, its stream, and control
+    /// of the dongle for retuning.
+    pub fn connect(
+        address: &str,
+        frequency: Hertz,
+        sample_rate: Hertz,
+        gain: Decibels,
+    ) -> anyhow::Result<(Self, ReadStream<Complex>, RtlTcpControl)> {
+        let mut stream =
+            TcpStream::connect(address).with_context(|| format!("connecting to {address}"))?;
+        let mut header = [0; 12];
+        stream
+            .read_exact(&mut header)
+            .with_context(|| format!("reading the header from {address}"))?;
+        if &header[..4] != MAGIC {
+            anyhow::bail!(RustIqError::Protocol {
+                peer: address.to_string(),
+                detail: "not an rtl_tcp server".into(),
+            });
+        }
+        let tuner = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        debug!("Connected to rtl_tcp at {}, tuner type {}", address, tuner);
+
+        let mut control = RtlTcpControl(stream.try_clone()?);
+        control.send(SET_SAMPLE_RATE, sample_rate.as_hz() as u32)?;
+        control.send(SET_GAIN_MODE, 1)?;
+        control.send(SET_GAIN, (gain.0 * 10.0).round().max(0.0) as u32)?;
+        control.set_frequency(frequency)?;
+
+        let (tx, rx) = mpsc::sync_channel(64);
+        let mut reader = stream.try_clone()?;
+        let address = address.to_string();
+        thread::spawn(move || {
+            loop {
+                let mut buf = vec![0; READ_SIZE];
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        buf.truncate(n);
+                        if tx.send(buf).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        warn!("Lost the connection to rtl_tcp at {}: {}", address, e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let (dst, dr) = rustradio::stream::new_stream();
+        Ok((
+            Self {
+                rx,
+                stream,
+                partial: None,
+                dst,
+            },
+            dr,
+            control,
+        ))
+    }
+}
+
+impl Drop for RtlTcpSource {
+    fn drop(&mut self) {
+        // Ends the reader thread's read
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// An unsigned 8-bit I or Q value, scaled to a full scale of 1.
+fn decode(value: u8) -> f32 {
+    (f32::from(value) - 127.5) / 127.5
+}
+
+impl Block for RtlTcpSource {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        let mut o = self.dst.write_buf()?;
+        // Room for all of a read
+        let wanted = READ_SIZE / 2 + 1;
+        if o.len() < wanted {
+            return Ok(BlockRet::WaitForStream(&self.dst, wanted));
+        }
+        let bytes = match self.rx.try_recv() {
+            Ok(bytes) => bytes,
+            Err(TryRecvError::Empty) => return Ok(BlockRet::Pending),
+            Err(TryRecvError::Disconnected) => return Ok(BlockRet::EOF),
+        };
+        let mut values = self.partial.take().into_iter().chain(bytes);
+        let mut produced = 0;
+        let out = o.slice();
+        while let Some(i) = values.next() {
+            let Some(q) = values.next() else {
+                self.partial = Some(i);
+                break;
+            };
+            out[produced] = Complex::new(decode(i), decode(q));
+            produced += 1;
+        }
+        o.produce(produced, &[]);
+        Ok(BlockRet::Again)
+    }
+}
@@ -0,0 +1,300 @@
+//! A client of SpyServer, which serves an Airspy or RTL-SDR to many
+//! listeners at once, each taking a decimated slice of the device's band.
+//!
+//! After a hello carrying the protocol version the server describes the
+//! device and the client's share of it; the client then picks the IQ stream,
+//! its format, frequency and decimation with settings and turns it on. All
+//! of it is little-endian: commands are a type and body size then the body,
+//! the server's messages a 20-byte header then theirs.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use anyhow::Context;
+use log::{debug, warn};
+use rustiq_messages::{Hertz, RustIqError};
+use rustradio::block::{Block, BlockRet};
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, Error, rustradio_macros};
+
+/// Version 2.0.1700, the one current servers speak.
+const PROTOCOL_VERSION: u32 = (2 << 24) | 1700;
+
+/// Name the client introduces itself with.
+const CLIENT_NAME: &[u8] = b"RustIQ";
+
+/// Commands to the server.
+const CMD_HELLO: u32 = 0;
+const CMD_SET_SETTING: u32 = 2;
+
+/// Settings, each set to a `u32`.
+const SETTING_STREAMING_MODE: u32 = 0;
+const SETTING_STREAMING_ENABLED: u32 = 1;
+/// The device's gain step, from 0 to its maximum
+const SETTING_GAIN: u32 = 2;
+const SETTING_IQ_FORMAT: u32 = 100;
+const SETTING_IQ_FREQUENCY: u32 = 101;
+/// Halvings of the device's rate
+const SETTING_IQ_DECIMATION: u32 = 102;
+
+/// Streaming mode for IQ alone.
+const STREAM_TYPE_IQ: u32 = 1;
+/// IQ format asked for, though a server may force its own.
+const STREAM_FORMAT_INT16: u32 = 2;
+
+/// Server messages, in the low 16 bits of their type.
+const MSG_TYPE_DEVICE_INFO: u32 = 0;
+const MSG_TYPE_CLIENT_SYNC: u32 = 1;
+const MSG_TYPE_UINT8_IQ: u32 = 100;
+const MSG_TYPE_INT16_IQ: u32 = 101;
+const MSG_TYPE_FLOAT_IQ: u32 = 103;
+
+/// Largest message body believed, well above the IQ buffers servers send.
+const MAX_BODY: usize = 1 << 20;
+
+/// What the server says about its device.
+#[derive(Debug)]
+struct DeviceInfo {
+    max_sample_rate: u32,
+    decimation_stages: u32,
+    max_gain_index: u32,
+    min_decimation: u32,
+}
+
+impl DeviceInfo {
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        let field = |i: usize| {
+            body.get(4 * i..4 * i + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .context("short device info")
+        };
+        if field(0)? == 0 {
+            anyhow::bail!("the server has no device");
+        }
+        Ok(Self {
+            max_sample_rate: field(2)?,
+            decimation_stages: field(4)?,
+            max_gain_index: field(6)?,
+            min_decimation: field(10)?,
+        })
+    }
+
+    /// The decimation giving the lowest rate at least `sample_rate`, or the
+    /// nearest the device allows.
+    fn decimation_for(&self, sample_rate: Hertz) -> u32 {
+        let mut decimation = self.min_decimation;
+        while decimation < self.decimation_stages
+            && (self.max_sample_rate >> (decimation + 1)) as u64 >= sample_rate.as_hz()
+        {
+            decimation += 1;
+        }
+        decimation
+    }
+}
+
+/// Sends settings to a SpyServer.
+pub struct SpyServerControl(TcpStream);
+
+impl SpyServerControl {
+    pub fn set_frequency(&mut self, frequency: Hertz) -> io::Result<()> {
+        let frequency = u32::try_from(frequency.as_hz()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{frequency} is out of SpyServer's range"),
+            )
+        })?;
+        self.set(SETTING_IQ_FREQUENCY, frequency)
+    }
+
+    fn set(&mut self, setting: u32, value: u32) -> io::Result<()> {
+        let body: Vec<u8> = [setting, value]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        self.send(CMD_SET_SETTING, &body)
+    }
+
+    fn send(&mut self, command: u32, body: &[u8]) -> io::Result<()> {
+        let mut message = Vec::with_capacity(8 + body.len());
+        message.extend_from_slice(&command.to_le_bytes());
+        message.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        message.extend_from_slice(body);
+        self.0.write_all(&message)
+    }
+}
+
+/// Read one message from the server, returning its type and body.
+fn read_message(stream: &mut impl Read) -> io::Result<(u32, Vec<u8>)> {
+    let mut header = [0; 20];
+    stream.read_exact(&mut header)?;
+    let word = |i: usize| u32::from_le_bytes(header[4 * i..4 * i + 4].try_into().unwrap());
+    let body_size = word(4) as usize;
+    if body_size > MAX_BODY {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("a message of {body_size} bytes"),
+        ));
+    }
+    let mut body = vec![0; body_size];
+    stream.read_exact(&mut body)?;
+    Ok((word(1) & 0xFFFF, body))
+}
+
+/// IQ samples in a message of `message_type`, scaled to a full scale of 1,
+/// or None if it holds none.
+fn decode(message_type: u32, body: &[u8]) -> Option<Vec<Complex>> {
+    let values: Vec<f32> = match message_type {
+        MSG_TYPE_UINT8_IQ => body
+            .iter()
+            .map(|&value| (f32::from(value) - 127.5) / 127.5)
+            .collect(),
+        MSG_TYPE_INT16_IQ => body
+            .chunks_exact(2)
+            .map(|bytes| f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32_768.0)
+            .collect(),
+        MSG_TYPE_FLOAT_IQ => body
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect(),
+        _ => return None,
+    };
+    Some(
+        values
+            .chunks_exact(2)
+            .map(|pair| Complex::new(pair[0], pair[1]))
+            .collect(),
+    )
+}
+
+/// A source block streaming IQ from a SpyServer. A thread reads the
+/// connection, so a slow network doesn't hold up the graph.
+#[derive(rustradio_macros::Block)]
+pub struct SpyServerSource {
+    rx: Receiver<Vec<Complex>>,
+    /// Shuts the connection down when the graph is done with it
+    stream: TcpStream,
+    /// Samples received but not yet written out
+    pending: Vec<Complex>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+}
+
+impl SpyServerSource {
+    /// Connect to `address` and stream IQ at `frequency`, at the device
+    /// rate's nearest halving to `sample_rate` and gain step `gain`. Returns
+    /// the source, its stream, the rate it streams at, and control of the
+    /// server for retuning.
+    pub fn connect(
+        address: &str,
+        frequency: Hertz,
+        sample_rate: Hertz,
+        gain: u32,
+    ) -> anyhow::Result<(Self, ReadStream<Complex>, Hertz, SpyServerControl)> {
+        let mut stream =
+            TcpStream::connect(address).with_context(|| format!("connecting to {address}"))?;
+        let mut control = SpyServerControl(stream.try_clone()?);
+        let mut hello = PROTOCOL_VERSION.to_le_bytes().to_vec();
+        hello.extend_from_slice(CLIENT_NAME);
+        control.send(CMD_HELLO, &hello)?;
+
+        // The device, then whether this client may control it
+        let mut device = None;
+        let can_control = loop {
+            let (message_type, body) = read_message(&mut stream)
+                .with_context(|| format!("reading the handshake from {address}"))?;
+            match message_type {
+                MSG_TYPE_DEVICE_INFO => {
+                    let info = DeviceInfo::parse(&body).map_err(|e| RustIqError::Protocol {
+                        peer: address.to_string(),
+                        detail: format!("{e:#}"),
+                    })?;
+                    device = Some(info);
+                }
+                MSG_TYPE_CLIENT_SYNC if device.is_some() => {
+                    break body.get(..4).is_some_and(|can| can != [0; 4]);
+                }
+                _ => {}
+            }
+        };
+        let device = device.unwrap();
+        debug!("Connected to SpyServer at {}: {:?}", address, device);
+
+        let decimation = device.decimation_for(sample_rate);
+        let rate = Hertz(u64::from(device.max_sample_rate >> decimation));
+        control.set(SETTING_STREAMING_MODE, STREAM_TYPE_IQ)?;
+        control.set(SETTING_IQ_FORMAT, STREAM_FORMAT_INT16)?;
+        control.set_frequency(frequency)?;
+        control.set(SETTING_IQ_DECIMATION, decimation)?;
+        if can_control {
+            control.set(SETTING_GAIN, gain.min(device.max_gain_index))?;
+        } else {
+            warn!("SpyServer at {} keeps the gain to itself", address);
+        }
+        control.set(SETTING_STREAMING_ENABLED, 1)?;
+
+        let (tx, rx) = mpsc::sync_channel(64);
+        let mut reader = stream.try_clone()?;
+        let address = address.to_string();
+        thread::spawn(move || {
+            loop {
+                match read_message(&mut reader) {
+                    Ok((message_type, body)) => {
+                        if let Some(samples) = decode(message_type, &body)
+                            && tx.send(samples).is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => {
+                        warn!("Lost the connection to SpyServer at {}: {}", address, e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let (dst, dr) = rustradio::stream::new_stream();
+        Ok((
+            Self {
+                rx,
+                stream,
+                pending: Vec::new(),
+                dst,
+            },
+            dr,
+            rate,
+            control,
+        ))
+    }
+}
+
+impl Drop for SpyServerSource {
+    fn drop(&mut self) {
+        // Ends the reader thread's read
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+impl Block for SpyServerSource {
+    fn work(&mut self) -> Result<BlockRet<'_>, Error> {
+        if self.pending.is_empty() {
+            self.pending = match self.rx.try_recv() {
+                Ok(samples) => samples,
+                Err(TryRecvError::Empty) => return Ok(BlockRet::Pending),
+                Err(TryRecvError::Disconnected) => return Ok(BlockRet::EOF),
+            };
+        }
+        let mut o = self.dst.write_buf()?;
+        if o.is_empty() {
+            return Ok(BlockRet::WaitForStream(&self.dst, 1));
+        }
+        let n = o.len().min(self.pending.len());
+        o.slice()[..n].copy_from_slice(&self.pending[..n]);
+        o.produce(n, &[]);
+        self.pending.drain(..n);
+        Ok(BlockRet::Again)
+    }
+}
@@ -0,0 +1,108 @@
+//! The RIFF header of a WAV file: enough of it to find the samples and how
+//! they are stored.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// WAVE_FORMAT_PCM
+const FORMAT_PCM: u16 = 1;
+/// WAVE_FORMAT_IEEE_FLOAT
+const FORMAT_FLOAT: u16 = 3;
+/// WAVE_FORMAT_EXTENSIBLE, whose subformat holds one of the above
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// How a WAV file's samples are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavEncoding {
+    /// Unsigned 8-bit PCM
+    U8,
+    /// Little-endian signed 16-bit PCM
+    I16,
+    /// Little-endian 32-bit float
+    F32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WavHeader {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub encoding: WavEncoding,
+    /// Offset of the first sample in the file
+    pub data_start: u64,
+    /// Bytes of samples, `u64::MAX` if they run to the end of the file
+    pub data_len: u64,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Read the header of the WAV file `file`, leaving it at the first sample.
+pub fn read_header(file: &mut (impl Read + Seek)) -> io::Result<WavHeader> {
+    let mut riff = [0; 12];
+    file.read_exact(&mut riff)?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(invalid("not a WAV file"));
+    }
+
+    let mut format = None;
+    loop {
+        let mut chunk = [0; 8];
+        file.read_exact(&mut chunk)
+            .map_err(|_| invalid("no data chunk"))?;
+        let len = read_u32(&chunk, 4);
+        match &chunk[..4] {
+            b"fmt " => {
+                if len < 16 {
+                    return Err(invalid("short fmt chunk"));
+                }
+                let mut fmt = vec![0; len as usize];
+                file.read_exact(&mut fmt)?;
+                let mut tag = read_u16(&fmt, 0);
+                if tag == FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+                    tag = read_u16(&fmt, 24);
+                }
+                let encoding = match (tag, read_u16(&fmt, 14)) {
+                    (FORMAT_PCM, 8) => WavEncoding::U8,
+                    (FORMAT_PCM, 16) => WavEncoding::I16,
+                    (FORMAT_FLOAT, 32) => WavEncoding::F32,
+                    (tag, bits) => {
+                        return Err(invalid(format!(
+                            "unsupported WAV samples (format {tag}, {bits} bits)"
+                        )));
+                    }
+                };
+                format = Some((read_u32(&fmt, 4), read_u16(&fmt, 2), encoding));
+                // Chunks are padded to an even length
+                if len % 2 == 1 {
+                    file.seek(SeekFrom::Current(1))?;
+                }
+            }
+            b"data" => {
+                let (sample_rate, channels, encoding) =
+                    format.ok_or_else(|| invalid("data chunk ahead of fmt chunk"))?;
+                return Ok(WavHeader {
+                    sample_rate,
+                    channels,
+                    encoding,
+                    data_start: file.stream_position()?,
+                    // Recorders streaming to disk may not fill the size in
+                    data_len: match len {
+                        0 | u32::MAX => u64::MAX,
+                        len => u64::from(len),
+                    },
+                });
+            }
+            _ => {
+                file.seek(SeekFrom::Current(i64::from(len + len % 2)))?;
+            }
+        }
+    }
+}
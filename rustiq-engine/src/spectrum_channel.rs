@@ -0,0 +1,193 @@
+//! The spectrum path's end of its channel to the UI. When the UI falls
+//! behind and the channel fills, it either waits for room or drops the
+//! oldest frame queued, so what the UI reads next is as recent as can be.
+//! On a channel shared with the other events, it drops the new frame
+//! instead, as what is queued may not be a frame. Either way it counts the
+//! frames, for the engine to report the flow.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use flume::{Receiver, Sender, TrySendError};
+use rustiq_messages::Event;
+
+use crate::Overflow;
+
+/// Frames handed to a spectrum channel, across its clones.
+#[derive(Debug, Default)]
+struct Counts {
+    sent: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Sends spectrum frames to the UI, doing what its overflow says when the
+/// channel is full. Clones share the channel and the counts.
+#[derive(Clone)]
+pub struct SpectrumTx {
+    tx: Sender<Event>,
+    full: Full,
+    counts: Arc<Counts>,
+}
+
+/// What a spectrum channel does when it is full.
+#[derive(Clone)]
+enum Full {
+    Wait,
+    /// Take the oldest frame off through the channel's reading end, only
+    /// kept when dropping, as it holds the channel open
+    DropOldest(Receiver<Event>),
+    DropNewest,
+}
+
+impl SpectrumTx {
+    /// The sending end of the channel `tx` and `rx` are the ends of.
+    pub fn new(tx: Sender<Event>, rx: &Receiver<Event>, overflow: Overflow) -> Self {
+        let full = match overflow {
+            Overflow::Block => Full::Wait,
+            Overflow::Drop => Full::DropOldest(rx.clone()),
+        };
+        Self {
+            tx,
+            full,
+            counts: Arc::default(),
+        }
+    }
+
+    /// Send on `tx`, a channel the other events go on too. Dropping, a
+    /// full channel drops the new frame.
+    pub fn shared(tx: Sender<Event>, overflow: Overflow) -> Self {
+        let full = match overflow {
+            Overflow::Block => Full::Wait,
+            Overflow::Drop => Full::DropNewest,
+        };
+        Self {
+            tx,
+            full,
+            counts: Arc::default(),
+        }
+    }
+
+    /// Send `event`, making room for it if the channel is full and frames
+    /// are dropped. Returns whether the UI's end is still there.
+    pub fn send(&self, event: Event) -> bool {
+        let oldest = match &self.full {
+            Full::Wait => {
+                if self.tx.send(event).is_err() {
+                    return false;
+                }
+                self.counts.sent.fetch_add(1, Ordering::Relaxed);
+                return true;
+            }
+            Full::DropNewest => {
+                match self.tx.try_send(event) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        self.counts.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(TrySendError::Disconnected(_)) => return false,
+                }
+                self.counts.sent.fetch_add(1, Ordering::Relaxed);
+                return true;
+            }
+            Full::DropOldest(oldest) => oldest,
+        };
+        // The end kept here doesn't count as the UI's
+        if self.tx.receiver_count() <= 1 {
+            return false;
+        }
+        let mut event = event;
+        loop {
+            match self.tx.try_send(event) {
+                Ok(()) => break,
+                Err(TrySendError::Full(unsent)) => {
+                    // The UI may have read it meanwhile, leaving room anyway
+                    if oldest.try_recv().is_ok() {
+                        self.counts.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    event = unsent;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+        self.counts.sent.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Frames sent so far, and of those, frames dropped from the channel
+    /// unread.
+    pub fn counts(&self) -> (u64, u64) {
+        (
+            self.counts.sent.load(Ordering::Relaxed),
+            self.counts.dropped.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use rustiq_messages::{Decibels, Hertz, SpectrumFrame};
+
+    fn frame(sequence: u64) -> Event {
+        Event::SpectrumData(SpectrumFrame {
+            sequence,
+            sample_time: Duration::ZERO,
+            timestamp: UNIX_EPOCH,
+            source: 0,
+            discontinuity: None,
+            center_frequency: Hertz(0),
+            sample_rate: Hertz(48_000),
+            magnitudes: vec![0.5, 1.0, 2.0],
+            peak_hold: None,
+            min_hold: None,
+        })
+    }
+
+    fn sequence(event: Event) -> u64 {
+        match event {
+            Event::SpectrumData(frame) => frame.sequence,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn drops_the_oldest_frame_when_full() {
+        let (tx, rx) = flume::bounded(2);
+        let spectrum = SpectrumTx::new(tx, &rx, Overflow::Drop);
+        for i in 0..5 {
+            assert!(spectrum.send(frame(i)));
+        }
+        assert_eq!(spectrum.counts(), (5, 3));
+        let sequences: Vec<u64> = rx.try_iter().map(sequence).collect();
+        assert_eq!(sequences, [3, 4]);
+    }
+
+    #[test]
+    fn fails_once_the_ui_is_gone() {
+        let (tx, rx) = flume::bounded(2);
+        let spectrum = SpectrumTx::new(tx, &rx, Overflow::Drop);
+        drop(rx);
+        assert!(!spectrum.send(frame(0)));
+
+        let (tx, rx) = flume::bounded(2);
+        let spectrum = SpectrumTx::new(tx, &rx, Overflow::Block);
+        drop(rx);
+        assert!(!spectrum.send(frame(0)));
+        assert_eq!(spectrum.counts(), (0, 0));
+    }
+
+    #[test]
+    fn drops_the_newest_frame_on_a_shared_channel() {
+        let (tx, rx) = flume::bounded(2);
+        let spectrum = SpectrumTx::shared(tx.clone(), Overflow::Drop);
+        tx.send(Event::DarkFrameCaptured(Decibels(0.0))).unwrap();
+        for i in 0..3 {
+            assert!(spectrum.send(frame(i)));
+        }
+        assert_eq!(spectrum.counts(), (3, 2));
+        assert!(matches!(rx.try_recv(), Ok(Event::DarkFrameCaptured(_))));
+        assert_eq!(rx.try_iter().map(sequence).collect::<Vec<_>>(), [0]);
+    }
+}
@@ -0,0 +1,245 @@
+//! The analyses and decoders the engine can attach to the IQ stream, each a
+//! `SubGraph`. The decoders are only built with their cargo feature.
+
+use rustiq_messages::{AudioChannel, Decibels, Hertz, MeteorConfig, SignalRegion, Vfo};
+use rustradio::Complex;
+
+use super::audio_routing::AudioRoutes;
+use super::chain::{ChainBuilder, Ports, SubGraph};
+use super::dsp::{
+    AudioDemodulator, BurstDetector, CarrierMeter, ImpulseDetector, PingDetector,
+    SymbolRateEstimator,
+};
+use super::sinks::{
+    AudioSettings, AudioSink, BurstSink, CarrierSink, ImpulseSink, MeteorSink, SymbolRateSink,
+    VfoSink,
+};
+
+/// Offset of `frequency` from the stream's DC.
+fn offset(ports: &Ports, frequency: Hertz) -> f64 {
+    frequency.as_hz() as f64 - ports.center_frequency.as_hz() as f64
+}
+
+pub struct CarrierMeasurement(pub Hertz);
+
+impl SubGraph for CarrierMeasurement {
+    fn name(&self) -> String {
+        format!("Carrier measurement at {}", self.0)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let meter = CarrierMeter::new(input.sample_rate() as f64, offset(ports, self.0));
+        let center = ports.center_frequency.as_hz() as f64;
+        input.sink(|src| CarrierSink::new(src, ports.event_tx.clone(), meter, center));
+    }
+}
+
+pub struct BurstDetection(pub Decibels);
+
+impl SubGraph for BurstDetection {
+    fn name(&self) -> String {
+        format!("Burst detection {} above the floor", self.0)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let detector = BurstDetector::new(input.sample_rate() as f64, self.0.to_power());
+        input.sink(|src| BurstSink::new(src, ports.event_tx.clone(), detector));
+    }
+}
+
+pub struct MeteorDetection(pub MeteorConfig);
+
+impl SubGraph for MeteorDetection {
+    fn name(&self) -> String {
+        format!("Meteor scatter detection at {}", self.0.frequency)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let config = self.0;
+        let detector = PingDetector::new(
+            input.sample_rate() as f64,
+            offset(ports, config.frequency),
+            config.bandwidth.as_hz() as f64,
+            config.threshold.to_power(),
+        );
+        input.sink(|src| MeteorSink::new(src, ports.event_tx.clone(), detector));
+    }
+}
+
+pub struct ImpulseCounter(pub Decibels);
+
+impl SubGraph for ImpulseCounter {
+    fn name(&self) -> String {
+        format!("Impulse counter {} above the floor", self.0)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let detector = ImpulseDetector::new(input.sample_rate() as f64, self.0.to_power());
+        input.sink(|src| ImpulseSink::new(src, ports.event_tx.clone(), detector));
+    }
+}
+
+pub struct SymbolRateEstimation(pub SignalRegion);
+
+impl SubGraph for SymbolRateEstimation {
+    fn name(&self) -> String {
+        format!("Symbol rate estimation at {}", self.0.frequency)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let region = self.0;
+        let estimator = SymbolRateEstimator::new(
+            input.sample_rate() as f64,
+            offset(ports, region.frequency),
+            region.bandwidth.as_hz() as f64,
+        );
+        input.sink(|src| SymbolRateSink::new(src, ports.event_tx.clone(), region, estimator));
+    }
+}
+
+pub struct Demodulator(pub AudioChannel);
+
+impl SubGraph for Demodulator {
+    fn name(&self) -> String {
+        format!(
+            "{} demodulator at {}",
+            self.0.demod.label(),
+            self.0.frequency
+        )
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let channel = self.0;
+        let demodulator = AudioDemodulator::new(
+            input.sample_rate() as f64,
+            offset(ports, channel.frequency),
+            channel.demod,
+        );
+        input.sink(|src| {
+            AudioSink::new(
+                src,
+                ports.event_tx.clone(),
+                demodulator,
+                AudioSettings {
+                    routes: ports.audio_routes.clone(),
+                    playback: ports.playback.clone(),
+                    squelch: ports.squelch.clone(),
+                    listening: ports.listening.clone(),
+                    passband: ports.passband.clone(),
+                },
+            )
+        });
+    }
+}
+
+/// A VFO's own down-conversion and demodulator, its audio going to its
+/// routes.
+pub struct VfoReceiver(pub Vfo, pub AudioRoutes);
+
+impl SubGraph for VfoReceiver {
+    fn name(&self) -> String {
+        let channel = self.0.config.channel;
+        format!(
+            "VFO {}: {} demodulator at {}",
+            self.0.id,
+            channel.demod.label(),
+            channel.frequency
+        )
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let VfoReceiver(vfo, routes) = *self;
+        let channel = vfo.config.channel;
+        let mut demodulator = AudioDemodulator::with_passband(
+            input.sample_rate() as f64,
+            offset(ports, channel.frequency),
+            channel.demod,
+            vfo.config.passband.unwrap_or(channel.demod.passband()),
+        );
+        demodulator.set_squelch(vfo.config.squelch);
+        input.sink(|src| VfoSink::new(src, ports.event_tx.clone(), vfo.id, demodulator, routes));
+    }
+}
+
+#[cfg(feature = "sstv")]
+pub struct SstvChannel(pub rustiq_messages::AudioChannel);
+
+#[cfg(feature = "sstv")]
+impl SubGraph for SstvChannel {
+    fn name(&self) -> String {
+        format!("SSTV decoder at {}", self.0.frequency)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        use super::dsp::{AudioDemodulator, SstvDecoder};
+        use super::sinks::SstvSink;
+
+        let channel = self.0;
+        let demodulator = AudioDemodulator::new(
+            input.sample_rate() as f64,
+            offset(ports, channel.frequency),
+            channel.demod,
+        );
+        let decoder = SstvDecoder::new(demodulator.output_rate());
+        input.sink(|src| SstvSink::new(src, ports.event_tx.clone(), demodulator, decoder));
+    }
+}
+
+#[cfg(feature = "selcall")]
+pub struct SelCallChannel(pub rustiq_messages::SelCallConfig);
+
+#[cfg(feature = "selcall")]
+impl SubGraph for SelCallChannel {
+    fn name(&self) -> String {
+        format!("SelCall decoder at {}", self.0.channel.frequency)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        use super::dsp::{AudioDemodulator, SelCallDecoder};
+        use super::sinks::SelCallSink;
+
+        let config = self.0;
+        let demodulator = AudioDemodulator::new(
+            input.sample_rate() as f64,
+            offset(ports, config.channel.frequency),
+            config.channel.demod,
+        );
+        let decoder = SelCallDecoder::new(demodulator.output_rate(), config.standard);
+        input.sink(|src| SelCallSink::new(src, ports.event_tx.clone(), demodulator, decoder));
+    }
+}
+
+#[cfg(feature = "ais")]
+pub struct AisChannel(pub Hertz);
+
+#[cfg(feature = "ais")]
+impl SubGraph for AisChannel {
+    fn name(&self) -> String {
+        format!("AIS decoder at {}", self.0)
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        use super::dsp::AisDecoder;
+        use super::sinks::AisSink;
+
+        let decoder = AisDecoder::new(input.sample_rate() as f64, offset(ports, self.0));
+        input.sink(|src| AisSink::new(src, ports.event_tx.clone(), decoder));
+    }
+}
+
+/// ADS-B decoding of the whole stream; needs a sample rate that is a
+/// multiple of 2 MHz, so the decoder is made up front.
+#[cfg(feature = "adsb")]
+pub struct AdsbReceiver(pub super::dsp::AdsbDecoder);
+
+#[cfg(feature = "adsb")]
+impl SubGraph for AdsbReceiver {
+    fn name(&self) -> String {
+        "ADS-B decoder".to_string()
+    }
+
+    fn build(self: Box<Self>, input: ChainBuilder<'_, Complex>, ports: &Ports) {
+        let decoder = self.0;
+        input.sink(|src| super::sinks::AdsbSink::new(src, ports.event_tx.clone(), decoder));
+    }
+}
@@ -0,0 +1,159 @@
+//! Retuning a running graph. The center frequency is shared with the blocks
+//! that label their output with it, and sources that can change frequency
+//! while streaming are retuned directly, so a retune needs no rebuild.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::warn;
+use rustiq_messages::Hertz;
+use rustradio::stream::{ReadStream, WriteStream};
+use rustradio::{Complex, rustradio_macros};
+
+use crate::dsp::Nco;
+
+/// With offset tuning, hardware is tuned this fraction of its sample rate
+/// above the center: far enough to take the DC spike of a zero-IF front end
+/// clear of a channel at the center, near enough that only the top of the
+/// band, where the anti-alias filter rolls off anyway, wraps around to the
+/// bottom once shifted back.
+const LO_OFFSET_DIVISOR: u64 = 16;
+
+/// Center frequency of a running graph, read by its blocks as they go.
+#[derive(Debug, Clone)]
+pub struct CenterFrequency(Arc<AtomicU64>);
+
+impl CenterFrequency {
+    pub fn get(&self) -> Hertz {
+        Hertz(self.0.load(Ordering::Relaxed))
+    }
+}
+
+type Retune = Box<dyn FnMut(Hertz) -> anyhow::Result<()> + Send>;
+
+/// Tunes the sources of one graph.
+pub struct Tuner {
+    center_frequency: CenterFrequency,
+    /// Set the frequency of the graph's hardware sources
+    retunes: Vec<Retune>,
+    /// A source can only change frequency by being reopened
+    fixed: bool,
+    /// Tune hardware sources off the center, and shift their samples back
+    offset_tuning: bool,
+}
+
+impl Tuner {
+    pub fn new(center_frequency: Hertz) -> Self {
+        Self {
+            center_frequency: CenterFrequency(Arc::new(AtomicU64::new(center_frequency.0))),
+            retunes: Vec::new(),
+            fixed: false,
+            offset_tuning: false,
+        }
+    }
+
+    /// Have hardware sources tuned off the center, by their `lo_offset`.
+    pub fn with_offset_tuning(mut self, enabled: bool) -> Self {
+        self.offset_tuning = enabled;
+        self
+    }
+
+    pub fn frequency(&self) -> Hertz {
+        self.center_frequency.get()
+    }
+
+    /// The center frequency, for a block to follow.
+    pub fn center_frequency(&self) -> CenterFrequency {
+        self.center_frequency.clone()
+    }
+
+    /// Tune the running graph to `frequency`. Returns false if it has to be
+    /// rebuilt instead: a source is fixed, or failed to retune.
+    pub fn retune(&mut self, frequency: Hertz) -> bool {
+        if self.fixed {
+            return false;
+        }
+        for retune in &mut self.retunes {
+            if let Err(e) = retune(frequency) {
+                warn!("Failed to retune the source to {}: {}", frequency, e);
+                return false;
+            }
+        }
+        self.center_frequency
+            .0
+            .store(frequency.0, Ordering::Relaxed);
+        true
+    }
+}
+
+/// For hardware sources.
+impl Tuner {
+    /// How far above the center to tune a hardware source running at
+    /// `sample_rate`; zero without offset tuning.
+    pub fn lo_offset(&self, sample_rate: Hertz) -> Hertz {
+        if self.offset_tuning {
+            Hertz(sample_rate.0 / LO_OFFSET_DIVISOR)
+        } else {
+            Hertz(0)
+        }
+    }
+
+    /// Have a hardware source follow retunes through `retune`.
+    pub fn follow(&mut self, retune: impl FnMut(Hertz) -> anyhow::Result<()> + Send + 'static) {
+        self.retunes.push(Box::new(retune));
+    }
+
+    /// Mark the graph as having a source that must be reopened to retune.
+    #[cfg_attr(not(feature = "rtlsdr"), allow(dead_code))]
+    pub fn mark_fixed(&mut self) {
+        self.fixed = true;
+    }
+}
+
+/// Shifts a stream by a mixer: a hardware source's samples by its LO
+/// offset, so the stream is centered on the center frequency again, or the
+/// spectrum's onto its frequency offset.
+#[derive(rustradio_macros::Block)]
+#[rustradio(new, sync)]
+pub struct LoShift {
+    #[rustradio(in)]
+    src: ReadStream<Complex>,
+    #[rustradio(out)]
+    dst: WriteStream<Complex>,
+    mixer: Nco,
+}
+
+impl LoShift {
+    fn process_sync(&mut self, sample: Complex) -> Complex {
+        self.mixer.mix(sample)
+    }
+}
+
+/// The mixer for a source tuned `lo_offset` above the center: the center
+/// arrives `lo_offset` below DC, and is moved up to it.
+pub fn lo_mixer(sample_rate: Hertz, lo_offset: Hertz) -> Nco {
+    Nco::new(sample_rate.0 as f64, -(lo_offset.0 as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_center_back_to_dc() {
+        let tuner = Tuner::new(Hertz::mhz(100)).with_offset_tuning(true);
+        let sample_rate = Hertz(48_000);
+        let lo_offset = tuner.lo_offset(sample_rate);
+        assert_eq!(lo_offset, Hertz(3_000));
+        assert_eq!(Tuner::new(Hertz::mhz(100)).lo_offset(sample_rate), Hertz(0));
+
+        // A carrier at the center, as the offset hardware delivers it
+        let mut mixer = lo_mixer(sample_rate, lo_offset);
+        let step = -std::f64::consts::TAU * lo_offset.0 as f64 / sample_rate.0 as f64;
+        for n in 0..1_000 {
+            let (sin, cos) = (step * n as f64).sin_cos();
+            let mixed = mixer.mix(Complex::new(cos as f32, sin as f32));
+            assert!((mixed - Complex::new(1.0, 0.0)).norm() < 1e-3, "{mixed}");
+        }
+    }
+}
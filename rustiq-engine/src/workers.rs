@@ -0,0 +1,82 @@
+//! Threads for the engine's work in the background, held to the thread
+//! limit it was started with.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+#[derive(Clone, Default)]
+pub struct Workers {
+    /// Threads running
+    running: Arc<AtomicUsize>,
+    /// Most threads to run at once, if limited
+    limit: Option<usize>,
+}
+
+impl Workers {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            running: Arc::default(),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Run `work` on a thread of its own, unless the limit is reached.
+    /// Returns whether it was started.
+    pub fn spawn(&self, work: impl FnOnce() + Send + 'static) -> bool {
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let taken = self
+            .running
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+                (running < limit).then_some(running + 1)
+            });
+        if taken.is_err() {
+            return false;
+        }
+        let running = self.running.clone();
+        thread::spawn(move || {
+            // Given back even if the work panics
+            let _done = Done(running);
+            work();
+        });
+        true
+    }
+}
+
+struct Done(Arc<AtomicUsize>);
+
+impl Drop for Done {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_to_the_limit_until_work_is_done() {
+        let workers = Workers::new(Some(1));
+        let (release_tx, release_rx) = flume::bounded::<()>(0);
+        let (done_tx, done_rx) = flume::bounded(1);
+        assert!(workers.spawn(move || {
+            let _ = release_rx.recv();
+            let _ = done_tx.send(());
+        }));
+        assert!(!workers.spawn(|| {}));
+
+        release_tx.send(()).unwrap();
+        done_rx.recv().unwrap();
+        // The thread gives its place back as it ends
+        while workers.running.load(Ordering::Acquire) > 0 {
+            thread::yield_now();
+        }
+        assert!(workers.spawn(|| {}));
+        assert!(Workers::default().spawn(|| {}));
+    }
+}
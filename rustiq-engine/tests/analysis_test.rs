@@ -0,0 +1,89 @@
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+use rustiq_engine::analysis::{AnalysisOptions, analyze_file};
+use rustiq_messages::{Hertz, SignalRegion};
+
+/// One second of a 10 kHz tone over weak noise at `sample_rate`.
+fn tone_file(sample_rate: u64) -> tempfile::NamedTempFile {
+    let mut state = 1u32;
+    let mut noise = move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        ((state >> 8) as f32 / (1 << 24) as f32 - 0.5) * 1e-3
+    };
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let samples: Vec<u8> = (0..sample_rate)
+        .flat_map(|i| {
+            let phase = TAU * 10_000.0 * i as f64 / sample_rate as f64;
+            [
+                0.5 * phase.cos() as f32 + noise(),
+                0.5 * phase.sin() as f32 + noise(),
+            ]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+    file
+}
+
+#[test]
+fn test_analyze_file_finds_tone() {
+    let sample_rate = 48_000;
+    let file = tone_file(sample_rate);
+    let options = AnalysisOptions {
+        sample_rate: Hertz(sample_rate),
+        center_frequency: Hertz(100_000_000),
+        fft_size: 1024,
+        max_rows: 16,
+        ..Default::default()
+    };
+    let analysis = analyze_file(file.path(), &options).unwrap();
+
+    assert_eq!(analysis.duration, Duration::from_secs(1));
+    // 46 blocks, averaged in threes to fit in 16 rows
+    assert_eq!(analysis.spectrogram.len(), 15);
+    assert!(analysis.spectrogram.iter().all(|row| row.len() == 1024));
+    assert_eq!(analysis.signals.len(), 1, "{:?}", analysis.signals);
+    let signal = analysis.signals[0];
+    let offset = signal.frequency.as_hz() as f64 - 100_010_000.0;
+    assert!(offset.abs() <= analysis.resolution, "{signal:?}");
+    // The window's main lobe, a few bins wide
+    assert!(
+        (signal.bandwidth.as_hz() as f64) < 5.0 * analysis.resolution,
+        "{signal:?}"
+    );
+    assert!(
+        signal.half_power_bandwidth <= signal.bandwidth,
+        "{signal:?}"
+    );
+    assert!(
+        signal.occupied_bandwidth > Hertz(0) && signal.occupied_bandwidth <= signal.bandwidth,
+        "{signal:?}"
+    );
+    assert!(signal.snr.0 > 30.0, "{signal:?}");
+}
+
+#[test]
+fn test_analyze_file_leaves_out_excluded_regions() {
+    let file = tone_file(48_000);
+    let options = AnalysisOptions {
+        sample_rate: Hertz(48_000),
+        fft_size: 1024,
+        excluded: vec![SignalRegion {
+            frequency: Hertz(10_000),
+            bandwidth: Hertz(2_000),
+        }],
+        ..Default::default()
+    };
+    let analysis = analyze_file(file.path(), &options).unwrap();
+
+    assert!(analysis.signals.is_empty(), "{:?}", analysis.signals);
+    assert_eq!(analysis.excluded.iter().filter(|&&e| e).count(), 43);
+    assert!(analysis.noise_floor.0 < -60.0, "{}", analysis.noise_floor);
+}
+
+#[test]
+fn test_analyze_file_rejects_short_file() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    assert!(analyze_file(file.path(), &AnalysisOptions::default()).is_err());
+}
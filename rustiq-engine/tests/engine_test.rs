@@ -1,16 +1,21 @@
-use flume;
+use std::f64::consts::TAU;
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use rustiq_engine::Engine;
-use rustiq_messages::{Command, Decibels, Event, Hertz, SourceConfig};
+use rustiq_engine::{Engine, EngineConfig, Overflow, ResourceLimits};
+use rustiq_messages::{
+    AudioChannel, AudioRouting, Averaging, CalibrationPoint, Command, Decibels, DemodMode,
+    Discontinuity, EngineState, Event, Feature, FrequencyRange, GainProfile, Hertz, IqFormat,
+    Lockout, MeteorConfig, Passband, Resource, RustIqError, ScanChannel, ScanList, SignalClass,
+    SignalRegion, SourceConfig, SourceKind, SpectrumFrame, Stage, Tone, VfoConfig,
+};
 
 // Test helpers to reduce boilerplate
 
 fn setup_engine() -> (
     flume::Sender<Command>,
     flume::Receiver<Event>,
-    JoinHandle<anyhow::Result<()>>,
+    JoinHandle<Result<(), RustIqError>>,
 ) {
     let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
     let (event_tx, event_rx) = flume::unbounded::<Event>();
@@ -23,15 +28,32 @@ fn setup_engine() -> (
     (cmd_tx, event_rx, handle)
 }
 
-fn teardown_engine(cmd_tx: flume::Sender<Command>, handle: JoinHandle<anyhow::Result<()>>) {
+fn teardown_engine(cmd_tx: flume::Sender<Command>, handle: JoinHandle<Result<(), RustIqError>>) {
     cmd_tx.send(Command::Stop).unwrap();
     let _ = handle.join();
 }
 
-fn skip_state_snapshot(event_rx: &flume::Receiver<Event>) {
+/// Skip the first state snapshot and the capabilities that follow it.
+fn skip_startup_events(event_rx: &flume::Receiver<Event>) {
     event_rx
         .recv_timeout(Duration::from_secs(2))
         .expect("Should receive StateSnapshot");
+    match event_rx.recv_timeout(Duration::from_secs(2)) {
+        Ok(Event::Capabilities(_)) => {}
+        other => panic!("Expected Capabilities, got {:?}", other),
+    }
+}
+
+/// The latest playback position reported within `wait`
+fn latest_position(event_rx: &flume::Receiver<Event>, wait: Duration) -> Option<Duration> {
+    let deadline = std::time::Instant::now() + wait;
+    let mut latest = None;
+    while let Ok(event) = event_rx.recv_deadline(deadline) {
+        if let Event::PlaybackPosition(position) = event {
+            latest = Some(position);
+        }
+    }
+    latest
 }
 
 #[test]
@@ -48,6 +70,19 @@ fn test_engine_construction() {
     drop(event_rx);
 }
 
+#[test]
+fn test_engine_fails_without_a_ui() {
+    let (_cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    drop(event_rx);
+
+    let result = Engine::new(cmd_rx, event_tx, SourceConfig::default()).run();
+    match result {
+        Err(RustIqError::Protocol { peer, .. }) => assert_eq!(peer, "UI"),
+        other => panic!("Expected a protocol error, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_engine_sends_state_snapshot() {
     let (cmd_tx, event_rx, handle) = setup_engine();
@@ -68,21 +103,85 @@ fn test_engine_sends_state_snapshot() {
     teardown_engine(cmd_tx, handle);
 }
 
+#[test]
+fn test_engine_announces_capabilities_once() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    next_state_snapshot(&event_rx);
+    match event_rx.recv_timeout(Duration::from_secs(2)) {
+        Ok(Event::Capabilities(capabilities)) => {
+            assert_eq!(capabilities, rustiq_engine::capabilities());
+            // The analyses are always built in, decoders with their feature
+            assert!(capabilities.supports(Feature::CarrierMeasurement));
+            assert_eq!(
+                capabilities.supports(Feature::SstvDecoder),
+                cfg!(feature = "sstv")
+            );
+        }
+        other => panic!("Expected Capabilities, got {:?}", other),
+    }
+
+    // Not again after a rebuild
+    cmd_tx.send(Command::SetGain(Decibels(6.0))).unwrap();
+    next_state_snapshot(&event_rx);
+    thread::sleep(Duration::from_millis(200));
+    assert!(
+        event_rx
+            .try_iter()
+            .all(|event| !matches!(event, Event::Capabilities(_)))
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_resync_resends_state_and_capabilities() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    // As for a UI attaching to the running engine
+    cmd_tx.send(Command::Resync).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.center_frequency, Hertz::mhz(100));
+    let capabilities = event_rx
+        .iter()
+        .find_map(|event| match event {
+            Event::Capabilities(capabilities) => Some(capabilities),
+            _ => None,
+        })
+        .expect("Should receive Capabilities");
+    assert_eq!(capabilities, rustiq_engine::capabilities());
+
+    teardown_engine(cmd_tx, handle);
+}
+
 #[test]
 fn test_engine_sends_spectrum_data() {
     let (cmd_tx, event_rx, handle) = setup_engine();
-    skip_state_snapshot(&event_rx);
+    skip_startup_events(&event_rx);
 
     let mut spectrum_count = 0;
     for _ in 0..5 {
         match event_rx.recv_timeout(Duration::from_secs(2)) {
-            Ok(Event::SpectrumData(data)) => {
-                assert!(!data.is_empty(), "Spectrum data should not be empty");
+            Ok(Event::SpectrumData(frame)) => {
+                assert!(
+                    !frame.magnitudes.is_empty(),
+                    "Spectrum data should not be empty"
+                );
+                // Stamped with the time it was taken
+                let age = SystemTime::now().duration_since(frame.timestamp).unwrap();
+                assert!(age < Duration::from_secs(2), "{age:?}");
                 spectrum_count += 1;
             }
             Ok(Event::StateSnapshot(_)) => {
                 panic!("Should not receive another StateSnapshot");
             }
+            Ok(other) => {
+                panic!("Unexpected event: {:?}", other);
+            }
             Err(e) => {
                 panic!("Failed to receive SpectrumData: {:?}", e);
             }
@@ -93,6 +192,194 @@ fn test_engine_sends_spectrum_data() {
     teardown_engine(cmd_tx, handle);
 }
 
+#[test]
+fn test_spectrum_frames_are_numbered_across_restarts() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let next_frame = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => return frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    let first = next_frame();
+    assert_eq!(first.sequence, 0);
+    assert_eq!(first.discontinuity, None);
+    assert_eq!(first.sample_time, Duration::ZERO);
+    assert_eq!(first.center_frequency, Hertz(0));
+    assert_eq!(first.sample_rate, Hertz(48_000));
+    let second = next_frame();
+    assert_eq!(second.sequence, 1);
+    // One 4096-point FFT frame later at 48 kHz
+    assert_eq!(
+        second.sample_time,
+        Duration::from_secs_f64(4096.0 / 48_000.0)
+    );
+
+    // Retuning in place carries the stream on; frames carry their tuning,
+    // so the UI can line them up
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.center_frequency, Hertz::mhz(100));
+    let retuned = loop {
+        let frame = next_frame();
+        if frame.center_frequency == Hertz::mhz(100) {
+            break frame;
+        }
+    };
+    assert_ne!(retuned.discontinuity, Some(Discontinuity::Restart));
+    assert!(retuned.sample_time > second.sample_time);
+
+    // A new gain rebuilds the graph; numbering carries on across it
+    cmd_tx.send(Command::SetGain(Decibels(6.0))).unwrap();
+    next_state_snapshot(&event_rx);
+    let restarted = next_frame();
+    assert_eq!(restarted.discontinuity, Some(Discontinuity::Restart));
+    assert!(restarted.sequence > retuned.sequence);
+    assert_eq!(restarted.sample_time, Duration::ZERO);
+    assert_eq!(restarted.center_frequency, Hertz::mhz(100));
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_second_source_gets_its_own_spectrum() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let second = SourceConfig::SignalGenerator {
+        sample_rate: Hertz(24_000),
+        tones: vec![Tone {
+            frequency: Hertz(3_000),
+            amplitude: Decibels(-6.0),
+        }],
+        snr: None,
+        sweep: None,
+    };
+    cmd_tx
+        .send(Command::SetSecondSource(Some(second.clone())))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.second_source, Some(second));
+
+    // Frames from both sources, each numbered from its own start
+    let mut first_frames = [None, None];
+    while first_frames.iter().any(Option::is_none) {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => {
+                first_frames[frame.source].get_or_insert(frame);
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    }
+    let [_, second] = first_frames.map(Option::unwrap);
+    assert_eq!(second.sequence, 0);
+    assert_eq!(second.discontinuity, None);
+    assert_eq!(second.sample_rate, Hertz(24_000));
+
+    cmd_tx.send(Command::SetSecondSource(None)).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.second_source, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_full_spectrum_channel_drops_frames() {
+    // As it does by default
+    let config = EngineConfig::default();
+    assert_eq!(config.spectrum_overflow, Overflow::Drop);
+    let (cmd_tx, cmd_rx) = config.command_channel();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let (spectrum_tx, spectrum_rx) = config.spectrum_channel();
+    let handle = thread::spawn(move || {
+        Engine::new(cmd_rx, event_tx, SourceConfig::default())
+            .with_spectrum_channel(spectrum_tx)
+            .run()
+    });
+
+    // Spectrum frames stay off the event channel
+    skip_startup_events(&event_rx);
+    thread::sleep(Duration::from_millis(300));
+    assert!(
+        event_rx
+            .try_iter()
+            .all(|event| matches!(event, Event::Stats { .. }))
+    );
+
+    // The engine keeps going while the UI isn't reading, dropping the
+    // oldest frame for each new one, so the one queued is the latest
+    let next_frame = || match spectrum_rx.recv_timeout(Duration::from_secs(2)) {
+        Ok(Event::SpectrumData(frame)) => frame,
+        other => panic!("Expected SpectrumData, got {:?}", other),
+    };
+    let mut previous = next_frame().sequence;
+    let dropped = (0..10).any(|_| {
+        thread::sleep(Duration::from_millis(300));
+        let sequence = next_frame().sequence;
+        let gap = sequence > previous + 1;
+        previous = sequence;
+        gap
+    });
+    assert!(dropped, "no frames dropped up to sequence {previous}");
+
+    // And reports the frames it dropped
+    loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::Stats { frames_dropped, .. }) if frames_dropped > 0 => break,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive Stats: {:?}", e),
+        }
+    }
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_engine_holds_to_memory_and_thread_limits() {
+    let config = EngineConfig {
+        limits: ResourceLimits {
+            // Two frames in flight from each source, of 12 bytes a bin
+            memory: Some(4 * 12 * 1024),
+            threads: Some(0),
+            ..ResourceLimits::default()
+        },
+        ..EngineConfig::default()
+    };
+    let (cmd_tx, cmd_rx) = flume::unbounded();
+    let (event_tx, event_rx) = flume::unbounded();
+    let handle = thread::spawn(move || {
+        Engine::new(cmd_rx, event_tx, SourceConfig::default())
+            .with_limits(&config)
+            .run()
+    });
+    let next_limit = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::Error(RustIqError::Limit { resource, .. })) => return resource,
+            Ok(_) => {}
+            Err(e) => panic!("Expected a limit to be hit, got {:?}", e),
+        }
+    };
+
+    // The default FFT size doesn't fit to begin with
+    assert_eq!(next_state_snapshot(&event_rx).fft_size, 1024);
+    cmd_tx.send(Command::SetFftSize(512)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).fft_size, 512);
+    cmd_tx.send(Command::SetFftSize(8192)).unwrap();
+    assert_eq!(next_limit(), Resource::Memory);
+    assert_eq!(next_state_snapshot(&event_rx).fft_size, 1024);
+
+    cmd_tx.send(Command::RunSelfTest).unwrap();
+    assert_eq!(next_limit(), Resource::Threads);
+
+    teardown_engine(cmd_tx, handle);
+}
+
 #[test]
 fn test_engine_runs_without_panic() {
     let (cmd_tx, event_rx, handle) = setup_engine();
@@ -108,10 +395,10 @@ fn test_engine_runs_without_panic() {
 #[test]
 fn test_fft_shows_peak_at_10khz() {
     let (cmd_tx, event_rx, handle) = setup_engine();
-    skip_state_snapshot(&event_rx);
+    skip_startup_events(&event_rx);
 
     let spectrum_data = match event_rx.recv_timeout(Duration::from_secs(2)) {
-        Ok(Event::SpectrumData(data)) => data,
+        Ok(Event::SpectrumData(frame)) => frame.magnitudes,
         Ok(other) => panic!("Expected SpectrumData, got {:?}", other),
         Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
     };
@@ -141,6 +428,34 @@ fn test_fft_shows_peak_at_10khz() {
     teardown_engine(cmd_tx, handle);
 }
 
+#[test]
+fn test_tone_between_bins_does_not_leak_across_the_spectrum() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let magnitudes = match event_rx.recv_timeout(Duration::from_secs(2)) {
+        Ok(Event::SpectrumData(frame)) => frame.magnitudes,
+        other => panic!("Expected SpectrumData, got {:?}", other),
+    };
+    // The 10 kHz tone falls a third of the way between bins 853 and 854
+    // past DC; unwindowed, it would leak to 43 dB below its peak 50 bins
+    // away
+    let peak_bin = 2048 + 853;
+    let peak = magnitudes[peak_bin].max(magnitudes[peak_bin + 1]);
+    let leak = magnitudes
+        .iter()
+        .enumerate()
+        .filter(|(bin, _)| bin.abs_diff(peak_bin) >= 50)
+        .map(|(_, &magnitude)| magnitude)
+        .fold(0.0, f32::max);
+    assert!(
+        20.0 * (leak / peak).log10() < -80.0,
+        "leaked {leak} against a peak of {peak}"
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
 #[test]
 fn test_engine_handles_change_source() {
     let (cmd_tx, event_rx, handle) = setup_engine();
@@ -152,7 +467,7 @@ fn test_engine_handles_change_source() {
 
     let initial_freq = match &initial_event {
         Event::StateSnapshot(state) => match &state.source_config {
-            SourceConfig::SignalGenerator { signal_freq, .. } => *signal_freq,
+            SourceConfig::SignalGenerator { tones, .. } => tones[0].frequency,
             _ => panic!("Expected SignalGenerator config"),
         },
         _ => panic!("Expected StateSnapshot"),
@@ -162,8 +477,12 @@ fn test_engine_handles_change_source() {
     // Send ChangeSource with different frequency, then Stop
     let new_config = SourceConfig::SignalGenerator {
         sample_rate: Hertz(48_000),
-        signal_freq: Hertz(5_000),
-        amplitude: Decibels(0.0),
+        tones: vec![Tone {
+            frequency: Hertz(5_000),
+            amplitude: Decibels(0.0),
+        }],
+        snr: None,
+        sweep: None,
     };
     cmd_tx.send(Command::ChangeSource(new_config)).unwrap();
     cmd_tx.send(Command::Stop).unwrap();
@@ -174,12 +493,11 @@ fn test_engine_handles_change_source() {
     // Drain all events and check for StateSnapshot with updated config
     let mut received_new_snapshot = false;
     while let Ok(event) = event_rx.try_recv() {
-        if let Event::StateSnapshot(state) = event {
-            if let SourceConfig::SignalGenerator { signal_freq, .. } = &state.source_config {
-                if *signal_freq == Hertz(5_000) {
-                    received_new_snapshot = true;
-                }
-            }
+        if let Event::StateSnapshot(state) = event
+            && let SourceConfig::SignalGenerator { tones, .. } = &state.source_config
+            && tones[0].frequency == Hertz(5_000)
+        {
+            received_new_snapshot = true;
         }
     }
 
@@ -188,3 +506,2104 @@ fn test_engine_handles_change_source() {
         "Should receive new StateSnapshot with updated config"
     );
 }
+
+#[test]
+fn test_carrier_measurement_reports_signal_frequency() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx
+        .send(Command::StartCarrierMeasurement(Hertz(10_200)))
+        .unwrap();
+
+    let measurement = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::CarrierMeasurement(measurement)) => break measurement,
+            Ok(Event::StateSnapshot(state)) => {
+                assert_eq!(state.carrier_measurement, Some(Hertz(10_200)));
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive CarrierMeasurement: {:?}", e),
+        }
+    };
+
+    // The signal generator's tone is at 10 kHz, within the lock range of the target
+    assert!(
+        (measurement.frequency - 10_000.0).abs() < 0.1,
+        "Measured {} Hz, expected 10 kHz",
+        measurement.frequency
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_burst_detection_reports_pulse_timing() {
+    // 10 ms pulses every 50 ms at 48 kHz, written as interleaved f32 IQ
+    let sample_rate = 48_000;
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let samples: Vec<u8> = (0..sample_rate)
+        .flat_map(|i| {
+            let level: f32 = if i % 2_400 >= 1_920 { 1.0 } else { 0.01 };
+            [level, 0.0]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx
+        .send(Command::StartBurstDetection(Decibels(10.0)))
+        .unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+
+    // The file ends mid-pulse, so 19 of its 20 pulses complete
+    let mut bursts = Vec::new();
+    while bursts.len() < 19 {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::Burst(burst)) => bursts.push(burst),
+            Ok(Event::StateSnapshot(state)) => {
+                assert_eq!(state.burst_detection, Some(Decibels(10.0)));
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive Burst: {:?}", e),
+        }
+    }
+    teardown_engine(cmd_tx, handle);
+
+    for burst in &bursts {
+        let duration = burst.duration.as_secs_f64();
+        assert!((duration - 0.010).abs() < 0.001, "Burst {:?}", burst);
+    }
+    for burst in &bursts[1..] {
+        let interval = burst
+            .interval
+            .expect("Should have an interval")
+            .as_secs_f64();
+        assert!((interval - 0.050).abs() < 1e-6, "Burst {:?}", burst);
+    }
+}
+
+#[test]
+fn test_wav_file_plays_at_its_header_sample_rate() {
+    // One second of silent 16-bit I/Q at 96 kHz
+    let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+    wav.extend(16u32.to_le_bytes());
+    for field in [1u16, 2] {
+        wav.extend(field.to_le_bytes());
+    }
+    wav.extend(96_000u32.to_le_bytes());
+    wav.extend((96_000u32 * 4).to_le_bytes());
+    for field in [4u16, 16] {
+        wav.extend(field.to_le_bytes());
+    }
+    wav.extend(b"data");
+    wav.extend((96_000u32 * 4).to_le_bytes());
+    wav.resize(wav.len() + 96_000 * 4, 0);
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, &wav).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(3_200_000),
+        format: IqFormat::Wav,
+    };
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    match event_rx.recv_timeout(Duration::from_secs(2)) {
+        Ok(Event::StateSnapshot(state)) => {
+            assert_eq!(state.sample_rate, Hertz(96_000));
+            assert!(matches!(
+                state.source_config,
+                SourceConfig::File {
+                    sample_rate: Hertz(96_000),
+                    ..
+                }
+            ));
+        }
+        other => panic!("Expected StateSnapshot, got {:?}", other),
+    }
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_playback_speed_paces_a_file_and_stretches_its_audio() {
+    // A second of a carrier, written as interleaved f32 IQ
+    let sample_rate = 48_000;
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let samples: Vec<u8> = (0..sample_rate)
+        .flat_map(|i| {
+            let phase = TAU * 1_000.0 * i as f64 / sample_rate as f64;
+            [phase.cos() as f32 * 0.5, phase.sin() as f32 * 0.5]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx.send(Command::SetPlaybackSpeed(Some(0.5))).unwrap();
+    let channel = AudioChannel {
+        frequency: Hertz(0),
+        demod: DemodMode::Fm,
+    };
+    cmd_tx.send(Command::SetDemodulator(Some(channel))).unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+    assert_eq!(next_state_snapshot(&event_rx).playback_speed, Some(0.5));
+    // The rebuild for the demodulator carries the file on
+    assert_eq!(next_state_snapshot(&event_rx).demodulator, Some(channel));
+
+    let mut frames = Vec::new();
+    let mut audio = 0.0;
+    loop {
+        match event_rx.recv_timeout(Duration::from_millis(500)) {
+            Ok(Event::SpectrumData(_)) => frames.push(std::time::Instant::now()),
+            Ok(Event::AudioChunk(chunk)) => audio += chunk.samples.len() as f64 / chunk.sample_rate,
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    // A frame every two frames' worth of samples, and two seconds of audio
+    // for the second of samples
+    let step = (frames[frames.len() - 1] - frames[0]).as_secs_f64() / (frames.len() - 1) as f64;
+    let expected = 2.0 * 4_096.0 / sample_rate as f64;
+    assert!(
+        (step - expected).abs() < 0.1 * expected,
+        "{step} s between frames"
+    );
+    assert!((audio - 2.0).abs() < 0.1, "{audio} s of audio");
+
+    // Out of range: ignored
+    cmd_tx.send(Command::SetPlaybackSpeed(Some(4.0))).unwrap();
+    cmd_tx.send(Command::SetPlaybackSpeed(None)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).playback_speed, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_file_overview_and_seeking_playback() {
+    // Two seconds: a carrier 6 kHz above the center, then 6 kHz below
+    let sample_rate = 48_000;
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let samples: Vec<u8> = (0..2 * sample_rate)
+        .flat_map(|i| {
+            let offset = if i < sample_rate { 6_000.0 } else { -6_000.0 };
+            let phase = TAU * offset * i as f64 / sample_rate as f64;
+            [phase.cos() as f32 * 0.5, phase.sin() as f32 * 0.5]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+
+    let overview = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::RecordingOverview(overview)) => break overview,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive RecordingOverview: {:?}", e),
+        }
+    };
+    assert_eq!(overview.path, file.path());
+    assert_eq!(overview.duration, Duration::from_secs(2));
+    let covered = overview.row_duration.as_secs_f64() * overview.rows.len() as f64;
+    assert!((covered - 2.0).abs() < 0.05, "{covered} s of rows");
+    let peak = |levels: &[f32]| {
+        (0..levels.len())
+            .max_by(|&a, &b| levels[a].total_cmp(&levels[b]))
+            .unwrap()
+    };
+    // 6 kHz is 64 of the 512 bins from the middle
+    assert_eq!(peak(&overview.rows[0]), 256 + 64);
+    assert_eq!(peak(overview.rows.last().unwrap()), 256 - 64);
+
+    cmd_tx
+        .send(Command::Seek(Duration::from_millis(1_500)))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.playback_start, Duration::from_millis(1_500));
+    // The stream starts in the second half
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    assert_eq!(peak(&frame.magnitudes), 2_048 - 512);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_pausing_a_file_holds_it_where_it_is_played() {
+    // Four seconds of a carrier
+    let sample_rate = 48_000;
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let samples: Vec<u8> = (0..4 * sample_rate)
+        .flat_map(|i| {
+            let phase = TAU * 1_000.0 * i as f64 / sample_rate as f64;
+            [phase.cos() as f32 * 0.5, phase.sin() as f32 * 0.5]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx.send(Command::SetPlaybackSpeed(Some(1.0))).unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+    next_state_snapshot(&event_rx);
+
+    let playing = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::PlaybackPosition(position)) if position > Duration::from_millis(200) => {
+                break position;
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive PlaybackPosition: {:?}", e),
+        }
+    };
+    assert!(playing < Duration::from_secs(2), "{playing:?}");
+
+    cmd_tx.send(Command::Pause).unwrap();
+    assert!(next_state_snapshot(&event_rx).playback_paused);
+    // What was on its way when paused is reported, then nothing moves
+    let held = latest_position(&event_rx, Duration::from_millis(500)).unwrap_or(playing);
+    assert_eq!(latest_position(&event_rx, Duration::from_millis(700)), None);
+
+    // A rebuild holds it where it is rather than starting it over
+    cmd_tx.send(Command::SetGain(Decibels(6.0))).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert!(state.playback_paused);
+    assert_eq!(state.playback_start, held);
+    assert_eq!(
+        latest_position(&event_rx, Duration::from_millis(700)),
+        Some(held)
+    );
+
+    cmd_tx.send(Command::Resume).unwrap();
+    assert!(!next_state_snapshot(&event_rx).playback_paused);
+    let resumed = latest_position(&event_rx, Duration::from_millis(800)).unwrap();
+    assert!(resumed > held + Duration::from_millis(200), "{resumed:?}");
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_seeking_a_paused_file_holds_it_where_it_is_sought_to() {
+    // Four seconds of a carrier
+    let sample_rate = 48_000;
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let samples: Vec<u8> = (0..4 * sample_rate)
+        .flat_map(|i| {
+            let phase = TAU * 1_000.0 * i as f64 / sample_rate as f64;
+            [phase.cos() as f32 * 0.5, phase.sin() as f32 * 0.5]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx.send(Command::SetPlaybackSpeed(Some(1.0))).unwrap();
+    cmd_tx.send(Command::Pause).unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+    next_state_snapshot(&event_rx);
+    assert!(next_state_snapshot(&event_rx).playback_paused);
+    latest_position(&event_rx, Duration::from_millis(500));
+
+    cmd_tx.send(Command::Seek(Duration::from_secs(3))).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert!(state.playback_paused);
+    assert_eq!(state.playback_start, Duration::from_secs(3));
+    // Reported at once, then held there
+    assert_eq!(
+        latest_position(&event_rx, Duration::from_millis(700)),
+        Some(Duration::from_secs(3))
+    );
+    assert_eq!(latest_position(&event_rx, Duration::from_millis(700)), None);
+
+    cmd_tx.send(Command::Resume).unwrap();
+    assert!(!next_state_snapshot(&event_rx).playback_paused);
+    let resumed = latest_position(&event_rx, Duration::from_millis(800)).unwrap();
+    assert!(resumed > Duration::from_millis(3_200), "{resumed:?}");
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_source_that_fails_to_open_is_reported() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let missing = SourceConfig::File {
+        path: "/nonexistent/pass.cf32".into(),
+        sample_rate: Hertz(48_000),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx.send(Command::ChangeSource(missing)).unwrap();
+    let error = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::Error(error)) => break error,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive Error: {:?}", e),
+        }
+    };
+    assert!(
+        matches!(
+            &error,
+            RustIqError::Source {
+                kind: SourceKind::File,
+                detail,
+            } if detail.contains("/nonexistent/pass.cf32")
+        ),
+        "{error:?}"
+    );
+    // The engine carries on with the signal generator
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.source_config, SourceConfig::default());
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_source_that_fails_to_open_falls_back_to_the_last_that_did() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let working = SourceConfig::SignalGenerator {
+        sample_rate: Hertz(96_000),
+        tones: vec![Tone {
+            frequency: Hertz(20_000),
+            amplitude: Decibels(-6.0),
+        }],
+        snr: None,
+        sweep: None,
+    };
+    cmd_tx.send(Command::ChangeSource(working.clone())).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).source_config, working);
+
+    let missing = SourceConfig::File {
+        path: "/nonexistent/pass.cf32".into(),
+        sample_rate: Hertz(48_000),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx.send(Command::ChangeSource(missing)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).source_config, working);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(feature = "sstv")]
+fn test_sstv_decoder_start_and_stop() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let channel = AudioChannel {
+        frequency: Hertz(14_000),
+        demod: DemodMode::Usb,
+    };
+    cmd_tx.send(Command::StartSstvDecoder(channel)).unwrap();
+    cmd_tx.send(Command::StopSstvDecoder).unwrap();
+
+    // Each command rebuilds the graph, reporting the decoder's channel in the snapshot
+    let mut snapshots = Vec::new();
+    while snapshots.len() < 2 {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::StateSnapshot(state)) => snapshots.push(state.sstv_decoder),
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive StateSnapshot: {:?}", e),
+        }
+    }
+    assert_eq!(snapshots, vec![Some(channel), None]);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(not(feature = "sstv"))]
+fn test_engine_ignores_decoders_not_built_in() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let channel = AudioChannel {
+        frequency: Hertz(14_000),
+        demod: DemodMode::Usb,
+    };
+    cmd_tx.send(Command::StartSstvDecoder(channel)).unwrap();
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+
+    // Only the tune is answered, and without a decoder
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.center_frequency, Hertz::mhz(100));
+    assert_eq!(state.sstv_decoder, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_demodulator_sends_audio_until_stopped() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    // FM at the generator's tone
+    let channel = AudioChannel {
+        frequency: Hertz(10_000),
+        demod: DemodMode::Fm,
+    };
+    cmd_tx.send(Command::SetDemodulator(Some(channel))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).demodulator, Some(channel));
+
+    // The channel filter is slow to get through the generator's first
+    // buffer in a debug build
+    let chunk = loop {
+        match event_rx.recv_timeout(Duration::from_secs(20)) {
+            Ok(Event::AudioChunk(chunk)) => break chunk,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive AudioChunk: {:?}", e),
+        }
+    };
+    assert_eq!(chunk.sample_rate, 24_000.0);
+    assert!(
+        chunk.samples.len() >= 480,
+        "{} samples",
+        chunk.samples.len()
+    );
+    // A steady carrier has no deviation to hear, once the filter settles
+    let settled = &chunk.samples[chunk.samples.len() / 2..];
+    assert!(settled.iter().all(|s| s.abs() < 0.05));
+
+    cmd_tx.send(Command::SetDemodulator(None)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).demodulator, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_squelch_mutes_a_channel_quieter_than_its_level() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    // The generator's full-scale tone
+    let channel = AudioChannel {
+        frequency: Hertz(10_000),
+        demod: DemodMode::Usb,
+    };
+    cmd_tx.send(Command::SetDemodulator(Some(channel))).unwrap();
+    next_state_snapshot(&event_rx);
+    let next_squelch_state = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(20)) {
+            Ok(Event::SquelchState(open)) => return open,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SquelchState: {:?}", e),
+        }
+    };
+    // Open until a level is set
+    assert!(next_squelch_state());
+
+    cmd_tx
+        .send(Command::SetSquelch(Some(Decibels(10.0))))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).squelch, Some(Decibels(10.0)));
+    assert!(!next_squelch_state());
+    // The first chunk may still hold audio from before it closed
+    let chunks: Vec<_> = event_rx
+        .iter()
+        .filter_map(|event| match event {
+            Event::AudioChunk(chunk) => Some(chunk),
+            _ => None,
+        })
+        .take(2)
+        .collect();
+    assert!(chunks[1].samples.iter().all(|&s| s == 0.0));
+
+    cmd_tx
+        .send(Command::SetSquelch(Some(Decibels(-20.0))))
+        .unwrap();
+    assert!(next_squelch_state());
+    cmd_tx.send(Command::SetSquelch(None)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).squelch, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_passband_filters_out_a_tone_it_no_longer_covers() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    // Not without a channel to filter
+    cmd_tx
+        .send(Command::SetPassband(Some(Passband {
+            low: 300,
+            high: 2_400,
+        })))
+        .unwrap();
+    // The generator's tone 1 kHz up the sideband, heard by the squelch
+    let channel = AudioChannel {
+        frequency: Hertz(9_000),
+        demod: DemodMode::Usb,
+    };
+    cmd_tx.send(Command::SetDemodulator(Some(channel))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).passband, None);
+    cmd_tx
+        .send(Command::SetSquelch(Some(Decibels(-20.0))))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    let next_squelch_state = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(20)) {
+            Ok(Event::SquelchState(open)) => return open,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SquelchState: {:?}", e),
+        }
+    };
+    assert!(next_squelch_state());
+
+    // Backwards, so ignored
+    cmd_tx
+        .send(Command::SetPassband(Some(Passband {
+            low: 2_400,
+            high: 1_500,
+        })))
+        .unwrap();
+    let above = Passband {
+        low: 1_500,
+        high: 2_400,
+    };
+    cmd_tx.send(Command::SetPassband(Some(above))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).passband, Some(above));
+    assert!(!next_squelch_state());
+
+    cmd_tx.send(Command::SetPassband(None)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).passband, None);
+    assert!(next_squelch_state());
+
+    // Another mode starts from its own
+    cmd_tx.send(Command::SetPassband(Some(above))).unwrap();
+    next_state_snapshot(&event_rx);
+    let channel = AudioChannel {
+        demod: DemodMode::Lsb,
+        ..channel
+    };
+    cmd_tx.send(Command::SetDemodulator(Some(channel))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).passband, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_scan_stops_on_the_active_channel_and_passes_it_once_locked_out() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    // The generator's tone is 10 kHz above the center
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz(100_000)))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    let channel = |frequency| {
+        let channel = AudioChannel {
+            frequency: Hertz(frequency),
+            demod: DemodMode::Fm,
+        };
+        ScanChannel::new(format!("{frequency}"), channel)
+    };
+    let list = ScanList {
+        channels: vec![channel(85_000), channel(110_000), channel(95_000)],
+        priority_every: 0,
+        dwell: None,
+    };
+    // Not without a squelch to tell active channels by
+    cmd_tx.send(Command::StartScan(list.clone())).unwrap();
+    cmd_tx
+        .send(Command::SetSquelch(Some(Decibels(-20.0))))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).scan, None);
+
+    cmd_tx.send(Command::StartScan(list.clone())).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).scan, Some(list));
+    let next_scanning = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(20)) {
+            Ok(Event::Scanning { channel, active }) => return (channel, active),
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive Scanning: {:?}", e),
+        }
+    };
+    while next_scanning() != (1, true) {}
+
+    cmd_tx
+        .send(Command::SetLockout {
+            channel: 1,
+            lockout: Lockout::Temporary,
+        })
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.scan.unwrap().channels[1].lockout, Lockout::Temporary);
+    // Quiet channels only from then on
+    for _ in 0..4 {
+        let (at, active) = next_scanning();
+        assert!(at != 1 && !active, "scanning {at}, active {active}");
+    }
+
+    cmd_tx.send(Command::StopScan).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.scan, None);
+    assert!(state.demodulator.is_some());
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_dwell_scan_moves_on_from_the_active_channel_and_classifies_it() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    // The generator's tone is 10 kHz above the center
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz(100_000)))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    cmd_tx
+        .send(Command::SetSquelch(Some(Decibels(-20.0))))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    let channel = |frequency| {
+        let channel = AudioChannel {
+            frequency: Hertz(frequency),
+            demod: DemodMode::Fm,
+        };
+        ScanChannel::new(format!("{frequency}"), channel)
+    };
+    let dwell = Duration::from_secs(1);
+    let list = ScanList {
+        channels: vec![channel(85_000), channel(110_000)],
+        priority_every: 0,
+        dwell: Some(dwell),
+    };
+    cmd_tx.send(Command::StartScan(list)).unwrap();
+    // Heard the whole dwell, though the tone never stops
+    let hit = loop {
+        match event_rx.recv_timeout(Duration::from_secs(20)) {
+            Ok(Event::ScanHit(hit)) => break hit,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive ScanHit: {:?}", e),
+        }
+    };
+    assert_eq!(hit.channel, 1);
+    assert_eq!(hit.frequency, Hertz(110_000));
+    assert!(
+        hit.duration >= dwell && hit.duration < dwell + Duration::from_millis(500),
+        "{:?}",
+        hit.duration
+    );
+    // An unmodulated tone on the dial
+    assert_eq!(hit.class, Some(SignalClass::Carrier));
+    assert!(hit.level > Decibels(-20.0), "{}", hit.level);
+
+    cmd_tx.send(Command::StopScan).unwrap();
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_audio_routing_records_and_streams_instead_of_playing() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav_path = dir.path().join("listening.wav");
+    let listener = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    listener
+        .set_read_timeout(Some(Duration::from_secs(20)))
+        .unwrap();
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let routing = AudioRouting {
+        speakers: false,
+        recording: Some(wav_path.clone()),
+        network: vec![listener.local_addr().unwrap().to_string()],
+    };
+    cmd_tx
+        .send(Command::SetAudioRouting(routing.clone()))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).audio_routing, routing);
+    let channel = AudioChannel {
+        frequency: Hertz(10_000),
+        demod: DemodMode::Fm,
+    };
+    cmd_tx.send(Command::SetDemodulator(Some(channel))).unwrap();
+    next_state_snapshot(&event_rx);
+
+    // A chunk of 16-bit samples
+    let mut datagram = [0; 4_096];
+    let received = listener.recv(&mut datagram).unwrap();
+    assert!(received >= 960, "{received} bytes");
+    assert_eq!(received % 2, 0);
+
+    cmd_tx.send(Command::SetDemodulator(None)).unwrap();
+    next_state_snapshot(&event_rx);
+    assert!(
+        event_rx
+            .try_iter()
+            .all(|event| !matches!(event, Event::AudioChunk(_))),
+        "Audio went to the speakers"
+    );
+    let wav = std::fs::read(&wav_path).unwrap();
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 24_000);
+    let data_bytes = u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize;
+    assert!(data_bytes > 0);
+    assert_eq!(wav.len(), 44 + data_bytes);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_vfos_demodulate_channels_beside_the_listening_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav_path = dir.path().join("vfo.wav");
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+    let next_vfo_squelch = |want: u32| loop {
+        match event_rx.recv_timeout(Duration::from_secs(20)) {
+            Ok(Event::VfoSquelch { id, open }) if id == want => return open,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive VfoSquelch: {:?}", e),
+        }
+    };
+
+    // The generator's tone is 10 kHz above the center
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz(100_000)))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    // One on the tone, recording it, and one off it
+    let vfo = |frequency| {
+        let mut config = VfoConfig::new(AudioChannel {
+            frequency: Hertz(frequency),
+            demod: DemodMode::Fm,
+        });
+        config.squelch = Some(Decibels(-20.0));
+        config
+    };
+    let on_tone = VfoConfig {
+        recording: Some(wav_path.clone()),
+        ..vfo(110_000)
+    };
+    cmd_tx.send(Command::AddVfo(on_tone.clone())).unwrap();
+    next_state_snapshot(&event_rx);
+    assert!(next_vfo_squelch(1));
+    cmd_tx.send(Command::AddVfo(vfo(85_000))).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    let ids: Vec<_> = state.vfos.iter().map(|vfo| vfo.id).collect();
+    assert_eq!(ids, [1, 2]);
+    assert_eq!(state.vfos[0].config, on_tone);
+    assert_eq!(state.demodulator, None);
+    assert!(!next_vfo_squelch(2));
+
+    // Moved onto the tone
+    cmd_tx
+        .send(Command::ConfigureVfo {
+            id: 2,
+            config: vfo(110_000),
+        })
+        .unwrap();
+    assert_eq!(
+        next_state_snapshot(&event_rx).vfos[1]
+            .config
+            .channel
+            .frequency,
+        Hertz(110_000)
+    );
+    assert!(next_vfo_squelch(2));
+
+    // No such VFO, so ignored
+    cmd_tx.send(Command::RemoveVfo(7)).unwrap();
+    cmd_tx.send(Command::RemoveVfo(1)).unwrap();
+    let ids: Vec<_> = next_state_snapshot(&event_rx)
+        .vfos
+        .iter()
+        .map(|vfo| vfo.id)
+        .collect();
+    assert_eq!(ids, [2]);
+    assert!(
+        event_rx
+            .try_iter()
+            .all(|event| !matches!(event, Event::AudioChunk(_))),
+        "VFO audio went to the speakers"
+    );
+    let wav = std::fs::read(&wav_path).unwrap();
+    assert_eq!(&wav[..4], b"RIFF");
+    let data_bytes = u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize;
+    assert!(data_bytes > 0);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+/// A stand-in for `rtl_tcp` on a local port: serves a steady carrier to the
+/// first client and passes on the commands it sends.
+fn fake_rtl_tcp() -> (u16, flume::Receiver<(u8, u32)>) {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (command_tx, command_rx) = flume::unbounded();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = stream.try_clone().unwrap();
+        thread::spawn(move || {
+            let mut command = [0; 5];
+            while reader.read_exact(&mut command).is_ok() {
+                let param = u32::from_be_bytes(command[1..].try_into().unwrap());
+                let _ = command_tx.send((command[0], param));
+            }
+        });
+        // RTL0, an R820T tuner with 29 gains
+        let mut header = b"RTL0".to_vec();
+        header.extend_from_slice(&5u32.to_be_bytes());
+        header.extend_from_slice(&29u32.to_be_bytes());
+        stream.write_all(&header).unwrap();
+        let samples = [255, 128].repeat(2_048);
+        while stream.write_all(&samples).is_ok() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+    (port, command_rx)
+}
+
+#[test]
+fn test_rtl_tcp_source_streams_and_retunes_in_place() {
+    let (port, command_rx) = fake_rtl_tcp();
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let config = SourceConfig::RtlTcp {
+        host: "127.0.0.1".to_string(),
+        port,
+        sample_rate: Hertz(1_024_000),
+        gain: Decibels(20.7),
+    };
+    cmd_tx.send(Command::ChangeSource(config.clone())).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).source_config, config);
+    let commands: Vec<(u8, u32)> = command_rx.iter().take(4).collect();
+    assert_eq!(
+        commands,
+        [(0x02, 1_024_000), (0x03, 1), (0x04, 207), (0x01, 0)]
+    );
+
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    assert_eq!(frame.sample_rate, Hertz(1_024_000));
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    assert_eq!(
+        next_state_snapshot(&event_rx).center_frequency,
+        Hertz::mhz(100)
+    );
+    assert_eq!(
+        command_rx.recv_timeout(Duration::from_secs(2)),
+        Ok((0x01, 100_000_000))
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_unsupported_rate_is_resampled_from_the_next_above() {
+    let (port, command_rx) = fake_rtl_tcp();
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx
+        .send(Command::ChangeSource(SourceConfig::RtlTcp {
+            host: "127.0.0.1".to_string(),
+            port,
+            sample_rate: Hertz::mhz(1),
+            gain: Decibels(20.0),
+        }))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.sample_rate, Hertz::mhz(1));
+    assert_eq!(state.device_sample_rate, Some(Hertz(1_024_000)));
+    assert_eq!(
+        command_rx.recv_timeout(Duration::from_secs(2)),
+        Ok((0x02, 1_024_000))
+    );
+
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    assert_eq!(frame.sample_rate, Hertz::mhz(1));
+
+    teardown_engine(cmd_tx, handle);
+}
+
+/// A stand-in for SpyServer on a local port, with a 2.4 MHz device: answers
+/// the first client's hello, passes on the settings it sends, and streams a
+/// steady carrier once streaming is enabled.
+fn fake_spyserver() -> (u16, flume::Receiver<(u32, u32)>) {
+    use std::io::{Read, Write};
+
+    fn message(message_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut message = Vec::new();
+        for word in [0x0200_06a4, message_type, 0, 0, body.len() as u32] {
+            message.extend_from_slice(&word.to_le_bytes());
+        }
+        message.extend_from_slice(body);
+        message
+    }
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (setting_tx, setting_rx) = flume::unbounded();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = stream.try_clone().unwrap();
+        let (enabled_tx, enabled_rx) = flume::bounded(1);
+        thread::spawn(move || {
+            let mut header = [0; 8];
+            while reader.read_exact(&mut header).is_ok() {
+                let command = u32::from_le_bytes(header[..4].try_into().unwrap());
+                let mut body =
+                    vec![0; u32::from_le_bytes(header[4..].try_into().unwrap()) as usize];
+                reader.read_exact(&mut body).unwrap();
+                // Settings; the hello carries the protocol version
+                if command == 2 {
+                    let word =
+                        |i: usize| u32::from_le_bytes(body[4 * i..4 * i + 4].try_into().unwrap());
+                    if (word(0), word(1)) == (1, 1) {
+                        let _ = enabled_tx.send(());
+                    }
+                    let _ = setting_tx.send((word(0), word(1)));
+                }
+            }
+        });
+        // An Airspy-like device at 2.4 MHz with 8 halvings and 22 gain steps,
+        // which this client may control
+        let device = words(&[
+            1,
+            1234,
+            2_400_000,
+            2_000_000,
+            8,
+            1,
+            21,
+            24_000_000,
+            1_800_000_000,
+            12,
+            0,
+            0,
+        ]);
+        stream.write_all(&message(0, &device)).unwrap();
+        let sync = words(&[1, 10, 0, 0, 0, 24_000_000, 1_800_000_000, 0, 0]);
+        stream.write_all(&message(1, &sync)).unwrap();
+        enabled_rx.recv().unwrap();
+        let iq = message(
+            101,
+            &[i16::MAX, 0]
+                .repeat(2_048)
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        );
+        while stream.write_all(&iq).is_ok() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+    (port, setting_rx)
+}
+
+#[test]
+fn test_spyserver_source_negotiates_its_rate_and_retunes_in_place() {
+    let (port, setting_rx) = fake_spyserver();
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let config = SourceConfig::SpyServer {
+        host: "127.0.0.1".to_string(),
+        port,
+        sample_rate: Hertz(500_000),
+        gain: 40,
+    };
+    cmd_tx.send(Command::ChangeSource(config)).unwrap();
+    // Rounded up to a quarter of the device's rate
+    let state = next_state_snapshot(&event_rx);
+    assert!(
+        matches!(
+            state.source_config,
+            SourceConfig::SpyServer {
+                sample_rate: Hertz(600_000),
+                ..
+            }
+        ),
+        "{:?}",
+        state.source_config
+    );
+    let settings: Vec<(u32, u32)> = setting_rx.iter().take(6).collect();
+    assert_eq!(
+        settings,
+        // IQ only, as 16-bit, at 0 Hz, decimated by 4, at the top gain step,
+        // streaming
+        [(0, 1), (100, 2), (101, 0), (102, 2), (2, 21), (1, 1)]
+    );
+
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    assert_eq!(frame.sample_rate, Hertz(600_000));
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    assert_eq!(
+        setting_rx.recv_timeout(Duration::from_secs(2)),
+        Ok((101, 100_000_000))
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(not(feature = "rtlsdr"))]
+fn test_engine_ignores_sources_not_built_in() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let rtl_sdr = SourceConfig::RtlSdr {
+        sample_rate: Hertz(2_400_000),
+        gain: Decibels(20.0),
+    };
+    cmd_tx.send(Command::ChangeSource(rtl_sdr)).unwrap();
+    #[cfg(not(feature = "soapysdr"))]
+    {
+        let soapy_sdr = SourceConfig::SoapySdr {
+            device: "driver=airspy".to_string(),
+            sample_rate: Hertz(2_500_000),
+            antenna: None,
+            bandwidth: None,
+            gains: Vec::new(),
+        };
+        cmd_tx
+            .send(Command::SetSecondSource(Some(soapy_sdr)))
+            .unwrap();
+    }
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+
+    // Only the tune is answered, still from the generator alone
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.center_frequency, Hertz::mhz(100));
+    assert_eq!(state.source_config, SourceConfig::default());
+    assert_eq!(state.second_source, None);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(feature = "selcall")]
+fn test_selcall_decoder_reports_fm_call() {
+    use rustiq_messages::{SelCallConfig, SelCallStandard};
+
+    // CCIR call "12234" (the second 2 sent as the repeat tone), FM modulated
+    // onto a carrier 5 kHz above center
+    let sample_rate = 48_000;
+    let standard = SelCallStandard::Ccir;
+    let tones = standard.tones();
+    let tone_len = sample_rate as usize / 10;
+    let mut audio = vec![0.0f64; tone_len];
+    for symbol in [1, 2, 10, 3, 4] {
+        let freq = tones[symbol] as f64;
+        audio.extend((0..tone_len).map(|i| (TAU * freq * i as f64 / sample_rate as f64).sin()));
+    }
+    audio.extend(vec![0.0; 3 * tone_len]);
+
+    let mut phase = 0.0f64;
+    let samples: Vec<u8> = audio
+        .iter()
+        .flat_map(|a| {
+            phase += TAU * (5_000.0 + 2_500.0 * a) / sample_rate as f64;
+            [phase.cos() as f32, phase.sin() as f32]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    let selcall = SelCallConfig {
+        channel: AudioChannel {
+            frequency: Hertz(5_000),
+            demod: DemodMode::Fm,
+        },
+        standard,
+    };
+    cmd_tx.send(Command::StartSelCallDecoder(selcall)).unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+
+    let call = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::SelCall(call)) => break call,
+            Ok(Event::StateSnapshot(state)) => {
+                assert_eq!(state.selcall_decoder, Some(selcall));
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SelCall: {:?}", e),
+        }
+    };
+    teardown_engine(cmd_tx, handle);
+
+    assert_eq!(call.id, "12234");
+    assert_eq!(call.standard, standard);
+    assert!(
+        (call.start.as_secs_f64() - 0.1).abs() < 0.05,
+        "Call started at {:?}",
+        call.start
+    );
+}
+
+#[test]
+#[cfg(feature = "adsb")]
+fn test_adsb_decoder_reports_aircraft() {
+    use rustiq_messages::TrackKind;
+
+    // One identification squitter as 2 MS/s pulse position modulation:
+    // preamble pulses, then a pulse in the first half of each 1 bit
+    let mut chips = vec![false; 16];
+    for pulse in [0, 2, 7, 9] {
+        chips[pulse] = true;
+    }
+    let message = "8D4840D6202CC371C32CE0576098";
+    for i in (0..message.len()).step_by(2) {
+        let byte = u8::from_str_radix(&message[i..i + 2], 16).unwrap();
+        for bit in (0..8).rev() {
+            let one = byte >> bit & 1 == 1;
+            chips.extend([one, !one]);
+        }
+    }
+    let mut amplitudes = vec![false; 1_000];
+    amplitudes.extend(chips);
+    amplitudes.extend(vec![false; 20_000]);
+
+    let samples: Vec<u8> = amplitudes
+        .iter()
+        .flat_map(|&on| [if on { 0.5f32 } else { 0.01 }, 0.0])
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(2_000_000),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx.send(Command::StartAdsbDecoder).unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+
+    let report = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::Track(report)) => break report,
+            Ok(Event::StateSnapshot(state)) => assert!(state.adsb_decoder),
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive track report: {:?}", e),
+        }
+    };
+    teardown_engine(cmd_tx, handle);
+
+    assert_eq!(report.kind, TrackKind::Aircraft);
+    assert_eq!(report.id, "4840D6");
+    assert_eq!(report.name.as_deref(), Some("KLM1023"));
+}
+
+#[test]
+fn test_meteor_detection_reports_ping() {
+    // A weak beacon 5 kHz above center that jumps 30 dB for 200 ms at t = 1 s
+    let sample_rate = 48_000;
+    let samples: Vec<u8> = (0..2 * sample_rate)
+        .flat_map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            let amplitude = if (1.0..1.2).contains(&t) { 0.1 } else { 0.003 };
+            let phase = TAU * 5_000.0 * t;
+            [
+                (amplitude * phase.cos()) as f32,
+                (amplitude * phase.sin()) as f32,
+            ]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    let meteor = MeteorConfig {
+        frequency: Hertz(5_000),
+        bandwidth: Hertz(500),
+        threshold: Decibels(10.0),
+    };
+    cmd_tx.send(Command::StartMeteorDetection(meteor)).unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+
+    let ping = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::MeteorPing(ping)) => break ping,
+            Ok(Event::StateSnapshot(state)) => {
+                assert_eq!(state.meteor_detection, Some(meteor));
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive MeteorPing: {:?}", e),
+        }
+    };
+    teardown_engine(cmd_tx, handle);
+
+    assert!(
+        (ping.start.as_secs_f64() - 1.0).abs() < 0.02,
+        "Ping started at {:?}",
+        ping.start
+    );
+    assert!(
+        (ping.duration.as_secs_f64() - 0.2).abs() < 0.02,
+        "Ping lasted {:?}",
+        ping.duration
+    );
+    assert!(ping.peak_snr.0 > 20.0, "Peak SNR {}", ping.peak_snr);
+}
+
+#[test]
+fn test_impulse_counter_reports_crash() {
+    // Low-level noise with a short spike at t = 1 s
+    let sample_rate = 48_000;
+    let mut state = 1u32;
+    let samples: Vec<u8> = (0..2 * sample_rate)
+        .flat_map(|i| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = ((state >> 8) as f32 / (1 << 24) as f32 - 0.5) * 0.01;
+            let offset = i as i64 - sample_rate as i64;
+            let spike = if (0..4).contains(&offset) {
+                2.0 / (offset + 1) as f32
+            } else {
+                0.0
+            };
+            [noise + spike, -noise]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    cmd_tx
+        .send(Command::StartImpulseCounter(Decibels(10.0)))
+        .unwrap();
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    skip_startup_events(&event_rx);
+
+    let impulse = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::Impulse(impulse)) => break impulse,
+            Ok(Event::StateSnapshot(state)) => {
+                assert_eq!(state.impulse_counter, Some(Decibels(10.0)));
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive Impulse: {:?}", e),
+        }
+    };
+    teardown_engine(cmd_tx, handle);
+
+    assert!(
+        (impulse.time.as_secs_f64() - 1.0).abs() < 0.01,
+        "Impulse at {:?}",
+        impulse.time
+    );
+    assert!(impulse.peak_snr.0 > 10.0, "Peak SNR {}", impulse.peak_snr);
+}
+
+/// Wait for the next StateSnapshot, skipping spectrum and measurement events.
+fn next_state_snapshot(event_rx: &flume::Receiver<Event>) -> EngineState {
+    loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::StateSnapshot(state)) => return *state,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive StateSnapshot: {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn test_tuning_applies_gain_profile() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let hf = GainProfile {
+        range: FrequencyRange::new(Hertz::mhz(3), Hertz::mhz(30)),
+        gain: Decibels(-10.0),
+    };
+    let uhf = GainProfile {
+        range: FrequencyRange::new(Hertz::mhz(300), Hertz::mhz(3_000)),
+        gain: Decibels(20.0),
+    };
+    cmd_tx
+        .send(Command::SetGainProfiles(vec![hf, uhf]))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.gain_profiles, vec![hf, uhf]);
+    assert_eq!(state.gain, Decibels(0.0), "No profile covers 0 Hz");
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(14)))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.center_frequency, Hertz::mhz(14));
+    assert_eq!(state.gain, hf.gain);
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(433)))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).gain, uhf.gain);
+
+    // Between profiles the last gain is kept, and a manual setting sticks
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).gain, uhf.gain);
+    cmd_tx.send(Command::SetGain(Decibels(5.0))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).gain, Decibels(5.0));
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_fft_size_changes_spectrum_bins() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx.send(Command::SetFftSize(1_024)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).fft_size, 1_024);
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    assert_eq!(frame.magnitudes.len(), 1_024);
+
+    // Not a power of two: ignored without a rebuild
+    cmd_tx.send(Command::SetFftSize(1_000)).unwrap();
+    cmd_tx.send(Command::SetGain(Decibels(1.0))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).fft_size, 1_024);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_decimation_zooms_the_spectrum_into_the_center() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx.send(Command::SetDecimation(2)).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.decimation, 2);
+    assert_eq!(state.spectrum_span, Hertz(24_000));
+    let next_frame = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    // Past the filter's first frames
+    for _ in 0..9 {
+        next_frame();
+    }
+    let frame = next_frame();
+    assert_eq!(frame.sample_rate, Hertz(24_000));
+    assert_eq!(frame.magnitudes.len(), state.fft_size);
+    // The generator's 10 kHz tone, now at twice the bins from the center
+    let peak = frame
+        .magnitudes
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap()
+        .0;
+    let offset = (peak as f64 - (state.fft_size / 2) as f64) * 24_000.0 / state.fft_size as f64;
+    assert!((offset - 10_000.0).abs() < 10.0, "peak at {offset} Hz");
+
+    // Not a power of two: ignored without a rebuild
+    cmd_tx.send(Command::SetDecimation(3)).unwrap();
+    cmd_tx.send(Command::SetGain(Decibels(1.0))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).decimation, 2);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_frequency_offset_centers_the_spectrum_off_the_center() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx.send(Command::SetFrequencyOffset(10_000)).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.frequency_offset, 10_000);
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    assert_eq!(
+        frame.center_frequency,
+        Hertz(state.center_frequency.0 + 10_000)
+    );
+    // The generator's 10 kHz tone, shifted onto the middle bin
+    let peak = frame
+        .magnitudes
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap()
+        .0;
+    assert_eq!(peak, state.fft_size / 2);
+
+    // Past the edge of the band: ignored without a rebuild
+    cmd_tx.send(Command::SetFrequencyOffset(-24_000)).unwrap();
+    cmd_tx.send(Command::SetGain(Decibels(1.0))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).frequency_offset, 10_000);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_block_averaging_sends_a_frame_per_block() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx
+        .send(Command::SetAveraging(Averaging::Block(4)))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.averaging, Averaging::Block(4));
+    let next_frame = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    let first = next_frame();
+    let second = next_frame();
+    // Each frame stands for four FFTs' worth of samples
+    let block = 4.0 * state.fft_size as f64 / state.sample_rate.0 as f64;
+    let step = (second.sample_time - first.sample_time).as_secs_f64();
+    assert!((step - block).abs() < 1e-6, "{step} s between frames");
+
+    // Out of range: ignored without a rebuild
+    cmd_tx
+        .send(Command::SetAveraging(Averaging::Exponential(0.0)))
+        .unwrap();
+    cmd_tx.send(Command::SetGain(Decibels(1.0))).unwrap();
+    assert_eq!(
+        next_state_snapshot(&event_rx).averaging,
+        Averaging::Block(4)
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_hold_traces_bound_each_frame() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx.send(Command::SetPeakHold(true)).unwrap();
+    cmd_tx.send(Command::SetMinHold(true)).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    let state = if state.min_hold {
+        state
+    } else {
+        next_state_snapshot(&event_rx)
+    };
+    assert!(state.peak_hold && state.min_hold);
+    let next_frame = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    let mut previous_peak = vec![0.0; state.fft_size];
+    for _ in 0..5 {
+        let frame = next_frame();
+        let peak = frame.peak_hold.expect("peak hold trace");
+        let min = frame.min_hold.expect("min hold trace");
+        assert_eq!(peak.len(), state.fft_size);
+        for (bin, magnitude) in frame.magnitudes.iter().enumerate() {
+            assert!(min[bin] <= *magnitude && *magnitude <= peak[bin]);
+            assert!(peak[bin] >= previous_peak[bin]);
+        }
+        previous_peak = peak;
+    }
+
+    cmd_tx.send(Command::SetPeakHold(false)).unwrap();
+    assert!(!next_state_snapshot(&event_rx).peak_hold);
+    let frame = next_frame();
+    assert!(frame.peak_hold.is_none() && frame.min_hold.is_some());
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_calibration_corrects_spectrum_levels() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+    let next_peak = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => {
+                break frame.magnitudes.iter().copied().fold(0.0, f32::max);
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    let uncorrected = next_peak();
+
+    // A front end 20 dB down at the 10 kHz test tone
+    let calibration = vec![
+        CalibrationPoint {
+            offset: 0.0,
+            gain: Decibels(0.0),
+        },
+        CalibrationPoint {
+            offset: 10_000.0,
+            gain: Decibels(-20.0),
+        },
+    ];
+    cmd_tx
+        .send(Command::SetCalibration(calibration.clone()))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).calibration, calibration);
+    let corrected = next_peak();
+    assert!(
+        (corrected / uncorrected - 10.0).abs() < 0.5,
+        "Tone should be 20 dB up, went from {uncorrected} to {corrected}"
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_offset_tuning_leaves_generator_alone() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx.send(Command::SetOffsetTuning(true)).unwrap();
+    assert!(next_state_snapshot(&event_rx).offset_tuning);
+    // Only hardware is tuned off the center; the test tone stays at 10 kHz
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) => break frame,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SpectrumData: {:?}", e),
+        }
+    };
+    let (peak, _) = frame
+        .magnitudes
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+    let n = frame.magnitudes.len() as f64;
+    let peak_frequency = (peak as f64 - n / 2.0) * 48_000.0 / n;
+    assert!(
+        (peak_frequency - 10_000.0).abs() < 100.0,
+        "{peak_frequency}"
+    );
+
+    teardown_engine(cmd_tx, handle);
+}
+
+/// The first spectrum frame centered on `frequency`, checking none before it
+/// restarted the stream.
+fn next_frame_at(event_rx: &flume::Receiver<Event>, frequency: Hertz) -> SpectrumFrame {
+    loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) if frame.center_frequency == frequency => return frame,
+            Ok(Event::SpectrumData(frame)) => assert_eq!(frame.discontinuity, None),
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive a frame at {frequency}: {:?}", e),
+        }
+    }
+}
+
+#[test]
+fn test_center_frequency_retunes_running_graph() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+    let first = next_frame_at(&event_rx, Hertz(0));
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    assert_eq!(
+        next_state_snapshot(&event_rx).center_frequency,
+        Hertz::mhz(100)
+    );
+    // The stream carries on
+    let frame = next_frame_at(&event_rx, Hertz::mhz(100));
+    assert_eq!(frame.discontinuity, None);
+    assert!(frame.sequence > first.sequence);
+
+    // A channel placed from the old center needs a rebuild
+    cmd_tx
+        .send(Command::StartCarrierMeasurement(Hertz::mhz(100)))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(200)))
+        .unwrap();
+    let frame = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::SpectrumData(frame)) if frame.center_frequency == Hertz::mhz(200) => {
+                break frame;
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive a retuned frame: {:?}", e),
+        }
+    };
+    assert_eq!(frame.discontinuity, Some(Discontinuity::Restart));
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(feature = "antenna-switch")]
+fn test_tuning_selects_antenna_by_rule() {
+    use rustiq_messages::{Antenna, AntennaRule, AntennaSwitchConfig, AntennaSwitchLink};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let antenna = |name: &str, command: &str| Antenna {
+        name: name.to_string(),
+        command: command.to_string(),
+    };
+    let switch = AntennaSwitchConfig {
+        link: AntennaSwitchLink::Network { address },
+        antennas: vec![
+            antenna("Dipole", r"ANT 1\r\n"),
+            antenna("Yagi", r"ANT 2\r\n"),
+        ],
+        rules: vec![
+            AntennaRule {
+                range: FrequencyRange::new(Hertz::mhz(3), Hertz::mhz(30)),
+                antenna: 0,
+            },
+            AntennaRule {
+                range: FrequencyRange::new(Hertz::mhz(400), Hertz::mhz(450)),
+                antenna: 1,
+            },
+        ],
+    };
+    cmd_tx
+        .send(Command::SetAntennaSwitch(Some(switch.clone())))
+        .unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.antenna_switch, Some(switch));
+    assert_eq!(state.antenna, None, "No rule covers 0 Hz");
+
+    let received = |listener: &std::net::TcpListener| {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut command = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut command).unwrap();
+        command
+    };
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(14)))
+        .unwrap();
+    assert_eq!(received(&listener), "ANT 1\r\n");
+    assert_eq!(next_state_snapshot(&event_rx).antenna, Some(0));
+
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(433)))
+        .unwrap();
+    assert_eq!(received(&listener), "ANT 2\r\n");
+    assert_eq!(next_state_snapshot(&event_rx).antenna, Some(1));
+
+    cmd_tx.send(Command::SelectAntenna(0)).unwrap();
+    assert_eq!(received(&listener), "ANT 1\r\n");
+    assert_eq!(next_state_snapshot(&event_rx).antenna, Some(0));
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(feature = "antenna-switch")]
+fn test_dark_frame_is_captured_on_the_termination_and_subtracted() {
+    use rustiq_messages::{Antenna, AntennaSwitchConfig, AntennaSwitchLink, DarkFrameConfig};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let received = |listener: &std::net::TcpListener| {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut command = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut command).unwrap();
+        command
+    };
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    let antenna = |name: &str, command: &str| Antenna {
+        name: name.to_string(),
+        command: command.to_string(),
+    };
+    cmd_tx
+        .send(Command::SetAntennaSwitch(Some(AntennaSwitchConfig {
+            link: AntennaSwitchLink::Network { address },
+            antennas: vec![antenna("Discone", "ANT 1"), antenna("Load", "ANT 2")],
+            rules: Vec::new(),
+        })))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+    cmd_tx.send(Command::SelectAntenna(0)).unwrap();
+    assert_eq!(received(&listener), "ANT 1");
+    assert_eq!(next_state_snapshot(&event_rx).antenna, Some(0));
+    let before = next_frame_at(&event_rx, Hertz(0));
+    let peak = before.magnitudes.iter().copied().fold(0.0, f32::max);
+
+    let config = DarkFrameConfig {
+        termination: 1,
+        interval: Duration::from_secs(3_600),
+        frames: 4,
+    };
+    cmd_tx.send(Command::SetDarkFrame(Some(config))).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).dark_frame, Some(config));
+    assert_eq!(received(&listener), "ANT 2");
+    loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::DarkFrameCaptured(level)) => {
+                assert!(level.0 > -40.0, "{level:?}");
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive DarkFrameCaptured: {:?}", e),
+        }
+    }
+    assert_eq!(received(&listener), "ANT 1");
+
+    // The generator's tone was there with the input "terminated" too, so it
+    // counts as the front end's own and is taken out, leaving the floor.
+    // Frames taken meanwhile aren't sent, without leaving a gap.
+    let after = next_frame_at(&event_rx, Hertz(0));
+    let highest = after.magnitudes.iter().copied().fold(0.0, f32::max);
+    assert!(highest < peak * 1e-3, "{highest} against {peak}");
+    assert!(after.discontinuity.is_none());
+
+    cmd_tx.send(Command::SetDarkFrame(None)).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).dark_frame, None);
+    let frame = next_frame_at(&event_rx, Hertz(0));
+    let restored = frame.magnitudes.iter().copied().fold(0.0, f32::max);
+    assert!((restored - peak).abs() < peak * 1e-3);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+#[cfg(feature = "rotator")]
+fn test_rotator_reports_and_follows_position() {
+    // A fake rotctld that starts at az 180, el 10 and moves instantly
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let rotctld = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut position = (180.0, 10.0);
+        let mut commands = Vec::new();
+        for line in std::io::BufRead::lines(std::io::BufReader::new(stream)) {
+            let line = line.unwrap();
+            let reply = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["p"] => format!("{:.6}\n{:.6}\n", position.0, position.1),
+                ["P", az, el] => {
+                    position = (az.parse().unwrap(), el.parse().unwrap());
+                    "RPRT 0\n".to_string()
+                }
+                _ => "RPRT -1\n".to_string(),
+            };
+            std::io::Write::write_all(&mut writer, reply.as_bytes()).unwrap();
+            commands.push(line);
+        }
+        commands
+    });
+
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+    cmd_tx
+        .send(Command::ConnectRotator(address.clone()))
+        .unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).rotator, Some(address));
+    // Connected in place, so the spectrum carries on
+    assert_ne!(
+        next_frame_at(&event_rx, Hertz(0)).discontinuity,
+        Some(Discontinuity::Restart)
+    );
+
+    let next_position = || loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::RotatorPosition(position)) => break position,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive RotatorPosition: {:?}", e),
+        }
+    };
+    let start = next_position();
+    assert_eq!(start.azimuth, 180.0);
+    assert_eq!(start.elevation, 10.0);
+
+    let target = start.nudged(-200.0, 5.0);
+    cmd_tx.send(Command::PointRotator(target)).unwrap();
+    let position = next_position();
+    assert_eq!(position.azimuth, 340.0);
+    assert_eq!(position.elevation, 15.0);
+
+    cmd_tx.send(Command::DisconnectRotator).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).rotator, None);
+    assert_ne!(
+        next_frame_at(&event_rx, Hertz(0)).discontinuity,
+        Some(Discontinuity::Restart)
+    );
+    teardown_engine(cmd_tx, handle);
+
+    let commands = rotctld.join().unwrap();
+    assert!(
+        commands.contains(&"P 340.0 15.0".to_string()),
+        "{commands:?}"
+    );
+}
+
+#[test]
+#[cfg(feature = "rig")]
+fn test_rig_frequency_moves_center() {
+    use rustiq_messages::RigConfig;
+
+    // A fake rigctld on 20 m FT8
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut frequency = 14_074_000u64;
+        for line in std::io::BufRead::lines(std::io::BufReader::new(stream)) {
+            let line = line.unwrap();
+            let reply = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["f"] => format!("{frequency}\n"),
+                ["F", hz] => {
+                    frequency = hz.parse().unwrap();
+                    "RPRT 0\n".to_string()
+                }
+                _ => "RPRT -1\n".to_string(),
+            };
+            std::io::Write::write_all(&mut writer, reply.as_bytes()).unwrap();
+        }
+    });
+
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+    let profile = GainProfile {
+        range: FrequencyRange::new(Hertz::mhz(7), Hertz::mhz(8)),
+        gain: Decibels(-6.0),
+    };
+    cmd_tx
+        .send(Command::SetGainProfiles(vec![profile]))
+        .unwrap();
+    next_state_snapshot(&event_rx);
+
+    let rig = RigConfig {
+        address,
+        if_frequency: None,
+    };
+    cmd_tx.send(Command::ConnectRig(rig.clone())).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).rig, Some(rig));
+    assert_eq!(
+        next_state_snapshot(&event_rx).center_frequency,
+        Hertz(14_074_000)
+    );
+
+    // Tuning the rig comes back as a new center, with the band's gain
+    cmd_tx.send(Command::TuneRig(Hertz(7_074_000))).unwrap();
+    let state = next_state_snapshot(&event_rx);
+    assert_eq!(state.center_frequency, Hertz(7_074_000));
+    assert_eq!(state.gain, profile.gain);
+    next_frame_at(&event_rx, Hertz(7_074_000));
+
+    // Disconnected in place, so the spectrum carries on
+    cmd_tx.send(Command::DisconnectRig).unwrap();
+    assert_eq!(next_state_snapshot(&event_rx).rig, None);
+    assert_ne!(
+        next_frame_at(&event_rx, Hertz(7_074_000)).discontinuity,
+        Some(Discontinuity::Restart)
+    );
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_self_test_passes_every_stage() {
+    let (cmd_tx, event_rx, handle) = setup_engine();
+    skip_startup_events(&event_rx);
+
+    cmd_tx.send(Command::RunSelfTest).unwrap();
+    let report = loop {
+        match event_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(Event::SelfTest(report)) => break report,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SelfTest: {:?}", e),
+        }
+    };
+
+    let stages: Vec<Stage> = report.checks.iter().map(|check| check.stage).collect();
+    assert_eq!(stages, vec![Stage::Source, Stage::Spectrum, Stage::Audio]);
+    assert!(report.passed(), "{:#?}", report);
+
+    teardown_engine(cmd_tx, handle);
+}
+
+#[test]
+fn test_symbol_rate_estimate_of_psk_signal() {
+    // BPSK at 1200 Bd, 6 kHz above the center: the phase flips when the
+    // random bit changes
+    let sample_rate = 48_000;
+    let mut state = 1u32;
+    let mut phase = 0.0;
+    let samples: Vec<u8> = (0..2 * sample_rate)
+        .flat_map(|i| {
+            if i % 40 == 0 {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                if state >> 31 == 1 {
+                    phase += std::f64::consts::PI;
+                }
+            }
+            let carrier = TAU * 6_000.0 * i as f64 / sample_rate as f64 + phase;
+            [0.1 * carrier.cos() as f32, 0.1 * carrier.sin() as f32]
+        })
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, &samples).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let config = SourceConfig::File {
+        path: file.path().to_path_buf(),
+        sample_rate: Hertz(sample_rate),
+        format: IqFormat::Cf32,
+    };
+    let handle = thread::spawn(move || Engine::new(cmd_rx, event_tx, config).run());
+    let center = next_state_snapshot(&event_rx).center_frequency;
+
+    let region = SignalRegion {
+        frequency: Hertz(center.as_hz() + 6_000),
+        bandwidth: Hertz(2_400),
+    };
+    cmd_tx.send(Command::EstimateSymbolRate(region)).unwrap();
+    let estimate = loop {
+        match event_rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(Event::SymbolRate(estimate)) => break estimate,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive SymbolRate: {:?}", e),
+        }
+    };
+
+    assert_eq!(estimate.region, region);
+    let best = estimate.candidates.first().expect("No candidates");
+    assert!((best.rate - 1_200.0).abs() < 2.0, "{:?}", estimate);
+
+    // A one-off: the next graph doesn't estimate again
+    cmd_tx.send(Command::SetGain(Decibels(3.0))).unwrap();
+    next_state_snapshot(&event_rx);
+    let deadline = std::time::Instant::now() + Duration::from_secs(3);
+    while let Ok(event) = event_rx.recv_deadline(deadline) {
+        assert!(!matches!(event, Event::SymbolRate(_)), "{:?}", event);
+    }
+
+    teardown_engine(cmd_tx, handle);
+}
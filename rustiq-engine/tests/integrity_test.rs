@@ -0,0 +1,106 @@
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use rustiq_engine::integrity::{self, Checksummer, Verification};
+
+/// Write `len` bytes of data in 10-byte chunks, as a recording would, and
+/// return the data's path.
+fn record(dir: &Path, len: u8, finish: bool) -> PathBuf {
+    let data_path = dir.join("capture.sigmf-data");
+    let data: Vec<u8> = (0..len).collect();
+    std::fs::write(&data_path, &data).unwrap();
+    let mut checksums = Checksummer::create(&integrity::manifest_path(&data_path), 10).unwrap();
+    for block in data.chunks(7) {
+        checksums.update(block).unwrap();
+    }
+    if finish {
+        checksums.finish().unwrap();
+    }
+    data_path
+}
+
+fn intact(chunks: u64) -> Verification {
+    Verification {
+        chunk_bytes: 10,
+        good_chunks: chunks,
+        corrupt_chunks: Vec::new(),
+        missing_chunks: 0,
+        unchecked_bytes: 0,
+        finished: true,
+    }
+}
+
+#[test]
+fn test_intact_recording_passes() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_path = record(dir.path(), 35, true);
+    let verification = integrity::verify(&data_path).unwrap();
+    assert_eq!(verification, intact(4));
+    assert!(verification.passed());
+}
+
+#[test]
+fn test_corrupt_chunk_is_located() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_path = record(dir.path(), 35, true);
+    let mut file = OpenOptions::new().write(true).open(&data_path).unwrap();
+    file.seek(SeekFrom::Start(23)).unwrap();
+    file.write_all(&[0xff]).unwrap();
+
+    let verification = integrity::verify(&data_path).unwrap();
+    assert_eq!(
+        verification,
+        Verification {
+            good_chunks: 3,
+            corrupt_chunks: vec![2],
+            ..intact(3)
+        }
+    );
+    assert!(!verification.passed());
+}
+
+#[test]
+fn test_truncated_recording_is_detected() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_path = record(dir.path(), 35, true);
+    OpenOptions::new()
+        .write(true)
+        .open(&data_path)
+        .unwrap()
+        .set_len(18)
+        .unwrap();
+
+    let verification = integrity::verify(&data_path).unwrap();
+    assert_eq!(
+        verification,
+        Verification {
+            missing_chunks: 3,
+            ..intact(1)
+        }
+    );
+    assert!(!verification.passed());
+}
+
+#[test]
+fn test_interrupted_recording_checks_up_to_last_chunk() {
+    let dir = tempfile::tempdir().unwrap();
+    let data_path = record(dir.path(), 35, false);
+    // Half a line written as the recorder died
+    let mut manifest = OpenOptions::new()
+        .append(true)
+        .open(integrity::manifest_path(&data_path))
+        .unwrap();
+    manifest.write_all(b"chunk 3 ab").unwrap();
+
+    let verification = integrity::verify(&data_path).unwrap();
+    assert_eq!(
+        verification,
+        Verification {
+            unchecked_bytes: 5,
+            finished: false,
+            ..intact(3)
+        }
+    );
+    assert!(!verification.passed());
+}
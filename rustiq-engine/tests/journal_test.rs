@@ -0,0 +1,78 @@
+use std::thread;
+use std::time::Duration;
+
+use rustiq_engine::Engine;
+use rustiq_engine::journal::{Journal, finalize_recording, recover};
+use rustiq_messages::{Command, Decibels, Event, Hertz, SessionRecord, SourceConfig, Tone};
+
+#[test]
+fn test_engine_offers_crashed_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.journal");
+    let recording = dir.path().join("capture.sigmf-data");
+    std::fs::write(&recording, [0u8; 8 * 3 + 5]).unwrap();
+    let crashed = SessionRecord {
+        source_config: SourceConfig::SignalGenerator {
+            sample_rate: Hertz(48_000),
+            tones: vec![Tone {
+                frequency: Hertz(5_000),
+                amplitude: Decibels(-6.0),
+            }],
+            snr: None,
+            sweep: None,
+        },
+        center_frequency: Hertz::mhz(145),
+        gain: Decibels(-10.0),
+        recording: Some(recording.clone()),
+    };
+    // Dropping the journal without closing it is what a crash leaves behind
+    Journal::create(&path).unwrap().write(&crashed).unwrap();
+
+    let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+    let (event_tx, event_rx) = flume::unbounded::<Event>();
+    let journal = path.clone();
+    let handle = thread::spawn(move || {
+        Engine::new(cmd_rx, event_tx, SourceConfig::default())
+            .with_journal(journal)
+            .run()
+    });
+
+    let previous = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::PreviousSession(previous)) => break previous,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive PreviousSession: {:?}", e),
+        }
+    };
+    assert_eq!(previous, crashed);
+    // The recording's partial sample was dropped
+    assert_eq!(std::fs::metadata(&recording).unwrap().len(), 8 * 3);
+    // The running session holds the journal
+    assert_eq!(recover(&path).unwrap(), None);
+
+    cmd_tx.send(Command::RestoreSession(previous)).unwrap();
+    let state = loop {
+        match event_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(Event::StateSnapshot(state)) => break state,
+            Ok(_) => {}
+            Err(e) => panic!("Failed to receive StateSnapshot: {:?}", e),
+        }
+    };
+    assert_eq!(state.source_config, crashed.source_config);
+    assert_eq!(state.center_frequency, Hertz::mhz(145));
+    assert_eq!(state.gain, Decibels(-10.0));
+
+    // A clean exit leaves nothing to recover
+    cmd_tx.send(Command::Stop).unwrap();
+    handle.join().unwrap().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_finalize_recording_keeps_whole_samples() {
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("capture.sigmf-data");
+    std::fs::write(&recording, [0u8; 8 * 10]).unwrap();
+    assert_eq!(finalize_recording(&recording).unwrap(), 10);
+    assert_eq!(std::fs::metadata(&recording).unwrap().len(), 8 * 10);
+}
@@ -0,0 +1,157 @@
+use std::thread;
+use std::time::Duration;
+
+use rustiq_engine::integrity;
+use rustiq_engine::recording::{Recording, RecordingOptions};
+use rustiq_engine::{Engine, EngineConfig, ResourceLimits};
+use rustiq_messages::{
+    Command, Event, Hertz, RecordingFormat, RecordingStatus, Resource, RustIqError, SourceConfig,
+};
+
+#[test]
+fn test_recording_writes_sigmf() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("capture");
+    let options = RecordingOptions {
+        center_frequency: Hertz::mhz(100),
+        duration: Some(Duration::from_millis(250)),
+        description: Some("test capture".to_string()),
+        hardware: None,
+    };
+    let recording = Recording::new(SourceConfig::default(), &base, &options).unwrap();
+    assert_eq!(recording.sample_rate(), Hertz(48_000));
+    let samples = recording.run().unwrap();
+
+    assert_eq!(samples, 12_000);
+    let data = std::fs::read(dir.path().join("capture.sigmf-data")).unwrap();
+    assert_eq!(data.len(), 12_000 * 8);
+    // Little-endian I/Q pairs of the generator's unit-amplitude tone
+    let i = f32::from_le_bytes(data[..4].try_into().unwrap());
+    let q = f32::from_le_bytes(data[4..8].try_into().unwrap());
+    assert!((i.hypot(q) - 1.0).abs() < 1e-3, "{i} {q}");
+
+    let meta = std::fs::read_to_string(dir.path().join("capture.sigmf-meta")).unwrap();
+    let meta = rustradio::sigmf::parse_meta(&meta).unwrap();
+    assert_eq!(meta.global.core_datatype, "cf32_le");
+    assert_eq!(meta.global.core_sample_rate, Some(48_000.0));
+    assert_eq!(
+        meta.global.core_description.as_deref(),
+        Some("test capture")
+    );
+    assert_eq!(meta.captures[0].core_frequency, Some(100e6));
+    assert!(meta.captures[0].core_datetime.is_some());
+
+    let verification = integrity::verify(&dir.path().join("capture.sigmf-data")).unwrap();
+    assert!(verification.passed(), "{verification:?}");
+    assert_eq!(verification.good_chunks, 1);
+}
+
+/// Next recording status the engine sends.
+fn next_recording_status(event_rx: &flume::Receiver<Event>) -> RecordingStatus {
+    event_rx
+        .iter()
+        .find_map(|event| match event {
+            Event::RecordingStatus(status) => Some(status),
+            _ => None,
+        })
+        .expect("Should receive RecordingStatus")
+}
+
+#[test]
+fn test_engine_records_live_stream_across_retunes() {
+    let dir = tempfile::tempdir().unwrap();
+    let (cmd_tx, cmd_rx) = flume::unbounded();
+    let (event_tx, event_rx) = flume::unbounded();
+    let handle =
+        thread::spawn(move || Engine::new(cmd_rx, event_tx, SourceConfig::default()).run());
+
+    cmd_tx
+        .send(Command::StartRecording {
+            path: dir.path().join("live"),
+            format: RecordingFormat::SigMf,
+        })
+        .unwrap();
+    let status = next_recording_status(&event_rx);
+    assert!(status.active);
+    assert_eq!(status.path, dir.path().join("live.sigmf-data"));
+
+    // The recording carries on through the retune
+    cmd_tx
+        .send(Command::SetCenterFrequency(Hertz::mhz(100)))
+        .unwrap();
+    next_recording_status(&event_rx);
+    cmd_tx.send(Command::StopRecording).unwrap();
+    let status = loop {
+        let status = next_recording_status(&event_rx);
+        if !status.active {
+            break status;
+        }
+    };
+    assert!(status.bytes_written > 0);
+    assert_eq!(status.bytes_written % 8, 0);
+
+    let data = std::fs::metadata(&status.path).unwrap();
+    assert_eq!(data.len(), status.bytes_written);
+    let meta = std::fs::read_to_string(dir.path().join("live.sigmf-meta")).unwrap();
+    let meta = rustradio::sigmf::parse_meta(&meta).unwrap();
+    assert_eq!(meta.captures.len(), 2);
+    assert_eq!(meta.captures[1].core_frequency, Some(100e6));
+    assert!(meta.captures[1].core_sample_start > 0);
+    let verification = integrity::verify(&status.path).unwrap();
+    assert!(verification.passed(), "{verification:?}");
+
+    cmd_tx.send(Command::Stop).unwrap();
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_engine_stops_recording_at_the_disk_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = EngineConfig {
+        limits: ResourceLimits {
+            disk: Some(100_000),
+            ..ResourceLimits::default()
+        },
+        ..EngineConfig::default()
+    };
+    let (cmd_tx, cmd_rx) = flume::unbounded();
+    let (event_tx, event_rx) = flume::unbounded();
+    let handle = thread::spawn(move || {
+        Engine::new(cmd_rx, event_tx, SourceConfig::default())
+            .with_limits(&config)
+            .run()
+    });
+
+    cmd_tx
+        .send(Command::StartRecording {
+            path: dir.path().join("live"),
+            format: RecordingFormat::Raw,
+        })
+        .unwrap();
+    // Stopped without being asked, saying why
+    let mut limit = None;
+    let status = loop {
+        match event_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::Error(error)) => limit = Some(error),
+            Ok(Event::RecordingStatus(status)) if !status.active => break status,
+            Ok(_) => {}
+            Err(e) => panic!("Expected the recording to stop, got {e}"),
+        }
+    };
+    assert!(
+        matches!(
+            limit,
+            Some(RustIqError::Limit {
+                resource: Resource::Disk,
+                ..
+            })
+        ),
+        "{limit:?}"
+    );
+    assert_eq!(status.bytes_written, 100_000);
+    let data = std::fs::metadata(&status.path).unwrap();
+    assert_eq!(data.len(), 100_000);
+
+    cmd_tx.send(Command::Stop).unwrap();
+    handle.join().unwrap().unwrap();
+}
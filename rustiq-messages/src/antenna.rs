@@ -0,0 +1,83 @@
+use crate::{FrequencyRange, Hertz};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How the engine talks to an external antenna switch.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AntennaSwitchLink {
+    /// Write each antenna's command to a serial port. The port's baud rate
+    /// and framing must already be configured (e.g. with `stty`).
+    Serial { path: PathBuf },
+    /// Send each antenna's command over TCP, as used by network relay boards.
+    Network { address: String },
+    /// Drive GPIO pins through Linux sysfs with the antenna's index in binary,
+    /// least significant bit on the first pin.
+    Gpio { pins: Vec<u32> },
+}
+
+impl AntennaSwitchLink {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Serial { .. } => "Serial",
+            Self::Network { .. } => "Network",
+            Self::Gpio { .. } => "GPIO",
+        }
+    }
+}
+
+/// One antenna port of the switch.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Antenna {
+    pub name: String,
+    /// Bytes sent to select this antenna over a serial or network link.
+    /// Escapes such as `\r`, `\n` and `\x01` are expanded.
+    pub command: String,
+}
+
+/// Antenna to select whenever the source is tuned into a frequency range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AntennaRule {
+    /// Center frequencies the rule applies to.
+    pub range: FrequencyRange,
+    /// Index into the switch's antennas.
+    pub antenna: usize,
+}
+
+impl AntennaRule {
+    /// The rule to use at `frequency`. Where ranges overlap the narrowest
+    /// one wins.
+    pub fn find(rules: &[AntennaRule], frequency: Hertz) -> Option<&AntennaRule> {
+        rules
+            .iter()
+            .filter(|rule| rule.range.contains(frequency))
+            .min_by_key(|rule| rule.range.width())
+    }
+}
+
+/// An external antenna switch and the rules for choosing its antenna.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AntennaSwitchConfig {
+    pub link: AntennaSwitchLink,
+    pub antennas: Vec<Antenna>,
+    pub rules: Vec<AntennaRule>,
+}
+
+/// When to capture a dark frame: a reference spectrum of the front end with
+/// its input switched to a termination (a dummy load on one of the antenna
+/// switch's ports), so artifacts of the front end itself, e.g. spurs and a
+/// DC spike drifting with temperature, can be subtracted from the spectrum.
+/// Captured again on a schedule, so monitoring stays clean over days.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DarkFrameConfig {
+    /// Index of the antenna switch port the termination is on
+    pub termination: usize,
+    /// Time between captures
+    pub interval: Duration,
+    /// Spectrum frames averaged into each dark frame
+    pub frames: u32,
+}
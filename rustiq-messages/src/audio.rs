@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use crate::{Hertz, SignalRegion};
+
+/// Signals at least this wide are taken for FM, narrower ones for SSB.
+const FM_MIN_BANDWIDTH: Hertz = Hertz(5_000);
+
+/// Signals at least this wide are taken for broadcast FM.
+const WFM_MIN_BANDWIDTH: Hertz = Hertz(100_000);
+
+/// Lowest audio frequency of a sideband's passband, in Hz from the dial;
+/// the mode's bandwidth extends it away from the dial frequency.
+const SSB_LOW: i64 = 150;
+
+/// How a narrow channel is demodulated to audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DemodMode {
+    /// Upper sideband: audio frequencies appear above the dial frequency.
+    Usb,
+    /// Narrowband FM.
+    Fm,
+    /// Broadcast FM, in stereo when the station sends its pilot tone.
+    Wfm,
+    /// Amplitude modulation, the carrier at the dial frequency.
+    Am,
+    /// Lower sideband: audio frequencies appear below the dial frequency.
+    Lsb,
+    /// Morse, keyed carrier at the dial frequency heard as a tone.
+    Cw,
+}
+
+impl DemodMode {
+    pub const ALL: [DemodMode; 6] = [
+        DemodMode::Am,
+        DemodMode::Fm,
+        DemodMode::Wfm,
+        DemodMode::Usb,
+        DemodMode::Lsb,
+        DemodMode::Cw,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Usb => "USB",
+            Self::Fm => "NFM",
+            Self::Wfm => "WFM",
+            Self::Am => "AM",
+            Self::Lsb => "LSB",
+            Self::Cw => "CW",
+        }
+    }
+
+    /// Width of the channel filter the mode is demodulated through, wide
+    /// enough for the usual signal of its kind.
+    pub fn bandwidth(self) -> Hertz {
+        match self {
+            Self::Usb | Self::Lsb => Hertz(2_700),
+            Self::Fm => Hertz(16_000),
+            Self::Wfm => Hertz(200_000),
+            Self::Am => Hertz(10_000),
+            Self::Cw => Hertz(500),
+        }
+    }
+
+    /// The mode's channel filter: its bandwidth off to one side of the dial
+    /// for the sidebands, centered on it for the others.
+    pub fn passband(self) -> Passband {
+        let width = self.bandwidth().0 as i64;
+        match self {
+            Self::Usb => Passband {
+                low: SSB_LOW,
+                high: SSB_LOW + width,
+            },
+            Self::Lsb => Passband {
+                low: -SSB_LOW - width,
+                high: -SSB_LOW,
+            },
+            _ => Passband {
+                low: -width / 2,
+                high: width / 2,
+            },
+        }
+    }
+}
+
+/// Edges of a channel filter, in Hz from the dial frequency: negative
+/// below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Passband {
+    pub low: i64,
+    pub high: i64,
+}
+
+impl Passband {
+    pub fn width(self) -> u64 {
+        self.high.abs_diff(self.low)
+    }
+
+    /// Middle of the passband, in Hz from the dial frequency.
+    pub fn center(self) -> f64 {
+        (self.low + self.high) as f64 / 2.0
+    }
+}
+
+/// A channel demodulated to audio for a decoder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioChannel {
+    /// Dial frequency: the suppressed carrier for USB and LSB, the carrier
+    /// for AM and CW, the channel center for FM.
+    pub frequency: Hertz,
+    pub demod: DemodMode,
+}
+
+/// Audio demodulated from the channel being listened to, for the UI to play.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioChunk {
+    /// Samples per second; the demodulator's rate, not a device's
+    pub sample_rate: f64,
+    /// Samples, full scale at ±1: mono, or left and right interleaved if
+    /// `stereo`
+    pub samples: Vec<f32>,
+    /// Whether the audio is stereo, as broadcast FM with a pilot tone is
+    pub stereo: bool,
+}
+
+/// Where the audio of the channel being listened to goes: any of the UI's
+/// speakers, a WAV recording and network listeners, or none of them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioRouting {
+    /// Send it to the UI to play
+    pub speakers: bool,
+    /// Append it to this WAV file, 16-bit mono at the demodulator's rate
+    pub recording: Option<PathBuf>,
+    /// Send it as UDP datagrams of 16-bit little-endian mono samples to
+    /// each of these `host:port` addresses
+    pub network: Vec<String>,
+}
+
+impl Default for AudioRouting {
+    fn default() -> Self {
+        Self {
+            speakers: true,
+            recording: None,
+            network: Vec::new(),
+        }
+    }
+}
+
+impl AudioChannel {
+    /// A guess at the channel a signal is heard on, from its width: a wide
+    /// signal is taken for FM, or broadcast FM if very wide, and demodulated
+    /// at its center, a narrow one for USB with the dial at its lower edge.
+    pub fn for_signal(region: SignalRegion) -> Self {
+        if region.bandwidth.0 >= WFM_MIN_BANDWIDTH.0 {
+            Self {
+                frequency: region.frequency,
+                demod: DemodMode::Wfm,
+            }
+        } else if region.bandwidth.0 >= FM_MIN_BANDWIDTH.0 {
+            Self {
+                frequency: region.frequency,
+                demod: DemodMode::Fm,
+            }
+        } else {
+            Self {
+                frequency: Hertz(region.frequency.0.saturating_sub(region.bandwidth.0 / 2)),
+                demod: DemodMode::Usb,
+            }
+        }
+    }
+}
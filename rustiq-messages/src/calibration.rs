@@ -0,0 +1,32 @@
+use crate::Decibels;
+
+/// Response of the front end at an offset from the center frequency, as
+/// measured with a flat reference source, relative to the center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationPoint {
+    /// Offset from the center frequency, in Hz (negative below it)
+    pub offset: f64,
+    /// Gain there relative to the center (negative for roll-off)
+    pub gain: Decibels,
+}
+
+impl CalibrationPoint {
+    /// The gain at `offset`, interpolated linearly between the points, which
+    /// are sorted by offset. Beyond the first and last points their gain
+    /// holds; with no points there is none.
+    pub fn gain_at(points: &[CalibrationPoint], offset: f64) -> Decibels {
+        let above = points.partition_point(|point| point.offset < offset);
+        match (
+            above.checked_sub(1).map(|i| points[i]),
+            points.get(above).copied(),
+        ) {
+            (Some(below), Some(above)) => {
+                let t = ((offset - below.offset) / (above.offset - below.offset)) as f32;
+                Decibels(below.gain.0 + t * (above.gain.0 - below.gain.0))
+            }
+            (Some(point), None) | (None, Some(point)) => point.gain,
+            (None, None) => Decibels(0.0),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{Decibels, DemodMode, FrequencyRange, Hertz, SourceConfig};
+
+/// Kinds of signal source, without their settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SourceKind {
+    SignalGenerator,
+    File,
+    RtlSdr,
+    SoapySdr,
+    RtlTcp,
+    SpyServer,
+}
+
+impl SourceKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::SignalGenerator => "Signal Generator",
+            Self::File => "IQ File",
+            Self::RtlSdr => "RTL-SDR",
+            Self::SoapySdr => "SoapySDR",
+            Self::RtlTcp => "rtl_tcp",
+            Self::SpyServer => "SpyServer",
+        }
+    }
+
+    pub fn of(config: &SourceConfig) -> Self {
+        match config {
+            SourceConfig::SignalGenerator { .. } => Self::SignalGenerator,
+            SourceConfig::File { .. } => Self::File,
+            SourceConfig::RtlSdr { .. } => Self::RtlSdr,
+            SourceConfig::SoapySdr { .. } => Self::SoapySdr,
+            SourceConfig::RtlTcp { .. } => Self::RtlTcp,
+            SourceConfig::SpyServer { .. } => Self::SpyServer,
+        }
+    }
+}
+
+/// A source the engine can open.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceCapability {
+    pub kind: SourceKind,
+    /// Highest sample rate the source runs at, if it has a limit
+    pub max_sample_rate: Option<Hertz>,
+    /// Sample rates the source runs at, lowest first; empty if it runs at
+    /// any. Other rates are resampled from the nearest above them.
+    pub sample_rates: Vec<Hertz>,
+    /// Devices of the kind found when the engine started, for sources that
+    /// open one of several
+    pub devices: Vec<SourceDevice>,
+}
+
+/// A device a source can open, with the settings it offers.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceDevice {
+    /// Arguments that open the device, e.g. `driver=hackrf,serial=1234`
+    pub args: String,
+    /// Name to show for it
+    pub label: String,
+    /// Antenna ports it receives on
+    pub antennas: Vec<String>,
+    /// Gain stages, in signal order
+    pub gain_stages: Vec<GainStage>,
+    /// Span its analog filter can be set across, if it can be set
+    pub bandwidth: Option<FrequencyRange>,
+    /// Sample rates it runs at, lowest first; empty if its driver doesn't
+    /// say
+    pub sample_rates: Vec<Hertz>,
+}
+
+/// A gain stage of a device, with its range.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GainStage {
+    pub name: String,
+    pub min: Decibels,
+    pub max: Decibels,
+}
+
+/// Optional parts of the engine: analyses, decoders and hardware control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Feature {
+    CarrierMeasurement,
+    BurstDetection,
+    MeteorDetection,
+    ImpulseCounter,
+    SymbolRateEstimation,
+    SstvDecoder,
+    SelCallDecoder,
+    AisDecoder,
+    AdsbDecoder,
+    AntennaSwitch,
+    Rig,
+    Rotator,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 12] = [
+        Feature::CarrierMeasurement,
+        Feature::BurstDetection,
+        Feature::MeteorDetection,
+        Feature::ImpulseCounter,
+        Feature::SymbolRateEstimation,
+        Feature::SstvDecoder,
+        Feature::SelCallDecoder,
+        Feature::AisDecoder,
+        Feature::AdsbDecoder,
+        Feature::AntennaSwitch,
+        Feature::Rig,
+        Feature::Rotator,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::CarrierMeasurement => "Carrier measurement",
+            Self::BurstDetection => "Burst detection",
+            Self::MeteorDetection => "Meteor scatter detection",
+            Self::ImpulseCounter => "Impulse counter",
+            Self::SymbolRateEstimation => "Symbol rate estimation",
+            Self::SstvDecoder => "SSTV decoder",
+            Self::SelCallDecoder => "SelCall decoder",
+            Self::AisDecoder => "AIS decoder",
+            Self::AdsbDecoder => "ADS-B decoder",
+            Self::AntennaSwitch => "Antenna switch",
+            Self::Rig => "Rig control",
+            Self::Rotator => "Rotator control",
+        }
+    }
+}
+
+/// What the running engine build supports, sent once at startup so the UI
+/// offers only what will work.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    /// Version of the engine
+    pub version: String,
+    pub sources: Vec<SourceCapability>,
+    /// Modes the demodulator and the audio channels of decoders can
+    /// demodulate
+    pub demod_modes: Vec<DemodMode>,
+    pub features: Vec<Feature>,
+}
+
+impl Capabilities {
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn source(&self, kind: SourceKind) -> Option<&SourceCapability> {
+        self.sources.iter().find(|source| source.kind == kind)
+    }
+
+    /// The device of a source kind opened with `args`, if the engine found it.
+    pub fn device(&self, kind: SourceKind, args: &str) -> Option<&SourceDevice> {
+        self.source(kind)?
+            .devices
+            .iter()
+            .find(|device| device.args == args)
+    }
+}
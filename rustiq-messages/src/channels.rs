@@ -0,0 +1,227 @@
+use crate::{DemodMode, Hertz};
+
+/// Part of the world whose band plans apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelRegion {
+    #[default]
+    Europe,
+    NorthAmerica,
+}
+
+impl ChannelRegion {
+    pub const ALL: [ChannelRegion; 2] = [Self::Europe, Self::NorthAmerica];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Europe => "Europe",
+            Self::NorthAmerica => "North America",
+        }
+    }
+
+    /// Band plans in use in the region.
+    pub fn plans(self) -> &'static [ChannelPlan] {
+        match self {
+            Self::Europe => &[
+                ChannelPlan::Marine,
+                ChannelPlan::Airband833,
+                ChannelPlan::Pmr446,
+            ],
+            Self::NorthAmerica => &[
+                ChannelPlan::Marine,
+                ChannelPlan::Airband,
+                ChannelPlan::FrsGmrs,
+            ],
+        }
+    }
+}
+
+/// Band plan of channels, each on a set frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelPlan {
+    /// International marine VHF, ship transmit frequencies
+    Marine,
+    /// VHF airband AM on 25 kHz channels, named by frequency
+    Airband,
+    /// VHF airband AM on 8.33 kHz channels, named by the frequencies that
+    /// tell them from 25 kHz ones, as dialled in Europe
+    Airband833,
+    /// PMR446 license-free radios
+    Pmr446,
+    /// FRS and GMRS radios, sharing channel numbers
+    FrsGmrs,
+}
+
+/// One channel of a plan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    /// Name within the plan, e.g. `Ch 16` or `121.500`
+    pub name: String,
+    pub frequency: Hertz,
+    /// What the channel is set aside for, if anything
+    pub purpose: Option<&'static str>,
+}
+
+impl ChannelPlan {
+    pub const ALL: [ChannelPlan; 5] = [
+        Self::Marine,
+        Self::Airband,
+        Self::Airband833,
+        Self::Pmr446,
+        Self::FrsGmrs,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Marine => "Marine",
+            Self::Airband | Self::Airband833 => "Air",
+            Self::Pmr446 => "PMR446",
+            Self::FrsGmrs => "FRS/GMRS",
+        }
+    }
+
+    /// Name of the plan in a list of them.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Marine => "Marine VHF",
+            Self::Airband => "Airband, 25 kHz",
+            Self::Airband833 => "Airband, 8.33 kHz",
+            Self::Pmr446 => "PMR446",
+            Self::FrsGmrs => "FRS/GMRS",
+        }
+    }
+
+    /// How the plan's channels are demodulated.
+    pub fn demod(self) -> DemodMode {
+        match self {
+            Self::Airband | Self::Airband833 => DemodMode::Am,
+            Self::Marine | Self::Pmr446 | Self::FrsGmrs => DemodMode::Fm,
+        }
+    }
+
+    /// Every channel of the plan in order, e.g. to scan through.
+    pub fn channels(self) -> Vec<Channel> {
+        match self {
+            // 25 kHz apart, 60 to 88 interleaved between 1 to 28
+            Self::Marine => self.numbered((1..=28).chain(60..=88), |number| match number {
+                1..=28 => 156_000_000 + 50_000 * u64::from(number),
+                _ => 153_025_000 + 50_000 * u64::from(number),
+            }),
+            Self::Pmr446 => self.numbered(1..=16, |number| {
+                446_006_250 + 12_500 * u64::from(number - 1)
+            }),
+            Self::FrsGmrs => self.numbered(1..=22, |number| match number {
+                1..=7 => 462_562_500 + 25_000 * u64::from(number - 1),
+                8..=14 => 467_562_500 + 25_000 * u64::from(number - 8),
+                _ => 462_550_000 + 25_000 * u64::from(number - 15),
+            }),
+            Self::Airband => (0..760)
+                .map(|i| {
+                    let frequency = 118_000_000 + 25_000 * i;
+                    Channel {
+                        name: format!("{:.3}", frequency as f64 / 1e6),
+                        frequency: Hertz(frequency),
+                        purpose: airband_purpose(frequency),
+                    }
+                })
+                .collect(),
+            // Each 25 kHz block splits in three, named 5, 10 and 15 kHz
+            // above the block
+            Self::Airband833 => (0..760)
+                .flat_map(|block| {
+                    let base = 118_000_000 + 25_000 * block;
+                    (0..3).map(move |i| {
+                        let frequency = base + (25_000 * i + 1) / 3;
+                        Channel {
+                            name: format!("{:.3}", (base + 5_000 * (i + 1)) as f64 / 1e6),
+                            frequency: Hertz(frequency),
+                            purpose: airband_purpose(frequency),
+                        }
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Channels called by `numbers`, each on `frequency(number)` Hz.
+    fn numbered(
+        self,
+        numbers: impl Iterator<Item = u32>,
+        frequency: impl Fn(u32) -> u64,
+    ) -> Vec<Channel> {
+        numbers
+            .map(|number| Channel {
+                name: format!("Ch {number}"),
+                frequency: Hertz(frequency(number)),
+                purpose: self.purpose(number),
+            })
+            .collect()
+    }
+
+    /// What numbered channel `number` of the plan is set aside for.
+    fn purpose(self, number: u32) -> Option<&'static str> {
+        match (self, number) {
+            (Self::Marine, 16) => Some("Distress, safety and calling"),
+            (Self::Marine, 70) => Some("Digital selective calling"),
+            (Self::Marine, 6) => Some("Intership safety"),
+            (Self::Marine, 13) => Some("Bridge to bridge"),
+            (Self::FrsGmrs, 20) => Some("Emergency, by convention"),
+            _ => None,
+        }
+    }
+
+    /// The channel exactly on `frequency`, if any.
+    pub fn channel_at(self, frequency: Hertz) -> Option<Channel> {
+        self.channels()
+            .into_iter()
+            .find(|channel| channel.frequency == frequency)
+    }
+
+    /// The channel nearest `frequency`, if it is within the plan's band:
+    /// no further outside it than half a channel spacing.
+    pub fn nearest(self, frequency: Hertz) -> Option<Channel> {
+        let channels = self.channels();
+        let nearest = channels
+            .iter()
+            .min_by_key(|channel| channel.frequency.as_hz().abs_diff(frequency.as_hz()))?;
+        let spacing = match self {
+            Self::Marine | Self::Airband | Self::FrsGmrs => 25_000,
+            Self::Airband833 => 8_333,
+            Self::Pmr446 => 12_500,
+        };
+        (nearest.frequency.as_hz().abs_diff(frequency.as_hz()) <= spacing / 2)
+            .then(|| nearest.clone())
+    }
+
+    /// The channel of this plan called `name`, with or without the plan's
+    /// label, ignoring case and spaces, e.g. `Marine Ch 16` or `ch16`.
+    pub fn find(self, name: &str) -> Option<Channel> {
+        let squash = |text: &str| -> String {
+            text.chars()
+                .filter(|c| !c.is_whitespace())
+                .flat_map(char::to_lowercase)
+                .collect()
+        };
+        let name = squash(name);
+        let name = name.strip_prefix(&squash(self.label())).unwrap_or(&name);
+        self.channels()
+            .into_iter()
+            .find(|channel| squash(&channel.name) == name)
+    }
+
+    /// `channel` as shown beside a frequency, e.g. `Marine Ch 16`.
+    pub fn display(self, channel: &Channel) -> String {
+        format!("{} {}", self.label(), channel.name)
+    }
+}
+
+/// What an airband frequency is set aside for.
+fn airband_purpose(frequency: u64) -> Option<&'static str> {
+    match frequency {
+        121_500_000 => Some("Emergency"),
+        123_450_000 => Some("Air to air"),
+        _ => None,
+    }
+}
@@ -1,10 +1,201 @@
-use crate::SourceConfig;
+use crate::{
+    AntennaSwitchConfig, AudioChannel, AudioRouting, Averaging, CalibrationPoint, DarkFrameConfig,
+    Decibels, GainProfile, Hertz, Lockout, MeteorConfig, Passband, RecordingFormat, RigConfig,
+    RotatorPosition, ScanList, SelCallConfig, SessionRecord, SignalRegion, SourceConfig, VfoConfig,
+};
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Commands sent from the UI to the engine.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command {
     /// Stop the engine and terminate the DSP graph.
     Stop,
     /// Change the input source. Engine will stop current graph, rebuild, and restart.
     ChangeSource(SourceConfig),
+    /// Tune the source to a new center frequency. The gain profile covering the
+    /// frequency, if any, replaces the current gain. The running graph is
+    /// retuned in place, so the stream carries on; the engine rebuilds it only
+    /// if it has to: the gain changes, a channel analysis or decoder is
+    /// running, or the source must be reopened to change frequency.
+    SetCenterFrequency(Hertz),
+    /// Set the source gain (negative for attenuation). Engine will rebuild the graph.
+    SetGain(Decibels),
+    /// Replace the gain profiles, applying the one covering the current center
+    /// frequency. Engine will rebuild the graph.
+    SetGainProfiles(Vec<GainProfile>),
+    /// Configure (or with `None`, remove) the external antenna switch, selecting
+    /// the antenna its rules give for the current center frequency.
+    /// Engine will rebuild the graph.
+    SetAntennaSwitch(Option<AntennaSwitchConfig>),
+    /// Select an antenna by index until the next retune.
+    /// Engine will rebuild the graph.
+    SelectAntenna(usize),
+    /// Capture dark frames on this schedule and subtract the latest from
+    /// the main source's spectrum, or stop with `None`. Needs the antenna
+    /// switch configured. The first is captured right away; while one is,
+    /// no spectrum frames are sent. The running graph is left alone.
+    SetDarkFrame(Option<DarkFrameConfig>),
+    /// Connect to a hamlib `rotctld` at the given `host:port`, replacing any
+    /// connected rotator.
+    ConnectRotator(String),
+    /// Disconnect from the rotator.
+    DisconnectRotator,
+    /// Turn the connected rotator to the given position.
+    PointRotator(RotatorPosition),
+    /// Follow a transceiver's frequency through `rigctld`, replacing any
+    /// connected rig. Engine will rebuild the graph on each change.
+    ConnectRig(RigConfig),
+    /// Stop following the rig.
+    DisconnectRig,
+    /// Tune the connected rig to the given frequency.
+    TuneRig(Hertz),
+    /// Return to the source and tuning of a session that ended in a crash.
+    /// Engine will rebuild the graph.
+    RestoreSession(SessionRecord),
+    /// Lock onto the carrier nearest the given frequency and report its frequency over time.
+    /// Engine will rebuild the graph with a measurement branch.
+    StartCarrierMeasurement(Hertz),
+    /// Stop the active carrier measurement.
+    StopCarrierMeasurement,
+    /// Detect bursts rising the given amount above the noise floor.
+    /// Engine will rebuild the graph with a detection branch.
+    StartBurstDetection(Decibels),
+    /// Stop the active burst detection.
+    StopBurstDetection,
+    /// Watch a narrow channel for meteor scatter pings.
+    /// Engine will rebuild the graph with a detection branch.
+    StartMeteorDetection(MeteorConfig),
+    /// Stop the active meteor scatter detection.
+    StopMeteorDetection,
+    /// Count broadband impulses rising the given amount above the noise floor.
+    /// Engine will rebuild the graph with a counting branch.
+    StartImpulseCounter(Decibels),
+    /// Stop the active impulse counter.
+    StopImpulseCounter,
+    /// Decode SSTV images from the given audio channel.
+    /// Engine will rebuild the graph with a decoder branch.
+    StartSstvDecoder(AudioChannel),
+    /// Stop the active SSTV decoder.
+    StopSstvDecoder,
+    /// Decode 5-tone selective calls from the given audio channel.
+    /// Engine will rebuild the graph with a decoder branch.
+    StartSelCallDecoder(SelCallConfig),
+    /// Stop the active SelCall decoder.
+    StopSelCallDecoder,
+    /// Decode AIS messages from the channel at the given frequency.
+    /// Engine will rebuild the graph with a decoder branch.
+    StartAisDecoder(Hertz),
+    /// Stop the active AIS decoder.
+    StopAisDecoder,
+    /// Decode ADS-B messages; the source must be centered on 1090 MHz at 2 MS/s
+    /// (or a multiple). Engine will rebuild the graph with a decoder branch.
+    StartAdsbDecoder,
+    /// Stop the active ADS-B decoder.
+    StopAdsbDecoder,
+    /// Show a second source next to the main one, or stop showing it with
+    /// `None`. It is tuned and scaled like the main source but only gets a
+    /// spectrum. Engine will rebuild the graph.
+    SetSecondSource(Option<SourceConfig>),
+    /// Run a reference tone through the receive chain on a graph of its own
+    /// and report the levels each stage measured. The running graph is left
+    /// alone.
+    RunSelfTest,
+    /// Estimate the symbol rate of the signal in a region, once, from a
+    /// second of the stream. Engine will rebuild the graph.
+    EstimateSymbolRate(SignalRegion),
+    /// Demodulate the given channel for listening, sending its audio as
+    /// `AudioChunk` events, or stop with `None`. Engine will rebuild the graph
+    /// with a demodulator branch.
+    SetDemodulator(Option<AudioChannel>),
+    /// Change the number of bins in the spectrum, a power of two.
+    /// Engine will rebuild the graph.
+    SetFftSize(usize),
+    /// Replace the calibration table the spectrum is corrected by, sorted by
+    /// offset, or stop correcting it with an empty one. Engine will rebuild
+    /// the graph.
+    SetCalibration(Vec<CalibrationPoint>),
+    /// Tune hardware sources a little off the center frequency and shift
+    /// their samples back, moving the DC spike of a zero-IF front end out of
+    /// the channel at the center. Engine will rebuild the graph.
+    SetOffsetTuning(bool),
+    /// Send the state snapshot and capabilities again, for a UI that has
+    /// just attached to a running engine. The running graph is left alone.
+    Resync,
+    /// Record the IQ stream of the main source to `path` while it runs on,
+    /// following retunes. Changing the source ends the recording. Engine
+    /// will rebuild the graph with a recording branch.
+    StartRecording {
+        path: PathBuf,
+        format: RecordingFormat,
+    },
+    /// Finish the recording. Engine will rebuild the graph.
+    StopRecording,
+    /// Change how spectrum frames are averaged before they are sent.
+    /// Engine will rebuild the graph.
+    SetAveraging(Averaging),
+    /// Keep the highest level of each bin, sent along with each spectrum
+    /// frame, or stop. Engine will rebuild the graph, starting the trace
+    /// over.
+    SetPeakHold(bool),
+    /// Keep the lowest level of each bin, as `SetPeakHold` the highest.
+    /// Engine will rebuild the graph.
+    SetMinHold(bool),
+    /// Send the listening channel's audio where `AudioRouting` says. A
+    /// recording already going to the same path carries on. The running
+    /// graph is left alone.
+    SetAudioRouting(AudioRouting),
+    /// Low-pass filter and decimate the stream ahead of the spectrum FFT by
+    /// a power of two, zooming into the slice of the band around the center
+    /// at full FFT resolution; 1 shows the whole band. Analyses still see
+    /// the whole band. Engine will rebuild the graph.
+    SetDecimation(usize),
+    /// Center the spectrum this many Hz off the center frequency, mixing
+    /// the stream ahead of the decimation rather than retuning the
+    /// hardware. Combined with `SetDecimation`, zooms into any slice of the
+    /// band. Must stay within half the sample rate of the center. Engine
+    /// will rebuild the graph.
+    SetFrequencyOffset(i64),
+    /// Play a file source at this many times real time, from 0.5 to 2,
+    /// rather than as fast as it can be read; `None` goes back to that.
+    /// The listening channel's audio is time-stretched to keep up with the
+    /// spectrum without changing its pitch. Live sources ignore it. The
+    /// running graph is left alone.
+    SetPlaybackSpeed(Option<f32>),
+    /// Play a file source from this far into it, as picked from its
+    /// `RecordingOverview`. Later rebuilds start an unpaced file over from
+    /// here; a paced one carries on. A paused file stays paused, held
+    /// there. Engine will rebuild the graph.
+    Seek(Duration),
+    /// Hold a file source where it is, sending nothing on until `Resume`.
+    /// Live sources ignore it. The running graph is left alone.
+    Pause,
+    /// Carry on playing a paused file source from where it was held.
+    Resume,
+    /// Mute the listening channel's audio while its power is below this
+    /// level, in dB relative to full scale; `None` leaves it open. The
+    /// running graph is left alone.
+    SetSquelch(Option<Decibels>),
+    /// Step the listening channel through the list, stopping on each
+    /// channel while its squelch is open. Needs the squelch set. The running
+    /// graph is left alone while the scan hops.
+    StartScan(ScanList),
+    /// Stop the scan, listening on where it got to.
+    StopScan,
+    /// Lock the scan list's channel at this index out of the scan, or let
+    /// it back in.
+    SetLockout { channel: usize, lockout: Lockout },
+    /// Filter the listening channel through this passband rather than its
+    /// mode's; `None` goes back to the mode's. Another mode starts from its
+    /// own. The running graph is left alone.
+    SetPassband(Option<Passband>),
+    /// Demodulate another channel of the source alongside the listening
+    /// channel. Engine will rebuild the graph.
+    AddVfo(VfoConfig),
+    /// Stop the VFO with this id. Engine will rebuild the graph.
+    RemoveVfo(u32),
+    /// Move or reconfigure the VFO with this id. Engine will rebuild the
+    /// graph; a recording to the same file carries on.
+    ConfigureVfo { id: u32, config: VfoConfig },
 }
@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use crate::AudioChannel;
+
+/// Slow-scan TV transmission modes understood by the SSTV decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SstvMode {
+    Martin1,
+    Martin2,
+    Scottie1,
+    Scottie2,
+    ScottieDx,
+    Robot36,
+}
+
+impl SstvMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Martin1 => "Martin M1",
+            Self::Martin2 => "Martin M2",
+            Self::Scottie1 => "Scottie S1",
+            Self::Scottie2 => "Scottie S2",
+            Self::ScottieDx => "Scottie DX",
+            Self::Robot36 => "Robot 36",
+        }
+    }
+
+    /// Image width in pixels.
+    pub fn width(self) -> usize {
+        320
+    }
+
+    /// Image height in lines.
+    pub fn height(self) -> usize {
+        match self {
+            Self::Robot36 => 240,
+            _ => 256,
+        }
+    }
+}
+
+/// Progress of an SSTV image being received.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SstvEvent {
+    /// A VIS code was received and a new image begins.
+    Started(SstvMode),
+    /// A decoded line of RGB pixels.
+    Line { line: usize, pixels: Vec<[u8; 3]> },
+    /// All lines of the image have been received.
+    Finished,
+}
+
+/// Tone sets for 5-tone selective calling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SelCallStandard {
+    Zvei1,
+    Zvei3,
+    Ccir,
+    Eea,
+    Eia,
+}
+
+impl SelCallStandard {
+    pub const ALL: [SelCallStandard; 5] = [
+        SelCallStandard::Zvei1,
+        SelCallStandard::Zvei3,
+        SelCallStandard::Ccir,
+        SelCallStandard::Eea,
+        SelCallStandard::Eia,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Zvei1 => "ZVEI1",
+            Self::Zvei3 => "ZVEI3",
+            Self::Ccir => "CCIR",
+            Self::Eea => "EEA",
+            Self::Eia => "EIA",
+        }
+    }
+
+    /// Tone frequencies in Hz for digits 0-9, followed by the repeat tone.
+    pub fn tones(self) -> [f32; 11] {
+        match self {
+            Self::Zvei1 => [
+                2400.0, 1060.0, 1160.0, 1270.0, 1400.0, 1530.0, 1670.0, 1830.0, 2000.0, 2200.0,
+                2600.0,
+            ],
+            Self::Zvei3 => [
+                2200.0, 970.0, 1060.0, 1160.0, 1270.0, 1400.0, 1530.0, 1670.0, 1830.0, 2000.0,
+                2400.0,
+            ],
+            Self::Ccir | Self::Eea => [
+                1981.0, 1124.0, 1197.0, 1275.0, 1358.0, 1446.0, 1540.0, 1640.0, 1747.0, 1860.0,
+                2110.0,
+            ],
+            Self::Eia => [
+                600.0, 741.0, 882.0, 1023.0, 1164.0, 1305.0, 1446.0, 1587.0, 1728.0, 1869.0, 459.0,
+            ],
+        }
+    }
+
+    /// Duration of each tone.
+    pub fn tone_duration(self) -> Duration {
+        Duration::from_millis(match self {
+            Self::Zvei1 | Self::Zvei3 => 70,
+            Self::Ccir => 100,
+            Self::Eea => 40,
+            Self::Eia => 33,
+        })
+    }
+}
+
+/// Channel and tone set for the SelCall decoder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelCallConfig {
+    pub channel: AudioChannel,
+    pub standard: SelCallStandard,
+}
+
+/// A decoded selective call.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelCall {
+    /// Start of the sequence since decoding started, in sample time.
+    pub start: Duration,
+    pub standard: SelCallStandard,
+    /// Decoded digits, with repeat tones already expanded.
+    pub id: String,
+}
+
+/// What a broadcast FM station sends about itself over RDS.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RdsData {
+    /// Programme identification code, unique to the station in its area
+    pub pi: u16,
+    /// Programme service name, up to 8 characters, once all of it is in
+    pub station: Option<String>,
+    /// Radiotext, up to 64 characters, once all of it is in
+    pub radiotext: Option<String>,
+}
+
+/// What kind of target a track report describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackKind {
+    /// A ship, from the AIS decoder.
+    Vessel,
+    /// An aircraft, from the ADS-B decoder.
+    Aircraft,
+}
+
+impl TrackKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Vessel => "AIS",
+            Self::Aircraft => "ADS-B",
+        }
+    }
+}
+
+/// A point on the WGS84 ellipsoid, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoPosition {
+    /// Degrees north of the equator
+    pub latitude: f64,
+    /// Degrees east of Greenwich
+    pub longitude: f64,
+}
+
+/// What one decoded AIS or ADS-B message says about a target.
+///
+/// A single message rarely carries everything, so each field is optional and
+/// consumers merge reports with the same kind and ID.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackReport {
+    pub kind: TrackKind,
+    /// MMSI for vessels, 24-bit ICAO address in hex for aircraft
+    pub id: String,
+    /// Vessel name or aircraft callsign
+    pub name: Option<String>,
+    pub position: Option<GeoPosition>,
+    /// Barometric altitude in feet (aircraft only)
+    pub altitude: Option<f32>,
+    /// Course over ground in degrees true
+    pub course: Option<f32>,
+    /// Speed over ground in knots
+    pub speed: Option<f32>,
+}
+
+impl TrackReport {
+    /// A report carrying nothing but the target's identity.
+    pub fn new(kind: TrackKind, id: impl Into<String>) -> Self {
+        Self {
+            kind,
+            id: id.into(),
+            name: None,
+            position: None,
+            altitude: None,
+            course: None,
+            speed: None,
+        }
+    }
+}
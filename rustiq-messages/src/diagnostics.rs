@@ -0,0 +1,57 @@
+use crate::{Decibels, Hertz};
+
+/// Stages of the receive chain the self test checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    /// IQ samples straight from the source
+    Source,
+    /// FFT magnitudes, as drawn in the waterfall
+    Spectrum,
+    /// USB-demodulated audio
+    Audio,
+}
+
+impl Stage {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Source => "Source",
+            Self::Spectrum => "Spectrum",
+            Self::Audio => "Audio",
+        }
+    }
+}
+
+/// The reference tone as measured at one stage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StageCheck {
+    pub stage: Stage,
+    /// Peak level of the tone, in dBFS, or `None` if nothing reached the stage
+    pub level: Option<Decibels>,
+    /// Frequency of the tone, or `None` if nothing reached the stage
+    pub frequency: Option<Hertz>,
+    /// Whether level and frequency are within tolerance of the reference
+    pub passed: bool,
+}
+
+/// Result of running a reference tone of known level and frequency through
+/// the receive chain.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelfTestReport {
+    /// Frequency of the tone above the source's DC, which is also its audio
+    /// frequency in USB
+    pub tone_frequency: Hertz,
+    /// Peak level of the tone, in dBFS
+    pub tone_level: Decibels,
+    /// Allowed deviation of each stage's level
+    pub tolerance: Decibels,
+    pub checks: Vec<StageCheck>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        !self.checks.is_empty() && self.checks.iter().all(|check| check.passed)
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::SourceKind;
+
+/// Something the engine couldn't do, with what it was doing it with. Sent
+/// to the UI, local or remote, as well as logged, so the user sees why and
+/// what to check.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RustIqError {
+    /// A source couldn't be opened or stopped delivering samples.
+    Source { kind: SourceKind, detail: String },
+    /// Hardware other than the source, such as an antenna switch, rig or
+    /// rotator, didn't respond as expected.
+    Device { device: String, detail: String },
+    /// A file couldn't be read or written as its format says, such as an IQ
+    /// recording, WAV file or SigMF metadata.
+    Format { path: PathBuf, detail: String },
+    /// A server or peer didn't speak the protocol expected of it, or the
+    /// connection to it broke.
+    Protocol { peer: String, detail: String },
+    /// The engine held back to stay within a limit it was started with.
+    Limit { resource: Resource, detail: String },
+}
+
+/// What the engine can be limited in, e.g. to run on a small board
+/// alongside other services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Resource {
+    /// Memory for spectrum frames
+    Memory,
+    /// Threads for work in the background
+    Threads,
+    /// Disk space for recordings
+    Disk,
+}
+
+impl Resource {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Memory => "Memory",
+            Self::Threads => "Thread",
+            Self::Disk => "Disk",
+        }
+    }
+}
+
+impl RustIqError {
+    /// What the user can check to put it right.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::Source {
+                kind: SourceKind::File,
+                ..
+            } => "Check the path, and that the format chosen matches the file.",
+            Self::Source {
+                kind: SourceKind::RtlSdr | SourceKind::SoapySdr,
+                ..
+            } => "Check the device is plugged in and not in use by another program.",
+            Self::Source {
+                kind: SourceKind::RtlTcp | SourceKind::SpyServer,
+                ..
+            } => "Check the server is running and reachable at that address.",
+            Self::Source { .. } => "Check the source settings.",
+            Self::Device { .. } => "Check the device is connected and its address is right.",
+            Self::Format { .. } => "Check the file is complete and in the format expected.",
+            Self::Protocol { .. } => {
+                "Check the address is of the right kind of server, and both ends run the same version."
+            }
+            Self::Limit { .. } => "Ask for less, or start the engine with a higher limit.",
+        }
+    }
+}
+
+impl fmt::Display for RustIqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Source { kind, detail } => write!(f, "{} source: {}", kind.label(), detail),
+            Self::Device { device, detail } => write!(f, "{device}: {detail}"),
+            Self::Format { path, detail } => write!(f, "{}: {}", path.display(), detail),
+            Self::Protocol { peer, detail } => write!(f, "{peer}: {detail}"),
+            Self::Limit { resource, detail } => write!(f, "{} limit: {}", resource.label(), detail),
+        }
+    }
+}
+
+impl std::error::Error for RustIqError {}
@@ -1,10 +1,83 @@
-use super::EngineState;
+use super::{
+    AudioChunk, Burst, Capabilities, CarrierMeasurement, Decibels, EngineState, Impulse, RdsData,
+    RecordingOverview, RecordingStatus, ReducedSpectrum, RotatorPosition, RustIqError, ScanHit,
+    SelCall, SelfTestReport, SessionRecord, SpectrumFrame, SstvEvent, SymbolRateEstimate,
+    TrackReport,
+};
+use std::time::Duration;
 
 /// Events sent from the engine to the UI.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
     /// Initial state snapshot sent on connection.
-    StateSnapshot(EngineState),
+    StateSnapshot(Box<EngineState>),
     /// FFT magnitude data for waterfall display.
-    SpectrumData(Vec<f32>),
+    SpectrumData(SpectrumFrame),
+    /// Frequency estimate from the active carrier measurement.
+    CarrierMeasurement(CarrierMeasurement),
+    /// A burst found by the active burst detection, sent once the burst has ended.
+    Burst(Burst),
+    /// A ping found by the active meteor scatter detection, sent once it has ended.
+    MeteorPing(Burst),
+    /// A static crash found by the active impulse counter, sent once it has ended.
+    Impulse(Impulse),
+    /// Image progress from the active SSTV decoder.
+    Sstv(SstvEvent),
+    /// A selective call from the active SelCall decoder.
+    SelCall(SelCall),
+    /// A ship or aircraft report from the active AIS or ADS-B decoder.
+    Track(TrackReport),
+    /// Where the connected rotator is pointing, polled periodically.
+    RotatorPosition(RotatorPosition),
+    /// The previous session didn't exit cleanly; sent once at startup so the
+    /// user can restore it. Its recording, if any, has been finalized.
+    PreviousSession(SessionRecord),
+    /// What the engine build supports; sent once at startup, after the
+    /// first state snapshot.
+    Capabilities(Capabilities),
+    /// Outcome of a `RunSelfTest`.
+    SelfTest(SelfTestReport),
+    /// Outcome of an `EstimateSymbolRate`.
+    SymbolRate(SymbolRateEstimate),
+    /// Audio from the channel set by `SetDemodulator`, sent as it is
+    /// demodulated.
+    AudioChunk(AudioChunk),
+    /// Progress of the recording started by `StartRecording`, sent about
+    /// once a second while it runs and once more when it ends.
+    RecordingStatus(RecordingStatus),
+    /// A spectrum frame cut down for a slow link, in place of
+    /// `SpectrumData`. Only sent over the network; the receiving end
+    /// expands it back.
+    ReducedSpectrum(ReducedSpectrum),
+    /// Spectrogram of the whole file being played, sent once it has been
+    /// worked out after the file source is opened.
+    RecordingOverview(RecordingOverview),
+    /// Something the engine couldn't do, such as open a source or start a
+    /// recording, sent as it happens. The engine carries on as it can.
+    Error(RustIqError),
+    /// Whether the listening channel's squelch opened (`true`) or closed,
+    /// sent as it changes.
+    SquelchState(bool),
+    /// Which of the scan list's channels the scan is on, and whether it
+    /// stopped there for activity, sent as either changes.
+    Scanning { channel: usize, active: bool },
+    /// Activity the scan stopped for, once it moves on from the channel.
+    ScanHit(ScanHit),
+    /// Whether the squelch of the VFO with this id is open, sent as it
+    /// changes.
+    VfoSquelch { id: u32, open: bool },
+    /// What the broadcast FM station being listened to sends over RDS,
+    /// sent as it changes.
+    RdsData(RdsData),
+    /// A dark frame was captured and is subtracted from the spectrum from
+    /// now on; its mean level, in dB relative to full scale.
+    DarkFrameCaptured(Decibels),
+    /// How spectrum frames flow to the UI, sent about once a second: the
+    /// frames dropped so far as the UI fell behind, and the frames sent
+    /// per second since the last report.
+    Stats { frames_dropped: u64, fps: f32 },
+    /// How far into a file source playback has got, sent a few times a
+    /// second while it moves and once after each seek.
+    PlaybackPosition(Duration),
 }
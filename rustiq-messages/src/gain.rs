@@ -0,0 +1,22 @@
+use crate::{Decibels, FrequencyRange, Hertz};
+
+/// Gain to apply whenever the source is tuned into a frequency range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GainProfile {
+    /// Center frequencies the profile applies to.
+    pub range: FrequencyRange,
+    /// Gain (negative for attenuation) applied to the source.
+    pub gain: Decibels,
+}
+
+impl GainProfile {
+    /// The profile to use at `frequency`. Where ranges overlap the narrowest
+    /// one wins, so a band can be carved out of a wider default.
+    pub fn find(profiles: &[GainProfile], frequency: Hertz) -> Option<&GainProfile> {
+        profiles
+            .iter()
+            .filter(|profile| profile.range.contains(frequency))
+            .min_by_key(|profile| profile.range.width())
+    }
+}
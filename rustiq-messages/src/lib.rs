@@ -1,9 +1,66 @@
+mod antenna;
+mod audio;
+mod calibration;
+mod capabilities;
+mod channels;
 mod command;
+mod decoder;
+mod diagnostics;
+mod error;
 mod event;
+mod gain;
+mod measurement;
+#[cfg(feature = "test-util")]
+pub mod mock;
+mod recording;
+mod rig;
+mod rotator;
+mod scanner;
+mod session;
+mod settings;
+mod spectrum;
 mod state;
+mod time;
+mod transport;
 mod units;
+mod vfo;
+mod wire;
 
+pub use antenna::{Antenna, AntennaRule, AntennaSwitchConfig, AntennaSwitchLink, DarkFrameConfig};
+pub use audio::{AudioChannel, AudioChunk, AudioRouting, DemodMode, Passband};
+pub use calibration::CalibrationPoint;
+pub use capabilities::{
+    Capabilities, Feature, GainStage, SourceCapability, SourceDevice, SourceKind,
+};
+pub use channels::{Channel, ChannelPlan, ChannelRegion};
 pub use command::Command;
+pub use decoder::{
+    GeoPosition, RdsData, SelCall, SelCallConfig, SelCallStandard, SstvEvent, SstvMode, TrackKind,
+    TrackReport,
+};
+pub use diagnostics::{SelfTestReport, Stage, StageCheck};
+pub use error::{Resource, RustIqError};
 pub use event::Event;
-pub use state::{EngineState, SourceConfig};
-pub use units::{Decibels, Hertz};
+pub use gain::GainProfile;
+pub use measurement::{
+    Burst, CarrierMeasurement, Impulse, MeteorConfig, SignalRegion, SymbolRateCandidate,
+    SymbolRateEstimate,
+};
+pub use recording::{RecordingFormat, RecordingStatus};
+pub use rig::RigConfig;
+pub use rotator::RotatorPosition;
+pub use scanner::{Lockout, ScanChannel, ScanHit, ScanList, SignalClass};
+pub use session::SessionRecord;
+pub use settings::{
+    Annotation, AnnotationPoint, AnnotationShape, CommandMacro, LastSession, SettingsBundle,
+    WindowGeometry,
+};
+pub use spectrum::{Averaging, Discontinuity, RecordingOverview, ReducedSpectrum, SpectrumFrame};
+pub use state::{EngineState, IqFormat, SourceConfig, StageGain, Sweep, Tone};
+pub use time::UtcTime;
+pub use transport::{
+    CommandSender, EventReceiver, Transport, WireCommands, WireEvents, WireTransport,
+};
+pub use units::{Decibels, FrequencyRange, Hertz};
+pub use vfo::{Vfo, VfoConfig};
+pub use wire::{Wire, WireError, read_frame, write_frame};
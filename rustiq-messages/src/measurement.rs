@@ -0,0 +1,85 @@
+use crate::{Decibels, Hertz};
+use std::time::Duration;
+
+/// A single reading from the carrier frequency measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CarrierMeasurement {
+    /// Time since the measurement started, in sample time (not wall clock).
+    pub elapsed: Duration,
+    /// Estimated carrier frequency in Hz, with sub-Hz precision.
+    pub frequency: f64,
+    /// Carrier amplitude relative to full scale.
+    pub power: Decibels,
+}
+
+/// A burst of energy found by the burst detector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Burst {
+    /// Start of the burst since detection started, in sample time.
+    pub start: Duration,
+    /// Time from the first to the last sample above the threshold.
+    pub duration: Duration,
+    /// Time since the previous burst started, if there was one.
+    pub interval: Option<Duration>,
+    /// Peak power relative to the noise floor.
+    pub peak_snr: Decibels,
+}
+
+/// Channel and trigger level for meteor scatter ping detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeteorConfig {
+    /// Frequency of the beacon or broadcast carrier being watched.
+    pub frequency: Hertz,
+    /// Width of the channel around `frequency`.
+    pub bandwidth: Hertz,
+    /// Rise above the channel's noise floor that counts as a ping.
+    pub threshold: Decibels,
+}
+
+/// A signal picked out for analysis: its center and width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalRegion {
+    pub frequency: Hertz,
+    pub bandwidth: Hertz,
+}
+
+impl SignalRegion {
+    /// Whether `frequency`, in Hz, lies within the region.
+    pub fn contains(&self, frequency: f64) -> bool {
+        (frequency - self.frequency.as_hz() as f64).abs() <= self.bandwidth.as_hz() as f64 / 2.0
+    }
+}
+
+/// A possible symbol rate, from a spectral line in the signal's envelope or
+/// frequency transitions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolRateCandidate {
+    /// Symbols per second.
+    pub rate: f64,
+    /// Height of the line above the median of the spectrum it is in.
+    pub strength: Decibels,
+}
+
+/// Result of a symbol rate estimate over one region.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolRateEstimate {
+    pub region: SignalRegion,
+    /// Candidates, strongest first. Empty if no line stood out.
+    pub candidates: Vec<SymbolRateCandidate>,
+}
+
+/// A broadband impulse (static crash) found by the impulse counter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Impulse {
+    /// Time of the impulse since counting started, in sample time.
+    pub time: Duration,
+    /// Peak power relative to the noise floor, averaged across the band.
+    pub peak_snr: Decibels,
+}
@@ -0,0 +1,224 @@
+//! A stand-in for the engine that replays a script of events, for testing
+//! the UI without running any DSP. Built with the `test-util` feature.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    AudioRouting, Averaging, Capabilities, Command, Decibels, DemodMode, Discontinuity,
+    EngineState, Event, Feature, Hertz, SourceCapability, SourceConfig, SourceKind, SpectrumFrame,
+};
+use flume::{Receiver, Sender};
+
+/// A step of a `MockEngine`'s script.
+#[derive(Debug)]
+pub enum Step {
+    /// Send an event to the UI
+    Send(Event),
+    /// Close the channels to the UI, as an engine that died would
+    Disconnect,
+}
+
+/// Plays the engine's side of the channels from a script. The script is
+/// stepped through by the test, so UI frames and engine events interleave
+/// deterministically; commands from the UI are collected for inspection.
+pub struct MockEngine {
+    cmd_rx: Receiver<Command>,
+    event_tx: Option<Sender<Event>>,
+    spectrum_tx: Option<Sender<Event>>,
+    script: VecDeque<Step>,
+}
+
+impl MockEngine {
+    /// Create a mock speaking on the same channels as `Engine::new`.
+    pub fn new(cmd_rx: Receiver<Command>, event_tx: Sender<Event>) -> Self {
+        Self {
+            cmd_rx,
+            event_tx: Some(event_tx),
+            spectrum_tx: None,
+            script: VecDeque::new(),
+        }
+    }
+
+    /// Send spectrum frames on their own channel, as
+    /// `Engine::with_spectrum_channel` does.
+    pub fn with_spectrum_channel(mut self, spectrum_tx: Sender<Event>) -> Self {
+        self.spectrum_tx = Some(spectrum_tx);
+        self
+    }
+
+    /// Append an event to the script.
+    pub fn then(mut self, event: Event) -> Self {
+        self.script.push_back(Step::Send(event));
+        self
+    }
+
+    /// Append a disconnect to the script.
+    pub fn then_disconnect(mut self) -> Self {
+        self.script.push_back(Step::Disconnect);
+        self
+    }
+
+    /// Carry out the next step of the script. Returns false once the script
+    /// is finished or the UI has gone away.
+    pub fn step(&mut self) -> bool {
+        let Some(step) = self.script.pop_front() else {
+            return false;
+        };
+        match step {
+            Step::Send(event) => {
+                let tx = match (&event, &self.spectrum_tx) {
+                    (Event::SpectrumData(_), Some(spectrum_tx)) => Some(spectrum_tx),
+                    _ => self.event_tx.as_ref(),
+                };
+                tx.is_some_and(|tx| tx.send(event).is_ok())
+            }
+            Step::Disconnect => {
+                self.event_tx = None;
+                self.spectrum_tx = None;
+                true
+            }
+        }
+    }
+
+    /// Steps left in the script.
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+
+    /// Commands the UI has sent since the last call.
+    pub fn commands(&self) -> Vec<Command> {
+        self.cmd_rx.try_iter().collect()
+    }
+
+    /// Play the whole script, then answer like an idle engine until the UI
+    /// sends `Command::Stop` or goes away (blocking). Returns the commands
+    /// received, for running the real UI against a scripted engine.
+    pub fn run(mut self) -> Vec<Command> {
+        while self.step() {}
+        let mut received = Vec::new();
+        loop {
+            match self.cmd_rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(Command::Stop) => {
+                    received.push(Command::Stop);
+                    return received;
+                }
+                Ok(command) => received.push(command),
+                Err(flume::RecvTimeoutError::Timeout) => {}
+                Err(flume::RecvTimeoutError::Disconnected) => return received,
+            }
+        }
+    }
+}
+
+/// The state a freshly started engine reports: the default signal generator
+/// with nothing else configured.
+pub fn initial_state() -> EngineState {
+    let source_config = SourceConfig::default();
+    let sample_rate = match &source_config {
+        SourceConfig::SignalGenerator { sample_rate, .. }
+        | SourceConfig::File { sample_rate, .. }
+        | SourceConfig::RtlSdr { sample_rate, .. }
+        | SourceConfig::SoapySdr { sample_rate, .. }
+        | SourceConfig::RtlTcp { sample_rate, .. }
+        | SourceConfig::SpyServer { sample_rate, .. } => *sample_rate,
+    };
+    EngineState {
+        center_frequency: Hertz(0),
+        gain: Decibels(0.0),
+        gain_profiles: Vec::new(),
+        antenna_switch: None,
+        antenna: None,
+        dark_frame: None,
+        rig: None,
+        rotator: None,
+        sample_rate,
+        device_sample_rate: None,
+        fft_size: 4096,
+        decimation: 1,
+        frequency_offset: 0,
+        spectrum_span: sample_rate,
+        calibration: Vec::new(),
+        averaging: Averaging::Off,
+        peak_hold: false,
+        min_hold: false,
+        offset_tuning: false,
+        source_config,
+        second_source: None,
+        playback_speed: None,
+        playback_start: Duration::ZERO,
+        playback_paused: false,
+        carrier_measurement: None,
+        burst_detection: None,
+        meteor_detection: None,
+        impulse_counter: None,
+        sstv_decoder: None,
+        selcall_decoder: None,
+        ais_decoder: None,
+        adsb_decoder: false,
+        demodulator: None,
+        squelch: None,
+        passband: None,
+        scan: None,
+        audio_routing: AudioRouting::default(),
+        vfos: Vec::new(),
+    }
+}
+
+/// What an engine built with every feature announces, with no SoapySDR
+/// devices attached.
+pub fn capabilities() -> Capabilities {
+    let rtl_sdr_rates = [
+        250_000, 1_024_000, 1_536_000, 1_792_000, 1_920_000, 2_048_000, 2_160_000, 2_400_000,
+        2_560_000, 2_880_000, 3_200_000,
+    ]
+    .map(Hertz)
+    .to_vec();
+    let source = |kind, max_sample_rate, sample_rates| SourceCapability {
+        kind,
+        max_sample_rate,
+        sample_rates,
+        devices: Vec::new(),
+    };
+    Capabilities {
+        version: "0.1.0".to_string(),
+        sources: vec![
+            source(SourceKind::SignalGenerator, None, Vec::new()),
+            source(SourceKind::File, None, Vec::new()),
+            source(
+                SourceKind::RtlSdr,
+                Some(Hertz(3_200_000)),
+                rtl_sdr_rates.clone(),
+            ),
+            source(SourceKind::RtlTcp, Some(Hertz(3_200_000)), rtl_sdr_rates),
+            source(SourceKind::SpyServer, None, Vec::new()),
+            source(SourceKind::SoapySdr, None, Vec::new()),
+        ],
+        demod_modes: DemodMode::ALL.to_vec(),
+        features: Feature::ALL.to_vec(),
+    }
+}
+
+/// Spectrum frame number `sequence` of a `state`-shaped stream, with
+/// linear `magnitudes`. Its sample time follows from the sequence, as if no
+/// frames had been lost since the stream started.
+pub fn spectrum_frame(
+    state: &EngineState,
+    sequence: u64,
+    discontinuity: Option<Discontinuity>,
+    magnitudes: Vec<f32>,
+) -> SpectrumFrame {
+    let frame_time = state.fft_size as f64 / state.sample_rate.0 as f64;
+    SpectrumFrame {
+        sequence,
+        sample_time: Duration::from_secs_f64(sequence as f64 * frame_time),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs_f64(sequence as f64 * frame_time),
+        source: 0,
+        discontinuity,
+        center_frequency: state.center_frequency,
+        sample_rate: state.sample_rate,
+        magnitudes,
+        peak_hold: None,
+        min_hold: None,
+    }
+}
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+/// How a recording of the live IQ stream is stored. Either way the samples
+/// are `cf32_le`: little-endian `f32` I/Q pairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RecordingFormat {
+    /// `<path>.sigmf-data` with its metadata in `<path>.sigmf-meta` and
+    /// checksums in `<path>.sigmf-sha256`
+    SigMf,
+    /// Samples alone, at the path as given
+    Raw,
+}
+
+impl RecordingFormat {
+    pub const ALL: [RecordingFormat; 2] = [RecordingFormat::SigMf, RecordingFormat::Raw];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::SigMf => "SigMF",
+            Self::Raw => "Raw cf32",
+        }
+    }
+}
+
+/// Progress of the recording of the live IQ stream.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordingStatus {
+    /// File the samples are written to
+    pub path: PathBuf,
+    /// Bytes of samples written so far
+    pub bytes_written: u64,
+    /// Whether the recording is still running; the last status of a
+    /// recording has this unset.
+    pub active: bool,
+}
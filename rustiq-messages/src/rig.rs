@@ -0,0 +1,14 @@
+use crate::Hertz;
+
+/// Connection to a transceiver through hamlib's `rigctld`, for using the SDR
+/// as a panadapter. Serial CAT rigs are reached by running `rigctld` for them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RigConfig {
+    /// `host:port` of `rigctld`.
+    pub address: String,
+    /// The rig's IF output frequency when the source is fed from an IF tap.
+    /// The front end then stays put and only the displayed frequencies follow
+    /// the rig; without it the source retunes to the rig's frequency.
+    pub if_frequency: Option<Hertz>,
+}
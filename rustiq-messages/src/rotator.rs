@@ -0,0 +1,20 @@
+/// Antenna rotator pointing, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RotatorPosition {
+    /// Bearing clockwise from true north.
+    pub azimuth: f32,
+    /// Angle above the horizon.
+    pub elevation: f32,
+}
+
+impl RotatorPosition {
+    /// The position moved by the given amounts, with the azimuth wrapped to
+    /// 0..360 and the elevation limited to 0..=90.
+    pub fn nudged(self, azimuth: f32, elevation: f32) -> Self {
+        Self {
+            azimuth: (self.azimuth + azimuth).rem_euclid(360.0),
+            elevation: (self.elevation + elevation).clamp(0.0, 90.0),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use crate::{AudioChannel, Decibels, DemodMode, Hertz};
+
+/// Whether the scanner passes a channel by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Lockout {
+    #[default]
+    None,
+    /// Passed by until the scan stops
+    Temporary,
+    /// Passed by in every scan, and kept with the list
+    Permanent,
+}
+
+impl Lockout {
+    pub const ALL: [Lockout; 3] = [Self::None, Self::Temporary, Self::Permanent];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Temporary => "Temporary",
+            Self::Permanent => "Permanent",
+        }
+    }
+}
+
+/// One channel of a scan list.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanChannel {
+    /// Name shown for the channel, e.g. its plan's channel name
+    pub name: String,
+    pub channel: AudioChannel,
+    /// Checked every few steps, whichever channel the scan is on
+    pub priority: bool,
+    pub lockout: Lockout,
+    /// How long the scan waits on the channel after it goes quiet, for a
+    /// reply
+    pub delay: Duration,
+    /// Longest the scan stays on the channel while it is active, or for as
+    /// long as it is
+    pub hold: Option<Duration>,
+}
+
+impl ScanChannel {
+    /// `channel` with no priority or lockout, the usual delay and no hold.
+    pub fn new(name: impl Into<String>, channel: AudioChannel) -> Self {
+        Self {
+            name: name.into(),
+            channel,
+            priority: false,
+            lockout: Lockout::None,
+            delay: Duration::from_secs(2),
+            hold: None,
+        }
+    }
+}
+
+/// Channels the scanner steps through, stopping on those the squelch opens
+/// on.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanList {
+    pub channels: Vec<ScanChannel>,
+    /// Ordinary channels stepped past between checks of the priority
+    /// channels, or 0 not to check them other than in turn
+    pub priority_every: u32,
+    /// For an unattended survey: how long each active channel is listened
+    /// to before the scan moves on, whatever its hold
+    pub dwell: Option<Duration>,
+}
+
+impl ScanList {
+    /// The list to keep, as in a settings bundle: temporary lockouts last
+    /// only as long as the scan.
+    pub fn without_temporary_lockouts(&self) -> Self {
+        let mut list = self.clone();
+        for channel in &mut list.channels {
+            if channel.lockout == Lockout::Temporary {
+                channel.lockout = Lockout::None;
+            }
+        }
+        list
+    }
+}
+
+/// What a signal's channel samples look like, as told apart while the scan
+/// listens to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignalClass {
+    /// Much of the power in a carrier on the dial frequency, as AM and CW
+    /// have
+    Carrier,
+    /// A steady level, as FM and FSK have
+    ConstantEnvelope,
+    /// A level that comes and goes, as SSB and voice AM have
+    VaryingEnvelope,
+}
+
+impl SignalClass {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Carrier => "carrier (AM/CW)",
+            Self::ConstantEnvelope => "constant envelope (FM/FSK)",
+            Self::VaryingEnvelope => "varying envelope (SSB/voice)",
+        }
+    }
+}
+
+/// Activity the scan stopped for on one of its channels, sent as it moves
+/// on.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanHit {
+    /// Index of the channel in the scan list
+    pub channel: usize,
+    pub name: String,
+    pub frequency: Hertz,
+    pub demod: DemodMode,
+    /// From when the squelch first opened to when it was last heard open
+    pub duration: Duration,
+    /// Average power of the channel while it was open, relative to full
+    /// scale
+    pub level: Decibels,
+    /// What the signal looked like, if enough of it was heard to tell
+    pub class: Option<SignalClass>,
+}
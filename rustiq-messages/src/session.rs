@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+
+use crate::{Decibels, Hertz, SourceConfig};
+
+/// What a session was doing, journaled as it changes so the session can be
+/// restored after a crash or power loss.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionRecord {
+    pub source_config: SourceConfig,
+    pub center_frequency: Hertz,
+    pub gain: Decibels,
+    /// Data file of a recording in progress.
+    pub recording: Option<PathBuf>,
+}
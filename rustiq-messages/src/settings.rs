@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use crate::{
+    AntennaSwitchConfig, AudioChannel, Command, Decibels, GainProfile, Hertz, Passband, RigConfig,
+    ScanList, SourceConfig,
+};
+
+/// A station's settings, exported to one file to set up another machine the
+/// same way. Importing replays them to the engine as commands.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SettingsBundle {
+    pub source_config: SourceConfig,
+    pub center_frequency: Hertz,
+    pub gain: Decibels,
+    pub gain_profiles: Vec<GainProfile>,
+    pub antenna_switch: Option<AntennaSwitchConfig>,
+    pub rig: Option<RigConfig>,
+    /// Address of the `rotctld` to connect to, if any
+    pub rotator: Option<String>,
+    /// SelCall ID patterns that raise an alert
+    pub alert_rules: Vec<String>,
+    /// Scan list, with its permanent lockouts
+    pub scan_list: ScanList,
+}
+
+/// What the app was set to when it last closed, put back as it starts
+/// again. Fields a saved session lacks, e.g. from an older version, are
+/// taken from the default.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LastSession {
+    pub source_config: SourceConfig,
+    pub center_frequency: Hertz,
+    pub gain: Decibels,
+    pub fft_size: usize,
+    /// Channel being listened to, if one was
+    pub listening: Option<AudioChannel>,
+    pub squelch: Option<Decibels>,
+    pub passband: Option<Passband>,
+    /// Playback volume, as a linear gain
+    pub volume: f32,
+    /// Name of the waterfall's color map
+    pub colormap: String,
+    pub window: Option<WindowGeometry>,
+    /// Marks drawn on the waterfall
+    pub annotations: Vec<Annotation>,
+}
+
+/// What a first run starts with.
+impl Default for LastSession {
+    fn default() -> Self {
+        Self {
+            source_config: SourceConfig::default(),
+            center_frequency: Hertz(0),
+            gain: Decibels(0.0),
+            fft_size: 4096,
+            listening: None,
+            squelch: None,
+            passband: None,
+            volume: 0.5,
+            colormap: "Grayscale".to_string(),
+            window: None,
+            annotations: Vec::new(),
+        }
+    }
+}
+
+/// Size of the app's window and, where the platform tells it, its
+/// position on the screen, in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowGeometry {
+    pub size: (f32, f32),
+    pub position: Option<(f32, f32)>,
+}
+
+/// A mark drawn on the waterfall by hand, e.g. to point out a signal in a
+/// screenshot. Kept apart from what the engine detects, and saved with the
+/// session.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Annotation {
+    pub shape: AnnotationShape,
+    /// Where it was drawn from and to; a label sits at the start
+    pub start: AnnotationPoint,
+    pub end: AnnotationPoint,
+    pub text: String,
+}
+
+/// A frequency on the waterfall line with the given sample time, so an
+/// annotation scrolls with the signal it marks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnnotationPoint {
+    pub frequency: Hertz,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnnotationShape {
+    Label,
+    Arrow,
+    Box,
+}
+
+impl AnnotationShape {
+    pub const ALL: [Self; 3] = [Self::Label, Self::Arrow, Self::Box];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Label => "Label",
+            Self::Arrow => "Arrow",
+            Self::Box => "Box",
+        }
+    }
+}
+
+/// A named sequence of commands recorded from the UI, replayed to the
+/// engine in the same order.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandMacro {
+    pub name: String,
+    /// Function key that plays the macro, 1 for F1 to 12 for F12, if any
+    pub hotkey: Option<u8>,
+    pub commands: Vec<Command>,
+}
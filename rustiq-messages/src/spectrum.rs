@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::Hertz;
+
+/// Why a spectrum frame doesn't follow on from the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Discontinuity {
+    /// The graph was rebuilt, e.g. to retune, so the stream started over.
+    Restart,
+    /// The source delivered no samples for a while.
+    SourceStall,
+}
+
+impl Discontinuity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Restart => "Stream restarted",
+            Self::SourceStall => "Source stalled",
+        }
+    }
+}
+
+/// How the engine averages spectrum frames before sending them, trading
+/// how quickly the levels follow a change for a steadier noise floor.
+/// Averaging is of power, so a carrier keeps its level.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Averaging {
+    /// Every frame as it is.
+    #[default]
+    Off,
+    /// Exponential moving average, each new frame weighted by the factor,
+    /// above 0 and up to 1. Smaller factors smooth more.
+    Exponential(f32),
+    /// Mean of each block of this many frames, sending one frame per block.
+    Block(usize),
+}
+
+impl Averaging {
+    /// Longest block, in frames.
+    pub const MAX_BLOCK: usize = 1_000;
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Exponential(_) => "Exponential",
+            Self::Block(_) => "Block",
+        }
+    }
+
+    /// Whether the engine can average by this: a factor above 0 and up to
+    /// 1, or a block of 1 to `MAX_BLOCK` frames.
+    pub fn is_valid(self) -> bool {
+        match self {
+            Self::Off => true,
+            Self::Exponential(factor) => factor > 0.0 && factor <= 1.0,
+            Self::Block(frames) => (1..=Self::MAX_BLOCK).contains(&frames),
+        }
+    }
+}
+
+/// One FFT frame for the waterfall.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectrumFrame {
+    /// Frames produced since the engine started. A jump between consecutive
+    /// frames means frames were dropped on the way to the UI.
+    pub sequence: u64,
+    /// Time of the frame's first sample since the stream (re)started, in
+    /// sample time.
+    pub sample_time: Duration,
+    /// Wall-clock time the engine took the frame at, to line bursts up
+    /// with the time of day.
+    pub timestamp: SystemTime,
+    /// Index of the source the frame is from: 0 for the main source, 1 for
+    /// the second source. Each source's frames are numbered separately.
+    pub source: usize,
+    /// Set on the first frame after a break in the sample stream.
+    pub discontinuity: Option<Discontinuity>,
+    /// Frequency of the middle bin when the frame was taken.
+    pub center_frequency: Hertz,
+    /// Width of the band the bins cover.
+    pub sample_rate: Hertz,
+    /// FFT magnitudes with DC in the middle bin.
+    pub magnitudes: Vec<f32>,
+    /// Highest magnitude of each bin since the stream started or was
+    /// retuned, while peak hold is on.
+    pub peak_hold: Option<Vec<f32>>,
+    /// Lowest magnitude of each bin since the stream started or was
+    /// retuned, while min hold is on.
+    pub min_hold: Option<Vec<f32>>,
+}
+
+/// A spectrum frame cut down to cross a slow link: groups of bins merged,
+/// levels quantized in dB, and compressed, possibly as the change from the
+/// source's previous reduced frame. The receiving end expands it back to
+/// a full frame.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReducedSpectrum {
+    /// The frame, with its magnitudes left out and its hold traces merged
+    /// like the levels, but neither quantized nor compressed
+    pub frame: SpectrumFrame,
+    /// Bins in the full frame.
+    pub bins: usize,
+    /// Bins merged into each level, the strongest of them kept.
+    pub merge: usize,
+    /// The levels are the change from the previous reduced frame of the
+    /// same source, rather than the levels themselves.
+    pub delta: bool,
+    /// Compressed levels, each a little-endian `i16` in tenths of a dB.
+    pub levels: Vec<u8>,
+}
+
+/// Spectrogram of a whole recording, worked out in the background when a
+/// file source is opened, so any part of it can be picked to play from.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordingOverview {
+    /// The file it is of.
+    pub path: PathBuf,
+    /// Width of the band the bins cover.
+    pub sample_rate: Hertz,
+    /// Length of the whole recording.
+    pub duration: Duration,
+    /// Stretch of the recording averaged into each row.
+    pub row_duration: Duration,
+    /// Power of each bin relative to a full-scale tone, with DC in the
+    /// middle bin, one row per `row_duration` from the start of the
+    /// recording.
+    pub rows: Vec<Vec<f32>>,
+}
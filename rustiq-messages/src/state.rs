@@ -1,38 +1,243 @@
-use crate::{Decibels, Hertz};
-use std::path::PathBuf;
+use crate::{
+    AntennaSwitchConfig, AudioChannel, AudioRouting, Averaging, CalibrationPoint, DarkFrameConfig,
+    Decibels, GainProfile, Hertz, MeteorConfig, Passband, RigConfig, ScanList, SelCallConfig, Vfo,
+};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Current state of the SDR engine.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EngineState {
     /// Center frequency
     pub center_frequency: Hertz,
+    /// Gain applied to the source
+    pub gain: Decibels,
+    /// Gains applied automatically on tuning into their frequency ranges
+    pub gain_profiles: Vec<GainProfile>,
+    /// External antenna switch, if configured
+    pub antenna_switch: Option<AntennaSwitchConfig>,
+    /// Index of the selected antenna, if the switch has been set successfully
+    pub antenna: Option<usize>,
+    /// Schedule dark frames are captured on, if they are
+    pub dark_frame: Option<DarkFrameConfig>,
+    /// Transceiver the center frequency follows, if any
+    pub rig: Option<RigConfig>,
+    /// Address of the `rotctld` the engine is connected to, if any
+    pub rotator: Option<String>,
     /// Sample rate
     pub sample_rate: Hertz,
+    /// Rate the source's hardware runs at, when it can't run at the one
+    /// asked for and the stream is resampled to `sample_rate` from it
+    pub device_sample_rate: Option<Hertz>,
     /// FFT size (number of bins)
     pub fft_size: usize,
+    /// Factor the stream is decimated by ahead of the spectrum FFT
+    pub decimation: usize,
+    /// Width of the band the spectrum shows, the sample rate over the
+    /// decimation
+    pub spectrum_span: Hertz,
+    /// How far off the center frequency the spectrum is centered, in Hz
+    pub frequency_offset: i64,
+    /// Calibration table the spectrum is corrected by, empty if none
+    pub calibration: Vec<CalibrationPoint>,
+    /// How spectrum frames are averaged
+    pub averaging: Averaging,
+    /// Whether spectrum frames carry a peak-hold trace
+    pub peak_hold: bool,
+    /// Whether spectrum frames carry a min-hold trace
+    pub min_hold: bool,
+    /// Whether hardware sources are tuned off the center frequency
+    pub offset_tuning: bool,
     /// Current source configuration
     pub source_config: SourceConfig,
+    /// Source shown next to the main one, e.g. the other polarization or
+    /// antenna, if any
+    pub second_source: Option<SourceConfig>,
+    /// Times real time a file source plays at, if it is paced rather than
+    /// read as fast as it can be
+    pub playback_speed: Option<f32>,
+    /// How far into a file source the running graph started playing it:
+    /// where it was sought to with `Seek`, or where a paced file had got to
+    /// when the graph was rebuilt
+    pub playback_start: Duration,
+    /// Whether a file source is held where it is, as by `Pause`
+    pub playback_paused: bool,
+    /// Target frequency of the active carrier measurement, if any
+    pub carrier_measurement: Option<Hertz>,
+    /// Threshold above the noise floor of the active burst detection, if any
+    pub burst_detection: Option<Decibels>,
+    /// Configuration of the active meteor scatter detection, if any
+    pub meteor_detection: Option<MeteorConfig>,
+    /// Threshold above the noise floor of the active impulse counter, if any
+    pub impulse_counter: Option<Decibels>,
+    /// Channel feeding the active SSTV decoder, if any
+    pub sstv_decoder: Option<AudioChannel>,
+    /// Configuration of the active SelCall decoder, if any
+    pub selcall_decoder: Option<SelCallConfig>,
+    /// Channel frequency of the active AIS decoder, if any
+    pub ais_decoder: Option<Hertz>,
+    /// Whether the ADS-B decoder is active
+    pub adsb_decoder: bool,
+    /// Channel demodulated for listening, if any
+    pub demodulator: Option<AudioChannel>,
+    /// Channel power the listening channel's audio is muted below, if it
+    /// is squelched
+    pub squelch: Option<Decibels>,
+    /// Passband the listening channel is filtered through, if not its
+    /// mode's
+    pub passband: Option<Passband>,
+    /// List being scanned, with its lockouts, if a scan is running
+    pub scan: Option<ScanList>,
+    /// Where the listening channel's audio goes
+    pub audio_routing: AudioRouting,
+    /// Receivers demodulating other channels, in the order added
+    pub vfos: Vec<Vfo>,
 }
 
 /// Configuration for the SDR signal source.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SourceConfig {
-    /// Generate a test signal (sine wave at specified frequency).
+    /// Generate a test signal: the sum of `tones`, swept up together if
+    /// `sweep` is set, with white Gaussian noise `snr` below the strongest
+    /// of them, or below full scale without any, if `snr` is set.
     SignalGenerator {
         sample_rate: Hertz,
-        signal_freq: Hertz,
-        amplitude: Decibels,
+        tones: Vec<Tone>,
+        snr: Option<Decibels>,
+        sweep: Option<Sweep>,
     },
-    /// Read IQ samples from a file.
-    File { path: PathBuf, sample_rate: Hertz },
+    /// Read IQ samples stored as `format` from a file.
+    File {
+        path: PathBuf,
+        sample_rate: Hertz,
+        format: IqFormat,
+    },
+    /// Stream live IQ from the first RTL-SDR dongle, tuned to the engine's
+    /// center frequency. `gain` is the tuner's gain, ahead of the engine's.
+    RtlSdr { sample_rate: Hertz, gain: Decibels },
+    /// Stream live IQ from a SoapySDR device, tuned to the engine's center
+    /// frequency. `device` holds the arguments that open it, as listed in the
+    /// engine's capabilities; the antenna port and analog bandwidth are left
+    /// to the driver when `None`.
+    SoapySdr {
+        device: String,
+        sample_rate: Hertz,
+        antenna: Option<String>,
+        bandwidth: Option<Hertz>,
+        gains: Vec<StageGain>,
+    },
+    /// Stream live IQ from an RTL-SDR dongle served by `rtl_tcp` at `host`
+    /// and `port`, tuned to the engine's center frequency. `gain` is the
+    /// tuner's gain, set over the connection like the frequency and rate.
+    RtlTcp {
+        host: String,
+        port: u16,
+        sample_rate: Hertz,
+        gain: Decibels,
+    },
+    /// Stream live IQ from a SpyServer at `host` and `port`, tuned to the
+    /// engine's center frequency. The server halves its device's rate to
+    /// the lowest it can at least `sample_rate`, which then holds the rate
+    /// streamed. `gain` is the device's gain step, from 0 to its maximum,
+    /// and only set if the server lets its clients.
+    SpyServer {
+        host: String,
+        port: u16,
+        sample_rate: Hertz,
+        gain: u32,
+    },
+}
+
+/// A tone of the signal generator, `frequency` above the center at
+/// `amplitude` relative to full scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tone {
+    pub frequency: Hertz,
+    pub amplitude: Decibels,
+}
+
+/// A chirp the signal generator's tones follow: each rises `span` above its
+/// frequency over `period`, then starts again from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sweep {
+    pub span: Hertz,
+    pub period: Duration,
+}
+
+/// Gain of one stage of a hardware source, e.g. a SoapySDR device's LNA.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StageGain {
+    pub stage: String,
+    pub gain: Decibels,
+}
+
+/// How the samples of an IQ file are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IqFormat {
+    /// Little-endian `f32` I/Q pairs, as RustIQ records them
+    #[default]
+    Cf32,
+    /// Unsigned 8-bit I/Q pairs, as `rtl_sdr` writes them
+    Cu8,
+    /// Signed 8-bit I/Q pairs, as `hackrf_transfer` writes them
+    Cs8,
+    /// Little-endian signed 16-bit I/Q pairs
+    Cs16,
+    /// A WAV file with I and Q as its two channels
+    Wav,
+}
+
+impl IqFormat {
+    pub const ALL: [IqFormat; 5] = [
+        IqFormat::Cf32,
+        IqFormat::Cu8,
+        IqFormat::Cs8,
+        IqFormat::Cs16,
+        IqFormat::Wav,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IqFormat::Cf32 => "cf32",
+            IqFormat::Cu8 => "cu8",
+            IqFormat::Cs8 => "cs8",
+            IqFormat::Cs16 => "cs16",
+            IqFormat::Wav => "WAV",
+        }
+    }
+
+    /// The format a file's extension names, `cf32` for any other.
+    pub fn from_extension(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("cu8" | "u8") => IqFormat::Cu8,
+            Some("cs8" | "s8") => IqFormat::Cs8,
+            Some("cs16" | "s16") => IqFormat::Cs16,
+            Some("wav") => IqFormat::Wav,
+            _ => IqFormat::Cf32,
+        }
+    }
 }
 
 impl Default for SourceConfig {
     fn default() -> Self {
         SourceConfig::SignalGenerator {
             sample_rate: Hertz(48_000),
-            signal_freq: Hertz(10_000),
-            amplitude: Decibels(0.0), // 0 dB = amplitude 1.0
+            tones: vec![Tone {
+                frequency: Hertz(10_000),
+                amplitude: Decibels(0.0), // 0 dB = amplitude 1.0
+            }],
+            snr: None,
+            sweep: None,
         }
     }
 }
@@ -0,0 +1,65 @@
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use crate::{Command, Event, read_frame, write_frame};
+
+/// A connection from a UI to an engine, carrying commands one way and
+/// events the other: wire frames over a socket, JSON over a WebSocket, or
+/// anything else. It is split in two so each direction can run on a thread
+/// of its own, the way the UI's channels are bridged onto it.
+pub trait Transport {
+    type Events: EventReceiver;
+    type Commands: CommandSender;
+
+    fn split(self) -> (Self::Events, Self::Commands);
+}
+
+/// The side of a transport the engine's events arrive on.
+pub trait EventReceiver: Send + 'static {
+    /// Wait for the next event. An error ends the connection.
+    fn recv(&mut self) -> io::Result<Event>;
+}
+
+/// The side of a transport commands go out on.
+pub trait CommandSender: Send + 'static {
+    /// Send `command` on its way. An error ends the connection.
+    fn send(&mut self, command: &Command) -> io::Result<()>;
+}
+
+/// Commands and events as wire frames, over a stream split into a reading
+/// and a writing half.
+pub struct WireTransport<R, W> {
+    pub reader: R,
+    pub writer: W,
+}
+
+impl<R, W> Transport for WireTransport<R, W>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    type Events = WireEvents<R>;
+    type Commands = WireCommands<W>;
+
+    fn split(self) -> (WireEvents<R>, WireCommands<W>) {
+        (
+            WireEvents(BufReader::new(self.reader)),
+            WireCommands(BufWriter::new(self.writer)),
+        )
+    }
+}
+
+pub struct WireEvents<R>(BufReader<R>);
+
+impl<R: Read + Send + 'static> EventReceiver for WireEvents<R> {
+    fn recv(&mut self) -> io::Result<Event> {
+        read_frame(&mut self.0)
+    }
+}
+
+pub struct WireCommands<W: Write>(BufWriter<W>);
+
+impl<W: Write + Send + 'static> CommandSender for WireCommands<W> {
+    fn send(&mut self, command: &Command) -> io::Result<()> {
+        write_frame(&mut self.0, command)
+    }
+}
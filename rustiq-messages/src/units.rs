@@ -2,6 +2,7 @@ use std::cmp::Ordering;
 
 /// Frequency in Hertz.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hertz(pub u64);
 
 impl std::fmt::Display for Hertz {
@@ -42,6 +43,7 @@ impl From<Hertz> for u64 {
 
 /// Amplitude in Decibels (dB).
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Decibels(pub f32);
 
 impl std::fmt::Display for Decibels {
@@ -63,6 +65,18 @@ impl Decibels {
         Self(20.0 * linear.log10())
     }
 
+    /// Convert decibels to a linear power ratio.
+    /// For power: linear = 10^(dB/10)
+    pub fn to_power(self) -> f32 {
+        10.0_f32.powf(self.0 / 10.0)
+    }
+
+    /// Convert a linear power ratio to decibels.
+    /// For power: dB = 10 * log10(linear)
+    pub fn from_power(power: f32) -> Self {
+        Self(10.0 * power.log10())
+    }
+
     pub const fn as_db(self) -> f32 {
         self.0
     }
@@ -83,3 +97,25 @@ impl From<Decibels> for f32 {
         db.0
     }
 }
+
+/// An inclusive range of frequencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrequencyRange {
+    pub start: Hertz,
+    pub end: Hertz,
+}
+
+impl FrequencyRange {
+    pub const fn new(start: Hertz, end: Hertz) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(self, frequency: Hertz) -> bool {
+        (self.start..=self.end).contains(&frequency)
+    }
+
+    pub fn width(self) -> Hertz {
+        Hertz(self.end.0.saturating_sub(self.start.0))
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use crate::{AudioChannel, AudioRouting, Decibels, Passband};
+
+/// An extra receiver on a channel of the source, demodulated alongside the
+/// listening channel. Its audio isn't played, but can be recorded or
+/// streamed.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VfoConfig {
+    pub channel: AudioChannel,
+    /// Passband the channel is filtered through, if not its mode's
+    pub passband: Option<Passband>,
+    /// Level the channel is squelched at, if it is
+    pub squelch: Option<Decibels>,
+    /// Append the audio to this WAV file, 16-bit mono at the demodulator's
+    /// rate
+    pub recording: Option<PathBuf>,
+    /// Send the audio as UDP datagrams of 16-bit little-endian mono samples
+    /// to each of these `host:port` addresses
+    pub network: Vec<String>,
+}
+
+impl VfoConfig {
+    /// A VFO on `channel` with its mode's passband, no squelch and its audio
+    /// going nowhere.
+    pub fn new(channel: AudioChannel) -> Self {
+        Self {
+            channel,
+            passband: None,
+            squelch: None,
+            recording: None,
+            network: Vec::new(),
+        }
+    }
+
+    /// Where the audio goes, never to the speakers.
+    pub fn routing(&self) -> AudioRouting {
+        AudioRouting {
+            speakers: false,
+            recording: self.recording.clone(),
+            network: self.network.clone(),
+        }
+    }
+}
+
+/// A VFO the engine runs, by the id it was given when added.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vfo {
+    pub id: u32,
+    pub config: VfoConfig,
+}
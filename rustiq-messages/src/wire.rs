@@ -0,0 +1,811 @@
+//! Binary encoding of the protocol, for running the engine in another process.
+//!
+//! Values are written field by field in declaration order: integers and
+//! floats little-endian, `usize` as `u64`, strings and vectors length-prefixed,
+//! options and enums as a tag byte followed by the contents. Each message on a
+//! stream is framed with a `u32` byte length.
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Annotation, AnnotationPoint, AnnotationShape, Antenna, AntennaRule, AntennaSwitchConfig,
+    AntennaSwitchLink, AudioChannel, AudioChunk, AudioRouting, Averaging, Burst, CalibrationPoint,
+    Capabilities, CarrierMeasurement, Command, CommandMacro, DarkFrameConfig, Decibels, DemodMode,
+    Discontinuity, EngineState, Event, Feature, FrequencyRange, GainProfile, GainStage,
+    GeoPosition, Hertz, Impulse, IqFormat, LastSession, Lockout, MeteorConfig, Passband, RdsData,
+    RecordingFormat, RecordingOverview, RecordingStatus, ReducedSpectrum, Resource, RigConfig,
+    RotatorPosition, RustIqError, ScanChannel, ScanHit, ScanList, SelCall, SelCallConfig,
+    SelCallStandard, SelfTestReport, SessionRecord, SettingsBundle, SignalClass, SignalRegion,
+    SourceCapability, SourceConfig, SourceDevice, SourceKind, SpectrumFrame, SstvEvent, SstvMode,
+    Stage, StageCheck, StageGain, Sweep, SymbolRateCandidate, SymbolRateEstimate, Tone, TrackKind,
+    TrackReport, Vfo, VfoConfig, WindowGeometry,
+};
+
+/// Largest frame accepted, to fail fast on a corrupt length.
+const MAX_FRAME: u32 = 64 * 1024 * 1024;
+
+/// A value that can be sent over the wire.
+pub trait Wire: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError>;
+}
+
+/// Malformed input while decoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireError(String);
+
+impl WireError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl std::fmt::Display for WireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "malformed message: {}", self.0)
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<WireError> for io::Error {
+    fn from(e: WireError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Write `value` as one length-prefixed frame.
+pub fn write_frame<T: Wire>(writer: &mut impl Write, value: &T) -> io::Result<()> {
+    let mut frame = vec![0; 4];
+    value.encode(&mut frame);
+    let len = u32::try_from(frame.len() - 4)
+        .ok()
+        .filter(|&len| len <= MAX_FRAME)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "message too large"))?;
+    frame[..4].copy_from_slice(&len.to_le_bytes());
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Read one length-prefixed frame. A clean end of stream before the frame
+/// starts is reported as `UnexpectedEof`.
+pub fn read_frame<T: Wire>(reader: &mut impl Read) -> io::Result<T> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME {
+        return Err(WireError::new(format!("frame of {len} bytes")).into());
+    }
+    let mut frame = vec![0; len as usize];
+    reader.read_exact(&mut frame)?;
+    let mut input = frame.as_slice();
+    let value = T::decode(&mut input)?;
+    if !input.is_empty() {
+        return Err(WireError::new(format!("{} trailing bytes", input.len())).into());
+    }
+    Ok(value)
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], WireError> {
+    if input.len() < n {
+        return Err(WireError::new("unexpected end of message"));
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+macro_rules! wire_number {
+    ($($ty:ty),*) => {$(
+        impl Wire for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+                let bytes = take(input, size_of::<$ty>())?;
+                Ok(<$ty>::from_le_bytes(bytes.try_into().expect("took exact size")))
+            }
+        }
+    )*};
+}
+
+wire_number!(u8, u16, u32, u64, i64, f32, f64);
+
+/// Struct encoded as its fields in order.
+macro_rules! wire_struct {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl Wire for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                $(self.$field.encode(out);)*
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+                Ok(Self { $($field: Wire::decode(input)?,)* })
+            }
+        }
+    };
+}
+
+/// Enum encoded as a tag byte followed by the variant's fields in order.
+/// Tags must stay stable once assigned.
+macro_rules! wire_enum {
+    ($ty:ident {
+        $($tag:literal => $variant:ident
+            $(( $($tuple_field:ident),* ))?
+            $({ $($struct_field:ident),* })?
+        ),* $(,)?
+    }) => {
+        impl Wire for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                match self {
+                    $(Self::$variant $(( $($tuple_field),* ))? $({ $($struct_field),* })? => {
+                        out.push($tag);
+                        $($($tuple_field.encode(out);)*)?
+                        $($($struct_field.encode(out);)*)?
+                    })*
+                }
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+                match u8::decode(input)? {
+                    $($tag => Ok(Self::$variant
+                        $(( $({
+                            let _ = stringify!($tuple_field);
+                            Wire::decode(input)?
+                        }),* ))?
+                        $({ $($struct_field: Wire::decode(input)?),* })?
+                    ),)*
+                    tag => Err(WireError::new(format!(
+                        "unknown {} tag {}",
+                        stringify!($ty),
+                        tag
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+impl Wire for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(WireError::new(format!("bool {other}"))),
+        }
+    }
+}
+
+impl Wire for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let value = u64::decode(input)?;
+        usize::try_from(value).map_err(|_| WireError::new(format!("usize {value}")))
+    }
+}
+
+impl Wire for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = usize::decode(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| WireError::new("invalid UTF-8"))
+    }
+}
+
+/// Paths travel as UTF-8; others are converted lossily.
+impl Wire for PathBuf {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_string_lossy().into_owned().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        String::decode(input).map(PathBuf::from)
+    }
+}
+
+impl Wire for Duration {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_secs().encode(out);
+        self.subsec_nanos().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let secs = u64::decode(input)?;
+        let nanos = u32::decode(input)?;
+        if nanos >= 1_000_000_000 {
+            return Err(WireError::new(format!("{nanos} ns")));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+/// As the time since the Unix epoch; times before it come out as the epoch.
+impl Wire for SystemTime {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Duration::decode(input).map(|since| UNIX_EPOCH + since)
+    }
+}
+
+impl<T: Wire> Wire for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => T::decode(input).map(Some),
+            other => Err(WireError::new(format!("option tag {other}"))),
+        }
+    }
+}
+
+impl<T: Wire> Wire for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = usize::decode(input)?;
+        // Every item takes at least a byte, so a length beyond the input is corrupt
+        if len > input.len() {
+            return Err(WireError::new(format!("{len} items")));
+        }
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl<T: Wire> Wire for Box<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        T::encode(self, out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        T::decode(input).map(Box::new)
+    }
+}
+
+impl<A: Wire, B: Wire> Wire for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl Wire for [u8; 3] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(take(input, 3)?.try_into().expect("took exact size"))
+    }
+}
+
+impl Wire for Hertz {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        u64::decode(input).map(Hertz)
+    }
+}
+
+impl Wire for Decibels {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, WireError> {
+        f32::decode(input).map(Decibels)
+    }
+}
+
+wire_struct!(FrequencyRange { start, end });
+wire_struct!(GainProfile { range, gain });
+wire_struct!(CalibrationPoint { offset, gain });
+wire_struct!(Antenna { name, command });
+wire_struct!(AntennaRule { range, antenna });
+wire_struct!(AntennaSwitchConfig {
+    link,
+    antennas,
+    rules
+});
+wire_struct!(DarkFrameConfig {
+    termination,
+    interval,
+    frames
+});
+wire_struct!(RigConfig {
+    address,
+    if_frequency
+});
+wire_struct!(RotatorPosition { azimuth, elevation });
+wire_struct!(SpectrumFrame {
+    sequence,
+    sample_time,
+    timestamp,
+    source,
+    discontinuity,
+    center_frequency,
+    sample_rate,
+    magnitudes,
+    peak_hold,
+    min_hold
+});
+wire_struct!(ReducedSpectrum {
+    frame,
+    bins,
+    merge,
+    delta,
+    levels
+});
+wire_struct!(SessionRecord {
+    source_config,
+    center_frequency,
+    gain,
+    recording
+});
+wire_struct!(SettingsBundle {
+    source_config,
+    center_frequency,
+    gain,
+    gain_profiles,
+    antenna_switch,
+    rig,
+    rotator,
+    alert_rules,
+    scan_list
+});
+wire_struct!(LastSession {
+    source_config,
+    center_frequency,
+    gain,
+    fft_size,
+    listening,
+    squelch,
+    passband,
+    volume,
+    colormap,
+    window,
+    annotations
+});
+wire_struct!(Annotation {
+    shape,
+    start,
+    end,
+    text
+});
+wire_struct!(AnnotationPoint { frequency, time });
+wire_struct!(WindowGeometry { size, position });
+wire_struct!(ScanChannel {
+    name,
+    channel,
+    priority,
+    lockout,
+    delay,
+    hold
+});
+wire_struct!(ScanList {
+    channels,
+    priority_every,
+    dwell
+});
+wire_struct!(ScanHit {
+    channel,
+    name,
+    frequency,
+    demod,
+    duration,
+    level,
+    class
+});
+wire_struct!(CommandMacro {
+    name,
+    hotkey,
+    commands
+});
+wire_struct!(AudioChannel { frequency, demod });
+wire_struct!(Passband { low, high });
+wire_struct!(AudioRouting {
+    speakers,
+    recording,
+    network,
+});
+wire_struct!(VfoConfig {
+    channel,
+    passband,
+    squelch,
+    recording,
+    network
+});
+wire_struct!(Vfo { id, config });
+wire_struct!(RdsData {
+    pi,
+    station,
+    radiotext
+});
+wire_struct!(AudioChunk {
+    sample_rate,
+    samples,
+    stereo
+});
+wire_struct!(SelCallConfig { channel, standard });
+wire_struct!(SelCall {
+    start,
+    standard,
+    id
+});
+wire_struct!(GeoPosition {
+    latitude,
+    longitude
+});
+wire_struct!(TrackReport {
+    kind,
+    id,
+    name,
+    position,
+    altitude,
+    course,
+    speed
+});
+wire_struct!(CarrierMeasurement {
+    elapsed,
+    frequency,
+    power
+});
+wire_struct!(Burst {
+    start,
+    duration,
+    interval,
+    peak_snr
+});
+wire_struct!(MeteorConfig {
+    frequency,
+    bandwidth,
+    threshold
+});
+wire_struct!(Impulse { time, peak_snr });
+wire_struct!(SignalRegion {
+    frequency,
+    bandwidth
+});
+wire_struct!(SymbolRateCandidate { rate, strength });
+wire_struct!(SymbolRateEstimate { region, candidates });
+wire_struct!(SourceCapability {
+    kind,
+    max_sample_rate,
+    sample_rates,
+    devices,
+});
+wire_struct!(SourceDevice {
+    args,
+    label,
+    antennas,
+    gain_stages,
+    bandwidth,
+    sample_rates,
+});
+wire_struct!(GainStage { name, min, max });
+wire_struct!(StageGain { stage, gain });
+wire_struct!(Tone {
+    frequency,
+    amplitude
+});
+wire_struct!(Sweep { span, period });
+wire_struct!(Capabilities {
+    version,
+    sources,
+    demod_modes,
+    features,
+});
+wire_struct!(StageCheck {
+    stage,
+    level,
+    frequency,
+    passed,
+});
+wire_struct!(SelfTestReport {
+    tone_frequency,
+    tone_level,
+    tolerance,
+    checks,
+});
+wire_struct!(EngineState {
+    center_frequency,
+    gain,
+    gain_profiles,
+    antenna_switch,
+    antenna,
+    dark_frame,
+    rig,
+    rotator,
+    sample_rate,
+    device_sample_rate,
+    fft_size,
+    decimation,
+    spectrum_span,
+    frequency_offset,
+    calibration,
+    averaging,
+    peak_hold,
+    min_hold,
+    offset_tuning,
+    source_config,
+    second_source,
+    playback_speed,
+    playback_start,
+    playback_paused,
+    carrier_measurement,
+    burst_detection,
+    meteor_detection,
+    impulse_counter,
+    sstv_decoder,
+    selcall_decoder,
+    ais_decoder,
+    adsb_decoder,
+    demodulator,
+    squelch,
+    passband,
+    scan,
+    audio_routing,
+    vfos,
+});
+
+wire_enum!(AntennaSwitchLink {
+    0 => Serial { path },
+    1 => Network { address },
+    2 => Gpio { pins },
+});
+wire_enum!(RecordingFormat {
+    0 => SigMf,
+    1 => Raw,
+});
+wire_struct!(RecordingStatus {
+    path,
+    bytes_written,
+    active
+});
+wire_struct!(RecordingOverview {
+    path,
+    sample_rate,
+    duration,
+    row_duration,
+    rows
+});
+wire_enum!(RustIqError {
+    0 => Source { kind, detail },
+    1 => Device { device, detail },
+    2 => Format { path, detail },
+    3 => Protocol { peer, detail },
+    4 => Limit { resource, detail },
+});
+wire_enum!(Resource {
+    0 => Memory,
+    1 => Threads,
+    2 => Disk,
+});
+wire_enum!(Lockout {
+    0 => None,
+    1 => Temporary,
+    2 => Permanent,
+});
+wire_enum!(SignalClass {
+    0 => Carrier,
+    1 => ConstantEnvelope,
+    2 => VaryingEnvelope,
+});
+wire_enum!(Discontinuity {
+    0 => Restart,
+    1 => SourceStall,
+});
+wire_enum!(DemodMode {
+    0 => Usb,
+    1 => Fm,
+    2 => Wfm,
+    3 => Am,
+    4 => Lsb,
+    5 => Cw,
+});
+wire_enum!(SstvMode {
+    0 => Martin1,
+    1 => Martin2,
+    2 => Scottie1,
+    3 => Scottie2,
+    4 => ScottieDx,
+    5 => Robot36,
+});
+wire_enum!(SstvEvent {
+    0 => Started(mode),
+    1 => Line { line, pixels },
+    2 => Finished,
+});
+wire_enum!(SelCallStandard {
+    0 => Zvei1,
+    1 => Zvei3,
+    2 => Ccir,
+    3 => Eea,
+    4 => Eia,
+});
+wire_enum!(AnnotationShape {
+    0 => Label,
+    1 => Arrow,
+    2 => Box,
+});
+wire_enum!(TrackKind {
+    0 => Vessel,
+    1 => Aircraft,
+});
+wire_enum!(SourceKind {
+    0 => SignalGenerator,
+    1 => File,
+    2 => RtlSdr,
+    3 => SoapySdr,
+    4 => RtlTcp,
+    5 => SpyServer,
+});
+wire_enum!(Feature {
+    0 => CarrierMeasurement,
+    1 => BurstDetection,
+    2 => MeteorDetection,
+    3 => ImpulseCounter,
+    4 => SstvDecoder,
+    5 => SelCallDecoder,
+    6 => AisDecoder,
+    7 => AdsbDecoder,
+    8 => AntennaSwitch,
+    9 => Rig,
+    10 => Rotator,
+    11 => SymbolRateEstimation,
+});
+wire_enum!(Stage {
+    0 => Source,
+    1 => Spectrum,
+    2 => Audio,
+});
+wire_enum!(IqFormat {
+    0 => Cf32,
+    1 => Cu8,
+    2 => Cs8,
+    3 => Cs16,
+    4 => Wav,
+});
+wire_enum!(Averaging {
+    0 => Off,
+    1 => Exponential(factor),
+    2 => Block(frames),
+});
+wire_enum!(SourceConfig {
+    0 => SignalGenerator { sample_rate, tones, snr, sweep },
+    1 => File { path, sample_rate, format },
+    2 => RtlSdr { sample_rate, gain },
+    3 => SoapySdr { device, sample_rate, antenna, bandwidth, gains },
+    4 => RtlTcp { host, port, sample_rate, gain },
+    5 => SpyServer { host, port, sample_rate, gain },
+});
+wire_enum!(Command {
+    0 => Stop,
+    1 => ChangeSource(config),
+    // 2 was a retune that always rebuilt the graph
+    3 => SetGain(gain),
+    4 => SetGainProfiles(profiles),
+    5 => SetAntennaSwitch(config),
+    6 => SelectAntenna(index),
+    7 => ConnectRotator(address),
+    8 => DisconnectRotator,
+    9 => PointRotator(position),
+    10 => ConnectRig(config),
+    11 => DisconnectRig,
+    12 => TuneRig(frequency),
+    13 => StartCarrierMeasurement(target),
+    14 => StopCarrierMeasurement,
+    15 => StartBurstDetection(threshold),
+    16 => StopBurstDetection,
+    17 => StartMeteorDetection(config),
+    18 => StopMeteorDetection,
+    19 => StartImpulseCounter(threshold),
+    20 => StopImpulseCounter,
+    21 => StartSstvDecoder(channel),
+    22 => StopSstvDecoder,
+    23 => StartSelCallDecoder(config),
+    24 => StopSelCallDecoder,
+    25 => StartAisDecoder(frequency),
+    26 => StopAisDecoder,
+    27 => StartAdsbDecoder,
+    28 => StopAdsbDecoder,
+    29 => RestoreSession(session),
+    30 => RunSelfTest,
+    31 => SetSecondSource(config),
+    32 => EstimateSymbolRate(region),
+    33 => SetCenterFrequency(frequency),
+    34 => SetDemodulator(channel),
+    35 => SetFftSize(size),
+    36 => SetCalibration(points),
+    37 => SetOffsetTuning(enabled),
+    38 => Resync,
+    39 => StartRecording { path, format },
+    40 => StopRecording,
+    41 => SetAveraging(averaging),
+    42 => SetPeakHold(enabled),
+    43 => SetMinHold(enabled),
+    44 => SetAudioRouting(routing),
+    45 => SetDecimation(decimation),
+    46 => SetFrequencyOffset(offset),
+    47 => SetPlaybackSpeed(speed),
+    48 => Seek(position),
+    49 => SetSquelch(threshold),
+    50 => StartScan(list),
+    51 => StopScan,
+    52 => SetLockout { channel, lockout },
+    53 => SetPassband(passband),
+    54 => AddVfo(config),
+    55 => RemoveVfo(id),
+    56 => ConfigureVfo { id, config },
+    57 => SetDarkFrame(config),
+    58 => Pause,
+    59 => Resume,
+});
+wire_enum!(Event {
+    0 => StateSnapshot(state),
+    1 => SpectrumData(frame),
+    2 => CarrierMeasurement(measurement),
+    3 => Burst(burst),
+    4 => MeteorPing(ping),
+    5 => Impulse(impulse),
+    6 => Sstv(event),
+    7 => SelCall(call),
+    8 => Track(report),
+    9 => RotatorPosition(position),
+    10 => PreviousSession(session),
+    11 => Capabilities(capabilities),
+    12 => SelfTest(report),
+    13 => SymbolRate(estimate),
+    14 => AudioChunk(chunk),
+    15 => RecordingStatus(status),
+    16 => ReducedSpectrum(reduced),
+    17 => RecordingOverview(overview),
+    18 => Error(error),
+    19 => SquelchState(open),
+    20 => Scanning { channel, active },
+    21 => ScanHit(hit),
+    22 => VfoSquelch { id, open },
+    23 => RdsData(data),
+    24 => DarkFrameCaptured(level),
+    25 => Stats { frames_dropped, fps },
+    26 => PlaybackPosition(position),
+});
@@ -0,0 +1,75 @@
+use rustiq_messages::{AudioChannel, DemodMode, Hertz, Passband, SignalRegion};
+
+#[test]
+fn test_wide_signal_is_demodulated_as_fm_at_its_center() {
+    let channel = AudioChannel::for_signal(SignalRegion {
+        frequency: Hertz(446_006_250),
+        bandwidth: Hertz(11_000),
+    });
+    assert_eq!(
+        channel,
+        AudioChannel {
+            frequency: Hertz(446_006_250),
+            demod: DemodMode::Fm,
+        }
+    );
+}
+
+#[test]
+fn test_broadcast_signal_is_demodulated_as_wfm_at_its_center() {
+    let channel = AudioChannel::for_signal(SignalRegion {
+        frequency: Hertz(98_800_000),
+        bandwidth: Hertz(180_000),
+    });
+    assert_eq!(
+        channel,
+        AudioChannel {
+            frequency: Hertz(98_800_000),
+            demod: DemodMode::Wfm,
+        }
+    );
+}
+
+#[test]
+fn test_narrow_signal_is_demodulated_as_usb_from_its_lower_edge() {
+    let channel = AudioChannel::for_signal(SignalRegion {
+        frequency: Hertz(14_231_500),
+        bandwidth: Hertz(2_400),
+    });
+    assert_eq!(
+        channel,
+        AudioChannel {
+            frequency: Hertz(14_230_300),
+            demod: DemodMode::Usb,
+        }
+    );
+}
+
+#[test]
+fn test_sideband_passbands_sit_to_their_side_of_the_dial() {
+    let usb = DemodMode::Usb.passband();
+    assert_eq!(
+        usb,
+        Passband {
+            low: 150,
+            high: 2_850
+        }
+    );
+    assert_eq!(usb.width(), DemodMode::Usb.bandwidth().0);
+    assert_eq!(
+        DemodMode::Lsb.passband(),
+        Passband {
+            low: -2_850,
+            high: -150
+        }
+    );
+    let am = DemodMode::Am.passband();
+    assert_eq!(
+        am,
+        Passband {
+            low: -5_000,
+            high: 5_000
+        }
+    );
+    assert_eq!(am.center(), 0.0);
+}
@@ -0,0 +1,28 @@
+use rustiq_messages::{CalibrationPoint, Decibels};
+
+fn points() -> Vec<CalibrationPoint> {
+    [(-1_000.0, -6.0), (0.0, 0.0), (500.0, -2.0)]
+        .into_iter()
+        .map(|(offset, gain)| CalibrationPoint {
+            offset,
+            gain: Decibels(gain),
+        })
+        .collect()
+}
+
+#[test]
+fn test_gain_is_interpolated_between_points() {
+    assert_eq!(CalibrationPoint::gain_at(&points(), -500.0), Decibels(-3.0));
+    assert_eq!(CalibrationPoint::gain_at(&points(), 0.0), Decibels(0.0));
+    assert_eq!(CalibrationPoint::gain_at(&points(), 250.0), Decibels(-1.0));
+}
+
+#[test]
+fn test_gain_holds_beyond_the_table() {
+    assert_eq!(
+        CalibrationPoint::gain_at(&points(), -2_000.0),
+        Decibels(-6.0)
+    );
+    assert_eq!(CalibrationPoint::gain_at(&points(), 900.0), Decibels(-2.0));
+    assert_eq!(CalibrationPoint::gain_at(&[], 900.0), Decibels(0.0));
+}
@@ -0,0 +1,66 @@
+use rustiq_messages::{ChannelPlan, ChannelRegion, Hertz};
+
+#[test]
+fn test_channels_are_on_their_band_plan_frequencies() {
+    let marine = ChannelPlan::Marine.channels();
+    assert_eq!(marine.len(), 57);
+    let sixteen = ChannelPlan::Marine.find("Marine Ch 16").unwrap();
+    assert_eq!(sixteen.frequency, Hertz(156_800_000));
+    assert_eq!(sixteen.purpose, Some("Distress, safety and calling"));
+    assert_eq!(
+        ChannelPlan::Marine.find("ch72").unwrap().frequency,
+        Hertz(156_625_000)
+    );
+    assert_eq!(
+        ChannelPlan::Pmr446.find("Ch 8").unwrap().frequency,
+        Hertz(446_093_750)
+    );
+    assert_eq!(
+        ChannelPlan::FrsGmrs.find("Ch 15").unwrap().frequency,
+        Hertz(462_550_000)
+    );
+    assert!(ChannelPlan::Marine.find("Ch 40").is_none());
+}
+
+#[test]
+fn test_airband_channels_are_named_as_dialled() {
+    let guard = ChannelPlan::Airband.channel_at(Hertz(121_500_000)).unwrap();
+    assert_eq!(ChannelPlan::Airband.display(&guard), "Air 121.500");
+    assert_eq!(guard.purpose, Some("Emergency"));
+    assert_eq!(ChannelPlan::Airband.channels().len(), 760);
+
+    // 118.025 MHz splits into 118.030, 118.035 and 118.040
+    let split = ChannelPlan::Airband833;
+    assert_eq!(split.find("118.030").unwrap().frequency, Hertz(118_025_000));
+    assert_eq!(split.find("118.035").unwrap().frequency, Hertz(118_033_333));
+    assert_eq!(split.find("118.040").unwrap().frequency, Hertz(118_041_667));
+}
+
+#[test]
+fn test_frequencies_snap_to_the_nearest_channel_within_the_band() {
+    let snapped = ChannelPlan::Marine.nearest(Hertz(156_790_000)).unwrap();
+    assert_eq!(snapped.name, "Ch 16");
+    assert_eq!(
+        ChannelPlan::Airband833
+            .nearest(Hertz(118_030_000))
+            .unwrap()
+            .name,
+        "118.035"
+    );
+    assert!(ChannelPlan::Marine.nearest(Hertz(100_000_000)).is_none());
+}
+
+#[test]
+fn test_regions_offer_their_own_plans() {
+    assert!(ChannelRegion::Europe.plans().contains(&ChannelPlan::Pmr446));
+    assert!(
+        !ChannelRegion::Europe
+            .plans()
+            .contains(&ChannelPlan::FrsGmrs)
+    );
+    assert!(
+        ChannelRegion::NorthAmerica
+            .plans()
+            .contains(&ChannelPlan::FrsGmrs)
+    );
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "serde")]
+
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rustiq_messages::{
+    AudioChannel, Command, Decibels, DemodMode, EngineState, Event, Hertz, IqFormat, Passband,
+    SourceConfig, SpectrumFrame, Vfo, VfoConfig,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Serialize each value to JSON and check it comes back the same.
+/// Commands and events don't implement `PartialEq`, so compare their debug output.
+fn round_trip<T: Serialize + DeserializeOwned + std::fmt::Debug>(values: Vec<T>) {
+    for value in &values {
+        let json = serde_json::to_string(value).unwrap();
+        let decoded: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{decoded:?}"), format!("{value:?}"), "{json}");
+    }
+}
+
+#[test]
+fn test_units_serialize_as_numbers() {
+    assert_eq!(serde_json::to_string(&Hertz(7_074_000)).unwrap(), "7074000");
+    assert_eq!(serde_json::to_string(&Decibels(-3.5)).unwrap(), "-3.5");
+    assert_eq!(
+        serde_json::from_str::<Hertz>("145500000").unwrap(),
+        Hertz(145_500_000)
+    );
+}
+
+#[test]
+fn test_commands_round_trip() {
+    round_trip(vec![
+        Command::SetCenterFrequency(Hertz::mhz(145)),
+        Command::SetGain(Decibels(20.0)),
+        Command::SetDemodulator(Some(AudioChannel {
+            frequency: Hertz(7_074_000),
+            demod: DemodMode::Usb,
+        })),
+        Command::SetPassband(Some(Passband {
+            low: 300,
+            high: 2_400,
+        })),
+        Command::ChangeSource(SourceConfig::File {
+            path: PathBuf::from("/tmp/capture.iq"),
+            sample_rate: Hertz(2_400_000),
+            format: IqFormat::Cu8,
+        }),
+        Command::AddVfo(VfoConfig::new(AudioChannel {
+            frequency: Hertz(121_500_000),
+            demod: DemodMode::Am,
+        })),
+        Command::Stop,
+    ]);
+}
+
+#[test]
+fn test_events_round_trip() {
+    let state = EngineState {
+        center_frequency: Hertz::mhz(144),
+        gain: Decibels(6.0),
+        gain_profiles: Vec::new(),
+        antenna_switch: None,
+        antenna: Some(1),
+        dark_frame: None,
+        rig: None,
+        rotator: None,
+        sample_rate: Hertz(48_000),
+        device_sample_rate: None,
+        fft_size: 4096,
+        decimation: 1,
+        spectrum_span: Hertz(48_000),
+        frequency_offset: 0,
+        calibration: Vec::new(),
+        averaging: Default::default(),
+        peak_hold: false,
+        min_hold: false,
+        offset_tuning: false,
+        source_config: SourceConfig::default(),
+        second_source: None,
+        playback_speed: None,
+        playback_start: Duration::ZERO,
+        playback_paused: false,
+        carrier_measurement: None,
+        burst_detection: None,
+        meteor_detection: None,
+        impulse_counter: None,
+        sstv_decoder: None,
+        selcall_decoder: None,
+        ais_decoder: None,
+        adsb_decoder: false,
+        demodulator: None,
+        squelch: Some(Decibels(-50.0)),
+        passband: None,
+        scan: None,
+        audio_routing: Default::default(),
+        vfos: vec![Vfo {
+            id: 1,
+            config: VfoConfig::new(AudioChannel {
+                frequency: Hertz(121_500_000),
+                demod: DemodMode::Am,
+            }),
+        }],
+    };
+    round_trip(vec![
+        Event::StateSnapshot(Box::new(state)),
+        Event::SpectrumData(SpectrumFrame {
+            sequence: 1_000,
+            sample_time: Duration::from_micros(85_333),
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            source: 0,
+            discontinuity: None,
+            center_frequency: Hertz::mhz(144),
+            sample_rate: Hertz(48_000),
+            magnitudes: vec![0.0, 1.5, 0.25],
+            peak_hold: None,
+            min_hold: None,
+        }),
+    ]);
+}
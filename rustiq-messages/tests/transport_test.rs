@@ -0,0 +1,39 @@
+use std::io::{BufReader, ErrorKind};
+
+use rustiq_messages::{
+    Command, CommandSender, Decibels, Event, EventReceiver, Hertz, Transport, WireTransport,
+    read_frame, write_frame,
+};
+
+#[test]
+fn test_wire_transport_carries_commands_and_events() {
+    // One pipe each way, with the engine's ends kept here
+    let (events_reader, mut events_writer) = std::io::pipe().unwrap();
+    let (commands_reader, commands_writer) = std::io::pipe().unwrap();
+    let (mut events, mut commands) = WireTransport {
+        reader: events_reader,
+        writer: commands_writer,
+    }
+    .split();
+
+    commands
+        .send(&Command::SetCenterFrequency(Hertz::mhz(145)))
+        .unwrap();
+    commands.send(&Command::SetGain(Decibels(20.0))).unwrap();
+    let mut engine_side = BufReader::new(commands_reader);
+    assert!(matches!(
+        read_frame(&mut engine_side).unwrap(),
+        Command::SetCenterFrequency(frequency) if frequency == Hertz::mhz(145)
+    ));
+    assert!(matches!(
+        read_frame(&mut engine_side).unwrap(),
+        Command::SetGain(Decibels(20.0))
+    ));
+
+    write_frame(&mut events_writer, &Event::SquelchState(true)).unwrap();
+    assert!(matches!(events.recv().unwrap(), Event::SquelchState(true)));
+
+    // The engine going away ends the events
+    drop(events_writer);
+    assert_eq!(events.recv().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
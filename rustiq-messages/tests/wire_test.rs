@@ -0,0 +1,585 @@
+use std::io::{Cursor, ErrorKind};
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rustiq_messages::{
+    Annotation, AnnotationPoint, AnnotationShape, Antenna, AntennaRule, AntennaSwitchConfig,
+    AntennaSwitchLink, AudioChannel, AudioChunk, AudioRouting, Averaging, Burst, CalibrationPoint,
+    Capabilities, Command, CommandMacro, DarkFrameConfig, Decibels, DemodMode, Discontinuity,
+    EngineState, Event, Feature, FrequencyRange, GainProfile, GainStage, GeoPosition, Hertz,
+    IqFormat, LastSession, Lockout, Passband, RdsData, RecordingFormat, RecordingOverview,
+    RecordingStatus, ReducedSpectrum, Resource, RigConfig, RustIqError, ScanChannel, ScanHit,
+    ScanList, SelfTestReport, SessionRecord, SettingsBundle, SignalClass, SignalRegion,
+    SourceCapability, SourceConfig, SourceDevice, SourceKind, SpectrumFrame, SstvEvent, Stage,
+    StageCheck, StageGain, Sweep, SymbolRateCandidate, SymbolRateEstimate, Tone, TrackKind,
+    TrackReport, Vfo, VfoConfig, WindowGeometry, read_frame, write_frame,
+};
+
+/// A scan list of two marine channels, one of them priority and one
+/// locked out.
+fn scan_list() -> ScanList {
+    let marine = |frequency| AudioChannel {
+        frequency: Hertz(frequency),
+        demod: DemodMode::Fm,
+    };
+    ScanList {
+        channels: vec![
+            ScanChannel {
+                priority: true,
+                hold: Some(Duration::from_secs(30)),
+                ..ScanChannel::new("Marine Ch 16", marine(156_800_000))
+            },
+            ScanChannel {
+                lockout: Lockout::Permanent,
+                delay: Duration::from_millis(500),
+                ..ScanChannel::new("Marine Ch 6", marine(156_300_000))
+            },
+        ],
+        priority_every: 4,
+        dwell: Some(Duration::from_secs(10)),
+    }
+}
+
+/// Send each value through one stream and check it comes back the same.
+/// Commands and events don't implement `PartialEq`, so compare their debug output.
+fn round_trip<T: rustiq_messages::Wire + std::fmt::Debug>(values: Vec<T>) {
+    let mut stream = Vec::new();
+    for value in &values {
+        write_frame(&mut stream, value).unwrap();
+    }
+    let mut reader = Cursor::new(stream);
+    for value in &values {
+        let decoded: T = read_frame(&mut reader).unwrap();
+        assert_eq!(format!("{decoded:?}"), format!("{value:?}"));
+    }
+    let end = read_frame::<T>(&mut reader).unwrap_err();
+    assert_eq!(end.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_commands_round_trip() {
+    round_trip(vec![
+        Command::Stop,
+        Command::ChangeSource(SourceConfig::File {
+            path: PathBuf::from("/tmp/capture.iq"),
+            sample_rate: Hertz(2_048_000),
+            format: IqFormat::Cu8,
+        }),
+        Command::SetGainProfiles(vec![GainProfile {
+            range: FrequencyRange::new(Hertz::mhz(3), Hertz::mhz(30)),
+            gain: Decibels(-10.5),
+        }]),
+        Command::SetAntennaSwitch(Some(AntennaSwitchConfig {
+            link: AntennaSwitchLink::Gpio { pins: vec![17, 27] },
+            antennas: vec![Antenna {
+                name: "Discone".to_string(),
+                command: String::new(),
+            }],
+            rules: vec![AntennaRule {
+                range: FrequencyRange::new(Hertz::mhz(25), Hertz::mhz(1_300)),
+                antenna: 0,
+            }],
+        })),
+        Command::SetAntennaSwitch(None),
+        Command::SetDarkFrame(Some(DarkFrameConfig {
+            termination: 3,
+            interval: Duration::from_secs(3_600),
+            frames: 64,
+        })),
+        Command::SetDarkFrame(None),
+        Command::StartSstvDecoder(AudioChannel {
+            frequency: Hertz(14_230_000),
+            demod: DemodMode::Usb,
+        }),
+        Command::StartAdsbDecoder,
+        Command::RunSelfTest,
+        Command::SetCenterFrequency(Hertz::mhz(145)),
+        Command::SetDemodulator(Some(AudioChannel {
+            frequency: Hertz::mhz(145),
+            demod: DemodMode::Fm,
+        })),
+        Command::SetDemodulator(Some(AudioChannel {
+            frequency: Hertz(7_074_000),
+            demod: DemodMode::Lsb,
+        })),
+        Command::SetDemodulator(Some(AudioChannel {
+            frequency: Hertz(118_100_000),
+            demod: DemodMode::Am,
+        })),
+        Command::SetDemodulator(Some(AudioChannel {
+            frequency: Hertz(14_025_000),
+            demod: DemodMode::Cw,
+        })),
+        Command::SetDemodulator(None),
+        Command::SetFftSize(16_384),
+        Command::SetOffsetTuning(true),
+        Command::Resync,
+        Command::SetCalibration(vec![
+            CalibrationPoint {
+                offset: -1_000_000.0,
+                gain: Decibels(-3.5),
+            },
+            CalibrationPoint {
+                offset: 0.0,
+                gain: Decibels(0.0),
+            },
+        ]),
+        Command::SetSecondSource(None),
+        Command::ChangeSource(SourceConfig::SignalGenerator {
+            sample_rate: Hertz(48_000),
+            tones: vec![
+                Tone {
+                    frequency: Hertz(1_000),
+                    amplitude: Decibels(-6.0),
+                },
+                Tone {
+                    frequency: Hertz(12_000),
+                    amplitude: Decibels(-40.0),
+                },
+            ],
+            snr: Some(Decibels(30.0)),
+            sweep: Some(Sweep {
+                span: Hertz(5_000),
+                period: Duration::from_millis(250),
+            }),
+        }),
+        Command::ChangeSource(SourceConfig::RtlSdr {
+            sample_rate: Hertz(2_400_000),
+            gain: Decibels(29.7),
+        }),
+        Command::ChangeSource(SourceConfig::SoapySdr {
+            device: "driver=airspy".to_string(),
+            sample_rate: Hertz(2_500_000),
+            antenna: Some("RX".to_string()),
+            bandwidth: None,
+            gains: vec![StageGain {
+                stage: "LNA".to_string(),
+                gain: Decibels(12.0),
+            }],
+        }),
+        Command::ChangeSource(SourceConfig::RtlTcp {
+            host: "pi.local".to_string(),
+            port: 1234,
+            sample_rate: Hertz(2_048_000),
+            gain: Decibels(33.8),
+        }),
+        Command::ChangeSource(SourceConfig::SpyServer {
+            host: "airspy.example.org".to_string(),
+            port: 5555,
+            sample_rate: Hertz(625_000),
+            gain: 12,
+        }),
+        Command::EstimateSymbolRate(SignalRegion {
+            frequency: Hertz(10_000),
+            bandwidth: Hertz(2_400),
+        }),
+        Command::StartRecording {
+            path: PathBuf::from("/data/pass"),
+            format: RecordingFormat::SigMf,
+        },
+        Command::StopRecording,
+        Command::SetAveraging(Averaging::Off),
+        Command::SetAveraging(Averaging::Exponential(0.25)),
+        Command::SetAveraging(Averaging::Block(8)),
+        Command::SetDecimation(16),
+        Command::SetFrequencyOffset(-250_000),
+        Command::SetPlaybackSpeed(Some(0.75)),
+        Command::SetPlaybackSpeed(None),
+        Command::Seek(Duration::from_millis(93_500)),
+        Command::Pause,
+        Command::Resume,
+        Command::SetSquelch(Some(Decibels(-45.5))),
+        Command::SetSquelch(None),
+        Command::StartScan(scan_list()),
+        Command::StopScan,
+        Command::SetLockout {
+            channel: 1,
+            lockout: Lockout::Temporary,
+        },
+        Command::SetPassband(Some(Passband {
+            low: -2_400,
+            high: -300,
+        })),
+        Command::SetPassband(None),
+        Command::AddVfo(VfoConfig::new(AudioChannel {
+            frequency: Hertz(156_800_000),
+            demod: DemodMode::Fm,
+        })),
+        Command::ConfigureVfo {
+            id: 3,
+            config: VfoConfig {
+                channel: AudioChannel {
+                    frequency: Hertz(7_074_000),
+                    demod: DemodMode::Usb,
+                },
+                passband: Some(Passband {
+                    low: 200,
+                    high: 3_000,
+                }),
+                squelch: Some(Decibels(-60.0)),
+                recording: Some(PathBuf::from("/tmp/vfo3.wav")),
+                network: vec!["127.0.0.1:7356".to_string()],
+            },
+        },
+        Command::RemoveVfo(3),
+        Command::SetPeakHold(true),
+        Command::SetMinHold(false),
+        Command::SetAudioRouting(AudioRouting {
+            speakers: false,
+            recording: Some(PathBuf::from("/tmp/listening.wav")),
+            network: vec!["127.0.0.1:7355".to_string()],
+        }),
+    ]);
+}
+
+#[test]
+fn test_events_round_trip() {
+    let state = EngineState {
+        center_frequency: Hertz::mhz(144),
+        gain: Decibels(6.0),
+        gain_profiles: Vec::new(),
+        antenna_switch: None,
+        antenna: Some(1),
+        dark_frame: Some(DarkFrameConfig {
+            termination: 2,
+            interval: Duration::from_secs(600),
+            frames: 32,
+        }),
+        rig: None,
+        rotator: Some("localhost:4533".to_string()),
+        sample_rate: Hertz(48_000),
+        device_sample_rate: Some(Hertz(250_000)),
+        fft_size: 4096,
+        decimation: 8,
+        spectrum_span: Hertz(6_000),
+        frequency_offset: -250_000,
+        calibration: Vec::new(),
+        averaging: Averaging::Exponential(0.5),
+        peak_hold: true,
+        min_hold: false,
+        offset_tuning: false,
+        source_config: SourceConfig::default(),
+        second_source: Some(SourceConfig::File {
+            path: PathBuf::from("/tmp/vertical.iq"),
+            sample_rate: Hertz(2_400_000),
+            format: IqFormat::Cf32,
+        }),
+        playback_speed: Some(1.5),
+        playback_start: Duration::from_secs(40),
+        playback_paused: true,
+        carrier_measurement: Some(Hertz(10_000)),
+        burst_detection: None,
+        meteor_detection: None,
+        impulse_counter: Some(Decibels(10.0)),
+        sstv_decoder: None,
+        selcall_decoder: None,
+        ais_decoder: None,
+        adsb_decoder: true,
+        demodulator: Some(AudioChannel {
+            frequency: Hertz(7_074_000),
+            demod: DemodMode::Usb,
+        }),
+        squelch: Some(Decibels(-50.0)),
+        passband: Some(Passband {
+            low: 300,
+            high: 2_400,
+        }),
+        scan: Some(scan_list()),
+        audio_routing: AudioRouting::default(),
+        vfos: vec![Vfo {
+            id: 1,
+            config: VfoConfig::new(AudioChannel {
+                frequency: Hertz(121_500_000),
+                demod: DemodMode::Am,
+            }),
+        }],
+    };
+    let mut report = TrackReport::new(TrackKind::Aircraft, "4840D6");
+    report.name = Some("KLM1023".to_string());
+    report.position = Some(GeoPosition {
+        latitude: 52.2572,
+        longitude: 3.9193,
+    });
+    report.altitude = Some(38_000.0);
+    round_trip(vec![
+        Event::StateSnapshot(Box::new(state)),
+        Event::SpectrumData(SpectrumFrame {
+            sequence: 1_000,
+            sample_time: Duration::from_micros(85_333),
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+            source: 1,
+            discontinuity: Some(Discontinuity::SourceStall),
+            center_frequency: Hertz::mhz(144),
+            sample_rate: Hertz(48_000),
+            magnitudes: vec![0.0, 1.5, f32::MIN_POSITIVE],
+            peak_hold: Some(vec![0.5, 2.0, 1.0]),
+            min_hold: None,
+        }),
+        Event::Burst(Burst {
+            start: Duration::from_millis(1_250),
+            duration: Duration::from_micros(900),
+            interval: None,
+            peak_snr: Decibels(23.4),
+        }),
+        Event::Sstv(SstvEvent::Line {
+            line: 3,
+            pixels: vec![[255, 0, 128]; 320],
+        }),
+        Event::Track(report),
+        Event::PreviousSession(SessionRecord {
+            source_config: SourceConfig::default(),
+            center_frequency: Hertz::mhz(433),
+            gain: Decibels(-3.0),
+            recording: Some(PathBuf::from("/tmp/capture.sigmf-data")),
+        }),
+        Event::Capabilities(Capabilities {
+            version: "0.1.0".to_string(),
+            sources: vec![
+                SourceCapability {
+                    kind: SourceKind::File,
+                    max_sample_rate: Some(Hertz::mhz(20)),
+                    sample_rates: Vec::new(),
+                    devices: Vec::new(),
+                },
+                SourceCapability {
+                    kind: SourceKind::SoapySdr,
+                    max_sample_rate: Some(Hertz::mhz(20)),
+                    sample_rates: Vec::new(),
+                    devices: vec![SourceDevice {
+                        args: "driver=hackrf,serial=1234".to_string(),
+                        label: "HackRF One".to_string(),
+                        antennas: vec!["TX/RX".to_string()],
+                        gain_stages: vec![GainStage {
+                            name: "VGA".to_string(),
+                            min: Decibels(0.0),
+                            max: Decibels(62.0),
+                        }],
+                        bandwidth: Some(FrequencyRange {
+                            start: Hertz(1_750_000),
+                            end: Hertz::mhz(28),
+                        }),
+                        sample_rates: vec![Hertz::mhz(2), Hertz::mhz(8), Hertz::mhz(20)],
+                    }],
+                },
+            ],
+            demod_modes: vec![DemodMode::Fm],
+            features: vec![Feature::AdsbDecoder, Feature::Rotator],
+        }),
+        Event::SelfTest(SelfTestReport {
+            tone_frequency: Hertz(1_500),
+            tone_level: Decibels(-20.0),
+            tolerance: Decibels(1.0),
+            checks: vec![StageCheck {
+                stage: Stage::Audio,
+                level: Some(Decibels(-20.4)),
+                frequency: None,
+                passed: false,
+            }],
+        }),
+        Event::SymbolRate(SymbolRateEstimate {
+            region: SignalRegion {
+                frequency: Hertz(10_000),
+                bandwidth: Hertz(2_400),
+            },
+            candidates: vec![SymbolRateCandidate {
+                rate: 1_200.5,
+                strength: Decibels(18.0),
+            }],
+        }),
+        Event::AudioChunk(AudioChunk {
+            sample_rate: 24_000.0,
+            samples: vec![0.0, 0.5, -0.25, 0.125],
+            stereo: true,
+        }),
+        Event::RecordingStatus(RecordingStatus {
+            path: PathBuf::from("/data/pass.sigmf-data"),
+            bytes_written: 8 << 20,
+            active: true,
+        }),
+        Event::ReducedSpectrum(ReducedSpectrum {
+            frame: SpectrumFrame {
+                sequence: 7,
+                sample_time: Duration::from_millis(40),
+                timestamp: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                source: 0,
+                discontinuity: None,
+                center_frequency: Hertz::mhz(433),
+                sample_rate: Hertz(2_048_000),
+                magnitudes: Vec::new(),
+                peak_hold: None,
+                min_hold: None,
+            },
+            bins: 2048,
+            merge: 4,
+            delta: true,
+            levels: vec![0x28, 0xb5, 0x2f, 0xfd],
+        }),
+        Event::RecordingOverview(RecordingOverview {
+            path: PathBuf::from("/data/pass.cu8"),
+            sample_rate: Hertz(2_048_000),
+            duration: Duration::from_secs(600),
+            row_duration: Duration::from_millis(750),
+            rows: vec![vec![1e-6, 0.25, 1e-6], vec![2e-6, 0.5, 1e-6]],
+        }),
+        Event::Error(RustIqError::Source {
+            kind: SourceKind::RtlTcp,
+            detail: "connecting to sdr.local:1234: connection refused".to_string(),
+        }),
+        Event::Error(RustIqError::Device {
+            device: "Antenna switch".to_string(),
+            detail: "no such device".to_string(),
+        }),
+        Event::Error(RustIqError::Format {
+            path: PathBuf::from("/data/pass.wav"),
+            detail: "not a RIFF file".to_string(),
+        }),
+        Event::Error(RustIqError::Protocol {
+            peer: "sdr.local:5555".to_string(),
+            detail: "not a SpyServer".to_string(),
+        }),
+        Event::Error(RustIqError::Limit {
+            resource: Resource::Disk,
+            detail: "stopped recording to /data/pass.sigmf-data at 100 MB".to_string(),
+        }),
+        Event::SquelchState(true),
+        Event::SquelchState(false),
+        Event::Scanning {
+            channel: 1,
+            active: true,
+        },
+        Event::ScanHit(ScanHit {
+            channel: 1,
+            name: "Marine Ch 16".to_string(),
+            frequency: Hertz(156_800_000),
+            demod: DemodMode::Fm,
+            duration: Duration::from_millis(4_250),
+            level: Decibels(-38.5),
+            class: Some(SignalClass::ConstantEnvelope),
+        }),
+        Event::VfoSquelch { id: 2, open: true },
+        Event::RdsData(RdsData {
+            pi: 0xC201,
+            station: Some("RUSTIQ".to_string()),
+            radiotext: None,
+        }),
+        Event::DarkFrameCaptured(Decibels(-97.5)),
+        Event::Stats {
+            frames_dropped: 42,
+            fps: 23.5,
+        },
+        Event::PlaybackPosition(Duration::from_millis(61_250)),
+    ]);
+}
+
+#[test]
+fn test_settings_bundle_round_trip() {
+    let bundle = SettingsBundle {
+        source_config: SourceConfig::File {
+            path: PathBuf::from("/data/capture.sigmf-meta"),
+            sample_rate: Hertz(2_048_000),
+            format: IqFormat::Cf32,
+        },
+        center_frequency: Hertz::mhz(433),
+        gain: Decibels(12.5),
+        gain_profiles: vec![GainProfile {
+            range: FrequencyRange::new(Hertz::mhz(88), Hertz::mhz(108)),
+            gain: Decibels(-10.0),
+        }],
+        antenna_switch: Some(AntennaSwitchConfig {
+            link: AntennaSwitchLink::Gpio { pins: vec![17] },
+            antennas: vec![Antenna {
+                name: "Yagi".to_string(),
+                command: String::new(),
+            }],
+            rules: Vec::new(),
+        }),
+        rig: Some(RigConfig {
+            address: "localhost:4532".to_string(),
+            if_frequency: Some(Hertz(73_095_000)),
+        }),
+        rotator: None,
+        alert_rules: vec!["12???".to_string(), "5*".to_string()],
+        scan_list: scan_list(),
+    };
+    let mut stream = Vec::new();
+    write_frame(&mut stream, &bundle).unwrap();
+    let decoded: SettingsBundle = read_frame(&mut Cursor::new(stream)).unwrap();
+    assert_eq!(decoded, bundle);
+}
+
+#[test]
+fn test_last_session_round_trip() {
+    let session = LastSession {
+        source_config: SourceConfig::default(),
+        center_frequency: Hertz(98_500_000),
+        gain: Decibels(20.0),
+        fft_size: 4096,
+        listening: Some(AudioChannel {
+            frequency: Hertz(98_500_000),
+            demod: DemodMode::Wfm,
+        }),
+        squelch: Some(Decibels(-45.0)),
+        passband: None,
+        volume: 0.8,
+        colormap: "Viridis".to_string(),
+        window: Some(WindowGeometry {
+            size: (1280.0, 800.0),
+            position: Some((40.0, 60.0)),
+        }),
+        annotations: vec![Annotation {
+            shape: AnnotationShape::Arrow,
+            start: AnnotationPoint {
+                frequency: Hertz(98_450_000),
+                time: Duration::from_millis(1_500),
+            },
+            end: AnnotationPoint {
+                frequency: Hertz(98_480_000),
+                time: Duration::from_millis(2_250),
+            },
+            text: "pilot".to_string(),
+        }],
+    };
+    let mut stream = Vec::new();
+    write_frame(&mut stream, &session).unwrap();
+    let decoded: LastSession = read_frame(&mut Cursor::new(stream)).unwrap();
+    assert_eq!(decoded, session);
+}
+
+#[test]
+fn test_command_macros_round_trip() {
+    round_trip(vec![vec![
+        CommandMacro {
+            name: "Check 2m".to_string(),
+            hotkey: Some(1),
+            commands: vec![
+                Command::SetCenterFrequency(Hertz::mhz(145)),
+                Command::SetFftSize(8_192),
+                Command::StartRecording {
+                    path: PathBuf::from("/data/2m"),
+                    format: RecordingFormat::Raw,
+                },
+            ],
+        },
+        CommandMacro {
+            name: "Quiet".to_string(),
+            hotkey: None,
+            commands: vec![Command::StopRecording],
+        },
+    ]]);
+}
+
+#[test]
+fn test_rejects_corrupt_frames() {
+    // An unknown command tag
+    let mut stream = 1u32.to_le_bytes().to_vec();
+    stream.push(255);
+    let err = read_frame::<Command>(&mut Cursor::new(stream)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // A vector claiming more items than the frame holds
+    let mut stream = 9u32.to_le_bytes().to_vec();
+    stream.push(1);
+    stream.extend_from_slice(&u64::MAX.to_le_bytes());
+    let err = read_frame::<Event>(&mut Cursor::new(stream)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,242 @@
+use eframe::egui::{
+    Align2, Color32, FontId, PointerButton, Pos2, Rect, Response, Stroke, StrokeKind, TextEdit, Ui,
+    Widget,
+};
+
+use rustiq_messages::{Annotation, AnnotationPoint, AnnotationShape, Hertz};
+
+use crate::measurement::Point;
+use crate::waterfall::Waterfall;
+
+/// Color annotations are drawn in, apart from the cursor and measurement.
+const COLOR: Color32 = Color32::from_rgb(255, 96, 255);
+
+/// Labels, arrows and boxes drawn on the waterfall by hand, e.g. to point
+/// out a signal in a screenshot. They are a layer of their own, apart from
+/// the markers of what the engine detects, and are saved with the session.
+///
+/// While a tool is picked, drags across the waterfall draw with it instead
+/// of measuring, and a click places a label instead of tuning.
+pub struct AnnotationPanel {
+    annotations: Vec<Annotation>,
+    /// Shape drags draw, if any
+    tool: Option<AnnotationShape>,
+    /// Text given to the next annotation
+    text: String,
+    visible: bool,
+    /// Annotation being dragged out
+    drawing: Option<Annotation>,
+}
+
+impl AnnotationPanel {
+    pub fn new() -> Self {
+        Self {
+            annotations: Vec::new(),
+            tool: None,
+            text: String::new(),
+            visible: true,
+            drawing: None,
+        }
+    }
+
+    /// Whether an annotation tool is picked, so the waterfall is left to it.
+    pub fn annotating(&self) -> bool {
+        self.tool.is_some()
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn set_annotations(&mut self, annotations: Vec<Annotation>) {
+        self.annotations = annotations;
+    }
+
+    /// Draw with the picked tool from a drag or click on the waterfall
+    /// `view`, whose response is `response`.
+    pub fn update(&mut self, ui: &Ui, response: &Response, view: &Waterfall) {
+        let Some(shape) = self.tool else {
+            return;
+        };
+        let point_at = |pos| view.point_at(response.rect, pos).and_then(annotation_point);
+        if response.drag_started_by(PointerButton::Primary) {
+            let origin = ui.input(|input| input.pointer.press_origin());
+            self.drawing = origin.and_then(point_at).map(|start| Annotation {
+                shape,
+                start,
+                end: start,
+                text: self.text.trim().to_string(),
+            });
+        } else if response.dragged_by(PointerButton::Primary)
+            && let Some(end) = response.interact_pointer_pos().and_then(point_at)
+            && let Some(drawing) = &mut self.drawing
+        {
+            drawing.end = end;
+        }
+        if response.drag_stopped_by(PointerButton::Primary)
+            && let Some(annotation) = self.drawing.take()
+        {
+            self.add(annotation);
+        } else if response.clicked()
+            && shape == AnnotationShape::Label
+            && let Some(start) = response.interact_pointer_pos().and_then(point_at)
+        {
+            self.add(Annotation {
+                shape,
+                start,
+                end: start,
+                text: self.text.trim().to_string(),
+            });
+        }
+    }
+
+    /// Keep `annotation`, unless it is a label without text.
+    fn add(&mut self, annotation: Annotation) {
+        if annotation.shape != AnnotationShape::Label || !annotation.text.is_empty() {
+            self.annotations.push(annotation);
+        }
+    }
+
+    /// Draw the annotations over the waterfall `view` drawn in `rect`. Each
+    /// stays on the lines it was drawn on as the waterfall scrolls, and is
+    /// left out once they scroll away.
+    pub fn show(&self, ui: &Ui, rect: Rect, view: &Waterfall) {
+        if !self.visible {
+            return;
+        }
+        let painter = ui.painter_at(rect);
+        let stroke = Stroke::new(1.5, COLOR);
+        let pos = |point: &AnnotationPoint| {
+            Some(Pos2::new(
+                view.position_of(rect, point.frequency.as_hz() as f64)?,
+                view.line_y(rect, point.time)?,
+            ))
+        };
+        for annotation in self.annotations.iter().chain(&self.drawing) {
+            let (Some(start), Some(end)) = (pos(&annotation.start), pos(&annotation.end)) else {
+                continue;
+            };
+            let anchor = match annotation.shape {
+                AnnotationShape::Label => {
+                    painter.circle_filled(start, 2.5, COLOR);
+                    start + [6.0, 0.0].into()
+                }
+                AnnotationShape::Arrow => {
+                    painter.arrow(start, end - start, stroke);
+                    start + [6.0, 0.0].into()
+                }
+                AnnotationShape::Box => {
+                    let bounds = Rect::from_two_pos(start, end);
+                    painter.rect_stroke(bounds, 0.0, stroke, StrokeKind::Middle);
+                    bounds.left_top() + [2.0, -2.0].into()
+                }
+            };
+            let align = match annotation.shape {
+                AnnotationShape::Box => Align2::LEFT_BOTTOM,
+                _ => Align2::LEFT_CENTER,
+            };
+            painter.text(
+                anchor,
+                align,
+                &annotation.text,
+                FontId::proportional(13.0),
+                COLOR,
+            );
+        }
+    }
+}
+
+/// Where an annotation drawn at `point` is kept.
+fn annotation_point(point: Point) -> Option<AnnotationPoint> {
+    (point.frequency >= 0.0).then(|| AnnotationPoint {
+        frequency: Hertz(point.frequency.round() as u64),
+        time: point.time,
+    })
+}
+
+impl Widget for &mut AnnotationPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Annotations");
+        ui.separator();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.selectable_value(&mut self.tool, None, "Measure")
+                .on_hover_text("Drags across the waterfall measure between their ends");
+            for shape in AnnotationShape::ALL {
+                ui.selectable_value(&mut self.tool, Some(shape), shape.label())
+                    .on_hover_text(match shape {
+                        AnnotationShape::Label => "Click the waterfall to place the text",
+                        AnnotationShape::Arrow => "Drag across the waterfall to draw an arrow",
+                        AnnotationShape::Box => "Drag across the waterfall to draw a box",
+                    });
+            }
+        });
+        if !self.annotating() {
+            self.drawing = None;
+        }
+        ui.add(TextEdit::singleline(&mut self.text).hint_text("Text"));
+        ui.checkbox(&mut self.visible, "Show annotations");
+
+        let mut removed = None;
+        for (i, annotation) in self.annotations.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let mut label = format!(
+                    "{} at {}",
+                    annotation.shape.label(),
+                    annotation.start.frequency
+                );
+                if !annotation.text.is_empty() {
+                    label += &format!(": {}", annotation.text);
+                }
+                ui.label(label);
+                if ui
+                    .small_button("✖")
+                    .on_hover_text("Remove the annotation")
+                    .clicked()
+                {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            self.annotations.remove(i);
+        }
+        if !self.annotations.is_empty() && ui.button("Clear annotations").clicked() {
+            self.annotations.clear();
+        }
+
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn point(frequency: u64) -> AnnotationPoint {
+        AnnotationPoint {
+            frequency: Hertz(frequency),
+            time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn keeps_only_labels_with_text() {
+        let mut panel = AnnotationPanel::new();
+        for (shape, text) in [
+            (AnnotationShape::Label, ""),
+            (AnnotationShape::Label, "beacon"),
+            (AnnotationShape::Arrow, ""),
+        ] {
+            panel.add(Annotation {
+                shape,
+                start: point(1_000),
+                end: point(2_000),
+                text: text.to_string(),
+            });
+        }
+        let shapes: Vec<_> = panel.annotations().iter().map(|a| a.shape).collect();
+        assert_eq!(shapes, [AnnotationShape::Label, AnnotationShape::Arrow]);
+    }
+}
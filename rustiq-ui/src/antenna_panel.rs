@@ -0,0 +1,392 @@
+use eframe::egui::{ComboBox, DragValue, Grid, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rustiq_messages::{
+    Antenna, AntennaRule, AntennaSwitchConfig, AntennaSwitchLink, Command, DarkFrameConfig,
+    Decibels, FrequencyRange, Hertz,
+};
+
+/// Links offered in the link dropdown, with their starting settings.
+fn default_links() -> [AntennaSwitchLink; 3] {
+    [
+        AntennaSwitchLink::Serial {
+            path: PathBuf::from("/dev/ttyUSB0"),
+        },
+        AntennaSwitchLink::Network {
+            address: "192.168.1.100:6000".to_string(),
+        },
+        AntennaSwitchLink::Gpio { pins: vec![17, 27] },
+    ]
+}
+
+/// External antenna switch panel: the link to the switch, its antennas, the
+/// frequency ranges each antenna is selected for on retune, and the schedule
+/// dark frames are captured on through a port with a termination.
+pub struct AntennaPanel {
+    cmd_tx: Sender<Command>,
+    /// Configuration being edited
+    config: AntennaSwitchConfig,
+    /// GPIO pins as typed, comma separated
+    pins_text: String,
+    /// Configuration the engine is currently using
+    active: Option<AntennaSwitchConfig>,
+    /// Antenna the engine last selected
+    selected: Option<usize>,
+    /// Dark frame schedule being edited
+    dark_frame: DarkFrameConfig,
+    /// Dark frame schedule the engine is keeping
+    active_dark_frame: Option<DarkFrameConfig>,
+    /// Level of the latest dark frame
+    dark_frame_level: Option<Decibels>,
+}
+
+impl AntennaPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        let config = AntennaSwitchConfig {
+            link: default_links()[0].clone(),
+            antennas: vec![Antenna {
+                name: "Antenna 1".to_string(),
+                command: r"1\r".to_string(),
+            }],
+            rules: Vec::new(),
+        };
+        Self {
+            cmd_tx,
+            config,
+            pins_text: String::new(),
+            active: None,
+            selected: None,
+            dark_frame: DarkFrameConfig {
+                termination: 0,
+                interval: Duration::from_secs(3_600),
+                frames: 64,
+            },
+            active_dark_frame: None,
+            dark_frame_level: None,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(
+        &mut self,
+        antenna_switch: Option<&AntennaSwitchConfig>,
+        antenna: Option<usize>,
+        dark_frame: Option<DarkFrameConfig>,
+    ) {
+        if let Some(config) = antenna_switch {
+            self.config = config.clone();
+            if let AntennaSwitchLink::Gpio { pins } = &config.link {
+                self.pins_text = pins_to_text(pins);
+            }
+        }
+        self.active = antenna_switch.cloned();
+        self.selected = antenna;
+        if let Some(config) = dark_frame {
+            self.dark_frame = config;
+        } else {
+            self.dark_frame_level = None;
+        }
+        self.active_dark_frame = dark_frame;
+    }
+
+    /// Note the level of the dark frame just captured.
+    pub fn set_dark_frame_level(&mut self, level: Decibels) {
+        self.dark_frame_level = Some(level);
+    }
+
+    fn send_apply(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::SetAntennaSwitch(Some(self.config.clone())));
+    }
+
+    fn send_disable(&self) {
+        let _ = self.cmd_tx.send(Command::SetAntennaSwitch(None));
+    }
+
+    fn send_select(&self, index: usize) {
+        let _ = self.cmd_tx.send(Command::SelectAntenna(index));
+    }
+
+    fn antenna_name(&self, index: usize) -> &str {
+        self.config
+            .antennas
+            .get(index)
+            .map_or("?", |antenna| antenna.name.as_str())
+    }
+
+    fn link_ui(&mut self, ui: &mut Ui) {
+        ComboBox::from_label("Link")
+            .selected_text(self.config.link.label())
+            .show_ui(ui, |ui| {
+                for link in default_links() {
+                    let selected = link.label() == self.config.link.label();
+                    if ui.selectable_label(selected, link.label()).clicked() && !selected {
+                        if let AntennaSwitchLink::Gpio { pins } = &link {
+                            self.pins_text = pins_to_text(pins);
+                        }
+                        self.config.link = link;
+                    }
+                }
+            });
+
+        ui.horizontal(|ui| match &mut self.config.link {
+            AntennaSwitchLink::Serial { path } => {
+                ui.label("Port:");
+                let mut path_str = path.display().to_string();
+                if ui.text_edit_singleline(&mut path_str).changed() {
+                    *path = PathBuf::from(path_str);
+                }
+            }
+            AntennaSwitchLink::Network { address } => {
+                ui.label("Address:");
+                ui.add(TextEdit::singleline(address).hint_text("host:port"));
+            }
+            AntennaSwitchLink::Gpio { pins } => {
+                ui.label("Pins:");
+                if ui
+                    .add(TextEdit::singleline(&mut self.pins_text).hint_text("17, 27"))
+                    .changed()
+                {
+                    *pins = self
+                        .pins_text
+                        .split(',')
+                        .filter_map(|pin| pin.trim().parse().ok())
+                        .collect();
+                }
+            }
+        });
+    }
+
+    fn antennas_ui(&mut self, ui: &mut Ui) {
+        let uses_commands = !matches!(self.config.link, AntennaSwitchLink::Gpio { .. });
+        let can_select = self.active.as_ref() == Some(&self.config);
+        let mut remove = None;
+        let mut select = None;
+        Grid::new("antennas").striped(true).show(ui, |ui| {
+            ui.label("Antenna");
+            if uses_commands {
+                ui.label("Command");
+            }
+            ui.end_row();
+            for (i, antenna) in self.config.antennas.iter_mut().enumerate() {
+                ui.add(TextEdit::singleline(&mut antenna.name).desired_width(80.0));
+                if uses_commands {
+                    ui.add(
+                        TextEdit::singleline(&mut antenna.command)
+                            .desired_width(80.0)
+                            .hint_text(r"ANT 1\r"),
+                    );
+                }
+                let label = if self.selected == Some(i) {
+                    "Selected"
+                } else {
+                    "Select"
+                };
+                ui.add_enabled_ui(can_select && self.selected != Some(i), |ui| {
+                    if ui.button(label).clicked() {
+                        select = Some(i);
+                    }
+                });
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = select {
+            self.send_select(i);
+        }
+        if let Some(i) = remove {
+            self.config.antennas.remove(i);
+            // Keep rules pointing at the same antennas
+            self.config.rules.retain(|rule| rule.antenna != i);
+            for rule in &mut self.config.rules {
+                if rule.antenna > i {
+                    rule.antenna -= 1;
+                }
+            }
+        }
+        if ui.button("Add antenna").clicked() {
+            let n = self.config.antennas.len() + 1;
+            self.config.antennas.push(Antenna {
+                name: format!("Antenna {n}"),
+                command: format!(r"{n}\r"),
+            });
+        }
+    }
+
+    fn rules_ui(&mut self, ui: &mut Ui) {
+        let names: Vec<String> = self
+            .config
+            .antennas
+            .iter()
+            .map(|antenna| antenna.name.clone())
+            .collect();
+        let mut remove = None;
+        Grid::new("antenna_rules").striped(true).show(ui, |ui| {
+            ui.label("From");
+            ui.label("To");
+            ui.label("Antenna");
+            ui.end_row();
+            for (i, rule) in self.config.rules.iter_mut().enumerate() {
+                ui.add(
+                    DragValue::new(&mut rule.range.start.0)
+                        .speed(1000)
+                        .suffix(" Hz"),
+                );
+                ui.add(
+                    DragValue::new(&mut rule.range.end.0)
+                        .speed(1000)
+                        .suffix(" Hz"),
+                );
+                ComboBox::from_id_salt(("antenna_rule", i))
+                    .selected_text(names.get(rule.antenna).map_or("?", String::as_str))
+                    .show_ui(ui, |ui| {
+                        for (index, name) in names.iter().enumerate() {
+                            ui.selectable_value(&mut rule.antenna, index, name);
+                        }
+                    });
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.config.rules.remove(i);
+        }
+        ui.add_enabled_ui(!names.is_empty(), |ui| {
+            if ui.button("Add rule").clicked() {
+                self.config.rules.push(AntennaRule {
+                    range: FrequencyRange::new(Hertz::mhz(1), Hertz::mhz(30)),
+                    antenna: 0,
+                });
+            }
+        });
+    }
+}
+
+impl AntennaPanel {
+    fn dark_frame_ui(&mut self, ui: &mut Ui) {
+        let config = &mut self.dark_frame;
+        Grid::new("dark_frame").show(ui, |ui| {
+            ui.label("Termination");
+            let name = self
+                .config
+                .antennas
+                .get(config.termination)
+                .map_or("?", |antenna| antenna.name.as_str());
+            ComboBox::from_id_salt("dark_frame_termination")
+                .selected_text(name)
+                .show_ui(ui, |ui| {
+                    for (index, antenna) in self.config.antennas.iter().enumerate() {
+                        ui.selectable_value(&mut config.termination, index, &antenna.name);
+                    }
+                })
+                .response
+                .on_hover_text("The port with a dummy load on it");
+            ui.end_row();
+            ui.label("Every");
+            let mut minutes = config.interval.as_secs_f64() / 60.0;
+            if ui
+                .add(
+                    DragValue::new(&mut minutes)
+                        .range(1.0..=10_080.0)
+                        .suffix(" min"),
+                )
+                .changed()
+            {
+                config.interval = Duration::from_secs_f64(minutes * 60.0);
+            }
+            ui.end_row();
+            ui.label("Frames");
+            ui.add(DragValue::new(&mut config.frames).range(1..=1_024));
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            let changed = self.active_dark_frame != Some(self.dark_frame);
+            let label = if self.active_dark_frame.is_some() {
+                "Apply"
+            } else {
+                "Capture dark frames"
+            };
+            if ui
+                .add_enabled(changed, eframe::egui::Button::new(label))
+                .on_hover_text(
+                    "Switch to the termination on this schedule, and subtract what the \
+                     front end shows there from the spectrum",
+                )
+                .clicked()
+            {
+                let _ = self
+                    .cmd_tx
+                    .send(Command::SetDarkFrame(Some(self.dark_frame)));
+            }
+            if self.active_dark_frame.is_some() && ui.button("Stop").clicked() {
+                let _ = self.cmd_tx.send(Command::SetDarkFrame(None));
+            }
+        });
+        if self.active_dark_frame.is_some() {
+            match self.dark_frame_level {
+                Some(level) => ui.label(format!("Last dark frame: {:.1} dB", level.0)),
+                None => ui.label("Waiting for the first dark frame"),
+            };
+        }
+    }
+}
+
+fn pins_to_text(pins: &[u32]) -> String {
+    pins.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Widget for &mut AntennaPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Antenna Switch");
+        ui.separator();
+
+        self.link_ui(ui);
+        ui.add_space(5.0);
+        self.antennas_ui(ui);
+        ui.add_space(5.0);
+        ui.label("Rules:");
+        self.rules_ui(ui);
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let changed = self.active.as_ref() != Some(&self.config);
+            let label = if self.active.is_some() {
+                "Apply"
+            } else {
+                "Enable"
+            };
+            ui.add_enabled_ui(changed, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_apply();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Disable").clicked() {
+                    self.send_disable();
+                }
+            });
+        });
+
+        if self.active.is_some() {
+            match self.selected {
+                Some(index) => ui.label(format!("Selected: {}", self.antenna_name(index))),
+                None => ui.label("Selected: unknown"),
+            };
+            ui.add_space(5.0);
+            ui.label("Dark frames:");
+            self.dark_frame_ui(ui);
+        }
+
+        ui.response()
+    }
+}
@@ -0,0 +1,154 @@
+//! Playing the engine's audio on the default output device.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BuildStreamError, Device, FromSample, OutputCallbackInfo, SampleFormat, SizedSample, Stream,
+    StreamConfig,
+};
+use log::{debug, warn};
+use rustiq_messages::AudioChunk;
+
+use crate::equalizer::{EqSettings, Equalizer};
+use crate::resample::Resampler;
+
+/// Seconds of audio queued before playing starts, and again after running
+/// dry, riding out the gaps between frames that deliver it.
+const LATENCY: f64 = 0.1;
+
+/// Most seconds of audio queued. Beyond this the oldest is dropped, so a
+/// source running faster than the device doesn't build up lag.
+const MAX_QUEUED: f64 = 0.3;
+
+/// Left and right samples waiting for the device, and whether it is taking
+/// them.
+#[derive(Default)]
+struct Queue {
+    frames: VecDeque<[f32; 2]>,
+    playing: bool,
+}
+
+type SharedQueue = Arc<Mutex<Queue>>;
+
+/// The default output device, playing audio until dropped. Stereo goes to
+/// the first two channels of a device with them, and mono to all of them.
+pub struct AudioOutput {
+    /// Kept for as long as it plays
+    _stream: Stream,
+    queue: SharedQueue,
+    sample_rate: f64,
+    /// Rate of the audio being resampled, and its left and right resamplers
+    resampler: Option<(f64, [Resampler; 2])>,
+    /// Equalizer for the last settings and rate played
+    equalizer: Option<Equalizer>,
+}
+
+impl AudioOutput {
+    /// Open the default output device at its preferred rate.
+    pub fn open() -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let format = supported.sample_format();
+        let config: StreamConfig = supported.into();
+        let queue = SharedQueue::default();
+        let stream = match format {
+            SampleFormat::F32 => build::<f32>(&device, &config, queue.clone()),
+            SampleFormat::I16 => build::<i16>(&device, &config, queue.clone()),
+            SampleFormat::U16 => build::<u16>(&device, &config, queue.clone()),
+            other => return Err(format!("unsupported sample format {other}")),
+        }
+        .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+        let sample_rate = config.sample_rate.0 as f64;
+        debug!("Opened the audio output at {} Hz", sample_rate);
+        Ok(Self {
+            _stream: stream,
+            queue,
+            sample_rate,
+            resampler: None,
+            equalizer: None,
+        })
+    }
+
+    /// Queue a chunk of audio, equalized as `eq` says and scaled by
+    /// `volume`.
+    pub fn play(&mut self, chunk: &AudioChunk, volume: f32, eq: &EqSettings) {
+        let equalizer = match &mut self.equalizer {
+            Some(equalizer) if equalizer.is_for(eq, chunk.sample_rate) => equalizer,
+            _ => self
+                .equalizer
+                .insert(Equalizer::new(*eq, chunk.sample_rate)),
+        };
+        let mut samples = chunk.samples.clone();
+        equalizer.process(&mut samples, chunk.stereo);
+
+        let [left, right] = match &mut self.resampler {
+            Some((rate, resamplers)) if *rate == chunk.sample_rate => resamplers,
+            _ => {
+                let resamplers =
+                    [(); 2].map(|_| Resampler::new(chunk.sample_rate, self.sample_rate));
+                &mut self.resampler.insert((chunk.sample_rate, resamplers)).1
+            }
+        };
+        let (left, right) = if chunk.stereo {
+            let channel =
+                |offset| -> Vec<f32> { samples.iter().skip(offset).step_by(2).copied().collect() };
+            (left.process(&channel(0)), right.process(&channel(1)))
+        } else {
+            (left.process(&samples), right.process(&samples))
+        };
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.frames.extend(
+            left.iter()
+                .zip(&right)
+                .map(|(left, right)| [left * volume, right * volume]),
+        );
+        let excess = queue
+            .frames
+            .len()
+            .saturating_sub((MAX_QUEUED * self.sample_rate) as usize);
+        queue.frames.drain(..excess);
+    }
+}
+
+fn build<T: SizedSample + FromSample<f32>>(
+    device: &Device,
+    config: &StreamConfig,
+    queue: SharedQueue,
+) -> Result<Stream, BuildStreamError> {
+    let channels = config.channels as usize;
+    let latency = (LATENCY * config.sample_rate.0 as f64) as usize;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &OutputCallbackInfo| {
+            let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+            queue.playing |= queue.frames.len() >= latency;
+            for frame in data.chunks_mut(channels) {
+                let samples = if queue.playing {
+                    queue.frames.pop_front()
+                } else {
+                    None
+                };
+                // Silence until enough is queued again
+                queue.playing &= samples.is_some();
+                let [left, right] = samples.unwrap_or_default().map(|s| s.clamp(-1.0, 1.0));
+                let mono = T::from_sample((left + right) / 2.0);
+                match frame {
+                    [only] => *only = mono,
+                    [first, second, rest @ ..] => {
+                        *first = T::from_sample(left);
+                        *second = T::from_sample(right);
+                        rest.fill(mono);
+                    }
+                    [] => {}
+                }
+            }
+        },
+        |e| warn!("Audio output error: {}", e),
+        None,
+    )
+}
@@ -0,0 +1,467 @@
+use std::path::PathBuf;
+
+use eframe::egui::{
+    Button, CollapsingHeader, Color32, DragValue, Grid, Popup, PopupCloseBehavior, Response,
+    Slider, TextEdit, Ui, Widget,
+};
+use flume::Sender;
+use log::warn;
+
+use crate::audio::AudioOutput;
+use crate::equalizer::{EqPreset, EqSettings};
+use rustiq_messages::{
+    AudioChannel, AudioChunk, AudioRouting, Command, Decibels, DemodMode, Hertz, Passband, RdsData,
+    SignalRegion,
+};
+
+/// Squelch level offered until the user picks one.
+const DEFAULT_SQUELCH: Decibels = Decibels(-50.0);
+
+/// Audio panel: the channel the engine demodulates, played on this
+/// machine's default output device.
+///
+/// The device is opened with the first audio after listening starts and
+/// closed when it stops.
+pub struct AudioPanel {
+    cmd_tx: Sender<Command>,
+    /// Channel entered in the controls
+    channel: AudioChannel,
+    /// Channel the engine is currently demodulating
+    active: Option<AudioChannel>,
+    output: Option<AudioOutput>,
+    /// Why the output couldn't be opened, until listening starts again
+    output_error: Option<String>,
+    /// Linear gain applied before playing
+    volume: f32,
+    /// Shaping of the audio before playing
+    eq: EqSettings,
+    /// Signal spanned by the waterfall's measurement line, if there is one
+    selection: Option<SignalRegion>,
+    /// Modes the engine can demodulate
+    demod_modes: Vec<DemodMode>,
+    /// Where the audio goes, as entered in the routing matrix
+    speakers: bool,
+    record: bool,
+    recording_path: String,
+    stream: bool,
+    /// Network listeners, comma-separated `host:port` addresses
+    listeners: String,
+    /// Whether the last audio received was stereo
+    stereo: bool,
+    /// What the broadcast FM station sends over RDS, as last heard
+    rds: Option<RdsData>,
+    /// Level the engine squelches the audio at, if it does
+    squelch: Option<Decibels>,
+    /// Squelch level entered in the controls
+    squelch_level: Decibels,
+    /// Whether the squelch is letting the audio through
+    squelch_open: bool,
+    /// Passband the engine filters the channel through, if not its mode's
+    passband: Option<Passband>,
+}
+
+impl AudioPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            channel: AudioChannel {
+                frequency: Hertz(145_500_000),
+                demod: DemodMode::Fm,
+            },
+            active: None,
+            output: None,
+            output_error: None,
+            volume: 0.5,
+            eq: EqSettings::default(),
+            selection: None,
+            demod_modes: DemodMode::ALL.to_vec(),
+            speakers: true,
+            record: false,
+            recording_path: "listening.wav".to_string(),
+            stream: false,
+            listeners: String::new(),
+            stereo: false,
+            rds: None,
+            squelch: None,
+            squelch_level: DEFAULT_SQUELCH,
+            squelch_open: true,
+            passband: None,
+        }
+    }
+
+    pub fn set_demod_modes(&mut self, demod_modes: &[DemodMode]) {
+        self.demod_modes = demod_modes.to_vec();
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SignalRegion>) {
+        self.selection = selection;
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(
+        &mut self,
+        demodulator: Option<AudioChannel>,
+        routing: &AudioRouting,
+        squelch: Option<Decibels>,
+        passband: Option<Passband>,
+    ) {
+        if demodulator != self.active {
+            // Another station, if any
+            self.rds = None;
+        }
+        self.active = demodulator;
+        self.squelch = squelch;
+        self.passband = passband;
+        if let Some(level) = squelch {
+            self.squelch_level = level;
+        }
+        match demodulator {
+            Some(channel) => self.channel = channel,
+            None => {
+                self.output = None;
+                self.stereo = false;
+            }
+        }
+        self.speakers = routing.speakers;
+        self.record = routing.recording.is_some();
+        if let Some(path) = &routing.recording {
+            self.recording_path = path.display().to_string();
+        }
+        self.stream = !routing.network.is_empty();
+        if self.stream {
+            self.listeners = routing.network.join(", ");
+        }
+    }
+
+    /// The channel being listened to and the passband it is filtered
+    /// through, if one is.
+    pub fn passband(&self) -> Option<(AudioChannel, Passband)> {
+        let channel = self.active?;
+        Some((channel, self.passband.unwrap_or(channel.demod.passband())))
+    }
+
+    /// Filter the channel through `passband`, or its mode's.
+    pub fn set_passband(&mut self, passband: Option<Passband>) {
+        // Shown as sent until the engine says otherwise
+        self.passband = passband;
+        let _ = self.cmd_tx.send(Command::SetPassband(passband));
+    }
+
+    pub fn set_squelch_open(&mut self, open: bool) {
+        self.squelch_open = open;
+    }
+
+    /// Linear gain the audio is played at.
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_rds(&mut self, data: RdsData) {
+        self.rds = Some(data);
+    }
+
+    /// Whether the channel being listened to plays out of the speakers
+    /// here.
+    pub fn plays_here(&self) -> bool {
+        self.active.is_some() && self.speakers
+    }
+
+    /// Play audio from the engine, opening the output if need be.
+    pub fn play(&mut self, chunk: &AudioChunk) {
+        // Audio still in flight when listening stopped
+        if self.active.is_none() {
+            return;
+        }
+        self.stereo = chunk.stereo;
+        if self.output_error.is_some() {
+            return;
+        }
+        if self.output.is_none() {
+            match AudioOutput::open() {
+                Ok(output) => self.output = Some(output),
+                Err(e) => {
+                    warn!("Failed to open the audio output: {}", e);
+                    self.output_error = Some(e);
+                    return;
+                }
+            }
+        }
+        if let Some(output) = &mut self.output {
+            output.play(chunk, self.volume, &self.eq);
+        }
+    }
+
+    fn send_listen(&mut self) {
+        self.output_error = None;
+        let _ = self
+            .cmd_tx
+            .send(Command::SetDemodulator(Some(self.channel)));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::SetDemodulator(None));
+    }
+
+    fn send_routing(&self) {
+        let routing = AudioRouting {
+            speakers: self.speakers,
+            recording: self
+                .record
+                .then(|| PathBuf::from(self.recording_path.trim())),
+            network: if self.stream {
+                self.listeners
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|address| !address.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            } else {
+                Vec::new()
+            },
+        };
+        let _ = self.cmd_tx.send(Command::SetAudioRouting(routing));
+    }
+
+    /// The equalizer's preset, filters and peaking band.
+    fn eq_ui(&mut self, ui: &mut Ui) {
+        Grid::new("audio_eq").num_columns(2).show(ui, |ui| {
+            ui.label("Preset:");
+            // None lit once the settings are changed from a preset
+            let preset = EqPreset::matching(&self.eq);
+            ui.horizontal(|ui| {
+                for choice in EqPreset::ALL {
+                    if ui
+                        .selectable_label(preset == Some(choice), choice.label())
+                        .clicked()
+                    {
+                        self.eq = choice.settings();
+                    }
+                }
+            });
+            ui.end_row();
+            corner_ui(ui, "High-pass", &mut self.eq.high_pass, 300.0);
+            corner_ui(ui, "Low-pass", &mut self.eq.low_pass, 3_000.0);
+            ui.label("Peak:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    DragValue::new(&mut self.eq.peak_frequency)
+                        .speed(10)
+                        .range(20.0..=20_000.0)
+                        .suffix(" Hz"),
+                );
+                ui.add(
+                    DragValue::new(&mut self.eq.peak_gain)
+                        .speed(0.1)
+                        .range(-12.0..=12.0)
+                        .suffix(" dB"),
+                );
+                ui.add(
+                    DragValue::new(&mut self.eq.peak_q)
+                        .speed(0.05)
+                        .range(0.1..=10.0)
+                        .prefix("Q "),
+                );
+            });
+            ui.end_row();
+        });
+    }
+
+    /// Which of the outputs the channel goes to, with where the recording
+    /// and the stream go.
+    fn routing_ui(&mut self, ui: &mut Ui) {
+        Grid::new("audio_routing").num_columns(2).show(ui, |ui| {
+            ui.checkbox(&mut self.speakers, "Speakers");
+            ui.end_row();
+            ui.checkbox(&mut self.record, "Record to");
+            ui.add(TextEdit::singleline(&mut self.recording_path).desired_width(140.0));
+            ui.end_row();
+            ui.checkbox(&mut self.stream, "Stream to");
+            ui.add(
+                TextEdit::singleline(&mut self.listeners)
+                    .hint_text("host:port, …")
+                    .desired_width(140.0),
+            );
+            ui.end_row();
+        });
+        if ui
+            .button("Route audio")
+            .on_hover_text("Send the channel's audio to the outputs ticked")
+            .clicked()
+        {
+            self.send_routing();
+        }
+    }
+}
+
+impl Widget for &mut AudioPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Audio");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Frequency:");
+            let mut freq = self.channel.frequency.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                .changed()
+            {
+                self.channel.frequency.0 = freq;
+            }
+        });
+
+        // Switching modes while listening switches the engine's too
+        ui.horizontal(|ui| {
+            ui.label("Mode:");
+            for demod in self.demod_modes.clone() {
+                let bandwidth = demod.bandwidth().0 as f64 / 1e3;
+                if ui
+                    .selectable_label(self.channel.demod == demod, demod.label())
+                    .on_hover_text(format!("{bandwidth} kHz channel filter"))
+                    .clicked()
+                    && self.channel.demod != demod
+                {
+                    self.channel.demod = demod;
+                    if self.active.is_some() {
+                        self.send_listen();
+                    }
+                }
+            }
+        });
+
+        if let Some((_, passband)) = self.passband() {
+            ui.horizontal(|ui| {
+                ui.label("Filter:");
+                ui.weak(format!("{} to {} Hz", passband.low, passband.high))
+                    .on_hover_text("Drag its edges on the spectrum or waterfall");
+                if ui
+                    .add_enabled(self.passband.is_some(), Button::new("Reset"))
+                    .on_hover_text("Back to the mode's filter")
+                    .clicked()
+                {
+                    self.set_passband(None);
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Volume:");
+            ui.add(Slider::new(&mut self.volume, 0.0..=1.0).show_value(false));
+            let eq = ui
+                .button("EQ")
+                .on_hover_text("Filters and presets for comfortable listening");
+            // Stays open while the settings are changed
+            Popup::menu(&eq)
+                .close_behavior(PopupCloseBehavior::CloseOnClickOutside)
+                .show(|ui| self.eq_ui(ui));
+        });
+
+        ui.horizontal(|ui| {
+            let mut enabled = self.squelch.is_some();
+            let toggled = ui
+                .checkbox(&mut enabled, "Squelch")
+                .on_hover_text("Mute the audio while the channel is quieter than this")
+                .changed();
+            let level = ui.add_enabled(
+                enabled,
+                DragValue::new(&mut self.squelch_level.0)
+                    .speed(0.5)
+                    .range(-120.0..=0.0)
+                    .suffix(" dB"),
+            );
+            if toggled || level.changed() {
+                let threshold = enabled.then_some(self.squelch_level);
+                let _ = self.cmd_tx.send(Command::SetSquelch(threshold));
+            }
+            if self.active.is_some() && self.squelch.is_some() {
+                if self.squelch_open {
+                    ui.colored_label(Color32::LIGHT_GREEN, "Open")
+                } else {
+                    ui.weak("Closed")
+                }
+                .on_hover_text("Whether the squelch is letting the audio through");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let retune = self.active.is_some_and(|active| active != self.channel);
+            let label = if retune { "Retune" } else { "Listen" };
+            ui.add_enabled_ui(self.active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_listen();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+            // Broadcast FM is stereo while its pilot is received
+            if self
+                .active
+                .is_some_and(|active| active.demod == DemodMode::Wfm)
+            {
+                if self.stereo {
+                    ui.colored_label(Color32::LIGHT_GREEN, "Stereo")
+                } else {
+                    ui.weak("Mono")
+                }
+                .on_hover_text("Whether the station's stereo pilot is received");
+            }
+        });
+        if let Some(rds) = &self.rds {
+            ui.horizontal(|ui| {
+                ui.strong(rds.station.as_deref().unwrap_or("…"))
+                    .on_hover_text("Station name, over RDS");
+                ui.weak(format!("PI {:04X}", rds.pi))
+                    .on_hover_text("Programme identification code");
+            });
+            if let Some(radiotext) = &rds.radiotext {
+                ui.label(radiotext);
+            }
+        }
+        ui.add_enabled_ui(self.selection.is_some(), |ui| {
+            if ui
+                .button("Listen to selection")
+                .on_hover_text("Tune to the signal, guessing its mode from its width")
+                .on_disabled_hover_text("Drag across a signal on the waterfall")
+                .clicked()
+                && let Some(selection) = self.selection
+            {
+                self.channel = AudioChannel::for_signal(selection);
+                self.send_listen();
+            }
+        });
+
+        CollapsingHeader::new("Routing")
+            .id_salt("audio_routing")
+            .show(ui, |ui| self.routing_ui(ui));
+
+        if let Some(e) = &self.output_error {
+            ui.colored_label(Color32::LIGHT_RED, format!("No audio output: {e}"));
+        }
+
+        ui.response()
+    }
+}
+
+/// A filter corner in the equalizer, off or at a frequency; turned on at
+/// `default`.
+fn corner_ui(ui: &mut Ui, label: &str, corner: &mut Option<f32>, default: f32) {
+    let mut on = corner.is_some();
+    if ui.checkbox(&mut on, label).changed() {
+        *corner = on.then_some(default);
+    }
+    if let Some(frequency) = corner {
+        ui.add(
+            DragValue::new(frequency)
+                .speed(10)
+                .range(20.0..=20_000.0)
+                .suffix(" Hz"),
+        );
+    }
+    ui.end_row();
+}
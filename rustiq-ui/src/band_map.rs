@@ -0,0 +1,308 @@
+//! Where a frequency is across the spectrum and waterfall: in which bin of
+//! a frame, and at which pixel of a view. Plain numbers in and out, so the
+//! markers, click-to-tune and annotations all agree, and the math is
+//! tested apart from the drawing.
+
+use rustiq_messages::SpectrumFrame;
+
+/// A band `span` Hz wide around `center`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub center: f64,
+    pub span: f64,
+}
+
+impl Band {
+    pub fn new(center: f64, span: f64) -> Self {
+        Self { center, span }
+    }
+
+    /// The band `frame` covers.
+    pub fn of(frame: &SpectrumFrame) -> Self {
+        Self::new(
+            frame.center_frequency.as_hz() as f64,
+            frame.sample_rate.as_hz() as f64,
+        )
+    }
+
+    pub fn low(&self) -> f64 {
+        self.center - self.span / 2.0
+    }
+
+    pub fn high(&self) -> f64 {
+        self.center + self.span / 2.0
+    }
+
+    /// How far across the band `frequency` is, 0 at its low edge and 1 at
+    /// its high edge; outside 0 to 1 for frequencies outside it.
+    pub fn fraction_of(&self, frequency: f64) -> f64 {
+        (frequency - self.center) / self.span + 0.5
+    }
+
+    /// The frequency `fraction` of the way across the band.
+    pub fn frequency_at(&self, fraction: f64) -> f64 {
+        self.center + (fraction - 0.5) * self.span
+    }
+}
+
+/// The bins of an FFT-shifted frame, `count` of them tiling `band`: lowest
+/// frequency first, or highest first if the spectrum is `inverted`. The
+/// band's center, DC, falls in bin `count / 2`, with the negative
+/// frequencies below it, for odd counts as well as even.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bins {
+    pub band: Band,
+    pub count: usize,
+    pub inverted: bool,
+}
+
+impl Bins {
+    pub fn new(band: Band, count: usize) -> Self {
+        Self {
+            band,
+            count,
+            inverted: false,
+        }
+    }
+
+    /// Width of each bin, in Hz.
+    pub fn width(&self) -> f64 {
+        self.band.span / self.count as f64
+    }
+
+    /// Position of `frequency` in bins from the low edge of the band, with
+    /// the bin it falls in the whole part; outside 0 to `count` for
+    /// frequencies outside the band. Counted from the high edge if
+    /// `inverted`.
+    pub fn position_of(&self, frequency: f64) -> f64 {
+        let position = self.band.fraction_of(frequency) * self.count as f64;
+        if self.inverted {
+            self.count as f64 - position
+        } else {
+            position
+        }
+    }
+
+    /// Bin holding `frequency`, if the band covers it. Each bin holds its
+    /// low edge and not its high one.
+    pub fn bin_at(&self, frequency: f64) -> Option<usize> {
+        let position = self.band.fraction_of(frequency) * self.count as f64;
+        let bin = (0.0..self.count as f64)
+            .contains(&position)
+            .then_some(position as usize)?;
+        Some(if self.inverted {
+            self.count - 1 - bin
+        } else {
+            bin
+        })
+    }
+
+    /// Frequency at the center of `bin`.
+    pub fn frequency_of(&self, bin: usize) -> f64 {
+        let bin = if self.inverted {
+            self.count - 1 - bin
+        } else {
+            bin
+        };
+        self.band
+            .frequency_at((bin as f64 + 0.5) / self.count as f64)
+    }
+}
+
+/// A view of `band` drawn `width` points wide from `left`, lowest
+/// frequency on the left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pixels {
+    pub left: f32,
+    pub width: f32,
+    pub band: Band,
+}
+
+impl Pixels {
+    pub fn new(left: f32, width: f32, band: Band) -> Self {
+        Self { left, width, band }
+    }
+
+    /// Frequency at `x`, in the view or past either side of it.
+    pub fn frequency_at(&self, x: f32) -> f64 {
+        self.band
+            .frequency_at(((x - self.left) / self.width) as f64)
+    }
+
+    /// Position of `frequency`, in the view or past either side of it.
+    pub fn x_of(&self, frequency: f64) -> f32 {
+        self.left + self.band.fraction_of(frequency) as f32 * self.width
+    }
+
+    /// Position of `frequency`, if it is in the view.
+    pub fn x_in_view(&self, frequency: f64) -> Option<f32> {
+        let x = self.x_of(frequency);
+        (self.left..=self.left + self.width)
+            .contains(&x)
+            .then_some(x)
+    }
+
+    /// Frequency at the middle of `column` of `columns` across the view,
+    /// as each pixel of an image stretched over it shows.
+    pub fn column_frequency(&self, column: usize, columns: usize) -> f64 {
+        self.band
+            .frequency_at((column as f64 + 0.5) / columns as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zoom::Zoom;
+
+    /// 2 MHz around 100 MHz.
+    const BAND: Band = Band {
+        center: 100e6,
+        span: 2e6,
+    };
+
+    #[test]
+    fn puts_dc_in_the_middle_bin_of_a_shifted_frame() {
+        for count in [4, 5, 16, 17, 1023, 1024, 4096] {
+            let bins = Bins::new(BAND, count);
+            assert_eq!(bins.bin_at(BAND.center), Some(count / 2), "{count} bins");
+            // Negative frequencies below it, positive above
+            assert_eq!(bins.bin_at(BAND.center - bins.width()), Some(count / 2 - 1));
+            assert_eq!(bins.bin_at(BAND.center + bins.width()), Some(count / 2 + 1));
+            assert_eq!(bins.bin_at(BAND.low()), Some(0));
+            assert_eq!(bins.bin_at(BAND.high() - 1.0), Some(count - 1));
+        }
+    }
+
+    #[test]
+    fn centers_dc_on_its_bin_only_for_odd_counts() {
+        let odd = Bins::new(BAND, 5);
+        assert_eq!(odd.frequency_of(2), BAND.center);
+        assert_eq!(odd.frequency_of(0), BAND.center - 2.0 * odd.width());
+        // DC at the low edge of its bin
+        let even = Bins::new(BAND, 4);
+        assert_eq!(even.frequency_of(2), BAND.center + even.width() / 2.0);
+        assert_eq!(even.frequency_of(0), BAND.low() + even.width() / 2.0);
+    }
+
+    #[test]
+    fn holds_the_low_edge_of_each_bin_and_not_the_high() {
+        let bins = Bins::new(Band::new(0.0, 800.0), 8);
+        assert_eq!(bins.width(), 100.0);
+        assert_eq!(bins.bin_at(-400.0), Some(0));
+        assert_eq!(bins.bin_at(-300.0), Some(1));
+        assert_eq!(bins.bin_at(-300.001), Some(0));
+        assert_eq!(bins.bin_at(399.999), Some(7));
+        assert_eq!(bins.bin_at(400.0), None);
+        assert_eq!(bins.bin_at(-400.001), None);
+        assert_eq!(bins.position_of(-350.0), 0.5);
+        assert_eq!(bins.position_of(500.0), 9.0);
+    }
+
+    #[test]
+    fn maps_every_bin_to_its_center_and_back() {
+        for count in [1, 2, 3, 7, 8, 255, 256] {
+            for inverted in [false, true] {
+                let bins = Bins {
+                    inverted,
+                    ..Bins::new(BAND, count)
+                };
+                for bin in 0..count {
+                    assert_eq!(bins.bin_at(bins.frequency_of(bin)), Some(bin));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reverses_an_inverted_spectrum() {
+        let bins = Bins {
+            inverted: true,
+            ..Bins::new(Band::new(0.0, 800.0), 8)
+        };
+        // Highest frequency first
+        assert_eq!(bins.bin_at(350.0), Some(0));
+        assert_eq!(bins.bin_at(-350.0), Some(7));
+        assert_eq!(bins.frequency_of(0), 350.0);
+        // DC in the bin after the middle, the one that held -100 Hz
+        assert_eq!(bins.bin_at(0.0), Some(3));
+        assert_eq!(bins.position_of(-400.0), 8.0);
+        assert_eq!(bins.position_of(400.0), 0.0);
+
+        let odd = Bins {
+            inverted: true,
+            ..Bins::new(Band::new(0.0, 500.0), 5)
+        };
+        assert_eq!(odd.bin_at(0.0), Some(2));
+        assert_eq!(odd.frequency_of(0), 200.0);
+    }
+
+    #[test]
+    fn maps_pixels_to_frequencies_and_back() {
+        let pixels = Pixels::new(100.0, 400.0, BAND);
+        assert_eq!(pixels.frequency_at(100.0), BAND.low());
+        assert_eq!(pixels.frequency_at(300.0), BAND.center);
+        assert_eq!(pixels.frequency_at(500.0), BAND.high());
+        // Past the sides, for drags that leave the view
+        assert_eq!(pixels.frequency_at(0.0), BAND.low() - 0.5e6);
+        for x in [100.0, 137.5, 300.0, 499.0] {
+            assert!((pixels.x_of(pixels.frequency_at(x)) - x).abs() < 1e-3);
+        }
+        assert_eq!(pixels.x_in_view(BAND.center), Some(300.0));
+        assert_eq!(pixels.x_in_view(BAND.high() + 1.0), None);
+        assert_eq!(pixels.x_in_view(BAND.low() - 1.0), None);
+    }
+
+    #[test]
+    fn stretches_odd_bin_counts_over_any_width() {
+        // 5 bins over 8 columns: each column takes the bin under its middle
+        let bins = Bins::new(Band::new(0.0, 500.0), 5);
+        let pixels = Pixels::new(0.0, 8.0, bins.band);
+        let columns: Vec<Option<usize>> = (0..8)
+            .map(|column| bins.bin_at(pixels.column_frequency(column, 8)))
+            .collect();
+        let expected = [0, 0, 1, 2, 2, 3, 4, 4].map(Some);
+        assert_eq!(columns, expected);
+    }
+
+    #[test]
+    fn follows_the_zoomed_view() {
+        let mut zoom = Zoom::default();
+        zoom.zoom_at(0.75, 4.0);
+        let (center, span) = zoom.band(BAND.center, BAND.span);
+        let pixels = Pixels::new(0.0, 1000.0, Band::new(center, span));
+        // A quarter of the band, around where the pointer was
+        assert_eq!(pixels.frequency_at(750.0), 100.5e6);
+        assert_eq!(pixels.frequency_at(0.0), 100.125e6);
+        assert_eq!(pixels.frequency_at(1000.0), 100.625e6);
+        assert_eq!(pixels.x_in_view(BAND.center), None);
+
+        // The bins of the whole frame still line up under it
+        let bins = Bins::new(BAND, 16);
+        assert_eq!(bins.bin_at(pixels.frequency_at(750.0)), Some(12));
+        assert_eq!(bins.bin_at(pixels.frequency_at(0.0)), Some(9));
+    }
+
+    #[test]
+    fn lines_up_frames_of_another_tuning() {
+        // A frame 500 kHz up, as the tuning moves under a waterfall
+        let older = Bins::new(Band::new(BAND.center + 500e3, BAND.span), 8);
+        let pixels = Pixels::new(0.0, 8.0, BAND);
+        let columns: Vec<Option<usize>> = (0..8)
+            .map(|column| older.bin_at(pixels.column_frequency(column, 8)))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                None,
+                None,
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(3),
+                Some(4),
+                Some(5)
+            ]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use eframe::egui::{DragValue, Response, TextEdit, Ui, Widget};
+use egui_plot::{Bar, BarChart, Plot};
+use flume::Sender;
+use log::{info, warn};
+
+use rustiq_messages::{Burst, Command, Decibels};
+
+/// Number of bursts kept for the statistics and histograms.
+const MAX_HISTORY: usize = 10_000;
+
+/// Number of bars in each histogram.
+const HISTOGRAM_BINS: usize = 50;
+
+/// Burst detection panel.
+///
+/// The widget (`ui.add(&mut panel)`) renders the threshold, statistics and
+/// export controls; `show_plots()` renders the duration and interval histograms.
+pub struct BurstPanel {
+    cmd_tx: Sender<Command>,
+    /// Threshold entered in the threshold field
+    threshold: Decibels,
+    /// Threshold the engine is currently detecting with
+    active: Option<Decibels>,
+    history: Vec<Burst>,
+    /// Destination of the CSV export
+    export_path: PathBuf,
+}
+
+impl BurstPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            threshold: Decibels(10.0),
+            active: None,
+            history: Vec::new(),
+            export_path: PathBuf::from("bursts.csv"),
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, burst_detection: Option<Decibels>) {
+        if burst_detection != self.active {
+            self.history.clear();
+        }
+        self.active = burst_detection;
+        if let Some(threshold) = burst_detection {
+            self.threshold = threshold;
+        }
+    }
+
+    pub fn insert_burst(&mut self, burst: Burst) {
+        if self.history.len() == MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(burst);
+    }
+
+    pub fn has_data(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    /// Render the duration and interval histograms side by side.
+    pub fn show_plots(&self, ui: &mut Ui) {
+        let height = ui.available_height();
+        ui.columns(2, |columns| {
+            let plots = [
+                ("burst_durations", "Duration (ms)", self.durations()),
+                ("burst_intervals", "Interval (ms)", self.intervals()),
+            ];
+            for (column, (id, label, seconds)) in columns.iter_mut().zip(plots) {
+                let millis: Vec<f64> = seconds.iter().map(|s| s * 1e3).collect();
+                let (start, width, counts) = histogram(&millis, HISTOGRAM_BINS);
+                let bars = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &count)| {
+                        Bar::new(start + (i as f64 + 0.5) * width, count as f64).width(width)
+                    })
+                    .collect();
+                Plot::new(id)
+                    .height(height)
+                    .x_axis_label(label)
+                    .y_axis_label("Bursts")
+                    .show(column, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new(label, bars));
+                    });
+            }
+        });
+    }
+
+    fn durations(&self) -> Vec<f64> {
+        self.history
+            .iter()
+            .map(|b| b.duration.as_secs_f64())
+            .collect()
+    }
+
+    fn intervals(&self) -> Vec<f64> {
+        self.history
+            .iter()
+            .filter_map(|b| b.interval)
+            .map(|i| i.as_secs_f64())
+            .collect()
+    }
+
+    fn export(&self) {
+        match std::fs::write(&self.export_path, to_csv(&self.history)) {
+            Ok(()) => info!(
+                "Exported {} bursts to {}",
+                self.history.len(),
+                self.export_path.display()
+            ),
+            Err(e) => warn!("Failed to export bursts: {}", e),
+        }
+    }
+
+    fn send_start(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::StartBurstDetection(self.threshold));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopBurstDetection);
+    }
+}
+
+impl Widget for &mut BurstPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Burst Detection");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Threshold:");
+            let mut db = self.threshold.0;
+            if ui
+                .add(
+                    DragValue::new(&mut db)
+                        .speed(0.5)
+                        .range(1.0..=60.0)
+                        .suffix(" dB"),
+                )
+                .changed()
+            {
+                self.threshold = Decibels(db);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let retune = self.active.is_some_and(|active| active != self.threshold);
+            let label = if retune { "Apply" } else { "Start" };
+            ui.add_enabled_ui(self.active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+
+        if self.has_data() {
+            ui.add_space(5.0);
+            ui.label(format!("Bursts: {}", self.history.len()));
+            if let Some(duration) = mean(&self.durations()) {
+                ui.label(format!("Mean duration: {:.3} ms", duration * 1e3));
+            }
+            if let Some(interval) = mean(&self.intervals()) {
+                ui.label(format!("Mean interval: {:.3} ms", interval * 1e3));
+                ui.label(format!("PRF: {:.3} Hz", 1.0 / interval));
+            }
+
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                let mut path_str = self.export_path.to_string_lossy().to_string();
+                if ui
+                    .add(TextEdit::singleline(&mut path_str).desired_width(150.0))
+                    .changed()
+                {
+                    self.export_path = PathBuf::from(path_str);
+                }
+                if ui.button("Export CSV").clicked() {
+                    self.export();
+                }
+            });
+        } else if self.active.is_some() {
+            ui.label("Waiting for bursts...");
+        }
+
+        ui.response()
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Count `values` into `bins` equal-width bins spanning their range.
+/// Returns (start of the first bin, bin width, counts).
+fn histogram(values: &[f64], bins: usize) -> (f64, f64, Vec<usize>) {
+    if values.is_empty() {
+        return (0.0, 1.0, Vec::new());
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    // A single distinct value still gets a visible bar
+    let width = if max > min {
+        (max - min) / bins as f64
+    } else {
+        min.abs().max(1.0) / bins as f64
+    };
+
+    let mut counts = vec![0; bins];
+    for value in values {
+        let bin = ((value - min) / width) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    (min, width, counts)
+}
+
+/// Format bursts as CSV, one row per burst. Times are in seconds.
+fn to_csv(bursts: &[Burst]) -> String {
+    let mut csv = String::from("start_s,duration_s,interval_s,peak_snr_db\n");
+    for burst in bursts {
+        let interval = burst
+            .interval
+            .map(|i| format!("{:.9}", i.as_secs_f64()))
+            .unwrap_or_default();
+        let _ = writeln!(
+            csv,
+            "{:.9},{:.9},{},{:.1}",
+            burst.start.as_secs_f64(),
+            burst.duration.as_secs_f64(),
+            interval,
+            burst.peak_snr.as_db()
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn histogram_spans_value_range() {
+        let (start, width, counts) = histogram(&[1.0, 2.0, 2.0, 3.0], 4);
+        assert_eq!(start, 1.0);
+        assert_eq!(width, 0.5);
+        // The maximum falls in the last bin rather than past it
+        assert_eq!(counts, vec![1, 0, 2, 1]);
+    }
+
+    #[test]
+    fn csv_leaves_first_interval_empty() {
+        let burst = |start_ms, interval| Burst {
+            start: Duration::from_millis(start_ms),
+            duration: Duration::from_millis(10),
+            interval,
+            peak_snr: Decibels(20.0),
+        };
+        let csv = to_csv(&[burst(0, None), burst(50, Some(Duration::from_millis(50)))]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "start_s,duration_s,interval_s,peak_snr_db");
+        assert_eq!(lines[1], "0.000000000,0.010000000,,20.0");
+        assert_eq!(lines[2], "0.050000000,0.010000000,0.050000000,20.0");
+    }
+}
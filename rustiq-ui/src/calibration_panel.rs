@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use eframe::egui::{Color32, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+use log::{info, warn};
+
+use rustiq_messages::{CalibrationPoint, Command, Decibels};
+
+/// Flatness calibration: a table of the front end's response across the
+/// span, measured with a flat reference source such as a noise generator,
+/// that the engine corrects the spectrum by. Levels read off the plot and
+/// the waterfall then compare fairly between the middle and the edges.
+///
+/// The table is a CSV file of `offset_hz,level_db` rows, the offset from the
+/// center frequency and the level measured there. Levels are taken relative
+/// to the center, so any unit will do.
+pub struct CalibrationPanel {
+    cmd_tx: Sender<Command>,
+    path: String,
+    /// Table the engine corrects the spectrum by
+    active: Vec<CalibrationPoint>,
+    /// Why the file couldn't be loaded, until the next load
+    error: Option<String>,
+}
+
+impl CalibrationPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            path: "calibration.csv".to_string(),
+            active: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, calibration: &[CalibrationPoint]) {
+        self.active = calibration.to_vec();
+    }
+
+    fn load(&mut self) {
+        match std::fs::read_to_string(Path::new(&self.path))
+            .map_err(|e| e.to_string())
+            .and_then(|csv| parse(&csv))
+        {
+            Ok(points) => {
+                info!(
+                    "Loaded {} calibration points from {}",
+                    points.len(),
+                    self.path
+                );
+                self.error = None;
+                let _ = self.cmd_tx.send(Command::SetCalibration(points));
+            }
+            Err(e) => {
+                warn!("Failed to load calibration from {}: {}", self.path, e);
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Points of a calibration table, sorted by offset and normalized to 0 dB at
+/// the center. A header line, blank lines and `#` comments are skipped.
+fn parse(csv: &str) -> Result<Vec<CalibrationPoint>, String> {
+    let mut points = Vec::new();
+    for (number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line
+            .split_once(',')
+            .and_then(|(offset, level)| Some((offset.trim().parse().ok()?, level.trim().parse())));
+        match fields {
+            Some((offset, Ok(level))) => points.push(CalibrationPoint {
+                offset,
+                gain: Decibels(level),
+            }),
+            // The header
+            None if number == 0 => {}
+            _ => return Err(format!("line {}: expected offset_hz,level_db", number + 1)),
+        }
+    }
+    if points.is_empty() {
+        return Err("no calibration points".to_string());
+    }
+    points.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    let center = CalibrationPoint::gain_at(&points, 0.0);
+    for point in &mut points {
+        point.gain.0 -= center.0;
+    }
+    Ok(points)
+}
+
+impl Widget for &mut CalibrationPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Calibration");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Table:");
+            ui.add(TextEdit::singleline(&mut self.path).desired_width(150.0))
+                .on_hover_text("CSV of offset_hz,level_db measured from a flat reference source");
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Load").clicked() {
+                self.load();
+            }
+            ui.add_enabled_ui(!self.active.is_empty(), |ui| {
+                if ui.button("Clear").clicked() {
+                    let _ = self.cmd_tx.send(Command::SetCalibration(Vec::new()));
+                }
+            });
+        });
+
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::LIGHT_RED, format!("Not loaded: {e}"));
+        } else if self.active.is_empty() {
+            ui.label("Spectrum uncorrected");
+        } else {
+            let (low, high) = self
+                .active
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), point| {
+                    (low.min(point.gain.0), high.max(point.gain.0))
+                });
+            ui.label(format!(
+                "Corrected for {:+.1} to {:+.1} dB, {} points",
+                low,
+                high,
+                self.active.len()
+            ));
+        }
+
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_table_to_center() {
+        let csv = "offset_hz,level_db\n\
+                   # measured with the noise source\n\
+                   1000000,-47.5\n\
+                   -1000000,-46\n\
+                   0,-40\n";
+        let points = parse(csv).unwrap();
+        let offsets: Vec<_> = points.iter().map(|point| point.offset).collect();
+        let gains: Vec<_> = points.iter().map(|point| point.gain.0).collect();
+        assert_eq!(offsets, [-1e6, 0.0, 1e6]);
+        assert_eq!(gains, [-6.0, 0.0, -7.5]);
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        assert_eq!(
+            parse("0,-40\n1000,loud\n"),
+            Err("line 2: expected offset_hz,level_db".to_string())
+        );
+        assert_eq!(
+            parse("offset_hz,level_db\n"),
+            Err("no calibration points".to_string())
+        );
+    }
+}
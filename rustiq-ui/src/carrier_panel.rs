@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+
+use eframe::egui::{DragValue, Response, Ui, Widget};
+use egui_plot::{Line, Plot, PlotPoints, Points};
+use flume::Sender;
+
+use rustiq_messages::{CarrierMeasurement, Command, Hertz};
+
+/// Number of readings kept for the frequency and stability plots.
+const MAX_HISTORY: usize = 10_000;
+
+/// Carrier measurement panel.
+///
+/// The widget (`ui.add(&mut panel)`) renders the target selector and the latest
+/// reading; `show_plots()` renders the frequency history and Allan deviation,
+/// which need more room than the side panel offers.
+pub struct CarrierPanel {
+    cmd_tx: Sender<Command>,
+    /// Frequency entered in the target field
+    target: Hertz,
+    /// Target the engine is currently measuring
+    active: Option<Hertz>,
+    history: VecDeque<CarrierMeasurement>,
+}
+
+impl CarrierPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            target: Hertz(10_000),
+            active: None,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, carrier_measurement: Option<Hertz>) {
+        if carrier_measurement != self.active {
+            self.history.clear();
+        }
+        self.active = carrier_measurement;
+        if let Some(target) = carrier_measurement {
+            self.target = target;
+        }
+    }
+
+    pub fn insert_measurement(&mut self, measurement: CarrierMeasurement) {
+        // Time starts over when the engine rebuilds its graph
+        if self
+            .history
+            .back()
+            .is_some_and(|last| measurement.elapsed < last.elapsed)
+        {
+            self.history.clear();
+        }
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(measurement);
+    }
+
+    pub fn has_data(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    /// Render the frequency-vs-time and Allan deviation plots side by side.
+    pub fn show_plots(&self, ui: &mut Ui) {
+        let Some(first) = self.history.front() else {
+            return;
+        };
+        let reference = first.frequency;
+        let height = ui.available_height();
+
+        ui.columns(2, |columns| {
+            let drift: PlotPoints = self
+                .history
+                .iter()
+                .map(|m| [m.elapsed.as_secs_f64(), m.frequency - reference])
+                .collect();
+            Plot::new("carrier_frequency")
+                .height(height)
+                .x_axis_label("Time (s)")
+                .y_axis_label(format!("Offset from {reference:.3} Hz (Hz)"))
+                .show(&mut columns[0], |plot_ui| {
+                    plot_ui.line(Line::new("Frequency", drift));
+                });
+
+            let adev = allan_deviation(&self.frequencies(), self.sample_interval());
+            let points: Vec<[f64; 2]> = adev
+                .iter()
+                .filter(|(_, sigma)| *sigma > 0.0)
+                .map(|(tau, sigma)| [tau.log10(), sigma.log10()])
+                .collect();
+            Plot::new("carrier_allan_deviation")
+                .height(height)
+                .x_axis_label("τ (s)")
+                .y_axis_label("σ(τ) (Hz)")
+                .x_axis_formatter(|mark, _| format!("{:.3}", 10f64.powf(mark.value)))
+                .y_axis_formatter(|mark, _| format!("{:.1e}", 10f64.powf(mark.value)))
+                .show(&mut columns[1], |plot_ui| {
+                    plot_ui.line(Line::new("Allan deviation", points.clone()));
+                    plot_ui.points(Points::new("Allan deviation", points).radius(3.0));
+                });
+        });
+    }
+
+    fn frequencies(&self) -> Vec<f64> {
+        self.history.iter().map(|m| m.frequency).collect()
+    }
+
+    /// Mean time between consecutive readings, in seconds.
+    fn sample_interval(&self) -> f64 {
+        match (self.history.front(), self.history.back()) {
+            (Some(first), Some(last)) if self.history.len() > 1 => {
+                (last.elapsed - first.elapsed).as_secs_f64() / (self.history.len() - 1) as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn send_start(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::StartCarrierMeasurement(self.target));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopCarrierMeasurement);
+    }
+}
+
+impl Widget for &mut CarrierPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Carrier Measurement");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Target:");
+            let mut freq = self.target.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                .changed()
+            {
+                self.target.0 = freq;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let retarget = self.active.is_some_and(|active| active != self.target);
+            let label = if retarget { "Relock" } else { "Start" };
+            ui.add_enabled_ui(self.active.is_none() || retarget, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+
+        if let Some(latest) = self.history.back() {
+            ui.add_space(5.0);
+            ui.monospace(format!("{:.3} Hz", latest.frequency));
+            ui.label(format!("Level: {}", latest.power));
+            let adev = allan_deviation(&self.frequencies(), self.sample_interval());
+            if let Some((tau, sigma)) = adev.first() {
+                ui.label(format!("σ({tau:.2} s): {sigma:.3} Hz"));
+            }
+        } else if self.active.is_some() {
+            ui.label("Waiting for lock...");
+        }
+
+        ui.response()
+    }
+}
+
+/// Overlapping Allan deviation of evenly spaced frequency readings.
+///
+/// Returns (τ, σ(τ)) pairs for averaging factors of 1, 2, 4, ... readings,
+/// stopping when fewer than two averaging windows fit in the data.
+/// σ is in the same unit as `frequencies`.
+fn allan_deviation(frequencies: &[f64], interval: f64) -> Vec<(f64, f64)> {
+    // Prefix sums make every window average O(1)
+    let mut prefix = Vec::with_capacity(frequencies.len() + 1);
+    prefix.push(0.0);
+    for f in frequencies {
+        prefix.push(prefix.last().unwrap() + f);
+    }
+    let mean = |start: usize, len: usize| (prefix[start + len] - prefix[start]) / len as f64;
+
+    let mut result = Vec::new();
+    let mut m = 1;
+    while 2 * m <= frequencies.len() {
+        let terms = frequencies.len() - 2 * m + 1;
+        let sum: f64 = (0..terms)
+            .map(|j| (mean(j + m, m) - mean(j, m)).powi(2))
+            .sum();
+        result.push((m as f64 * interval, (sum / (2.0 * terms as f64)).sqrt()));
+        m *= 2;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rustiq_messages::Decibels;
+
+    use super::*;
+
+    fn reading(elapsed: Duration) -> CarrierMeasurement {
+        CarrierMeasurement {
+            elapsed,
+            frequency: 10_000.0,
+            power: Decibels(-20.0),
+        }
+    }
+
+    #[test]
+    fn keeps_the_latest_readings_and_their_spacing() {
+        let mut panel = CarrierPanel::new(flume::unbounded().0);
+        let interval = Duration::from_millis(250);
+        // The first reading comes a whole interval in
+        for i in 1..=MAX_HISTORY + 10 {
+            panel.insert_measurement(reading(interval * i as u32));
+        }
+        assert_eq!(panel.history.len(), MAX_HISTORY);
+        assert_eq!(panel.history.front().unwrap().elapsed, interval * 11);
+        assert!((panel.sample_interval() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn starts_over_when_time_goes_back() {
+        let mut panel = CarrierPanel::new(flume::unbounded().0);
+        for i in 1..=20 {
+            panel.insert_measurement(reading(Duration::from_millis(500) * i));
+        }
+        // The graph was rebuilt: same target, time from zero again
+        panel.insert_measurement(reading(Duration::from_millis(100)));
+        panel.insert_measurement(reading(Duration::from_millis(200)));
+        assert_eq!(panel.history.len(), 2);
+        assert!((panel.sample_interval() - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn allan_deviation_of_constant_is_zero() {
+        let adev = allan_deviation(&[100.0; 16], 0.5);
+        assert_eq!(adev.len(), 4);
+        assert_eq!(adev[0], (0.5, 0.0));
+        assert!(adev.iter().all(|(_, sigma)| *sigma == 0.0));
+    }
+
+    #[test]
+    fn allan_deviation_of_alternating_series() {
+        // Consecutive readings differ by 2, so σ²(τ0) = 2² / 2
+        let readings: Vec<f64> = (0..64)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let adev = allan_deviation(&readings, 1.0);
+        assert!((adev[0].1 - 2f64.sqrt()).abs() < 1e-12);
+        // Averaging pairs cancels the alternation entirely
+        assert!(adev[1].1 < 1e-12);
+    }
+}
@@ -0,0 +1,308 @@
+use std::io;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use eframe::egui::{Checkbox, Color32, TextEdit, Ui};
+use flume::Receiver;
+use log::{debug, warn};
+
+/// NTP server probed for the time.
+const DEFAULT_SERVER: &str = "pool.ntp.org";
+
+/// Offset beyond which the clock is reported as drifting. Decode and
+/// annotation timestamps are taken from the system clock, so they are off
+/// by as much.
+const MAX_DRIFT: Duration = Duration::from_millis(500);
+
+/// Time between checks while enabled; a clock drifts slowly.
+const RECHECK: Duration = Duration::from_secs(15 * 60);
+
+/// Give up on the server after this long.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970).
+const NTP_EPOCH_OFFSET: f64 = 2_208_988_800.0;
+
+/// Length of an SNTP packet without extensions.
+const PACKET_LEN: usize = 48;
+
+/// Offset of this machine's clock from a server's, as measured by one probe.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ClockOffset {
+    /// Seconds the server's clock is ahead of ours
+    offset: f64,
+    /// Round trip to the server, in seconds, bounding the offset's error
+    delay: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClockStatus {
+    Off,
+    Checking,
+    Measured(ClockOffset),
+    Failed(String),
+}
+
+/// Opt-in check of the system clock against an NTP server, shown in the
+/// status bar with a warning when it has drifted.
+pub struct ClockCheck {
+    /// Whether the user opted in
+    enabled: bool,
+    server: String,
+    status: ClockStatus,
+    /// Result of the probe running in the background
+    result_rx: Option<Receiver<Result<ClockOffset, String>>>,
+    /// When to probe again
+    next_check: Option<Instant>,
+}
+
+impl ClockCheck {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            server: DEFAULT_SERVER.to_string(),
+            status: ClockStatus::Off,
+            result_rx: None,
+            next_check: None,
+        }
+    }
+
+    /// Probe the server on a background thread.
+    fn start(&mut self) {
+        let (result_tx, result_rx) = flume::bounded(1);
+        let server = self.server.clone();
+        thread::spawn(move || {
+            let result = probe(&server);
+            debug!("Clock check against {} returned {:?}", server, result);
+            let _ = result_tx.send(result);
+        });
+        self.result_rx = Some(result_rx);
+        self.next_check = None;
+        if !matches!(self.status, ClockStatus::Measured(_)) {
+            self.status = ClockStatus::Checking;
+        }
+    }
+
+    /// Pick up the result of a running probe, and start the next one when
+    /// it is due.
+    pub fn poll(&mut self) {
+        if self.enabled
+            && self.result_rx.is_none()
+            && self.next_check.is_some_and(|due| Instant::now() >= due)
+        {
+            self.start();
+        }
+        let Some(result) = self.result_rx.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.result_rx = None;
+        if !self.enabled {
+            return;
+        }
+        self.next_check = Some(Instant::now() + RECHECK);
+        self.status = match result {
+            Ok(measured) => {
+                if drifting(measured) {
+                    warn!(
+                        "System clock is {:+.3} s off {}",
+                        -measured.offset, self.server
+                    );
+                }
+                ClockStatus::Measured(measured)
+            }
+            Err(e) => {
+                warn!("Clock check failed: {}", e);
+                ClockStatus::Failed(e)
+            }
+        };
+    }
+
+    /// Status bar contents: the opt-in toggle and the clock's offset.
+    pub fn show(&mut self, ui: &mut Ui) {
+        let toggle = ui
+            .add(Checkbox::new(&mut self.enabled, "Check clock"))
+            .on_hover_text(
+                "Compare the system clock, which timestamps decodes and annotations, \
+                 with an NTP server",
+            );
+        if toggle.changed() {
+            if self.enabled {
+                self.start();
+            } else {
+                self.status = ClockStatus::Off;
+                self.next_check = None;
+            }
+        }
+
+        match &self.status {
+            ClockStatus::Off => {}
+            ClockStatus::Checking => {
+                ui.spinner();
+            }
+            ClockStatus::Measured(measured) => {
+                // Shown as our clock's error, the opposite of the offset
+                let error_ms = -measured.offset * 1000.0;
+                let detail = format!(
+                    "System clock is {:+.1} ms off {} (round trip {:.1} ms)",
+                    error_ms,
+                    self.server,
+                    measured.delay * 1000.0
+                );
+                if drifting(*measured) {
+                    ui.colored_label(
+                        Color32::LIGHT_RED,
+                        format!("⚠ Clock off by {:+.2} s", error_ms / 1000.0),
+                    )
+                    .on_hover_text(format!(
+                        "{detail}. Timestamps of decodes and annotations are off by as much; \
+                         check the system's time sync (chrony, systemd-timesyncd)."
+                    ));
+                } else {
+                    ui.label(format!("Clock {error_ms:+.0} ms"))
+                        .on_hover_text(detail);
+                }
+            }
+            ClockStatus::Failed(e) => {
+                ui.colored_label(Color32::LIGHT_RED, "Clock check failed")
+                    .on_hover_text(e);
+                ui.add(TextEdit::singleline(&mut self.server).desired_width(150.0))
+                    .on_hover_text("NTP server");
+                if ui.button("Retry").clicked() {
+                    self.start();
+                }
+            }
+        }
+    }
+}
+
+fn drifting(measured: ClockOffset) -> bool {
+    measured.offset.abs() > MAX_DRIFT.as_secs_f64()
+}
+
+/// Ask `server` for the time over SNTP and measure our clock against it.
+fn probe(server: &str) -> Result<ClockOffset, String> {
+    let address = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{server}:123")
+    };
+    let exchange = || -> io::Result<(f64, [u8; PACKET_LEN], f64)> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_read_timeout(Some(TIMEOUT))?;
+        socket.connect(&address)?;
+        let mut request = [0; PACKET_LEN];
+        // No leap indicator, version 4, client mode
+        request[0] = (4 << 3) | 3;
+        let sent = unix_now();
+        socket.send(&request)?;
+        let mut reply = [0; PACKET_LEN];
+        let len = socket.recv(&mut reply)?;
+        let received = unix_now();
+        if len < PACKET_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("short reply of {len} bytes"),
+            ));
+        }
+        Ok((sent, reply, received))
+    };
+    let (sent, reply, received) = exchange().map_err(|e| format!("{address}: {e}"))?;
+    measure(sent, &reply, received)
+}
+
+/// Seconds since the Unix epoch by the system clock.
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// Offset and round trip from a server's `reply` to a request sent at `sent`
+/// and answered at `received`, both Unix seconds by our clock.
+fn measure(sent: f64, reply: &[u8; PACKET_LEN], received: f64) -> Result<ClockOffset, String> {
+    let leap = reply[0] >> 6;
+    let mode = reply[0] & 0b111;
+    let stratum = reply[1];
+    if mode != 4 {
+        return Err(format!("not a server reply (mode {mode})"));
+    }
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(&reply[12..16]);
+        return Err(format!("server refused: {}", code.trim_end_matches('\0')));
+    }
+    if leap == 3 {
+        return Err("server is not synchronized".to_string());
+    }
+    let server_received = timestamp(&reply[32..40]);
+    let server_sent = timestamp(&reply[40..48]);
+    Ok(ClockOffset {
+        offset: ((server_received - sent) + (server_sent - received)) / 2.0,
+        delay: ((received - sent) - (server_sent - server_received)).max(0.0),
+    })
+}
+
+/// A 64-bit NTP timestamp as Unix seconds.
+fn timestamp(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    seconds as f64 + fraction as f64 / 4_294_967_296.0 - NTP_EPOCH_OFFSET
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A server reply with stratum 2 that received and sent at the given
+    /// Unix seconds.
+    fn reply(server_received: f64, server_sent: f64) -> [u8; PACKET_LEN] {
+        let mut reply = [0; PACKET_LEN];
+        reply[0] = (4 << 3) | 4;
+        reply[1] = 2;
+        for (at, time) in [(32, server_received), (40, server_sent)] {
+            let ntp = time + NTP_EPOCH_OFFSET;
+            let seconds = ntp.floor();
+            let fraction = ((ntp - seconds) * 4_294_967_296.0) as u32;
+            reply[at..at + 4].copy_from_slice(&(seconds as u32).to_be_bytes());
+            reply[at + 4..at + 8].copy_from_slice(&fraction.to_be_bytes());
+        }
+        reply
+    }
+
+    #[test]
+    fn measures_offset_and_round_trip() {
+        // Our clock 2 s behind the server, 40 ms each way, 10 ms at the server
+        let sent = 1_700_000_000.0;
+        let server_received = sent + 2.0 + 0.040;
+        let server_sent = server_received + 0.010;
+        let received = sent + 0.090;
+        let measured = measure(sent, &reply(server_received, server_sent), received).unwrap();
+        assert!((measured.offset - 2.0).abs() < 1e-6, "{measured:?}");
+        assert!((measured.delay - 0.080).abs() < 1e-6, "{measured:?}");
+        assert!(drifting(measured));
+
+        let measured = measure(sent, &reply(sent + 0.05, sent + 0.05), sent + 0.1).unwrap();
+        assert!(measured.offset.abs() < 1e-6, "{measured:?}");
+        assert!(!drifting(measured));
+    }
+
+    #[test]
+    fn rejects_bad_replies() {
+        let mut refused = reply(0.0, 0.0);
+        refused[1] = 0;
+        refused[12..16].copy_from_slice(b"RATE");
+        assert_eq!(
+            measure(0.0, &refused, 0.0),
+            Err("server refused: RATE".to_string())
+        );
+
+        let mut unsynchronized = reply(0.0, 0.0);
+        unsynchronized[0] |= 0b11 << 6;
+        assert!(measure(0.0, &unsynchronized, 0.0).is_err());
+
+        let mut client = reply(0.0, 0.0);
+        client[0] = (4 << 3) | 3;
+        assert!(measure(0.0, &client, 0.0).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use eframe::egui::{Align2, Checkbox, Context, Ui, ViewportCommand, Window};
+
+/// Asks before the window closes, so a stray click doesn't end a long
+/// recording or scan along with the UI.
+pub struct ClosePrompt {
+    enabled: bool,
+    /// A close is awaiting the user's answer
+    asking: bool,
+    /// The user has confirmed the close
+    confirmed: bool,
+}
+
+impl ClosePrompt {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            asking: false,
+            confirmed: false,
+        }
+    }
+
+    /// Hold off a close of the window to ask about it first.
+    pub fn intercept(&mut self, ctx: &Context) {
+        let requested = ctx.input(|input| input.viewport().close_requested());
+        if requested && self.enabled && !self.confirmed {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            self.asking = true;
+        }
+    }
+
+    /// Ask while the answer is pending.
+    pub fn show(&mut self, ctx: &Context) {
+        if !self.asking {
+            return;
+        }
+        Window::new("Close RustIQ?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Recordings and scans stop with the window, unless the engine was");
+                ui.label("started on its own with rustiq engine --keep-running.");
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Close").clicked() {
+                        self.confirmed = true;
+                        self.asking = false;
+                        ctx.send_viewport_cmd(ViewportCommand::Close);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.asking = false;
+                    }
+                });
+            });
+    }
+
+    /// Status bar toggle.
+    pub fn show_toggle(&mut self, ui: &mut Ui) {
+        ui.add(Checkbox::new(&mut self.enabled, "Confirm close"))
+            .on_hover_text("Ask before closing the window");
+    }
+}
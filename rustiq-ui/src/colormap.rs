@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use eframe::epaint::Color32;
+
+/// Viridis, sampled at even steps.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 45, 123],
+    [59, 82, 139],
+    [44, 114, 142],
+    [33, 145, 140],
+    [40, 174, 128],
+    [94, 201, 98],
+    [173, 220, 48],
+    [253, 231, 37],
+];
+
+/// Inferno, sampled at even steps.
+const INFERNO: [[u8; 3]; 10] = [
+    [0, 0, 4],
+    [27, 12, 65],
+    [74, 12, 107],
+    [120, 28, 109],
+    [165, 44, 96],
+    [207, 68, 70],
+    [237, 105, 37],
+    [251, 155, 6],
+    [247, 209, 61],
+    [252, 255, 164],
+];
+
+/// Turbo, sampled at even steps.
+const TURBO: [[u8; 3]; 15] = [
+    [48, 18, 59],
+    [65, 69, 171],
+    [70, 117, 237],
+    [57, 162, 252],
+    [27, 207, 212],
+    [36, 236, 166],
+    [97, 252, 108],
+    [164, 252, 59],
+    [209, 232, 52],
+    [243, 198, 58],
+    [254, 155, 45],
+    [243, 99, 21],
+    [217, 56, 6],
+    [177, 25, 1],
+    [122, 4, 2],
+];
+
+/// The blue to yellow to red of older SDR programs.
+const CLASSIC: [[u8; 3]; 5] = [
+    [0, 0, 48],
+    [0, 0, 255],
+    [0, 255, 255],
+    [255, 255, 0],
+    [255, 0, 0],
+];
+
+/// Colors the waterfall draws levels in, from the weakest to the strongest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Colormap {
+    Grayscale,
+    Viridis,
+    Inferno,
+    Turbo,
+    Classic,
+    /// One the user loaded from a file
+    Custom(Arc<CustomColormap>),
+}
+
+/// A color map read from a file: colors at positions from 0, the weakest
+/// level, to 1, the strongest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomColormap {
+    name: String,
+    stops: Vec<(f32, [u8; 3])>,
+}
+
+impl CustomColormap {
+    /// A map of `stops`, their positions rising and stretched to span 0 to
+    /// 1. There must be two or more.
+    pub fn new(name: &str, mut stops: Vec<(f32, [u8; 3])>) -> Result<Self, String> {
+        if stops.len() < 2 {
+            return Err("a color map needs two or more colors".to_string());
+        }
+        if stops.iter().any(|(position, _)| !position.is_finite()) {
+            return Err("positions must be numbers".to_string());
+        }
+        if stops.windows(2).any(|pair| pair[1].0 < pair[0].0) {
+            return Err("positions must rise from one color to the next".to_string());
+        }
+        let (low, high) = (stops[0].0, stops[stops.len() - 1].0);
+        if high <= low {
+            return Err("the colors must span a range of positions".to_string());
+        }
+        for (position, _) in &mut stops {
+            *position = (*position - low) / (high - low);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            stops,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn color(&self, fraction: f32) -> Color32 {
+        let after = self
+            .stops
+            .iter()
+            .position(|&(position, _)| position > fraction)
+            .unwrap_or(self.stops.len() - 1)
+            .max(1);
+        let ((from, low), (to, high)) = (self.stops[after - 1], self.stops[after]);
+        let t = if to > from {
+            ((fraction - from) / (to - from)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        blend(low, high, t)
+    }
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 5] = [
+        Colormap::Grayscale,
+        Colormap::Viridis,
+        Colormap::Inferno,
+        Colormap::Turbo,
+        Colormap::Classic,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            Colormap::Grayscale => "Grayscale",
+            Colormap::Viridis => "Viridis",
+            Colormap::Inferno => "Inferno",
+            Colormap::Turbo => "Turbo",
+            Colormap::Classic => "Classic",
+            Colormap::Custom(custom) => &custom.name,
+        }
+    }
+
+    /// Color of a level `fraction` of the way from the weakest to the
+    /// strongest, clamped to 0 to 1.
+    pub fn color(&self, fraction: f32) -> Color32 {
+        let fraction = fraction.clamp(0.0, 1.0);
+        match self {
+            Colormap::Grayscale => Color32::from_gray((fraction * 255.0) as u8),
+            Colormap::Viridis => gradient(&VIRIDIS, fraction),
+            Colormap::Inferno => gradient(&INFERNO, fraction),
+            Colormap::Turbo => gradient(&TURBO, fraction),
+            Colormap::Classic => gradient(&CLASSIC, fraction),
+            Colormap::Custom(custom) => custom.color(fraction),
+        }
+    }
+}
+
+/// Color `fraction` of the way along evenly spaced `stops`, blended
+/// between the two either side.
+fn gradient(stops: &[[u8; 3]], fraction: f32) -> Color32 {
+    let position = fraction * (stops.len() - 1) as f32;
+    let index = (position as usize).min(stops.len() - 2);
+    blend(stops[index], stops[index + 1], position - index as f32)
+}
+
+/// The color `t` of the way from `from` to `to`.
+fn blend(from: [u8; 3], to: [u8; 3], t: f32) -> Color32 {
+    let [r, g, b] = std::array::from_fn(|channel| {
+        let (from, to) = (from[channel] as f32, to[channel] as f32);
+        (from + (to - from) * t).round() as u8
+    });
+    Color32::from_rgb(r, g, b)
+}
@@ -0,0 +1,297 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use eframe::egui::{Color32, Rect, Response, Sense, TextEdit, Ui, Vec2, Widget, pos2};
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::colormap::{Colormap, CustomColormap};
+
+/// Size of a color map's preview strip, in points.
+const PREVIEW_SIZE: Vec2 = Vec2::new(150.0, 12.0);
+
+/// Color maps loaded from files, to pick for the waterfall alongside the
+/// built-in ones, so palettes can be shared between stations.
+///
+/// Two formats are read. A GMT `.cpt` palette, of `z r g b z r g b` rows
+/// giving the colors at each end of a slice, `r/g/b` also taken. Or JSON:
+/// a list of colors, as `"#rrggbb"` or `[r, g, b]`, spread evenly, or of
+/// `{"position": p, "color": c}` stops; or an object with the list as its
+/// `stops` and a `name`. Without a name the map takes the file's.
+pub struct ColormapFiles {
+    path: String,
+    /// Maps loaded so far, the latest last
+    loaded: Vec<Colormap>,
+    /// Map just loaded, for the waterfall to switch to
+    picked: Option<Colormap>,
+    /// Why the file couldn't be loaded, until the next load
+    error: Option<String>,
+}
+
+impl ColormapFiles {
+    pub fn new() -> Self {
+        Self {
+            path: "palette.cpt".to_string(),
+            loaded: Vec::new(),
+            picked: None,
+            error: None,
+        }
+    }
+
+    pub fn loaded(&self) -> &[Colormap] {
+        &self.loaded
+    }
+
+    /// The map just loaded, once.
+    pub fn take_picked(&mut self) -> Option<Colormap> {
+        self.picked.take()
+    }
+
+    fn load(&mut self) {
+        let path = Path::new(&self.path);
+        match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| parse(&text, path))
+        {
+            Ok(custom) => {
+                info!("Loaded the color map {} from {}", custom.name(), self.path);
+                self.error = None;
+                let colormap = Colormap::Custom(Arc::new(custom));
+                // Loading it again replaces it
+                self.loaded
+                    .retain(|loaded| loaded.label() != colormap.label());
+                self.loaded.push(colormap.clone());
+                self.picked = Some(colormap);
+            }
+            Err(e) => {
+                warn!("Failed to load a color map from {}: {}", self.path, e);
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// The color map in `text`, read as JSON if it looks like it and as a
+/// `.cpt` palette otherwise, named after the file at `path` unless it names
+/// itself.
+fn parse(text: &str, path: &Path) -> Result<CustomColormap, String> {
+    let stem = path
+        .file_stem()
+        .map_or("Custom".into(), |stem| stem.to_string_lossy());
+    if text.trim_start().starts_with(['[', '{']) {
+        parse_json(text, &stem)
+    } else {
+        parse_cpt(text, &stem)
+    }
+}
+
+/// A GMT color palette. Comments, and the background, foreground and NaN
+/// colors, are skipped.
+fn parse_cpt(text: &str, name: &str) -> Result<CustomColormap, String> {
+    let mut stops = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(['B', 'F', 'N']) {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .filter(|field| !field.is_empty())
+            .collect();
+        let slice = (fields.len() >= 8)
+            .then(|| {
+                let low = (fields[0].parse().ok()?, rgb_fields(&fields[1..4])?);
+                let high = (fields[4].parse().ok()?, rgb_fields(&fields[5..8])?);
+                Some([low, high])
+            })
+            .flatten();
+        let Some(slice) = slice else {
+            return Err(format!("line {}: expected z r g b z r g b", number + 1));
+        };
+        for stop in slice {
+            if stops.last() != Some(&stop) {
+                stops.push(stop);
+            }
+        }
+    }
+    CustomColormap::new(name, stops)
+}
+
+fn rgb_fields(fields: &[&str]) -> Option<[u8; 3]> {
+    let mut rgb = [0; 3];
+    for (channel, field) in rgb.iter_mut().zip(fields) {
+        *channel = field.parse().ok()?;
+    }
+    Some(rgb)
+}
+
+/// A JSON list of colors or stops, or an object holding one.
+fn parse_json(text: &str, name: &str) -> Result<CustomColormap, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let (name, list) = match &value {
+        Value::Object(object) => (
+            object.get("name").and_then(Value::as_str).unwrap_or(name),
+            object.get("stops"),
+        ),
+        list => (name, Some(list)),
+    };
+    let Some(Value::Array(list)) = list else {
+        return Err("expected a list of colors".to_string());
+    };
+    let last = list.len().saturating_sub(1).max(1) as f32;
+    let stops = list
+        .iter()
+        .enumerate()
+        .map(|(index, stop)| {
+            let (position, color) = match stop {
+                Value::Object(stop) => (
+                    stop.get("position")
+                        .and_then(Value::as_f64)
+                        .ok_or(format!("stop {}: expected a position", index + 1))?
+                        as f32,
+                    stop.get("color").unwrap_or(&Value::Null),
+                ),
+                color => (index as f32 / last, color),
+            };
+            let color = json_color(color).ok_or(format!(
+                "stop {}: expected \"#rrggbb\" or [r, g, b]",
+                index + 1
+            ))?;
+            Ok((position, color))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    CustomColormap::new(name, stops)
+}
+
+fn json_color(value: &Value) -> Option<[u8; 3]> {
+    match value {
+        Value::String(hex) => {
+            let hex = hex.strip_prefix('#')?;
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+            Some([channel(0)?, channel(2)?, channel(4)?])
+        }
+        Value::Array(channels) if channels.len() == 3 => {
+            let mut rgb = [0; 3];
+            for (channel, value) in rgb.iter_mut().zip(channels) {
+                *channel = u8::try_from(value.as_u64()?).ok()?;
+            }
+            Some(rgb)
+        }
+        _ => None,
+    }
+}
+
+/// A strip of `colormap` from the weakest level to the strongest.
+pub fn preview(ui: &mut Ui, colormap: &Colormap) -> Response {
+    let (rect, response) = ui.allocate_exact_size(PREVIEW_SIZE, Sense::click());
+    let painter = ui.painter_at(rect);
+    let columns = rect.width().round() as usize;
+    for column in 0..columns {
+        let x = rect.left() + column as f32;
+        painter.rect_filled(
+            Rect::from_min_max(pos2(x, rect.top()), pos2(x + 1.0, rect.bottom())),
+            0.0,
+            colormap.color(column as f32 / (columns - 1).max(1) as f32),
+        );
+    }
+    response
+}
+
+impl Widget for &mut ColormapFiles {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.add(TextEdit::singleline(&mut self.path).desired_width(150.0))
+            .on_hover_text("A .cpt palette, or a JSON list of \"#rrggbb\" colors");
+        if ui.button("Add map").clicked() {
+            self.load();
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::LIGHT_RED, format!("Not loaded: {e}"));
+        }
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors(colormap: &CustomColormap, fractions: &[f32]) -> Vec<Color32> {
+        let colormap = Colormap::Custom(Arc::new(colormap.clone()));
+        fractions
+            .iter()
+            .map(|&fraction| colormap.color(fraction))
+            .collect()
+    }
+
+    #[test]
+    fn reads_a_cpt_palette() {
+        let cpt = "# blue to red, then a step to yellow\n\
+                   -100 0/0/255 -75 255/0/0\n\
+                   -75  255 0 0   -50 255 0 0\n\
+                   -50  255 255 0 0   255 255 0\n\
+                   B 0 0 0\n\
+                   F 255 255 255\n";
+        let colormap = parse(cpt, Path::new("/palettes/storm.cpt")).unwrap();
+        assert_eq!(colormap.name(), "storm");
+        assert_eq!(
+            colors(&colormap, &[0.0, 0.125, 0.5, 0.6, 1.0]),
+            [
+                Color32::from_rgb(0, 0, 255),
+                Color32::from_rgb(128, 0, 128),
+                Color32::from_rgb(255, 255, 0),
+                Color32::from_rgb(255, 255, 0),
+                Color32::from_rgb(255, 255, 0),
+            ]
+        );
+        assert_eq!(colors(&colormap, &[0.49]), [Color32::from_rgb(255, 0, 0)]);
+    }
+
+    #[test]
+    fn reads_json_colors_and_stops() {
+        let even = parse(r##"["#000000", [255, 255, 255]]"##, Path::new("gray.json")).unwrap();
+        assert_eq!(even.name(), "gray");
+        assert_eq!(colors(&even, &[0.5]), [Color32::from_rgb(128, 128, 128)]);
+
+        let named = r##"{
+            "name": "Dusk",
+            "stops": [
+                {"position": 0, "color": "#000040"},
+                {"position": 0.75, "color": "#ff8000"},
+                {"position": 1, "color": [255, 255, 255]}
+            ]
+        }"##;
+        let named = parse(named, Path::new("dusk.json")).unwrap();
+        assert_eq!(named.name(), "Dusk");
+        assert_eq!(
+            colors(&named, &[0.75, 1.0]),
+            [Color32::from_rgb(255, 128, 0), Color32::WHITE]
+        );
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_color_map() {
+        let path = Path::new("bad.json");
+        assert_eq!(
+            parse(r##"["#00ff00"]"##, path),
+            Err("a color map needs two or more colors".to_string())
+        );
+        assert_eq!(
+            parse(r##"["#00ff00", [0, 300, 0]]"##, path),
+            Err("stop 2: expected \"#rrggbb\" or [r, g, b]".to_string())
+        );
+        assert_eq!(
+            parse(
+                r##"[{"position": 1, "color": "#ffffff"}, {"position": 0, "color": "#000000"}]"##,
+                path
+            ),
+            Err("positions must rise from one color to the next".to_string())
+        );
+        assert_eq!(
+            parse("0 0 0 0 1 255 255\n", Path::new("short.cpt")),
+            Err("line 1: expected z r g b z r g b".to_string())
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use eframe::egui::{Button, DragValue, Response, Ui, Widget};
+
+/// Pins a stretch of the waterfall from a while ago beside the live view,
+/// at the live view's band, to compare the activity then against now
+/// without exporting images.
+pub struct ComparisonPanel {
+    /// How long before the newest line the stretch ends, in minutes
+    minutes_ago: f64,
+    /// How long the stretch lasts, in seconds
+    seconds: f64,
+    /// Sample time of the newest line, which the stretch is taken back from
+    latest: Option<Duration>,
+    /// Sample times of the pinned stretch, and how long ago it ended when
+    /// pinned, in minutes
+    pinned: Option<(RangeInclusive<Duration>, f64)>,
+}
+
+impl ComparisonPanel {
+    pub fn new() -> Self {
+        Self {
+            minutes_ago: 60.0,
+            seconds: 60.0,
+            latest: None,
+            pinned: None,
+        }
+    }
+
+    pub fn set_latest(&mut self, latest: Option<Duration>) {
+        self.latest = latest;
+    }
+
+    /// Sample times of the stretch to show beside the live view, if one is
+    /// pinned.
+    pub fn pinned(&self) -> Option<RangeInclusive<Duration>> {
+        self.pinned.as_ref().map(|(times, _)| times.clone())
+    }
+
+    /// Heading of the pinned stretch beside the live view.
+    pub fn label(&self) -> Option<String> {
+        let (_, minutes_ago) = self.pinned.as_ref()?;
+        Some(format!("Then: {minutes_ago} min ago"))
+    }
+
+    /// Pin the stretch set, back from the newest line.
+    fn pin(&mut self) {
+        let Some(latest) = self.latest else {
+            return;
+        };
+        let end = latest.saturating_sub(Duration::from_secs_f64(self.minutes_ago * 60.0));
+        let start = end.saturating_sub(Duration::from_secs_f64(self.seconds));
+        self.pinned = Some((start..=end, self.minutes_ago));
+    }
+}
+
+impl Widget for &mut ComparisonPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Then vs Now");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.minutes_ago)
+                    .range(0.0..=1440.0)
+                    .speed(1.0)
+                    .suffix(" min ago"),
+            );
+            ui.add(
+                DragValue::new(&mut self.seconds)
+                    .range(1.0..=3600.0)
+                    .speed(1.0)
+                    .suffix(" s long"),
+            );
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.latest.is_some(), Button::new("Pin"))
+                .on_hover_text("Show the stretch beside the live waterfall, at the same band")
+                .clicked()
+            {
+                self.pin();
+            }
+            if self.pinned.is_some() && ui.button("Unpin").clicked() {
+                self.pinned = None;
+            }
+        });
+
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_the_stretch_back_from_the_newest_line() {
+        let mut panel = ComparisonPanel::new();
+        panel.pin();
+        assert_eq!(panel.pinned(), None);
+
+        panel.set_latest(Some(Duration::from_secs(3_700)));
+        panel.pin();
+        assert_eq!(
+            panel.pinned(),
+            Some(Duration::from_secs(40)..=Duration::from_secs(100))
+        );
+        assert_eq!(panel.label().as_deref(), Some("Then: 60 min ago"));
+
+        // Before the record started, it is cut short
+        panel.seconds = 600.0;
+        panel.pin();
+        assert_eq!(
+            panel.pinned(),
+            Some(Duration::ZERO..=Duration::from_secs(100))
+        );
+    }
+}
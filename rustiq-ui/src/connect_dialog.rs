@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::thread;
+
+use eframe::egui::{Button, Color32, Context, TextEdit, Ui, Window};
+use flume::{Receiver, Sender};
+
+use rustiq_messages::{Command, Event};
+
+/// The UI's channels to an engine: events, spectrum frames and commands.
+pub type EngineChannels = (Receiver<Event>, Receiver<Event>, Sender<Command>);
+
+/// Opens a connection to the engine at an address typed in the connect
+/// dialog, presenting the token typed there if any, and bridges it onto
+/// channels for the UI. The connection itself is the caller's: wire frames
+/// over a socket, JSON over a WebSocket to a headless engine, or anything
+/// else behind a `Transport`.
+pub type Connector =
+    Arc<dyn Fn(&str, Option<&str>) -> anyhow::Result<EngineChannels> + Send + Sync>;
+
+/// Switches the UI over to an engine elsewhere on the network, while it
+/// runs. The connection is opened on a thread of its own, so a slow or
+/// unreachable engine doesn't hold up the UI.
+pub struct ConnectDialog {
+    /// How to connect, if the UI can switch engines at all
+    connector: Option<Connector>,
+    open: bool,
+    address: String,
+    token: String,
+    /// Connection being opened
+    pending: Option<Receiver<anyhow::Result<EngineChannels>>>,
+    /// Channels to the engine just connected to, for the app to switch to
+    connected: Option<EngineChannels>,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+impl ConnectDialog {
+    pub fn new(connector: Option<Connector>) -> Self {
+        Self {
+            connector,
+            open: false,
+            address: String::new(),
+            token: String::new(),
+            pending: None,
+            connected: None,
+            error: None,
+        }
+    }
+
+    /// Channels to an engine connected to since last asked.
+    pub fn take_connected(&mut self) -> Option<EngineChannels> {
+        self.connected.take()
+    }
+
+    /// Status bar button opening the dialog, if engines can be switched.
+    pub fn show_toggle(&mut self, ui: &mut Ui) {
+        if self.connector.is_some() {
+            ui.toggle_value(&mut self.open, "Switch engine")
+                .on_hover_text("Switch to an engine elsewhere on the network");
+        }
+    }
+
+    /// The dialog while it is open.
+    pub fn show(&mut self, ctx: &Context) {
+        self.poll();
+        let Some(connector) = &self.connector else {
+            return;
+        };
+        let mut open = self.open;
+        let mut connect = false;
+        Window::new("Switch Engine")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Address");
+                ui.add(
+                    TextEdit::singleline(&mut self.address)
+                        .hint_text("HOST:PORT or ws://HOST:PORT"),
+                )
+                .on_hover_text(
+                    "HOST:PORT for an engine started with rustiq engine --socket, \
+                     ws://HOST:PORT (wss:// with TLS) for one started with \
+                     rustiq --headless",
+                );
+                ui.label("Token");
+                ui.add(
+                    TextEdit::singleline(&mut self.token)
+                        .password(true)
+                        .hint_text("If the engine asks for one"),
+                );
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let ready = self.pending.is_none() && !self.address.trim().is_empty();
+                    connect = ui.add_enabled(ready, Button::new("Connect")).clicked();
+                    if self.pending.is_some() {
+                        ui.spinner();
+                        ui.label("Connecting…");
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::LIGHT_RED, error);
+                }
+            });
+        self.open = open;
+
+        if connect {
+            let connector = connector.clone();
+            let address = self.address.trim().to_string();
+            let token = Some(self.token.trim().to_string()).filter(|token| !token.is_empty());
+            let (done_tx, done_rx) = flume::bounded(1);
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let _ = done_tx.send(connector(&address, token.as_deref()));
+                ctx.request_repaint();
+            });
+            self.pending = Some(done_rx);
+            self.error = None;
+        }
+    }
+
+    /// Take the outcome of the connection being opened, once there is one.
+    fn poll(&mut self) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        let outcome = match pending.try_recv() {
+            Ok(outcome) => outcome,
+            Err(flume::TryRecvError::Empty) => return,
+            Err(flume::TryRecvError::Disconnected) => {
+                Err(anyhow::anyhow!("the connection attempt was abandoned"))
+            }
+        };
+        self.pending = None;
+        match outcome {
+            Ok(channels) => {
+                self.connected = Some(channels);
+                self.open = false;
+            }
+            Err(e) => self.error = Some(format!("{e:#}")),
+        }
+    }
+}
@@ -0,0 +1,575 @@
+use eframe::egui::text::{CCursor, CCursorRange};
+use eframe::egui::{
+    Context, Key, Modifiers, RichText, ScrollArea, TextEdit, TextStyle, Ui, Window,
+};
+use flume::Sender;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rustiq_messages::{
+    Averaging, Command, Decibels, EngineState, Hertz, RecordingFormat, SourceKind,
+};
+
+use crate::frequency::{self, FrequencyUnit};
+
+/// Lines of output kept.
+const SCROLLBACK: usize = 500;
+
+/// Name, arguments and description of each console command.
+const COMMANDS: [(&str, &str, &str); 25] = [
+    ("help", "", "list the commands"),
+    ("state", "", "show the engine's state"),
+    ("clear", "", "clear the output"),
+    ("tune", "FREQ", "retune, e.g. tune 433.92M"),
+    ("gain", "DB", "set the source gain"),
+    ("fft", "SIZE", "set the number of bins"),
+    ("zoom", "N", "decimate by N ahead of the FFT"),
+    (
+        "shift",
+        "OFFSET",
+        "center the spectrum OFFSET off, e.g. shift -250k",
+    ),
+    (
+        "average",
+        "off|exp|block [N]",
+        "average spectrum frames, by factor N or in blocks of N",
+    ),
+    (
+        "offset-tuning",
+        "on|off",
+        "tune hardware off the center frequency",
+    ),
+    (
+        "speed",
+        "X|off",
+        "play a file at X times real time, 0.5 to 2",
+    ),
+    ("seek", "SECONDS", "play a file from SECONDS in"),
+    ("pause", "", "hold a file where it is"),
+    ("resume", "", "carry on playing a paused file"),
+    (
+        "squelch",
+        "DB|off",
+        "mute the audio while the channel is below DB",
+    ),
+    ("record", "PATH [sigmf|raw]", "record the IQ stream"),
+    ("stop-recording", "", "finish the recording"),
+    ("carrier", "FREQ", "measure the carrier nearest FREQ"),
+    ("stop-carrier", "", "stop the carrier measurement"),
+    ("bursts", "DB", "detect bursts DB above the noise floor"),
+    ("stop-bursts", "", "stop the burst detection"),
+    ("impulses", "DB", "count impulses DB above the noise floor"),
+    ("stop-impulses", "", "stop the impulse counter"),
+    ("self-test", "", "test the receive chain"),
+    ("resync", "", "ask the engine for its state again"),
+];
+
+/// What a console command asks for.
+#[derive(Debug)]
+enum Action {
+    Send(Command),
+    State,
+    Help,
+    Clear,
+}
+
+/// A console for typing commands to the engine, for power users and
+/// debugging. A line holds one command or several separated by `;`, run in
+/// turn; the arrow keys step through earlier lines and Tab completes
+/// command names.
+pub struct Console {
+    cmd_tx: Sender<Command>,
+    open: bool,
+    input: String,
+    output: VecDeque<String>,
+    /// Lines entered, oldest first
+    history: Vec<String>,
+    /// Index into `history` while stepping through it
+    browsing: Option<usize>,
+}
+
+impl Console {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            open: false,
+            input: String::new(),
+            output: VecDeque::new(),
+            history: Vec::new(),
+            browsing: None,
+        }
+    }
+
+    fn print(&mut self, line: impl Into<String>) {
+        if self.output.len() == SCROLLBACK {
+            self.output.pop_front();
+        }
+        self.output.push_back(line.into());
+    }
+
+    /// Run the entered line against the engine in `state`.
+    fn run(&mut self, state: Option<&EngineState>) {
+        let line = std::mem::take(&mut self.input);
+        let line = line.trim();
+        self.browsing = None;
+        if line.is_empty() {
+            return;
+        }
+        self.print(format!("> {line}"));
+        if self.history.last().is_none_or(|last| last != line) {
+            self.history.push(line.to_string());
+        }
+        // Nothing runs unless the whole line makes sense
+        let actions = match parse(line) {
+            Ok(actions) => actions,
+            Err(e) => {
+                self.print(format!("error: {e}"));
+                return;
+            }
+        };
+        for action in actions {
+            match action {
+                Action::Send(command) => {
+                    if self.cmd_tx.send(command).is_err() {
+                        self.print("error: the engine is gone");
+                        return;
+                    }
+                }
+                Action::State => match state {
+                    Some(state) => {
+                        for line in describe(state) {
+                            self.print(line);
+                        }
+                    }
+                    None => self.print("no state from the engine yet"),
+                },
+                Action::Help => {
+                    for (name, args, description) in COMMANDS {
+                        self.print(format!("{:<32} {description}", format!("{name} {args}")));
+                    }
+                    self.print("Separate commands with ; to run several in turn.");
+                }
+                Action::Clear => self.output.clear(),
+            }
+        }
+    }
+
+    /// Step `back` or forward through the history into the input.
+    fn browse(&mut self, back: bool) {
+        let index = match (self.browsing, back) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|&next| next < self.history.len()),
+        };
+        self.browsing = index;
+        self.input = index.map_or_else(String::new, |index| self.history[index].clone());
+    }
+
+    /// Complete the command name being typed, listing the choices when
+    /// there are several.
+    fn complete(&mut self) {
+        let start = self.input.rfind(';').map_or(0, |semicolon| semicolon + 1);
+        let word = self.input[start..].trim_start().to_string();
+        if word.contains(char::is_whitespace) {
+            return;
+        }
+        let matches: Vec<&str> = COMMANDS
+            .iter()
+            .map(|(name, _, _)| *name)
+            .filter(|name| name.starts_with(&word))
+            .collect();
+        let completion = match matches.as_slice() {
+            [] => return,
+            [name] => format!("{name} "),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.len(), |len, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                self.print(matches.join("  "));
+                first[..common].to_string()
+            }
+        };
+        let word_start = self.input.len() - word.len();
+        self.input.replace_range(word_start.., &completion);
+    }
+
+    /// Status bar toggle.
+    pub fn show_toggle(&mut self, ui: &mut Ui) {
+        ui.toggle_value(&mut self.open, "Console")
+            .on_hover_text("Type commands to the engine");
+    }
+
+    /// The console window while it is open, for the engine in `state`.
+    pub fn show(&mut self, ctx: &Context, state: Option<&EngineState>) {
+        let mut open = self.open;
+        Window::new("Console")
+            .open(&mut open)
+            .default_size([520.0, 300.0])
+            .show(ctx, |ui| {
+                ScrollArea::vertical()
+                    .max_height(240.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &self.output {
+                            ui.label(RichText::new(line).monospace());
+                        }
+                    });
+                ui.separator();
+                let id = ui.make_persistent_id("console_input");
+                let mut edited = false;
+                // The keys the console uses are taken before the text field
+                // sees them
+                if ui.memory(|memory| memory.has_focus(id)) {
+                    let (up, down, tab) = ui.input_mut(|input| {
+                        (
+                            input.consume_key(Modifiers::NONE, Key::ArrowUp),
+                            input.consume_key(Modifiers::NONE, Key::ArrowDown),
+                            input.consume_key(Modifiers::NONE, Key::Tab),
+                        )
+                    });
+                    if up || down {
+                        self.browse(up);
+                        edited = true;
+                    }
+                    if tab {
+                        self.complete();
+                        edited = true;
+                    }
+                }
+                let mut output = TextEdit::singleline(&mut self.input)
+                    .id(id)
+                    .font(TextStyle::Monospace)
+                    .hint_text("help for commands")
+                    .desired_width(f32::INFINITY)
+                    .lock_focus(true)
+                    .show(ui);
+                let enter = ui.input(|input| input.key_pressed(Key::Enter));
+                if output.response.lost_focus() && enter {
+                    self.run(state);
+                    output.response.request_focus();
+                    edited = true;
+                }
+                if edited {
+                    let end = CCursor::new(self.input.chars().count());
+                    output
+                        .state
+                        .cursor
+                        .set_char_range(Some(CCursorRange::one(end)));
+                    output.state.store(ui.ctx(), id);
+                }
+            });
+        self.open = open;
+    }
+}
+
+/// The actions of a console line.
+fn parse(line: &str) -> Result<Vec<Action>, String> {
+    line.split(';')
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+        .map(parse_command)
+        .collect()
+}
+
+fn parse_command(command: &str) -> Result<Action, String> {
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+    let arity = COMMANDS
+        .iter()
+        .find(|(known, _, _)| *known == name)
+        .map(|(_, args, _)| args)
+        .ok_or_else(|| format!("unknown command {name}, try help"))?;
+    let usage = || format!("usage: {name} {arity}");
+    let arg = |index: usize| args.get(index).copied().ok_or_else(usage);
+    let decibels = |text: &str| {
+        text.trim_end_matches("dB")
+            .parse()
+            .map(Decibels)
+            .map_err(|_| format!("{text} is not a level in dB"))
+    };
+    let max_args = if arity.is_empty() {
+        0
+    } else {
+        arity.split(' ').count()
+    };
+    if args.len() > max_args {
+        return Err(usage());
+    }
+    let command = match name {
+        "help" => return Ok(Action::Help),
+        "state" => return Ok(Action::State),
+        "clear" => return Ok(Action::Clear),
+        "tune" => Command::SetCenterFrequency(parse_frequency(arg(0)?)?),
+        "gain" => Command::SetGain(decibels(arg(0)?)?),
+        "fft" => {
+            let size = arg(0)?;
+            match size.parse::<usize>() {
+                Ok(size) if size.is_power_of_two() => Command::SetFftSize(size),
+                _ => return Err(format!("{size} is not a power of two")),
+            }
+        }
+        "zoom" => {
+            let decimation = arg(0)?;
+            match decimation.parse::<usize>() {
+                Ok(decimation) if decimation.is_power_of_two() => {
+                    Command::SetDecimation(decimation)
+                }
+                _ => return Err(format!("{decimation} is not a power of two")),
+            }
+        }
+        "shift" => {
+            let offset = arg(0)?;
+            let (sign, magnitude) = match offset.strip_prefix('-') {
+                Some(magnitude) => (-1, magnitude),
+                None => (1, offset.strip_prefix('+').unwrap_or(offset)),
+            };
+            let magnitude =
+                parse_frequency(magnitude).map_err(|_| format!("{offset} is not a frequency"))?;
+            Command::SetFrequencyOffset(sign * magnitude.as_hz() as i64)
+        }
+        "average" => {
+            let averaging = match (arg(0)?, args.get(1)) {
+                ("off", None) => Averaging::Off,
+                ("exp", Some(factor)) => {
+                    Averaging::Exponential(factor.parse().map_err(|_| usage())?)
+                }
+                ("block", Some(frames)) => Averaging::Block(frames.parse().map_err(|_| usage())?),
+                _ => return Err(usage()),
+            };
+            if !averaging.is_valid() {
+                return Err(format!(
+                    "the factor is above 0 and up to 1, blocks are 1 to {} frames",
+                    Averaging::MAX_BLOCK
+                ));
+            }
+            Command::SetAveraging(averaging)
+        }
+        "offset-tuning" => match arg(0)? {
+            "on" => Command::SetOffsetTuning(true),
+            "off" => Command::SetOffsetTuning(false),
+            _ => return Err(usage()),
+        },
+        "speed" => match arg(0)? {
+            "off" => Command::SetPlaybackSpeed(None),
+            speed => match speed.trim_end_matches(['x', '×']).parse::<f32>() {
+                Ok(speed) if (0.5..=2.0).contains(&speed) => Command::SetPlaybackSpeed(Some(speed)),
+                _ => return Err(format!("{speed} is not a speed from 0.5 to 2")),
+            },
+        },
+        "seek" => match arg(0)?.trim_end_matches('s').parse::<f64>() {
+            Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                Command::Seek(Duration::from_secs_f64(seconds))
+            }
+            _ => return Err(format!("{} is not a time in seconds", arg(0)?)),
+        },
+        "pause" => Command::Pause,
+        "resume" => Command::Resume,
+        "squelch" => match arg(0)? {
+            "off" => Command::SetSquelch(None),
+            level => Command::SetSquelch(Some(decibels(level)?)),
+        },
+        "record" => {
+            let format = match args.get(1) {
+                None => RecordingFormat::SigMf,
+                Some(&"sigmf") => RecordingFormat::SigMf,
+                Some(&"raw") => RecordingFormat::Raw,
+                Some(_) => return Err(usage()),
+            };
+            Command::StartRecording {
+                path: PathBuf::from(arg(0)?),
+                format,
+            }
+        }
+        "stop-recording" => Command::StopRecording,
+        "carrier" => Command::StartCarrierMeasurement(parse_frequency(arg(0)?)?),
+        "stop-carrier" => Command::StopCarrierMeasurement,
+        "bursts" => Command::StartBurstDetection(decibels(arg(0)?)?),
+        "stop-bursts" => Command::StopBurstDetection,
+        "impulses" => Command::StartImpulseCounter(decibels(arg(0)?)?),
+        "stop-impulses" => Command::StopImpulseCounter,
+        "self-test" => Command::RunSelfTest,
+        "resync" => Command::Resync,
+        _ => unreachable!("{name} is in COMMANDS"),
+    };
+    Ok(Action::Send(command))
+}
+
+/// A frequency in Hz, or in kHz, MHz or GHz with a `k`, `M` or `G` suffix.
+fn parse_frequency(text: &str) -> Result<Hertz, String> {
+    frequency::parse_frequency(text, FrequencyUnit::Hz)
+}
+
+/// Lines summing up `state`.
+fn describe(state: &EngineState) -> Vec<String> {
+    let mut lines = vec![
+        format!(
+            "source       {}",
+            SourceKind::of(&state.source_config).label()
+        ),
+        format!("center       {}", state.center_frequency),
+        format!("sample rate  {}", state.sample_rate),
+        format!("gain         {}", state.gain),
+        format!("fft          {} bins", state.fft_size),
+        format!(
+            "zoom         ×{} ({} wide)",
+            state.decimation, state.spectrum_span
+        ),
+        format!("shift        {:+} Hz", state.frequency_offset),
+        format!("average      {}", describe_averaging(state.averaging)),
+        format!(
+            "offset tune  {}",
+            if state.offset_tuning { "on" } else { "off" }
+        ),
+    ];
+    if let Some(rate) = state.device_sample_rate {
+        lines.push(format!("device rate  {rate}, resampled"));
+    }
+    if let Some(speed) = state.playback_speed {
+        lines.push(format!("playback     {speed}×"));
+    }
+    if state.playback_paused {
+        lines.push("playback     paused".to_string());
+    }
+    if !state.playback_start.is_zero() {
+        lines.push(format!(
+            "played from  {:.1} s",
+            state.playback_start.as_secs_f64()
+        ));
+    }
+    if let Some(threshold) = state.squelch {
+        lines.push(format!("squelch      {threshold}"));
+    }
+    if let Some(frequency) = state.carrier_measurement {
+        lines.push(format!("carrier      {frequency}"));
+    }
+    if let Some(threshold) = state.burst_detection {
+        lines.push(format!("bursts       {threshold}"));
+    }
+    if let Some(threshold) = state.impulse_counter {
+        lines.push(format!("impulses     {threshold}"));
+    }
+    lines
+}
+
+fn describe_averaging(averaging: Averaging) -> String {
+    match averaging {
+        Averaging::Off => "off".to_string(),
+        Averaging::Exponential(factor) => format!("exponential, factor {factor}"),
+        Averaging::Block(frames) => format!("blocks of {frames} frames"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frequencies_with_suffixes() {
+        assert_eq!(parse_frequency("433.92M"), Ok(Hertz(433_920_000)));
+        assert_eq!(parse_frequency("145500k"), Ok(Hertz(145_500_000)));
+        assert_eq!(parse_frequency("1.2G"), Ok(Hertz(1_200_000_000)));
+        assert_eq!(parse_frequency("7074000Hz"), Ok(Hertz(7_074_000)));
+        assert!(parse_frequency("fast").is_err());
+    }
+
+    #[test]
+    fn parses_a_line_only_if_every_command_is_valid() {
+        let actions = parse(
+            "tune 145M; gain -6dB ;fft 8192; zoom 8; shift -2.5k; speed 0.75; seek 90; pause; squelch -45",
+        )
+        .unwrap();
+        assert!(
+            matches!(
+                actions.as_slice(),
+                [
+                    Action::Send(Command::SetCenterFrequency(Hertz(145_000_000))),
+                    Action::Send(Command::SetGain(Decibels(-6.0))),
+                    Action::Send(Command::SetFftSize(8192)),
+                    Action::Send(Command::SetDecimation(8)),
+                    Action::Send(Command::SetFrequencyOffset(-2_500)),
+                    Action::Send(Command::SetPlaybackSpeed(Some(0.75))),
+                    Action::Send(Command::Seek(position)),
+                    Action::Send(Command::Pause),
+                    Action::Send(Command::SetSquelch(Some(Decibels(-45.0)))),
+                ] if position.as_secs() == 90
+            ),
+            "{actions:?}"
+        );
+        assert_eq!(
+            parse("tune 145M; fft 1000").unwrap_err(),
+            "1000 is not a power of two"
+        );
+        assert_eq!(parse("gain").unwrap_err(), "usage: gain DB");
+        assert_eq!(
+            parse("launch").unwrap_err(),
+            "unknown command launch, try help"
+        );
+    }
+
+    #[test]
+    fn parses_averaging() {
+        let averaging = |line| match parse(line) {
+            Ok(actions) => match actions.as_slice() {
+                [Action::Send(Command::SetAveraging(averaging))] => Ok(*averaging),
+                _ => panic!("{actions:?}"),
+            },
+            Err(e) => Err(e),
+        };
+        assert_eq!(averaging("average off"), Ok(Averaging::Off));
+        assert_eq!(
+            averaging("average exp 0.2"),
+            Ok(Averaging::Exponential(0.2))
+        );
+        assert_eq!(averaging("average block 10"), Ok(Averaging::Block(10)));
+        assert_eq!(
+            averaging("average block"),
+            Err("usage: average off|exp|block [N]".to_string())
+        );
+        assert!(averaging("average exp 2").is_err());
+    }
+
+    #[test]
+    fn completes_command_names() {
+        let (cmd_tx, _cmd_rx) = flume::unbounded();
+        let mut console = Console::new(cmd_tx);
+        console.input = "tune 1M; of".to_string();
+        console.complete();
+        assert_eq!(console.input, "tune 1M; offset-tuning ");
+
+        console.input = "stop-".to_string();
+        console.complete();
+        assert_eq!(console.input, "stop-");
+        console.input = "stop-r".to_string();
+        console.complete();
+        assert_eq!(console.input, "stop-recording ");
+    }
+
+    #[test]
+    fn steps_through_history() {
+        let (cmd_tx, cmd_rx) = flume::unbounded();
+        let mut console = Console::new(cmd_tx);
+        for line in ["tune 1M", "gain 10", "gain 10"] {
+            console.input = line.to_string();
+            console.run(None);
+        }
+        assert_eq!(cmd_rx.len(), 3);
+        console.browse(true);
+        assert_eq!(console.input, "gain 10");
+        console.browse(true);
+        assert_eq!(console.input, "tune 1M");
+        console.browse(true);
+        assert_eq!(console.input, "tune 1M");
+        console.browse(false);
+        assert_eq!(console.input, "gain 10");
+        console.browse(false);
+        assert_eq!(console.input, "");
+    }
+}
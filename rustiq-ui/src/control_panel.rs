@@ -1,118 +1,514 @@
-use eframe::egui::{ComboBox, DragValue, Response, TextEdit, Ui, Widget};
+use eframe::egui::{
+    Checkbox, CollapsingHeader, ComboBox, DragValue, Response, Slider, TextEdit, Ui, Widget,
+};
 use flume::Sender;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use rustiq_messages::{Command, Decibels, Hertz, SourceConfig};
+use rustiq_messages::{
+    Averaging, Command, Decibels, Hertz, IqFormat, SourceCapability, SourceConfig, SourceDevice,
+    SourceKind, StageGain, Sweep, Tone,
+};
 
-/// Which source type is selected in the UI dropdown.
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum SourceType {
-    SignalGenerator,
-    File,
-}
+/// FFT sizes offered for the spectrum.
+const FFT_SIZES: [usize; 6] = [1_024, 2_048, 4_096, 8_192, 16_384, 65_536];
 
-impl SourceType {
-    fn label(&self) -> &'static str {
-        match self {
-            Self::SignalGenerator => "Signal Generator",
-            Self::File => "IQ File",
-        }
-    }
+/// Decimations offered ahead of the spectrum FFT.
+const DECIMATIONS: [usize; 9] = [1, 2, 4, 8, 16, 32, 64, 128, 256];
 
-    fn from_config(config: &SourceConfig) -> Self {
-        match config {
-            SourceConfig::SignalGenerator { .. } => Self::SignalGenerator,
-            SourceConfig::File { .. } => Self::File,
-        }
-    }
+/// Speeds offered for playing a file, unpaced first.
+const PLAYBACK_SPEEDS: [Option<f32>; 6] =
+    [None, Some(0.5), Some(0.75), Some(1.0), Some(1.5), Some(2.0)];
+
+/// Averaging each mode starts out with when picked.
+const AVERAGING_MODES: [Averaging; 3] = [
+    Averaging::Off,
+    Averaging::Exponential(0.3),
+    Averaging::Block(8),
+];
+
+/// Which of the engine's sources a control panel configures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Slot {
+    Main,
+    /// Shown next to the main source; may be absent
+    Second,
 }
 
 /// Control panel widget for configuring the input source.
 pub struct ControlPanel {
     cmd_tx: Sender<Command>,
+    slot: Slot,
+    /// Whether the engine has a source in this slot
+    active: bool,
+    /// Whether the source in this slot is a file
+    playing_file: bool,
     pending_config: SourceConfig,
     has_pending_changes: bool,
     waiting_for_apply: bool,
+    /// Sources the engine can open
+    sources: Vec<SourceCapability>,
+    /// Bins in the engine's spectrum, shared by both sources
+    fft_size: usize,
+    /// Decimation ahead of the spectrum FFT, shared by both sources
+    decimation: usize,
+    /// How far off the center the spectrum is centered, in Hz; the field's
+    /// value while it is edited
+    frequency_offset: i64,
+    /// How the engine averages spectrum frames; the slider's value while
+    /// it is dragged
+    averaging: Averaging,
+    /// Whether the engine tunes hardware sources off the center
+    offset_tuning: bool,
+    /// Times real time the engine plays a file at, if paced
+    playback_speed: Option<f32>,
+    /// Rate the source's hardware runs at, when the engine resamples it
+    device_sample_rate: Option<Hertz>,
 }
 
 impl ControlPanel {
     pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self::for_slot(cmd_tx, Slot::Main)
+    }
+
+    /// A panel for the second source of a dual view.
+    pub fn second(cmd_tx: Sender<Command>) -> Self {
+        Self::for_slot(cmd_tx, Slot::Second)
+    }
+
+    fn for_slot(cmd_tx: Sender<Command>, slot: Slot) -> Self {
         Self {
             cmd_tx,
+            slot,
+            active: slot == Slot::Main,
+            playing_file: false,
             pending_config: SourceConfig::default(),
             has_pending_changes: false,
             waiting_for_apply: false,
+            sources: [SourceKind::SignalGenerator, SourceKind::File]
+                .map(|kind| SourceCapability {
+                    kind,
+                    max_sample_rate: None,
+                    sample_rates: Vec::new(),
+                    devices: Vec::new(),
+                })
+                .to_vec(),
+            fft_size: 4_096,
+            decimation: 1,
+            frequency_offset: 0,
+            averaging: Averaging::Off,
+            offset_tuning: false,
+            playback_speed: None,
+            device_sample_rate: None,
         }
     }
 
-    /// Update from engine state snapshot.
-    pub fn update_from_engine_state(&mut self, config: &SourceConfig) {
-        self.pending_config = config.clone();
+    /// Offer only the sources the engine supports.
+    pub fn set_sources(&mut self, sources: &[SourceCapability]) {
+        self.sources = sources.to_vec();
+    }
+
+    /// Highest sample rate to offer for the selected source.
+    fn max_sample_rate(&self) -> u64 {
+        self.sources
+            .iter()
+            .find(|source| source.kind == self.current_source_type())
+            .and_then(|source| source.max_sample_rate)
+            .map_or(u64::MAX, |rate| rate.as_hz())
+    }
+
+    /// Sample rates to offer for the selected source, or the device `args`
+    /// names; empty if it runs at any.
+    fn sample_rates(&self, args: Option<&str>) -> Vec<Hertz> {
+        self.sources
+            .iter()
+            .find(|source| source.kind == self.current_source_type())
+            .map_or(Vec::new(), |source| match args {
+                Some(args) => source
+                    .devices
+                    .iter()
+                    .find(|device| device.args == args)
+                    .map_or(Vec::new(), |device| device.sample_rates.clone()),
+                None => source.sample_rates.clone(),
+            })
+    }
+
+    /// Devices the engine found for the selected source.
+    fn devices(&self) -> &[SourceDevice] {
+        self.sources
+            .iter()
+            .find(|source| source.kind == self.current_source_type())
+            .map_or(&[], |source| &source.devices)
+    }
+
+    /// Update from engine state snapshot, `None` if the engine has no source
+    /// in this panel's slot.
+    pub fn update_from_engine_state(&mut self, config: Option<&SourceConfig>) {
+        if let Some(config) = config {
+            self.pending_config = config.clone();
+        }
+        self.active = config.is_some();
+        self.playing_file = matches!(config, Some(SourceConfig::File { .. }));
         self.has_pending_changes = false;
         self.waiting_for_apply = false;
     }
 
-    fn current_source_type(&self) -> SourceType {
-        SourceType::from_config(&self.pending_config)
+    pub fn set_fft_size(&mut self, fft_size: usize) {
+        self.fft_size = fft_size;
+    }
+
+    pub fn set_decimation(&mut self, decimation: usize) {
+        self.decimation = decimation;
+    }
+
+    pub fn set_frequency_offset(&mut self, frequency_offset: i64) {
+        self.frequency_offset = frequency_offset;
+    }
+
+    pub fn set_playback_speed(&mut self, playback_speed: Option<f32>) {
+        self.playback_speed = playback_speed;
+    }
+
+    pub fn set_averaging(&mut self, averaging: Averaging) {
+        self.averaging = averaging;
+    }
+
+    pub fn set_offset_tuning(&mut self, offset_tuning: bool) {
+        self.offset_tuning = offset_tuning;
+    }
+
+    pub fn set_device_sample_rate(&mut self, device_sample_rate: Option<Hertz>) {
+        self.device_sample_rate = device_sample_rate;
+    }
+
+    /// Averaging mode and its factor or block length. Each change rebuilds
+    /// the engine's graph, so a slider sends its value once let go.
+    fn averaging_ui(&mut self, ui: &mut Ui) {
+        let mut mode = self.averaging;
+        ComboBox::from_label("Averaging")
+            .selected_text(mode.label())
+            .show_ui(ui, |ui| {
+                for choice in AVERAGING_MODES {
+                    if ui
+                        .selectable_label(choice.label() == mode.label(), choice.label())
+                        .clicked()
+                        && choice.label() != mode.label()
+                    {
+                        mode = choice;
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Smooth the noise in the spectrum, at the cost of slower updates");
+        if mode != self.averaging {
+            self.averaging = mode;
+            let _ = self.cmd_tx.send(Command::SetAveraging(mode));
+        }
+
+        let response = match &mut self.averaging {
+            Averaging::Off => return,
+            Averaging::Exponential(factor) => ui
+                .add(
+                    Slider::new(factor, 0.01..=1.0)
+                        .logarithmic(true)
+                        .text("Factor"),
+                )
+                .on_hover_text("Weight of each new frame: lower smooths more"),
+            Averaging::Block(frames) => ui
+                .add(
+                    Slider::new(frames, 2..=100)
+                        .logarithmic(true)
+                        .text("Frames"),
+                )
+                .on_hover_text("Frames averaged into each one shown"),
+        };
+        if response.drag_stopped() || (response.changed() && !response.dragged()) {
+            let _ = self.cmd_tx.send(Command::SetAveraging(self.averaging));
+        }
+    }
+
+    fn current_source_type(&self) -> SourceKind {
+        SourceKind::of(&self.pending_config)
     }
 
-    fn switch_source_type(&mut self, new_type: SourceType) {
+    fn switch_source_type(&mut self, new_type: SourceKind) {
         let current_type = self.current_source_type();
         if new_type == current_type {
             return;
         }
 
         self.pending_config = match new_type {
-            SourceType::SignalGenerator => SourceConfig::SignalGenerator {
-                sample_rate: Hertz(48_000),
-                signal_freq: Hertz(10_000),
-                amplitude: Decibels(0.0),
-            },
-            SourceType::File => SourceConfig::File {
+            SourceKind::SignalGenerator => SourceConfig::default(),
+            SourceKind::File => SourceConfig::File {
                 path: PathBuf::new(),
                 sample_rate: Hertz(3_200_000),
+                format: IqFormat::Cf32,
+            },
+            SourceKind::RtlSdr => SourceConfig::RtlSdr {
+                sample_rate: Hertz(2_400_000),
+                gain: Decibels(20.0),
+            },
+            SourceKind::RtlTcp => SourceConfig::RtlTcp {
+                host: "localhost".to_string(),
+                port: 1234,
+                sample_rate: Hertz(2_400_000),
+                gain: Decibels(20.0),
             },
+            SourceKind::SpyServer => SourceConfig::SpyServer {
+                host: "localhost".to_string(),
+                port: 5555,
+                sample_rate: Hertz(2_500_000),
+                gain: 10,
+            },
+            SourceKind::SoapySdr => {
+                let device = self
+                    .sources
+                    .iter()
+                    .find(|source| source.kind == SourceKind::SoapySdr)
+                    .and_then(|source| source.devices.first());
+                SourceConfig::SoapySdr {
+                    device: device.map(|device| device.args.clone()).unwrap_or_default(),
+                    sample_rate: Hertz(2_000_000),
+                    antenna: None,
+                    bandwidth: None,
+                    gains: device.map(default_gains).unwrap_or_default(),
+                }
+            }
         };
         self.has_pending_changes = true;
     }
 
-    fn send_change_source(&self) {
-        let _ = self
-            .cmd_tx
-            .send(Command::ChangeSource(self.pending_config.clone()));
+    fn send_change_source(&mut self) {
+        let config = self.pending_config.clone();
+        let command = match self.slot {
+            Slot::Main => Command::ChangeSource(config),
+            Slot::Second => Command::SetSecondSource(Some(config)),
+        };
+        self.waiting_for_apply = self.cmd_tx.send(command).is_ok();
+    }
+
+    fn send_remove(&mut self) {
+        self.waiting_for_apply = self.cmd_tx.send(Command::SetSecondSource(None)).is_ok();
+    }
+}
+
+/// The address of a server streaming IQ. Returns whether it changed.
+fn server_ui(ui: &mut Ui, host: &mut String, port: &mut u16, hint: &str) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Host:");
+        changed |= ui.add(TextEdit::singleline(host).hint_text(hint)).changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Port:");
+        changed |= ui.add(DragValue::new(port)).changed();
+    });
+    changed
+}
+
+/// The sample rate of a source that runs at only the `rates` listed, or at
+/// any up to `max_rate` if none are. A rate it doesn't run at, as from the
+/// command line, stays selected until another is picked. Returns whether
+/// it changed.
+fn sample_rate_ui(
+    ui: &mut Ui,
+    id_salt: impl std::hash::Hash,
+    sample_rate: &mut Hertz,
+    rates: &[Hertz],
+    max_rate: u64,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Sample Rate:");
+        if rates.is_empty() {
+            changed |= ui
+                .add(
+                    DragValue::new(&mut sample_rate.0)
+                        .speed(1000)
+                        .range(0..=max_rate)
+                        .suffix(" Hz"),
+                )
+                .changed();
+            return;
+        }
+        ComboBox::from_id_salt(id_salt)
+            .selected_text(sample_rate.to_string())
+            .show_ui(ui, |ui| {
+                for &rate in rates {
+                    changed |= ui
+                        .selectable_value(sample_rate, rate, rate.to_string())
+                        .changed();
+                }
+            })
+            .response
+            .on_hover_text("Rates the hardware runs at; others are resampled from the next above");
+    });
+    changed
+}
+
+/// The tones of the signal generator, its noise and its sweep. Returns
+/// whether they changed.
+fn generator_ui(
+    ui: &mut Ui,
+    tones: &mut Vec<Tone>,
+    snr: &mut Option<Decibels>,
+    sweep: &mut Option<Sweep>,
+) -> bool {
+    let mut changed = false;
+    let mut removed = None;
+    // Always at least one
+    let removable = tones.len() > 1;
+    for (index, tone) in tones.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Tone {}:", index + 1));
+            changed |= ui
+                .add(
+                    DragValue::new(&mut tone.frequency.0)
+                        .speed(100)
+                        .suffix(" Hz"),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    DragValue::new(&mut tone.amplitude.0)
+                        .speed(0.1)
+                        .suffix(" dB"),
+                )
+                .changed();
+            if removable && ui.small_button("✖").on_hover_text("Remove").clicked() {
+                removed = Some(index);
+            }
+        });
+    }
+    if let Some(index) = removed {
+        tones.remove(index);
+        changed = true;
+    }
+    // The noise and sweep settings only take rows of their own when on
+    ui.horizontal(|ui| {
+        if ui.button("Add tone").clicked() {
+            // An octave above the last, 6 dB down, to tell them apart
+            tones.push(tones.last().map_or(
+                Tone {
+                    frequency: Hertz(1_000),
+                    amplitude: Decibels(0.0),
+                },
+                |last| Tone {
+                    frequency: Hertz(last.frequency.0 * 2),
+                    amplitude: Decibels(last.amplitude.0 - 6.0),
+                },
+            ));
+            changed = true;
+        }
+        let mut noisy = snr.is_some();
+        if ui.checkbox(&mut noisy, "Noise").changed() {
+            *snr = noisy.then_some(Decibels(20.0));
+            changed = true;
+        }
+        let mut swept = sweep.is_some();
+        if ui.checkbox(&mut swept, "Sweep").changed() {
+            *sweep = swept.then_some(Sweep {
+                span: Hertz(10_000),
+                period: Duration::from_secs(1),
+            });
+            changed = true;
+        }
+    });
+    if let Some(snr) = snr {
+        ui.horizontal(|ui| {
+            ui.label("SNR:");
+            changed |= ui
+                .add(DragValue::new(&mut snr.0).speed(0.1).suffix(" dB"))
+                .on_hover_text("Below the strongest tone")
+                .changed();
+        });
+    }
+    if let Some(sweep) = sweep {
+        ui.horizontal(|ui| {
+            ui.label("Sweep:");
+            changed |= ui
+                .add(DragValue::new(&mut sweep.span.0).speed(100).suffix(" Hz"))
+                .on_hover_text("How far the tones rise before starting again")
+                .changed();
+            ui.label("over");
+            let mut seconds = sweep.period.as_secs_f64();
+            if ui
+                .add(
+                    DragValue::new(&mut seconds)
+                        .speed(0.01)
+                        .range(0.001..=60.0)
+                        .suffix(" s"),
+                )
+                .changed()
+            {
+                sweep.period = Duration::from_secs_f64(seconds);
+                changed = true;
+            }
+        });
     }
+    changed
+}
+
+/// The settings of an RTL-SDR dongle, local or served by `rtl_tcp`.
+/// Returns whether they changed.
+fn rtl_sdr_ui(
+    ui: &mut Ui,
+    id_salt: impl std::hash::Hash,
+    sample_rate: &mut Hertz,
+    gain: &mut Decibels,
+    rates: &[Hertz],
+    max_rate: u64,
+) -> bool {
+    let mut changed = sample_rate_ui(ui, id_salt, sample_rate, rates, max_rate);
+    ui.horizontal(|ui| {
+        ui.label("Tuner Gain:");
+        changed |= ui
+            .add(
+                DragValue::new(&mut gain.0)
+                    .speed(0.1)
+                    .range(0.0..=50.0)
+                    .suffix(" dB"),
+            )
+            .changed();
+    });
+    changed
 }
 
 impl Widget for &mut ControlPanel {
     fn ui(self, ui: &mut Ui) -> Response {
-        ui.heading("Input Source");
+        match self.slot {
+            Slot::Main => ui.heading("Input Source"),
+            Slot::Second => ui.heading("Second Source"),
+        };
         ui.separator();
 
         let fields_enabled = !self.waiting_for_apply;
 
+        // An empty second slot only offers to fill it, for a dual view
+        if !self.active {
+            ui.add_enabled_ui(fields_enabled, |ui| {
+                if ui.button("Add for dual view").clicked() {
+                    self.send_change_source();
+                }
+            });
+            return ui.response();
+        }
+
         // Source type selector
         let current_type = self.current_source_type();
         ui.add_enabled_ui(fields_enabled, |ui| {
             ComboBox::from_label("Source")
                 .selected_text(current_type.label())
                 .show_ui(ui, |ui| {
-                    if ui
-                        .selectable_label(
-                            current_type == SourceType::SignalGenerator,
-                            SourceType::SignalGenerator.label(),
-                        )
-                        .clicked()
-                    {
-                        self.switch_source_type(SourceType::SignalGenerator);
-                    }
-                    if ui
-                        .selectable_label(
-                            current_type == SourceType::File,
-                            SourceType::File.label(),
-                        )
-                        .clicked()
-                    {
-                        self.switch_source_type(SourceType::File);
+                    let kinds: Vec<SourceKind> =
+                        self.sources.iter().map(|source| source.kind).collect();
+                    for kind in kinds {
+                        if ui
+                            .selectable_label(current_type == kind, kind.label())
+                            .clicked()
+                        {
+                            self.switch_source_type(kind);
+                        }
                     }
                 });
         });
@@ -120,70 +516,249 @@ impl Widget for &mut ControlPanel {
         ui.add_space(10.0);
 
         // Source-specific controls
+        let max_rate = self.max_sample_rate();
+        let devices = self.devices().to_vec();
+        let rates = match &self.pending_config {
+            SourceConfig::SoapySdr { device, .. } => self.sample_rates(Some(device)),
+            _ => self.sample_rates(None),
+        };
+        let slot = self.slot;
         ui.add_enabled_ui(fields_enabled, |ui| match &mut self.pending_config {
             SourceConfig::SignalGenerator {
                 sample_rate,
-                signal_freq,
-                amplitude,
+                tones,
+                snr,
+                sweep,
             } => {
                 ui.horizontal(|ui| {
                     ui.label("Sample Rate:");
                     let mut rate = sample_rate.0;
                     if ui
-                        .add(DragValue::new(&mut rate).speed(1000).suffix(" Hz"))
+                        .add(
+                            DragValue::new(&mut rate)
+                                .speed(1000)
+                                .range(0..=max_rate)
+                                .suffix(" Hz"),
+                        )
                         .changed()
                     {
                         sample_rate.0 = rate;
                         self.has_pending_changes = true;
                     }
                 });
+                if generator_ui(ui, tones, snr, sweep) {
+                    self.has_pending_changes = true;
+                }
+            }
+            SourceConfig::File {
+                path,
+                sample_rate,
+                format,
+            } => {
                 ui.horizontal(|ui| {
-                    ui.label("Frequency:");
-                    let mut freq = signal_freq.0;
+                    ui.label("Path:");
+                    let mut path_str = path.display().to_string();
                     if ui
-                        .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                        .add(TextEdit::singleline(&mut path_str).hint_text("/path/to/file.iq"))
                         .changed()
                     {
-                        signal_freq.0 = freq;
+                        *path = PathBuf::from(path_str);
+                        // Most tools name the format in the extension
+                        *format = IqFormat::from_extension(path);
                         self.has_pending_changes = true;
                     }
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Amplitude:");
-                    let mut amp = amplitude.0;
+                    ui.label("Format:");
+                    ComboBox::from_id_salt(("iq_format", slot))
+                        .selected_text(format.label())
+                        .show_ui(ui, |ui| {
+                            for option in IqFormat::ALL {
+                                if ui
+                                    .selectable_value(format, option, option.label())
+                                    .changed()
+                                {
+                                    self.has_pending_changes = true;
+                                }
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Sample Rate:");
+                    let mut rate = sample_rate.0;
+                    // The engine reads a WAV file's rate from its header
                     if ui
-                        .add(DragValue::new(&mut amp).speed(0.1).suffix(" dB"))
+                        .add_enabled(
+                            *format != IqFormat::Wav,
+                            DragValue::new(&mut rate)
+                                .speed(1000)
+                                .range(0..=max_rate)
+                                .suffix(" Hz"),
+                        )
+                        .on_disabled_hover_text("From the WAV header")
                         .changed()
                     {
-                        amplitude.0 = amp;
+                        sample_rate.0 = rate;
                         self.has_pending_changes = true;
                     }
                 });
             }
-            SourceConfig::File { path, sample_rate } => {
+            SourceConfig::RtlSdr { sample_rate, gain } => {
+                if rtl_sdr_ui(ui, ("rtl_rate", slot), sample_rate, gain, &rates, max_rate) {
+                    self.has_pending_changes = true;
+                }
+            }
+            SourceConfig::RtlTcp {
+                host,
+                port,
+                sample_rate,
+                gain,
+            } => {
+                if server_ui(ui, host, port, "rtl_tcp host") {
+                    self.has_pending_changes = true;
+                }
+                if rtl_sdr_ui(ui, ("rtl_rate", slot), sample_rate, gain, &rates, max_rate) {
+                    self.has_pending_changes = true;
+                }
+            }
+            SourceConfig::SpyServer {
+                host,
+                port,
+                sample_rate,
+                gain,
+            } => {
+                if server_ui(ui, host, port, "SpyServer host") {
+                    self.has_pending_changes = true;
+                }
                 ui.horizontal(|ui| {
-                    ui.label("Path:");
-                    let mut path_str = path.display().to_string();
+                    ui.label("Sample Rate:");
                     if ui
-                        .add(TextEdit::singleline(&mut path_str).hint_text("/path/to/file.iq"))
+                        .add(
+                            DragValue::new(&mut sample_rate.0)
+                                .speed(1000)
+                                .range(0..=max_rate)
+                                .suffix(" Hz"),
+                        )
+                        .on_hover_text("Rounded up to a halving of the server's rate")
                         .changed()
                     {
-                        *path = PathBuf::from(path_str);
                         self.has_pending_changes = true;
                     }
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Sample Rate:");
-                    let mut rate = sample_rate.0;
+                    ui.label("Gain Step:");
                     if ui
-                        .add(DragValue::new(&mut rate).speed(1000).suffix(" Hz"))
+                        .add(DragValue::new(gain).range(0..=29))
+                        .on_hover_text("From 0 to the device's most; set only if the server allows")
                         .changed()
                     {
-                        sample_rate.0 = rate;
                         self.has_pending_changes = true;
                     }
                 });
             }
+            SourceConfig::SoapySdr {
+                device,
+                sample_rate,
+                antenna,
+                bandwidth,
+                gains,
+            } => {
+                let selected = devices.iter().find(|candidate| candidate.args == *device);
+                ui.horizontal(|ui| {
+                    ui.label("Device:");
+                    if devices.is_empty() {
+                        ui.label("None found");
+                        return;
+                    }
+                    ComboBox::from_id_salt(("soapy_device", slot))
+                        .selected_text(selected.map_or(device.as_str(), |d| d.label.as_str()))
+                        .show_ui(ui, |ui| {
+                            for candidate in &devices {
+                                if ui
+                                    .selectable_label(candidate.args == *device, &candidate.label)
+                                    .clicked()
+                                    && candidate.args != *device
+                                {
+                                    *device = candidate.args.clone();
+                                    *antenna = None;
+                                    *bandwidth = None;
+                                    *gains = default_gains(candidate);
+                                    self.has_pending_changes = true;
+                                }
+                            }
+                        });
+                });
+                if sample_rate_ui(ui, ("soapy_rate", slot), sample_rate, &rates, max_rate) {
+                    self.has_pending_changes = true;
+                }
+                let Some(selected) = selected else {
+                    return;
+                };
+                if !selected.antennas.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label("Antenna:");
+                        ComboBox::from_id_salt(("soapy_antenna", slot))
+                            .selected_text(antenna.as_deref().unwrap_or("Default"))
+                            .show_ui(ui, |ui| {
+                                let choices = std::iter::once(None)
+                                    .chain(selected.antennas.iter().cloned().map(Some));
+                                for choice in choices {
+                                    let label = choice.clone().unwrap_or("Default".to_string());
+                                    if ui.selectable_value(antenna, choice, label).changed() {
+                                        self.has_pending_changes = true;
+                                    }
+                                }
+                            });
+                    });
+                }
+                if let Some(range) = selected.bandwidth {
+                    ui.horizontal(|ui| {
+                        ui.label("Bandwidth:");
+                        let mut auto = bandwidth.is_none();
+                        if ui.checkbox(&mut auto, "Auto").changed() {
+                            *bandwidth = (!auto)
+                                .then(|| Hertz(sample_rate.0.clamp(range.start.0, range.end.0)));
+                            self.has_pending_changes = true;
+                        }
+                        if let Some(bandwidth) = bandwidth {
+                            let mut width = bandwidth.0;
+                            if ui
+                                .add(
+                                    DragValue::new(&mut width)
+                                        .speed(1000)
+                                        .range(range.start.0..=range.end.0)
+                                        .suffix(" Hz"),
+                                )
+                                .changed()
+                            {
+                                bandwidth.0 = width;
+                                self.has_pending_changes = true;
+                            }
+                        }
+                    });
+                }
+                for stage in &selected.gain_stages {
+                    let Some(setting) = gains.iter_mut().find(|g| g.stage == stage.name) else {
+                        continue;
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} Gain:", stage.name));
+                        let mut value = setting.gain.0;
+                        if ui
+                            .add(
+                                DragValue::new(&mut value)
+                                    .speed(0.1)
+                                    .range(stage.min.0..=stage.max.0)
+                                    .suffix(" dB"),
+                            )
+                            .changed()
+                        {
+                            setting.gain.0 = value;
+                            self.has_pending_changes = true;
+                        }
+                    });
+                }
+            }
         });
 
         ui.add_space(10.0);
@@ -191,13 +766,131 @@ impl Widget for &mut ControlPanel {
 
         // Apply button (enabled when there are changes and not waiting)
         let can_apply = self.has_pending_changes && !self.waiting_for_apply;
-        ui.add_enabled_ui(can_apply, |ui| {
-            if ui.button("Apply").clicked() {
-                self.waiting_for_apply = true;
-                self.send_change_source();
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(can_apply, |ui| {
+                if ui.button("Apply").clicked() {
+                    self.send_change_source();
+                }
+            });
+            if self.slot == Slot::Second {
+                ui.add_enabled_ui(fields_enabled, |ui| {
+                    if ui.button("Remove").clicked() {
+                        self.send_remove();
+                    }
+                });
             }
         });
+        if self.slot == Slot::Main
+            && let Some(rate) = self.device_sample_rate
+        {
+            ui.label(format!("Resampled from the hardware's {rate}"));
+        }
+
+        // Only the main source is paced, and changes speed as it plays
+        if self.slot == Slot::Main && self.playing_file {
+            let mut speed = self.playback_speed;
+            ComboBox::from_label("Playback")
+                .selected_text(speed_label(speed))
+                .show_ui(ui, |ui| {
+                    for choice in PLAYBACK_SPEEDS {
+                        ui.selectable_value(&mut speed, choice, speed_label(choice));
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Play the file in time with the clock, slower or faster; the audio keeps \
+                     its pitch",
+                );
+            if speed != self.playback_speed {
+                let _ = self.cmd_tx.send(Command::SetPlaybackSpeed(speed));
+            }
+        }
+
+        // Applies to the second source too, so only offered once
+        if self.slot == Slot::Main {
+            ui.add_space(10.0);
+            let mut fft_size = self.fft_size;
+            ComboBox::from_label("FFT size")
+                .selected_text(fft_size.to_string())
+                .show_ui(ui, |ui| {
+                    for size in FFT_SIZES {
+                        ui.selectable_value(&mut fft_size, size, size.to_string());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Bins across the spectrum: more resolve finer detail but update less often",
+                );
+            if fft_size != self.fft_size {
+                let _ = self.cmd_tx.send(Command::SetFftSize(fft_size));
+            }
+            // Zoomed in, the shift picks the slice of the band to show
+            ui.horizontal(|ui| {
+                let mut decimation = self.decimation;
+                ComboBox::from_label("Zoom")
+                    .selected_text(format!("×{decimation}"))
+                    .show_ui(ui, |ui| {
+                        for factor in DECIMATIONS {
+                            ui.selectable_value(&mut decimation, factor, format!("×{factor}"));
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Narrow the spectrum to the middle of the band, decimating ahead of \
+                         the FFT so its bins span less",
+                    );
+                if decimation != self.decimation {
+                    let _ = self.cmd_tx.send(Command::SetDecimation(decimation));
+                }
+                let response = ui
+                    .add(
+                        DragValue::new(&mut self.frequency_offset)
+                            .speed(100)
+                            .suffix(" Hz"),
+                    )
+                    .on_hover_text(
+                        "Center the spectrum this far off the center frequency without \
+                         retuning, e.g. to zoom into a signal away from the middle",
+                    );
+                ui.label("Shift");
+                if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    let _ = self
+                        .cmd_tx
+                        .send(Command::SetFrequencyOffset(self.frequency_offset));
+                }
+            });
+            self.averaging_ui(ui);
+
+            CollapsingHeader::new("Advanced").show(ui, |ui| {
+                let mut offset_tuning = self.offset_tuning;
+                ui.add(Checkbox::new(&mut offset_tuning, "Offset tuning"))
+                    .on_hover_text(
+                        "Tune the hardware a little above the center and shift it back, \
+                         keeping the DC spike of zero-IF receivers out of the center channel",
+                    );
+                if offset_tuning != self.offset_tuning {
+                    let _ = self.cmd_tx.send(Command::SetOffsetTuning(offset_tuning));
+                }
+            });
+        }
 
         ui.response()
     }
 }
+
+/// A playback speed as offered.
+fn speed_label(speed: Option<f32>) -> String {
+    speed.map_or("Unpaced".to_string(), |speed| format!("{speed}×"))
+}
+
+/// Each of a device's gain stages at the middle of its range.
+fn default_gains(device: &SourceDevice) -> Vec<StageGain> {
+    device
+        .gain_stages
+        .iter()
+        .map(|stage| StageGain {
+            stage: stage.name.clone(),
+            gain: Decibels((stage.min.0 + stage.max.0) / 2.0),
+        })
+        .collect()
+}
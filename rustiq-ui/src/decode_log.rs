@@ -0,0 +1,143 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use eframe::egui::{Checkbox, Color32, Grid, RichText, ScrollArea, TextEdit, Ui};
+use log::warn;
+use rustiq_messages::UtcTime;
+
+/// Number of entries kept in memory; the log file keeps everything.
+const MAX_ENTRIES: usize = 5_000;
+
+/// One decoded message.
+pub struct DecodeEntry {
+    /// Wall-clock time the UI received the decode
+    pub received: SystemTime,
+    /// Decoder that produced it, e.g. "SelCall CCIR"
+    pub decoder: String,
+    pub text: String,
+    /// Whether the entry matched an alert rule
+    pub alert: bool,
+}
+
+/// Log of everything the decoders have produced, shared by all decoders.
+///
+/// Entries are kept in memory for display and, when enabled, appended to a
+/// CSV file (`time_utc,decoder,text,alert`) as they arrive.
+pub struct DecodeLog {
+    entries: Vec<DecodeEntry>,
+    persist: bool,
+    path: PathBuf,
+}
+
+impl DecodeLog {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            persist: false,
+            path: PathBuf::from("decodes.csv"),
+        }
+    }
+
+    pub fn record(&mut self, decoder: impl Into<String>, text: impl Into<String>, alert: bool) {
+        let entry = DecodeEntry {
+            received: SystemTime::now(),
+            decoder: decoder.into(),
+            text: text.into(),
+            alert,
+        };
+        if self.persist
+            && let Err(e) = self.append_to_file(&entry)
+        {
+            warn!("Failed to write decode log {}: {}", self.path.display(), e);
+        }
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn has_entries(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    fn append_to_file(&self, entry: &DecodeEntry) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            file,
+            "{},{},{},{}",
+            format_utc(entry.received),
+            entry.decoder,
+            entry.text,
+            entry.alert
+        )
+    }
+
+    /// Render the log settings and the entries, newest first.
+    pub fn show(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Decode Log");
+            ui.separator();
+            ui.add(Checkbox::new(&mut self.persist, "Append to"));
+            let mut path = self.path.to_string_lossy().to_string();
+            if ui
+                .add(TextEdit::singleline(&mut path).desired_width(200.0))
+                .changed()
+            {
+                self.path = PathBuf::from(path);
+            }
+            if ui.button("Clear").clicked() {
+                self.entries.clear();
+            }
+        });
+        ui.separator();
+
+        ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+            Grid::new("decode_log")
+                .striped(true)
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for entry in self.entries.iter().rev() {
+                        let text = RichText::new(&entry.text).monospace();
+                        let text = if entry.alert {
+                            text.color(Color32::RED).strong()
+                        } else {
+                            text
+                        };
+                        ui.label(format_utc(entry.received));
+                        ui.label(&entry.decoder);
+                        ui.label(text);
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+/// Format a time as "YYYY-MM-DD HH:MM:SSZ" in UTC.
+fn format_utc(time: SystemTime) -> String {
+    let utc = UtcTime::from(time);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}Z",
+        utc.year, utc.month, utc.day, utc.hour, utc.minute, utc.second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01 00:00:00Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_utc(leap_day), "2024-02-29 12:34:56Z");
+        let new_year = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+        assert_eq!(format_utc(new_year), "2024-12-31 23:59:59Z");
+    }
+}
@@ -0,0 +1,89 @@
+use eframe::egui::{Color32, Grid, Response, RichText, Ui, Widget};
+use flume::Sender;
+
+use rustiq_messages::{Command, SelfTestReport};
+
+/// Self test of the receive chain, with the level and frequency of the
+/// reference tone at each stage.
+pub struct DiagnosticsPanel {
+    cmd_tx: Sender<Command>,
+    /// Waiting for the engine's report
+    running: bool,
+    report: Option<SelfTestReport>,
+}
+
+impl DiagnosticsPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            running: false,
+            report: None,
+        }
+    }
+
+    pub fn set_report(&mut self, report: SelfTestReport) {
+        self.running = false;
+        self.report = Some(report);
+    }
+
+    fn send_run(&mut self) {
+        if self.cmd_tx.send(Command::RunSelfTest).is_ok() {
+            self.running = true;
+        }
+    }
+}
+
+fn verdict(passed: bool) -> RichText {
+    if passed {
+        RichText::new("PASS").color(Color32::GREEN).strong()
+    } else {
+        RichText::new("FAIL").color(Color32::RED).strong()
+    }
+}
+
+impl Widget for &mut DiagnosticsPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Diagnostics");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!self.running, |ui| {
+                if ui.button("Run self test").clicked() {
+                    self.send_run();
+                }
+            });
+            if self.running {
+                ui.spinner();
+            }
+        });
+
+        if let Some(report) = &self.report {
+            ui.add_space(5.0);
+            ui.label(format!(
+                "Tone {} at {:.1} dBFS, ±{:.1} dB",
+                report.tone_frequency, report.tone_level.0, report.tolerance.0
+            ));
+            Grid::new("self_test").striped(true).show(ui, |ui| {
+                for check in &report.checks {
+                    ui.label(check.stage.label());
+                    match check.level {
+                        Some(level) => ui.label(format!("{:.1} dBFS", level.0)),
+                        None => ui.label("no signal"),
+                    };
+                    match check.frequency {
+                        Some(frequency) => ui.label(frequency.to_string()),
+                        None => ui.label("-"),
+                    };
+                    ui.label(verdict(check.passed));
+                    ui.end_row();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Overall:");
+                ui.label(verdict(report.passed()));
+            });
+        }
+
+        ui.response()
+    }
+}
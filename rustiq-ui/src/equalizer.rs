@@ -0,0 +1,252 @@
+//! Shaping the audio played, for comfort while monitoring: a high-pass and a
+//! low-pass filter and one peaking band, each a biquad from the Audio EQ
+//! Cookbook.
+
+use std::f64::consts::{FRAC_1_SQRT_2, TAU};
+
+/// Settings of the equalizer. Frequencies are in Hz.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EqSettings {
+    /// Corner of the high-pass filter, if on
+    pub high_pass: Option<f32>,
+    /// Corner of the low-pass filter, if on
+    pub low_pass: Option<f32>,
+    pub peak_frequency: f32,
+    /// Boost or cut at `peak_frequency` in dB; 0 leaves the band out
+    pub peak_gain: f32,
+    /// Sharpness of the peak
+    pub peak_q: f32,
+}
+
+impl Default for EqSettings {
+    fn default() -> Self {
+        EqPreset::Flat.settings()
+    }
+}
+
+/// Starting points for the equalizer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EqPreset {
+    Flat,
+    /// Speech band, with some presence
+    Voice,
+    /// A few hundred Hz around a 700 Hz CW note
+    CwNarrow,
+    /// Full band, a little warmer
+    Music,
+}
+
+impl EqPreset {
+    pub const ALL: [EqPreset; 4] = [Self::Flat, Self::Voice, Self::CwNarrow, Self::Music];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Flat => "Flat",
+            Self::Voice => "Voice",
+            Self::CwNarrow => "CW narrow",
+            Self::Music => "Music",
+        }
+    }
+
+    pub fn settings(self) -> EqSettings {
+        let (high_pass, low_pass, peak_frequency, peak_gain, peak_q) = match self {
+            Self::Flat => (None, None, 1_000.0, 0.0, 1.0),
+            Self::Voice => (Some(300.0), Some(3_000.0), 2_000.0, 3.0, 1.0),
+            Self::CwNarrow => (Some(500.0), Some(900.0), 700.0, 6.0, 2.0),
+            Self::Music => (Some(30.0), None, 100.0, 3.0, 0.7),
+        };
+        EqSettings {
+            high_pass,
+            low_pass,
+            peak_frequency,
+            peak_gain,
+            peak_q,
+        }
+    }
+
+    /// The preset `settings` are, if any.
+    pub fn matching(settings: &EqSettings) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.settings() == *settings)
+    }
+}
+
+/// One second-order section, in direct form I.
+#[derive(Clone)]
+struct Biquad {
+    /// Numerator and denominator, normalized so a0 is 1
+    b: [f64; 3],
+    a: [f64; 2],
+    inputs: [f64; 2],
+    outputs: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: b.map(|b| b / a[0]),
+            a: [a[1] / a[0], a[2] / a[0]],
+            inputs: [0.0; 2],
+            outputs: [0.0; 2],
+        }
+    }
+
+    fn high_pass(w0: f64, q: f64) -> Self {
+        let (cos, alpha) = (w0.cos(), w0.sin() / (2.0 * q));
+        Self::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn low_pass(w0: f64, q: f64) -> Self {
+        let (cos, alpha) = (w0.cos(), w0.sin() / (2.0 * q));
+        Self::new(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn peak(w0: f64, q: f64, gain_db: f64) -> Self {
+        let (cos, alpha) = (w0.cos(), w0.sin() / (2.0 * q));
+        let a = 10f64.powf(gain_db / 40.0);
+        Self::new(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.inputs[0] + self.b[2] * self.inputs[1]
+            - self.a[0] * self.outputs[0]
+            - self.a[1] * self.outputs[1];
+        self.inputs = [x, self.inputs[0]];
+        self.outputs = [y, self.outputs[0]];
+        y
+    }
+}
+
+/// The equalizer running at one sample rate, for up to two channels.
+pub struct Equalizer {
+    settings: EqSettings,
+    sample_rate: f64,
+    /// The sections for the left, or only, channel and the right
+    channels: [Vec<Biquad>; 2],
+}
+
+impl Equalizer {
+    pub fn new(settings: EqSettings, sample_rate: f64) -> Self {
+        // Corners too near Nyquist would make the sections unstable
+        let w0 = |frequency: f32| {
+            (f64::from(frequency) < 0.45 * sample_rate && frequency > 0.0)
+                .then(|| TAU * f64::from(frequency) / sample_rate)
+        };
+        let mut sections = Vec::new();
+        if let Some(w0) = settings.high_pass.and_then(w0) {
+            sections.push(Biquad::high_pass(w0, FRAC_1_SQRT_2));
+        }
+        if let Some(w0) = settings.low_pass.and_then(w0) {
+            sections.push(Biquad::low_pass(w0, FRAC_1_SQRT_2));
+        }
+        if settings.peak_gain != 0.0
+            && let Some(w0) = w0(settings.peak_frequency)
+        {
+            sections.push(Biquad::peak(
+                w0,
+                f64::from(settings.peak_q.max(0.1)),
+                f64::from(settings.peak_gain),
+            ));
+        }
+        Self {
+            settings,
+            sample_rate,
+            channels: [sections.clone(), sections],
+        }
+    }
+
+    /// Whether this equalizer is the one for `settings` at `sample_rate`.
+    pub fn is_for(&self, settings: &EqSettings, sample_rate: f64) -> bool {
+        self.settings == *settings && self.sample_rate == sample_rate
+    }
+
+    /// Filter `samples` in place, left and right interleaved if `stereo`.
+    pub fn process(&mut self, samples: &mut [f32], stereo: bool) {
+        let channels = if stereo { 2 } else { 1 };
+        for frame in samples.chunks_mut(channels) {
+            for (sample, sections) in frame.iter_mut().zip(&mut self.channels) {
+                let filtered = sections
+                    .iter_mut()
+                    .fold(f64::from(*sample), |x, section| section.process(x));
+                *sample = filtered as f32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 24_000.0;
+
+    /// Gain of `equalizer` for a tone at `frequency`, once settled.
+    fn gain(settings: EqSettings, frequency: f64) -> f64 {
+        let mut equalizer = Equalizer::new(settings, RATE);
+        let mut tone: Vec<f32> = (0..RATE as usize)
+            .map(|i| (TAU * frequency * i as f64 / RATE).sin() as f32)
+            .collect();
+        equalizer.process(&mut tone, false);
+        let settled = &tone[tone.len() / 2..];
+        let rms =
+            (settled.iter().map(|s| (s * s) as f64).sum::<f64>() / settled.len() as f64).sqrt();
+        rms * 2f64.sqrt()
+    }
+
+    #[test]
+    fn flat_passes_everything() {
+        for frequency in [50.0, 1_000.0, 8_000.0] {
+            let gain = gain(EqPreset::Flat.settings(), frequency);
+            assert!((gain - 1.0).abs() < 0.01, "{gain} at {frequency} Hz");
+        }
+    }
+
+    #[test]
+    fn voice_keeps_the_speech_band() {
+        let voice = EqPreset::Voice.settings();
+        assert!(gain(voice, 1_000.0) > 0.9);
+        assert!(gain(voice, 60.0) < 0.1);
+        assert!(gain(voice, 9_000.0) < 0.15);
+    }
+
+    #[test]
+    fn cw_narrow_picks_out_the_note() {
+        let cw = EqPreset::CwNarrow.settings();
+        let note = gain(cw, 700.0);
+        assert!(note > 1.0, "{note}");
+        assert!(gain(cw, 2_000.0) < note / 5.0);
+        assert!(gain(cw, 150.0) < note / 5.0);
+    }
+
+    #[test]
+    fn filters_stereo_channels_apart() {
+        let mut equalizer = Equalizer::new(EqPreset::Voice.settings(), RATE);
+        // A step on the left only
+        let mut samples = [1.0, 0.0].repeat(100);
+        equalizer.process(&mut samples, true);
+        assert!(samples.iter().skip(1).step_by(2).all(|&right| right == 0.0));
+        assert!(samples[0] != 0.0);
+    }
+
+    #[test]
+    fn finds_the_preset_of_settings() {
+        for preset in EqPreset::ALL {
+            assert_eq!(EqPreset::matching(&preset.settings()), Some(preset));
+        }
+        let custom = EqSettings {
+            peak_gain: -4.0,
+            ..EqPreset::Voice.settings()
+        };
+        assert_eq!(EqPreset::matching(&custom), None);
+    }
+}
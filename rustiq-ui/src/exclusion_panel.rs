@@ -0,0 +1,170 @@
+use eframe::egui::{Button, Color32, Rect, Response, Ui, Widget};
+
+use rustiq_messages::{Hertz, SignalRegion, SpectrumFrame};
+
+use crate::waterfall::Waterfall;
+
+/// Bins either side of the middle one the DC spike is taken to cover.
+const DC_BINS: usize = 2;
+
+/// Shade drawn over excluded regions on the spectrum.
+const SHADE: Color32 = Color32::from_rgba_premultiplied(60, 60, 60, 90);
+
+/// Frequency regions left out of the spectrum's and waterfall's level
+/// range, e.g. the DC spike or a local pager transmitter, so one strong
+/// signal doesn't squash the rest of the band into a few colors.
+///
+/// Regions are taken from the line last dragged across the waterfall.
+pub struct ExclusionPanel {
+    regions: Vec<SignalRegion>,
+    /// Whether the bins around the center are left out too
+    exclude_dc: bool,
+    /// Region spanned by the waterfall's measurement line, if there is one
+    selection: Option<SignalRegion>,
+    /// Whether the regions changed since last asked
+    changed: bool,
+}
+
+impl ExclusionPanel {
+    pub fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+            exclude_dc: false,
+            selection: None,
+            changed: false,
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SignalRegion>) {
+        self.selection = selection;
+    }
+
+    /// Whether the regions changed since this was last called, so the
+    /// level ranges can start over without them.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Whether each bin of `frame` is left out.
+    pub fn excluded_bins(&self, frame: &SpectrumFrame) -> Vec<bool> {
+        let bins = frame.magnitudes.len();
+        let resolution = frame.sample_rate.as_hz() as f64 / bins.max(1) as f64;
+        (0..bins)
+            .map(|bin| {
+                let offset = bin as f64 - (bins / 2) as f64;
+                let frequency = frame.center_frequency.as_hz() as f64 + offset * resolution;
+                (self.exclude_dc && bin.abs_diff(bins / 2) <= DC_BINS)
+                    || self.regions.iter().any(|region| region.contains(frequency))
+            })
+            .collect()
+    }
+
+    /// Shade the excluded regions over the spectrum plot drawn in `plot`,
+    /// on the frequency axis of `view`.
+    pub fn show_regions(&self, ui: &Ui, plot: Rect, view: &Waterfall) {
+        let painter = ui.painter_at(plot);
+        for region in &self.regions {
+            let half = region.bandwidth.as_hz() as f64 / 2.0;
+            let center = region.frequency.as_hz() as f64;
+            let (Some(low), Some(high)) = (
+                view.position_of(plot, center - half),
+                view.position_of(plot, center + half),
+            ) else {
+                return;
+            };
+            painter.rect_filled(
+                Rect::from_x_y_ranges(low..=high.max(low + 1.0), plot.y_range()),
+                0.0,
+                SHADE,
+            );
+        }
+    }
+}
+
+impl Widget for &mut ExclusionPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Excluded Regions");
+        ui.separator();
+
+        self.changed |= ui
+            .checkbox(&mut self.exclude_dc, "Exclude DC spike")
+            .on_hover_text("Leave the bins at the center out of the level range")
+            .changed();
+
+        let selection = self.selection;
+        if ui
+            .add_enabled(selection.is_some(), Button::new("Exclude selection"))
+            .on_hover_text("Leave the region dragged across the waterfall out of the level range")
+            .on_disabled_hover_text("Drag across the waterfall to select a region")
+            .clicked()
+            && let Some(region) = selection
+        {
+            self.regions.push(region);
+            self.changed = true;
+        }
+
+        let mut removed = None;
+        for (i, region) in self.regions.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} ± {}",
+                    region.frequency,
+                    Hertz(region.bandwidth.as_hz() / 2)
+                ));
+                if ui
+                    .small_button("✖")
+                    .on_hover_text("Include the region again")
+                    .clicked()
+                {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            self.regions.remove(i);
+            self.changed = true;
+        }
+
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(bins: usize) -> SpectrumFrame {
+        SpectrumFrame {
+            sequence: 0,
+            sample_time: std::time::Duration::ZERO,
+            timestamp: std::time::UNIX_EPOCH,
+            source: 0,
+            discontinuity: None,
+            center_frequency: Hertz(100_000),
+            sample_rate: Hertz(16_000),
+            magnitudes: vec![0.0; bins],
+            peak_hold: None,
+            min_hold: None,
+        }
+    }
+
+    #[test]
+    fn excludes_bins_in_regions_and_at_dc() {
+        let mut panel = ExclusionPanel::new();
+        assert!(panel.excluded_bins(&frame(16)).iter().all(|&e| !e));
+
+        panel.exclude_dc = true;
+        panel.regions.push(SignalRegion {
+            frequency: Hertz(94_000),
+            bandwidth: Hertz(2_000),
+        });
+        // 1 kHz bins from 92 kHz: 93 to 95 kHz, and 2 either side of 100 kHz
+        let excluded: Vec<usize> = panel
+            .excluded_bins(&frame(16))
+            .iter()
+            .enumerate()
+            .filter_map(|(bin, &e)| e.then_some(bin))
+            .collect();
+        assert_eq!(excluded, [1, 2, 3, 6, 7, 8, 9, 10]);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use eframe::egui::Ui;
+
+/// Span of recent frames the rate and loss are computed over.
+const WINDOW: Duration = Duration::from_secs(2);
+
+/// Flow accounting for the spectrum stream: effective frame rate and the
+/// share of frames lost, from the frames' sequence numbers.
+pub struct FlowStats {
+    /// Arrival time of each recent frame and the frames lost just before it
+    recent: VecDeque<(Instant, u64)>,
+    last_sequence: Option<u64>,
+    /// Sample time of the latest frame
+    sample_time: Duration,
+    /// Totals since the UI started
+    received: u64,
+    lost: u64,
+    /// Frames the engine dropped as the UI fell behind, and the rate it
+    /// sends them at, as it last reported
+    engine: Option<(u64, f32)>,
+}
+
+impl FlowStats {
+    pub fn new() -> Self {
+        Self {
+            recent: VecDeque::new(),
+            last_sequence: None,
+            sample_time: Duration::ZERO,
+            received: 0,
+            lost: 0,
+            engine: None,
+        }
+    }
+
+    /// Account for a frame arriving at `now`.
+    pub fn record(&mut self, sequence: u64, sample_time: Duration, now: Instant) {
+        let lost = self
+            .last_sequence
+            .map_or(0, |last| sequence.saturating_sub(last + 1));
+        self.last_sequence = Some(sequence);
+        self.sample_time = sample_time;
+        self.received += 1;
+        self.lost += lost;
+        self.recent.push_back((now, lost));
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|&(time, _)| now.duration_since(time) > WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Frames received per second over the window.
+    pub fn frame_rate(&self, now: Instant) -> f64 {
+        let mut times = self
+            .recent
+            .iter()
+            .map(|&(time, _)| time)
+            .filter(|&time| now.duration_since(time) <= WINDOW);
+        let Some(first) = times.next() else {
+            return 0.0;
+        };
+        // Until the window fills, rate over the time since the first frame
+        let span = now.duration_since(first).as_secs_f64();
+        if span > 0.0 {
+            (1 + times.count()) as f64 / span
+        } else {
+            0.0
+        }
+    }
+
+    /// Fraction of frames lost over the window.
+    pub fn loss(&self) -> f64 {
+        let lost: u64 = self.recent.iter().map(|&(_, lost)| lost).sum();
+        let total = lost + self.recent.len() as u64;
+        if total > 0 {
+            lost as f64 / total as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Take the engine's report of the frames it sends.
+    pub fn set_engine_stats(&mut self, frames_dropped: u64, fps: f32) {
+        self.engine = Some((frames_dropped, fps));
+    }
+
+    /// Status bar readout.
+    pub fn show(&mut self, ui: &mut Ui) {
+        let now = Instant::now();
+        self.prune(now);
+        let mut details = format!(
+            "Spectrum frames over the last {} s\n{} received, {} lost since start\nSample time {:.3} s",
+            WINDOW.as_secs(),
+            self.received,
+            self.lost,
+            self.sample_time.as_secs_f64()
+        );
+        if let Some((dropped, fps)) = self.engine {
+            details.push_str(&format!(
+                "\nEngine sends {fps:.1} fps, {dropped} dropped for the UI falling behind"
+            ));
+        }
+        ui.label(format!(
+            "{:.1} fps, {:.1}% lost",
+            self.frame_rate(now),
+            self.loss() * 100.0
+        ))
+        .on_hover_text(details);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_rate_and_loss_over_window() {
+        let start = Instant::now();
+        let mut flow = FlowStats::new();
+        let ms = |ms| start + Duration::from_millis(ms);
+        // 10 fps for a second, losing frames 5 and 6
+        for (i, sequence) in [0, 1, 2, 3, 4, 7, 8, 9, 10, 11].into_iter().enumerate() {
+            flow.record(sequence, Duration::ZERO, ms(100 * i as u64));
+        }
+        assert!((flow.frame_rate(ms(1_000)) - 10.0).abs() < 1e-9);
+        assert!((flow.loss() - 2.0 / 12.0).abs() < 1e-9);
+        assert_eq!((flow.received, flow.lost), (10, 2));
+
+        // Once the loss leaves the window only the steady frames remain
+        for sequence in 12..40 {
+            flow.record(sequence, Duration::ZERO, ms(100 * (sequence - 2)));
+        }
+        assert_eq!(flow.loss(), 0.0);
+        assert!((flow.frame_rate(ms(3_800)) - 10.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,216 @@
+//! Showing frequencies the way the user reads them, and reading them back
+//! however they are typed: with a unit or without, a comma or a point for
+//! the decimals, or as a channel of a band plan.
+
+use rustiq_messages::{Channel, ChannelPlan, ChannelRegion, Hertz};
+
+/// Unit a frequency is shown in, and taken to be in when typed bare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyUnit {
+    Hz,
+    Khz,
+    Mhz,
+}
+
+impl FrequencyUnit {
+    pub const ALL: [FrequencyUnit; 3] = [Self::Hz, Self::Khz, Self::Mhz];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Hz => "Hz",
+            Self::Khz => "kHz",
+            Self::Mhz => "MHz",
+        }
+    }
+
+    fn scale(self) -> f64 {
+        match self {
+            Self::Hz => 1.0,
+            Self::Khz => 1e3,
+            Self::Mhz => 1e6,
+        }
+    }
+}
+
+/// How frequencies are shown: in a unit to a number of decimals, or as a
+/// channel where one of the plan's is on the frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrequencyFormat {
+    pub unit: FrequencyUnit,
+    /// Decimals shown in kHz or MHz
+    pub decimals: usize,
+    /// Region whose band plans are offered
+    pub region: ChannelRegion,
+    pub channels: Option<ChannelPlan>,
+    /// Tune to the plan's nearest channel rather than between them
+    pub snap: bool,
+}
+
+impl Default for FrequencyFormat {
+    fn default() -> Self {
+        Self {
+            unit: FrequencyUnit::Mhz,
+            decimals: 6,
+            region: ChannelRegion::default(),
+            channels: None,
+            snap: false,
+        }
+    }
+}
+
+impl FrequencyFormat {
+    /// `frequency` as a channel if it is on one, otherwise in the unit.
+    pub fn format(&self, frequency: Hertz) -> String {
+        match (self.channels, self.channel(frequency)) {
+            (Some(plan), Some(channel)) => plan.display(&channel),
+            _ => self.format_in_unit(frequency),
+        }
+    }
+
+    /// The plan's channel exactly on `frequency`, if any.
+    pub fn channel(&self, frequency: Hertz) -> Option<Channel> {
+        self.channels?.channel_at(frequency)
+    }
+
+    /// `frequency` moved onto the plan's nearest channel when snapping, if
+    /// it is within the plan's band.
+    pub fn snap(&self, frequency: Hertz) -> Hertz {
+        match self.channels {
+            Some(plan) if self.snap => plan
+                .nearest(frequency)
+                .map_or(frequency, |channel| channel.frequency),
+            _ => frequency,
+        }
+    }
+
+    /// `frequency` in the unit, never as a channel.
+    pub fn format_in_unit(&self, frequency: Hertz) -> String {
+        match self.unit {
+            FrequencyUnit::Hz => format!("{} Hz", frequency.0),
+            unit => format!(
+                "{:.*} {}",
+                self.decimals,
+                frequency.0 as f64 / unit.scale(),
+                unit.label()
+            ),
+        }
+    }
+
+    /// A frequency typed by the user: a channel of the plan by its name, as
+    /// `Ch 16` or `Air 118.005`, or a number in the unit unless it says
+    /// otherwise.
+    pub fn parse(&self, text: &str) -> Result<Hertz, String> {
+        if let Some(plan) = self.channels {
+            if let Some(channel) = plan.find(text) {
+                return Ok(channel.frequency);
+            }
+            let lower = text.trim().to_lowercase();
+            if lower.starts_with("ch") || lower.starts_with(&plan.label().to_lowercase()) {
+                return Err(format!("{text} is not a {} channel", plan.label()));
+            }
+        }
+        parse_frequency(text, self.unit)
+    }
+}
+
+/// A frequency in `unit`, or in Hz, kHz, MHz or GHz as its suffix says.
+/// Either a point or a comma marks the decimals; where both appear, or one
+/// appears more than once, the other groups the thousands.
+pub fn parse_frequency(text: &str, unit: FrequencyUnit) -> Result<Hertz, String> {
+    let invalid = || format!("{text} is not a frequency");
+    let trimmed = text.trim();
+    let number = trimmed
+        .strip_suffix("Hz")
+        .or_else(|| trimmed.strip_suffix("hz"))
+        .unwrap_or(trimmed)
+        .trim_end();
+    let (number, scale) = match number.char_indices().last() {
+        Some((at, 'k' | 'K')) => (&number[..at], 1e3),
+        Some((at, 'm' | 'M')) => (&number[..at], 1e6),
+        Some((at, 'g' | 'G')) => (&number[..at], 1e9),
+        _ => (number, unit.scale()),
+    };
+    let number: String = number
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '_' | '\''))
+        .collect();
+    let decimal = match (number.rfind('.'), number.rfind(',')) {
+        (Some(point), Some(comma)) => Some(point.max(comma)),
+        (Some(at), None) if number.matches('.').count() == 1 => Some(at),
+        (None, Some(at)) if number.matches(',').count() == 1 => Some(at),
+        _ => None,
+    };
+    let number: String = number
+        .char_indices()
+        .filter_map(|(at, c)| match c {
+            '.' | ',' if Some(at) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    match number.parse::<f64>() {
+        Ok(value) if value >= 0.0 && value.is_finite() => Ok(Hertz((value * scale).round() as u64)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_frequencies_however_they_are_written() {
+        let mhz = FrequencyUnit::Mhz;
+        assert_eq!(parse_frequency("145,500", mhz), Ok(Hertz(145_500_000)));
+        assert_eq!(parse_frequency("145.5M", mhz), Ok(Hertz(145_500_000)));
+        assert_eq!(
+            parse_frequency("7.074 MHz", FrequencyUnit::Hz),
+            Ok(Hertz(7_074_000))
+        );
+        assert_eq!(
+            parse_frequency("7,074,000", FrequencyUnit::Hz),
+            Ok(Hertz(7_074_000))
+        );
+        assert_eq!(
+            parse_frequency("1.296.500,5 kHz", mhz),
+            Ok(Hertz(1_296_500_500))
+        );
+        assert_eq!(parse_frequency("14 074 k", mhz), Ok(Hertz(14_074_000)));
+        assert!(parse_frequency("-3M", mhz).is_err());
+        assert!(parse_frequency("M", mhz).is_err());
+    }
+
+    #[test]
+    fn shows_channels_and_units() {
+        let mut format = FrequencyFormat {
+            unit: FrequencyUnit::Khz,
+            decimals: 1,
+            ..FrequencyFormat::default()
+        };
+        assert_eq!(format.format(Hertz(156_800_000)), "156800.0 kHz");
+        format.channels = Some(ChannelPlan::Marine);
+        assert_eq!(format.format(Hertz(156_800_000)), "Marine Ch 16");
+        assert_eq!(format.format(Hertz(156_810_000)), "156810.0 kHz");
+        assert_eq!(format.parse("Marine Ch 16"), Ok(Hertz(156_800_000)));
+        assert_eq!(format.parse("ch72"), Ok(Hertz(156_625_000)));
+        assert!(format.parse("Ch 40").is_err());
+        assert_eq!(format.parse("156 625"), Ok(Hertz(156_625_000)));
+
+        format.channels = Some(ChannelPlan::Airband833);
+        assert_eq!(format.format(Hertz(118_008_333)), "Air 118.010");
+        assert_eq!(format.parse("Air 118.010"), Ok(Hertz(118_008_333)));
+    }
+
+    #[test]
+    fn snaps_onto_the_nearest_channel_when_asked() {
+        let mut format = FrequencyFormat {
+            channels: Some(ChannelPlan::Pmr446),
+            ..FrequencyFormat::default()
+        };
+        assert_eq!(format.snap(Hertz(446_010_000)), Hertz(446_010_000));
+        format.snap = true;
+        assert_eq!(format.snap(Hertz(446_010_000)), Hertz(446_006_250));
+        // Outside the band it is left alone
+        assert_eq!(format.snap(Hertz(145_500_000)), Hertz(145_500_000));
+    }
+}
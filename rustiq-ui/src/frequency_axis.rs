@@ -0,0 +1,125 @@
+//! Frequency ticks, labels and grid along the top of the spectrum and
+//! waterfall, so each column can be read off as a frequency.
+
+use eframe::egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Stroke};
+
+use crate::band_map::{Band, Pixels};
+
+/// Least room between labels, in points. The number of ticks follows the
+/// width, so resizing the window thins them out or fills them in.
+const LABEL_SPACING: f32 = 110.0;
+
+/// Length of the tick marks, in points.
+const TICK_LENGTH: f32 = 5.0;
+
+const TICK_COLOR: Color32 = Color32::LIGHT_GRAY;
+const LABEL_COLOR: Color32 = Color32::LIGHT_GRAY;
+/// Faint enough to leave the signals under it readable
+const GRID_COLOR: Color32 = Color32::from_rgba_premultiplied(20, 20, 20, 20);
+
+/// The 1, 2 or 5 times a power of ten of at least `step`.
+fn nice_step(step: f64) -> f64 {
+    let magnitude = 10f64.powf(step.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|&nice| nice >= step * (1.0 - 1e-9))
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Tick spacing and the frequencies of the ticks across `low..=high` Hz,
+/// with no more than `max_ticks` of them.
+fn ticks(low: f64, high: f64, max_ticks: usize) -> Option<(f64, Vec<f64>)> {
+    let span = high - low;
+    if span.is_nan() || span <= 0.0 || max_ticks == 0 {
+        return None;
+    }
+    let step = nice_step(span / max_ticks as f64);
+    let first = (low / step).ceil() as i64;
+    let last = (high / step).floor() as i64;
+    Some((step, (first..=last).map(|n| n as f64 * step).collect()))
+}
+
+/// `frequency` in the unit the band's top is in, to as many decimals as
+/// the tick spacing `step` needs.
+fn label(frequency: f64, step: f64, top: f64) -> String {
+    let (scale, unit) = match top.abs() {
+        top if top >= 1e6 => (1e6, "MHz"),
+        top if top >= 1e3 => (1e3, "kHz"),
+        _ => (1.0, "Hz"),
+    };
+    let decimals = (-(step / scale).log10().floor()).max(0.0) as usize;
+    format!("{:.*} {unit}", decimals, frequency / scale)
+}
+
+/// Paint ticks and frequency labels along the top of `rect`, which shows
+/// `band`, with grid lines down from each tick.
+pub fn paint(painter: &Painter, rect: Rect, band: Band) {
+    let max_ticks = (rect.width() / LABEL_SPACING).floor() as usize;
+    let (low, high) = (band.low(), band.high());
+    let Some((step, frequencies)) = ticks(low, high, max_ticks) else {
+        return;
+    };
+    let top = low.abs().max(high.abs());
+    let font = FontId::monospace(10.0);
+    for frequency in frequencies {
+        let x = Pixels::new(rect.left(), rect.width(), band).x_of(frequency);
+        painter.vline(x, rect.y_range(), Stroke::new(1.0, GRID_COLOR));
+        painter.vline(
+            x,
+            rect.top()..=rect.top() + TICK_LENGTH,
+            Stroke::new(1.0, TICK_COLOR),
+        );
+        painter.text(
+            Pos2::new(x, rect.top() + TICK_LENGTH),
+            Align2::CENTER_TOP,
+            label(frequency, step, top),
+            font.clone(),
+            LABEL_COLOR,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_in_ones_twos_and_fives() {
+        assert_eq!(nice_step(0.7), 1.0);
+        assert_eq!(nice_step(1.0), 1.0);
+        assert_eq!(nice_step(1.3), 2.0);
+        assert_eq!(nice_step(3_000.0), 5_000.0);
+        assert_eq!(nice_step(240_000.0), 500_000.0);
+        assert_eq!(nice_step(6e6), 1e7);
+    }
+
+    #[test]
+    fn ticks_fall_on_round_frequencies_in_the_band() {
+        // 2 MHz around 145.3 MHz, room for 8 labels
+        let (step, frequencies) = ticks(144.3e6, 146.3e6, 8).unwrap();
+        assert_eq!(step, 500e3);
+        assert_eq!(frequencies, vec![144.5e6, 145e6, 145.5e6, 146e6]);
+        assert_eq!(label(144.5e6, step, 146.3e6), "144.5 MHz");
+
+        // Baseband, either side of zero
+        let (step, frequencies) = ticks(-24e3, 24e3, 4).unwrap();
+        assert_eq!(step, 20e3);
+        assert_eq!(frequencies, vec![-20e3, 0.0, 20e3]);
+        assert_eq!(label(-20e3, step, 24e3), "-20 kHz");
+
+        // Narrower windows have fewer
+        let (_, frequencies) = ticks(144.3e6, 146.3e6, 2).unwrap();
+        assert_eq!(frequencies, vec![145e6, 146e6]);
+        assert!(ticks(144.3e6, 146.3e6, 0).is_none());
+        assert!(ticks(145e6, 145e6, 8).is_none());
+    }
+
+    #[test]
+    fn labels_as_many_decimals_as_the_step_needs() {
+        assert_eq!(label(7.074e6, 1e3, 7.1e6), "7.074 MHz");
+        assert_eq!(label(7.07e6, 10e3, 7.1e6), "7.07 MHz");
+        assert_eq!(label(7e6, 1e6, 7.1e6), "7 MHz");
+        assert_eq!(label(500.0, 100.0, 900.0), "500 Hz");
+    }
+}
@@ -0,0 +1,64 @@
+use std::f64::consts::PI;
+
+use rustiq_messages::GeoPosition;
+
+/// Northernmost (and southernmost) latitude the Web Mercator projection covers.
+pub const MAX_LATITUDE: f64 = 85.051_128_78;
+
+/// Project a position to Web Mercator "world" coordinates: `[x, y]` in 0..1,
+/// with the origin at the north-west corner, as used by slippy map tiles.
+pub fn to_world(position: GeoPosition) -> [f64; 2] {
+    let lat = position
+        .latitude
+        .clamp(-MAX_LATITUDE, MAX_LATITUDE)
+        .to_radians();
+    let x = (position.longitude + 180.0) / 360.0;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0;
+    [x, y]
+}
+
+/// Inverse of `to_world`.
+pub fn from_world([x, y]: [f64; 2]) -> GeoPosition {
+    let n = PI * (1.0 - 2.0 * y);
+    GeoPosition {
+        latitude: n.sinh().atan().to_degrees(),
+        longitude: x * 360.0 - 180.0,
+    }
+}
+
+/// Column and row of the tile containing a world point at a zoom level.
+pub fn tile_at([x, y]: [f64; 2], zoom: u8) -> (u32, u32) {
+    let n = 1u32 << zoom;
+    let index = |v: f64| ((v * n as f64).floor().max(0.0) as u32).min(n - 1);
+    (index(x), index(y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_coordinates_round_trip() {
+        for (latitude, longitude) in [(0.0, 0.0), (52.37, 4.89), (-33.86, 151.21), (64.1, -21.9)] {
+            let world = to_world(GeoPosition {
+                latitude,
+                longitude,
+            });
+            assert!(world.iter().all(|v| (0.0..1.0).contains(v)), "{world:?}");
+            let back = from_world(world);
+            assert!((back.latitude - latitude).abs() < 1e-9);
+            assert!((back.longitude - longitude).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn finds_slippy_map_tiles() {
+        let london = to_world(GeoPosition {
+            latitude: 51.5074,
+            longitude: -0.1278,
+        });
+        assert_eq!(tile_at(london, 0), (0, 0));
+        assert_eq!(tile_at(london, 10), (511, 340));
+        assert_eq!(tile_at([1.0, 1.0], 2), (3, 3));
+    }
+}
@@ -0,0 +1,101 @@
+//! Comparison of rendered images against reference PNGs in `tests/golden`.
+//! Run the tests with `RUSTIQ_UPDATE_GOLDEN=1` to (re)write the references
+//! after an intended change, and check the new PNGs in with it.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use eframe::egui::ColorImage;
+
+/// Largest difference allowed in any channel of any pixel, so rounding
+/// differences between platforms don't fail the comparison.
+const TOLERANCE: u8 = 2;
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+/// Assert that `image` matches the reference `name`. On a mismatch the
+/// rendered image is saved to the temp directory for inspection.
+pub fn assert_matches_golden(name: &str, image: &ColorImage) {
+    let path = golden_path(name);
+    if std::env::var_os("RUSTIQ_UPDATE_GOLDEN").is_some() {
+        write_png(&path, image).expect("writing golden image");
+        return;
+    }
+    let golden = read_png(&path).unwrap_or_else(|e| {
+        panic!(
+            "reading {}: {e} (run with RUSTIQ_UPDATE_GOLDEN=1 to create it)",
+            path.display()
+        )
+    });
+    let actual = std::env::temp_dir().join(format!("{name}.actual.png"));
+    let save_actual = || {
+        write_png(&actual, image).expect("writing rendered image");
+        actual.display().to_string()
+    };
+    assert_eq!(
+        golden.size,
+        image.size,
+        "{name} size differs; rendered image saved to {}",
+        save_actual()
+    );
+    let mismatch = golden
+        .pixels
+        .iter()
+        .zip(&image.pixels)
+        .position(|(expected, actual)| {
+            expected
+                .to_array()
+                .iter()
+                .zip(actual.to_array())
+                .any(|(&e, a)| e.abs_diff(a) > TOLERANCE)
+        });
+    if let Some(index) = mismatch {
+        let [width, _] = image.size;
+        panic!(
+            "{name} differs at ({}, {}): expected {:?}, got {:?}; rendered image saved to {}",
+            index % width,
+            index / width,
+            golden.pixels[index],
+            image.pixels[index],
+            save_actual()
+        );
+    }
+}
+
+fn write_png(path: &Path, image: &ColorImage) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let [width, height] = image.size;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let rgba: Vec<u8> = image.pixels.iter().flat_map(|p| p.to_array()).collect();
+    encoder.write_header()?.write_image_data(&rgba)?;
+    Ok(())
+}
+
+fn read_png(path: &Path) -> anyhow::Result<ColorImage> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size().unwrap_or_default()];
+    let info = reader.next_frame(&mut buffer)?;
+    anyhow::ensure!(
+        info.color_type == png::ColorType::Rgba && info.bit_depth == png::BitDepth::Eight,
+        "not an 8-bit RGBA image"
+    );
+    let size = [info.width as usize, info.height as usize];
+    Ok(ColorImage::from_rgba_premultiplied(
+        size,
+        &buffer[..info.buffer_size()],
+    ))
+}
@@ -0,0 +1,224 @@
+//! Headless test harness: runs the app against a `MockEngine`, one egui
+//! frame at a time, and looks up what was drawn by its text.
+//!
+//! egui_kittest isn't used: it finds widgets through their AccessKit nodes,
+//! which the labels painted onto plots and the waterfall don't have, and
+//! its image snapshots need a wgpu renderer. This reads the shapes egui
+//! drew instead, and leaves comparing images to `golden`.
+
+use std::time::Duration;
+
+use eframe::egui::{
+    Context, Event as InputEvent, FullOutput, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2,
+    RawInput, Rect, Shape, Vec2, ViewportId, ViewportInfo, epaint::ClippedShape,
+};
+use rustiq_messages::mock::MockEngine;
+use rustiq_messages::{Command, Event};
+
+use crate::RustIqApp;
+
+/// Size of the simulated window.
+const SCREEN: Vec2 = Vec2::new(1024.0, 768.0);
+
+pub struct Harness {
+    pub app: RustIqApp,
+    pub engine: MockEngine,
+    ctx: Context,
+    /// Shapes drawn in the last frame
+    shapes: Vec<ClippedShape>,
+    /// Whether the window has focus, if the platform says
+    focused: Option<bool>,
+    /// How long after the last frame the app asked for the next
+    repaint_delay: Duration,
+}
+
+impl Harness {
+    /// Build the app and a mock engine on the channels between them.
+    /// `script` adds the engine's events.
+    pub fn new(script: impl FnOnce(MockEngine) -> MockEngine) -> Self {
+        let (cmd_tx, cmd_rx) = flume::unbounded::<Command>();
+        let (event_tx, event_rx) = flume::unbounded::<Event>();
+        let (spectrum_tx, spectrum_rx) = flume::unbounded::<Event>();
+        let engine = MockEngine::new(cmd_rx, event_tx).with_spectrum_channel(spectrum_tx);
+        Self {
+            app: RustIqApp::new(event_rx, spectrum_rx, cmd_tx),
+            engine: script(engine),
+            ctx: Context::default(),
+            shapes: Vec::new(),
+            focused: None,
+            repaint_delay: Duration::ZERO,
+        }
+    }
+
+    /// Give the window focus or take it away, as from the next frame.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = Some(focused);
+    }
+
+    /// How long after the last frame the app asked for the next.
+    pub fn repaint_delay(&self) -> Duration {
+        self.repaint_delay
+    }
+
+    /// Size of the texture named `name`, as uploaded to the GPU, which
+    /// takes textures up to egui's default size without a backend.
+    pub fn texture_size(&self, name: &str) -> Option<[usize; 2]> {
+        let textures = self.ctx.tex_manager();
+        let textures = textures.read();
+        textures
+            .allocated()
+            .find(|(_, meta)| meta.name == name)
+            .map(|(_, meta)| meta.size)
+    }
+
+    /// Run one UI frame with `events` as input.
+    pub fn frame_with(&mut self, events: Vec<InputEvent>) {
+        let viewport = ViewportInfo {
+            focused: self.focused,
+            ..ViewportInfo::default()
+        };
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, SCREEN)),
+            viewports: [(ViewportId::ROOT, viewport)].into_iter().collect(),
+            events,
+            ..RawInput::default()
+        };
+        let FullOutput {
+            shapes,
+            viewport_output,
+            ..
+        } = self.ctx.run(input, |ctx| self.app.show(ctx));
+        self.shapes = shapes;
+        self.repaint_delay = viewport_output[&ViewportId::ROOT].repaint_delay;
+    }
+
+    pub fn frame(&mut self) {
+        self.frame_with(Vec::new());
+    }
+
+    /// Step the engine's script once and draw the result. Windows settle
+    /// their size over a couple of frames, so two are run.
+    pub fn step(&mut self) {
+        assert!(self.engine.step(), "script finished");
+        self.frame();
+        self.frame();
+    }
+
+    /// Step through the rest of the script.
+    pub fn step_all(&mut self) {
+        while self.engine.remaining() > 0 {
+            self.step();
+        }
+    }
+
+    /// Where text containing `needle` was drawn in the last frame.
+    pub fn find_text(&self, needle: &str) -> Option<Rect> {
+        fn find(shape: &Shape, needle: &str) -> Option<Rect> {
+            match shape {
+                Shape::Text(text) if text.galley.text().contains(needle) => {
+                    Some(text.galley.rect.translate(text.pos.to_vec2()))
+                }
+                Shape::Vec(shapes) => shapes.iter().find_map(|shape| find(shape, needle)),
+                _ => None,
+            }
+        }
+        self.shapes
+            .iter()
+            .find_map(|clipped| find(&clipped.shape, needle))
+    }
+
+    pub fn has_text(&self, needle: &str) -> bool {
+        self.find_text(needle).is_some()
+    }
+
+    /// Click on the text containing `needle`, such as a button's label.
+    pub fn click_text(&mut self, needle: &str) {
+        let pos = self
+            .find_text(needle)
+            .unwrap_or_else(|| panic!("{needle:?} not shown"))
+            .center();
+        self.click_at(pos);
+    }
+
+    /// Click at `pos` in the window.
+    pub fn click_at(&mut self, pos: Pos2) {
+        let button = |pressed| InputEvent::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        self.frame_with(vec![InputEvent::PointerMoved(pos), button(true)]);
+        self.frame_with(vec![button(false)]);
+        self.frame();
+    }
+
+    /// Drag from `from` to `to` with the primary button.
+    pub fn drag(&mut self, from: Pos2, to: Pos2) {
+        let button = |pos, pressed| InputEvent::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        self.frame_with(vec![InputEvent::PointerMoved(from), button(from, true)]);
+        self.frame_with(vec![InputEvent::PointerMoved(to)]);
+        self.frame_with(vec![button(to, false)]);
+        self.frame();
+    }
+
+    /// Scroll whatever is under `pos` by `delta` points; negative moves the
+    /// content up, bringing what is below it into view.
+    pub fn scroll_at(&mut self, pos: Pos2, delta: Vec2) {
+        self.frame_with(vec![
+            InputEvent::PointerMoved(pos),
+            InputEvent::MouseWheel {
+                unit: MouseWheelUnit::Point,
+                delta,
+                modifiers: Modifiers::NONE,
+            },
+        ]);
+        // Scrolling is animated
+        for _ in 0..30 {
+            self.frame();
+        }
+    }
+
+    /// Replace the contents of the text field showing `needle` with `text`.
+    pub fn type_text(&mut self, needle: &str, text: &str) {
+        let pos = self
+            .find_text(needle)
+            .unwrap_or_else(|| panic!("{needle:?} not shown"))
+            .center();
+        self.type_at(pos, text);
+    }
+
+    /// Replace the contents of the text field at `pos` with `text`.
+    pub fn type_at(&mut self, pos: Pos2, text: &str) {
+        self.click_at(pos);
+        self.frame_with(vec![
+            InputEvent::Key {
+                key: Key::A,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: Modifiers::COMMAND,
+            },
+            InputEvent::Text(text.to_string()),
+        ]);
+        self.frame();
+    }
+
+    /// Press and release `key`.
+    pub fn press(&mut self, key: Key) {
+        let event = |pressed| InputEvent::Key {
+            key,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        };
+        self.frame_with(vec![event(true), event(false)]);
+        self.frame();
+    }
+}
@@ -0,0 +1,365 @@
+//! HTTP and HTTPS GETs for the opt-in checks that fetch from the web.
+//!
+//! HTTPS servers are checked against the system's certificate authorities.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+/// Version of this build, sent as the user agent.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Give up on the server after this long.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Redirects followed before giving up.
+const MAX_REDIRECTS: u32 = 3;
+
+/// Largest response read, so a misbehaving server can't exhaust memory.
+const MAX_RESPONSE: u64 = 4 * 1024 * 1024;
+
+/// Where distributions keep their bundle of trusted certificate authorities,
+/// if `SSL_CERT_FILE` doesn't say.
+const CA_BUNDLES: [&str; 4] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/ssl/cert.pem",
+];
+
+/// A connection to the server, encrypted or not.
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// GET an `http://` or `https://` URL for a body of the `accept` media
+/// type, returning the body of a 200 response.
+pub fn get(url: &str, accept: &str) -> io::Result<String> {
+    fetch(url, accept, MAX_REDIRECTS, None)
+}
+
+/// GET a URL, returning the body of a 200 response. HTTPS servers are
+/// checked against `tls`, else the system's authorities. Follows up to
+/// `redirects` redirects, but not from HTTPS back to plain HTTP.
+fn fetch(
+    url: &str,
+    accept: &str,
+    redirects: u32,
+    tls: Option<Arc<ClientConfig>>,
+) -> io::Result<String> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let (secure, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{url} is not an HTTP URL"),
+        ));
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = if path.is_empty() { "/" } else { path };
+    let (host, address) = match authority.rsplit_once(':') {
+        Some((host, _)) => (host, authority.to_string()),
+        None => (
+            authority,
+            format!("{authority}:{}", if secure { 443 } else { 80 }),
+        ),
+    };
+
+    let tcp = connect(&address)?;
+    let mut stream: Box<dyn Stream> = if secure {
+        let config = match &tls {
+            Some(config) => config.clone(),
+            None => system_tls()?,
+        };
+        let name =
+            ServerName::try_from(host.to_string()).map_err(|e| invalid(format!("{host}: {e}")))?;
+        let connection = ClientConnection::new(config, name).map_err(io::Error::other)?;
+        Box::new(StreamOwned::new(connection, tcp))
+    } else {
+        Box::new(tcp)
+    };
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: {authority}\r\nUser-Agent: rustiq/{VERSION}\r\n\
+         Accept: {accept}\r\nConnection: close\r\n\r\n"
+    )?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream.take(MAX_RESPONSE));
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| invalid(format!("bad status line {status_line:?}")))?;
+
+    let mut chunked = false;
+    let mut length = None;
+    let mut location = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("response ended in the headers".to_string()));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            } else if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse::<u64>().ok();
+            } else if name.eq_ignore_ascii_case("location") {
+                location = Some(value.to_string());
+            }
+        }
+    }
+    match (status, location) {
+        (200, _) => {}
+        (300..=399, Some(location))
+            if redirects > 0
+                && (location.starts_with("https://")
+                    || !secure && location.starts_with("http://")) =>
+        {
+            return fetch(&location, accept, redirects - 1, tls);
+        }
+        (300..=399, Some(location)) => {
+            return Err(invalid(format!("moved to {location}")));
+        }
+        _ => return Err(invalid(format!("server answered {}", status_line.trim()))),
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        read_chunked(&mut reader, &mut body)?;
+    } else if let Some(length) = length {
+        // Read no further, so a server that closes without a TLS
+        // close_notify doesn't fail a complete response
+        reader.take(length).read_to_end(&mut body)?;
+    } else {
+        reader.read_to_end(&mut body)?;
+    }
+    String::from_utf8(body).map_err(|e| invalid(e.to_string()))
+}
+
+/// Connect to the first of `address`'s addresses that answers.
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for socket_address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&socket_address, TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{address} has no address"),
+        )
+    }))
+}
+
+/// TLS trusting the certificate authorities in the system's bundle.
+fn system_tls() -> io::Result<Arc<ClientConfig>> {
+    let from_env = std::env::var("SSL_CERT_FILE").ok();
+    let bundle = from_env
+        .as_deref()
+        .into_iter()
+        .chain(CA_BUNDLES)
+        .find(|path| Path::new(path).is_file())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no certificate authorities found; set SSL_CERT_FILE",
+            )
+        })?;
+    tls_trusting(Path::new(bundle))
+}
+
+/// TLS trusting the certificate authorities in the PEM file `bundle`.
+fn tls_trusting(bundle: &Path) -> io::Result<Arc<ClientConfig>> {
+    let unreadable = |e: rustls::pki_types::pem::Error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("reading {}: {e}", bundle.display()),
+        )
+    };
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(bundle).map_err(unreadable)? {
+        // Bundles carry the odd certificate rustls can't parse; skip those
+        let _ = roots.add(cert.map_err(unreadable)?);
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Decode a `Transfer-Encoding: chunked` body.
+fn read_chunked(reader: &mut impl BufRead, body: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        let mut size_line = String::new();
+        reader.read_line(&mut size_line)?;
+        // Chunk extensions follow a ';'
+        let size = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("bad chunk size {size:?}"),
+            )
+        })?;
+        if size == 0 {
+            return Ok(());
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut crlf = [0; 2];
+        reader.read_exact(&mut crlf)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread;
+
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ServerConfig, ServerConnection};
+
+    use super::*;
+
+    /// Certificates in `tests/tls`: a test authority, and a certificate it
+    /// issued for localhost.
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/tls")
+            .join(name)
+    }
+
+    /// Answer one request on a local port with `response`, over TLS with
+    /// the localhost certificate if `secure`. Returns the port.
+    fn serve(secure: bool, response: String) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            let mut stream: Box<dyn Stream> = if secure {
+                let certs = CertificateDer::pem_file_iter(fixture("server.pem"))
+                    .unwrap()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                let key = PrivateKeyDer::from_pem_file(fixture("server.key")).unwrap();
+                let config = ServerConfig::builder()
+                    .with_no_client_auth()
+                    .with_single_cert(certs, key)
+                    .unwrap();
+                let connection = ServerConnection::new(Arc::new(config)).unwrap();
+                Box::new(StreamOwned::new(connection, tcp))
+            } else {
+                Box::new(tcp)
+            };
+            let mut reader = BufReader::new(&mut stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                line.clear();
+            }
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.flush();
+        });
+        port
+    }
+
+    fn ok(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    #[test]
+    fn fetches_over_https() {
+        let port = serve(true, ok("<feed/>"));
+        let tls = tls_trusting(&fixture("ca.pem")).unwrap();
+        let body = fetch(
+            &format!("https://localhost:{port}/releases.atom"),
+            "application/atom+xml",
+            0,
+            Some(tls),
+        );
+        assert_eq!(body.unwrap(), "<feed/>");
+    }
+
+    #[test]
+    fn follows_a_redirect_to_https() {
+        let secure = serve(true, ok("<feed/>"));
+        let redirect = format!(
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: https://localhost:{secure}/releases.atom\r\n\
+             Content-Length: 0\r\n\r\n"
+        );
+        let plain = serve(false, redirect);
+        let tls = tls_trusting(&fixture("ca.pem")).unwrap();
+        let body = fetch(
+            &format!("http://localhost:{plain}/releases.atom"),
+            "application/atom+xml",
+            1,
+            Some(tls),
+        );
+        assert_eq!(body.unwrap(), "<feed/>");
+    }
+
+    #[test]
+    fn refuses_an_untrusted_server() {
+        let port = serve(true, ok("<feed/>"));
+        let other = ClientConfig::builder()
+            .with_root_certificates(RootCertStore::empty())
+            .with_no_client_auth();
+        let body = fetch(
+            &format!("https://localhost:{port}/releases.atom"),
+            "application/atom+xml",
+            0,
+            Some(Arc::new(other)),
+        );
+        assert!(body.is_err());
+    }
+
+    #[test]
+    fn refuses_a_redirect_from_https_to_http() {
+        let redirect = "HTTP/1.1 302 Found\r\nLocation: http://localhost/releases.atom\r\n\
+                        Content-Length: 0\r\n\r\n";
+        let port = serve(true, redirect.to_string());
+        let tls = tls_trusting(&fixture("ca.pem")).unwrap();
+        let body = fetch(
+            &format!("https://localhost:{port}/releases.atom"),
+            "application/atom+xml",
+            1,
+            Some(tls),
+        );
+        assert!(body.unwrap_err().to_string().contains("moved to"));
+    }
+
+    #[test]
+    fn decodes_chunked_body() {
+        let mut body = Vec::new();
+        let mut input: &[u8] = b"5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        read_chunked(&mut input, &mut body).unwrap();
+        assert_eq!(body, b"hello, world");
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use eframe::egui::{DragValue, Response, Ui, Widget};
+use egui_plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+use flume::Sender;
+
+use crate::rate::{counts_per_bin, moving_average};
+use rustiq_messages::{Command, Decibels, Impulse};
+
+/// Number of impulses kept for the trend plot.
+const MAX_HISTORY: usize = 100_000;
+
+/// Minutes averaged for the trend line.
+const TREND_MINUTES: usize = 10;
+
+/// Lightning / impulse noise counter panel.
+///
+/// The widget (`ui.add(&mut panel)`) renders the threshold control and crash
+/// counts; `show_trend()` renders crashes per minute over time.
+pub struct ImpulsePanel {
+    cmd_tx: Sender<Command>,
+    /// Threshold entered in the threshold field
+    threshold: Decibels,
+    /// Threshold the engine is currently counting with
+    active: Option<Decibels>,
+    impulses: Vec<Impulse>,
+}
+
+impl ImpulsePanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            threshold: Decibels(10.0),
+            active: None,
+            impulses: Vec::new(),
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, impulse_counter: Option<Decibels>) {
+        if impulse_counter != self.active {
+            self.impulses.clear();
+        }
+        self.active = impulse_counter;
+        if let Some(threshold) = impulse_counter {
+            self.threshold = threshold;
+        }
+    }
+
+    pub fn insert_impulse(&mut self, impulse: Impulse) {
+        if self.impulses.len() == MAX_HISTORY {
+            self.impulses.remove(0);
+        }
+        self.impulses.push(impulse);
+    }
+
+    pub fn has_data(&self) -> bool {
+        !self.impulses.is_empty()
+    }
+
+    fn per_minute(&self) -> Vec<usize> {
+        counts_per_bin(
+            self.impulses.iter().map(|i| i.time),
+            Duration::from_secs(60),
+        )
+    }
+
+    /// Render crashes per minute, with a trailing average as the trend.
+    pub fn show_trend(&self, ui: &mut Ui) {
+        let counts = self.per_minute();
+        let bars = counts
+            .iter()
+            .enumerate()
+            .map(|(minute, &count)| Bar::new(minute as f64 + 0.5, count as f64).width(1.0))
+            .collect();
+        let trend: PlotPoints = moving_average(&counts, TREND_MINUTES)
+            .into_iter()
+            .enumerate()
+            .map(|(minute, mean)| [minute as f64 + 0.5, mean])
+            .collect();
+        Plot::new("impulse_trend")
+            .height(ui.available_height())
+            .legend(Legend::default())
+            .x_axis_label("Time (min)")
+            .y_axis_label("Crashes per min")
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new("Crashes", bars));
+                plot_ui.line(Line::new(format!("{TREND_MINUTES} min average"), trend));
+            });
+    }
+
+    fn send_start(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::StartImpulseCounter(self.threshold));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopImpulseCounter);
+    }
+}
+
+impl Widget for &mut ImpulsePanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Impulse Counter");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Threshold:");
+            let mut db = self.threshold.0;
+            if ui
+                .add(
+                    DragValue::new(&mut db)
+                        .speed(0.5)
+                        .range(1.0..=60.0)
+                        .suffix(" dB"),
+                )
+                .changed()
+            {
+                self.threshold = Decibels(db);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let retune = self.active.is_some_and(|active| active != self.threshold);
+            let label = if retune { "Apply" } else { "Start" };
+            ui.add_enabled_ui(self.active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+
+        if self.has_data() {
+            ui.add_space(5.0);
+            let counts = self.per_minute();
+            ui.label(format!("Crashes: {}", self.impulses.len()));
+            // The last bin is still filling, so report the last complete minute
+            if counts.len() > 1 {
+                ui.label(format!("Last minute: {}", counts[counts.len() - 2]));
+            }
+        } else if self.active.is_some() {
+            ui.label("Waiting for impulses...");
+        }
+
+        ui.response()
+    }
+}
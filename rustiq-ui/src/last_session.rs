@@ -0,0 +1,168 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::Context;
+use log::{info, warn};
+
+use rustiq_messages::{Command, LastSession, WindowGeometry};
+
+/// Where the app keeps what it was set to between runs, and whether to put
+/// the engine's part back too: not for an engine started on its own, or
+/// one given a file to play.
+#[derive(Debug, Clone)]
+pub struct LastSessionFile {
+    pub path: PathBuf,
+    pub restore_engine: bool,
+}
+
+impl LastSessionFile {
+    /// The settings saved at the end of the last run, if there are any
+    /// that can be read.
+    pub fn load(&self) -> Option<LastSession> {
+        match load(&self.path) {
+            Ok(session) => Some(session),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                warn!(
+                    "Ignoring the last session in {}: {}",
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, session: &LastSession) {
+        match save(&self.path, session) {
+            Ok(()) => info!("Saved the session to {}", self.path.display()),
+            Err(e) => warn!(
+                "Failed to save the session to {}: {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Commands that put the engine back as the last session left it. The
+/// gain is set after tuning, as tuning applies the gain profiles.
+pub fn commands(session: &LastSession) -> Vec<Command> {
+    vec![
+        Command::ChangeSource(session.source_config.clone()),
+        Command::SetFftSize(session.fft_size),
+        Command::SetCenterFrequency(session.center_frequency),
+        Command::SetGain(session.gain),
+        Command::SetSquelch(session.squelch),
+        Command::SetDemodulator(session.listening),
+        Command::SetPassband(session.passband),
+    ]
+}
+
+/// The window's size and position as egui last saw them.
+pub fn window_geometry(ctx: &Context) -> Option<WindowGeometry> {
+    ctx.input(|input| {
+        let viewport = input.viewport();
+        let inner = viewport.inner_rect?;
+        Some(WindowGeometry {
+            size: (inner.width(), inner.height()),
+            position: viewport.outer_rect.map(|outer| (outer.left(), outer.top())),
+        })
+    })
+}
+
+/// The session as TOML, so it can be read and fixed by hand, and read by
+/// later versions with fields added.
+fn save(path: &Path, session: &LastSession) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(session).map_err(io::Error::other)?;
+    std::fs::write(path, text)
+}
+
+fn load(path: &Path) -> io::Result<LastSession> {
+    let text = std::fs::read_to_string(path)?;
+    toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use rustiq_messages::{
+        Annotation, AnnotationPoint, AnnotationShape, AudioChannel, Decibels, DemodMode, Hertz,
+        Passband, SourceConfig,
+    };
+
+    fn session_file(name: &str) -> LastSessionFile {
+        LastSessionFile {
+            path: std::env::temp_dir()
+                .join(format!("rustiq-{name}-{}", std::process::id()))
+                .join("last-session.toml"),
+            restore_engine: true,
+        }
+    }
+
+    #[test]
+    fn saves_and_loads_the_session() {
+        let file = session_file("last");
+        assert_eq!(file.load(), None);
+        let session = LastSession {
+            source_config: SourceConfig::default(),
+            center_frequency: Hertz::mhz(145),
+            gain: Decibels(20.0),
+            fft_size: 2048,
+            listening: Some(AudioChannel {
+                frequency: Hertz(145_500_000),
+                demod: DemodMode::Fm,
+            }),
+            squelch: None,
+            passband: Some(Passband {
+                low: -6_000,
+                high: 6_000,
+            }),
+            volume: 0.5,
+            colormap: "Turbo".to_string(),
+            window: Some(WindowGeometry {
+                size: (1280.0, 800.0),
+                position: None,
+            }),
+            annotations: vec![Annotation {
+                shape: AnnotationShape::Arrow,
+                start: AnnotationPoint {
+                    frequency: Hertz(145_490_000),
+                    time: Duration::from_secs(12),
+                },
+                end: AnnotationPoint {
+                    frequency: Hertz(145_500_000),
+                    time: Duration::from_millis(12_500),
+                },
+                text: "repeater".to_string(),
+            }],
+        };
+        file.save(&session);
+        assert_eq!(file.load(), Some(session));
+
+        std::fs::write(&file.path, b"something else").unwrap();
+        assert_eq!(file.load(), None);
+        std::fs::remove_dir_all(file.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn fills_in_what_an_older_file_lacks() {
+        let file = session_file("older");
+        std::fs::create_dir_all(file.path.parent().unwrap()).unwrap();
+        std::fs::write(&file.path, "fft_size = 8192\ncolormap = \"Turbo\"\n").unwrap();
+        assert_eq!(
+            file.load(),
+            Some(LastSession {
+                fft_size: 8192,
+                colormap: "Turbo".to_string(),
+                ..LastSession::default()
+            })
+        );
+        std::fs::remove_dir_all(file.path.parent().unwrap()).unwrap();
+    }
+}
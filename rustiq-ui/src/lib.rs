@@ -1,49 +1,468 @@
+mod annotation_panel;
+mod antenna_panel;
+#[cfg(feature = "audio")]
+mod audio;
+mod audio_panel;
+mod band_map;
+mod burst_panel;
+mod calibration_panel;
+mod carrier_panel;
+mod clock_check;
+mod close_prompt;
+mod colormap;
+mod colormap_files;
+mod comparison_panel;
+mod connect_dialog;
+mod console;
 mod control_panel;
+mod decode_log;
+mod diagnostics_panel;
+// Set in the audio panel, but only the audio output runs it
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+mod equalizer;
+mod exclusion_panel;
+mod flow;
+mod frequency;
+mod frequency_axis;
+mod geo;
+#[cfg(test)]
+mod golden;
+#[cfg(test)]
+mod harness;
+mod http;
+mod impulse_panel;
+mod last_session;
+mod macro_panel;
+mod map_panel;
+mod measurement;
+mod meteor_panel;
+mod overview_panel;
+mod passband;
+mod power;
+mod propagation;
+mod rate;
+mod recording_panel;
+// Only the audio output plays at another rate
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+mod resample;
+mod rig_panel;
+mod rotator_panel;
+mod scanner_panel;
+mod schedule;
+mod selcall_panel;
+mod session_prompt;
+mod settings_panel;
+mod spectrum_plot;
+mod sstv_panel;
 mod state;
+mod symbol_rate_panel;
+mod time_axis;
+mod toasts;
+mod tuning_panel;
+mod update_check;
+mod vfo_panel;
+mod wake;
 mod waterfall;
+mod zoom;
 
-use rustiq_messages::{Command, Event};
+use colormap::Colormap;
+use comparison_panel::ComparisonPanel;
+use connect_dialog::ConnectDialog;
+pub use connect_dialog::{Connector, EngineChannels};
+pub use last_session::LastSessionFile;
+use rustiq_messages::{Command, Event, Feature, LastSession, RustIqError, WindowGeometry};
 use state::UiState;
+use waterfall::Waterfall;
+
+/// Stands in for the audio output when it is left out of the build, so the
+/// audio panel can say why nothing plays.
+#[cfg(not(feature = "audio"))]
+mod audio {
+    use rustiq_messages::AudioChunk;
+
+    use crate::equalizer::EqSettings;
+
+    pub struct AudioOutput;
+
+    impl AudioOutput {
+        pub fn open() -> Result<Self, String> {
+            Err("built without audio output (the audio feature)".to_string())
+        }
+
+        pub fn play(&mut self, _chunk: &AudioChunk, _volume: f32, _eq: &EqSettings) {}
+    }
+}
 
 /// Main application struct implementing the egui App trait.
 pub struct RustIqApp {
     /// Receiver for events from engine
     event_rx: flume::Receiver<Event>,
 
+    /// Receiver for spectrum frames from engine
+    spectrum_rx: flume::Receiver<Event>,
+
     /// Local application state
     state: UiState,
+
+    /// Where to keep what the app is set to for the next run, if anywhere
+    last_session: Option<LastSessionFile>,
+    /// Engine settings of the last run, put back once the engine is heard
+    /// from
+    restore: Option<LastSession>,
+    /// The window's size and position, as last seen
+    window: Option<WindowGeometry>,
+    /// Switches to an engine elsewhere while running
+    connect_dialog: ConnectDialog,
+    /// Woken as the engine's events come in, if running in a window
+    waker: Option<eframe::egui::Context>,
 }
 
 impl RustIqApp {
-    fn new(event_rx: flume::Receiver<Event>, cmd_tx: flume::Sender<Command>) -> Self {
+    fn new(
+        event_rx: flume::Receiver<Event>,
+        spectrum_rx: flume::Receiver<Event>,
+        cmd_tx: flume::Sender<Command>,
+    ) -> Self {
         Self {
             event_rx,
+            spectrum_rx,
             state: UiState::new(cmd_tx),
+            last_session: None,
+            restore: None,
+            window: None,
+            connect_dialog: ConnectDialog::new(None),
+            waker: None,
+        }
+    }
+
+    /// Wake `ctx` for a frame as the engine's events come in, rather than
+    /// waiting on the next frame to take them in.
+    fn with_waker(mut self, ctx: eframe::egui::Context) -> Self {
+        self.event_rx = wake::forward(self.event_rx, ctx.clone());
+        self.waker = Some(ctx);
+        self
+    }
+
+    /// Offer to switch to another engine while running, connecting to it
+    /// with `connector`.
+    fn with_connector(mut self, connector: Connector) -> Self {
+        self.connect_dialog = ConnectDialog::new(Some(connector));
+        self
+    }
+
+    /// Switch to the engine on `channels`, just connected to.
+    fn switch_engine(&mut self, (event_rx, spectrum_rx, cmd_tx): EngineChannels) {
+        self.event_rx = match &self.waker {
+            Some(ctx) => wake::forward(event_rx, ctx.clone()),
+            None => event_rx,
+        };
+        self.spectrum_rx = spectrum_rx;
+        self.state.switch_engine(cmd_tx);
+        // The last run's engine settings were for the engine it started with
+        self.restore = None;
+    }
+
+    /// Keep what the app is set to in `file` when it closes, starting from
+    /// `session`, what it held from the last run.
+    fn with_last_session(mut self, file: LastSessionFile, session: Option<LastSession>) -> Self {
+        if let Some(session) = session {
+            self.state.restore_view(&session);
+            self.window = session.window;
+            if file.restore_engine {
+                self.restore = Some(session);
+            }
         }
+        self.last_session = Some(file);
+        self
     }
 }
 
 impl eframe::App for RustIqApp {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
-        // Pull events from engine
-        if let Ok(event) = self.event_rx.try_recv() {
+        self.show(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Some(file) = &self.last_session
+            && let Some(session) = self.state.last_session(self.window)
+        {
+            file.save(&session);
+        }
+    }
+}
+
+impl RustIqApp {
+    /// Lay out one frame and pass the commands it gave on to the engine.
+    fn show(&mut self, ctx: &eframe::egui::Context) {
+        self.lay_out(ctx);
+        self.state.forward_commands();
+        if let Some(window) = last_session::window_geometry(ctx) {
+            self.window = Some(window);
+        }
+    }
+
+    /// Handle pending events and lay out one frame.
+    fn lay_out(&mut self, ctx: &eframe::egui::Context) {
+        if let Some(channels) = self.connect_dialog.take_connected() {
+            self.switch_engine(channels);
+        }
+        // Pull events from engine, state and decodes ahead of spectrum frames
+        for event in self.event_rx.try_iter() {
+            self.state.handle_event(event);
+        }
+        for event in self.spectrum_rx.try_iter() {
             self.state.handle_event(event);
         }
+        // The engine is running now, as it started
+        if self.state.engine_state.is_some()
+            && let Some(session) = self.restore.take()
+        {
+            self.state.restore_engine(&session);
+        }
 
-        // Always request continuous repainting for smooth 60 FPS
-        ctx.request_repaint();
+        // Flash the taskbar entry when a decode matches an alert rule
+        if self.state.selcall_panel.take_alert().is_some() {
+            ctx.send_viewport_cmd(eframe::egui::ViewportCommand::RequestUserAttention(
+                eframe::egui::UserAttentionType::Informational,
+            ));
+        }
+
+        self.state.update_check.poll();
+        self.state.clock_check.poll();
+        self.state.propagation.poll();
+
+        self.state.close_prompt.intercept(ctx);
+        self.state.macro_panel.poll_hotkeys(ctx);
+
+        // Events are taken in even while minimized, but nothing is drawn
+        let listening = self.state.audio_panel.plays_here();
+        self.state.power_saving.schedule(ctx, listening);
+        if self.state.power_saving.hidden() {
+            return;
+        }
+
+        // Status bar along the bottom edge, across the full width
+        eframe::egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(concat!("RustIQ ", env!("CARGO_PKG_VERSION")));
+                ui.separator();
+                self.state.spectrum_flow.show(ui);
+                ui.separator();
+                self.state.update_check.show(ui);
+                ui.separator();
+                self.state.clock_check.show(ui);
+                ui.separator();
+                self.state.propagation.show(ui);
+                ui.separator();
+                self.state.power_saving.show(ui);
+                ui.separator();
+                self.state.close_prompt.show_toggle(ui);
+                ui.separator();
+                self.state.console.show_toggle(ui);
+                ui.separator();
+                self.connect_dialog.show_toggle(ui);
+            });
+        });
 
         // Right side panel for controls
         eframe::egui::SidePanel::right("control_panel")
             .default_width(250.0)
             .show(ctx, |ui| {
-                ui.add(&mut self.state.control_panel);
+                eframe::egui::ScrollArea::vertical().show(ui, |ui| {
+                    let state = &mut self.state;
+                    ui.add(&mut state.control_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.second_control_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.tuning_panel);
+                    ui.add_space(20.0);
+                    state
+                        .audio_panel
+                        .set_selection(state.waterfall.selected_region());
+                    ui.add(&mut state.audio_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.scanner_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.vfo_panel);
+                    ui.add_space(20.0);
+                    // Only the parts the engine supports
+                    if state.supports(Feature::AntennaSwitch) {
+                        ui.add(&mut state.antenna_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::Rig) {
+                        ui.add(&mut state.rig_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::Rotator) {
+                        ui.add(&mut state.rotator_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::CarrierMeasurement) {
+                        ui.add(&mut state.carrier_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::BurstDetection) {
+                        ui.add(&mut state.burst_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::MeteorDetection) {
+                        ui.add(&mut state.meteor_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::ImpulseCounter) {
+                        ui.add(&mut state.impulse_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::SymbolRateEstimation) {
+                        state
+                            .symbol_rate_panel
+                            .set_selection(state.waterfall.selected_region());
+                        ui.add(&mut state.symbol_rate_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::SstvDecoder) {
+                        state
+                            .sstv_panel
+                            .set_selection(state.waterfall.selected_region());
+                        ui.add(&mut state.sstv_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::SelCallDecoder) {
+                        state
+                            .selcall_panel
+                            .set_selection(state.waterfall.selected_region());
+                        ui.add(&mut state.selcall_panel);
+                        ui.add_space(20.0);
+                    }
+                    if state.supports(Feature::AisDecoder) || state.supports(Feature::AdsbDecoder) {
+                        ui.add(&mut state.map_panel);
+                        ui.add_space(20.0);
+                    }
+                    ui.add(&mut state.diagnostics_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.settings_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.macro_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.calibration_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.recording_panel);
+                    ui.add_space(20.0);
+                    state
+                        .exclusion_panel
+                        .set_selection(state.waterfall.selected_region());
+                    ui.add(&mut state.exclusion_panel);
+                    ui.add_space(20.0);
+                    ui.add(&mut state.annotation_panel);
+                    ui.add_space(20.0);
+                    state
+                        .comparison_panel
+                        .set_latest(state.waterfall.latest_time());
+                    ui.add(&mut state.comparison_panel);
+                });
+            });
+        self.state.transfer_settings();
+        self.state.apply_exclusions();
+
+        // Bottom panel for carrier measurement plots
+        if self.state.carrier_panel.has_data() {
+            eframe::egui::TopBottomPanel::bottom("carrier_plots")
+                .resizable(true)
+                .default_height(200.0)
+                .show(ctx, |ui| {
+                    self.state.carrier_panel.show_plots(ui);
+                });
+        }
+
+        // Bottom panel for burst histograms
+        if self.state.burst_panel.has_data() {
+            eframe::egui::TopBottomPanel::bottom("burst_plots")
+                .resizable(true)
+                .default_height(200.0)
+                .show(ctx, |ui| {
+                    self.state.burst_panel.show_plots(ui);
+                });
+        }
+
+        // Bottom panel for the meteor ping rate timeline
+        if self.state.meteor_panel.has_data() {
+            eframe::egui::TopBottomPanel::bottom("meteor_timeline")
+                .resizable(true)
+                .default_height(150.0)
+                .show(ctx, |ui| {
+                    self.state.meteor_panel.show_timeline(ui);
+                });
+        }
+
+        // Bottom panel for the impulse rate trend
+        if self.state.impulse_panel.has_data() {
+            eframe::egui::TopBottomPanel::bottom("impulse_trend")
+                .resizable(true)
+                .default_height(150.0)
+                .show(ctx, |ui| {
+                    self.state.impulse_panel.show_trend(ui);
+                });
+        }
+
+        // Bottom panel for decoded messages
+        if self.state.decode_log.has_entries() {
+            eframe::egui::TopBottomPanel::bottom("decode_log")
+                .resizable(true)
+                .default_height(150.0)
+                .show(ctx, |ui| {
+                    self.state.decode_log.show(ui);
+                });
+        }
+
+        // Left panel for the overview of the recording being played
+        if self.state.overview_panel.has_overview() {
+            eframe::egui::SidePanel::left("recording_overview")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| {
+                    self.state.overview_panel.show(ui);
+                });
+        }
+
+        // Left panel for the SSTV image
+        if self.state.sstv_panel.has_image() {
+            eframe::egui::SidePanel::left("sstv_image")
+                .resizable(true)
+                .default_width(320.0)
+                .show(ctx, |ui| {
+                    self.state.sstv_panel.show_image(ui);
+                });
+        }
+
+        // Floating window for the AIS/ADS-B map
+        let mut map_open = self.state.map_panel.map_open();
+        eframe::egui::Window::new("Map")
+            .open(&mut map_open)
+            .default_size([640.0, 480.0])
+            .show(ctx, |ui| {
+                self.state.map_panel.show_map(ui);
             });
+        self.state.map_panel.set_map_open(map_open);
+
+        // Offer to restore a crashed session
+        self.state.session_prompt.show(ctx);
+
+        // Ask before closing the window
+        self.state.close_prompt.show(ctx);
+
+        // Errors the engine reported
+        self.state.toasts.show(ctx);
+
+        // Floating window for typed commands
+        let state = &mut self.state;
+        state.console.show(ctx, state.engine_state.as_ref());
+        self.connect_dialog.show(ctx);
 
         // Central panel for waterfall
         eframe::egui::CentralPanel::default().show(ctx, |ui| {
             if self.state.engine_state.is_some() {
-                ui.add(&mut self.state.waterfall);
+                self.show_waterfalls(ui);
             } else {
                 ui.centered_and_justified(|ui| {
                     ui.label("Waiting for engine connection...");
@@ -53,23 +472,1839 @@ impl eframe::App for RustIqApp {
     }
 }
 
+impl RustIqApp {
+    /// Draw the spectrum over the waterfall, or with a second source both
+    /// side by side, under the color map they share, with a cursor linked across them at the hovered
+    /// frequency. A click on any of them tunes to the bin clicked.
+    fn show_waterfalls(&mut self, ui: &mut eframe::egui::Ui) {
+        let state = &mut self.state;
+        let mut colormap = state
+            .colormap_files
+            .take_picked()
+            .unwrap_or_else(|| state.waterfall.colormap());
+        ui.horizontal(|ui| {
+            eframe::egui::ComboBox::from_label("Color map")
+                .selected_text(colormap.label())
+                .show_ui(ui, |ui| {
+                    let loaded = state.colormap_files.loaded().iter().cloned();
+                    for choice in Colormap::ALL.into_iter().chain(loaded) {
+                        ui.horizontal(|ui| {
+                            colormap_files::preview(ui, &choice);
+                            let label = choice.label().to_string();
+                            ui.selectable_value(&mut colormap, choice, label);
+                        });
+                    }
+                });
+            colormap_files::preview(ui, &colormap);
+            ui.add(&mut state.colormap_files);
+        });
+        state.waterfall.set_colormap(colormap.clone());
+        state.second_waterfall.set_colormap(colormap.clone());
+        state.overview_panel.set_colormap(colormap);
+        // Drags on the main waterfall draw annotations while a tool is picked
+        state
+            .waterfall
+            .set_measuring(!state.annotation_panel.annotating());
+        state.waterfall.set_pinned(state.comparison_panel.pinned());
+        // Each plot shows the part of the band its waterfall is zoomed to
+        state.spectrum_plot.set_zoom(state.waterfall.zoom());
+        state
+            .second_spectrum_plot
+            .set_zoom(state.second_waterfall.zoom());
+
+        let dual = state
+            .engine_state
+            .as_ref()
+            .is_some_and(|engine_state| engine_state.second_source.is_some());
+        // Plot and waterfall responses of each source, and of the stretch
+        // pinned beside the main one
+        let mut then = None;
+        let responses = if dual {
+            ui.columns(2, |columns| {
+                columns[0].label("Main source");
+                columns[1].label("Second source");
+                vec![
+                    (columns[0].add(&mut state.spectrum_plot), {
+                        let (now, pinned) = add_main_waterfall(
+                            &mut columns[0],
+                            &mut state.waterfall,
+                            &state.comparison_panel,
+                        );
+                        then = pinned;
+                        now
+                    }),
+                    (
+                        columns[1].add(&mut state.second_spectrum_plot),
+                        columns[1].add(&mut state.second_waterfall),
+                    ),
+                ]
+            })
+        } else {
+            let plot = ui.add(&mut state.spectrum_plot);
+            let (now, pinned) =
+                add_main_waterfall(ui, &mut state.waterfall, &state.comparison_panel);
+            then = pinned;
+            vec![(plot, now)]
+        };
+
+        // Zooming on a plot or the pinned stretch zooms its waterfall too
+        let waterfalls = [&mut state.waterfall, &mut state.second_waterfall];
+        for ((plot, _), waterfall) in responses.iter().zip(waterfalls) {
+            waterfall.update_zoom(ui, plot, eframe::egui::PointerButton::Primary);
+        }
+        if let Some(then) = &then {
+            state
+                .waterfall
+                .update_zoom(ui, then, eframe::egui::PointerButton::Secondary);
+        }
+
+        // The listening channel's passband, on the main source's
+        if let Some((channel, passband)) = state.audio_panel.passband() {
+            let (plot, waterfall) = &responses[0];
+            if let Some(passband) = state.passband.show(
+                ui,
+                plot.rect,
+                waterfall.rect,
+                &state.waterfall,
+                channel.frequency.as_hz() as f64,
+                passband,
+            ) {
+                state.audio_panel.set_passband(Some(passband));
+            }
+        }
+
+        // The VFOs, HF bands, excluded regions and annotations, on the main
+        // source's
+        let (plot, waterfall) = &responses[0];
+        state
+            .exclusion_panel
+            .show_regions(ui, plot.rect, &state.waterfall);
+        state
+            .propagation
+            .show_bands(ui, plot.rect, &state.waterfall);
+        state
+            .vfo_panel
+            .show_markers(ui, plot.rect, waterfall.rect, &state.waterfall);
+        state
+            .annotation_panel
+            .update(ui, waterfall, &state.waterfall);
+        state
+            .annotation_panel
+            .show(ui, waterfall.rect, &state.waterfall);
+
+        // The plot shares the waterfall's frequency axis
+        let views = [
+            (&state.spectrum_plot, &state.waterfall),
+            (&state.second_spectrum_plot, &state.second_waterfall),
+        ];
+        let hovered = responses
+            .iter()
+            .zip(views)
+            .flat_map(|((plot, waterfall), (_, view))| [(plot, view), (waterfall, view)])
+            .chain(then.iter().map(|then| (then, &state.waterfall)))
+            .find_map(|(response, waterfall)| {
+                waterfall.frequency_at(response.rect, response.hover_pos()?.x)
+            });
+        if let Some(frequency) = hovered {
+            for ((plot_response, waterfall_response), (plot, waterfall)) in
+                responses.iter().zip(views)
+            {
+                plot.mark_frequency(ui, plot_response.rect, frequency);
+                waterfall.mark_frequency(ui, waterfall_response.rect, frequency);
+            }
+            if let Some(then) = &then {
+                state.waterfall.mark_frequency(ui, then.rect, frequency);
+            }
+            // What is scheduled there, on the main source's
+            state.tuning_panel.schedule().mark_frequency(
+                ui,
+                responses[0].1.rect,
+                &state.waterfall,
+                frequency,
+            );
+        }
+        // A click placing a label doesn't tune
+        let labelled = responses[0].1.clicked() && state.annotation_panel.annotating();
+        let clicked = responses
+            .iter()
+            .zip(views)
+            .flat_map(|((plot, waterfall), (_, view))| [(plot, view), (waterfall, view)])
+            .chain(then.iter().map(|then| (then, &state.waterfall)))
+            .filter(|(response, _)| response.clicked() && !labelled)
+            .find_map(|(response, waterfall)| {
+                waterfall.bin_frequency_at(response.rect, response.interact_pointer_pos()?.x)
+            });
+        if let Some(frequency) = clicked {
+            state.handle_waterfall_click(frequency);
+        }
+    }
+}
+
+/// Draw the main waterfall, with the stretch pinned from earlier, if any,
+/// beside it at the same band. Returns the live view's response and the
+/// pinned stretch's.
+fn add_main_waterfall(
+    ui: &mut eframe::egui::Ui,
+    waterfall: &mut Waterfall,
+    comparison: &ComparisonPanel,
+) -> (eframe::egui::Response, Option<eframe::egui::Response>) {
+    let Some(label) = comparison.label() else {
+        return (ui.add(waterfall), None);
+    };
+    ui.columns(2, |columns| {
+        columns[0].label(label);
+        let then = waterfall.show_pinned(&mut columns[0]);
+        columns[1].label("Now");
+        (columns[1].add(waterfall), then)
+    })
+}
+
 /// Entry point for the UI module.
 ///
-/// Runs the eframe application on the main thread (blocking).
-pub fn run(event_rx: flume::Receiver<Event>, cmd_tx: flume::Sender<Command>) -> anyhow::Result<()> {
+/// Runs the eframe application on the main thread (blocking). Spectrum
+/// frames may arrive on `spectrum_rx` or, if the engine sends them there,
+/// along with the other events on `event_rx`. With `last_session`, the app
+/// starts as it was left at the end of the last run, and keeps what it is
+/// set to for the next. With `connector`, the user can switch to an
+/// engine elsewhere on the network while it runs. Fails if no window can
+/// be opened, e.g. without a display.
+pub fn run(
+    event_rx: flume::Receiver<Event>,
+    spectrum_rx: flume::Receiver<Event>,
+    cmd_tx: flume::Sender<Command>,
+    last_session: Option<LastSessionFile>,
+    connector: Option<Connector>,
+) -> Result<(), RustIqError> {
+    let session = last_session.as_ref().and_then(LastSessionFile::load);
+    let window = session.as_ref().and_then(|session| session.window);
+    let (width, height) = window.map_or((1024.0, 768.0), |window| window.size);
+    let mut viewport = eframe::egui::ViewportBuilder::default()
+        .with_inner_size([width, height])
+        .with_title("RustIQ");
+    if let Some((x, y)) = window.and_then(|window| window.position) {
+        viewport = viewport.with_position([x, y]);
+    }
     let options = eframe::NativeOptions {
-        viewport: eframe::egui::ViewportBuilder::default()
-            .with_inner_size([1024.0, 768.0])
-            .with_title("RustIQ"),
+        viewport,
         ..Default::default()
     };
 
     eframe::run_native(
         "RustIQ",
         options,
-        Box::new(|_cc| Ok(Box::new(RustIqApp::new(event_rx, cmd_tx)))),
+        Box::new(|cc| {
+            let mut app =
+                RustIqApp::new(event_rx, spectrum_rx, cmd_tx).with_waker(cc.egui_ctx.clone());
+            if let Some(connector) = connector {
+                app = app.with_connector(connector);
+            }
+            Ok(Box::new(match last_session {
+                Some(file) => app.with_last_session(file, session),
+                None => app,
+            }))
+        }),
     )
-    .map_err(|e| anyhow::anyhow!("{}", e))?;
+    .map_err(|e| RustIqError::Device {
+        device: "Display".to_string(),
+        detail: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rustiq_messages::mock::{capabilities, initial_state, spectrum_frame};
+    use rustiq_messages::{
+        Annotation, AnnotationPoint, AnnotationShape, Antenna, AntennaSwitchConfig,
+        AntennaSwitchLink, AudioChannel, AudioChunk, AudioRouting, Averaging, CalibrationPoint,
+        Capabilities, ChannelPlan, Command, DarkFrameConfig, Decibels, DemodMode, EngineState,
+        Event, Feature, GainStage, Hertz, IqFormat, LastSession, Lockout, Passband, RdsData,
+        RecordingOverview, RustIqError, ScanChannel, ScanHit, ScanList, SelfTestReport,
+        SessionRecord, SignalClass, SignalRegion, SourceCapability, SourceConfig, SourceDevice,
+        SourceKind, Stage, StageCheck, StageGain, SymbolRateCandidate, SymbolRateEstimate, Tone,
+        Vfo, VfoConfig,
+    };
+
+    use crate::Connector;
+    use crate::connect_dialog::ConnectDialog;
+    use crate::harness::Harness;
+    use std::time::Duration;
+
+    fn snapshot() -> Event {
+        Event::StateSnapshot(Box::new(initial_state()))
+    }
+
+    #[test]
+    fn shows_waterfall_once_engine_reports_state() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.frame();
+        assert!(harness.has_text("Waiting for engine connection"));
+
+        harness.step();
+        assert!(!harness.has_text("Waiting for engine connection"));
+    }
+
+    #[test]
+    fn restores_previous_session() {
+        let session = SessionRecord {
+            source_config: SourceConfig::default(),
+            center_frequency: Hertz::mhz(145),
+            gain: Decibels(20.0),
+            recording: None,
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::PreviousSession(session.clone()))
+        });
+        harness.step_all();
+        assert!(harness.has_text("Restore previous session?"));
+
+        harness.click_text("Restore");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::RestoreSession(restored)] if *restored == session),
+            "{commands:?}"
+        );
+        assert!(!harness.has_text("Restore previous session?"));
+    }
+
+    #[test]
+    fn reports_lost_spectrum_frames() {
+        let state = initial_state();
+        let magnitudes = vec![1e-3; state.fft_size];
+        let mut harness = Harness::new(|engine| {
+            [0, 1, 3]
+                .into_iter()
+                .fold(engine.then(snapshot()), |engine, sequence| {
+                    engine.then(Event::SpectrumData(spectrum_frame(
+                        &state,
+                        sequence,
+                        None,
+                        magnitudes.clone(),
+                    )))
+                })
+        });
+        harness.step_all();
+        assert!(harness.has_text("25.0% lost"));
+    }
+
+    #[test]
+    fn offers_only_supported_features() {
+        let capabilities = Capabilities {
+            features: vec![Feature::Rig],
+            ..capabilities()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Capabilities(capabilities))
+        });
+        harness.step();
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -180.0].into());
+        assert!(harness.has_text("Antenna Switch"));
+
+        harness.step();
+        assert!(!harness.has_text("Antenna Switch"));
+        assert!(harness.has_text("Rig Control"));
+    }
+
+    #[test]
+    fn configures_device_of_hardware_source() {
+        let device = SourceDevice {
+            args: "driver=airspy,serial=42".to_string(),
+            label: "Airspy R2".to_string(),
+            antennas: vec!["RX".to_string()],
+            gain_stages: vec![GainStage {
+                name: "LNA".to_string(),
+                min: Decibels(0.0),
+                max: Decibels(14.0),
+            }],
+            bandwidth: None,
+            sample_rates: Vec::new(),
+        };
+        let capabilities = Capabilities {
+            sources: vec![
+                SourceCapability {
+                    kind: SourceKind::SignalGenerator,
+                    max_sample_rate: None,
+                    sample_rates: Vec::new(),
+                    devices: Vec::new(),
+                },
+                SourceCapability {
+                    kind: SourceKind::SoapySdr,
+                    max_sample_rate: None,
+                    sample_rates: Vec::new(),
+                    devices: vec![device],
+                },
+            ],
+            features: Vec::new(),
+            ..capabilities()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Capabilities(capabilities))
+        });
+        harness.step_all();
+
+        harness.click_text("Signal Generator");
+        harness.click_text("SoapySDR");
+        assert!(harness.has_text("Airspy R2"));
+        assert!(harness.has_text("LNA Gain:"));
+
+        harness.click_text("Apply");
+        let commands = harness.engine.commands();
+        let expected = SourceConfig::SoapySdr {
+            device: "driver=airspy,serial=42".to_string(),
+            sample_rate: Hertz(2_000_000),
+            antenna: None,
+            bandwidth: None,
+            gains: vec![StageGain {
+                stage: "LNA".to_string(),
+                gain: Decibels(7.0),
+            }],
+        };
+        assert!(
+            matches!(commands.as_slice(), [Command::ChangeSource(sent)] if *sent == expected),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn adds_tones_and_noise_to_the_signal_generator() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step_all();
+
+        harness.click_text("Add tone");
+        harness.click_text("Noise");
+        harness.click_text("Apply");
+        let commands = harness.engine.commands();
+        let expected = SourceConfig::SignalGenerator {
+            sample_rate: Hertz(48_000),
+            tones: vec![
+                Tone {
+                    frequency: Hertz(10_000),
+                    amplitude: Decibels(0.0),
+                },
+                Tone {
+                    frequency: Hertz(20_000),
+                    amplitude: Decibels(-6.0),
+                },
+            ],
+            snr: Some(Decibels(20.0)),
+            sweep: None,
+        };
+        assert!(
+            matches!(commands.as_slice(), [Command::ChangeSource(sent)] if *sent == expected),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn offers_only_the_rates_a_dongle_runs_at() {
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Capabilities(capabilities()))
+        });
+        harness.step_all();
+
+        harness.click_text("Signal Generator");
+        harness.click_text("rtl_tcp");
+        harness.click_text("2400000 Hz");
+        assert!(harness.has_text("250000 Hz"));
+        harness.click_text("2048000 Hz");
+        harness.click_text("Apply");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::ChangeSource(SourceConfig::RtlTcp { sample_rate, .. })]
+                    if *sample_rate == Hertz(2_048_000)
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn runs_self_test_and_shows_each_stage() {
+        // Without optional features, so the diagnostics fit on screen
+        let capabilities = Capabilities {
+            features: Vec::new(),
+            ..capabilities()
+        };
+        let report = SelfTestReport {
+            tone_frequency: Hertz(1_500),
+            tone_level: Decibels(-20.0),
+            tolerance: Decibels(0.5),
+            checks: vec![
+                StageCheck {
+                    stage: Stage::Source,
+                    level: Some(Decibels(-20.0)),
+                    frequency: Some(Hertz(1_500)),
+                    passed: true,
+                },
+                StageCheck {
+                    stage: Stage::Audio,
+                    level: None,
+                    frequency: None,
+                    passed: false,
+                },
+            ],
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Capabilities(capabilities))
+                .then(Event::SelfTest(report))
+        });
+        harness.step();
+        harness.step();
+
+        // Down to the diagnostics, below the other panels
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -220.0].into());
+        harness.click_text("Run self test");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::RunSelfTest]),
+            "{commands:?}"
+        );
+
+        harness.step();
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -800.0].into());
+        assert!(harness.has_text("-20.0 dBFS"));
+        assert!(harness.has_text("no signal"));
+        assert!(harness.has_text("PASS"));
+        assert!(harness.has_text("FAIL"));
+    }
+
+    #[test]
+    fn tunes_to_frequency_clicked_on_spectrum() {
+        let state = EngineState {
+            center_frequency: Hertz::mhz(100),
+            ..initial_state()
+        };
+        let frame = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        let span = state.sample_rate.as_hz() as f64;
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(state)))
+                .then(Event::SpectrumData(frame))
+        });
+        harness.step_all();
+
+        // Left of the middle of the plot above the waterfall
+        harness.click_at([200.0, 80.0].into());
+        let commands = harness.engine.commands();
+        let [Command::SetCenterFrequency(frequency)] = commands.as_slice() else {
+            panic!("{commands:?}");
+        };
+        let offset = frequency.as_hz() as f64 - 100e6;
+        assert!(offset < 0.0 && offset > -span / 2.0, "{frequency}");
+        assert!(harness.has_text("-60.0 dB"));
+        assert!(harness.has_text(" MHz"));
+    }
 
-    Ok(())
+    #[test]
+    fn zooms_and_pans_the_view_of_the_band() {
+        let state = EngineState {
+            center_frequency: Hertz::mhz(100),
+            ..initial_state()
+        };
+        let frame = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(state)))
+                .then(Event::SpectrumData(frame))
+        });
+        harness.step_all();
+        // Frequencies tuned to by clicks on the plot at two places, far
+        // enough apart in time not to make a double click
+        let tuned = |harness: &mut Harness, xs: [f32; 2]| {
+            xs.map(|x| {
+                for _ in 0..30 {
+                    harness.frame();
+                }
+                harness.click_at([x, 80.0].into());
+                let commands = harness.engine.commands();
+                let [Command::SetCenterFrequency(frequency)] = commands.as_slice() else {
+                    panic!("{commands:?}");
+                };
+                frequency.as_hz() as f64
+            })
+        };
+        let [left, right] = tuned(&mut harness, [300.0, 400.0]);
+
+        // Sixteen times as far in, around the left click
+        harness.scroll_at([300.0, 80.0].into(), [0.0, 800.0].into());
+        let [zoomed_left, zoomed_right] = tuned(&mut harness, [300.0, 400.0]);
+        assert!(
+            (zoomed_left - left).abs() < (right - left) / 16.0,
+            "{zoomed_left}"
+        );
+        let ratio = (right - left) / (zoomed_right - zoomed_left);
+        assert!((12.0..20.0).contains(&ratio), "{ratio}");
+
+        // Dragging the band right brings lower frequencies into view
+        harness.drag([400.0, 80.0].into(), [500.0, 80.0].into());
+        let [panned, _] = tuned(&mut harness, [300.0, 400.0]);
+        assert!(panned < zoomed_left, "{panned}");
+
+        // A double click zooms back out
+        harness.click_at([300.0, 80.0].into());
+        harness.click_at([300.0, 80.0].into());
+        harness.engine.commands();
+        assert_eq!(tuned(&mut harness, [300.0, 400.0]), [left, right]);
+    }
+
+    #[test]
+    fn drags_the_listening_channel_passband_along() {
+        let listening = || EngineState {
+            center_frequency: Hertz::mhz(100),
+            demodulator: Some(AudioChannel {
+                frequency: Hertz::mhz(100),
+                demod: DemodMode::Usb,
+            }),
+            ..initial_state()
+        };
+        let state = listening();
+        let frame = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        let filtered = EngineState {
+            passband: Some(Passband {
+                low: 300,
+                high: 2_400,
+            }),
+            ..listening()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(state)))
+                .then(Event::SpectrumData(frame))
+                .then(Event::StateSnapshot(Box::new(filtered)))
+        });
+        harness.step();
+        harness.step();
+        assert!(harness.has_text("150 to 2850 Hz"));
+
+        // By its width, over the middle of it
+        let width = harness.find_text("2.70 kHz").unwrap().center();
+        harness.drag(width, width + eframe::egui::vec2(20.0, 0.0));
+        let commands = harness.engine.commands();
+        let [Command::SetPassband(Some(moved))] = commands.as_slice() else {
+            panic!("{commands:?}");
+        };
+        assert_eq!(moved.width(), 2_700);
+        assert!(moved.low > 150, "{moved:?}");
+
+        harness.step();
+        assert!(harness.has_text("300 to 2400 Hz"));
+        harness.click_text("Reset");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetPassband(None)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn drags_a_vfo_marker_to_move_the_vfo() {
+        let vfo = Vfo {
+            id: 1,
+            config: VfoConfig::new(AudioChannel {
+                frequency: Hertz(100_010_000),
+                demod: DemodMode::Fm,
+            }),
+        };
+        let state = EngineState {
+            center_frequency: Hertz::mhz(100),
+            vfos: vec![vfo.clone()],
+            ..initial_state()
+        };
+        let frame = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(state)))
+                .then(Event::SpectrumData(frame))
+                .then(Event::VfoSquelch { id: 1, open: true })
+        });
+        harness.step_all();
+
+        // The marker's line is just left of its label
+        let label = harness.find_text("VFO 1 NFM").unwrap();
+        let line = label.left_center() - eframe::egui::vec2(4.0, 0.0);
+        harness.drag(line, line + eframe::egui::vec2(30.0, 0.0));
+        let commands = harness.engine.commands();
+        let [Command::ConfigureVfo { id: 1, config }] = commands.as_slice() else {
+            panic!("{commands:?}");
+        };
+        assert!(
+            config.channel.frequency > vfo.config.channel.frequency,
+            "{config:?}"
+        );
+        assert_eq!(config.channel.frequency.as_hz() % 100, 0);
+        assert_eq!(config.channel.demod, DemodMode::Fm);
+    }
+
+    #[test]
+    fn tunes_to_a_frequency_typed_with_a_decimal_comma() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+        assert!(harness.has_text("0.000000 MHz"));
+
+        // The field is right of its label
+        let label = harness.find_text("Center:").unwrap();
+        let field = label.right_center() + eframe::egui::vec2(40.0, 0.0);
+        harness.type_at(field, "145,500");
+        harness.press(eframe::egui::Key::Enter);
+        harness.type_at(field, "7.074 MHz");
+        harness.click_text("Tune");
+        harness.type_at(field, "fast");
+        assert!(harness.has_text("fast is not a frequency"));
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [
+                    Command::SetCenterFrequency(Hertz(145_500_000)),
+                    Command::SetCenterFrequency(Hertz(7_074_000)),
+                ]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn compares_spectrum_with_reference_trace() {
+        let state = initial_state();
+        let quiet = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        let loud = spectrum_frame(&state, 1, None, vec![2e-3; state.fft_size]);
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::SpectrumData(quiet))
+                .then(Event::SpectrumData(loud))
+        });
+        harness.step();
+        harness.step();
+
+        harness.click_text("Store 1");
+        assert!(harness.has_text("Clear 1"));
+
+        harness.step();
+        harness.frame_with(vec![eframe::egui::Event::PointerMoved(
+            [200.0, 80.0].into(),
+        )]);
+        assert!(harness.has_text("-54.0 dB"));
+        assert!(harness.has_text("1: -60.0 dB (Δ +6.0)"));
+
+        harness.click_text("Clear 1");
+        assert!(harness.has_text("Store 1"));
+    }
+
+    #[test]
+    fn changes_fft_size() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        harness.click_text("4096");
+        harness.click_text("16384");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetFftSize(16_384)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn draws_more_bins_than_the_gpu_takes_across() {
+        let state = EngineState {
+            fft_size: 65_536,
+            ..initial_state()
+        };
+        let frame = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(state)))
+                .then(Event::SpectrumData(frame))
+        });
+        harness.step_all();
+        // egui's limit without a backend
+        assert_eq!(harness.texture_size("waterfall"), Some([2_048, 1]));
+    }
+
+    #[test]
+    fn zooms_into_the_spectrum() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        harness.click_text("×1");
+        harness.click_text("×16");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetDecimation(16)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn shifts_the_spectrum_off_the_center() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        // The field is left of its label
+        let label = harness.find_text("Shift").unwrap();
+        harness.type_at(label.left_center() - eframe::egui::vec2(20.0, 0.0), "-2500");
+        harness.press(eframe::egui::Key::Enter);
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetFrequencyOffset(-2_500)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn changes_the_speed_a_file_plays_at() {
+        let playing = EngineState {
+            source_config: SourceConfig::File {
+                path: "pass.cf32".into(),
+                sample_rate: Hertz(48_000),
+                format: IqFormat::Cf32,
+            },
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(playing)))
+        });
+        harness.step();
+        // Live sources play as they come
+        assert!(!harness.has_text("Playback"));
+
+        harness.step();
+        harness.click_text("Unpaced");
+        harness.click_text("0.75×");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetPlaybackSpeed(Some(0.75))]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn plays_a_recording_from_where_its_overview_is_clicked() {
+        let playing = EngineState {
+            source_config: SourceConfig::File {
+                path: "pass.cf32".into(),
+                sample_rate: Hertz(48_000),
+                format: IqFormat::Cf32,
+            },
+            ..initial_state()
+        };
+        // Ten minutes, a row every three seconds
+        let overview = RecordingOverview {
+            path: "pass.cf32".into(),
+            sample_rate: Hertz(48_000),
+            duration: Duration::from_secs(600),
+            row_duration: Duration::from_secs(3),
+            rows: (0..200)
+                .map(|row| {
+                    (0..64)
+                        .map(|bin| if bin == row % 64 { 1.0 } else { 1e-6 })
+                        .collect()
+                })
+                .collect(),
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(playing)))
+                .then(Event::RecordingOverview(overview))
+        });
+        harness.step();
+        assert!(!harness.has_text("Click to play"));
+
+        harness.step();
+        assert!(harness.has_text("0:00.0 of 10:00.0"));
+        // Twenty rows down the overview
+        let hint = harness.find_text("Click to play").unwrap();
+        harness.click_at(hint.center_bottom() + eframe::egui::vec2(0.0, 30.0));
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::Seek(position)] if (45..75).contains(&position.as_secs())
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn pauses_and_scrubs_a_recording_from_its_transport_bar() {
+        let playing = |paused| EngineState {
+            source_config: SourceConfig::File {
+                path: "pass.cf32".into(),
+                sample_rate: Hertz(48_000),
+                format: IqFormat::Cf32,
+            },
+            playback_paused: paused,
+            ..initial_state()
+        };
+        let overview = RecordingOverview {
+            path: "pass.cf32".into(),
+            sample_rate: Hertz(48_000),
+            duration: Duration::from_secs(600),
+            row_duration: Duration::from_secs(3),
+            rows: vec![vec![1e-6; 64]; 200],
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(playing(false))))
+                .then(Event::RecordingOverview(overview))
+                .then(Event::PlaybackPosition(Duration::from_secs(90)))
+                .then(Event::StateSnapshot(Box::new(playing(true))))
+        });
+        harness.step();
+        harness.step();
+        harness.step();
+        assert!(harness.has_text("1:30.0 of 10:00.0"));
+
+        harness.click_text("Pause");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::Pause]),
+            "{commands:?}"
+        );
+        harness.step();
+        assert!(harness.has_text("Resume"));
+        assert!(!harness.has_text("Pause"));
+
+        // Dragged back past the start of the scrubber beside it, sought
+        // once let go
+        let resume = harness.find_text("Resume").unwrap();
+        let from = resume.right_center() + eframe::egui::vec2(40.0, 0.0);
+        harness.drag(from, resume.right_center());
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::Seek(Duration::ZERO)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn shows_engine_errors_until_dismissed() {
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Error(RustIqError::Source {
+                    kind: SourceKind::File,
+                    detail: "can't read pass.cf32: No such file or directory".into(),
+                }))
+        });
+        harness.step();
+        harness.step();
+        assert!(harness.has_text("File source: can't read pass.cf32: No such file or directory"));
+        assert!(harness.has_text("Check the path, and that the format chosen matches the file."));
+
+        harness.click_text("✖");
+        assert!(!harness.has_text("File source: can't read pass.cf32"));
+    }
+
+    #[test]
+    fn squelches_the_audio_and_shows_whether_it_is_open() {
+        let listening = || EngineState {
+            demodulator: Some(AudioChannel {
+                frequency: Hertz(145_500_000),
+                demod: DemodMode::Fm,
+            }),
+            ..initial_state()
+        };
+        let squelched = EngineState {
+            squelch: Some(Decibels(-50.0)),
+            ..listening()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(listening())))
+                .then(Event::StateSnapshot(Box::new(squelched)))
+                .then(Event::SquelchState(false))
+        });
+        harness.step();
+        assert!(!harness.has_text("Closed"));
+
+        harness.click_text("Squelch");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetSquelch(Some(Decibels(-50.0)))]
+            ),
+            "{commands:?}"
+        );
+
+        harness.step();
+        harness.step();
+        assert!(harness.has_text("Closed"));
+    }
+
+    #[test]
+    fn switches_the_mode_listened_in_from_the_mode_bar() {
+        let listening = EngineState {
+            demodulator: Some(AudioChannel {
+                frequency: Hertz(7_074_000),
+                demod: DemodMode::Usb,
+            }),
+            ..initial_state()
+        };
+        let mut harness =
+            Harness::new(|engine| engine.then(Event::StateSnapshot(Box::new(listening))));
+        harness.step();
+
+        harness.click_text("LSB");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetDemodulator(Some(AudioChannel {
+                    frequency: Hertz(7_074_000),
+                    demod: DemodMode::Lsb,
+                }))]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn scans_a_channel_plan_and_locks_out_the_active_channel() {
+        let plan = ChannelPlan::Marine;
+        let list = ScanList {
+            channels: plan
+                .channels()
+                .iter()
+                .map(|channel| {
+                    let audio = AudioChannel {
+                        frequency: channel.frequency,
+                        demod: DemodMode::Fm,
+                    };
+                    ScanChannel::new(plan.display(channel), audio)
+                })
+                .collect(),
+            priority_every: 5,
+            dwell: None,
+        };
+        let scanning = EngineState {
+            scan: Some(list.clone()),
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(scanning)))
+                .then(Event::Scanning {
+                    channel: 0,
+                    active: true,
+                })
+        });
+        harness.step();
+
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -220.0].into());
+        harness.click_text("Scan list (0)");
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -200.0].into());
+        harness.click_text("Add channels");
+        harness.click_text("Start scan");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::StartScan(sent)] if *sent == list),
+            "{commands:?}"
+        );
+
+        harness.step_all();
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -200.0].into());
+        assert!(harness.has_text("Stop scan"));
+        // The first channel's lockout
+        harness.click_text("None");
+        harness.click_text("Temporary");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetLockout {
+                    channel: 0,
+                    lockout: Lockout::Temporary
+                }]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn logs_what_the_scan_heard_where() {
+        let hit = ScanHit {
+            channel: 15,
+            name: "Marine Ch 16".to_string(),
+            frequency: Hertz(156_800_000),
+            demod: DemodMode::Fm,
+            duration: Duration::from_millis(4_250),
+            level: Decibels(-38.5),
+            class: Some(SignalClass::ConstantEnvelope),
+        };
+        let mut harness = Harness::new(|engine| engine.then(snapshot()).then(Event::ScanHit(hit)));
+        harness.step_all();
+        assert!(harness.has_text("Marine Ch 16 156.800000 MHz NFM for 4.2 s at -38.5 dB"));
+        assert!(harness.has_text("constant envelope (FM/FSK)"));
+    }
+
+    #[test]
+    fn changes_spectrum_averaging() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        harness.click_text("Off");
+        harness.click_text("Block");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetAveraging(Averaging::Block(8))]
+            ),
+            "{commands:?}"
+        );
+        assert!(harness.has_text("Frames"));
+    }
+
+    #[test]
+    fn toggles_hold_traces() {
+        let held = EngineState {
+            peak_hold: true,
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(held)))
+        });
+        harness.step();
+
+        harness.click_text("Peak hold");
+        harness.click_text("Min hold");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetPeakHold(true), Command::SetMinHold(true)]
+            ),
+            "{commands:?}"
+        );
+
+        // Once the engine keeps the peak, a click turns it off
+        harness.step();
+        harness.click_text("Peak hold");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetPeakHold(false)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn records_and_replays_a_macro() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        // The macros are near the bottom of the side panel, the FFT size at
+        // the top
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -2_000.0].into());
+        harness.click_text("Record macro");
+        harness.scroll_at([900.0, 400.0].into(), [0.0, 2_000.0].into());
+        harness.click_text("4096");
+        harness.click_text("16384");
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -2_000.0].into());
+        harness.click_text("Stop macro (1 commands)");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetFftSize(16_384)]),
+            "{commands:?}"
+        );
+        assert!(harness.has_text("Macro 1"));
+
+        harness.press(eframe::egui::Key::F1);
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetFftSize(16_384)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn runs_commands_typed_in_console() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        harness.click_text("Console");
+        // The window settles its size over a couple of frames
+        harness.frame();
+        harness.type_text("help for commands", "tune 145.5M; fft 8192");
+        harness.press(eframe::egui::Key::Enter);
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [
+                    Command::SetCenterFrequency(Hertz(145_500_000)),
+                    Command::SetFftSize(8192)
+                ]
+            ),
+            "{commands:?}"
+        );
+        assert!(harness.has_text("> tune 145.5M; fft 8192"));
+
+        harness.type_text("help for commands", "state");
+        harness.press(eframe::egui::Key::Enter);
+        assert!(harness.has_text("4096 bins"));
+    }
+
+    #[test]
+    fn toggles_offset_tuning() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+
+        harness.click_text("Advanced");
+        harness.click_text("Offset tuning");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetOffsetTuning(true)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn splits_waterfall_for_second_source() {
+        let second = SourceConfig::default();
+        let dual = EngineState {
+            second_source: Some(second.clone()),
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(dual)))
+        });
+        harness.step();
+        assert!(!harness.has_text("Main source"));
+
+        harness.click_text("Add for dual view");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetSecondSource(Some(config))] if *config == second),
+            "{commands:?}"
+        );
+
+        harness.step();
+        assert!(harness.has_text("Main source"));
+        assert!(harness.has_text("Remove"));
+    }
+
+    #[test]
+    fn listens_to_channel() {
+        let channel = AudioChannel {
+            frequency: Hertz(145_500_000),
+            demod: DemodMode::Fm,
+        };
+        let playing = EngineState {
+            demodulator: Some(channel),
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(playing)))
+                .then(Event::AudioChunk(AudioChunk {
+                    sample_rate: 24_000.0,
+                    samples: vec![0.0; 480],
+                    stereo: false,
+                }))
+        });
+        harness.step();
+
+        harness.click_text("Listen");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetDemodulator(Some(sent))] if *sent == channel),
+            "{commands:?}"
+        );
+
+        harness.step();
+        #[cfg(not(feature = "audio"))]
+        {
+            harness.step();
+            assert!(harness.has_text("No audio output: built without audio output"));
+        }
+        harness.click_text("Stop");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetDemodulator(None)]),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn keeps_up_with_audio_in_the_background() {
+        let playing = EngineState {
+            demodulator: Some(AudioChannel {
+                frequency: Hertz(145_500_000),
+                demod: DemodMode::Fm,
+            }),
+            ..initial_state()
+        };
+        let chunk = || {
+            Event::AudioChunk(AudioChunk {
+                sample_rate: 24_000.0,
+                samples: vec![0.0; 480],
+                stereo: false,
+            })
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(playing)))
+                .then(chunk())
+                .then(chunk())
+                .then(snapshot())
+        });
+        // The delay asked for once windows have settled
+        let settled_delay = |harness: &mut Harness| {
+            for _ in 0..5 {
+                harness.frame();
+            }
+            harness.repaint_delay()
+        };
+        harness.set_focused(false);
+        harness.step();
+        assert!(settled_delay(&mut harness) > Duration::from_millis(100));
+
+        // Every frame while the audio plays
+        for _ in 0..3 {
+            harness.step();
+            assert_eq!(settled_delay(&mut harness), Duration::ZERO);
+        }
+
+        harness.step();
+        assert!(settled_delay(&mut harness) > Duration::from_millis(100));
+    }
+
+    #[test]
+    fn picks_an_audio_eq_preset() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+        harness.click_text("EQ");
+        harness.frame();
+        assert!(!harness.has_text("900 Hz"));
+        harness.click_text("CW narrow");
+        harness.frame();
+        assert!(harness.has_text("900 Hz"));
+        assert!(harness.has_text("Q 2"));
+    }
+
+    #[test]
+    fn shows_whether_broadcast_fm_is_stereo() {
+        let playing = EngineState {
+            demodulator: Some(AudioChannel {
+                frequency: Hertz(98_500_000),
+                demod: DemodMode::Wfm,
+            }),
+            ..initial_state()
+        };
+        let chunk = |stereo| {
+            Event::AudioChunk(AudioChunk {
+                sample_rate: 48_000.0,
+                samples: vec![0.0; 1920],
+                stereo,
+            })
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(playing)))
+                .then(chunk(false))
+                .then(chunk(true))
+        });
+        harness.step();
+        harness.step();
+        assert!(harness.has_text("Mono"));
+        harness.step();
+        assert!(harness.has_text("Stereo"));
+    }
+
+    #[test]
+    fn shows_what_the_station_sends_over_rds() {
+        let playing = |demod: Option<DemodMode>| EngineState {
+            demodulator: demod.map(|demod| AudioChannel {
+                frequency: Hertz(98_500_000),
+                demod,
+            }),
+            ..initial_state()
+        };
+        let rds = |radiotext: Option<&str>| {
+            Event::RdsData(RdsData {
+                pi: 0xC201,
+                station: Some("RUSTIQ".to_string()),
+                radiotext: radiotext.map(str::to_string),
+            })
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(playing(Some(
+                    DemodMode::Wfm,
+                )))))
+                .then(rds(None))
+                .then(rds(Some("Now playing: tests")))
+                .then(Event::StateSnapshot(Box::new(playing(None))))
+        });
+        harness.step();
+        assert!(!harness.has_text("PI C201"));
+        harness.step();
+        assert!(harness.has_text("RUSTIQ"));
+        assert!(harness.has_text("PI C201"));
+        harness.step();
+        assert!(harness.has_text("Now playing: tests"));
+        // Gone with the station
+        harness.step();
+        assert!(!harness.has_text("RUSTIQ"));
+    }
+
+    #[test]
+    fn routes_audio_from_the_matrix() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+        harness.click_text("Routing");
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -300.0].into());
+
+        harness.click_text("Speakers");
+        harness.click_text("Stream to");
+        harness.type_text("host:port", "127.0.0.1:7355, 10.0.0.2:7355");
+        harness.click_text("Route audio");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetAudioRouting(AudioRouting { speakers: false, recording: None, network })]
+                    if network == &["127.0.0.1:7355", "10.0.0.2:7355"]
+            ),
+            "{commands:?}"
+        );
+    }
+
+    #[test]
+    fn exports_and_imports_settings() {
+        // Without optional features, so the settings fit on screen
+        let capabilities = Capabilities {
+            features: Vec::new(),
+            ..capabilities()
+        };
+        let station = EngineState {
+            center_frequency: Hertz::mhz(433),
+            rotator: Some("localhost:4533".to_string()),
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(Event::StateSnapshot(Box::new(station)))
+                .then(Event::Capabilities(capabilities))
+        });
+        harness.step_all();
+        // Down to the bottom of the side panel
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -2_000.0].into());
+        let path = std::env::temp_dir().join(format!("rustiq-station-{}", std::process::id()));
+        harness.type_text("station.rustiq", path.to_str().unwrap());
+
+        harness.click_text("Export settings");
+        assert!(harness.has_text("Exported to"));
+
+        harness.click_text("Import settings");
+        std::fs::remove_file(&path).unwrap();
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.first(), Some(Command::ChangeSource(_))),
+            "{commands:?}"
+        );
+        assert!(
+            commands.iter().any(|command| matches!(
+                command,
+                Command::ConnectRotator(address) if address == "localhost:4533"
+            )),
+            "{commands:?}"
+        );
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [
+                    ..,
+                    Command::SetCenterFrequency(Hertz(433_000_000)),
+                    Command::SetGain(_)
+                ]
+            ),
+            "{commands:?}"
+        );
+        assert!(harness.has_text("Imported"));
+    }
+
+    #[test]
+    fn shows_broadcasts_scheduled_at_the_frequency() {
+        let tuned = EngineState {
+            center_frequency: Hertz(6_005_000),
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::StateSnapshot(Box::new(tuned)))
+        });
+        harness.step();
+        let path = std::env::temp_dir().join(format!("rustiq-sked-{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "kHz:75;Time(UTC):93;Days:59;ITU:49;Station:201;Lng:49;Target:62;Remarks:135\n\
+             6005;0000-2400;;D;Radio Seefunk;G;D;\n\
+             9420;0000-2400;;GRC;Voice of Greece;Gr;Eu;\n",
+        )
+        .unwrap();
+        harness.click_text("Broadcast schedule");
+        harness.type_text("sked.csv", path.to_str().unwrap());
+        harness.click_text("Load schedule");
+        std::fs::remove_file(&path).unwrap();
+        assert!(harness.has_text("2 broadcasts"));
+
+        // At the frequency tuned to, and not the other
+        harness.step();
+        assert!(harness.has_text("Radio Seefunk (G) → D"));
+        assert!(!harness.has_text("Voice of Greece"));
+    }
+
+    #[test]
+    fn loads_calibration_table() {
+        let capabilities = Capabilities {
+            features: Vec::new(),
+            ..capabilities()
+        };
+        let points = [(-24_000.0, -3.0), (0.0, 0.0), (24_000.0, -2.0)].map(|(offset, gain)| {
+            CalibrationPoint {
+                offset,
+                gain: Decibels(gain),
+            }
+        });
+        let calibrated = EngineState {
+            calibration: points.to_vec(),
+            ..initial_state()
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Capabilities(capabilities))
+                .then(Event::StateSnapshot(Box::new(calibrated)))
+        });
+        harness.step();
+        harness.step();
+        // Down to the bottom of the side panel
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -2_000.0].into());
+        assert!(harness.has_text("Spectrum uncorrected"));
+        let path =
+            std::env::temp_dir().join(format!("rustiq-calibration-{}.csv", std::process::id()));
+        std::fs::write(&path, "offset_hz,level_db\n-24000,-53\n0,-50\n24000,-52\n").unwrap();
+        harness.type_text("calibration.csv", path.to_str().unwrap());
+
+        harness.click_text("Load");
+        std::fs::remove_file(&path).unwrap();
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::SetCalibration(loaded)] if *loaded == points),
+            "{commands:?}"
+        );
+
+        harness.step();
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -2_000.0].into());
+        assert!(harness.has_text("Corrected for -3.0 to +0.0 dB, 3 points"));
+    }
+
+    #[test]
+    fn estimates_symbol_rate_of_region() {
+        let capabilities = Capabilities {
+            features: vec![Feature::SymbolRateEstimation],
+            ..capabilities()
+        };
+        let region = SignalRegion {
+            frequency: Hertz(10_000),
+            bandwidth: Hertz(2_400),
+        };
+        let estimate = SymbolRateEstimate {
+            region,
+            candidates: vec![SymbolRateCandidate {
+                rate: 1_200.0,
+                strength: Decibels(32.5),
+            }],
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(snapshot())
+                .then(Event::Capabilities(capabilities))
+                .then(Event::SymbolRate(estimate))
+        });
+        harness.step();
+        harness.step();
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -280.0].into());
+
+        harness.click_text("Estimate");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(commands.as_slice(), [Command::EstimateSymbolRate(sent)] if *sent == region),
+            "{commands:?}"
+        );
+
+        harness.step();
+        // The estimate shows further down the side panel
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -300.0].into());
+        assert!(harness.has_text("1200.0 Bd"));
+        assert!(harness.has_text("32.5 dB"));
+    }
+
+    #[test]
+    fn leaves_the_dc_spike_out_of_the_level_range() {
+        let state = initial_state();
+        let bins = state.fft_size;
+        let mut magnitudes = vec![2e-3; bins];
+        magnitudes[bins / 2] = 1.0;
+        let frames: Vec<_> = (0..2)
+            .map(|sequence| spectrum_frame(&state, sequence, None, magnitudes.clone()))
+            .collect();
+        let mut harness = Harness::new(|engine| {
+            frames
+                .into_iter()
+                .fold(engine.then(snapshot()), |engine, frame| {
+                    engine.then(Event::SpectrumData(frame))
+                })
+        });
+        harness.step();
+        harness.step();
+        // The spike stretches the plot's levels up to 0 dB
+        assert!(harness.has_text("-30 dB"));
+
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -5000.0].into());
+        harness.click_text("Exclude DC spike");
+        harness.step();
+        assert!(harness.has_text("-50 dB"));
+        assert!(!harness.has_text("-30 dB"));
+    }
+
+    #[test]
+    fn keeps_annotations_with_the_session() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+        let point = AnnotationPoint {
+            frequency: Hertz::mhz(145),
+            time: Duration::ZERO,
+        };
+        let annotation = Annotation {
+            shape: AnnotationShape::Arrow,
+            start: point,
+            end: point,
+            text: "pilot".to_string(),
+        };
+        let session = harness.app.state.last_session(None).unwrap();
+        harness.app.state.restore_view(&LastSession {
+            annotations: vec![annotation.clone()],
+            ..session
+        });
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -5000.0].into());
+        assert!(harness.has_text("Arrow at 145000000 Hz: pilot"));
+        let saved = harness.app.state.last_session(None).unwrap();
+        assert_eq!(saved.annotations, [annotation]);
+
+        harness.click_text("Clear annotations");
+        harness.frame();
+        assert!(!harness.has_text("pilot"));
+        let saved = harness.app.state.last_session(None).unwrap();
+        assert!(saved.annotations.is_empty());
+    }
+
+    #[test]
+    fn colors_the_waterfall_with_a_map_from_a_file() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.step();
+        let path = std::env::temp_dir().join(format!("rustiq-dusk-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r##"{"name": "Dusk", "stops": ["#000040", "#ff8000"]}"##,
+        )
+        .unwrap();
+        harness.type_text("palette.cpt", path.to_str().unwrap());
+
+        harness.click_text("Add map");
+        std::fs::remove_file(&path).unwrap();
+        harness.frame();
+        // Listed with a preview, and picked right away
+        assert!(harness.has_text("Dusk"));
+        let colormap = harness.app.state.waterfall.colormap();
+        assert_eq!(colormap.label(), "Dusk");
+        assert_eq!(
+            colormap.color(1.0),
+            eframe::egui::Color32::from_rgb(255, 128, 0)
+        );
+        let saved = harness.app.state.last_session(None).unwrap();
+        assert_eq!(saved.colormap, "Dusk");
+    }
+
+    #[test]
+    fn pins_a_stretch_beside_the_live_waterfall() {
+        let state = initial_state();
+        let magnitudes = vec![1e-3; state.fft_size];
+        let mut harness = Harness::new(|engine| {
+            (0..3).fold(engine.then(snapshot()), |engine, sequence| {
+                engine.then(Event::SpectrumData(spectrum_frame(
+                    &state,
+                    sequence,
+                    None,
+                    magnitudes.clone(),
+                )))
+            })
+        });
+        harness.step_all();
+        assert!(!harness.has_text("Then: "));
+
+        // An hour back is before the record started, so the first line
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -5000.0].into());
+        harness.click_text("Pin");
+        harness.frame();
+        assert!(harness.has_text("Then: 60 min ago"));
+        assert!(harness.has_text("Now"));
+        assert_eq!(
+            harness.app.state.comparison_panel.pinned(),
+            Some(Duration::ZERO..=Duration::ZERO)
+        );
+
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -5000.0].into());
+        harness.click_text("Unpin");
+        harness.frame();
+        assert!(!harness.has_text("Then: "));
+    }
+
+    #[test]
+    fn captures_dark_frames_on_the_termination() {
+        let capabilities = Capabilities {
+            features: vec![Feature::AntennaSwitch],
+            ..capabilities()
+        };
+        let switch = AntennaSwitchConfig {
+            link: AntennaSwitchLink::Network {
+                address: "127.0.0.1:4000".to_string(),
+            },
+            antennas: ["Dummy load", "Discone"]
+                .into_iter()
+                .map(|name| Antenna {
+                    name: name.to_string(),
+                    command: String::new(),
+                })
+                .collect(),
+            rules: Vec::new(),
+        };
+        let dark_frame = DarkFrameConfig {
+            termination: 0,
+            interval: Duration::from_secs(3_600),
+            frames: 64,
+        };
+        let state = |dark_frame| {
+            Event::StateSnapshot(Box::new(EngineState {
+                antenna_switch: Some(switch.clone()),
+                antenna: Some(1),
+                dark_frame,
+                ..initial_state()
+            }))
+        };
+        let mut harness = Harness::new(|engine| {
+            engine
+                .then(state(None))
+                .then(Event::Capabilities(capabilities))
+                .then(state(Some(dark_frame)))
+                .then(Event::DarkFrameCaptured(Decibels(-97.5)))
+        });
+        harness.step();
+        harness.step();
+        // Down to the antenna switch, below the other panels
+        harness.scroll_at([900.0, 400.0].into(), [0.0, -800.0].into());
+        harness.click_text("Capture dark frames");
+        let commands = harness.engine.commands();
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [Command::SetDarkFrame(Some(config))]
+                    if *config == dark_frame
+            ),
+            "{commands:?}"
+        );
+
+        harness.step();
+        assert!(harness.has_text("Waiting for the first dark frame"));
+        harness.step();
+        assert!(harness.has_text("Last dark frame: -97.5 dB"));
+    }
+
+    #[test]
+    fn switches_to_an_engine_connected_to() {
+        let (event_tx, event_rx) = flume::unbounded();
+        let (_spectrum_tx, spectrum_rx) = flume::unbounded();
+        let (cmd_tx, cmd_rx) = flume::unbounded();
+        let remote = std::sync::Mutex::new(Some((event_rx, spectrum_rx, cmd_tx)));
+        let connector: Connector = std::sync::Arc::new(move |address, token| {
+            assert_eq!(address, "receiver.local:7355");
+            assert_eq!(token, Some("secret"));
+            remote
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| anyhow::anyhow!("already connected"))
+        });
+        let mut harness = Harness::new(|engine| engine.then(snapshot()));
+        harness.app.connect_dialog = ConnectDialog::new(Some(connector));
+        harness.step();
+
+        harness.click_text("Switch engine");
+        harness.type_text("HOST:PORT or ws://HOST:PORT", "receiver.local:7355");
+        harness.type_text("If the engine asks for one", "secret");
+        harness.click_text("Connect");
+        for _ in 0..100 {
+            if !harness.has_text("Switch Engine") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            harness.frame();
+        }
+        harness.frame();
+        assert!(harness.has_text("Waiting for engine connection"));
+
+        let state = EngineState {
+            center_frequency: Hertz::mhz(435),
+            ..initial_state()
+        };
+        event_tx
+            .send(Event::StateSnapshot(Box::new(state)))
+            .unwrap();
+        harness.frame();
+        let engine_state = harness.app.state.engine_state.as_ref().unwrap();
+        assert_eq!(engine_state.center_frequency, Hertz::mhz(435));
+
+        // Commands go to the new engine, not the first
+        harness.app.state.handle_waterfall_click(435_100_000.0);
+        harness.frame();
+        assert!(
+            cmd_rx
+                .try_iter()
+                .any(|command| matches!(command, Command::SetCenterFrequency(_)))
+        );
+        assert!(harness.engine.commands().is_empty());
+    }
+
+    #[test]
+    fn keeps_last_state_when_engine_disconnects() {
+        let mut harness = Harness::new(|engine| engine.then(snapshot()).then_disconnect());
+        harness.step_all();
+        harness.frame();
+        assert!(!harness.has_text("Waiting for engine connection"));
+        assert!(harness.engine.commands().is_empty());
+    }
 }
@@ -0,0 +1,330 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use eframe::egui::{Color32, ComboBox, Context, Key, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+use log::{info, warn};
+
+use rustiq_messages::{Command, CommandMacro, read_frame, write_frame};
+
+/// Start of a macros file, ahead of the macros in wire format. The version
+/// changes whenever their layout, or that of a command, does.
+const MAGIC: &[u8; 8] = b"RIQMAC01";
+
+/// Keys macros can be bound to, F1 to F12.
+const FUNCTION_KEYS: [Key; 12] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+/// Records what the user does, as the commands the panels give, into named
+/// macros that replay it to the engine from a button or a function key.
+pub struct MacroPanel {
+    cmd_tx: Sender<Command>,
+    macros: Vec<CommandMacro>,
+    /// Commands of the macro being recorded, if one is
+    recording: Option<Vec<Command>>,
+    name: String,
+    path: String,
+    /// Outcome of the last save or load: what was done, or why it failed
+    status: Option<Result<String, String>>,
+}
+
+impl MacroPanel {
+    /// A panel replaying macros on `cmd_tx`, straight to the engine, so
+    /// playing one isn't itself recorded.
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            macros: Vec::new(),
+            recording: None,
+            name: String::new(),
+            path: "macros.rustiq".to_string(),
+            status: None,
+        }
+    }
+
+    /// Replay macros on `cmd_tx` from now on, for an engine just switched to.
+    pub fn set_engine(&mut self, cmd_tx: Sender<Command>) {
+        self.cmd_tx = cmd_tx;
+    }
+
+    /// Note a command on its way to the engine.
+    pub fn record(&mut self, command: &Command) {
+        // Not something the user did
+        if matches!(command, Command::Stop | Command::Resync) {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.push(command.clone());
+        }
+    }
+
+    /// Play the macros bound to the function keys pressed.
+    pub fn poll_hotkeys(&mut self, ctx: &Context) {
+        for (number, key) in (1..).zip(FUNCTION_KEYS) {
+            if !ctx.input(|input| input.key_pressed(key)) {
+                continue;
+            }
+            if let Some(index) = self
+                .macros
+                .iter()
+                .position(|recorded| recorded.hotkey == Some(number))
+            {
+                self.play(index);
+            }
+        }
+    }
+
+    fn play(&mut self, index: usize) {
+        let recorded = &self.macros[index];
+        info!(
+            "Playing macro {} ({} commands)",
+            recorded.name,
+            recorded.commands.len()
+        );
+        for command in &recorded.commands {
+            let _ = self.cmd_tx.send(command.clone());
+        }
+    }
+
+    /// Keep the recording as a macro, on the first function key free.
+    fn finish_recording(&mut self) {
+        let Some(commands) = self.recording.take() else {
+            return;
+        };
+        if commands.is_empty() {
+            return;
+        }
+        let name = match self.name.trim() {
+            "" => format!("Macro {}", self.macros.len() + 1),
+            name => name.to_string(),
+        };
+        let hotkey = (1..=FUNCTION_KEYS.len() as u8)
+            .find(|number| self.macros.iter().all(|m| m.hotkey != Some(*number)));
+        self.macros.push(CommandMacro {
+            name,
+            hotkey,
+            commands,
+        });
+        self.name.clear();
+    }
+
+    fn save(&mut self) {
+        self.status = Some(match save(Path::new(&self.path), &self.macros) {
+            Ok(()) => {
+                info!("Saved macros to {}", self.path);
+                Ok(format!("Saved to {}", self.path))
+            }
+            Err(e) => {
+                warn!("Failed to save macros to {}: {}", self.path, e);
+                Err(format!("Save failed: {e}"))
+            }
+        });
+    }
+
+    fn load(&mut self) {
+        self.status = Some(match load(Path::new(&self.path)) {
+            Ok(macros) => {
+                info!("Loaded {} macros from {}", macros.len(), self.path);
+                self.macros = macros;
+                Ok(format!("Loaded {}", self.path))
+            }
+            Err(e) => {
+                warn!("Failed to load macros from {}: {}", self.path, e);
+                Err(format!("Load failed: {e}"))
+            }
+        });
+    }
+}
+
+fn save(path: &Path, macros: &Vec<CommandMacro>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    write_frame(&mut writer, macros)
+}
+
+fn load(path: &Path) -> io::Result<Vec<CommandMacro>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a RustIQ macros file, or from another version",
+        ));
+    }
+    read_frame(&mut reader)
+}
+
+fn hotkey_label(hotkey: Option<u8>) -> String {
+    match hotkey {
+        Some(number) => format!("F{number}"),
+        None => "No key".to_string(),
+    }
+}
+
+impl Widget for &mut MacroPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Macros");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.add(TextEdit::singleline(&mut self.name).desired_width(150.0));
+        });
+        match &self.recording {
+            None => {
+                if ui
+                    .button("Record macro")
+                    .on_hover_text("Record what you do from here on, to play it again later")
+                    .clicked()
+                {
+                    self.recording = Some(Vec::new());
+                }
+            }
+            Some(commands) => {
+                let label = format!("Stop macro ({} commands)", commands.len());
+                if ui.button(label).clicked() {
+                    self.finish_recording();
+                }
+            }
+        }
+
+        let mut play = None;
+        let mut delete = None;
+        for (index, recorded) in self.macros.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Play")
+                    .on_hover_text(format!("{} commands", recorded.commands.len()))
+                    .clicked()
+                {
+                    play = Some(index);
+                }
+                ComboBox::from_id_salt(("macro_hotkey", index))
+                    .width(60.0)
+                    .selected_text(hotkey_label(recorded.hotkey))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut recorded.hotkey, None, hotkey_label(None));
+                        for number in 1..=FUNCTION_KEYS.len() as u8 {
+                            ui.selectable_value(
+                                &mut recorded.hotkey,
+                                Some(number),
+                                hotkey_label(Some(number)),
+                            );
+                        }
+                    });
+                ui.label(&recorded.name);
+                if ui.small_button("✖").on_hover_text("Delete").clicked() {
+                    delete = Some(index);
+                }
+            });
+        }
+        if let Some(index) = play {
+            self.play(index);
+        }
+        if let Some(index) = delete {
+            self.macros.remove(index);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.add(TextEdit::singleline(&mut self.path).desired_width(150.0));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Export macros").clicked() {
+                self.save();
+            }
+            if ui
+                .button("Import macros")
+                .on_hover_text("Replace the macros with the file's")
+                .clicked()
+            {
+                self.load();
+            }
+        });
+
+        match &self.status {
+            Some(Ok(done)) => {
+                ui.label(done);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(Color32::LIGHT_RED, e);
+            }
+            None => {}
+        }
+
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustiq_messages::Hertz;
+
+    #[test]
+    fn records_user_commands_onto_free_keys() {
+        let (cmd_tx, cmd_rx) = flume::unbounded();
+        let mut panel = MacroPanel::new(cmd_tx);
+        panel.macros.push(CommandMacro {
+            name: "Loaded".to_string(),
+            hotkey: Some(1),
+            commands: Vec::new(),
+        });
+        panel.record(&Command::SetCenterFrequency(Hertz::mhz(7)));
+
+        panel.recording = Some(Vec::new());
+        panel.record(&Command::SetCenterFrequency(Hertz::mhz(145)));
+        panel.record(&Command::Resync);
+        panel.record(&Command::SetFftSize(8_192));
+        panel.finish_recording();
+
+        let recorded = &panel.macros[1];
+        assert_eq!(recorded.name, "Macro 2");
+        assert_eq!(recorded.hotkey, Some(2));
+        assert!(
+            matches!(
+                recorded.commands.as_slice(),
+                [
+                    Command::SetCenterFrequency(Hertz(145_000_000)),
+                    Command::SetFftSize(8_192)
+                ]
+            ),
+            "{:?}",
+            recorded.commands
+        );
+
+        panel.play(1);
+        assert_eq!(cmd_rx.len(), 2);
+    }
+
+    #[test]
+    fn saves_and_loads_macros() {
+        let path = std::env::temp_dir().join(format!("rustiq-macros-{}", std::process::id()));
+        let macros = vec![CommandMacro {
+            name: "Check 2m".to_string(),
+            hotkey: Some(3),
+            commands: vec![Command::SetCenterFrequency(Hertz::mhz(145))],
+        }];
+        save(&path, &macros).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, "Check 2m");
+        assert_eq!(loaded[0].hotkey, Some(3));
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use eframe::egui::{
+    self, Align2, Checkbox, Color32, ColorImage, DragValue, FontId, Painter, Pos2, Rect, Response,
+    Sense, Shape, Stroke, TextEdit, TextureHandle, TextureOptions, Ui, Vec2, Widget,
+};
+use flume::Sender;
+use log::warn;
+
+use crate::geo::{self, MAX_LATITUDE};
+use rustiq_messages::{Command, GeoPosition, Hertz, TrackKind, TrackReport};
+
+/// Size of a map tile in pixels.
+const TILE_SIZE: f64 = 256.0;
+
+/// Deepest zoom level tiles are loaded for.
+const MAX_ZOOM: u8 = 18;
+
+/// Tiles kept as textures before the cache is flushed.
+const MAX_CACHED_TILES: usize = 512;
+
+/// Positions kept in each track's trail.
+const MAX_TRAIL: usize = 500;
+
+/// Zoom level the view jumps to when the first position arrives.
+const INITIAL_ZOOM: f64 = 8.0;
+
+/// Tracks not heard from for this long are dropped.
+fn track_timeout(kind: TrackKind) -> Duration {
+    match kind {
+        TrackKind::Vessel => Duration::from_secs(15 * 60),
+        TrackKind::Aircraft => Duration::from_secs(2 * 60),
+    }
+}
+
+fn track_color(kind: TrackKind) -> Color32 {
+    match kind {
+        TrackKind::Vessel => Color32::from_rgb(80, 200, 255),
+        TrackKind::Aircraft => Color32::from_rgb(255, 200, 60),
+    }
+}
+
+/// Everything known about one ship or aircraft, merged from its reports.
+struct Track {
+    kind: TrackKind,
+    id: String,
+    name: Option<String>,
+    altitude: Option<f32>,
+    course: Option<f32>,
+    speed: Option<f32>,
+    /// Positions received, oldest first
+    trail: Vec<GeoPosition>,
+    last_seen: Instant,
+}
+
+impl Track {
+    fn label(&self) -> String {
+        let name = self.name.as_deref().unwrap_or(&self.id);
+        match self.altitude {
+            Some(altitude) => format!("{name} {altitude:.0} ft"),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// AIS/ADS-B decoder controls and the map of decoded targets.
+///
+/// The widget (`ui.add(&mut panel)`) renders the decoder and tile settings;
+/// `show_map()` renders the map itself. Map tiles are read from a local
+/// directory in the usual slippy map layout (`{zoom}/{x}/{y}.png`), so the map
+/// works offline from any pre-downloaded tile cache. Without tiles the map
+/// still shows a latitude/longitude grid.
+pub struct MapPanel {
+    cmd_tx: Sender<Command>,
+    /// AIS channel entered in the controls
+    ais_frequency: Hertz,
+    /// AIS channel the engine is currently decoding
+    ais_active: Option<Hertz>,
+    adsb_active: bool,
+    tracks: HashMap<(TrackKind, String), Track>,
+    map_open: bool,
+    tile_dir: PathBuf,
+    /// Loaded tiles by (zoom, x, y); `None` for tiles missing from `tile_dir`
+    tiles: HashMap<(u8, u32, u32), Option<TextureHandle>>,
+    /// World coordinates (see `geo::to_world`) of the center of the view
+    center: [f64; 2],
+    /// Continuous zoom level: the world is 256 * 2^zoom pixels wide
+    zoom: f64,
+    /// Whether the view has been moved to the first position received
+    centered: bool,
+}
+
+impl MapPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            ais_frequency: Hertz(161_975_000),
+            ais_active: None,
+            adsb_active: false,
+            tracks: HashMap::new(),
+            map_open: false,
+            tile_dir: PathBuf::from("tiles"),
+            tiles: HashMap::new(),
+            center: [0.5, 0.5],
+            zoom: 2.0,
+            centered: false,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, ais_decoder: Option<Hertz>, adsb_decoder: bool) {
+        self.ais_active = ais_decoder;
+        if let Some(frequency) = ais_decoder {
+            self.ais_frequency = frequency;
+        }
+        self.adsb_active = adsb_decoder;
+    }
+
+    /// Merge a report into its track, returning whether the track is new.
+    pub fn insert_report(&mut self, report: TrackReport) -> bool {
+        let key = (report.kind, report.id.clone());
+        let is_new = !self.tracks.contains_key(&key);
+        let track = self.tracks.entry(key).or_insert_with(|| Track {
+            kind: report.kind,
+            id: report.id,
+            name: None,
+            altitude: None,
+            course: None,
+            speed: None,
+            trail: Vec::new(),
+            last_seen: Instant::now(),
+        });
+        track.last_seen = Instant::now();
+        track.name = report.name.or(track.name.take());
+        track.altitude = report.altitude.or(track.altitude);
+        track.course = report.course.or(track.course);
+        track.speed = report.speed.or(track.speed);
+        if let Some(position) = report.position {
+            if track.trail.len() == MAX_TRAIL {
+                track.trail.remove(0);
+            }
+            track.trail.push(position);
+            if !self.centered {
+                self.center = geo::to_world(position);
+                self.zoom = INITIAL_ZOOM;
+                self.centered = true;
+                self.map_open = true;
+            }
+        }
+        is_new
+    }
+
+    pub fn map_open(&self) -> bool {
+        self.map_open
+    }
+
+    pub fn set_map_open(&mut self, open: bool) {
+        self.map_open = open;
+    }
+
+    /// Pixels per unit of world coordinates at the current zoom.
+    fn scale(&self) -> f64 {
+        TILE_SIZE * self.zoom.exp2()
+    }
+
+    fn world_to_screen(&self, rect: Rect, [x, y]: [f64; 2]) -> Pos2 {
+        let scale = self.scale();
+        rect.center()
+            + Vec2::new(
+                ((x - self.center[0]) * scale) as f32,
+                ((y - self.center[1]) * scale) as f32,
+            )
+    }
+
+    fn screen_to_world(&self, rect: Rect, pos: Pos2) -> [f64; 2] {
+        let scale = self.scale();
+        let offset = pos - rect.center();
+        [
+            self.center[0] + offset.x as f64 / scale,
+            self.center[1] + offset.y as f64 / scale,
+        ]
+    }
+
+    /// Render the map: tiles, grid and tracks. Drag to pan, scroll to zoom.
+    pub fn show_map(&mut self, ui: &mut Ui) {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
+        let rect = response.rect;
+
+        if response.dragged() {
+            let delta = response.drag_delta();
+            let scale = self.scale();
+            self.center[0] -= delta.x as f64 / scale;
+            self.center[1] -= delta.y as f64 / scale;
+        }
+        if let Some(pointer) = response.hover_pos() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 {
+                // Keep the point under the pointer fixed while zooming
+                let anchor = self.screen_to_world(rect, pointer);
+                self.zoom = (self.zoom + scroll as f64 / 200.0).clamp(1.0, MAX_ZOOM as f64);
+                let moved = self.screen_to_world(rect, pointer);
+                self.center[0] += anchor[0] - moved[0];
+                self.center[1] += anchor[1] - moved[1];
+            }
+        }
+        self.center = self.center.map(|v| v.clamp(0.0, 1.0));
+
+        painter.rect_filled(rect, 0.0, Color32::from_rgb(18, 26, 36));
+        self.draw_tiles(ui.ctx(), &painter, rect);
+        self.draw_grid(&painter, rect);
+        self.draw_tracks(&painter, rect);
+    }
+
+    fn draw_tiles(&mut self, ctx: &egui::Context, painter: &Painter, rect: Rect) {
+        let zoom = (self.zoom.round() as u8).min(MAX_ZOOM);
+        let (first_x, first_y) = geo::tile_at(self.screen_to_world(rect, rect.min), zoom);
+        let (last_x, last_y) = geo::tile_at(self.screen_to_world(rect, rect.max), zoom);
+        if self.tiles.len() > MAX_CACHED_TILES {
+            self.tiles.clear();
+        }
+
+        let span = 1.0 / (1u32 << zoom) as f64;
+        let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+        for x in first_x..=last_x {
+            for y in first_y..=last_y {
+                let min = [x as f64 * span, y as f64 * span];
+                let max = [min[0] + span, min[1] + span];
+                let screen = Rect::from_min_max(
+                    self.world_to_screen(rect, min),
+                    self.world_to_screen(rect, max),
+                );
+                let tile = self
+                    .tiles
+                    .entry((zoom, x, y))
+                    .or_insert_with(|| load_tile(ctx, &self.tile_dir, zoom, x, y));
+                let Some(texture) = tile else {
+                    continue;
+                };
+                painter.image(texture.id(), screen, uv, Color32::WHITE);
+            }
+        }
+    }
+
+    /// Draw latitude and longitude lines a sensible number of degrees apart.
+    fn draw_grid(&self, painter: &Painter, rect: Rect) {
+        let pixels_per_degree = self.scale() / 360.0;
+        let step = [0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0]
+            .into_iter()
+            .find(|step| step * pixels_per_degree >= 80.0)
+            .unwrap_or(60.0);
+        let stroke = Stroke::new(1.0, Color32::from_white_alpha(40));
+        let text_color = Color32::from_white_alpha(120);
+        let font = FontId::proportional(10.0);
+
+        let north_west = geo::from_world(self.screen_to_world(rect, rect.min));
+        let south_east = geo::from_world(self.screen_to_world(rect, rect.max));
+
+        let first = (north_west.longitude / step).ceil() as i64;
+        let last = (south_east.longitude / step).floor() as i64;
+        for i in first..=last {
+            let longitude = i as f64 * step;
+            let x = self
+                .world_to_screen(
+                    rect,
+                    geo::to_world(GeoPosition {
+                        latitude: 0.0,
+                        longitude,
+                    }),
+                )
+                .x;
+            painter.line_segment(
+                [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                stroke,
+            );
+            painter.text(
+                Pos2::new(x + 2.0, rect.bottom() - 2.0),
+                Align2::LEFT_BOTTOM,
+                format!("{longitude}°"),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        let first = (south_east.latitude.max(-MAX_LATITUDE) / step).ceil() as i64;
+        let last = (north_west.latitude.min(MAX_LATITUDE) / step).floor() as i64;
+        for i in first..=last {
+            let latitude = i as f64 * step;
+            let y = self
+                .world_to_screen(
+                    rect,
+                    geo::to_world(GeoPosition {
+                        latitude,
+                        longitude: 0.0,
+                    }),
+                )
+                .y;
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                stroke,
+            );
+            painter.text(
+                Pos2::new(rect.left() + 2.0, y - 2.0),
+                Align2::LEFT_BOTTOM,
+                format!("{latitude}°"),
+                font.clone(),
+                text_color,
+            );
+        }
+    }
+
+    fn draw_tracks(&self, painter: &Painter, rect: Rect) {
+        for track in self.tracks.values() {
+            let Some(&position) = track.trail.last() else {
+                continue;
+            };
+            let color = track_color(track.kind);
+            let points: Vec<Pos2> = track
+                .trail
+                .iter()
+                .map(|&p| self.world_to_screen(rect, geo::to_world(p)))
+                .collect();
+            if points.len() > 1 {
+                painter.add(Shape::line(
+                    points,
+                    Stroke::new(1.5, color.gamma_multiply(0.6)),
+                ));
+            }
+
+            let at = self.world_to_screen(rect, geo::to_world(position));
+            if !rect.expand(50.0).contains(at) {
+                continue;
+            }
+            match (track.kind, track.course) {
+                (TrackKind::Aircraft, Some(course)) => {
+                    // Arrowhead pointing along the course
+                    let direction = Vec2::angled((course - 90.0).to_radians());
+                    let side = direction.rot90() * 4.0;
+                    let tip = at + direction * 8.0;
+                    let tail = at - direction * 5.0;
+                    painter.add(Shape::convex_polygon(
+                        vec![tip, tail + side, tail - side],
+                        color,
+                        Stroke::NONE,
+                    ));
+                }
+                _ => {
+                    painter.circle_filled(at, 4.0, color);
+                }
+            }
+            painter.text(
+                at + Vec2::new(8.0, -8.0),
+                Align2::LEFT_BOTTOM,
+                track.label(),
+                FontId::proportional(11.0),
+                color,
+            );
+        }
+    }
+
+    fn remove_stale_tracks(&mut self) {
+        self.tracks
+            .retain(|_, track| track.last_seen.elapsed() < track_timeout(track.kind));
+    }
+
+    fn send_start_ais(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::StartAisDecoder(self.ais_frequency));
+    }
+
+    fn send_stop_ais(&self) {
+        let _ = self.cmd_tx.send(Command::StopAisDecoder);
+    }
+
+    fn send_start_adsb(&self) {
+        let _ = self.cmd_tx.send(Command::StartAdsbDecoder);
+    }
+
+    fn send_stop_adsb(&self) {
+        let _ = self.cmd_tx.send(Command::StopAdsbDecoder);
+    }
+}
+
+impl Widget for &mut MapPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        self.remove_stale_tracks();
+
+        ui.heading("AIS / ADS-B Map");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("AIS:");
+            let mut freq = self.ais_frequency.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(1_000).suffix(" Hz"))
+                .changed()
+            {
+                self.ais_frequency.0 = freq;
+            }
+        });
+        ui.horizontal(|ui| {
+            let retune = self
+                .ais_active
+                .is_some_and(|active| active != self.ais_frequency);
+            let label = if retune { "Retune" } else { "Start" };
+            ui.add_enabled_ui(self.ais_active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start_ais();
+                }
+            });
+            ui.add_enabled_ui(self.ais_active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop_ais();
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("ADS-B:");
+            ui.add_enabled_ui(!self.adsb_active, |ui| {
+                if ui.button("Start").clicked() {
+                    self.send_start_adsb();
+                }
+            });
+            ui.add_enabled_ui(self.adsb_active, |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop_adsb();
+                }
+            });
+        });
+
+        ui.add_space(5.0);
+        let count = |kind| self.tracks.values().filter(|t| t.kind == kind).count();
+        ui.label(format!(
+            "{} vessels, {} aircraft",
+            count(TrackKind::Vessel),
+            count(TrackKind::Aircraft)
+        ));
+
+        ui.horizontal(|ui| {
+            ui.label("Tiles:");
+            let mut dir = self.tile_dir.to_string_lossy().to_string();
+            if ui
+                .add(TextEdit::singleline(&mut dir).desired_width(120.0))
+                .changed()
+            {
+                self.tile_dir = PathBuf::from(dir);
+                self.tiles.clear();
+            }
+        });
+        ui.add(Checkbox::new(&mut self.map_open, "Show map"));
+
+        ui.response()
+    }
+}
+
+/// Load a tile from the tile directory, or `None` if it isn't there.
+fn load_tile(ctx: &egui::Context, dir: &Path, zoom: u8, x: u32, y: u32) -> Option<TextureHandle> {
+    let path = dir.join(format!("{zoom}/{x}/{y}.png"));
+    if !path.exists() {
+        return None;
+    }
+    match read_png(&path) {
+        Ok(image) => Some(ctx.load_texture(
+            format!("tile_{zoom}_{x}_{y}"),
+            image,
+            TextureOptions::LINEAR,
+        )),
+        Err(e) => {
+            warn!("Failed to load map tile {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn read_png(path: &Path) -> anyhow::Result<ColorImage> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(
+        png::Transformations::normalize_to_color8() | png::Transformations::ALPHA,
+    );
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size().context("Image too large")?];
+    let info = reader.next_frame(&mut buffer)?;
+    let pixels = &buffer[..info.buffer_size()];
+    let size = [info.width as usize, info.height as usize];
+    match info.color_type {
+        png::ColorType::Rgba => Ok(ColorImage::from_rgba_unmultiplied(size, pixels)),
+        png::ColorType::GrayscaleAlpha => {
+            let rgba: Vec<u8> = pixels
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect();
+            Ok(ColorImage::from_rgba_unmultiplied(size, &rgba))
+        }
+        other => anyhow::bail!("Unsupported color type {:?}", other),
+    }
+}
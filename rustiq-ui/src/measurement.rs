@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use rustiq_messages::Decibels;
+
+/// A point picked on the waterfall: a frequency on one of its lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    /// Frequency in Hz
+    pub frequency: f64,
+    /// Line the point is on, counted from the first line
+    pub line: u64,
+    /// Sample time of the line
+    pub time: Duration,
+    /// Level of the bin at the frequency, if the line covers it
+    pub level: Option<Decibels>,
+}
+
+/// A line dragged between two points on the waterfall, with the differences
+/// in frequency, time and level between its ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Measurement {
+    pub fn new(start: Point) -> Self {
+        Self { start, end: start }
+    }
+
+    /// Δf in Hz.
+    pub fn frequency(&self) -> f64 {
+        self.end.frequency - self.start.frequency
+    }
+
+    /// Δt in seconds, positive when the end is later.
+    pub fn time(&self) -> f64 {
+        self.end.time.as_secs_f64() - self.start.time.as_secs_f64()
+    }
+
+    /// ΔdB, if both ends have a level.
+    pub fn level(&self) -> Option<Decibels> {
+        Some(Decibels(self.end.level?.0 - self.start.level?.0))
+    }
+
+    /// Symbols per second, taking the ends as one period of a repeating
+    /// feature.
+    pub fn symbol_rate(&self) -> Option<f64> {
+        let period = self.time().abs();
+        (period > 0.0).then(|| 1.0 / period)
+    }
+
+    pub fn label(&self) -> String {
+        let mut lines = vec![
+            format!("Δf {:+.0} Hz", self.frequency()),
+            format!("Δt {:+.1} ms", self.time() * 1e3),
+        ];
+        if let Some(level) = self.level() {
+            lines.push(format!("ΔdB {:+.1}", level.0));
+        }
+        if let Some(rate) = self.symbol_rate() {
+            lines.push(format!("≈ {rate:.1} Bd"));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(frequency: f64, millis: u64, level: Option<f32>) -> Point {
+        Point {
+            frequency,
+            line: 0,
+            time: Duration::from_millis(millis),
+            level: level.map(Decibels),
+        }
+    }
+
+    #[test]
+    fn measures_between_ends() {
+        let measurement = Measurement {
+            start: point(1_000.0, 100, Some(-60.0)),
+            end: point(2_500.0, 80, Some(-40.0)),
+        };
+        assert_eq!(measurement.frequency(), 1_500.0);
+        assert!((measurement.time() + 0.02).abs() < 1e-9);
+        assert_eq!(measurement.level(), Some(Decibels(20.0)));
+        assert!((measurement.symbol_rate().unwrap() - 50.0).abs() < 1e-6);
+        assert_eq!(
+            measurement.label(),
+            "Δf +1500 Hz\nΔt -20.0 ms\nΔdB +20.0\n≈ 50.0 Bd"
+        );
+    }
+
+    #[test]
+    fn leaves_out_what_it_cannot_measure() {
+        let measurement = Measurement {
+            start: point(1_000.0, 100, None),
+            ..Measurement::new(point(1_200.0, 100, Some(-50.0)))
+        };
+        assert_eq!(measurement.level(), None);
+        assert_eq!(measurement.symbol_rate(), None);
+        assert_eq!(measurement.label(), "Δf +200 Hz\nΔt +0.0 ms");
+    }
+}
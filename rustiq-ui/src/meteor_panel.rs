@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use eframe::egui::{ComboBox, DragValue, Response, Ui, Widget};
+use egui_plot::{Bar, BarChart, Plot};
+use flume::Sender;
+
+use crate::rate::counts_per_bin;
+use rustiq_messages::{Burst, Command, Decibels, Hertz, MeteorConfig};
+
+/// Number of pings kept for the timeline.
+const MAX_HISTORY: usize = 100_000;
+
+/// Timeline bin widths offered, in minutes.
+const BIN_MINUTES: [u32; 4] = [1, 5, 10, 60];
+
+/// Meteor scatter / sporadic-E ping detection panel.
+///
+/// The widget (`ui.add(&mut panel)`) renders the channel and threshold
+/// controls and ping statistics; `show_timeline()` renders the ping rate over
+/// time. Individual pings also go to the decode log.
+pub struct MeteorPanel {
+    cmd_tx: Sender<Command>,
+    /// Configuration entered in the controls
+    config: MeteorConfig,
+    /// Configuration the engine is currently detecting with
+    active: Option<MeteorConfig>,
+    pings: Vec<Burst>,
+    /// Width of the timeline bins in minutes
+    bin_minutes: u32,
+}
+
+impl MeteorPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            config: MeteorConfig {
+                // The GRAVES space surveillance radar, a popular meteor scatter beacon
+                frequency: Hertz(143_050_000),
+                bandwidth: Hertz(500),
+                threshold: Decibels(10.0),
+            },
+            active: None,
+            pings: Vec::new(),
+            bin_minutes: 1,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, meteor_detection: Option<MeteorConfig>) {
+        if meteor_detection != self.active {
+            self.pings.clear();
+        }
+        self.active = meteor_detection;
+        if let Some(config) = meteor_detection {
+            self.config = config;
+        }
+    }
+
+    pub fn insert_ping(&mut self, ping: Burst) {
+        if self.pings.len() == MAX_HISTORY {
+            self.pings.remove(0);
+        }
+        self.pings.push(ping);
+    }
+
+    pub fn has_data(&self) -> bool {
+        !self.pings.is_empty()
+    }
+
+    /// Render pings per bin against time since detection started.
+    pub fn show_timeline(&self, ui: &mut Ui) {
+        let bin = Duration::from_secs(60 * self.bin_minutes as u64);
+        let width = self.bin_minutes as f64;
+        let bars = counts_per_bin(self.pings.iter().map(|p| p.start), bin)
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| Bar::new((i as f64 + 0.5) * width, count as f64).width(width))
+            .collect();
+        Plot::new("meteor_timeline")
+            .height(ui.available_height())
+            .x_axis_label("Time (min)")
+            .y_axis_label(format!("Pings per {} min", self.bin_minutes))
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new("Ping rate", bars));
+            });
+    }
+
+    fn send_start(&self) {
+        let _ = self.cmd_tx.send(Command::StartMeteorDetection(self.config));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopMeteorDetection);
+    }
+}
+
+impl Widget for &mut MeteorPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Meteor Scatter");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Frequency:");
+            let mut freq = self.config.frequency.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                .changed()
+            {
+                self.config.frequency.0 = freq;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Bandwidth:");
+            let mut bandwidth = self.config.bandwidth.0;
+            if ui
+                .add(
+                    DragValue::new(&mut bandwidth)
+                        .speed(10)
+                        .range(50..=20_000)
+                        .suffix(" Hz"),
+                )
+                .changed()
+            {
+                self.config.bandwidth.0 = bandwidth;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Threshold:");
+            let mut db = self.config.threshold.0;
+            if ui
+                .add(
+                    DragValue::new(&mut db)
+                        .speed(0.5)
+                        .range(1.0..=60.0)
+                        .suffix(" dB"),
+                )
+                .changed()
+            {
+                self.config.threshold = Decibels(db);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let retune = self.active.is_some_and(|active| active != self.config);
+            let label = if retune { "Retune" } else { "Start" };
+            ui.add_enabled_ui(self.active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+
+        if let Some(last) = self.pings.last() {
+            ui.add_space(5.0);
+            ui.label(format!("Pings: {}", self.pings.len()));
+            ui.label(format!(
+                "Last: {:.0} ms, {}",
+                last.duration.as_secs_f64() * 1e3,
+                last.peak_snr
+            ));
+            ui.horizontal(|ui| {
+                ui.label("Timeline bins:");
+                ComboBox::from_id_salt("meteor_bins")
+                    .selected_text(format!("{} min", self.bin_minutes))
+                    .show_ui(ui, |ui| {
+                        for minutes in BIN_MINUTES {
+                            ui.selectable_value(
+                                &mut self.bin_minutes,
+                                minutes,
+                                format!("{minutes} min"),
+                            );
+                        }
+                    });
+            });
+        } else if self.active.is_some() {
+            ui.label("Waiting for pings...");
+        }
+
+        ui.response()
+    }
+}
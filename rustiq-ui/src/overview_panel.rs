@@ -0,0 +1,235 @@
+//! Overview of the recording being played: its whole spectrogram, worked
+//! out by the engine when the file is opened, with the point playing
+//! marked. A click plays the file from the row clicked; above it, a
+//! transport bar pauses the file and scrubs through it.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use eframe::egui::{
+    self, Color32, ColorImage, Image, Sense, Slider, Stroke, TextureHandle, TextureOptions, Ui,
+};
+use flume::Sender;
+
+use crate::colormap::Colormap;
+use rustiq_messages::{Command, Decibels, RecordingOverview, SourceConfig};
+
+/// Spectrogram of the whole recording, beside the waterfall.
+pub struct OverviewPanel {
+    cmd_tx: Sender<Command>,
+    overview: Option<RecordingOverview>,
+    /// File the engine is playing, if any
+    playing: Option<PathBuf>,
+    /// How far into the file the stream started
+    start: Duration,
+    /// How far into the file the engine has played
+    position: Duration,
+    /// Whether the engine holds the file where it is
+    paused: bool,
+    /// Where the scrubber is while it is dragged
+    scrub: Option<Duration>,
+    colormap: Colormap,
+    texture: Option<TextureHandle>,
+    /// Whether `texture` is behind the overview or color map
+    texture_stale: bool,
+}
+
+impl OverviewPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            overview: None,
+            playing: None,
+            start: Duration::ZERO,
+            position: Duration::ZERO,
+            paused: false,
+            scrub: None,
+            colormap: Colormap::Grayscale,
+            texture: None,
+            texture_stale: false,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(
+        &mut self,
+        source_config: &SourceConfig,
+        start: Duration,
+        paused: bool,
+    ) {
+        let playing = match source_config {
+            SourceConfig::File { path, .. } => Some(path.clone()),
+            _ => None,
+        };
+        // Until the engine reports otherwise, playing from the start
+        if playing != self.playing || start != self.start {
+            self.position = start;
+        }
+        self.playing = playing;
+        self.start = start;
+        self.paused = paused;
+    }
+
+    pub fn set_overview(&mut self, overview: RecordingOverview) {
+        self.overview = Some(overview);
+        self.texture_stale = true;
+    }
+
+    /// Note how far into the file the engine has played.
+    pub fn set_position(&mut self, position: Duration) {
+        self.position = position;
+    }
+
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        if colormap != self.colormap {
+            self.colormap = colormap;
+            self.texture_stale = true;
+        }
+    }
+
+    /// Whether there is an overview of the file being played.
+    pub fn has_overview(&self) -> bool {
+        self.overview
+            .as_ref()
+            .is_some_and(|overview| Some(&overview.path) == self.playing.as_ref())
+    }
+
+    /// Render the spectrogram, oldest row at the top, scrolling through
+    /// the whole recording.
+    pub fn show(&mut self, ui: &mut Ui) {
+        let Some(overview) = &self.overview else {
+            return;
+        };
+        if overview.rows.is_empty() {
+            ui.label("Recording too short for an overview");
+            return;
+        }
+        if self.texture_stale {
+            let image = render(overview, &self.colormap);
+            match &mut self.texture {
+                Some(texture) => texture.set(image, TextureOptions::LINEAR),
+                None => {
+                    self.texture = Some(ui.ctx().load_texture(
+                        "recording_overview",
+                        image,
+                        TextureOptions::LINEAR,
+                    ));
+                }
+            }
+            self.texture_stale = false;
+        }
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        ui.heading("Recording");
+        let mut scrubbed = None;
+        ui.horizontal(|ui| {
+            let (label, command) = if self.paused {
+                ("Resume", Command::Resume)
+            } else {
+                ("Pause", Command::Pause)
+            };
+            if ui.button(label).clicked() {
+                let _ = self.cmd_tx.send(command);
+            }
+            // Seeks once let go, as each seek rebuilds the graph
+            let mut seconds = self.scrub.unwrap_or(self.position).as_secs_f64();
+            ui.spacing_mut().slider_width = ui.available_width();
+            let scrubber = ui.add(
+                Slider::new(&mut seconds, 0.0..=overview.duration.as_secs_f64()).show_value(false),
+            );
+            let time = Duration::from_secs_f64(seconds);
+            if scrubber.dragged() {
+                self.scrub = Some(time);
+            } else if scrubber.drag_stopped() || scrubber.changed() {
+                self.scrub = None;
+                scrubbed = Some(time);
+            }
+        });
+        if let Some(position) = scrubbed {
+            self.position = position;
+            let _ = self.cmd_tx.send(Command::Seek(position));
+        }
+        ui.label(format!(
+            "{} of {}",
+            format_time(self.scrub.unwrap_or(self.position)),
+            format_time(overview.duration)
+        ));
+        ui.weak("Click to play from there");
+        ui.separator();
+
+        let row_duration = overview.row_duration.as_secs_f32();
+        let mut seek = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            // One point per row
+            let size = egui::vec2(ui.available_width(), overview.rows.len() as f32);
+            let response = ui.add(
+                Image::new(texture)
+                    .fit_to_exact_size(size)
+                    .sense(Sense::click()),
+            );
+            let rect = response.rect;
+            let time_at = |y: f32| {
+                let seconds = (y - rect.top()).max(0.0) * row_duration;
+                Duration::from_secs_f32(seconds).min(overview.duration)
+            };
+            let playing = rect.top() + self.position.as_secs_f32() / row_duration;
+            if playing <= rect.bottom() {
+                ui.painter()
+                    .hline(rect.x_range(), playing, Stroke::new(1.0, Color32::WHITE));
+            }
+            if let Some(pos) = response.hover_pos() {
+                response
+                    .clone()
+                    .on_hover_text_at_pointer(format_time(time_at(pos.y)));
+            }
+            if response.clicked() {
+                seek = response.interact_pointer_pos().map(|pos| time_at(pos.y));
+            }
+        });
+        if let Some(position) = seek {
+            let _ = self.cmd_tx.send(Command::Seek(position));
+        }
+    }
+}
+
+/// The overview's rows in `colormap`, scaled between its weakest and
+/// strongest levels.
+fn render(overview: &RecordingOverview, colormap: &Colormap) -> ColorImage {
+    let levels: Vec<f32> = overview
+        .rows
+        .iter()
+        .flatten()
+        // Silence would be minus infinity
+        .map(|&power| Decibels::from_power(power.max(1e-12)).0)
+        .collect();
+    let min = levels.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = levels.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = (max - min).max(0.01);
+    let width = overview.rows[0].len();
+    ColorImage::new(
+        [width, overview.rows.len()],
+        levels
+            .iter()
+            .map(|level| colormap.color((level - min) / range))
+            .collect(),
+    )
+}
+
+/// `time` as minutes and seconds to a tenth.
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs_f64();
+    format!("{}:{:04.1}", (seconds / 60.0) as u64, seconds % 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_minutes_and_seconds() {
+        assert_eq!(format_time(Duration::from_millis(93_450)), "1:33.5");
+        assert_eq!(format_time(Duration::from_secs(7)), "0:07.0");
+    }
+}
@@ -0,0 +1,166 @@
+//! The listening channel's passband, drawn over the spectrum and waterfall
+//! with handles that drag its edges.
+
+use eframe::egui::{Align2, Color32, CursorIcon, FontId, Pos2, Rect, Sense, Stroke, Ui};
+use rustiq_messages::Passband;
+
+use crate::waterfall::Waterfall;
+
+/// Tint over the passband.
+const FILL: Color32 = Color32::from_rgba_premultiplied(30, 40, 10, 40);
+
+/// Color of the passband's edges and width.
+const EDGE_COLOR: Color32 = Color32::from_rgb(180, 230, 90);
+
+/// How far either side of an edge grabs it, in points.
+const GRIP: f32 = 4.0;
+
+/// Narrowest passband the edges can be dragged to, in Hz.
+const MIN_WIDTH: i64 = 50;
+
+/// Edges snap to multiples of this many Hz.
+const STEP: f64 = 10.0;
+
+/// What of the passband is being dragged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Grip {
+    Low,
+    High,
+    /// The whole of it, from the spectrum plot
+    Middle,
+}
+
+/// Passband handles over the spectrum and waterfall of the main source.
+pub struct PassbandOverlay {
+    /// Passband as dragged so far, until the drag ends
+    dragged: Option<Passband>,
+}
+
+impl PassbandOverlay {
+    pub fn new() -> Self {
+        Self { dragged: None }
+    }
+
+    /// Draw `passband` of the channel on `dial` Hz over the spectrum plot
+    /// drawn in `plot` and the waterfall `view` drawn in `waterfall`. Its
+    /// edges drag on either; its middle drags the whole of it along on the
+    /// plot. Returns the passband to filter through once a drag ends.
+    pub fn show(
+        &mut self,
+        ui: &Ui,
+        plot: Rect,
+        waterfall: Rect,
+        view: &Waterfall,
+        dial: f64,
+        passband: Passband,
+    ) -> Option<Passband> {
+        let shown = self.dragged.unwrap_or(passband);
+        let mut ended = false;
+        for (index, rect) in [plot, waterfall].into_iter().enumerate() {
+            let (Some(low), Some(high)) = (
+                view.position_of(rect, dial + shown.low as f64),
+                view.position_of(rect, dial + shown.high as f64),
+            ) else {
+                return None;
+            };
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(Rect::from_x_y_ranges(low..=high, rect.y_range()), 0.0, FILL);
+            let stroke = Stroke::new(1.0, EDGE_COLOR);
+            painter.vline(low, rect.y_range(), stroke);
+            painter.vline(high, rect.y_range(), stroke);
+
+            let mut grips = vec![(Grip::Low, low - GRIP..=low + GRIP)];
+            grips.push((Grip::High, high - GRIP..=high + GRIP));
+            if index == 0 {
+                painter.text(
+                    Pos2::new((low + high) / 2.0, rect.top() + 4.0),
+                    Align2::CENTER_TOP,
+                    format!("{:.2} kHz", shown.width() as f64 / 1e3),
+                    FontId::monospace(12.0),
+                    EDGE_COLOR,
+                );
+                if high - low > 4.0 * GRIP {
+                    grips.push((Grip::Middle, low + GRIP..=high - GRIP));
+                }
+            }
+            for (grip, x_range) in grips {
+                let response = ui.interact(
+                    Rect::from_x_y_ranges(x_range, rect.y_range()).intersect(rect),
+                    ui.id().with(("passband", index, grip)),
+                    Sense::drag(),
+                );
+                let response = match grip {
+                    Grip::Middle => response.on_hover_cursor(CursorIcon::Grab),
+                    _ => response.on_hover_cursor(CursorIcon::ResizeHorizontal),
+                };
+                if response.dragged()
+                    && let Some(pos) = response.interact_pointer_pos()
+                    && let Some(origin) = ui.input(|input| input.pointer.press_origin())
+                    && let (Some(at), Some(from)) = (
+                        view.frequency_at(rect, pos.x),
+                        view.frequency_at(rect, origin.x),
+                    )
+                {
+                    self.dragged = Some(drag(passband, grip, at - dial, at - from));
+                }
+                ended |= response.drag_stopped();
+            }
+        }
+        if ended { self.dragged.take() } else { None }
+    }
+}
+
+/// `passband` with `grip` dragged to `offset` Hz from the dial, `shift` Hz
+/// from where the drag started.
+fn drag(passband: Passband, grip: Grip, offset: f64, shift: f64) -> Passband {
+    let snap = |hz: f64| ((hz / STEP).round() * STEP) as i64;
+    match grip {
+        Grip::Low => Passband {
+            low: snap(offset).min(passband.high - MIN_WIDTH),
+            ..passband
+        },
+        Grip::High => Passband {
+            high: snap(offset).max(passband.low + MIN_WIDTH),
+            ..passband
+        },
+        Grip::Middle => Passband {
+            low: passband.low + snap(shift),
+            high: passband.high + snap(shift),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USB: Passband = Passband {
+        low: 150,
+        high: 2_850,
+    };
+
+    #[test]
+    fn drags_edges_no_closer_than_the_narrowest_passband() {
+        assert_eq!(
+            drag(USB, Grip::Low, 302.4, 0.0),
+            Passband {
+                low: 300,
+                high: 2_850
+            }
+        );
+        assert_eq!(
+            drag(USB, Grip::High, -500.0, 0.0),
+            Passband {
+                low: 150,
+                high: 200
+            }
+        );
+        assert_eq!(
+            drag(USB, Grip::Middle, 0.0, -147.0),
+            Passband {
+                low: 0,
+                high: 2_700
+            }
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{Checkbox, Context, Ui};
+
+/// Frame rate while the window is unfocused, enough to keep the waterfall
+/// moving in a corner of the screen.
+const BACKGROUND_FPS: f64 = 5.0;
+
+/// Time between frames while the window is minimized. Nothing is drawn;
+/// the frames only take in the engine's events so they don't pile up.
+const HIDDEN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Frames are counted over this window for the frame rate shown.
+const WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visibility {
+    Foreground,
+    Background,
+    Hidden,
+}
+
+/// Repaint budget: as fast as the display allows while the window has
+/// focus, and with power saving on, a few frames a second behind other
+/// windows and no drawing at all while minimized. Focusing the window
+/// wakes it at once, since input events always repaint. Listening holds
+/// power saving off, the audio being played as its frames take it in.
+pub struct PowerSaving {
+    enabled: bool,
+    visibility: Visibility,
+    /// Start of each frame within the last `WINDOW`
+    frames: VecDeque<Instant>,
+}
+
+impl PowerSaving {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            visibility: Visibility::Foreground,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Take the window's state at the start of a frame and schedule the
+    /// next frame, at full rate while `listening` to audio played here.
+    pub fn schedule(&mut self, ctx: &Context, listening: bool) {
+        let (focused, minimized) =
+            ctx.input(|input| (input.viewport().focused, input.viewport().minimized));
+        self.visibility = visibility(self.enabled && !listening, focused, minimized);
+        match self.visibility {
+            Visibility::Foreground => ctx.request_repaint(),
+            Visibility::Background => {
+                ctx.request_repaint_after(Duration::from_secs_f64(1.0 / BACKGROUND_FPS))
+            }
+            Visibility::Hidden => ctx.request_repaint_after(HIDDEN_INTERVAL),
+        }
+
+        let now = Instant::now();
+        while self
+            .frames
+            .front()
+            .is_some_and(|&start| now.duration_since(start) > WINDOW)
+        {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(now);
+    }
+
+    /// Whether to skip drawing this frame, and with it the texture uploads.
+    pub fn hidden(&self) -> bool {
+        self.visibility == Visibility::Hidden
+    }
+
+    /// Status bar contents: the toggle and the UI's frame rate.
+    pub fn show(&mut self, ui: &mut Ui) {
+        ui.add(Checkbox::new(&mut self.enabled, "Power saving"))
+            .on_hover_text(format!(
+                "Redraw {BACKGROUND_FPS} times a second while the window is in the background, \
+                 and not at all while it is minimized, unless listening"
+            ));
+        let fps = self.frames.len() as f64 / WINDOW.as_secs_f64();
+        ui.label(format!("UI {fps:.0} fps"))
+            .on_hover_text("Frames the UI drew per second");
+    }
+}
+
+/// How to draw a window with the given state; unknown counts as focused and
+/// shown, since not every platform reports it.
+fn visibility(enabled: bool, focused: Option<bool>, minimized: Option<bool>) -> Visibility {
+    if !enabled {
+        Visibility::Foreground
+    } else if minimized == Some(true) {
+        Visibility::Hidden
+    } else if focused == Some(false) {
+        Visibility::Background
+    } else {
+        Visibility::Foreground
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slows_down_only_when_saving_power() {
+        assert_eq!(visibility(true, None, None), Visibility::Foreground);
+        assert_eq!(
+            visibility(true, Some(true), Some(false)),
+            Visibility::Foreground
+        );
+        assert_eq!(
+            visibility(true, Some(false), Some(false)),
+            Visibility::Background
+        );
+        assert_eq!(
+            visibility(true, Some(false), Some(true)),
+            Visibility::Hidden
+        );
+        assert_eq!(
+            visibility(false, Some(false), Some(true)),
+            Visibility::Foreground
+        );
+    }
+}
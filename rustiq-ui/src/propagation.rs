@@ -0,0 +1,361 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{Align2, Checkbox, Color32, FontId, Rect, TextEdit, Ui};
+use flume::Receiver;
+use log::{debug, warn};
+
+use crate::http;
+use crate::waterfall::Waterfall;
+
+/// Solar data in XML, as N0NBH publishes it, updated every few hours.
+const DEFAULT_SOURCE: &str = "http://www.hamqsl.com/solarxml.php";
+
+/// Time between fetches while enabled; the indices change slowly.
+const REFETCH: Duration = Duration::from_secs(3 * 60 * 60);
+
+/// Height of the strip of band labels along the bottom of the spectrum, in
+/// points.
+const STRIP_HEIGHT: f32 = 16.0;
+
+/// Below this many MHz a band is absorbed by day and opens after dark.
+const NIGHT_BANDS_BELOW: f64 = 5.0;
+
+/// Share of the MUF above which a band is only marginally open.
+const MARGINAL: f64 = 0.85;
+
+/// A shortwave band, amateur or broadcast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HfBand {
+    pub name: &'static str,
+    /// Edges of the band, in Hz
+    pub low: u64,
+    pub high: u64,
+}
+
+const fn band(name: &'static str, low_khz: u64, high_khz: u64) -> HfBand {
+    HfBand {
+        name,
+        low: low_khz * 1_000,
+        high: high_khz * 1_000,
+    }
+}
+
+/// The amateur HF bands, as allocated in most of the world, and the
+/// international broadcast bands.
+pub const HF_BANDS: [HfBand; 20] = [
+    band("160m", 1_800, 2_000),
+    band("120m BC", 2_300, 2_495),
+    band("80m", 3_500, 4_000),
+    band("60m", 5_351, 5_367),
+    band("49m BC", 5_900, 6_200),
+    band("40m", 7_000, 7_200),
+    band("41m BC", 7_200, 7_450),
+    band("31m BC", 9_400, 9_900),
+    band("30m", 10_100, 10_150),
+    band("25m BC", 11_600, 12_100),
+    band("22m BC", 13_570, 13_870),
+    band("20m", 14_000, 14_350),
+    band("19m BC", 15_100, 15_800),
+    band("16m BC", 17_480, 17_900),
+    band("17m", 18_068, 18_168),
+    band("15m", 21_000, 21_450),
+    band("13m BC", 21_450, 21_850),
+    band("12m", 24_890, 24_990),
+    band("11m BC", 25_670, 26_100),
+    band("10m", 28_000, 29_700),
+];
+
+/// The solar indices propagation is guessed from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolarIndices {
+    /// 10.7 cm solar flux, in solar flux units
+    pub flux: f64,
+    /// Planetary K index, 0 to 9
+    pub k_index: f64,
+}
+
+/// How open a band is expected to be, by a rule of thumb.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Openness {
+    Open,
+    /// Close to the MUF, so open on some paths or at some times
+    Marginal,
+    /// Above the MUF
+    Closed,
+    /// Absorbed by day, open after dark
+    Night,
+}
+
+impl Openness {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Marginal => "marginal",
+            Self::Closed => "closed",
+            Self::Night => "night",
+        }
+    }
+
+    fn color(self) -> Color32 {
+        match self {
+            Self::Open => Color32::from_rgb(80, 200, 80),
+            Self::Marginal => Color32::from_rgb(220, 190, 60),
+            Self::Closed => Color32::from_rgb(130, 130, 130),
+            Self::Night => Color32::from_rgb(90, 130, 230),
+        }
+    }
+}
+
+impl SolarIndices {
+    /// Rough daytime MUF of a 3000 km hop at mid latitudes, in MHz: the
+    /// sunspot number follows from the flux, the F2 critical frequency from
+    /// that, and a geomagnetic storm (K of 5 or more) pulls it down.
+    pub fn muf(&self) -> f64 {
+        // Inverse of flux = 63.7 + 0.728 ssn + 0.00089 ssn²
+        let ssn = ((0.728f64.powi(2) + 4.0 * 0.00089 * (self.flux - 63.7).max(0.0)).sqrt() - 0.728)
+            / (2.0 * 0.00089);
+        let fo_f2 = 5.5 + 0.045 * ssn;
+        let storm = 1.0 - 0.1 * (self.k_index - 4.0).max(0.0);
+        3.3 * fo_f2 * storm
+    }
+
+    /// How open `band` is expected to be by day.
+    pub fn openness(&self, band: &HfBand) -> Openness {
+        let center = (band.low + band.high) as f64 / 2e6;
+        let muf = self.muf();
+        if center > muf {
+            Openness::Closed
+        } else if center > MARGINAL * muf {
+            Openness::Marginal
+        } else if center < NIGHT_BANDS_BELOW {
+            Openness::Night
+        } else {
+            Openness::Open
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FetchStatus {
+    Off,
+    Fetching,
+    Fetched(SolarIndices),
+    Failed(String),
+}
+
+/// Opt-in fetch of the solar indices, shown in the status bar, and the HF
+/// bands labelled with how open they are expected to be along the bottom
+/// of the spectrum. Purely informational.
+pub struct Propagation {
+    /// Whether the user opted in
+    enabled: bool,
+    source: String,
+    status: FetchStatus,
+    /// Result of the fetch running in the background
+    result_rx: Option<Receiver<Result<SolarIndices, String>>>,
+    /// When to fetch again
+    next_fetch: Option<Instant>,
+}
+
+impl Propagation {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            source: DEFAULT_SOURCE.to_string(),
+            status: FetchStatus::Off,
+            result_rx: None,
+            next_fetch: None,
+        }
+    }
+
+    /// Fetch the indices on a background thread.
+    fn start(&mut self) {
+        let (result_tx, result_rx) = flume::bounded(1);
+        let source = self.source.clone();
+        thread::spawn(move || {
+            let result = http::get(&source, "application/xml")
+                .map_err(|e| e.to_string())
+                .and_then(|xml| parse_indices(&xml).ok_or_else(|| "no indices in it".into()));
+            debug!("Solar indices from {} were {:?}", source, result);
+            let _ = result_tx.send(result);
+        });
+        self.result_rx = Some(result_rx);
+        self.next_fetch = None;
+        if !matches!(self.status, FetchStatus::Fetched(_)) {
+            self.status = FetchStatus::Fetching;
+        }
+    }
+
+    /// Pick up the result of a running fetch, and start the next when due.
+    pub fn poll(&mut self) {
+        if self.enabled && self.next_fetch.is_some_and(|at| Instant::now() >= at) {
+            self.start();
+        }
+        let Some(result) = self.result_rx.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.result_rx = None;
+        if !self.enabled {
+            return;
+        }
+        self.status = match result {
+            Ok(indices) => FetchStatus::Fetched(indices),
+            Err(e) => {
+                warn!("Fetching the solar indices failed: {}", e);
+                FetchStatus::Failed(e)
+            }
+        };
+        self.next_fetch = Some(Instant::now() + REFETCH);
+    }
+
+    /// The indices last fetched, while enabled.
+    fn indices(&self) -> Option<SolarIndices> {
+        match self.status {
+            FetchStatus::Fetched(indices) => Some(indices),
+            _ => None,
+        }
+    }
+
+    /// Status bar contents: the opt-in toggle and the indices fetched.
+    pub fn show(&mut self, ui: &mut Ui) {
+        let toggle = ui
+            .add(Checkbox::new(&mut self.enabled, "Propagation"))
+            .on_hover_text(
+                "Fetch the solar indices and label the HF bands with how open they are \
+                 likely to be by day",
+            );
+        if toggle.changed() {
+            if self.enabled {
+                self.start();
+            } else {
+                self.status = FetchStatus::Off;
+                self.next_fetch = None;
+            }
+        }
+
+        match &self.status {
+            FetchStatus::Off => {}
+            FetchStatus::Fetching => {
+                ui.spinner();
+            }
+            FetchStatus::Fetched(indices) => {
+                ui.label(format!(
+                    "SFI {:.0} K {:.0}, MUF ≈ {:.0} MHz",
+                    indices.flux,
+                    indices.k_index,
+                    indices.muf()
+                ))
+                .on_hover_text("A rule of thumb for a 3000 km path by day, not a forecast");
+            }
+            FetchStatus::Failed(e) => {
+                ui.colored_label(Color32::LIGHT_RED, "Solar indices unavailable")
+                    .on_hover_text(e);
+                ui.add(TextEdit::singleline(&mut self.source).desired_width(250.0))
+                    .on_hover_text("Solar data XML URL");
+                if ui.button("Retry").clicked() {
+                    self.start();
+                }
+            }
+        }
+    }
+
+    /// Label the HF bands in view along the bottom of the spectrum plot
+    /// drawn in `plot`, on the frequency axis of `view`.
+    pub fn show_bands(&self, ui: &Ui, plot: Rect, view: &Waterfall) {
+        let Some(indices) = self.indices() else {
+            return;
+        };
+        let painter = ui.painter_at(plot);
+        let y_range = plot.bottom() - STRIP_HEIGHT..=plot.bottom();
+        for band in &HF_BANDS {
+            let (Some(low), Some(high)) = (
+                view.position_of(plot, band.low as f64),
+                view.position_of(plot, band.high as f64),
+            ) else {
+                return;
+            };
+            if high < plot.left() || low > plot.right() {
+                continue;
+            }
+            let openness = indices.openness(band);
+            let color = openness.color();
+            painter.rect_filled(
+                Rect::from_x_y_ranges(low..=high, y_range.clone()),
+                0.0,
+                color.gamma_multiply(0.35),
+            );
+            let text = format!("{} {}", band.name, openness.label());
+            let center = Rect::from_x_y_ranges(
+                low.max(plot.left())..=high.min(plot.right()),
+                y_range.clone(),
+            )
+            .center();
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                text,
+                FontId::proportional(11.0),
+                color,
+            );
+        }
+    }
+}
+
+/// The solar flux and K index in solar data XML, each the text of its
+/// element.
+fn parse_indices(xml: &str) -> Option<SolarIndices> {
+    let value = |element: &str| -> Option<f64> {
+        let start = xml.find(&format!("<{element}>"))? + element.len() + 2;
+        let end = start + xml[start..].find('<')?;
+        xml[start..end].trim().parse().ok()
+    };
+    Some(SolarIndices {
+        flux: value("solarflux")?,
+        k_index: value("kindex")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_indices_from_solar_data() {
+        let xml = "<solar><solardata><source url=\"http://www.hamqsl.com/solar.html\">N0NBH\
+                   </source><updated> 17 Oct 2026 1200 GMT</updated><solarflux>152</solarflux>\
+                   <aindex> 8</aindex><kindex> 2</kindex></solardata></solar>";
+        assert_eq!(
+            parse_indices(xml),
+            Some(SolarIndices {
+                flux: 152.0,
+                k_index: 2.0
+            })
+        );
+        assert_eq!(parse_indices("<solar></solar>"), None);
+    }
+
+    #[test]
+    fn opens_higher_bands_with_more_flux() {
+        let band = |name| HF_BANDS.iter().find(|band| band.name == name).unwrap();
+        let quiet = SolarIndices {
+            flux: 68.0,
+            k_index: 1.0,
+        };
+        let active = SolarIndices {
+            flux: 180.0,
+            ..quiet
+        };
+        assert!(quiet.muf() < 20.0 && active.muf() > 30.0);
+        assert_eq!(quiet.openness(band("10m")), Openness::Closed);
+        assert_eq!(active.openness(band("10m")), Openness::Open);
+        assert_eq!(quiet.openness(band("20m")), Openness::Open);
+        assert_eq!(quiet.openness(band("80m")), Openness::Night);
+        // A storm pulls the MUF down
+        let stormy = SolarIndices {
+            k_index: 7.0,
+            ..active
+        };
+        assert!(stormy.muf() < active.muf());
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+/// Count events by time in consecutive bins, from time zero up to the bin
+/// holding the latest event.
+pub fn counts_per_bin(times: impl IntoIterator<Item = Duration>, bin: Duration) -> Vec<usize> {
+    let mut counts = Vec::new();
+    for time in times {
+        let index = (time.as_secs_f64() / bin.as_secs_f64()) as usize;
+        if index >= counts.len() {
+            counts.resize(index + 1, 0);
+        }
+        counts[index] += 1;
+    }
+    counts
+}
+
+/// Trailing mean over up to `window` values, one output per input.
+pub fn moving_average(values: &[usize], window: usize) -> Vec<f64> {
+    let window = window.max(1);
+    (0..values.len())
+        .map(|i| {
+            let recent = &values[(i + 1).saturating_sub(window)..=i];
+            recent.iter().sum::<usize>() as f64 / recent.len() as f64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bins_events_by_time() {
+        let times = [5.0, 30.0, 59.9, 60.0, 250.0].map(Duration::from_secs_f64);
+        assert_eq!(
+            counts_per_bin(times, Duration::from_secs(60)),
+            vec![3, 1, 0, 0, 1]
+        );
+        assert_eq!(counts_per_bin(times, Duration::from_secs(300)), vec![5]);
+        assert!(counts_per_bin([], Duration::from_secs(60)).is_empty());
+    }
+
+    #[test]
+    fn averages_trailing_window() {
+        assert_eq!(moving_average(&[3, 1, 2, 6], 2), vec![3.0, 2.0, 1.5, 4.0]);
+        assert!(moving_average(&[], 5).is_empty());
+    }
+}
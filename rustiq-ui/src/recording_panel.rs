@@ -0,0 +1,96 @@
+use eframe::egui::{ComboBox, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+use std::path::PathBuf;
+
+use rustiq_messages::{Command, RecordingFormat, RecordingStatus};
+
+/// Recording of the live IQ stream to disk: where, in what format, and how
+/// much has been written.
+pub struct RecordingPanel {
+    cmd_tx: Sender<Command>,
+    /// Path entered in the path field
+    path: String,
+    format: RecordingFormat,
+    /// Latest progress of the current or last recording
+    status: Option<RecordingStatus>,
+}
+
+impl RecordingPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            path: "recording".to_string(),
+            format: RecordingFormat::SigMf,
+            status: None,
+        }
+    }
+
+    pub fn set_status(&mut self, status: RecordingStatus) {
+        self.status = Some(status);
+    }
+
+    fn recording(&self) -> bool {
+        self.status.as_ref().is_some_and(|status| status.active)
+    }
+
+    fn send_start(&self) {
+        let _ = self.cmd_tx.send(Command::StartRecording {
+            path: PathBuf::from(&self.path),
+            format: self.format,
+        });
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopRecording);
+    }
+}
+
+impl Widget for &mut RecordingPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Recording");
+        ui.separator();
+
+        let recording = self.recording();
+        ui.add_enabled_ui(!recording, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Path:");
+                ui.add(TextEdit::singleline(&mut self.path).hint_text("file to record to"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Format:");
+                ComboBox::from_id_salt("recording_format")
+                    .selected_text(self.format.label())
+                    .show_ui(ui, |ui| {
+                        for format in RecordingFormat::ALL {
+                            ui.selectable_value(&mut self.format, format, format.label());
+                        }
+                    });
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!recording && !self.path.is_empty(), |ui| {
+                if ui.button("Start").clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(recording, |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+
+        if let Some(status) = &self.status {
+            let megabytes = status.bytes_written as f64 / 1e6;
+            let verb = if status.active {
+                "Recording"
+            } else {
+                "Recorded"
+            };
+            ui.label(format!("{verb} {megabytes:.1} MB"))
+                .on_hover_text(status.path.display().to_string());
+        }
+
+        ui.response()
+    }
+}
@@ -0,0 +1,88 @@
+/// Changes the rate of real audio by linear interpolation, carrying its
+/// position across calls. Plenty for demodulated audio on its way to a
+/// device, which only ever raises the rate.
+pub struct Resampler {
+    /// Input samples per output sample
+    step: f64,
+    /// Position of the next output sample, in input samples after `previous`
+    position: f64,
+    /// Last sample of the previous input
+    previous: f32,
+}
+
+impl Resampler {
+    pub fn new(input_rate: f64, output_rate: f64) -> Self {
+        Self {
+            step: input_rate / output_rate,
+            position: 0.0,
+            previous: 0.0,
+        }
+    }
+
+    /// Resample `input`, returning the output samples that fall within it.
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let Some(&last) = input.last() else {
+            return Vec::new();
+        };
+        let mut output = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        while self.position < input.len() as f64 {
+            let index = self.position as usize;
+            let fraction = (self.position - index as f64) as f32;
+            let before = if index == 0 {
+                self.previous
+            } else {
+                input[index - 1]
+            };
+            output.push(before + (input[index] - before) * fraction);
+            self.position += self.step;
+        }
+        self.position -= input.len() as f64;
+        self.previous = last;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    fn tone(freq: f64, rate: f64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (TAU * freq * i as f64 / rate).sin() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_tone_frequency() {
+        let input = tone(1_000.0, 24_000.0, 24_000);
+        let output = Resampler::new(24_000.0, 44_100.0).process(&input);
+        assert!(
+            output.len().abs_diff(44_100) <= 1,
+            "{} samples",
+            output.len()
+        );
+
+        // One input sample late, after the silence before the input
+        for (i, got) in output.iter().enumerate().skip(10) {
+            let t = (i as f64 * 24_000.0 / 44_100.0 - 1.0) / 24_000.0;
+            let want = (TAU * 1_000.0 * t).sin() as f32;
+            assert!((got - want).abs() < 0.02, "{got} vs {want} at {i}");
+        }
+    }
+
+    #[test]
+    fn carries_position_across_calls() {
+        let input = tone(700.0, 24_000.0, 10_000);
+        let whole = Resampler::new(24_000.0, 48_000.0).process(&input);
+        let mut resampler = Resampler::new(24_000.0, 48_000.0);
+        let pieces: Vec<f32> = input
+            .chunks(333)
+            .flat_map(|chunk| resampler.process(chunk))
+            .collect();
+        assert_eq!(pieces.len(), whole.len());
+        for (a, b) in pieces.iter().zip(&whole) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use eframe::egui::{DragValue, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+
+use rustiq_messages::{Command, Hertz, RigConfig};
+
+/// Default IF offered when switching to an IF tap.
+const DEFAULT_IF: Hertz = Hertz::khz(9_000);
+
+/// Panadapter panel: follow a transceiver through `rigctld`, with clicks on
+/// the waterfall tuning the rig.
+pub struct RigPanel {
+    cmd_tx: Sender<Command>,
+    /// Configuration entered in the controls
+    config: RigConfig,
+    /// Configuration the engine is following, if any
+    active: Option<RigConfig>,
+    /// The rig's frequency, as last centered on by the engine
+    frequency: Hertz,
+}
+
+impl RigPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            config: RigConfig {
+                address: "localhost:4532".to_string(),
+                if_frequency: None,
+            },
+            active: None,
+            frequency: Hertz(0),
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, rig: Option<&RigConfig>, center_frequency: Hertz) {
+        self.active = rig.cloned();
+        if let Some(config) = rig {
+            self.config = config.clone();
+        }
+        self.frequency = center_frequency;
+    }
+
+    /// Tune the rig, if one is connected. Returns whether it was.
+    pub fn tune_rig(&self, frequency: Hertz) -> bool {
+        if self.active.is_some() {
+            let _ = self.cmd_tx.send(Command::TuneRig(frequency));
+        }
+        self.active.is_some()
+    }
+
+    fn send_connect(&self) {
+        let _ = self.cmd_tx.send(Command::ConnectRig(self.config.clone()));
+    }
+
+    fn send_disconnect(&self) {
+        let _ = self.cmd_tx.send(Command::DisconnectRig);
+    }
+}
+
+impl Widget for &mut RigPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Rig Control");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("rigctld:");
+            ui.add(TextEdit::singleline(&mut self.config.address).hint_text("host:port"));
+        });
+
+        ui.horizontal(|ui| {
+            let mut if_tap = self.config.if_frequency.is_some();
+            if ui
+                .checkbox(&mut if_tap, "IF tap")
+                .on_hover_text("The SDR is fed from the rig's IF output rather than the antenna")
+                .changed()
+            {
+                self.config.if_frequency = if_tap.then_some(DEFAULT_IF);
+            }
+            if let Some(if_frequency) = &mut self.config.if_frequency {
+                ui.add(DragValue::new(&mut if_frequency.0).speed(100).suffix(" Hz"));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let changed = self.active.as_ref() != Some(&self.config);
+            let label = if self.active.is_some() {
+                "Apply"
+            } else {
+                "Connect"
+            };
+            ui.add_enabled_ui(changed, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_connect();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Disconnect").clicked() {
+                    self.send_disconnect();
+                }
+            });
+        });
+
+        if self.active.is_some() {
+            ui.label(format!("Rig: {}", self.frequency));
+            ui.label("Click the waterfall to tune the rig.");
+        }
+
+        ui.response()
+    }
+}
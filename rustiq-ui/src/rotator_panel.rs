@@ -0,0 +1,200 @@
+use eframe::egui::{
+    Color32, ComboBox, DragValue, Pos2, Response, Sense, Stroke, TextEdit, Ui, Vec2, Widget,
+};
+use flume::Sender;
+
+use rustiq_messages::{Command, RotatorPosition};
+
+/// Nudge step sizes offered, in degrees.
+const STEPS: [f32; 4] = [1.0, 5.0, 10.0, 45.0];
+
+/// Diameter of the azimuth dial in points.
+const DIAL_SIZE: f32 = 100.0;
+
+/// Antenna rotator panel: connection to `rotctld`, an az/el readout with a
+/// compass dial, manual nudge buttons and a go-to position.
+pub struct RotatorPanel {
+    cmd_tx: Sender<Command>,
+    /// Address entered in the address field
+    address: String,
+    /// Address of the connected `rotctld`
+    active: Option<String>,
+    /// Last position read back from the rotator
+    position: Option<RotatorPosition>,
+    /// Position entered in the go-to fields
+    target: RotatorPosition,
+    /// Degrees moved by each nudge
+    step: f32,
+}
+
+impl RotatorPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            address: "localhost:4533".to_string(),
+            active: None,
+            position: None,
+            target: RotatorPosition {
+                azimuth: 0.0,
+                elevation: 0.0,
+            },
+            step: 5.0,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, rotator: Option<&String>) {
+        if rotator != self.active.as_ref() {
+            self.position = None;
+        }
+        self.active = rotator.cloned();
+        if let Some(address) = rotator {
+            self.address = address.clone();
+        }
+    }
+
+    pub fn set_position(&mut self, position: RotatorPosition) {
+        self.position = Some(position);
+    }
+
+    fn send_connect(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::ConnectRotator(self.address.clone()));
+    }
+
+    fn send_disconnect(&self) {
+        let _ = self.cmd_tx.send(Command::DisconnectRotator);
+    }
+
+    fn send_point(&self, position: RotatorPosition) {
+        let _ = self.cmd_tx.send(Command::PointRotator(position));
+    }
+
+    /// Compass dial with north up and a needle at the rotator's azimuth.
+    fn dial(&self, ui: &mut Ui) {
+        let (response, painter) = ui.allocate_painter(Vec2::splat(DIAL_SIZE), Sense::hover());
+        let center = response.rect.center();
+        let radius = DIAL_SIZE / 2.0 - 10.0;
+        let stroke = ui.visuals().widgets.noninteractive.fg_stroke;
+        painter.circle_stroke(center, radius, stroke);
+        for (label, bearing) in [("N", 0.0), ("E", 90.0), ("S", 180.0), ("W", 270.0)] {
+            painter.text(
+                point_at(center, radius + 6.0, bearing),
+                eframe::egui::Align2::CENTER_CENTER,
+                label,
+                eframe::egui::FontId::proportional(10.0),
+                stroke.color,
+            );
+        }
+        if let Some(position) = self.position {
+            painter.line_segment(
+                [center, point_at(center, radius, position.azimuth)],
+                Stroke::new(2.0, Color32::LIGHT_RED),
+            );
+        }
+    }
+}
+
+/// Point `distance` from `center` on the given compass bearing in degrees.
+fn point_at(center: Pos2, distance: f32, bearing: f32) -> Pos2 {
+    let angle = bearing.to_radians();
+    center + distance * Vec2::new(angle.sin(), -angle.cos())
+}
+
+impl Widget for &mut RotatorPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Rotator");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("rotctld:");
+            ui.add(TextEdit::singleline(&mut self.address).hint_text("host:port"));
+        });
+        ui.horizontal(|ui| {
+            let changed = self.active.as_ref() != Some(&self.address);
+            let label = if self.active.is_some() {
+                "Reconnect"
+            } else {
+                "Connect"
+            };
+            ui.add_enabled_ui(changed, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_connect();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Disconnect").clicked() {
+                    self.send_disconnect();
+                }
+            });
+        });
+
+        if self.active.is_none() {
+            return ui.response();
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            self.dial(ui);
+            ui.vertical(|ui| match self.position {
+                Some(position) => {
+                    ui.label(format!("Az: {:.1}°", position.azimuth));
+                    ui.label(format!("El: {:.1}°", position.elevation));
+                }
+                None => {
+                    ui.label("Waiting for position...");
+                }
+            });
+        });
+
+        // Nudges are relative to where the rotator last reported
+        ui.add_enabled_ui(self.position.is_some(), |ui| {
+            let mut nudge = None;
+            ui.horizontal(|ui| {
+                if ui.button("◀").on_hover_text("Counterclockwise").clicked() {
+                    nudge = Some((-self.step, 0.0));
+                }
+                if ui.button("▶").on_hover_text("Clockwise").clicked() {
+                    nudge = Some((self.step, 0.0));
+                }
+                if ui.button("▲").on_hover_text("Up").clicked() {
+                    nudge = Some((0.0, self.step));
+                }
+                if ui.button("▼").on_hover_text("Down").clicked() {
+                    nudge = Some((0.0, -self.step));
+                }
+                ComboBox::from_id_salt("rotator_step")
+                    .selected_text(format!("{}°", self.step))
+                    .width(50.0)
+                    .show_ui(ui, |ui| {
+                        for step in STEPS {
+                            ui.selectable_value(&mut self.step, step, format!("{step}°"));
+                        }
+                    });
+            });
+            if let (Some((azimuth, elevation)), Some(position)) = (nudge, self.position) {
+                self.send_point(position.nudged(azimuth, elevation));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Go to:");
+            ui.add(
+                DragValue::new(&mut self.target.azimuth)
+                    .range(0.0..=360.0)
+                    .suffix("°"),
+            );
+            ui.add(
+                DragValue::new(&mut self.target.elevation)
+                    .range(0.0..=90.0)
+                    .suffix("°"),
+            );
+            if ui.button("Point").clicked() {
+                self.send_point(self.target);
+            }
+        });
+
+        ui.response()
+    }
+}
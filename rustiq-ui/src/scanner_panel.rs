@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use eframe::egui::{
+    Button, Checkbox, CollapsingHeader, Color32, ComboBox, DragValue, Grid, Response, RichText,
+    ScrollArea, Ui, Widget,
+};
+use flume::Sender;
+
+use rustiq_messages::{AudioChannel, ChannelPlan, Command, Lockout, ScanChannel, ScanList};
+
+/// Scanner panel: a list of channels, built from the channel plans, that
+/// the engine steps the listening channel through, stopping where the
+/// squelch opens.
+pub struct ScannerPanel {
+    cmd_tx: Sender<Command>,
+    /// List as edited, kept with the settings
+    list: ScanList,
+    /// Whether the engine is scanning the list
+    scanning: bool,
+    /// Channel the scan is on, and whether it stopped there for activity
+    status: Option<(usize, bool)>,
+    /// Plan offered for adding channels from
+    plan: ChannelPlan,
+}
+
+impl ScannerPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            list: ScanList {
+                channels: Vec::new(),
+                priority_every: 5,
+                dwell: None,
+            },
+            scanning: false,
+            status: None,
+            plan: ChannelPlan::Marine,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, scan: Option<&ScanList>) {
+        match scan {
+            // With the lockouts made while scanning
+            Some(list) => self.list = list.clone(),
+            None => {
+                self.list = self.list.without_temporary_lockouts();
+                self.status = None;
+            }
+        }
+        self.scanning = scan.is_some();
+    }
+
+    pub fn set_status(&mut self, channel: usize, active: bool) {
+        self.status = Some((channel, active));
+    }
+
+    /// The list to keep with the settings.
+    pub fn list(&self) -> ScanList {
+        self.list.without_temporary_lockouts()
+    }
+
+    pub fn set_list(&mut self, list: ScanList) {
+        self.list = list;
+    }
+
+    /// Append the plan's channels not already in the list.
+    fn add_plan(&mut self) {
+        let plan = self.plan;
+        for channel in plan.channels() {
+            if self
+                .list
+                .channels
+                .iter()
+                .any(|scan| scan.channel.frequency == channel.frequency)
+            {
+                continue;
+            }
+            let audio = AudioChannel {
+                frequency: channel.frequency,
+                demod: plan.demod(),
+            };
+            self.list
+                .channels
+                .push(ScanChannel::new(plan.display(&channel), audio));
+        }
+    }
+
+    fn list_ui(&mut self, ui: &mut Ui) {
+        ui.add_enabled_ui(!self.scanning, |ui| {
+            ui.horizontal(|ui| {
+                ComboBox::from_id_salt("scan_plan")
+                    .selected_text(self.plan.description())
+                    .show_ui(ui, |ui| {
+                        for plan in ChannelPlan::ALL {
+                            ui.selectable_value(&mut self.plan, plan, plan.description());
+                        }
+                    });
+                if ui.button("Add channels").clicked() {
+                    self.add_plan();
+                }
+                if ui.button("Clear").clicked() {
+                    self.list.channels.clear();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Priority every");
+                ui.add(
+                    DragValue::new(&mut self.list.priority_every)
+                        .range(0..=50)
+                        .suffix(" steps"),
+                )
+                .on_hover_text("0 checks priority channels only in turn");
+            });
+            ui.horizontal(|ui| {
+                let mut dwell = self.list.dwell.is_some();
+                let mut seconds = self.list.dwell.map_or(10.0, |dwell| dwell.as_secs_f64());
+                let toggled = ui
+                    .checkbox(&mut dwell, "Dwell")
+                    .on_hover_text(
+                        "Survey: listen to each active channel this long, log what was \
+                         heard and move on",
+                    )
+                    .changed();
+                let changed = ui
+                    .add_enabled(
+                        dwell,
+                        DragValue::new(&mut seconds).range(1.0..=600.0).suffix(" s"),
+                    )
+                    .changed();
+                if toggled || changed {
+                    self.list.dwell = dwell.then(|| Duration::from_secs_f64(seconds));
+                }
+            });
+        });
+
+        let mut remove = None;
+        let mut lockouts = Vec::new();
+        // Only the rows in view, as a plan can have thousands of channels
+        let row_height = ui.spacing().interact_size.y;
+        let len = self.list.channels.len();
+        ScrollArea::vertical()
+            .max_height(200.0)
+            .show_rows(ui, row_height, len, |ui, rows| {
+                Grid::new("scan_list").striped(true).show(ui, |ui| {
+                    ui.label("Channel");
+                    ui.label("Pri").on_hover_text("Priority");
+                    ui.label("Lockout");
+                    ui.label("Delay");
+                    ui.label("Hold")
+                        .on_hover_text("Longest stay on an active channel; 0 for as long as it is");
+                    ui.end_row();
+                    let first = rows.start;
+                    for (i, channel) in self.list.channels[rows].iter_mut().enumerate() {
+                        let i = first + i;
+                        let name = match self.status {
+                            Some((at, true)) if at == i => {
+                                RichText::new(&channel.name).color(Color32::LIGHT_GREEN)
+                            }
+                            Some((at, false)) if at == i => RichText::new(&channel.name).strong(),
+                            _ => RichText::new(&channel.name),
+                        };
+                        ui.label(name);
+                        ui.add_enabled(
+                            !self.scanning,
+                            Checkbox::without_text(&mut channel.priority),
+                        );
+                        let lockout = channel.lockout;
+                        ComboBox::from_id_salt(("scan_lockout", i))
+                            .selected_text(channel.lockout.label())
+                            .show_ui(ui, |ui| {
+                                for choice in Lockout::ALL {
+                                    ui.selectable_value(
+                                        &mut channel.lockout,
+                                        choice,
+                                        choice.label(),
+                                    );
+                                }
+                            });
+                        if channel.lockout != lockout {
+                            lockouts.push((i, channel.lockout));
+                        }
+                        let mut delay = channel.delay.as_secs_f64();
+                        if ui
+                            .add_enabled(
+                                !self.scanning,
+                                DragValue::new(&mut delay)
+                                    .speed(0.1)
+                                    .range(0.0..=60.0)
+                                    .suffix(" s"),
+                            )
+                            .changed()
+                        {
+                            channel.delay = Duration::from_secs_f64(delay);
+                        }
+                        let mut hold = channel.hold.map_or(0.0, |hold| hold.as_secs_f64());
+                        if ui
+                            .add_enabled(
+                                !self.scanning,
+                                DragValue::new(&mut hold).range(0.0..=600.0).suffix(" s"),
+                            )
+                            .changed()
+                        {
+                            channel.hold = (hold > 0.0).then(|| Duration::from_secs_f64(hold));
+                        }
+                        if !self.scanning && ui.small_button("✖").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if let Some(i) = remove {
+            self.list.channels.remove(i);
+        }
+        if self.scanning {
+            for (channel, lockout) in lockouts {
+                let _ = self.cmd_tx.send(Command::SetLockout { channel, lockout });
+            }
+        }
+    }
+}
+
+impl Widget for &mut ScannerPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Scanner");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if self.scanning {
+                if ui.button("Stop scan").clicked() {
+                    let _ = self.cmd_tx.send(Command::StopScan);
+                }
+                if let Some((at, active)) = self.status
+                    && let Some(channel) = self.list.channels.get(at)
+                {
+                    if active {
+                        ui.colored_label(Color32::LIGHT_GREEN, &channel.name)
+                    } else {
+                        ui.weak(&channel.name)
+                    };
+                }
+            } else {
+                let scan = ui
+                    .add_enabled(!self.list.channels.is_empty(), Button::new("Start scan"))
+                    .on_hover_text("Needs the squelch set in the Audio panel")
+                    .on_disabled_hover_text("Add channels to the scan list");
+                if scan.clicked() {
+                    let _ = self.cmd_tx.send(Command::StartScan(self.list.clone()));
+                }
+            }
+        });
+
+        CollapsingHeader::new(format!("Scan list ({})", self.list.channels.len()))
+            .id_salt("scan_list")
+            .show(ui, |ui| self.list_ui(ui));
+
+        ui.response()
+    }
+}
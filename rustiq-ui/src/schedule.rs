@@ -0,0 +1,346 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui::{Align2, Color32, FontId, Pos2, Rect, TextEdit, Ui};
+use log::{info, warn};
+
+use crate::waterfall::Waterfall;
+
+/// How far from a broadcast's frequency it is still taken to be the one
+/// tuned to, in Hz: half the 5 kHz shortwave channel spacing.
+const TOLERANCE: f64 = 2_500.0;
+
+/// Most broadcasts listed at one frequency.
+const MAX_SHOWN: usize = 4;
+
+/// Color of the broadcasts listed under the waterfall's frequency readout.
+const LABEL_COLOR: Color32 = Color32::from_rgb(255, 220, 150);
+
+/// A moment in the week, as broadcast schedules go by, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekTime {
+    /// Day of the week, 1 for Monday to 7 for Sunday
+    pub weekday: u8,
+    /// Minutes since midnight
+    pub minute: u16,
+}
+
+impl From<SystemTime> for WeekTime {
+    fn from(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let days = secs / 86_400;
+        Self {
+            // 1970-01-01 was a Thursday
+            weekday: ((days + 3) % 7 + 1) as u8,
+            minute: (secs % 86_400 / 60) as u16,
+        }
+    }
+}
+
+/// One broadcast of a schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Broadcast {
+    /// Frequency in Hz
+    pub frequency: f64,
+    /// Minutes since midnight UTC it starts and ends at; it runs past
+    /// midnight if it ends before it starts
+    pub start: u16,
+    pub end: u16,
+    /// Days of the week it is on, bit 0 for Monday, or every day
+    pub days: Option<u8>,
+    pub station: String,
+    /// Language code, as the schedule gives it
+    pub language: String,
+    /// Target area code, as the schedule gives it
+    pub target: String,
+}
+
+impl Broadcast {
+    /// Whether the broadcast is on the air at `time`.
+    pub fn on_air(&self, time: WeekTime) -> bool {
+        let on_day = |weekday: u8| {
+            self.days
+                .is_none_or(|days| days & (1 << (weekday - 1)) != 0)
+        };
+        if self.start < self.end {
+            on_day(time.weekday) && (self.start..self.end).contains(&time.minute)
+        } else if time.minute >= self.start {
+            on_day(time.weekday)
+        } else {
+            // The part after midnight belongs to the day it started on
+            let yesterday = (time.weekday + 5) % 7 + 1;
+            time.minute < self.end && on_day(yesterday)
+        }
+    }
+
+    /// Station, language and target, as listed.
+    pub fn describe(&self) -> String {
+        let mut text = self.station.clone();
+        if !self.language.is_empty() {
+            text += &format!(" ({})", self.language);
+        }
+        if !self.target.is_empty() {
+            text += &format!(" → {}", self.target);
+        }
+        text
+    }
+}
+
+/// An offline shortwave broadcast schedule, in the CSV format EiBi
+/// publishes: `kHz;Time(UTC);Days;ITU;Station;Lng;Target;...`, one
+/// broadcast per line, times as `HHMM-HHMM` and days as `1245` (Monday is
+/// 1) or `Mo-Fr` and the like, blank for every day.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    broadcasts: Vec<Broadcast>,
+}
+
+impl Schedule {
+    pub fn len(&self) -> usize {
+        self.broadcasts.len()
+    }
+
+    /// The broadcasts on the air at `frequency` at `time`.
+    pub fn on_air(&self, frequency: f64, time: WeekTime) -> impl Iterator<Item = &Broadcast> {
+        self.broadcasts
+            .iter()
+            .filter(move |b| (b.frequency - frequency).abs() <= TOLERANCE && b.on_air(time))
+    }
+}
+
+/// Days of the week as EiBi writes them: digits, a range of day names, or
+/// a list of them. Anything else, such as "irr", counts as every day.
+fn parse_days(days: &str) -> Option<u8> {
+    const NAMES: [&str; 7] = ["mo", "tu", "we", "th", "fr", "sa", "su"];
+    let days = days.trim().to_ascii_lowercase();
+    if days.is_empty() {
+        return None;
+    }
+    if days.chars().all(|c| ('1'..='7').contains(&c)) {
+        return Some(days.bytes().fold(0, |set, c| set | 1 << (c - b'1')));
+    }
+    let day = |name: &str| NAMES.iter().position(|&n| n == name);
+    if let Some((from, to)) = days.split_once('-') {
+        let (from, to) = (day(from)?, day(to)?);
+        // A range may wrap past Sunday, as "Fr-Mo"
+        return Some((0..7).fold(0, |set, offset| {
+            let d = (from + offset) % 7;
+            let within = (d + 7 - from) % 7 <= (to + 7 - from) % 7;
+            if within { set | 1 << d } else { set }
+        }));
+    }
+    // Names run together, as "MoWeFr"
+    if days.len().is_multiple_of(2) && days.is_ascii() {
+        return (0..days.len())
+            .step_by(2)
+            .try_fold(0, |set, i| Some(set | 1 << day(&days[i..i + 2])?));
+    }
+    None
+}
+
+/// Minutes since midnight of an `HHMM` time; 2400 is the end of the day.
+fn parse_minute(time: &str) -> Option<u16> {
+    let time: u16 = time.trim().parse().ok()?;
+    let (hour, minute) = (time / 100, time % 100);
+    (hour <= 24 && minute < 60 && time <= 2400).then_some(hour * 60 + minute)
+}
+
+/// A broadcast schedule in EiBi's CSV format. The header line, blank lines
+/// and lines whose frequency or time can't be read are skipped.
+pub fn parse(csv: &str) -> Result<Schedule, String> {
+    let broadcasts: Vec<Broadcast> = csv
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            let khz: f64 = fields.first()?.parse().ok()?;
+            let (start, end) = fields.get(1)?.split_once('-')?;
+            Some(Broadcast {
+                frequency: khz * 1e3,
+                start: parse_minute(start)?,
+                end: parse_minute(end)?,
+                days: parse_days(fields.get(2).copied().unwrap_or_default()),
+                station: fields.get(4)?.to_string(),
+                language: fields.get(5).copied().unwrap_or_default().to_string(),
+                target: fields.get(6).copied().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+    if broadcasts.is_empty() {
+        return Err("no broadcasts in it".to_string());
+    }
+    Ok(Schedule { broadcasts })
+}
+
+/// The broadcast schedule looked up at the frequency tuned to and the one
+/// under the pointer, loaded from a file once asked to.
+pub struct ScheduleLookup {
+    path: String,
+    schedule: Schedule,
+    /// Outcome of the last load: what was loaded, or why it failed
+    status: Option<Result<String, String>>,
+}
+
+impl ScheduleLookup {
+    pub fn new() -> Self {
+        Self {
+            path: "sked.csv".to_string(),
+            schedule: Schedule::default(),
+            status: None,
+        }
+    }
+
+    fn load(&mut self) {
+        match std::fs::read_to_string(Path::new(&self.path))
+            .map_err(|e| e.to_string())
+            .and_then(|csv| parse(&csv))
+        {
+            Ok(schedule) => {
+                info!("Loaded {} broadcasts from {}", schedule.len(), self.path);
+                self.status = Some(Ok(format!("{} broadcasts", schedule.len())));
+                self.schedule = schedule;
+            }
+            Err(e) => {
+                warn!("Failed to load the schedule from {}: {}", self.path, e);
+                self.status = Some(Err(e));
+            }
+        }
+    }
+
+    /// The broadcasts on the air now at `frequency`, described, at most
+    /// `MAX_SHOWN` of them.
+    fn on_air_now(&self, frequency: f64) -> Vec<String> {
+        let now = WeekTime::from(SystemTime::now());
+        self.schedule
+            .on_air(frequency, now)
+            .take(MAX_SHOWN)
+            .map(Broadcast::describe)
+            .collect()
+    }
+
+    /// The file to load the schedule from, and the outcome of the last
+    /// load.
+    pub fn settings_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.path).desired_width(150.0));
+            if ui
+                .button("Load schedule")
+                .on_hover_text("An EiBi CSV file, as published at eibispace.de")
+                .clicked()
+            {
+                self.load();
+            }
+        });
+        match &self.status {
+            Some(Ok(loaded)) => {
+                ui.weak(loaded);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(Color32::LIGHT_RED, e);
+            }
+            None => {}
+        }
+    }
+
+    /// List the broadcasts on the air at `frequency` now, a line each.
+    pub fn show_on_air(&self, ui: &mut Ui, frequency: f64) {
+        for broadcast in self.on_air_now(frequency) {
+            ui.weak(broadcast);
+        }
+    }
+
+    /// List the broadcasts on the air at `frequency` now under its readout
+    /// on the waterfall `view` drawn in `rect`.
+    pub fn mark_frequency(&self, ui: &Ui, rect: Rect, view: &Waterfall, frequency: f64) {
+        let Some(x) = view.position_of(rect, frequency) else {
+            return;
+        };
+        let painter = ui.painter_at(rect);
+        let mut pos = Pos2::new(x + 4.0, rect.top() + 20.0);
+        for broadcast in self.on_air_now(frequency) {
+            let label = painter.text(
+                pos,
+                Align2::LEFT_TOP,
+                broadcast,
+                FontId::proportional(12.0),
+                LABEL_COLOR,
+            );
+            pos.y += label.height();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SKED: &str = "kHz:75;Time(UTC):93;Days:59;ITU:49;Station:201;Lng:49;Target:62;Remarks:135;P:35;Start:60;Stop:60;\n\
+                        6005;0600-0630;;D;Radio Seefunk;G;D;;1;;\n\
+                        6005;2300-0100;Sa;D;Late Show;E;Eu;;1;;\n\
+                        9420;0000-2400;1245;GRC;Voice of Greece;Gr;Eu;;1;;\n\
+                        nonsense\n";
+
+    fn at(weekday: u8, hour: u16, minute: u16) -> WeekTime {
+        WeekTime {
+            weekday,
+            minute: hour * 60 + minute,
+        }
+    }
+
+    fn on_air(schedule: &Schedule, khz: f64, time: WeekTime) -> Vec<&str> {
+        schedule
+            .on_air(khz * 1e3, time)
+            .map(|b| b.station.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn reads_an_eibi_schedule() {
+        let schedule = parse(SKED).unwrap();
+        assert_eq!(schedule.len(), 3);
+        let seefunk = &schedule.broadcasts[0];
+        assert_eq!(seefunk.frequency, 6_005_000.0);
+        assert_eq!((seefunk.start, seefunk.end), (360, 390));
+        assert_eq!(seefunk.describe(), "Radio Seefunk (G) → D");
+        assert!(parse("kHz;Time(UTC)\n").is_err());
+    }
+
+    #[test]
+    fn finds_broadcasts_on_the_air() {
+        let schedule = parse(SKED).unwrap();
+        assert_eq!(on_air(&schedule, 6005.0, at(2, 6, 15)), ["Radio Seefunk"]);
+        // Near enough the frequency, not the time
+        assert_eq!(on_air(&schedule, 6006.0, at(2, 6, 15)), ["Radio Seefunk"]);
+        assert!(on_air(&schedule, 6010.0, at(2, 6, 15)).is_empty());
+        assert!(on_air(&schedule, 6005.0, at(2, 6, 30)).is_empty());
+        // Saturday's late show runs into Sunday, not Saturday morning
+        assert_eq!(on_air(&schedule, 6005.0, at(6, 23, 30)), ["Late Show"]);
+        assert_eq!(on_air(&schedule, 6005.0, at(7, 0, 30)), ["Late Show"]);
+        assert!(on_air(&schedule, 6005.0, at(6, 0, 30)).is_empty());
+        // All day, but not on Wednesdays
+        assert_eq!(
+            on_air(&schedule, 9420.0, at(1, 23, 59)),
+            ["Voice of Greece"]
+        );
+        assert!(on_air(&schedule, 9420.0, at(3, 12, 0)).is_empty());
+    }
+
+    #[test]
+    fn reads_days_as_eibi_writes_them() {
+        assert_eq!(parse_days(""), None);
+        assert_eq!(parse_days("1245"), Some(0b0011011));
+        assert_eq!(parse_days("Mo-Fr"), Some(0b0011111));
+        assert_eq!(parse_days("Fr-Mo"), Some(0b1110001));
+        assert_eq!(parse_days("SaSu"), Some(0b1100000));
+        assert_eq!(parse_days("irr"), None);
+    }
+
+    #[test]
+    fn tells_the_weekday_from_the_time() {
+        // 2026-10-17 12:34 UTC was a Saturday
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_792_240_440);
+        assert_eq!(WeekTime::from(time), at(6, 12, 34));
+    }
+}
@@ -0,0 +1,234 @@
+use eframe::egui::{Color32, ComboBox, DragValue, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+use log::warn;
+
+use rustiq_messages::{
+    AudioChannel, Command, DemodMode, Hertz, SelCall, SelCallConfig, SelCallStandard, SignalRegion,
+};
+
+/// SelCall decoder panel: channel and tone set selection, plus alert rules.
+///
+/// Decoded calls go to the decode log; this panel only keeps the latest one.
+pub struct SelCallPanel {
+    cmd_tx: Sender<Command>,
+    /// Configuration entered in the controls
+    config: SelCallConfig,
+    /// Configuration the engine is currently decoding with
+    active: Option<SelCallConfig>,
+    /// Signal spanned by the waterfall's measurement line, if there is one
+    selection: Option<SignalRegion>,
+    last_call: Option<SelCall>,
+    /// ID patterns that raise an alert; `?` matches one digit, `*` any remainder
+    alert_rules: Vec<String>,
+    new_rule: String,
+    /// Alert waiting for the app to request the user's attention
+    pending_alert: Option<String>,
+    /// Modes the engine can demodulate
+    demod_modes: Vec<DemodMode>,
+}
+
+impl SelCallPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            config: SelCallConfig {
+                channel: AudioChannel {
+                    frequency: Hertz(446_006_250),
+                    demod: DemodMode::Fm,
+                },
+                standard: SelCallStandard::Zvei1,
+            },
+            active: None,
+            selection: None,
+            last_call: None,
+            alert_rules: Vec::new(),
+            new_rule: String::new(),
+            pending_alert: None,
+            demod_modes: DemodMode::ALL.to_vec(),
+        }
+    }
+
+    pub fn set_demod_modes(&mut self, demod_modes: &[DemodMode]) {
+        self.demod_modes = demod_modes.to_vec();
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SignalRegion>) {
+        self.selection = selection;
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, selcall_decoder: Option<SelCallConfig>) {
+        self.active = selcall_decoder;
+        if let Some(config) = selcall_decoder {
+            self.config = config;
+        }
+    }
+
+    /// Record a decoded call, returning whether it matched an alert rule.
+    pub fn insert_call(&mut self, call: SelCall) -> bool {
+        let alert = self
+            .alert_rules
+            .iter()
+            .any(|rule| id_matches(rule, &call.id));
+        if alert {
+            warn!("SelCall alert: {}", call.id);
+            self.pending_alert = Some(call.id.clone());
+        }
+        self.last_call = Some(call);
+        alert
+    }
+
+    /// Take the ID of an alert raised since the last call.
+    pub fn take_alert(&mut self) -> Option<String> {
+        self.pending_alert.take()
+    }
+
+    pub fn alert_rules(&self) -> &[String] {
+        &self.alert_rules
+    }
+
+    pub fn set_alert_rules(&mut self, alert_rules: Vec<String>) {
+        self.alert_rules = alert_rules;
+    }
+
+    fn send_start(&self) {
+        let _ = self.cmd_tx.send(Command::StartSelCallDecoder(self.config));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopSelCallDecoder);
+    }
+}
+
+impl Widget for &mut SelCallPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("SelCall Decoder");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Frequency:");
+            let mut freq = self.config.channel.frequency.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                .changed()
+            {
+                self.config.channel.frequency.0 = freq;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Demod:");
+            ComboBox::from_id_salt("selcall_demod")
+                .selected_text(self.config.channel.demod.label())
+                .show_ui(ui, |ui| {
+                    for &demod in &self.demod_modes {
+                        ui.selectable_value(&mut self.config.channel.demod, demod, demod.label());
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Standard:");
+            ComboBox::from_id_salt("selcall_standard")
+                .selected_text(self.config.standard.label())
+                .show_ui(ui, |ui| {
+                    for standard in SelCallStandard::ALL {
+                        ui.selectable_value(&mut self.config.standard, standard, standard.label());
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            let retune = self.active.is_some_and(|active| active != self.config);
+            let label = if retune { "Retune" } else { "Start" };
+            ui.add_enabled_ui(self.active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+        ui.add_enabled_ui(self.selection.is_some(), |ui| {
+            if ui
+                .button("Decode selection")
+                .on_hover_text("Tune to the signal, guessing its mode from its width")
+                .on_disabled_hover_text("Drag across a signal on the waterfall")
+                .clicked()
+                && let Some(selection) = self.selection
+            {
+                self.config.channel = AudioChannel::for_signal(selection);
+                self.send_start();
+            }
+        });
+
+        if let Some(call) = &self.last_call {
+            ui.add_space(5.0);
+            ui.monospace(format!("Last: {} ({})", call.id, call.standard.label()));
+        }
+
+        ui.add_space(5.0);
+        ui.label("Alert on IDs:");
+        let mut remove = None;
+        for (i, rule) in self.alert_rules.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.colored_label(Color32::RED, rule);
+                if ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.alert_rules.remove(i);
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.new_rule)
+                    .hint_text("e.g. 12??5")
+                    .desired_width(100.0),
+            );
+            if ui.button("Add").clicked() && !self.new_rule.trim().is_empty() {
+                self.alert_rules.push(self.new_rule.trim().to_string());
+                self.new_rule.clear();
+            }
+        });
+
+        ui.response()
+    }
+}
+
+/// Match a call ID against an alert pattern.
+/// `?` matches any single character and a trailing `*` matches the rest.
+fn id_matches(pattern: &str, id: &str) -> bool {
+    let mut id = id.chars();
+    for p in pattern.chars() {
+        if p == '*' {
+            return true;
+        }
+        match id.next() {
+            Some(c) if p == '?' || p == c => {}
+            _ => return false,
+        }
+    }
+    id.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alert_patterns() {
+        assert!(id_matches("12345", "12345"));
+        assert!(!id_matches("12345", "12346"));
+        assert!(id_matches("12??5", "12005"));
+        assert!(!id_matches("12??5", "1205"));
+        assert!(id_matches("12*", "12"));
+        assert!(id_matches("12*", "129999"));
+        assert!(!id_matches("12*", "13"));
+        assert!(!id_matches("123", "1234"));
+    }
+}
@@ -0,0 +1,94 @@
+use eframe::egui::{Align2, Context, Grid, Window};
+use flume::Sender;
+
+use rustiq_messages::{Command, SessionRecord, SourceConfig};
+
+/// Offer to restore a session that ended in a crash, shown once at startup.
+pub struct SessionPrompt {
+    cmd_tx: Sender<Command>,
+    /// Crashed session awaiting the user's answer
+    session: Option<SessionRecord>,
+}
+
+impl SessionPrompt {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            session: None,
+        }
+    }
+
+    pub fn offer(&mut self, session: SessionRecord) {
+        self.session = Some(session);
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let mut answered = false;
+        Window::new("Restore previous session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("RustIQ didn't exit cleanly last time.");
+                ui.add_space(5.0);
+                Grid::new("previous_session").num_columns(2).show(ui, |ui| {
+                    ui.label("Source:");
+                    ui.label(match &session.source_config {
+                        SourceConfig::SignalGenerator { tones, .. } => match tones.first() {
+                            Some(tone) => format!("Signal generator at {}", tone.frequency),
+                            None => "Signal generator".to_string(),
+                        },
+                        SourceConfig::File { path, .. } => path.display().to_string(),
+                        SourceConfig::RtlSdr { sample_rate, .. } => {
+                            format!("RTL-SDR at {sample_rate}")
+                        }
+                        SourceConfig::SoapySdr {
+                            device,
+                            sample_rate,
+                            ..
+                        } => format!("SoapySDR {device} at {sample_rate}"),
+                        SourceConfig::RtlTcp {
+                            host,
+                            port,
+                            sample_rate,
+                            ..
+                        } => format!("rtl_tcp {host}:{port} at {sample_rate}"),
+                        SourceConfig::SpyServer {
+                            host,
+                            port,
+                            sample_rate,
+                            ..
+                        } => format!("SpyServer {host}:{port} at {sample_rate}"),
+                    });
+                    ui.end_row();
+                    ui.label("Frequency:");
+                    ui.label(session.center_frequency.to_string());
+                    ui.end_row();
+                    ui.label("Gain:");
+                    ui.label(session.gain.to_string());
+                    ui.end_row();
+                    if let Some(recording) = &session.recording {
+                        ui.label("Recording:");
+                        ui.label(format!("{} (closed)", recording.display()));
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        let _ = self.cmd_tx.send(Command::RestoreSession(session.clone()));
+                        answered = true;
+                    }
+                    if ui.button("Start fresh").clicked() {
+                        answered = true;
+                    }
+                });
+            });
+        if answered {
+            self.session = None;
+        }
+    }
+}
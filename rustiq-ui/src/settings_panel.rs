@@ -0,0 +1,204 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use eframe::egui::{Color32, Response, TextEdit, Ui, Widget};
+use flume::Sender;
+use log::{info, warn};
+
+use rustiq_messages::{Command, SettingsBundle, read_frame, write_frame};
+
+/// Start of a settings file, ahead of the bundle in wire format. The
+/// version changes whenever the bundle's layout does.
+const MAGIC: &[u8; 8] = b"RIQSET02";
+
+/// What the user asked the settings panel to do, carried out by the app,
+/// which holds the settings of the other panels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Export,
+    Import,
+}
+
+/// Export of the station's settings to one file, and import of such a file,
+/// to move a setup to another machine or share it.
+pub struct SettingsPanel {
+    cmd_tx: Sender<Command>,
+    path: String,
+    requested: Option<Transfer>,
+    /// Outcome of the last export or import: what was done, or why it failed
+    status: Option<Result<String, String>>,
+}
+
+impl SettingsPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            path: "station.rustiq".to_string(),
+            requested: None,
+            status: None,
+        }
+    }
+
+    pub fn take_transfer(&mut self) -> Option<Transfer> {
+        self.requested.take()
+    }
+
+    /// Write `bundle` to the file, or report that there is nothing to export
+    /// yet with `None`.
+    pub fn export(&mut self, bundle: Option<SettingsBundle>) {
+        let Some(bundle) = bundle else {
+            self.status = Some(Err("Engine hasn't reported its settings yet".to_string()));
+            return;
+        };
+        self.status = Some(match save(Path::new(&self.path), &bundle) {
+            Ok(()) => {
+                info!("Exported settings to {}", self.path);
+                Ok(format!("Exported to {}", self.path))
+            }
+            Err(e) => {
+                warn!("Failed to export settings to {}: {}", self.path, e);
+                Err(format!("Export failed: {e}"))
+            }
+        });
+    }
+
+    /// Read the file and send its engine settings to the engine. Returns the
+    /// bundle for the app to apply the rest.
+    pub fn import(&mut self) -> Option<SettingsBundle> {
+        let bundle = match load(Path::new(&self.path)) {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                warn!("Failed to import settings from {}: {}", self.path, e);
+                self.status = Some(Err(format!("Import failed: {e}")));
+                return None;
+            }
+        };
+        info!("Importing settings from {}", self.path);
+        for command in commands(&bundle) {
+            let _ = self.cmd_tx.send(command);
+        }
+        self.status = Some(Ok(format!("Imported {}", self.path)));
+        Some(bundle)
+    }
+}
+
+/// Commands that put the engine into the bundle's settings. The gain is
+/// set last, as tuning applies the gain profiles.
+fn commands(bundle: &SettingsBundle) -> Vec<Command> {
+    vec![
+        Command::ChangeSource(bundle.source_config.clone()),
+        Command::SetGainProfiles(bundle.gain_profiles.clone()),
+        Command::SetAntennaSwitch(bundle.antenna_switch.clone()),
+        match &bundle.rig {
+            Some(rig) => Command::ConnectRig(rig.clone()),
+            None => Command::DisconnectRig,
+        },
+        match &bundle.rotator {
+            Some(address) => Command::ConnectRotator(address.clone()),
+            None => Command::DisconnectRotator,
+        },
+        Command::SetCenterFrequency(bundle.center_frequency),
+        Command::SetGain(bundle.gain),
+    ]
+}
+
+fn save(path: &Path, bundle: &SettingsBundle) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    write_frame(&mut writer, bundle)
+}
+
+fn load(path: &Path) -> io::Result<SettingsBundle> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a RustIQ settings file, or from another version",
+        ));
+    }
+    read_frame(&mut reader)
+}
+
+impl Widget for &mut SettingsPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Settings");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.add(TextEdit::singleline(&mut self.path).desired_width(150.0));
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button("Export settings")
+                .on_hover_text(
+                    "Save the source, tuning, gain profiles, antenna switch, rig, rotator \
+                     and alert rules",
+                )
+                .clicked()
+            {
+                self.requested = Some(Transfer::Export);
+            }
+            if ui
+                .button("Import settings")
+                .on_hover_text("Replace the current settings with the file's")
+                .clicked()
+            {
+                self.requested = Some(Transfer::Import);
+            }
+        });
+
+        match &self.status {
+            Some(Ok(done)) => {
+                ui.label(done);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(Color32::LIGHT_RED, e);
+            }
+            None => {}
+        }
+
+        ui.response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustiq_messages::{Decibels, Hertz, ScanList, SourceConfig};
+
+    fn bundle() -> SettingsBundle {
+        SettingsBundle {
+            source_config: SourceConfig::default(),
+            center_frequency: Hertz::mhz(145),
+            gain: Decibels(20.0),
+            gain_profiles: Vec::new(),
+            antenna_switch: None,
+            rig: None,
+            rotator: Some("localhost:4533".to_string()),
+            alert_rules: vec!["12???".to_string()],
+            scan_list: ScanList::default(),
+        }
+    }
+
+    #[test]
+    fn saves_and_loads_bundle() {
+        let path = std::env::temp_dir().join(format!("rustiq-settings-{}", std::process::id()));
+        save(&path, &bundle()).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), bundle());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = std::env::temp_dir().join(format!("rustiq-not-settings-{}", std::process::id()));
+        std::fs::write(&path, b"time_utc,decoder,text,alert\n").unwrap();
+        let err = load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,511 @@
+use eframe::egui::{
+    Align2, Color32, FontId, Pos2, Rect, Response, RichText, Sense, Shape, Stroke, Ui, Vec2, Widget,
+};
+use flume::Sender;
+use rustiq_messages::{Command, Decibels, SpectrumFrame};
+
+use crate::band_map::{Band, Bins, Pixels};
+use crate::frequency_axis;
+use crate::zoom::Zoom;
+
+/// Height of the plot above the waterfall, in points.
+const HEIGHT: f32 = 150.0;
+
+/// Spacing of the level grid, in dB.
+const GRID_STEP: f32 = 10.0;
+
+/// Frames in the running average a reference trace is taken from.
+const AVERAGE_FRAMES: f32 = 10.0;
+
+const TRACE_COLOR: Color32 = Color32::LIGHT_GREEN;
+const GRID_COLOR: Color32 = Color32::from_gray(60);
+const LABEL_COLOR: Color32 = Color32::GRAY;
+
+/// Color of the cursor at the frequency under the pointer, as on the waterfall.
+const CURSOR_COLOR: Color32 = Color32::YELLOW;
+
+/// Colors of the traces of the highest and lowest level of each bin.
+const PEAK_HOLD_COLOR: Color32 = Color32::LIGHT_RED;
+const MIN_HOLD_COLOR: Color32 = Color32::from_rgb(110, 110, 255);
+
+/// Colors of the reference trace slots; one slot per color.
+const REFERENCE_COLORS: [Color32; 3] = [Color32::LIGHT_BLUE, Color32::ORANGE, Color32::KHAKI];
+
+/// Levels across a band, one per bin.
+struct Trace {
+    band: Band,
+    levels: Vec<Decibels>,
+}
+
+impl Trace {
+    /// The band of `frame` with `magnitudes` across it.
+    fn of(frame: &SpectrumFrame, magnitudes: &[f32]) -> Self {
+        Self {
+            band: Band::of(frame),
+            levels: magnitudes
+                .iter()
+                .map(|&magnitude| Decibels::from_linear(magnitude))
+                .collect(),
+        }
+    }
+
+    fn bins(&self) -> Bins {
+        Bins::new(self.band, self.levels.len())
+    }
+
+    /// Width of a bin, the resolution bandwidth, in Hz.
+    fn rbw(&self) -> f64 {
+        Bins::new(self.band, self.levels.len().max(1)).width()
+    }
+
+    /// What to add to a bin's level for the power spectral density, in dB/Hz
+    /// relative to full scale: the FFT's gain of the bins squared taken off,
+    /// then the resolution bandwidth.
+    fn density_offset(&self) -> f32 {
+        let bins = self.levels.len().max(1) as f64;
+        -(20.0 * bins.log10() + 10.0 * self.rbw().log10()) as f32
+    }
+
+    fn level_at(&self, frequency: f64) -> Option<Decibels> {
+        Some(self.levels[self.bins().bin_at(frequency)?])
+    }
+
+    /// The highest level falling in each of `width` columns across `view`,
+    /// so narrow signals still show when there are more bins than columns.
+    /// Columns outside this trace's band have none.
+    fn column_peaks(&self, view: Band, width: usize) -> Vec<Option<f32>> {
+        let bins = self.levels.len() as f64;
+        let edge = |column: usize| {
+            let frequency = view.frequency_at(column as f64 / width as f64);
+            self.bins().position_of(frequency)
+        };
+        (0..width)
+            .map(|column| {
+                let (start, end) = (edge(column), edge(column + 1));
+                if end <= 0.0 || start >= bins {
+                    return None;
+                }
+                // Edges a rounding error past a bin boundary stay out of the bin
+                let start = (start + 1e-6).floor().max(0.0) as usize;
+                let end = ((end - 1e-6).ceil() as usize).clamp(start + 1, self.levels.len());
+                self.levels[start..end]
+                    .iter()
+                    .map(|level| level.0)
+                    .reduce(f32::max)
+            })
+            .collect()
+    }
+}
+
+/// Line plot of the latest spectrum frame, drawn above the waterfall and as
+/// wide, so the two share a frequency axis.
+///
+/// The level axis only ever widens, in whole grid steps, like the
+/// waterfall's color scale, so the trace doesn't jump as the noise moves.
+/// Reference traces freeze the running average for comparison, e.g. before
+/// and after swapping an antenna. The engine's peak and min hold traces,
+/// when on, are drawn over the latest frame. Only the part of the band the
+/// waterfall is zoomed to is drawn. Levels are each bin's power, or its
+/// power spectral density in dB/Hz, which stays put across FFT sizes and
+/// sample rates.
+pub struct SpectrumPlot {
+    cmd_tx: Sender<Command>,
+    latest: Option<Trace>,
+    /// Hold traces of the latest frame, if the engine keeps them
+    peak_hold: Option<Trace>,
+    min_hold: Option<Trace>,
+    /// Whether the engine keeps each hold trace
+    peak_hold_on: bool,
+    min_hold_on: bool,
+    /// Running mean power of each bin of the latest band
+    mean_power: Vec<f32>,
+    references: [Option<Trace>; REFERENCE_COLORS.len()],
+    /// Bottom and top of the level axis
+    range: Option<(f32, f32)>,
+    /// Part of the band in view, as on the waterfall below
+    zoom: Zoom,
+    /// Whether levels are shown as power spectral density
+    density: bool,
+}
+
+impl SpectrumPlot {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            latest: None,
+            peak_hold: None,
+            min_hold: None,
+            peak_hold_on: false,
+            min_hold_on: false,
+            mean_power: Vec::new(),
+            references: Default::default(),
+            range: None,
+            zoom: Zoom::default(),
+            density: false,
+        }
+    }
+
+    /// Show which hold traces the engine keeps.
+    pub fn set_holds(&mut self, peak_hold: bool, min_hold: bool) {
+        self.peak_hold_on = peak_hold;
+        self.min_hold_on = min_hold;
+    }
+
+    /// Show `frame` as the latest, widening the level axis to fit all but
+    /// the bins marked in `excluded`.
+    pub fn insert_frame(&mut self, frame: &SpectrumFrame, excluded: &[bool]) {
+        let trace = Trace::of(frame, &frame.magnitudes);
+        self.peak_hold = frame
+            .peak_hold
+            .as_ref()
+            .map(|magnitudes| Trace::of(frame, magnitudes));
+        self.min_hold = frame
+            .min_hold
+            .as_ref()
+            .map(|magnitudes| Trace::of(frame, magnitudes));
+        let same_band = self.latest.as_ref().is_some_and(|latest| {
+            latest.band == trace.band && self.mean_power.len() == frame.magnitudes.len()
+        });
+        if same_band {
+            for (mean, magnitude) in self.mean_power.iter_mut().zip(&frame.magnitudes) {
+                *mean += (magnitude * magnitude - *mean) / AVERAGE_FRAMES;
+            }
+        } else {
+            self.mean_power = frame.magnitudes.iter().map(|m| m * m).collect();
+        }
+
+        let (mut low, mut high) = self.range.unwrap_or((f32::INFINITY, f32::NEG_INFINITY));
+        let levels = [
+            Some(&trace),
+            self.peak_hold.as_ref(),
+            self.min_hold.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|trace| trace.levels.iter().enumerate())
+        .filter(|(bin, _)| !excluded.get(*bin).copied().unwrap_or(false))
+        .map(|(_, level)| level);
+        for level in levels.filter(|level| level.0.is_finite()) {
+            low = low.min((level.0 / GRID_STEP).floor() * GRID_STEP);
+            high = high.max((level.0 / GRID_STEP).ceil() * GRID_STEP);
+        }
+        if low < high {
+            self.range = Some((low, high));
+        }
+        self.latest = Some(trace);
+    }
+
+    /// Show the part of the band `zoom` picks.
+    pub fn set_zoom(&mut self, zoom: Zoom) {
+        self.zoom = zoom;
+    }
+
+    /// Start the level axis over from the next frame, e.g. once a strong
+    /// signal that stretched it is excluded.
+    pub fn reset_range(&mut self) {
+        self.range = None;
+    }
+
+    /// Freeze the running average as the reference trace in `slot`.
+    fn store_reference(&mut self, slot: usize) {
+        let Some(latest) = &self.latest else {
+            return;
+        };
+        self.references[slot] = Some(Trace {
+            band: latest.band,
+            levels: self
+                .mean_power
+                .iter()
+                .map(|power| Decibels::from_linear(power.sqrt()))
+                .collect(),
+        });
+    }
+
+    /// Draw a cursor down the plot drawn in `rect` at `frequency`, labelled
+    /// with the level there and how far each reference trace is from it, if
+    /// it is in view.
+    pub fn mark_frequency(&self, ui: &Ui, rect: Rect, frequency: f64) {
+        let Some(latest) = &self.latest else {
+            return;
+        };
+        let Some(level) = latest.level_at(frequency) else {
+            return;
+        };
+        let level = level.0 + self.offset(latest);
+        let unit = self.unit();
+        let Some(x) =
+            Pixels::new(rect.left(), rect.width(), self.view(latest)).x_in_view(frequency)
+        else {
+            return;
+        };
+        let painter = ui.painter_at(rect);
+        painter.vline(x, rect.y_range(), Stroke::new(1.0, CURSOR_COLOR));
+        let mut pos = Pos2::new(x + 4.0, rect.top() + 4.0);
+        let font = FontId::monospace(12.0);
+        let label = painter.text(
+            pos,
+            Align2::LEFT_TOP,
+            format!("{level:.1} {unit}"),
+            font.clone(),
+            CURSOR_COLOR,
+        );
+        for (slot, reference) in self.references.iter().enumerate() {
+            let Some((reference, reference_level)) = reference
+                .as_ref()
+                .and_then(|r| Some((r, r.level_at(frequency)?)))
+            else {
+                continue;
+            };
+            let reference_level = reference_level.0 + self.offset(reference);
+            pos.y += label.height();
+            painter.text(
+                pos,
+                Align2::LEFT_TOP,
+                format!(
+                    "{}: {:.1} {unit} (Δ {:+.1})",
+                    slot + 1,
+                    reference_level,
+                    level - reference_level
+                ),
+                font.clone(),
+                REFERENCE_COLORS[slot],
+            );
+        }
+    }
+
+    /// The part in view of the band of `latest`.
+    fn view(&self, latest: &Trace) -> Band {
+        let (center, span) = self.zoom.band(latest.band.center, latest.band.span);
+        Band::new(center, span)
+    }
+
+    /// What to add to the levels of `trace` to show them.
+    fn offset(&self, trace: &Trace) -> f32 {
+        if self.density {
+            trace.density_offset()
+        } else {
+            0.0
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        if self.density { "dB/Hz" } else { "dB" }
+    }
+
+    /// Buttons storing and clearing the reference traces.
+    fn reference_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Reference:");
+            for (slot, color) in REFERENCE_COLORS.into_iter().enumerate() {
+                let stored = self.references[slot].is_some();
+                let label = if stored {
+                    format!("Clear {}", slot + 1)
+                } else {
+                    format!("Store {}", slot + 1)
+                };
+                let button = ui.button(RichText::new(label).color(color));
+                if !button.clicked() {
+                    continue;
+                }
+                if stored {
+                    self.references[slot] = None;
+                } else {
+                    self.store_reference(slot);
+                }
+            }
+            ui.separator();
+            let mut peak_hold = self.peak_hold_on;
+            ui.toggle_value(
+                &mut peak_hold,
+                RichText::new("Peak hold").color(PEAK_HOLD_COLOR),
+            )
+            .on_hover_text("Trace the highest level of each bin since the last retune");
+            if peak_hold != self.peak_hold_on {
+                let _ = self.cmd_tx.send(Command::SetPeakHold(peak_hold));
+            }
+            let mut min_hold = self.min_hold_on;
+            ui.toggle_value(
+                &mut min_hold,
+                RichText::new("Min hold").color(MIN_HOLD_COLOR),
+            )
+            .on_hover_text("Trace the lowest level of each bin since the last retune");
+            if min_hold != self.min_hold_on {
+                let _ = self.cmd_tx.send(Command::SetMinHold(min_hold));
+            }
+            ui.separator();
+            ui.toggle_value(&mut self.density, "dB/Hz").on_hover_text(
+                "Show the power spectral density, which doesn't change with the FFT size or \
+                 sample rate, rather than the power in each bin",
+            );
+        });
+    }
+}
+
+impl Widget for &mut SpectrumPlot {
+    /// Renders the reference and hold controls, then the trace and any
+    /// reference and hold traces over a level grid labelled in dB or dB/Hz,
+    /// with the resolution bandwidth, and a frequency grid labelled along
+    /// the top. The returned response is
+    /// the plot's, which senses clicks like the waterfall's, and drags to
+    /// pan the zoomed view.
+    fn ui(self, ui: &mut Ui) -> Response {
+        self.reference_ui(ui);
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(ui.available_width(), HEIGHT),
+            Sense::click_and_drag(),
+        );
+        let (Some((low, high)), Some(latest)) = (self.range, &self.latest) else {
+            return response;
+        };
+        let painter = ui.painter_at(rect);
+        // Widened to whole grid steps again once moved
+        let offset = self.offset(latest);
+        let low = ((low + offset) / GRID_STEP).floor() * GRID_STEP;
+        let high = ((high + offset) / GRID_STEP).ceil() * GRID_STEP;
+        let y_of = |level: f32| rect.bottom() - (level - low) / (high - low) * rect.height();
+        let unit = self.unit();
+
+        let mut grid = low;
+        while grid <= high {
+            let y = y_of(grid);
+            painter.hline(rect.x_range(), y, Stroke::new(1.0, GRID_COLOR));
+            painter.text(
+                Pos2::new(
+                    rect.left() + 2.0,
+                    y.clamp(rect.top() + 6.0, rect.bottom() - 6.0),
+                ),
+                Align2::LEFT_CENTER,
+                format!("{grid:.0} {unit}"),
+                FontId::monospace(10.0),
+                LABEL_COLOR,
+            );
+            grid += GRID_STEP;
+        }
+        painter.text(
+            rect.right_bottom() + Vec2::new(-2.0, -2.0),
+            Align2::RIGHT_BOTTOM,
+            format!("RBW {}", format_rbw(latest.rbw())),
+            FontId::monospace(10.0),
+            LABEL_COLOR,
+        );
+        let view = self.view(latest);
+        frequency_axis::paint(&painter, rect, view);
+
+        let bins_in_view =
+            (latest.levels.len() as f64 * view.span / latest.band.span).ceil() as usize;
+        let columns = (rect.width().round() as usize).clamp(1, bins_in_view.max(1));
+        let line = |trace: &Trace, color: Color32| {
+            let offset = self.offset(trace);
+            // A line for each run of columns the trace covers
+            let mut points = Vec::new();
+            for (column, level) in trace.column_peaks(view, columns).into_iter().enumerate() {
+                match level {
+                    Some(level) => {
+                        let level = level + offset;
+                        let x = rect.left() + (column as f32 + 0.5) / columns as f32 * rect.width();
+                        points.push(Pos2::new(x, y_of(level.clamp(low, high))));
+                    }
+                    None => {
+                        painter.add(Shape::line(
+                            std::mem::take(&mut points),
+                            Stroke::new(1.0, color),
+                        ));
+                    }
+                }
+            }
+            painter.add(Shape::line(points, Stroke::new(1.0, color)));
+        };
+        for (reference, color) in self.references.iter().zip(REFERENCE_COLORS) {
+            if let Some(reference) = reference {
+                line(reference, color);
+            }
+        }
+        if let Some(min_hold) = &self.min_hold {
+            line(min_hold, MIN_HOLD_COLOR);
+        }
+        line(latest, TRACE_COLOR);
+        if let Some(peak_hold) = &self.peak_hold {
+            line(peak_hold, PEAK_HOLD_COLOR);
+        }
+
+        response
+    }
+}
+
+/// A resolution bandwidth in Hz or kHz, to three figures.
+fn format_rbw(rbw: f64) -> String {
+    let (value, unit) = if rbw >= 1e3 {
+        (rbw / 1e3, "kHz")
+    } else {
+        (rbw, "Hz")
+    };
+    let decimals = if value >= 100.0 {
+        0
+    } else if value >= 10.0 {
+        1
+    } else {
+        2
+    };
+    format!("{value:.decimals$} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(center: f64, levels: &[f32]) -> Trace {
+        Trace {
+            band: Band::new(center, 600.0),
+            levels: levels.iter().copied().map(Decibels).collect(),
+        }
+    }
+
+    #[test]
+    fn keeps_peaks_when_narrowing() {
+        let levels = [-90.0, -20.0, -95.0, -91.0, -92.0, -93.0];
+        let view = trace(0.0, &levels);
+        let some = |levels: &[f32]| levels.iter().copied().map(Some).collect::<Vec<_>>();
+        assert_eq!(
+            view.column_peaks(Band::new(0.0, 600.0), 3),
+            some(&[-20.0, -91.0, -92.0])
+        );
+        assert_eq!(view.column_peaks(Band::new(0.0, 600.0), 6), some(&levels));
+    }
+
+    #[test]
+    fn lines_up_reference_from_another_band() {
+        // 200 Hz higher: its first four bins are the view's last four
+        let reference = trace(200.0, &[-10.0, -20.0, -30.0, -40.0, -50.0, -60.0]);
+        assert_eq!(
+            reference.column_peaks(Band::new(0.0, 600.0), 6),
+            vec![
+                None,
+                None,
+                Some(-10.0),
+                Some(-20.0),
+                Some(-30.0),
+                Some(-40.0)
+            ]
+        );
+        assert_eq!(reference.level_at(150.0), Some(Decibels(-30.0)));
+        assert_eq!(reference.level_at(-150.0), None);
+    }
+
+    #[test]
+    fn density_of_noise_holds_across_fft_sizes() {
+        // White noise of unit power is N in each of N bins at 48 kHz
+        for bins in [1_024, 4_096] {
+            let noise = Trace {
+                band: Band::new(0.0, 48_000.0),
+                levels: vec![Decibels(10.0 * (bins as f32).log10()); bins],
+            };
+            let density = noise.levels[0].0 + noise.density_offset();
+            assert!(
+                (density + 10.0 * 48_000f32.log10()).abs() < 1e-3,
+                "{density}"
+            );
+        }
+        assert_eq!(format_rbw(48_000.0 / 4_096.0), "11.7 Hz");
+        assert_eq!(format_rbw(2_000_000.0 / 1_024.0), "1.95 kHz");
+    }
+}
@@ -0,0 +1,289 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui::{
+    self, Checkbox, ColorImage, ComboBox, DragValue, Response, TextEdit, TextureHandle,
+    TextureOptions, Ui, Widget,
+};
+use flume::Sender;
+use log::{info, warn};
+
+use rustiq_messages::{AudioChannel, Command, DemodMode, Hertz, SignalRegion, SstvEvent, SstvMode};
+
+/// An image received (or being received) by the SSTV decoder.
+struct Picture {
+    mode: SstvMode,
+    /// RGB bytes, row by row
+    rgb: Vec<u8>,
+    lines: usize,
+    finished: bool,
+}
+
+impl Picture {
+    fn new(mode: SstvMode) -> Self {
+        Self {
+            mode,
+            rgb: vec![0; mode.width() * mode.height() * 3],
+            lines: 0,
+            finished: false,
+        }
+    }
+
+    fn size(&self) -> [usize; 2] {
+        [self.mode.width(), self.mode.height()]
+    }
+}
+
+/// SSTV decoder panel.
+///
+/// The widget (`ui.add(&mut panel)`) renders the channel selector, progress and
+/// save controls; `show_image()` renders the picture being received.
+pub struct SstvPanel {
+    cmd_tx: Sender<Command>,
+    /// Channel entered in the controls
+    channel: AudioChannel,
+    /// Channel the engine is currently decoding
+    active: Option<AudioChannel>,
+    /// Signal spanned by the waterfall's measurement line, if there is one
+    selection: Option<SignalRegion>,
+    picture: Option<Picture>,
+    texture: Option<TextureHandle>,
+    /// Whether `texture` is behind `picture`
+    texture_stale: bool,
+    /// Save each image once it has been fully received
+    auto_save: bool,
+    save_dir: PathBuf,
+    /// Modes the engine can demodulate
+    demod_modes: Vec<DemodMode>,
+}
+
+impl SstvPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            channel: AudioChannel {
+                frequency: Hertz(14_230_000),
+                demod: DemodMode::Usb,
+            },
+            active: None,
+            selection: None,
+            picture: None,
+            texture: None,
+            texture_stale: false,
+            auto_save: false,
+            save_dir: PathBuf::from("."),
+            demod_modes: DemodMode::ALL.to_vec(),
+        }
+    }
+
+    pub fn set_demod_modes(&mut self, demod_modes: &[DemodMode]) {
+        self.demod_modes = demod_modes.to_vec();
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SignalRegion>) {
+        self.selection = selection;
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(&mut self, sstv_decoder: Option<AudioChannel>) {
+        self.active = sstv_decoder;
+        if let Some(channel) = sstv_decoder {
+            self.channel = channel;
+        }
+    }
+
+    pub fn handle_event(&mut self, event: SstvEvent) {
+        match event {
+            SstvEvent::Started(mode) => {
+                self.picture = Some(Picture::new(mode));
+                self.texture_stale = true;
+            }
+            SstvEvent::Line { line, pixels } => {
+                let Some(picture) = &mut self.picture else {
+                    return;
+                };
+                let width = picture.mode.width();
+                if line >= picture.mode.height() || pixels.len() != width {
+                    warn!("Ignoring malformed SSTV line {}", line);
+                    return;
+                }
+                let row = &mut picture.rgb[line * width * 3..(line + 1) * width * 3];
+                row.copy_from_slice(pixels.as_flattened());
+                picture.lines = picture.lines.max(line + 1);
+                self.texture_stale = true;
+            }
+            SstvEvent::Finished => {
+                if let Some(picture) = &mut self.picture {
+                    picture.finished = true;
+                }
+                if self.auto_save {
+                    self.save();
+                }
+            }
+        }
+    }
+
+    pub fn has_image(&self) -> bool {
+        self.picture.is_some()
+    }
+
+    /// Render the picture, scaled to fit the available space.
+    pub fn show_image(&mut self, ui: &mut Ui) {
+        let Some(picture) = &self.picture else {
+            return;
+        };
+        if self.texture_stale {
+            let image = ColorImage::from_rgb(picture.size(), &picture.rgb);
+            match &mut self.texture {
+                Some(texture) => texture.set(image, TextureOptions::NEAREST),
+                None => {
+                    self.texture = Some(ui.ctx().load_texture(
+                        "sstv",
+                        image,
+                        TextureOptions::NEAREST,
+                    ));
+                }
+            }
+            self.texture_stale = false;
+        }
+        if let Some(texture) = &self.texture {
+            ui.centered_and_justified(|ui| {
+                ui.add(egui::Image::new(texture).shrink_to_fit());
+            });
+        }
+    }
+
+    fn save(&self) {
+        let Some(picture) = &self.picture else {
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let name = picture.mode.label().replace(' ', "_").to_lowercase();
+        let path = self.save_dir.join(format!("sstv_{timestamp}_{name}.png"));
+        match write_png(&path, picture) {
+            Ok(()) => info!("Saved SSTV image to {}", path.display()),
+            Err(e) => warn!("Failed to save SSTV image: {}", e),
+        }
+    }
+
+    fn send_start(&self) {
+        let _ = self.cmd_tx.send(Command::StartSstvDecoder(self.channel));
+    }
+
+    fn send_stop(&self) {
+        let _ = self.cmd_tx.send(Command::StopSstvDecoder);
+    }
+}
+
+impl Widget for &mut SstvPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("SSTV Decoder");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Frequency:");
+            let mut freq = self.channel.frequency.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                .changed()
+            {
+                self.channel.frequency.0 = freq;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Demod:");
+            ComboBox::from_id_salt("sstv_demod")
+                .selected_text(self.channel.demod.label())
+                .show_ui(ui, |ui| {
+                    for &demod in &self.demod_modes {
+                        ui.selectable_value(&mut self.channel.demod, demod, demod.label());
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            let retune = self.active.is_some_and(|active| active != self.channel);
+            let label = if retune { "Retune" } else { "Start" };
+            ui.add_enabled_ui(self.active.is_none() || retune, |ui| {
+                if ui.button(label).clicked() {
+                    self.send_start();
+                }
+            });
+            ui.add_enabled_ui(self.active.is_some(), |ui| {
+                if ui.button("Stop").clicked() {
+                    self.send_stop();
+                }
+            });
+        });
+        ui.add_enabled_ui(self.selection.is_some(), |ui| {
+            if ui
+                .button("Decode selection")
+                .on_hover_text("Tune to the signal, guessing its mode from its width")
+                .on_disabled_hover_text("Drag across a signal on the waterfall")
+                .clicked()
+                && let Some(selection) = self.selection
+            {
+                self.channel = AudioChannel::for_signal(selection);
+                self.send_start();
+            }
+        });
+
+        if let Some(picture) = &self.picture {
+            ui.add_space(5.0);
+            let status = if picture.finished {
+                "complete"
+            } else {
+                "receiving"
+            };
+            ui.label(format!(
+                "{}: {}/{} lines ({})",
+                picture.mode.label(),
+                picture.lines,
+                picture.mode.height(),
+                status
+            ));
+        } else if self.active.is_some() {
+            ui.label("Waiting for VIS code...");
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Save to:");
+            let mut dir = self.save_dir.to_string_lossy().to_string();
+            if ui
+                .add(TextEdit::singleline(&mut dir).desired_width(120.0))
+                .changed()
+            {
+                self.save_dir = PathBuf::from(dir);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(Checkbox::new(&mut self.auto_save, "Auto-save"));
+            ui.add_enabled_ui(self.picture.is_some(), |ui| {
+                if ui.button("Save now").clicked() {
+                    self.save();
+                }
+            });
+        });
+
+        ui.response()
+    }
+}
+
+fn write_png(path: &Path, picture: &Picture) -> anyhow::Result<()> {
+    let [width, height] = picture.size();
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&picture.rgb)?;
+    writer.finish()?;
+    Ok(())
+}
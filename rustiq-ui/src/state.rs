@@ -1,41 +1,545 @@
+use crate::annotation_panel::AnnotationPanel;
+use crate::antenna_panel::AntennaPanel;
+use crate::audio_panel::AudioPanel;
+use crate::burst_panel::BurstPanel;
+use crate::calibration_panel::CalibrationPanel;
+use crate::carrier_panel::CarrierPanel;
+use crate::clock_check::ClockCheck;
+use crate::close_prompt::ClosePrompt;
+use crate::colormap::Colormap;
+use crate::colormap_files::ColormapFiles;
+use crate::comparison_panel::ComparisonPanel;
+use crate::console::Console;
 use crate::control_panel::ControlPanel;
+use crate::decode_log::DecodeLog;
+use crate::diagnostics_panel::DiagnosticsPanel;
+use crate::exclusion_panel::ExclusionPanel;
+use crate::flow::FlowStats;
+use crate::impulse_panel::ImpulsePanel;
+use crate::last_session;
+use crate::macro_panel::MacroPanel;
+use crate::map_panel::MapPanel;
+use crate::meteor_panel::MeteorPanel;
+use crate::overview_panel::OverviewPanel;
+use crate::passband::PassbandOverlay;
+use crate::power::PowerSaving;
+use crate::propagation::Propagation;
+use crate::recording_panel::RecordingPanel;
+use crate::rig_panel::RigPanel;
+use crate::rotator_panel::RotatorPanel;
+use crate::scanner_panel::ScannerPanel;
+use crate::selcall_panel::SelCallPanel;
+use crate::session_prompt::SessionPrompt;
+use crate::settings_panel::{SettingsPanel, Transfer};
+use crate::spectrum_plot::SpectrumPlot;
+use crate::sstv_panel::SstvPanel;
+use crate::symbol_rate_panel::SymbolRatePanel;
+use crate::toasts::Toasts;
+use crate::tuning_panel::TuningPanel;
+use crate::update_check::UpdateCheck;
+use crate::vfo_panel::VfoPanel;
 use crate::waterfall::Waterfall;
-use flume::Sender;
+use flume::{Receiver, Sender};
 use log::trace;
-use rustiq_messages::{Command, EngineState, Event};
+use rustiq_messages::{
+    Capabilities, Command, EngineState, Event, Feature, Hertz, LastSession, SettingsBundle,
+    WindowGeometry,
+};
+use std::time::Instant;
 
 /// Local UI state derived from engine events.
 pub(super) struct UiState {
     /// Current engine state (from StateSnapshot)
     pub engine_state: Option<EngineState>,
 
+    /// What the engine supports, once it has said
+    pub capabilities: Option<Capabilities>,
+
+    /// Line plot of the latest spectrum, above the waterfall
+    pub spectrum_plot: SpectrumPlot,
+
     /// Waterfall widget state
     pub waterfall: Waterfall,
 
+    /// Listening channel's passband over the spectrum and waterfall
+    pub passband: PassbandOverlay,
+
+    /// Regions left out of the spectrum's and waterfall's level ranges
+    pub exclusion_panel: ExclusionPanel,
+
+    /// Marks drawn on the main waterfall by hand
+    pub annotation_panel: AnnotationPanel,
+
+    /// Stretch of the main waterfall pinned beside the live view
+    pub comparison_panel: ComparisonPanel,
+    pub colormap_files: ColormapFiles,
+
+    /// Frame rate and loss of the spectrum stream
+    pub spectrum_flow: FlowStats,
+
     /// Control panel widget state
     pub control_panel: ControlPanel,
+
+    /// Spectrum and waterfall of the second source, for a dual view
+    pub second_spectrum_plot: SpectrumPlot,
+    pub second_waterfall: Waterfall,
+
+    /// Controls for the second source
+    pub second_control_panel: ControlPanel,
+
+    /// Center frequency and gain controls
+    pub tuning_panel: TuningPanel,
+
+    /// Channel played on the audio output
+    pub audio_panel: AudioPanel,
+
+    /// Recording of the live IQ stream
+    pub recording_panel: RecordingPanel,
+
+    /// External antenna switch controls
+    pub antenna_panel: AntennaPanel,
+
+    /// Transceiver panadapter controls
+    pub rig_panel: RigPanel,
+
+    /// Antenna rotator controls
+    pub rotator_panel: RotatorPanel,
+
+    /// Carrier measurement panel state
+    pub carrier_panel: CarrierPanel,
+
+    /// Burst detection panel state
+    pub burst_panel: BurstPanel,
+
+    /// Meteor scatter detection panel state
+    pub meteor_panel: MeteorPanel,
+
+    /// Impulse counter panel state
+    pub impulse_panel: ImpulsePanel,
+
+    /// Symbol rate estimation panel state
+    pub symbol_rate_panel: SymbolRatePanel,
+
+    /// SSTV decoder panel state
+    pub sstv_panel: SstvPanel,
+
+    /// SelCall decoder panel state
+    pub scanner_panel: ScannerPanel,
+    pub selcall_panel: SelCallPanel,
+
+    /// VFOs beside the listening channel
+    pub vfo_panel: VfoPanel,
+
+    /// AIS/ADS-B decoder controls and map
+    pub map_panel: MapPanel,
+
+    /// Spectrogram of the whole recording being played
+    pub overview_panel: OverviewPanel,
+
+    /// Self test of the receive chain
+    pub diagnostics_panel: DiagnosticsPanel,
+
+    /// Flatness correction of the spectrum
+    pub calibration_panel: CalibrationPanel,
+
+    /// Export and import of the station's settings
+    pub settings_panel: SettingsPanel,
+
+    /// Messages from all decoders
+    pub decode_log: DecodeLog,
+
+    /// Offer to restore a crashed session
+    pub session_prompt: SessionPrompt,
+
+    /// Errors the engine reported
+    pub toasts: Toasts,
+
+    /// Opt-in check for a newer release
+    pub update_check: UpdateCheck,
+
+    /// Opt-in check of the system clock's sync
+    pub clock_check: ClockCheck,
+
+    /// Opt-in fetch of the solar indices, labelling the HF bands
+    pub propagation: Propagation,
+
+    /// Repaint rate for the window's state
+    pub power_saving: PowerSaving,
+
+    /// Confirmation before the window closes
+    pub close_prompt: ClosePrompt,
+
+    /// Typed commands to the engine
+    pub console: Console,
+
+    /// Recorded sequences of commands, replayed on demand
+    pub macro_panel: MacroPanel,
+
+    /// Journal of the commands the panels give, passed on to the engine
+    /// once a frame so macros can record them on the way
+    journal: Receiver<Command>,
+    cmd_tx: Sender<Command>,
+    engine_tx: Sender<Command>,
 }
 
 impl UiState {
-    pub fn new(cmd_tx: Sender<Command>) -> Self {
+    pub fn new(engine_tx: Sender<Command>) -> Self {
+        let (cmd_tx, journal) = flume::unbounded();
         Self {
             engine_state: None,
+            capabilities: None,
+            spectrum_plot: SpectrumPlot::new(cmd_tx.clone()),
             waterfall: Waterfall::new(),
-            control_panel: ControlPanel::new(cmd_tx),
+            passband: PassbandOverlay::new(),
+            exclusion_panel: ExclusionPanel::new(),
+            annotation_panel: AnnotationPanel::new(),
+            comparison_panel: ComparisonPanel::new(),
+            colormap_files: ColormapFiles::new(),
+            spectrum_flow: FlowStats::new(),
+            control_panel: ControlPanel::new(cmd_tx.clone()),
+            second_spectrum_plot: SpectrumPlot::new(cmd_tx.clone()),
+            second_waterfall: Waterfall::new(),
+            second_control_panel: ControlPanel::second(cmd_tx.clone()),
+            tuning_panel: TuningPanel::new(cmd_tx.clone()),
+            audio_panel: AudioPanel::new(cmd_tx.clone()),
+            recording_panel: RecordingPanel::new(cmd_tx.clone()),
+            antenna_panel: AntennaPanel::new(cmd_tx.clone()),
+            rig_panel: RigPanel::new(cmd_tx.clone()),
+            rotator_panel: RotatorPanel::new(cmd_tx.clone()),
+            carrier_panel: CarrierPanel::new(cmd_tx.clone()),
+            burst_panel: BurstPanel::new(cmd_tx.clone()),
+            meteor_panel: MeteorPanel::new(cmd_tx.clone()),
+            impulse_panel: ImpulsePanel::new(cmd_tx.clone()),
+            symbol_rate_panel: SymbolRatePanel::new(cmd_tx.clone()),
+            sstv_panel: SstvPanel::new(cmd_tx.clone()),
+            scanner_panel: ScannerPanel::new(cmd_tx.clone()),
+            selcall_panel: SelCallPanel::new(cmd_tx.clone()),
+            vfo_panel: VfoPanel::new(cmd_tx.clone()),
+            map_panel: MapPanel::new(cmd_tx.clone()),
+            overview_panel: OverviewPanel::new(cmd_tx.clone()),
+            diagnostics_panel: DiagnosticsPanel::new(cmd_tx.clone()),
+            calibration_panel: CalibrationPanel::new(cmd_tx.clone()),
+            settings_panel: SettingsPanel::new(cmd_tx.clone()),
+            decode_log: DecodeLog::new(),
+            session_prompt: SessionPrompt::new(cmd_tx.clone()),
+            toasts: Toasts::new(),
+            update_check: UpdateCheck::new(),
+            clock_check: ClockCheck::new(),
+            propagation: Propagation::new(),
+            power_saving: PowerSaving::new(),
+            close_prompt: ClosePrompt::new(),
+            console: Console::new(cmd_tx.clone()),
+            macro_panel: MacroPanel::new(engine_tx.clone()),
+            journal,
+            cmd_tx,
+            engine_tx,
         }
     }
 
+    /// Send the engine the commands given since the last call, in order,
+    /// recording them into a macro if one is being recorded.
+    pub fn forward_commands(&mut self) {
+        for command in self.journal.try_iter() {
+            self.macro_panel.record(&command);
+            let _ = self.engine_tx.send(command);
+        }
+    }
+
+    /// Send commands to `engine_tx` from now on, for an engine just
+    /// connected to, and wait to hear its state and capabilities.
+    pub fn switch_engine(&mut self, engine_tx: Sender<Command>) {
+        self.macro_panel.set_engine(engine_tx.clone());
+        self.engine_tx = engine_tx;
+        self.engine_state = None;
+        self.capabilities = None;
+    }
+
+    /// Whether to offer `feature`. An engine that hasn't announced its
+    /// capabilities is assumed to support everything.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.capabilities
+            .as_ref()
+            .is_none_or(|capabilities| capabilities.supports(feature))
+    }
+
     pub fn handle_event(&mut self, event: Event) {
         trace!("UI received event: {:.50}", format!("{:?}", event));
         match event {
             Event::StateSnapshot(state) => {
                 self.control_panel
-                    .update_from_engine_state(&state.source_config);
-                self.engine_state = Some(state);
+                    .update_from_engine_state(Some(&state.source_config));
+                self.control_panel.set_fft_size(state.fft_size);
+                self.control_panel.set_decimation(state.decimation);
+                self.control_panel
+                    .set_frequency_offset(state.frequency_offset);
+                self.control_panel.set_playback_speed(state.playback_speed);
+                self.overview_panel.update_from_engine_state(
+                    &state.source_config,
+                    state.playback_start,
+                    state.playback_paused,
+                );
+                self.control_panel.set_averaging(state.averaging);
+                self.control_panel.set_offset_tuning(state.offset_tuning);
+                self.control_panel
+                    .set_device_sample_rate(state.device_sample_rate);
+                self.calibration_panel
+                    .update_from_engine_state(&state.calibration);
+                self.second_control_panel
+                    .update_from_engine_state(state.second_source.as_ref());
+                if state.second_source.is_none() {
+                    self.second_spectrum_plot = SpectrumPlot::new(self.cmd_tx.clone());
+                    self.second_waterfall = Waterfall::new();
+                }
+                self.spectrum_plot
+                    .set_holds(state.peak_hold, state.min_hold);
+                self.second_spectrum_plot
+                    .set_holds(state.peak_hold, state.min_hold);
+                self.tuning_panel.update_from_engine_state(
+                    state.center_frequency,
+                    state.gain,
+                    &state.gain_profiles,
+                );
+                self.antenna_panel.update_from_engine_state(
+                    state.antenna_switch.as_ref(),
+                    state.antenna,
+                    state.dark_frame,
+                );
+                self.rig_panel
+                    .update_from_engine_state(state.rig.as_ref(), state.center_frequency);
+                self.rotator_panel
+                    .update_from_engine_state(state.rotator.as_ref());
+                self.carrier_panel
+                    .update_from_engine_state(state.carrier_measurement);
+                self.burst_panel
+                    .update_from_engine_state(state.burst_detection);
+                self.meteor_panel
+                    .update_from_engine_state(state.meteor_detection);
+                self.impulse_panel
+                    .update_from_engine_state(state.impulse_counter);
+                self.sstv_panel.update_from_engine_state(state.sstv_decoder);
+                self.audio_panel.update_from_engine_state(
+                    state.demodulator,
+                    &state.audio_routing,
+                    state.squelch,
+                    state.passband,
+                );
+                self.scanner_panel
+                    .update_from_engine_state(state.scan.as_ref());
+                self.vfo_panel.update_from_engine_state(
+                    &state.vfos,
+                    state.demodulator,
+                    state.center_frequency,
+                );
+                self.map_panel
+                    .update_from_engine_state(state.ais_decoder, state.adsb_decoder);
+                self.engine_state = Some(*state);
+            }
+            Event::SpectrumData(frame) if frame.source == 0 => {
+                self.spectrum_flow
+                    .record(frame.sequence, frame.sample_time, Instant::now());
+                let excluded = self.exclusion_panel.excluded_bins(&frame);
+                self.spectrum_plot.insert_frame(&frame, &excluded);
+                self.waterfall.insert_frame(&frame, &excluded);
+            }
+            Event::SpectrumData(frame) => {
+                let excluded = self.exclusion_panel.excluded_bins(&frame);
+                self.second_spectrum_plot.insert_frame(&frame, &excluded);
+                self.second_waterfall.insert_frame(&frame, &excluded);
+            }
+            Event::CarrierMeasurement(measurement) => {
+                self.carrier_panel.insert_measurement(measurement);
+            }
+            Event::Burst(burst) => {
+                self.burst_panel.insert_burst(burst);
+            }
+            Event::MeteorPing(ping) => {
+                let text = format!(
+                    "Ping {:.0} ms, {}",
+                    ping.duration.as_secs_f64() * 1e3,
+                    ping.peak_snr
+                );
+                self.meteor_panel.insert_ping(ping);
+                self.decode_log.record("Meteor", text, false);
+            }
+            Event::Impulse(impulse) => {
+                self.impulse_panel.insert_impulse(impulse);
+            }
+            Event::Sstv(event) => {
+                self.sstv_panel.handle_event(event);
+            }
+            Event::AudioChunk(chunk) => {
+                self.audio_panel.play(&chunk);
+            }
+            Event::SelCall(call) => {
+                let decoder = format!("SelCall {}", call.standard.label());
+                let id = call.id.clone();
+                let alert = self.selcall_panel.insert_call(call);
+                self.decode_log.record(decoder, id, alert);
+            }
+            Event::RotatorPosition(position) => {
+                self.rotator_panel.set_position(position);
+            }
+            Event::PreviousSession(session) => {
+                self.session_prompt.offer(session);
+            }
+            Event::Capabilities(capabilities) => {
+                self.control_panel.set_sources(&capabilities.sources);
+                self.sstv_panel.set_demod_modes(&capabilities.demod_modes);
+                self.audio_panel.set_demod_modes(&capabilities.demod_modes);
+                self.vfo_panel.set_demod_modes(&capabilities.demod_modes);
+                self.selcall_panel
+                    .set_demod_modes(&capabilities.demod_modes);
+                self.capabilities = Some(capabilities);
+            }
+            Event::RecordingStatus(status) => {
+                self.recording_panel.set_status(status);
+            }
+            // The connection expands these into `SpectrumData` on the way in
+            Event::ReducedSpectrum(_) => {}
+            Event::SelfTest(report) => {
+                self.diagnostics_panel.set_report(report);
+            }
+            Event::RecordingOverview(overview) => {
+                self.overview_panel.set_overview(overview);
+            }
+            Event::PlaybackPosition(position) => {
+                self.overview_panel.set_position(position);
+            }
+            Event::Error(error) => {
+                self.toasts.push(error);
+            }
+            Event::RdsData(data) => {
+                self.audio_panel.set_rds(data);
+            }
+            Event::DarkFrameCaptured(level) => {
+                self.antenna_panel.set_dark_frame_level(level);
+            }
+            Event::Stats {
+                frames_dropped,
+                fps,
+            } => {
+                self.spectrum_flow.set_engine_stats(frames_dropped, fps);
+            }
+            Event::SquelchState(open) => {
+                self.audio_panel.set_squelch_open(open);
             }
-            Event::SpectrumData(data) => {
-                self.waterfall.insert_spectrum_line(&data);
+            Event::VfoSquelch { id, open } => {
+                self.vfo_panel.set_squelch_open(id, open);
             }
+            Event::Scanning { channel, active } => {
+                self.scanner_panel.set_status(channel, active);
+            }
+            Event::ScanHit(hit) => {
+                let class = hit.class.map_or("too short to tell", |class| class.label());
+                // No commas, to keep to the log file's columns
+                let text = format!(
+                    "{} {:.6} MHz {} for {:.1} s at {}: {}",
+                    hit.name,
+                    hit.frequency.as_hz() as f64 / 1e6,
+                    hit.demod.label(),
+                    hit.duration.as_secs_f64(),
+                    hit.level,
+                    class
+                );
+                self.decode_log.record("Scan", text, false);
+            }
+            Event::SymbolRate(estimate) => {
+                self.symbol_rate_panel.set_estimate(estimate);
+            }
+            Event::Track(report) => {
+                let (decoder, id) = (report.kind.label(), report.id.clone());
+                if self.map_panel.insert_report(report) {
+                    self.decode_log
+                        .record(decoder, format!("New target {id}"), false);
+                }
+            }
+        }
+    }
+
+    /// Start the level ranges over once the excluded regions change, so
+    /// a signal just excluded stops stretching them.
+    pub fn apply_exclusions(&mut self) {
+        if self.exclusion_panel.take_changed() {
+            self.spectrum_plot.reset_range();
+            self.waterfall.reset_range();
+            self.second_spectrum_plot.reset_range();
+            self.second_waterfall.reset_range();
+        }
+    }
+
+    /// What the app is set to, to put back at the next run, once the engine
+    /// has said what it is doing.
+    pub fn last_session(&self, window: Option<WindowGeometry>) -> Option<LastSession> {
+        let state = self.engine_state.as_ref()?;
+        Some(LastSession {
+            source_config: state.source_config.clone(),
+            center_frequency: state.center_frequency,
+            gain: state.gain,
+            fft_size: state.fft_size,
+            listening: state.demodulator,
+            squelch: state.squelch,
+            passband: state.passband,
+            volume: self.audio_panel.volume(),
+            colormap: self.waterfall.colormap().label().to_string(),
+            window,
+            annotations: self.annotation_panel.annotations().to_vec(),
+        })
+    }
+
+    /// Put back the UI's part of the last session.
+    pub fn restore_view(&mut self, session: &LastSession) {
+        if let Some(colormap) = Colormap::ALL
+            .into_iter()
+            .chain(self.colormap_files.loaded().iter().cloned())
+            .find(|colormap| colormap.label() == session.colormap)
+        {
+            self.waterfall.set_colormap(colormap);
+        }
+        self.audio_panel.set_volume(session.volume);
+        self.annotation_panel
+            .set_annotations(session.annotations.clone());
+    }
+
+    /// Put the engine back as the last session left it.
+    pub fn restore_engine(&self, session: &LastSession) {
+        for command in last_session::commands(session) {
+            let _ = self.cmd_tx.send(command);
+        }
+    }
+
+    /// Carry out an export or import asked for in the settings panel.
+    pub fn transfer_settings(&mut self) {
+        match self.settings_panel.take_transfer() {
+            Some(Transfer::Export) => {
+                let bundle = self.engine_state.as_ref().map(|state| SettingsBundle {
+                    source_config: state.source_config.clone(),
+                    center_frequency: state.center_frequency,
+                    gain: state.gain,
+                    gain_profiles: state.gain_profiles.clone(),
+                    antenna_switch: state.antenna_switch.clone(),
+                    rig: state.rig.clone(),
+                    rotator: state.rotator.clone(),
+                    alert_rules: self.selcall_panel.alert_rules().to_vec(),
+                    scan_list: self.scanner_panel.list(),
+                });
+                self.settings_panel.export(bundle);
+            }
+            Some(Transfer::Import) => {
+                if let Some(bundle) = self.settings_panel.import() {
+                    self.selcall_panel.set_alert_rules(bundle.alert_rules);
+                    self.scanner_panel.set_list(bundle.scan_list);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Handle a click on a waterfall at `frequency`, in Hz. With a rig
+    /// connected the click tunes the rig, otherwise it retunes the source.
+    pub fn handle_waterfall_click(&mut self, frequency: f64) {
+        let frequency = Hertz(frequency.max(0.0).round() as u64);
+        if !self.rig_panel.tune_rig(frequency) {
+            self.tuning_panel.tune(frequency);
         }
     }
 }
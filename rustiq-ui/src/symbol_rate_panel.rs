@@ -0,0 +1,131 @@
+use eframe::egui::{DragValue, Grid, Response, Ui, Widget};
+use flume::Sender;
+
+use rustiq_messages::{Command, Hertz, SignalRegion, SymbolRateEstimate};
+
+/// Symbol rate estimation panel.
+///
+/// The region is entered here or taken from the line last dragged across
+/// the waterfall, which spans the signal's width. Each estimate is a one-off
+/// over a second of the stream.
+pub struct SymbolRatePanel {
+    cmd_tx: Sender<Command>,
+    /// Region entered in the controls
+    region: SignalRegion,
+    /// Region spanned by the waterfall's measurement line, if there is one
+    selection: Option<SignalRegion>,
+    /// Waiting for the engine's estimate
+    running: bool,
+    estimate: Option<SymbolRateEstimate>,
+}
+
+impl SymbolRatePanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            region: SignalRegion {
+                frequency: Hertz(10_000),
+                bandwidth: Hertz(2_400),
+            },
+            selection: None,
+            running: false,
+            estimate: None,
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: Option<SignalRegion>) {
+        self.selection = selection;
+    }
+
+    pub fn set_estimate(&mut self, estimate: SymbolRateEstimate) {
+        self.running = false;
+        self.estimate = Some(estimate);
+    }
+
+    fn send_estimate(&mut self) {
+        if self
+            .cmd_tx
+            .send(Command::EstimateSymbolRate(self.region))
+            .is_ok()
+        {
+            self.running = true;
+        }
+    }
+}
+
+impl Widget for &mut SymbolRatePanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Symbol Rate");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Frequency:");
+            let mut freq = self.region.frequency.0;
+            if ui
+                .add(DragValue::new(&mut freq).speed(100).suffix(" Hz"))
+                .changed()
+            {
+                self.region.frequency.0 = freq;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Bandwidth:");
+            let mut bandwidth = self.region.bandwidth.0;
+            if ui
+                .add(
+                    DragValue::new(&mut bandwidth)
+                        .speed(10)
+                        .range(50..=200_000)
+                        .suffix(" Hz"),
+                )
+                .changed()
+            {
+                self.region.bandwidth.0 = bandwidth;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(self.selection.is_some(), |ui| {
+                if ui
+                    .button("Use selection")
+                    .on_disabled_hover_text("Drag across a signal on the waterfall")
+                    .clicked()
+                    && let Some(selection) = self.selection
+                {
+                    self.region = selection;
+                }
+            });
+            ui.add_enabled_ui(!self.running, |ui| {
+                if ui.button("Estimate").clicked() {
+                    self.send_estimate();
+                }
+            });
+            if self.running {
+                ui.spinner();
+            }
+        });
+
+        if let Some(estimate) = &self.estimate {
+            ui.add_space(5.0);
+            ui.label(format!(
+                "{} wide at {}",
+                estimate.region.bandwidth, estimate.region.frequency
+            ));
+            if estimate.candidates.is_empty() {
+                ui.label("No symbol rate found");
+            }
+            Grid::new("symbol_rate_candidates")
+                .striped(true)
+                .show(ui, |ui| {
+                    for candidate in &estimate.candidates {
+                        ui.monospace(format!("{:.1} Bd", candidate.rate));
+                        ui.label(candidate.strength.to_string());
+                        ui.end_row();
+                    }
+                });
+        }
+
+        ui.response()
+    }
+}
@@ -0,0 +1,135 @@
+//! Times of day down the left edge of the waterfall, so bursts can be lined
+//! up with the wall clock. Times are UTC, as in logs and schedules.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Stroke};
+use rustiq_messages::UtcTime;
+
+/// Least room between labels, in points. The number of labels follows the
+/// height, so resizing the window thins them out or fills them in.
+const LABEL_SPACING: f32 = 60.0;
+
+/// Room left at the top for the frequency labels, in points.
+const TOP_MARGIN: f32 = 20.0;
+
+/// Length of the tick marks, in points.
+const TICK_LENGTH: f32 = 5.0;
+
+const TICK_COLOR: Color32 = Color32::LIGHT_GRAY;
+const LABEL_COLOR: Color32 = Color32::LIGHT_GRAY;
+
+/// Steps the labels go in, in seconds: round numbers of seconds, minutes
+/// and hours.
+const STEPS: [u64; 17] = [
+    1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1_800, 3_600, 7_200, 10_800, 21_600, 43_200,
+];
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// The smallest step that puts no more than `max_labels` labels across
+/// `span` seconds.
+fn step(span: u64, max_labels: usize) -> u64 {
+    let max_labels = max_labels.max(1) as u64;
+    STEPS
+        .into_iter()
+        .find(|step| span / step < max_labels)
+        .unwrap_or(STEPS[STEPS.len() - 1])
+}
+
+/// Where a multiple of `step` seconds falls between lines taken at
+/// `times`, newest first: the index of the line just after it, and the
+/// time it marks.
+fn ticks(times: &[SystemTime], step: u64) -> Vec<(usize, u64)> {
+    times
+        .windows(2)
+        .enumerate()
+        .filter_map(|(index, pair)| {
+            let (newer, older) = (seconds(pair[0]) / step, seconds(pair[1]) / step);
+            (newer > older).then_some((index + 1, newer * step))
+        })
+        .collect()
+}
+
+/// `time` as hours, minutes and, for steps under a minute, seconds.
+fn label(time: u64, step: u64) -> String {
+    let time = UtcTime::from(UNIX_EPOCH + std::time::Duration::from_secs(time));
+    if step < 60 {
+        format!("{:02}:{:02}:{:02}", time.hour, time.minute, time.second)
+    } else {
+        format!("{:02}:{:02}", time.hour, time.minute)
+    }
+}
+
+/// Paint ticks and times of day along the left of `rect`, which shows
+/// lines taken at `times`, newest first at the top.
+pub fn paint(painter: &Painter, rect: Rect, times: &[SystemTime]) {
+    let (Some(&newest), Some(&oldest)) = (times.first(), times.last()) else {
+        return;
+    };
+    let span = seconds(newest).saturating_sub(seconds(oldest));
+    let step = step(span, (rect.height() / LABEL_SPACING) as usize);
+    let font = FontId::monospace(10.0);
+    let mut last_y = rect.top() + TOP_MARGIN - LABEL_SPACING / 2.0;
+    for (index, time) in ticks(times, step) {
+        let y = rect.top() + index as f32 / times.len() as f32 * rect.height();
+        // Lines bunch up where the stream paused
+        if y - last_y < LABEL_SPACING / 2.0 {
+            continue;
+        }
+        last_y = y;
+        painter.hline(
+            rect.left()..=rect.left() + TICK_LENGTH,
+            y,
+            Stroke::new(1.0, TICK_COLOR),
+        );
+        painter.text(
+            Pos2::new(rect.left() + TICK_LENGTH + 2.0, y),
+            Align2::LEFT_CENTER,
+            label(time, step),
+            font.clone(),
+            LABEL_COLOR,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: f64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs_f64(seconds)
+    }
+
+    #[test]
+    fn steps_in_round_seconds_minutes_and_hours() {
+        assert_eq!(step(0, 10), 1);
+        assert_eq!(step(9, 10), 1);
+        assert_eq!(step(10, 10), 2);
+        assert_eq!(step(100, 10), 15);
+        assert_eq!(step(3_000, 10), 600);
+        assert_eq!(step(1_000_000, 10), 43_200);
+        assert_eq!(step(10, 0), 15);
+    }
+
+    #[test]
+    fn ticks_fall_between_the_lines_either_side() {
+        // Four lines a second, newest first, from 12:00:00.5 back
+        let times: Vec<SystemTime> = (0..12)
+            .map(|line| at(43_200.5 - line as f64 * 0.25))
+            .collect();
+        // 12:00:00 falls after the third line, 11:59:59 after the seventh
+        assert_eq!(
+            ticks(&times, 1),
+            vec![(3, 43_200), (7, 43_199), (11, 43_198)]
+        );
+        assert_eq!(ticks(&times, 2), vec![(3, 43_200), (11, 43_198)]);
+        assert_eq!(label(43_200, 1), "12:00:00");
+        assert_eq!(label(43_260, 60), "12:01");
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui::{Align2, Area, Color32, Context, Frame, Id, RichText};
+
+use rustiq_messages::RustIqError;
+
+/// How long a notice stays up unless closed sooner.
+const SHOWN_FOR: Duration = Duration::from_secs(10);
+
+/// Most notices shown at once; older ones give way.
+const MAX_SHOWN: usize = 4;
+
+/// Errors the engine reported, stacked in the bottom-right corner with what
+/// to check, until they expire or are closed.
+pub struct Toasts {
+    /// Errors shown, oldest first, with when each arrived
+    shown: Vec<(RustIqError, Instant)>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self { shown: Vec::new() }
+    }
+
+    pub fn push(&mut self, error: RustIqError) {
+        // The same error again only restarts its time
+        self.shown.retain(|(shown, _)| *shown != error);
+        self.shown.push((error, Instant::now()));
+        if self.shown.len() > MAX_SHOWN {
+            self.shown.remove(0);
+        }
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        self.shown
+            .retain(|(_, arrived)| arrived.elapsed() < SHOWN_FOR);
+        let Some(first) = self.shown.first() else {
+            return;
+        };
+        // Repaint when the oldest one is due to go
+        ctx.request_repaint_after(SHOWN_FOR.saturating_sub(first.1.elapsed()));
+
+        let mut closed = None;
+        Area::new(Id::new("error_toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+            .show(ctx, |ui| {
+                for (i, (error, _)) in self.shown.iter().enumerate().rev() {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(error.to_string()).color(Color32::LIGHT_RED));
+                            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                                closed = Some(i);
+                            }
+                        });
+                        ui.weak(error.hint());
+                    });
+                }
+            });
+        if let Some(i) = closed {
+            self.shown.remove(i);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use eframe::egui::{
+    Checkbox, CollapsingHeader, Color32, ComboBox, DragValue, Grid, Key, Response, TextEdit, Ui,
+    Widget,
+};
+use flume::Sender;
+
+use crate::frequency::{FrequencyFormat, FrequencyUnit};
+use crate::schedule::ScheduleLookup;
+use rustiq_messages::{
+    ChannelPlan, ChannelRegion, Command, Decibels, FrequencyRange, GainProfile, Hertz,
+};
+
+/// Width of the profile created by "Add for current frequency", either side
+/// of the center frequency.
+const NEW_PROFILE_SPAN: Hertz = Hertz::mhz(1);
+
+/// Center frequency and gain controls, with gain profiles the engine applies
+/// automatically when tuning into their frequency ranges, and the
+/// broadcasts scheduled at the frequency tuned to.
+pub struct TuningPanel {
+    cmd_tx: Sender<Command>,
+    /// Center frequency as typed, in `format`
+    entry: String,
+    /// How frequencies are shown and typed
+    format: FrequencyFormat,
+    /// Gain entered in the controls
+    gain: Decibels,
+    /// Profiles being edited
+    profiles: Vec<GainProfile>,
+    /// Values the engine is currently using
+    active_frequency: Hertz,
+    active_gain: Decibels,
+    active_profiles: Vec<GainProfile>,
+    schedule: ScheduleLookup,
+}
+
+impl TuningPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            entry: FrequencyFormat::default().format(Hertz(0)),
+            format: FrequencyFormat::default(),
+            gain: Decibels(0.0),
+            profiles: Vec::new(),
+            active_frequency: Hertz(0),
+            active_gain: Decibels(0.0),
+            active_profiles: Vec::new(),
+            schedule: ScheduleLookup::new(),
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(
+        &mut self,
+        center_frequency: Hertz,
+        gain: Decibels,
+        gain_profiles: &[GainProfile],
+    ) {
+        self.entry = self.format.format(center_frequency);
+        self.gain = gain;
+        self.profiles = gain_profiles.to_vec();
+        self.active_frequency = center_frequency;
+        self.active_gain = gain;
+        self.active_profiles = gain_profiles.to_vec();
+    }
+
+    /// Retune the source to `frequency`, as from a click on the waterfall,
+    /// or to the channel nearest it when snapping to channels.
+    pub fn tune(&mut self, frequency: Hertz) {
+        let frequency = self.format.snap(frequency);
+        self.entry = self.format.format(frequency);
+        self.send_tune(frequency);
+    }
+
+    /// The broadcast schedule, to look up other frequencies in.
+    pub fn schedule(&self) -> &ScheduleLookup {
+        &self.schedule
+    }
+
+    fn send_tune(&self, frequency: Hertz) {
+        let _ = self.cmd_tx.send(Command::SetCenterFrequency(frequency));
+    }
+
+    /// Unit, decimals and channel plan frequencies are shown in, with the
+    /// plans offered for a region.
+    fn format_ui(&mut self, ui: &mut Ui) {
+        let mut format = self.format;
+        ui.horizontal(|ui| {
+            ui.label("Show in:");
+            ComboBox::from_id_salt("frequency_unit")
+                .selected_text(format.unit.label())
+                .show_ui(ui, |ui| {
+                    for unit in FrequencyUnit::ALL {
+                        ui.selectable_value(&mut format.unit, unit, unit.label());
+                    }
+                });
+            ui.add_enabled(
+                format.unit != FrequencyUnit::Hz,
+                DragValue::new(&mut format.decimals)
+                    .range(0..=6)
+                    .suffix(" decimals"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Region:");
+            ComboBox::from_id_salt("channel_region")
+                .selected_text(format.region.label())
+                .show_ui(ui, |ui| {
+                    for region in ChannelRegion::ALL {
+                        ui.selectable_value(&mut format.region, region, region.label());
+                    }
+                });
+        });
+        if format
+            .channels
+            .is_some_and(|plan| !format.region.plans().contains(&plan))
+        {
+            format.channels = None;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Channels:");
+            ComboBox::from_id_salt("channel_plan")
+                .selected_text(format.channels.map_or("None", ChannelPlan::description))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut format.channels, None, "None");
+                    for &plan in format.region.plans() {
+                        ui.selectable_value(&mut format.channels, Some(plan), plan.description());
+                    }
+                });
+        });
+        ui.add_enabled(
+            format.channels.is_some(),
+            Checkbox::new(&mut format.snap, "Snap to channels"),
+        );
+        if format != self.format {
+            // Show what was typed in the new format, if it can be read
+            if let Ok(frequency) = self.format.parse(&self.entry) {
+                self.entry = format.format(frequency);
+            }
+            self.format = format;
+        }
+    }
+
+    fn send_gain(&self) {
+        let _ = self.cmd_tx.send(Command::SetGain(self.gain));
+    }
+
+    fn send_profiles(&self) {
+        let _ = self
+            .cmd_tx
+            .send(Command::SetGainProfiles(self.profiles.clone()));
+    }
+
+    fn profiles_ui(&mut self, ui: &mut Ui) {
+        let mut remove = None;
+        Grid::new("gain_profiles").striped(true).show(ui, |ui| {
+            ui.label("From");
+            ui.label("To");
+            ui.label("Gain");
+            ui.end_row();
+            for (i, profile) in self.profiles.iter_mut().enumerate() {
+                ui.add(
+                    DragValue::new(&mut profile.range.start.0)
+                        .speed(1000)
+                        .suffix(" Hz"),
+                );
+                ui.add(
+                    DragValue::new(&mut profile.range.end.0)
+                        .speed(1000)
+                        .suffix(" Hz"),
+                );
+                ui.add(
+                    DragValue::new(&mut profile.gain.0)
+                        .speed(0.5)
+                        .range(-60.0..=60.0)
+                        .suffix(" dB"),
+                );
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.profiles.remove(i);
+        }
+    }
+}
+
+impl Widget for &mut TuningPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("Tuning");
+        ui.separator();
+
+        let entered = self
+            .format
+            .parse(&self.entry)
+            .map(|frequency| self.format.snap(frequency));
+        ui.horizontal(|ui| {
+            ui.label("Center:");
+            let response = ui.add(TextEdit::singleline(&mut self.entry).desired_width(130.0));
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            let changed = entered
+                .as_ref()
+                .is_ok_and(|&frequency| frequency != self.active_frequency);
+            ui.add_enabled_ui(changed, |ui| {
+                let clicked = ui.button("Tune").clicked();
+                if let Ok(frequency) = entered
+                    && (clicked || submitted && changed)
+                {
+                    self.entry = self.format.format(frequency);
+                    self.send_tune(frequency);
+                }
+            });
+            ui.menu_button("⚙", |ui| self.format_ui(ui))
+                .response
+                .on_hover_text("How frequencies are shown");
+        });
+        if let Err(e) = &entered {
+            ui.colored_label(Color32::LIGHT_RED, e);
+        } else {
+            // Tuned to a channel, the frequency it is on and what it's for
+            if let Some(channel) = self.format.channel(self.active_frequency) {
+                let in_unit = self.format.format_in_unit(self.active_frequency);
+                match channel.purpose {
+                    Some(purpose) => ui.weak(format!("{in_unit}, {purpose}")),
+                    None => ui.weak(in_unit),
+                };
+            }
+            self.schedule
+                .show_on_air(ui, self.active_frequency.as_hz() as f64);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Gain:");
+            ui.add(
+                DragValue::new(&mut self.gain.0)
+                    .speed(0.5)
+                    .range(-60.0..=60.0)
+                    .suffix(" dB"),
+            );
+            ui.add_enabled_ui(self.gain != self.active_gain, |ui| {
+                if ui.button("Set").clicked() {
+                    self.send_gain();
+                }
+            });
+        });
+
+        ui.add_space(5.0);
+        ui.label("Gain profiles:");
+        self.profiles_ui(ui);
+
+        ui.horizontal(|ui| {
+            if ui.button("Add for current frequency").clicked() {
+                let center = self.active_frequency.0;
+                self.profiles.push(GainProfile {
+                    range: FrequencyRange::new(
+                        Hertz(center.saturating_sub(NEW_PROFILE_SPAN.0)),
+                        Hertz(center + NEW_PROFILE_SPAN.0),
+                    ),
+                    gain: self.active_gain,
+                });
+            }
+            ui.add_enabled_ui(self.profiles != self.active_profiles, |ui| {
+                if ui.button("Save").clicked() {
+                    self.send_profiles();
+                }
+            });
+        });
+
+        CollapsingHeader::new("Broadcast schedule")
+            .id_salt("broadcast_schedule")
+            .show(ui, |ui| self.schedule.settings_ui(ui));
+
+        ui.response()
+    }
+}
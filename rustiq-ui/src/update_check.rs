@@ -0,0 +1,226 @@
+use std::thread;
+
+use eframe::egui::{Checkbox, Color32, TextEdit, Ui};
+use flume::Receiver;
+use log::{debug, warn};
+
+use crate::http;
+
+/// Atom feed of the project's releases.
+const DEFAULT_FEED: &str = "https://github.com/B1tWhys/RustIQ/releases.atom";
+
+/// Version of this build, compared against the feed.
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A release listed in the feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Release {
+    version: [u64; 3],
+    /// Release page
+    link: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum UpdateStatus {
+    Off,
+    Checking,
+    UpToDate,
+    Available(Release),
+    Failed(String),
+}
+
+/// Opt-in check for a newer release, shown in the status bar.
+pub struct UpdateCheck {
+    /// Whether the user opted in
+    enabled: bool,
+    feed: String,
+    status: UpdateStatus,
+    /// Result of the check running in the background
+    result_rx: Option<Receiver<Result<Option<Release>, String>>>,
+}
+
+impl UpdateCheck {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            feed: DEFAULT_FEED.to_string(),
+            status: UpdateStatus::Off,
+            result_rx: None,
+        }
+    }
+
+    /// Check the feed on a background thread.
+    fn start(&mut self) {
+        let (result_tx, result_rx) = flume::bounded(1);
+        let feed = self.feed.clone();
+        thread::spawn(move || {
+            let result = check(&feed);
+            debug!("Update check of {} returned {:?}", feed, result);
+            let _ = result_tx.send(result);
+        });
+        self.result_rx = Some(result_rx);
+        self.status = UpdateStatus::Checking;
+    }
+
+    /// Pick up the result of a running check.
+    pub fn poll(&mut self) {
+        let Some(result) = self.result_rx.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.result_rx = None;
+        if !self.enabled {
+            return;
+        }
+        self.status = match result {
+            Ok(Some(release)) => UpdateStatus::Available(release),
+            Ok(None) => UpdateStatus::UpToDate,
+            Err(e) => {
+                warn!("Update check failed: {}", e);
+                UpdateStatus::Failed(e)
+            }
+        };
+    }
+
+    /// Status bar contents: the opt-in toggle and the outcome of the check.
+    pub fn show(&mut self, ui: &mut Ui) {
+        let toggle = ui
+            .add(Checkbox::new(&mut self.enabled, "Check for updates"))
+            .on_hover_text("Look for a newer release in the project's release feed");
+        if toggle.changed() {
+            if self.enabled {
+                self.start();
+            } else {
+                self.status = UpdateStatus::Off;
+            }
+        }
+
+        match &self.status {
+            UpdateStatus::Off => {}
+            UpdateStatus::Checking => {
+                ui.spinner();
+            }
+            UpdateStatus::UpToDate => {
+                ui.label("Up to date");
+            }
+            UpdateStatus::Available(release) => {
+                let [major, minor, patch] = release.version;
+                ui.hyperlink_to(
+                    format!("New version available: {major}.{minor}.{patch}"),
+                    &release.link,
+                );
+            }
+            UpdateStatus::Failed(e) => {
+                ui.colored_label(Color32::LIGHT_RED, "Update check failed")
+                    .on_hover_text(e);
+                ui.add(TextEdit::singleline(&mut self.feed).desired_width(250.0))
+                    .on_hover_text("Release feed URL");
+                if ui.button("Retry").clicked() {
+                    self.start();
+                }
+            }
+        }
+    }
+}
+
+/// Fetch the feed and return its latest release if newer than this build.
+fn check(feed: &str) -> Result<Option<Release>, String> {
+    let current = parse_version(CURRENT_VERSION).ok_or("unparseable build version")?;
+    let body = http::get(feed, "application/atom+xml").map_err(|e| e.to_string())?;
+    let latest = latest_release(&body).ok_or("no releases in the feed")?;
+    Ok((latest.version > current).then_some(latest))
+}
+
+/// The newest release in an Atom feed (feeds list newest first). The version
+/// is taken from the tag at the end of the entry's link, else its title.
+fn latest_release(feed: &str) -> Option<Release> {
+    feed.split("<entry>").skip(1).find_map(|entry| {
+        let entry = &entry[..entry.find("</entry>")?];
+        let link = element_attribute(entry, "link", "href").map(unescape)?;
+        let tag = link.trim_end_matches('/').rsplit('/').next()?;
+        let version = parse_version(tag).or_else(|| {
+            let title = &entry[entry.find("<title")?..];
+            let title = &title[title.find('>')? + 1..title.find("</title>")?];
+            parse_version(&unescape(title))
+        })?;
+        Some(Release { version, link })
+    })
+}
+
+/// Value of `attribute` on the first `<element ...>` tag in `xml`.
+fn element_attribute<'a>(xml: &'a str, element: &str, attribute: &str) -> Option<&'a str> {
+    let tag = &xml[xml.find(&format!("<{element} "))?..];
+    let tag = &tag[..tag.find('>')?];
+    let value = &tag[tag.find(&format!("{attribute}=\""))? + attribute.len() + 2..];
+    Some(&value[..value.find('"')?])
+}
+
+/// Replace the predefined XML entities.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parse "v1.2.3", "1.2" or "rustiq-1.2.3-beta" into [major, minor, patch].
+fn parse_version(text: &str) -> Option<[u64; 3]> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let numbers = text[start..]
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()?;
+    let mut version = [0; 3];
+    for (part, number) in version.iter_mut().zip(numbers.split('.')) {
+        *part = number.parse().ok()?;
+    }
+    Some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Release notes from RustIQ</title>
+  <entry>
+    <title>Spring release</title>
+    <link rel="alternate" type="text/html" href="https://github.com/B1tWhys/RustIQ/releases/tag/v0.3.1"/>
+  </entry>
+  <entry>
+    <title>v0.2.0</title>
+    <link rel="alternate" type="text/html" href="https://github.com/B1tWhys/RustIQ/releases/tag/v0.2.0"/>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn finds_latest_release() {
+        assert_eq!(
+            latest_release(FEED),
+            Some(Release {
+                version: [0, 3, 1],
+                link: "https://github.com/B1tWhys/RustIQ/releases/tag/v0.3.1".to_string(),
+            })
+        );
+        assert_eq!(latest_release("<feed></feed>"), None);
+    }
+
+    #[test]
+    #[ignore = "fetches the release feed from github.com"]
+    fn checks_the_release_feed() {
+        let result = check(DEFAULT_FEED);
+        assert!(
+            result.is_ok() || result == Err("no releases in the feed".to_string()),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("v1.2.3"), Some([1, 2, 3]));
+        assert_eq!(parse_version("1.2"), Some([1, 2, 0]));
+        assert_eq!(parse_version("rustiq-0.10.0-beta.1"), Some([0, 10, 0]));
+        assert_eq!(parse_version("nightly"), None);
+        assert!(parse_version("0.10.0") > parse_version("0.9.9"));
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use eframe::egui::{
+    Align2, Color32, ComboBox, CursorIcon, DragValue, FontId, Grid, Pos2, Rect, Response, Sense,
+    Stroke, TextEdit, Ui, Widget,
+};
+use flume::Sender;
+
+use rustiq_messages::{AudioChannel, Command, Decibels, DemodMode, Hertz, Vfo, VfoConfig};
+
+use crate::waterfall::Waterfall;
+
+/// Colors the VFO markers take in turn.
+const COLORS: [Color32; 4] = [
+    Color32::from_rgb(255, 170, 60),
+    Color32::from_rgb(90, 200, 255),
+    Color32::from_rgb(230, 110, 230),
+    Color32::from_rgb(250, 240, 110),
+];
+
+/// How far either side of a marker grabs it, in points.
+const GRIP: f32 = 4.0;
+
+/// Markers snap to multiples of this many Hz.
+const STEP: f64 = 100.0;
+
+/// VFO panel: extra receivers on other channels of the source, each shown
+/// as a marker on the spectrum and waterfall that drags it along.
+pub struct VfoPanel {
+    cmd_tx: Sender<Command>,
+    /// VFOs as the engine runs them, edited in place until sent
+    vfos: Vec<Vfo>,
+    /// Where a VFO added starts: the listening channel, or the center
+    new_channel: AudioChannel,
+    demod_modes: Vec<DemodMode>,
+    /// Whether each VFO's squelch is open, as last heard
+    open: HashMap<u32, bool>,
+    /// File each VFO records to once ticked
+    recording_paths: HashMap<u32, String>,
+    /// VFO being dragged, and where to so far
+    dragged: Option<(u32, f64)>,
+}
+
+impl VfoPanel {
+    pub fn new(cmd_tx: Sender<Command>) -> Self {
+        Self {
+            cmd_tx,
+            vfos: Vec::new(),
+            new_channel: AudioChannel {
+                frequency: Hertz(100_000_000),
+                demod: DemodMode::Fm,
+            },
+            demod_modes: DemodMode::ALL.to_vec(),
+            open: HashMap::new(),
+            recording_paths: HashMap::new(),
+            dragged: None,
+        }
+    }
+
+    /// Update from engine state snapshot.
+    pub fn update_from_engine_state(
+        &mut self,
+        vfos: &[Vfo],
+        listening: Option<AudioChannel>,
+        center_frequency: Hertz,
+    ) {
+        self.vfos = vfos.to_vec();
+        self.open
+            .retain(|id, _| vfos.iter().any(|vfo| vfo.id == *id));
+        self.new_channel = listening.unwrap_or(AudioChannel {
+            frequency: center_frequency,
+            demod: self.new_channel.demod,
+        });
+    }
+
+    pub fn set_demod_modes(&mut self, demod_modes: &[DemodMode]) {
+        self.demod_modes = demod_modes.to_vec();
+    }
+
+    pub fn set_squelch_open(&mut self, id: u32, open: bool) {
+        self.open.insert(id, open);
+    }
+
+    fn send_config(&self, vfo: &Vfo) {
+        let _ = self.cmd_tx.send(Command::ConfigureVfo {
+            id: vfo.id,
+            config: vfo.config.clone(),
+        });
+    }
+
+    /// Draw each VFO's marker over the spectrum plot drawn in `plot` and
+    /// the waterfall `view` drawn in `waterfall`. A marker dragged on
+    /// either moves its VFO once the drag ends.
+    pub fn show_markers(&mut self, ui: &Ui, plot: Rect, waterfall: Rect, view: &Waterfall) {
+        let mut moved = None;
+        for (i, vfo) in self.vfos.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            let frequency = match self.dragged {
+                Some((id, frequency)) if id == vfo.id => frequency,
+                _ => vfo.config.channel.frequency.as_hz() as f64,
+            };
+            let open = self.open.get(&vfo.id).copied().unwrap_or(false);
+            for (index, rect) in [plot, waterfall].into_iter().enumerate() {
+                let Some(x) = view.position_of(rect, frequency) else {
+                    continue;
+                };
+                let painter = ui.painter_at(rect);
+                let width = if open { 2.5 } else { 1.0 };
+                painter.vline(x, rect.y_range(), Stroke::new(width, color));
+                if index == 0 {
+                    painter.text(
+                        Pos2::new(x + 4.0, rect.top() + 20.0),
+                        Align2::LEFT_TOP,
+                        format!("VFO {} {}", vfo.id, vfo.config.channel.demod.label()),
+                        FontId::monospace(12.0),
+                        color,
+                    );
+                }
+                let response = ui
+                    .interact(
+                        Rect::from_x_y_ranges(x - GRIP..=x + GRIP, rect.y_range()).intersect(rect),
+                        ui.id().with(("vfo", vfo.id, index)),
+                        Sense::drag(),
+                    )
+                    .on_hover_cursor(CursorIcon::ResizeHorizontal);
+                if response.dragged()
+                    && let Some(pos) = response.interact_pointer_pos()
+                    && let Some(at) = view.frequency_at(rect, pos.x)
+                {
+                    self.dragged = Some((vfo.id, (at / STEP).round() * STEP));
+                }
+                if response.drag_stopped()
+                    && let Some((id, frequency)) = self.dragged.take()
+                {
+                    moved = Some((id, frequency));
+                }
+            }
+        }
+        if let Some((id, frequency)) = moved
+            && let Some(vfo) = self.vfos.iter_mut().find(|vfo| vfo.id == id)
+        {
+            vfo.config.channel.frequency = Hertz(frequency.max(0.0) as u64);
+            let vfo = vfo.clone();
+            self.send_config(&vfo);
+        }
+    }
+
+    fn vfo_ui(&mut self, ui: &mut Ui, i: usize) -> bool {
+        let color = COLORS[i % COLORS.len()];
+        let demod_modes = self.demod_modes.clone();
+        let open = self.open.get(&self.vfos[i].id).copied();
+        let vfo = &mut self.vfos[i];
+        let mut changed = false;
+
+        ui.colored_label(color, format!("VFO {}", vfo.id));
+        let mut frequency = vfo.config.channel.frequency.0;
+        let response = ui.add(DragValue::new(&mut frequency).speed(100).suffix(" Hz"));
+        vfo.config.channel.frequency.0 = frequency;
+        // Once dragged to where it goes, not on the way
+        changed |= (response.changed() && !response.dragged()) || response.drag_stopped();
+
+        let demod = vfo.config.channel.demod;
+        ComboBox::from_id_salt(("vfo_demod", vfo.id))
+            .selected_text(vfo.config.channel.demod.label())
+            .width(60.0)
+            .show_ui(ui, |ui| {
+                for demod in demod_modes {
+                    changed |= ui
+                        .selectable_value(&mut vfo.config.channel.demod, demod, demod.label())
+                        .changed();
+                }
+            });
+        // Another mode starts from its own passband
+        if vfo.config.channel.demod != demod {
+            vfo.config.passband = None;
+        }
+
+        let mut squelched = vfo.config.squelch.is_some();
+        let mut level = vfo.config.squelch.unwrap_or(Decibels(-50.0));
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut squelched, "")
+                .on_hover_text("Squelch")
+                .changed();
+            let response = ui.add_enabled(
+                squelched,
+                DragValue::new(&mut level.0)
+                    .speed(0.5)
+                    .range(-120.0..=0.0)
+                    .suffix(" dB"),
+            );
+            changed |= (response.changed() && !response.dragged()) || response.drag_stopped();
+        });
+        vfo.config.squelch = squelched.then_some(level);
+        match open {
+            Some(true) if squelched => ui.colored_label(Color32::LIGHT_GREEN, "Open"),
+            Some(false) if squelched => ui.weak("Closed"),
+            _ => ui.label(""),
+        };
+        ui.end_row();
+
+        ui.label("");
+        let path = self
+            .recording_paths
+            .entry(vfo.id)
+            .or_insert_with(|| format!("vfo{}.wav", vfo.id));
+        let mut record = vfo.config.recording.is_some();
+        if ui.checkbox(&mut record, "Record to").changed() {
+            vfo.config.recording = record.then(|| PathBuf::from(path.as_str()));
+            changed = true;
+        }
+        ui.add_enabled(!record, TextEdit::singleline(path).desired_width(100.0));
+        let remove = ui
+            .small_button("✖")
+            .on_hover_text("Remove the VFO")
+            .clicked();
+        if remove {
+            let _ = self.cmd_tx.send(Command::RemoveVfo(vfo.id));
+        }
+        ui.end_row();
+        changed && !remove
+    }
+}
+
+impl Widget for &mut VfoPanel {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.heading("VFOs");
+        ui.separator();
+
+        if ui
+            .button("Add VFO")
+            .on_hover_text("Demodulate another channel; drag its marker to move it")
+            .clicked()
+        {
+            let config = VfoConfig::new(self.new_channel);
+            let _ = self.cmd_tx.send(Command::AddVfo(config));
+        }
+
+        if !self.vfos.is_empty() {
+            Grid::new("vfos").num_columns(5).show(ui, |ui| {
+                for i in 0..self.vfos.len() {
+                    if self.vfo_ui(ui, i) {
+                        self.send_config(&self.vfos[i]);
+                    }
+                }
+            });
+        }
+
+        ui.response()
+    }
+}
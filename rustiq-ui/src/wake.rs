@@ -0,0 +1,68 @@
+//! Waking the UI as the engine's events come in. Events are only taken in
+//! by a frame, and with power saving the next one may be a second off,
+//! while the engine waits on a full channel and the audio runs dry.
+
+use std::thread;
+
+use eframe::egui::Context;
+use flume::Receiver;
+use rustiq_messages::Event;
+
+/// Pass `events` on through a channel holding as many, asking `ctx` for a
+/// frame as each arrives. Spectrum frames are left to the frame rate, so
+/// the waterfall alone doesn't keep a window in the background drawing.
+pub fn forward(events: Receiver<Event>, ctx: Context) -> Receiver<Event> {
+    let (tx, rx) = match events.capacity() {
+        Some(capacity) => flume::bounded(capacity),
+        None => flume::unbounded(),
+    };
+    thread::spawn(move || {
+        for event in events.iter() {
+            let wake = !matches!(event, Event::SpectrumData(_));
+            if tx.send(event).is_err() {
+                break;
+            }
+            if wake {
+                ctx.request_repaint();
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use rustiq_messages::AudioChunk;
+    use rustiq_messages::mock::{initial_state, spectrum_frame};
+
+    #[test]
+    fn wakes_the_ui_for_events_but_spectrum_frames() {
+        let ctx = Context::default();
+        let (wake_tx, wake_rx) = flume::unbounded();
+        ctx.set_request_repaint_callback(move |_| {
+            let _ = wake_tx.send(());
+        });
+        let (event_tx, event_rx) = flume::bounded(1);
+        let events = forward(event_rx, ctx);
+        assert_eq!(events.capacity(), Some(1));
+
+        let state = initial_state();
+        let frame = spectrum_frame(&state, 0, None, vec![1e-3; state.fft_size]);
+        event_tx.send(Event::SpectrumData(frame)).unwrap();
+        assert!(matches!(events.recv(), Ok(Event::SpectrumData(_))));
+        assert!(wake_rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        event_tx
+            .send(Event::AudioChunk(AudioChunk {
+                sample_rate: 48_000.0,
+                samples: vec![0.0; 960],
+                stereo: false,
+            }))
+            .unwrap();
+        assert!(matches!(events.recv(), Ok(Event::AudioChunk(_))));
+        assert!(wake_rx.recv_timeout(Duration::from_secs(1)).is_ok());
+    }
+}
@@ -1,6 +1,166 @@
-use eframe::egui::{ColorImage, Image, Response, TextureHandle, TextureOptions, Ui, Widget};
+use eframe::egui::{
+    Align2, ColorImage, FontId, Image, PointerButton, Pos2, Rect, Response, Sense, Stroke,
+    TextureHandle, TextureOptions, Ui, Widget,
+};
 use eframe::epaint::Color32;
-use rustiq_messages::Decibels;
+use rustiq_messages::{Decibels, Discontinuity, Hertz, SignalRegion, SpectrumFrame};
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use std::time::{Duration, SystemTime};
+
+use crate::band_map::{Band, Bins, Pixels};
+use crate::colormap::Colormap;
+use crate::frequency_axis;
+use crate::measurement::{Measurement, Point};
+use crate::time_axis;
+use crate::zoom::Zoom;
+
+/// Hovering within this many points of a gap marker shows its details.
+const GAP_HOVER_DISTANCE: f32 = 3.0;
+
+/// Color of the line marking the frequency under the pointer.
+const CURSOR_COLOR: Color32 = Color32::YELLOW;
+
+/// Where a line from an earlier tuning has no data for the current band.
+const NO_DATA: Color32 = Color32::TRANSPARENT;
+
+/// Lines of history kept, and so the height of the image; well within the
+/// textures any GPU takes.
+const MAX_ROWS: usize = 1_024;
+
+/// Lines that scroll off the history are archived one per this much time,
+/// for pinning a stretch from long ago.
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Lines archived at most, a day's worth.
+const ARCHIVE_ROWS: usize = 8_640;
+
+/// Bins an archived line is narrowed to at most, keeping the strongest.
+const ARCHIVE_BINS: usize = 512;
+
+/// A line of the waterfall, colored as it arrived, and the band it covers.
+struct Row {
+    tuning: Band,
+    /// Sample time of the frame
+    time: Duration,
+    /// Wall-clock time the frame was taken at
+    timestamp: SystemTime,
+    levels: Vec<Decibels>,
+    /// Lowest and highest level seen when the row arrived, which its
+    /// colors are scaled between
+    range: (Decibels, Decibels),
+    pixels: Vec<Color32>,
+}
+
+impl Row {
+    /// Color the row's levels with `colormap`, keeping its scale.
+    fn recolor(&mut self, colormap: &Colormap) {
+        self.pixels = self
+            .levels
+            .iter()
+            .map(|&level| level_color(colormap, self.range, level))
+            .collect();
+    }
+
+    /// The row with its bins merged, the strongest of each kept, so it has
+    /// no more than `bins` of them; left as it is if they don't merge evenly.
+    fn narrowed(mut self, bins: usize, colormap: &Colormap) -> Self {
+        let merged = self.levels.len().div_ceil(bins);
+        if merged > 1 && self.levels.len().is_multiple_of(merged) {
+            self.levels = self
+                .levels
+                .chunks_exact(merged)
+                .map(|levels| {
+                    levels
+                        .iter()
+                        .copied()
+                        .max_by(|a, b| a.total_cmp(*b))
+                        .expect("merged bins")
+                })
+                .collect();
+            self.recolor(colormap);
+        }
+        self
+    }
+
+    /// The row's bins across the band it covers.
+    fn bins(&self) -> Bins {
+        Bins::new(self.tuning, self.pixels.len())
+    }
+
+    /// Draw the row into `out`, a line of the image showing `view`. Each
+    /// pixel takes the bin at its frequency, so rows from other tunings
+    /// line up by frequency with the current one, or the strongest of the
+    /// bins it covers where it covers several, so narrow signals still
+    /// show in an image narrower than the row.
+    fn resample(&self, view: Band, out: &mut [Color32]) {
+        if self.tuning == view && self.pixels.len() == out.len() {
+            out.copy_from_slice(&self.pixels);
+            return;
+        }
+        let bins = self.bins();
+        let columns = Pixels::new(0.0, out.len() as f32, view);
+        let width = out.len();
+        for (x, pixel) in out.iter_mut().enumerate() {
+            let edges = [x, x + 1].map(|edge| {
+                bins.position_of(view.frequency_at(edge as f64 / width as f64))
+                    .clamp(0.0, bins.count as f64)
+            });
+            let (low, high) = (edges[0].min(edges[1]), edges[0].max(edges[1]));
+            *pixel = if high - low > 1.0 {
+                (low as usize..(high.ceil() as usize).min(bins.count))
+                    .max_by(|&a, &b| self.levels[a].total_cmp(self.levels[b]))
+                    .map_or(NO_DATA, |bin| self.pixels[bin])
+            } else {
+                bins.bin_at(columns.column_frequency(x, width))
+                    .map_or(NO_DATA, |bin| self.pixels[bin])
+            };
+        }
+    }
+}
+
+/// A break in the waterfall's record, marked with a line between the rows.
+struct Gap {
+    /// Line inserted right after the gap, counted from the first line
+    line: u64,
+    /// Frames missing from the sequence
+    dropped: u64,
+    discontinuity: Option<Discontinuity>,
+}
+
+impl Gap {
+    fn description(&self) -> String {
+        let dropped = match self.dropped {
+            0 => None,
+            1 => Some("Dropped 1 frame".to_string()),
+            n => Some(format!("Dropped {n} frames")),
+        };
+        let cause = self.discontinuity.map(|d| d.label().to_string());
+        [cause, dropped]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn color(&self) -> Color32 {
+        match self.discontinuity {
+            Some(Discontinuity::SourceStall) => Color32::LIGHT_RED,
+            Some(Discontinuity::Restart) => Color32::LIGHT_BLUE,
+            None => Color32::ORANGE,
+        }
+    }
+}
+
+/// A stretch of the record pinned beside the live view, drawn at the band
+/// of the newest line so the two line up by frequency.
+struct Pinned {
+    /// Sample times of the lines in it
+    times: RangeInclusive<Duration>,
+    image: ColorImage,
+    needs_gpu_upload: bool,
+    texture_handle: Option<TextureHandle>,
+}
 
 /// Waterfall display widget that renders a scrolling spectrogram.
 ///
@@ -8,12 +168,23 @@ use rustiq_messages::Decibels;
 /// to be used with `ui.add(&mut waterfall)`. It manages its own texture state and
 /// handles GPU uploads efficiently.
 ///
-/// The image pixels are updated via `insert_spectrum_line()` when new spectrum data
+/// The image pixels are updated via `insert_frame()` when new spectrum data
 /// arrives. The `needs_gpu_upload` flag tracks whether the texture needs to be
 /// re-uploaded to the GPU, avoiding redundant uploads when rendering multiple frames
 /// without new data.
+///
+/// The image shows the band of the newest line. Each line keeps the band it
+/// was taken at, so after a retune the older lines are redrawn shifted and
+/// scaled to line up by frequency, rather than stacked as if nothing moved.
+///
+/// The view can be zoomed into part of that band; everything placed by
+/// frequency on the waterfall goes through it, so it follows the zoom.
 pub struct Waterfall {
     image: ColorImage,
+    /// The last `MAX_ROWS` lines inserted, newest first
+    rows: VecDeque<Row>,
+    /// Older lines, thinned out and narrowed, newest first
+    archive: VecDeque<Row>,
     needs_gpu_upload: bool,
     /// Cached texture handle to avoid re-uploading on every frame
     waterfall_texture_handle: Option<TextureHandle>,
@@ -23,58 +194,474 @@ pub struct Waterfall {
     min_px_val: Option<Decibels>,
     /// Max value in the waterfall. Used to scale the colors
     max_px_val: Option<Decibels>,
+    colormap: Colormap,
+
+    /// Lines inserted so far
+    lines: u64,
+    /// Sequence number of the last frame inserted
+    last_sequence: Option<u64>,
+    /// Breaks in the record among the lines kept, oldest first
+    gaps: Vec<Gap>,
+    /// Line dragged across the waterfall to measure between its ends
+    measurement: Option<Measurement>,
+    /// Whether drags draw the measurement line, rather than being left
+    /// to an annotation tool
+    measuring: bool,
+    /// Stretch of the record to compare the live view against
+    pinned: Option<Pinned>,
+    /// Part of the band in view
+    zoom: Zoom,
+    /// Widest image the GPU takes; rows with more bins are drawn narrower
+    max_width: usize,
 }
 
 impl Waterfall {
     pub fn new() -> Self {
         Self {
             image: ColorImage::default(),
+            rows: VecDeque::new(),
+            archive: VecDeque::new(),
             needs_gpu_upload: false,
             waterfall_texture_handle: None,
             min_px_val: None,
             max_px_val: None,
+            colormap: Colormap::Grayscale,
+            lines: 0,
+            last_sequence: None,
+            gaps: Vec::new(),
+            measurement: None,
+            measuring: true,
+            pinned: None,
+            zoom: Zoom::default(),
+            max_width: usize::MAX,
+        }
+    }
+
+    /// Insert a frame from the engine, marking any gap before it. Bins
+    /// marked in `excluded` are left out of the color scale.
+    pub fn insert_frame(&mut self, frame: &SpectrumFrame, excluded: &[bool]) {
+        let dropped = self
+            .last_sequence
+            .map_or(0, |last| frame.sequence.saturating_sub(last + 1));
+        if (dropped > 0 || frame.discontinuity.is_some()) && self.lines > 0 {
+            self.gaps.push(Gap {
+                line: self.lines,
+                dropped,
+                discontinuity: frame.discontinuity,
+            });
         }
+        self.last_sequence = Some(frame.sequence);
+        self.insert_spectrum_line(
+            &frame.magnitudes,
+            Band::of(frame),
+            (frame.sample_time, frame.timestamp),
+            excluded,
+        );
     }
 
     /// Insert new line of pixel data at the top of the waterfall
-    pub fn insert_spectrum_line(&mut self, data: &[f32]) {
+    fn insert_spectrum_line(
+        &mut self,
+        data: &[f32],
+        tuning: Band,
+        (time, timestamp): (Duration, SystemTime),
+        excluded: &[bool],
+    ) {
         if data.is_empty() {
             return;
         };
 
-        let img_width = data.len();
-        if !self.image.pixels.is_empty() {
-            assert_eq!(self.image.size[0], img_width);
+        let decibels: Vec<Decibels> = data.iter().map(|&f| Decibels::from_linear(f)).collect();
+        let included: Vec<Decibels> = decibels
+            .iter()
+            .enumerate()
+            .filter(|(bin, _)| !excluded.get(*bin).copied().unwrap_or(false))
+            .map(|(_, &level)| level)
+            .collect();
+        // With every bin excluded, the scale still has to start somewhere
+        if included.is_empty() && self.min_px_val.is_none() {
+            self.update_min_max_values(&decibels);
+        } else if !included.is_empty() {
+            self.update_min_max_values(&included);
         }
 
-        let decibels: Vec<Decibels> = data.iter().map(|&f| Decibels::from_linear(f)).collect();
-        self.update_min_max_values(&decibels);
+        let range = (
+            self.min_px_val
+                .expect("Tried to color a waterfall line before establishing the min value to scale colors from"),
+            self.max_px_val
+                .expect("Tried to color a waterfall line before establishing the max value to scale colors from"),
+        );
+        let mut row = Row {
+            tuning,
+            time,
+            timestamp,
+            levels: decibels,
+            range,
+            pixels: Vec::new(),
+        };
+        row.recolor(&self.colormap);
+        let retuned = self
+            .rows
+            .front()
+            .is_some_and(|newest| newest.tuning != tuning || newest.pixels.len() != data.len());
+
+        // Same band as the image, so the line can go on top as it is
+        let line = (!retuned).then(|| {
+            let mut line = vec![NO_DATA; data.len().min(self.max_width)];
+            row.resample(tuning, &mut line);
+            line
+        });
+        self.rows.push_front(row);
+        self.lines += 1;
+        self.trim_history();
+        match line {
+            Some(line) => {
+                let width = line.len();
+                self.image.pixels.truncate(width * (self.rows.len() - 1));
+                self.image.pixels.splice(0..0, line);
+                self.image.size = [width, self.rows.len()];
+            }
+            None => self.render(),
+        }
+        self.needs_gpu_upload = true;
+    }
+
+    /// Let the oldest lines go past `MAX_ROWS`, and the gaps among them,
+    /// archiving one now and then.
+    fn trim_history(&mut self) {
+        while self.rows.len() > MAX_ROWS {
+            let Some(row) = self.rows.pop_back() else {
+                break;
+            };
+            // A line from before a restart of the stream starts over
+            let due = self.archive.front().is_none_or(|newest| {
+                row.time < newest.time || row.time >= newest.time + ARCHIVE_INTERVAL
+            });
+            if due {
+                self.archive
+                    .push_front(row.narrowed(ARCHIVE_BINS, &self.colormap));
+                self.archive.truncate(ARCHIVE_ROWS);
+            }
+        }
+        let oldest = self.lines - self.rows.len() as u64;
+        self.gaps.retain(|gap| gap.line > oldest);
+    }
+
+    /// Start the color scale over from the next line, e.g. once a strong
+    /// signal that stretched it is excluded. Lines already drawn keep theirs.
+    pub fn reset_range(&mut self) {
+        self.min_px_val = None;
+        self.max_px_val = None;
+    }
+
+    pub fn colormap(&self) -> Colormap {
+        self.colormap.clone()
+    }
+
+    /// Color the waterfall with `colormap`, the lines already drawn too.
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        if colormap == self.colormap {
+            return;
+        }
+        self.colormap = colormap;
+        for row in self.rows.iter_mut().chain(&mut self.archive) {
+            row.recolor(&self.colormap);
+        }
+        self.render();
+        self.needs_gpu_upload = true;
+    }
+
+    /// Redraw every row into an image of the newest row's band.
+    fn render(&mut self) {
+        let Some(newest) = self.rows.front() else {
+            return;
+        };
+        let (view, width) = (newest.tuning, newest.pixels.len().min(self.max_width));
+        let mut pixels = vec![NO_DATA; width * self.rows.len()];
+        for (row, out) in self.rows.iter().zip(pixels.chunks_exact_mut(width)) {
+            row.resample(view, out);
+        }
+        self.image = ColorImage::new([width, self.rows.len()], pixels);
+        self.render_pinned();
+    }
 
-        let new_pixels: Vec<Color32> = decibels
+    /// Redraw the pinned stretch into an image of the newest row's band, no
+    /// taller than the live one.
+    fn render_pinned(&mut self) {
+        let (Some(pinned), Some(newest)) = (&mut self.pinned, self.rows.front()) else {
+            return;
+        };
+        let (view, width) = (newest.tuning, newest.pixels.len().min(self.max_width));
+        let rows: Vec<&Row> = self
+            .rows
+            .iter()
+            .chain(&self.archive)
+            .filter(|row| pinned.times.contains(&row.time))
+            .collect();
+        let rows: Vec<&Row> = rows
             .iter()
-            .map(|&db| self.decibels_to_color(db))
+            .step_by(rows.len().div_ceil(MAX_ROWS).max(1))
+            .copied()
             .collect();
+        let mut pixels = vec![NO_DATA; width * rows.len()];
+        for (row, out) in rows.iter().zip(pixels.chunks_exact_mut(width)) {
+            row.resample(view, out);
+        }
+        pinned.image = ColorImage::new([width, rows.len()], pixels);
+        pinned.needs_gpu_upload = true;
+    }
 
-        self.image.pixels.extend(new_pixels);
-        self.image.pixels.rotate_right(img_width);
+    /// Pin the lines taken during `times` beside the live view, or take
+    /// them away with `None`.
+    pub fn set_pinned(&mut self, times: Option<RangeInclusive<Duration>>) {
+        if self.pinned.as_ref().map(|pinned| &pinned.times) == times.as_ref() {
+            return;
+        }
+        self.pinned = times.map(|times| Pinned {
+            times,
+            image: ColorImage::default(),
+            needs_gpu_upload: false,
+            texture_handle: None,
+        });
+        self.render_pinned();
+    }
 
-        assert_eq!(self.image.pixels.len() % img_width, 0);
-        self.image.size = [img_width, self.image.pixels.len() / img_width];
-        self.needs_gpu_upload = true;
+    pub fn zoom(&self) -> Zoom {
+        self.zoom
     }
 
-    fn decibels_to_color(&self, decibels: Decibels) -> Color32 {
-        let min_val = self.min_px_val
-            .expect("Tried to calculate a waterfall pixel color before establishing the min value to scale colors from");
-        let max_val = self.max_px_val
-            .expect("Tried to calculate a waterfall pixel color before establishing the max value to scale colors from");
+    /// Zoom and pan the view with gestures on `response`, a view of the
+    /// same band such as the plot above, panning with drags by
+    /// `pan_button`.
+    pub fn update_zoom(&mut self, ui: &Ui, response: &Response, pan_button: PointerButton) {
+        if self.zoom.update(ui, response, pan_button) {
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// The part of the newest row's band in view.
+    fn view(&self) -> Option<Band> {
+        let newest = self.rows.front()?.tuning;
+        let (center, span) = self.zoom.band(newest.center, newest.span);
+        Some(Band::new(center, span))
+    }
 
-        debug_assert!(decibels >= min_val);
-        debug_assert!(decibels <= max_val);
+    /// The part in view, drawn across `rect`.
+    fn pixels(&self, rect: Rect) -> Option<Pixels> {
+        Some(Pixels::new(rect.left(), rect.width(), self.view()?))
+    }
+
+    /// Sample time of the newest line, if any.
+    pub fn latest_time(&self) -> Option<Duration> {
+        Some(self.rows.front()?.time)
+    }
+
+    /// Draw the images no wider than the GPU drawing `ui` takes, as it
+    /// turns out to be once drawn.
+    fn fit_to_texture_limit(&mut self, ui: &Ui) {
+        let max_width = ui.ctx().input(|input| input.max_texture_side);
+        if max_width != self.max_width {
+            self.max_width = max_width;
+            self.render();
+            self.needs_gpu_upload = true;
+        }
+    }
+
+    /// Draw the pinned stretch in the space left in `ui`, if one is
+    /// pinned. The response senses hovering and clicks like the live view's.
+    pub fn show_pinned(&mut self, ui: &mut Ui) -> Option<Response> {
+        self.fit_to_texture_limit(ui);
+        let pinned = self.pinned.as_mut()?;
+        if pinned.image.pixels.is_empty() {
+            return Some(ui.label("Nothing was recorded then"));
+        }
+        if pinned.needs_gpu_upload || pinned.texture_handle.is_none() {
+            pinned.texture_handle = Some(ui.ctx().load_texture(
+                "pinned_waterfall",
+                pinned.image.clone(),
+                TextureOptions::LINEAR,
+            ));
+            pinned.needs_gpu_upload = false;
+        }
+        let texture_handle = pinned.texture_handle.as_ref()?;
+        let response = ui.add(
+            Image::new(texture_handle)
+                .uv(self.zoom.uv())
+                .fit_to_exact_size(ui.available_size())
+                .sense(Sense::click()),
+        );
+        self.paint_frequency_axis(ui, response.rect);
+        Some(response)
+    }
+
+    /// Label the frequencies across the waterfall drawn in `rect`.
+    fn paint_frequency_axis(&self, ui: &Ui, rect: Rect) {
+        if let Some(view) = self.view() {
+            frequency_axis::paint(&ui.painter_at(rect), rect, view);
+        }
+    }
+
+    /// Frequency shown at `x` in the waterfall drawn in `rect`, in Hz.
+    pub fn frequency_at(&self, rect: Rect, x: f32) -> Option<f64> {
+        Some(self.pixels(rect)?.frequency_at(x))
+    }
 
-        let range_len = max_val.0 - min_val.0;
-        let scaled = (decibels.0 - min_val.0) / range_len.max(0.01); // avoid div by 0
-        Color32::from_gray((scaled * 255.0) as u8)
+    /// Center of the newest row's bin at `x` in the waterfall drawn in
+    /// `rect`, in Hz: the frequency a click there tunes to.
+    pub fn bin_frequency_at(&self, rect: Rect, x: f32) -> Option<f64> {
+        let bins = self.rows.front()?.bins();
+        let bin = bins.bin_at(self.frequency_at(rect, x)?)?;
+        Some(bins.frequency_of(bin))
+    }
+
+    /// Position of `frequency` across the waterfall drawn in `rect`, if it
+    /// is in view.
+    fn x_of(&self, rect: Rect, frequency: f64) -> Option<f32> {
+        self.pixels(rect)?.x_in_view(frequency)
+    }
+
+    /// Position `frequency` would be at across the waterfall drawn in
+    /// `rect`, in view or not.
+    pub fn position_of(&self, rect: Rect, frequency: f64) -> Option<f32> {
+        Some(self.pixels(rect)?.x_of(frequency))
+    }
+
+    /// Draw a cursor down the waterfall drawn in `rect` at `frequency`,
+    /// labelled with the frequency, if it is in view.
+    pub fn mark_frequency(&self, ui: &Ui, rect: Rect, frequency: f64) {
+        if let Some(x) = self.x_of(rect, frequency) {
+            let painter = ui.painter_at(rect);
+            painter.vline(x, rect.y_range(), Stroke::new(1.0, CURSOR_COLOR));
+            painter.text(
+                Pos2::new(x + 4.0, rect.top() + 4.0),
+                Align2::LEFT_TOP,
+                format!("{:.6} MHz", frequency / 1e6),
+                FontId::monospace(12.0),
+                CURSOR_COLOR,
+            );
+        }
+    }
+
+    /// The frequency and line at `pos` in the waterfall drawn in `rect`.
+    pub fn point_at(&self, rect: Rect, pos: Pos2) -> Option<Point> {
+        let frequency = self.frequency_at(rect, pos.x)?;
+        let index = ((pos.y - rect.top()) / rect.height() * self.rows.len() as f32).floor();
+        if index < 0.0 {
+            return None;
+        }
+        let row = self.rows.get(index as usize)?;
+        Some(Point {
+            frequency,
+            line: self.lines - 1 - index as u64,
+            time: row.time,
+            level: row.bins().bin_at(frequency).map(|bin| row.levels[bin]),
+        })
+    }
+
+    /// Height of the middle of the newest line taken at or before `time`
+    /// in the waterfall drawn in `rect`, if that is still in view.
+    pub fn line_y(&self, rect: Rect, time: Duration) -> Option<f32> {
+        if time > self.rows.front()?.time {
+            return None;
+        }
+        let index = self.rows.iter().position(|row| row.time <= time)?;
+        let rows = self.rows.len() as f32;
+        Some(rect.top() + (index as f32 + 0.5) / rows * rect.height())
+    }
+
+    /// Leave drags to an annotation tool instead of measuring with them,
+    /// unless `measuring`.
+    pub fn set_measuring(&mut self, measuring: bool) {
+        self.measuring = measuring;
+        if !measuring {
+            self.measurement = None;
+        }
+    }
+
+    /// The band the measurement line spans, for analysing the signal in it.
+    pub fn selected_region(&self) -> Option<SignalRegion> {
+        let measurement = self.measurement.as_ref()?;
+        let (start, end) = (measurement.start.frequency, measurement.end.frequency);
+        let bandwidth = (end - start).abs().round();
+        let frequency = (start + end) / 2.0;
+        (bandwidth >= 1.0 && frequency >= 0.0).then(|| SignalRegion {
+            frequency: Hertz(frequency.round() as u64),
+            bandwidth: Hertz(bandwidth as u64),
+        })
+    }
+
+    /// Start, extend or clear the measurement from a drag or click.
+    fn update_measurement(&mut self, ui: &Ui, response: &Response) {
+        if response.clicked() {
+            self.measurement = None;
+        } else if response.drag_started_by(PointerButton::Primary) {
+            let origin = ui.input(|input| input.pointer.press_origin());
+            self.measurement = origin
+                .and_then(|pos| self.point_at(response.rect, pos))
+                .map(Measurement::new);
+        } else if response.dragged_by(PointerButton::Primary)
+            && let Some(pos) = response.interact_pointer_pos()
+            && let Some(end) = self.point_at(response.rect, pos)
+            && let Some(measurement) = &mut self.measurement
+        {
+            measurement.end = end;
+        }
+    }
+
+    /// Draw the measurement line, with the differences between its ends
+    /// beside the end. The ends stay on their lines as the waterfall scrolls.
+    fn mark_measurement(&self, ui: &Ui, rect: Rect) {
+        let Some(measurement) = &self.measurement else {
+            return;
+        };
+        let rows = self.rows.len() as f32;
+        let pos = |point: &Point| {
+            let x = self.x_of(rect, point.frequency)?;
+            let index = (self.lines - 1 - point.line) as f32;
+            Some(Pos2::new(
+                x,
+                rect.top() + (index + 0.5) / rows * rect.height(),
+            ))
+        };
+        let (Some(start), Some(end)) = (pos(&measurement.start), pos(&measurement.end)) else {
+            return;
+        };
+        let painter = ui.painter_at(rect);
+        let stroke = Stroke::new(1.5, CURSOR_COLOR);
+        painter.line_segment([start, end], stroke);
+        painter.circle_stroke(start, 3.0, stroke);
+        painter.circle_stroke(end, 3.0, stroke);
+        painter.text(
+            end + [8.0, 0.0].into(),
+            Align2::LEFT_CENTER,
+            measurement.label(),
+            FontId::monospace(12.0),
+            CURSOR_COLOR,
+        );
+    }
+
+    /// Draw a line across the waterfall at each gap, with its details on hover.
+    fn mark_gaps(&self, ui: &Ui, response: &Response) {
+        let rect = response.rect;
+        let rows = self.image.size[1] as f32;
+        // The newest line is at the top; a gap sits below the line after it
+        let gap_y = |gap: &Gap| rect.top() + (self.lines - gap.line) as f32 / rows * rect.height();
+        let painter = ui.painter_at(rect);
+        for gap in &self.gaps {
+            painter.hline(rect.x_range(), gap_y(gap), Stroke::new(1.0, gap.color()));
+        }
+
+        if let Some(pointer) = response.hover_pos()
+            && let Some(gap) = self
+                .gaps
+                .iter()
+                .find(|gap| (gap_y(gap) - pointer.y).abs() <= GAP_HOVER_DISTANCE)
+        {
+            let description = gap.description();
+            response.clone().on_hover_ui_at_pointer(|ui| {
+                ui.label(description);
+            });
+        }
     }
 
     fn update_min_max_values(&mut self, decibels: &[Decibels]) {
@@ -94,12 +681,27 @@ impl Waterfall {
     }
 }
 
+/// Color of `decibels` in `colormap`, scaled between the levels of `range`.
+/// Levels of excluded bins may fall outside it, and take the end colors.
+fn level_color(colormap: &Colormap, range: (Decibels, Decibels), decibels: Decibels) -> Color32 {
+    let (min_val, max_val) = range;
+
+    let range_len = max_val.0 - min_val.0;
+    let scaled = (decibels.0 - min_val.0) / range_len.max(0.01); // avoid div by 0
+    colormap.color(scaled)
+}
+
 impl Widget for &mut Waterfall {
     /// Renders the waterfall display.
     ///
     /// Pixel data is pre-computed in `insert_spectrum_line()` (not during rendering),
     /// so this function only uploads the texture to the GPU when new data is available.
     /// The texture handle is cached to avoid re-uploading on every frame.
+    /// The returned response senses clicks on the spectrogram; drags on it
+    /// draw a measurement line. Scrolling zooms into the band around the
+    /// pointer, drags with the secondary button pan and a double click
+    /// zooms back out. Frequencies are labelled along the top, and times of
+    /// day down the left.
     fn ui(self, ui: &mut Ui) -> Response {
         // Check if we have any image data
         if self.image.pixels.is_empty() {
@@ -108,6 +710,8 @@ impl Widget for &mut Waterfall {
         }
         self.image.pixels.truncate(self.image.size.iter().product());
 
+        self.fit_to_texture_limit(ui);
+
         // Only upload texture if we have new data
         if self.needs_gpu_upload {
             let texture =
@@ -125,9 +729,371 @@ impl Widget for &mut Waterfall {
         if let Some(texture_handle) = &self.waterfall_texture_handle {
             let available_size = ui.available_size();
             // ui.add(eframe::egui::Image::new(texture_handle).fit_to_exact_size(available_size));
-            ui.add(Image::new(texture_handle).fit_to_exact_size(available_size));
+            let response = ui.add(
+                Image::new(texture_handle)
+                    .uv(self.zoom.uv())
+                    .fit_to_exact_size(available_size)
+                    .sense(Sense::click_and_drag()),
+            );
+            self.update_zoom(ui, &response, PointerButton::Secondary);
+            self.paint_frequency_axis(ui, response.rect);
+            let times: Vec<SystemTime> = self.rows.iter().map(|row| row.timestamp).collect();
+            time_axis::paint(&ui.painter_at(response.rect), response.rect, &times);
+            self.mark_gaps(ui, &response);
+            if self.measuring {
+                self.update_measurement(ui, &response);
+            }
+            self.mark_measurement(ui, response.rect);
+            return response;
         }
 
         ui.response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::assert_matches_golden;
+    use rustiq_messages::Hertz;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const TUNING: Band = Band {
+        center: 0.0,
+        span: 48_000.0,
+    };
+
+    fn frame(sequence: u64, discontinuity: Option<Discontinuity>) -> SpectrumFrame {
+        SpectrumFrame {
+            sequence,
+            sample_time: Duration::ZERO,
+            timestamp: UNIX_EPOCH,
+            source: 0,
+            discontinuity,
+            center_frequency: Hertz(0),
+            sample_rate: Hertz(48_000),
+            magnitudes: vec![0.5, 1.0, 2.0],
+            peak_hold: None,
+            min_hold: None,
+        }
+    }
+
+    #[test]
+    fn marks_gaps_in_sequence() {
+        let mut waterfall = Waterfall::new();
+        waterfall.insert_frame(&frame(0, None), &[]);
+        waterfall.insert_frame(&frame(1, None), &[]);
+        waterfall.insert_frame(&frame(15, None), &[]);
+        waterfall.insert_frame(&frame(16, Some(Discontinuity::Restart)), &[]);
+        waterfall.insert_frame(&frame(17, None), &[]);
+
+        assert_eq!(waterfall.lines, 5);
+        assert_eq!(waterfall.gaps.len(), 2);
+        assert_eq!(waterfall.gaps[0].line, 2);
+        assert_eq!(waterfall.gaps[0].description(), "Dropped 13 frames");
+        assert_eq!(waterfall.gaps[1].line, 3);
+        assert_eq!(waterfall.gaps[1].description(), "Stream restarted");
+    }
+
+    #[test]
+    fn keeps_a_bounded_history() {
+        let mut waterfall = Waterfall::new();
+        waterfall.insert_frame(&frame(0, None), &[]);
+        // Falls off the end with the line before it
+        waterfall.insert_frame(&frame(5, None), &[]);
+        for sequence in 6..MAX_ROWS as u64 + 8 {
+            waterfall.insert_frame(&frame(sequence, None), &[]);
+        }
+        waterfall.insert_frame(&frame(MAX_ROWS as u64 + 10, None), &[]);
+
+        assert_eq!(waterfall.lines, MAX_ROWS as u64 + 5);
+        assert_eq!(waterfall.rows.len(), MAX_ROWS);
+        assert_eq!(waterfall.image.size, [3, MAX_ROWS]);
+        assert_eq!(waterfall.image.pixels.len(), 3 * MAX_ROWS);
+        assert_eq!(waterfall.gaps.len(), 1);
+        assert_eq!(waterfall.gaps[0].description(), "Dropped 2 frames");
+
+        // Redrawn from the lines kept, as on a retune
+        waterfall.set_colormap(Colormap::Viridis);
+        assert_eq!(waterfall.image.size, [3, MAX_ROWS]);
+    }
+
+    #[test]
+    fn archives_lines_older_than_the_history_for_pinning() {
+        let mut waterfall = Waterfall::new();
+        // A line a second, the first a tone in bin 1,025 of 2,048
+        let line_count = MAX_ROWS as u64 + 60;
+        for second in 0..line_count {
+            let mut magnitudes = vec![1e-3; 2_048];
+            magnitudes[1_025] = if second == 0 { 1.0 } else { 1e-3 };
+            let time = Duration::from_secs(second);
+            waterfall.insert_spectrum_line(&magnitudes, TUNING, (time, UNIX_EPOCH), &[]);
+        }
+        assert_eq!(waterfall.rows.len(), MAX_ROWS);
+        let times: Vec<u64> = waterfall
+            .archive
+            .iter()
+            .map(|row| row.time.as_secs())
+            .collect();
+        assert_eq!(times, [50, 40, 30, 20, 10, 0]);
+        assert!(waterfall.archive.iter().all(|row| row.levels.len() == 512));
+        // Its bin merged with three others
+        assert_eq!(waterfall.archive[5].levels[256], Decibels::from_linear(1.0));
+
+        waterfall.set_pinned(Some(Duration::ZERO..=Duration::from_secs(65)));
+        let pinned = &waterfall.pinned.as_ref().unwrap().image;
+        assert_eq!(pinned.size, [2_048, 6 + 6]);
+    }
+
+    /// Magnitudes of noise around `floor`, varying by a few dB, repeatably.
+    fn noise(bins: usize, floor: f32, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..bins)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                floor * (0.5 + (state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_drifting_tone_over_noise() {
+        let mut waterfall = Waterfall::new();
+        for line in 0..48 {
+            let mut magnitudes = noise(128, 1e-3, line);
+            // Newest line on top, so the tone drifts up the band going down
+            magnitudes[40 + line as usize / 4] = 0.5;
+            waterfall.insert_spectrum_line(&magnitudes, TUNING, (Duration::ZERO, UNIX_EPOCH), &[]);
+        }
+        assert_matches_golden("waterfall_drifting_tone", &waterfall.image);
+    }
+
+    #[test]
+    fn keeps_earlier_lines_when_range_widens() {
+        let mut waterfall = Waterfall::new();
+        for line in 0..32 {
+            let mut magnitudes = noise(64, 1e-3, line);
+            magnitudes[16] = 1e-2;
+            // A much stronger signal appears halfway through
+            if line >= 16 {
+                magnitudes[48] = 1.0;
+            }
+            waterfall.insert_spectrum_line(&magnitudes, TUNING, (Duration::ZERO, UNIX_EPOCH), &[]);
+        }
+        assert_matches_golden("waterfall_range_widens", &waterfall.image);
+    }
+
+    #[test]
+    fn leaves_excluded_bins_out_of_the_range() {
+        let mut waterfall = Waterfall::new();
+        let mut excluded = vec![false; 64];
+        excluded[48] = true;
+        let mut magnitudes = noise(64, 1e-3, 0);
+        magnitudes[16] = 1e-2;
+        magnitudes[48] = 1.0;
+        waterfall.insert_spectrum_line(
+            &magnitudes,
+            TUNING,
+            (Duration::ZERO, UNIX_EPOCH),
+            &excluded,
+        );
+
+        // The weaker signal tops the scale, and the excluded one saturates
+        assert_eq!(waterfall.max_px_val, Some(Decibels::from_linear(1e-2)));
+        let top = Colormap::Grayscale.color(1.0);
+        assert_eq!(waterfall.image.pixels[16], top);
+        assert_eq!(waterfall.image.pixels[48], top);
+
+        // Once included again, the scale starts over with it
+        waterfall.reset_range();
+        waterfall.insert_spectrum_line(&magnitudes, TUNING, (Duration::ZERO, UNIX_EPOCH), &[]);
+        assert_eq!(waterfall.max_px_val, Some(Decibels::from_linear(1.0)));
+    }
+
+    #[test]
+    fn keeps_narrow_signals_in_an_image_narrower_than_the_rows() {
+        let mut waterfall = Waterfall::new();
+        waterfall.max_width = 16;
+        let mut magnitudes = vec![1e-3; 64];
+        // Off the middle of the four bins its column covers
+        magnitudes[33] = 1.0;
+        for _ in 0..2 {
+            waterfall.insert_spectrum_line(&magnitudes, TUNING, (Duration::ZERO, UNIX_EPOCH), &[]);
+        }
+        waterfall.set_colormap(Colormap::Viridis);
+
+        let image = &waterfall.image;
+        assert_eq!(image.size, [16, 2]);
+        let top = Colormap::Viridis.color(1.0);
+        for line in image.pixels.chunks_exact(16) {
+            let lit: Vec<usize> = (0..16).filter(|&x| line[x] == top).collect();
+            assert_eq!(lit, [8]);
+        }
+    }
+
+    #[test]
+    fn recolors_history_with_colormap() {
+        let mut waterfall = Waterfall::new();
+        for line in 0..32 {
+            let mut magnitudes = noise(64, 1e-3, line);
+            magnitudes[16] = 1e-2;
+            if line >= 16 {
+                magnitudes[48] = 1.0;
+            }
+            waterfall.insert_spectrum_line(&magnitudes, TUNING, (Duration::ZERO, UNIX_EPOCH), &[]);
+        }
+        let grayscale = waterfall.image.pixels.clone();
+
+        waterfall.set_colormap(Colormap::Viridis);
+        assert_matches_golden("waterfall_viridis", &waterfall.image);
+
+        // Each line keeps the scale it arrived with
+        waterfall.set_colormap(Colormap::Grayscale);
+        assert_eq!(waterfall.image.pixels, grayscale);
+    }
+
+    #[test]
+    fn lines_up_rows_across_retunes() {
+        let mut waterfall = Waterfall::new();
+        let tone = 12_000.0;
+        for line in 0..32 {
+            // Retuned up a quarter of the band halfway through
+            let center = if line < 16 { 0.0 } else { 12_000.0 };
+            let tuning = Band { center, ..TUNING };
+            let mut magnitudes = noise(64, 1e-3, line);
+            let bin = ((tone - center) / tuning.span + 0.5) * 64.0;
+            magnitudes[bin as usize] = 0.5;
+            waterfall.insert_spectrum_line(&magnitudes, tuning, (Duration::ZERO, UNIX_EPOCH), &[]);
+        }
+
+        // The tone stays in one column, and the band above the old tuning
+        // has no data in the old rows
+        let image = &waterfall.image;
+        let [width, height] = image.size;
+        let rows: Vec<&[Color32]> = image.pixels.chunks_exact(width).collect();
+        assert_eq!(rows.len(), height);
+        for row in &rows {
+            assert_eq!(row[32], Color32::WHITE);
+        }
+        assert!(rows[..16].iter().all(|row| row[63] != NO_DATA));
+        assert!(rows[16..].iter().all(|row| row[48..] == [NO_DATA; 16]));
+        assert_matches_golden("waterfall_retune", image);
+    }
+
+    #[test]
+    fn finds_the_line_taken_at_a_time() {
+        let mut waterfall = Waterfall::new();
+        let rect = Rect::from_min_size([0.0, 100.0].into(), [400.0, 40.0].into());
+        for line in 0..4 {
+            let time = Duration::from_millis(100 * line);
+            waterfall.insert_spectrum_line(
+                &noise(64, 1e-3, 0),
+                TUNING,
+                (time, UNIX_EPOCH + time),
+                &[],
+            );
+        }
+        // Newest line on top, 10 points each
+        assert_eq!(
+            waterfall.line_y(rect, Duration::from_millis(300)),
+            Some(105.0)
+        );
+        assert_eq!(
+            waterfall.line_y(rect, Duration::from_millis(150)),
+            Some(125.0)
+        );
+        assert_eq!(waterfall.line_y(rect, Duration::ZERO), Some(135.0));
+        assert_eq!(waterfall.line_y(rect, Duration::from_millis(301)), None);
+    }
+
+    #[test]
+    fn pins_a_stretch_at_the_live_band() {
+        let mut waterfall = Waterfall::new();
+        for line in 0..8 {
+            // Retuned up a quarter of the band after the pinned stretch
+            let center = if line < 6 { 0.0 } else { 12_000.0 };
+            let mut magnitudes = vec![1e-3; 64];
+            magnitudes[20 + line as usize] = 1.0;
+            let time = Duration::from_secs(line);
+            waterfall.insert_spectrum_line(
+                &magnitudes,
+                Band { center, ..TUNING },
+                (time, UNIX_EPOCH + time),
+                &[],
+            );
+        }
+        waterfall.set_pinned(Some(Duration::from_secs(2)..=Duration::from_secs(4)));
+
+        // The lines of seconds 4, 3 and 2, newest on top, shifted to the
+        // live band with nothing above the old one
+        let pinned = &waterfall.pinned.as_ref().unwrap().image;
+        assert_eq!(pinned.size, [64, 3]);
+        let rows: Vec<&[Color32]> = pinned.pixels.chunks_exact(64).collect();
+        for (row, peak) in rows.iter().zip([24, 23, 22]) {
+            assert_eq!(row[peak - 16], Color32::WHITE);
+            assert_eq!(row[48..], [NO_DATA; 16]);
+        }
+
+        // Pinning nothing recorded leaves it empty, and unpinning drops it
+        waterfall.set_pinned(Some(Duration::from_secs(20)..=Duration::from_secs(30)));
+        assert!(waterfall.pinned.as_ref().unwrap().image.pixels.is_empty());
+        waterfall.set_pinned(None);
+        assert!(waterfall.pinned.is_none());
+    }
+
+    #[test]
+    fn maps_position_to_frequency_of_newest_row() {
+        let mut waterfall = Waterfall::new();
+        let rect = Rect::from_min_size([100.0, 0.0].into(), [400.0, 300.0].into());
+        assert_eq!(waterfall.frequency_at(rect, 300.0), None);
+
+        waterfall.insert_spectrum_line(
+            &noise(64, 1e-3, 0),
+            TUNING,
+            (Duration::ZERO, UNIX_EPOCH),
+            &[],
+        );
+        waterfall.insert_spectrum_line(
+            &noise(64, 1e-3, 1),
+            Band {
+                center: 1e6,
+                ..TUNING
+            },
+            (Duration::ZERO, UNIX_EPOCH),
+            &[],
+        );
+        assert_eq!(waterfall.frequency_at(rect, 100.0), Some(976_000.0));
+        assert_eq!(waterfall.frequency_at(rect, 300.0), Some(1e6));
+        assert_eq!(waterfall.frequency_at(rect, 500.0), Some(1_024_000.0));
+
+        // 64 bins of 750 Hz across 400 points
+        assert_eq!(waterfall.bin_frequency_at(rect, 100.0), Some(976_375.0));
+        assert_eq!(waterfall.bin_frequency_at(rect, 302.0), Some(1_000_375.0));
+        assert_eq!(waterfall.bin_frequency_at(rect, 500.0), None);
+    }
+
+    #[test]
+    fn picks_points_for_measurement() {
+        let mut waterfall = Waterfall::new();
+        let rect = Rect::from_min_size([0.0, 0.0].into(), [64.0, 20.0].into());
+        for line in 0..2 {
+            let mut magnitudes = vec![1e-3; 64];
+            magnitudes[16 + line * 16] = 0.1;
+            let time = Duration::from_millis(100 * line as u64);
+            waterfall.insert_spectrum_line(&magnitudes, TUNING, (time, UNIX_EPOCH + time), &[]);
+        }
+
+        // The newest line is the top half, with its peak at column 32
+        let start = waterfall.point_at(rect, [16.5, 15.0].into()).unwrap();
+        let end = waterfall.point_at(rect, [32.5, 5.0].into()).unwrap();
+        assert_eq!((start.line, end.line), (0, 1));
+        assert_eq!(start.frequency, -12_000.0 + 0.5 * 750.0);
+        assert_eq!(start.level, Some(Decibels::from_linear(0.1)));
+        let measurement = Measurement { start, end };
+        assert_eq!(measurement.frequency(), 12_000.0);
+        assert!((measurement.time() - 0.1).abs() < 1e-9);
+        assert_eq!(measurement.level(), Some(Decibels(0.0)));
+        assert!((measurement.symbol_rate().unwrap() - 10.0).abs() < 1e-6);
+        assert!(waterfall.point_at(rect, [16.5, 25.0].into()).is_none());
+    }
+}
@@ -0,0 +1,122 @@
+//! Zoom into part of the band the spectrum and waterfall show. It only
+//! picks which bins are in view, so the engine goes on computing the
+//! whole band and zooming out again is instant.
+
+use eframe::egui::{PointerButton, Rect, Response, Ui, pos2};
+
+/// Narrowest view, as a fraction of the band.
+const MIN_WIDTH: f64 = 1.0 / 256.0;
+
+/// Scroll that zooms in twice as far, in points.
+const SCROLL_PER_DOUBLING: f32 = 200.0;
+
+/// The part of the band in view, as fractions of its width from the low
+/// edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Zoom {
+    center: f64,
+    width: f64,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            center: 0.5,
+            width: 1.0,
+        }
+    }
+}
+
+impl Zoom {
+    /// Center and width in Hz of the part in view of the band `span` Hz
+    /// wide around `center`.
+    pub fn band(&self, center: f64, span: f64) -> (f64, f64) {
+        (center + (self.center - 0.5) * span, self.width * span)
+    }
+
+    /// The part of a texture of the whole band in view.
+    pub fn uv(&self) -> Rect {
+        let half = (self.width / 2.0) as f32;
+        let center = self.center as f32;
+        Rect::from_min_max(pos2(center - half, 0.0), pos2(center + half, 1.0))
+    }
+
+    /// Zoom in by `factor`, or out for factors under one, keeping what is
+    /// `at` across the view, as a fraction of its width, in place.
+    pub fn zoom_at(&mut self, at: f64, factor: f64) {
+        let anchor = self.center + (at - 0.5) * self.width;
+        self.width = (self.width / factor).clamp(MIN_WIDTH, 1.0);
+        self.center = anchor - (at - 0.5) * self.width;
+        self.keep_in_band();
+    }
+
+    /// Move the view along the band by `by` of its width.
+    fn pan(&mut self, by: f64) {
+        self.center += by * self.width;
+        self.keep_in_band();
+    }
+
+    fn keep_in_band(&mut self) {
+        let half = self.width / 2.0;
+        self.center = self.center.clamp(half, 1.0 - half);
+    }
+
+    /// Zoom around the pointer with a scroll over `response`, pan with a
+    /// sideways scroll or a drag by `pan_button`, and zoom back out with a
+    /// double click. Returns whether the view changed.
+    pub fn update(&mut self, ui: &Ui, response: &Response, pan_button: PointerButton) -> bool {
+        let before = *self;
+        let rect = response.rect;
+        if let Some(pointer) = response.hover_pos() {
+            let scroll = ui.input(|input| input.smooth_scroll_delta);
+            if scroll.y != 0.0 {
+                let at = ((pointer.x - rect.left()) / rect.width()) as f64;
+                self.zoom_at(at, (scroll.y / SCROLL_PER_DOUBLING).exp2() as f64);
+            }
+            self.pan(-(scroll.x / rect.width()) as f64);
+        }
+        if response.dragged_by(pan_button) {
+            self.pan(-(response.drag_delta().x / rect.width()) as f64);
+        }
+        if response.double_clicked() {
+            *self = Self::default();
+        }
+        *self != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooms_around_the_pointer() {
+        let mut zoom = Zoom::default();
+        assert_eq!(zoom.band(100e6, 2e6), (100e6, 2e6));
+
+        // A quarter of the way across stays put
+        zoom.zoom_at(0.25, 4.0);
+        assert_eq!(zoom.band(100e6, 2e6), (99.625e6, 0.5e6));
+        assert_eq!(
+            zoom.uv(),
+            Rect::from_min_max(pos2(0.1875, 0.0), pos2(0.4375, 1.0))
+        );
+
+        // No further in than the narrowest view, nor out past the band
+        zoom.zoom_at(0.5, 1e6);
+        assert_eq!(zoom.width, MIN_WIDTH);
+        zoom.zoom_at(0.5, 1e-6);
+        assert_eq!(zoom, Zoom::default());
+    }
+
+    #[test]
+    fn pans_within_the_band() {
+        let mut zoom = Zoom::default();
+        zoom.zoom_at(0.5, 2.0);
+        zoom.pan(0.25);
+        assert_eq!(zoom.band(0.0, 48_000.0), (6_000.0, 24_000.0));
+        // Stops at the edge
+        zoom.pan(10.0);
+        assert_eq!(zoom.band(0.0, 48_000.0), (12_000.0, 24_000.0));
+    }
+}
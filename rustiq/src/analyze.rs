@@ -0,0 +1,165 @@
+//! `rustiq analyze`: summarize an IQ recording without the GUI.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use rustiq_engine::analysis::{AnalysisOptions, FileAnalysis, analyze_file};
+use rustiq_messages::{Decibels, Hertz, SignalRegion};
+
+pub const USAGE: &str = "\
+Usage: rustiq analyze FILE [OPTIONS]
+
+Print the duration, noise floor and strongest signals of an IQ recording.
+
+Options:
+  --rate HZ          Sample rate (default 3200000)
+  --center HZ        Frequency the recording is centered on (default 0)
+  --fft-size N       FFT size, setting the resolution (default 4096)
+  --threshold DB     Rise above the noise floor that counts as a signal (default 10)
+  --exclude LOW-HIGH Leave the band from LOW to HIGH Hz out of the noise floor
+                     and signals, e.g. a strong local transmitter; repeatable
+  --top N            Number of signals to list (default 10)
+  --png PATH         Also write the spectrogram, oldest at the top";
+
+struct Args {
+    file: PathBuf,
+    options: AnalysisOptions,
+    top: usize,
+    png: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut args = args.into_iter();
+        let mut file = None;
+        let mut options = AnalysisOptions::default();
+        let mut top = 10;
+        let mut png = None;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .with_context(|| format!("{name} needs a value\n\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "--rate" => options.sample_rate = Hertz(value(&arg)?.parse()?),
+                "--center" => options.center_frequency = Hertz(value(&arg)?.parse()?),
+                "--fft-size" => options.fft_size = value(&arg)?.parse()?,
+                "--threshold" => options.threshold = Decibels(value(&arg)?.parse()?),
+                "--exclude" => options.excluded.push(parse_region(&value(&arg)?)?),
+                "--top" => top = value(&arg)?.parse()?,
+                "--png" => png = Some(PathBuf::from(value(&arg)?)),
+                "-h" | "--help" => bail!("{USAGE}"),
+                flag if flag.starts_with('-') => bail!("Unknown option {flag}\n\n{USAGE}"),
+                _ if file.is_none() => file = Some(PathBuf::from(arg)),
+                _ => bail!("Unexpected argument {arg}\n\n{USAGE}"),
+            }
+        }
+        if options.fft_size < 2 {
+            bail!("--fft-size must be at least 2");
+        }
+        let file = file.with_context(|| format!("No file given\n\n{USAGE}"))?;
+        Ok(Self {
+            file,
+            options,
+            top,
+            png,
+        })
+    }
+}
+
+/// A region given as `LOW-HIGH` in Hz.
+fn parse_region(text: &str) -> anyhow::Result<SignalRegion> {
+    let (low, high) = text
+        .split_once('-')
+        .with_context(|| format!("--exclude takes LOW-HIGH in Hz, not {text}"))?;
+    let (low, high): (u64, u64) = (low.trim().parse()?, high.trim().parse()?);
+    if high <= low {
+        bail!("--exclude {text} ends before it starts");
+    }
+    Ok(SignalRegion {
+        frequency: Hertz((low + high) / 2),
+        bandwidth: Hertz(high - low),
+    })
+}
+
+pub fn run(args: impl IntoIterator<Item = String>) -> anyhow::Result<()> {
+    let args = Args::parse(args)?;
+    let analysis = analyze_file(&args.file, &args.options)
+        .with_context(|| format!("analyzing {}", args.file.display()))?;
+
+    println!("File:        {}", args.file.display());
+    println!("Duration:    {:.3} s", analysis.duration.as_secs_f64());
+    println!("Sample rate: {}", args.options.sample_rate);
+    println!("Resolution:  {:.1} Hz", analysis.resolution);
+    println!(
+        "Noise floor: {} (per bin, relative to full scale)",
+        analysis.noise_floor
+    );
+    println!();
+    if analysis.signals.is_empty() {
+        println!(
+            "No signals {} above the noise floor",
+            args.options.threshold
+        );
+    } else {
+        let shown = args.top.min(analysis.signals.len());
+        println!("Signals ({shown} of {}):", analysis.signals.len());
+        println!(
+            "  {:>14}  {:>12}  {:>12}  {:>12}  {:>10}  {:>10}",
+            "Frequency", "-3 dB BW", "-20 dB BW", "99% BW", "Power", "SNR"
+        );
+        for signal in &analysis.signals[..shown] {
+            println!(
+                "  {:>14}  {:>12}  {:>12}  {:>12}  {:>10}  {:>10}",
+                signal.frequency.to_string(),
+                signal.half_power_bandwidth.to_string(),
+                signal.bandwidth.to_string(),
+                signal.occupied_bandwidth.to_string(),
+                signal.power.to_string(),
+                signal.snr.to_string()
+            );
+        }
+    }
+
+    if let Some(path) = &args.png {
+        write_spectrogram(path, &analysis)
+            .with_context(|| format!("writing {}", path.display()))?;
+        println!();
+        println!("Spectrogram written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Write the spectrogram as a grayscale PNG scaled from the noise floor to the
+/// strongest bin not excluded, like the waterfall.
+fn write_spectrogram(path: &Path, analysis: &FileAnalysis) -> anyhow::Result<()> {
+    let rows = &analysis.spectrogram;
+    let width = rows.first().map_or(0, Vec::len);
+    if width == 0 {
+        bail!("the recording is too short for a spectrogram");
+    }
+    let max = rows
+        .iter()
+        .flat_map(|row| row.iter().zip(&analysis.excluded))
+        .filter(|(_, excluded)| !**excluded)
+        .map(|(db, _)| db.0)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let min = analysis.noise_floor.0;
+    let range = (max - min).max(0.01);
+    let pixels: Vec<u8> = rows
+        .iter()
+        .flatten()
+        .map(|db| ((db.0 - min) / range * 255.0).clamp(0.0, 255.0) as u8)
+        .collect();
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, rows.len() as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(())
+}
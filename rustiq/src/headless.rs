@@ -0,0 +1,319 @@
+//! Running the engine without a UI, for a receiver next to the antenna
+//! (e.g. on a Raspberry Pi) controlled from elsewhere. Any number of
+//! WebSocket clients can connect; each sends commands and is sent events
+//! as JSON text messages, in the shape the messages' serde feature gives
+//! them, over TLS (wss) if the engine has a certificate. Each client has its own queues: one whose link can't keep up is
+//! sent fewer spectrum frames, and one that falls too far behind on other
+//! events is disconnected, so the engine never waits on a client.
+
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Context;
+use flume::{Receiver, Sender, TrySendError};
+use log::{debug, error, info, warn};
+use rustiq_engine::{Engine, EngineConfig};
+use rustiq_messages::{Command, Event, RustIqError, SourceConfig};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::{StatusCode, header};
+use tungstenite::{Message, Utf8Bytes, WebSocket};
+
+use crate::transport::{self, Address, Duplex, HANDSHAKE_TIMEOUT, ServerSecurity, Socket};
+
+/// Spectrum frames queued for each client. Frames beyond these are
+/// dropped, so each client gets as many as its link carries.
+const SPECTRUM_QUEUE: usize = 2;
+
+/// Other events queued for each client. A client this far behind is
+/// disconnected.
+const EVENT_QUEUE: usize = 1024;
+
+/// How long a client's thread waits for a command before sending what is
+/// queued for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Where the headless engine listens and what clients must present.
+pub struct HeadlessOptions {
+    /// `HOST:PORT` to listen on for WebSocket clients
+    pub listen: String,
+    /// Token each client must present, as `Authorization: Bearer TOKEN`,
+    /// and the certificate to serve wss with
+    pub security: ServerSecurity,
+}
+
+/// An event serialized as JSON, shareable between clients.
+type Text = Utf8Bytes;
+
+/// Queues to one client, emptied by its thread.
+struct Client {
+    events: Sender<Text>,
+    spectrum: Sender<Text>,
+}
+
+type Clients = Arc<Mutex<Vec<Client>>>;
+
+/// Run the engine for WebSocket clients connecting as `options` say
+/// (blocking), journaling the session to `journal` if given. The engine
+/// runs with or without clients, until Ctrl-C or a client sends `Stop`.
+pub fn serve(
+    options: &HeadlessOptions,
+    source_config: SourceConfig,
+    journal: Option<PathBuf>,
+    config: EngineConfig,
+) -> anyhow::Result<()> {
+    options
+        .security
+        .check(&Address::Tcp(options.listen.clone()));
+    let listener = TcpListener::bind(&options.listen)
+        .with_context(|| format!("binding {}", options.listen))?;
+    let scheme = if options.security.tls.is_some() {
+        "wss"
+    } else {
+        "ws"
+    };
+    info!(
+        "Headless engine listening on {}://{}",
+        scheme, options.listen
+    );
+
+    let (cmd_tx, cmd_rx) = config.command_channel();
+    let (event_tx, event_rx) = config.event_channel();
+    let (spectrum_tx, spectrum_rx) = config.spectrum_channel();
+
+    let stop = cmd_tx.clone();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(Command::Stop);
+    })
+    .context("installing Ctrl-C handler")?;
+
+    let clients = Clients::default();
+    {
+        let clients = clients.clone();
+        let security = options.security.clone();
+        thread::spawn(move || accept_clients(&listener, &security, &clients, &cmd_tx));
+    }
+    let engine_handle = thread::spawn(move || {
+        let mut engine = Engine::new(cmd_rx, event_tx, source_config)
+            .with_spectrum_channel(spectrum_tx)
+            .with_limits(&config);
+        if let Some(journal) = journal {
+            engine = engine.with_journal(journal);
+        }
+        engine.run()
+    });
+
+    loop {
+        // Other events go out ahead of queued spectrum frames
+        let event = match event_rx.try_recv() {
+            Ok(event) => Ok(event),
+            Err(_) => flume::Selector::new()
+                .recv(&event_rx, |event| event)
+                .recv(&spectrum_rx, |event| event)
+                .wait(),
+        };
+        let Ok(event) = event else {
+            // The engine exited
+            break;
+        };
+        broadcast(&clients, &event);
+    }
+    // Dropping the queues closes the clients' connections
+    clients
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+
+    engine_handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("Engine thread panicked"))??;
+    Ok(())
+}
+
+/// Send `event` to every client.
+fn broadcast(clients: &Clients, event: &Event) {
+    let mut clients = clients.lock().unwrap_or_else(PoisonError::into_inner);
+    if clients.is_empty() {
+        return;
+    }
+    let text: Text = match serde_json::to_string(event) {
+        Ok(json) => json.into(),
+        Err(e) => {
+            debug!("Failed to serialize event: {}", e);
+            return;
+        }
+    };
+    if matches!(event, Event::SpectrumData(_)) {
+        clients.retain(|client| {
+            !matches!(
+                client.spectrum.try_send(text.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+        return;
+    }
+    clients.retain(|client| match client.events.try_send(text.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            info!("Client fell too far behind, disconnecting it");
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
+
+/// Serve each client connecting, on a thread of its own, bringing it up to
+/// date with the engine's state.
+fn accept_clients(
+    listener: &TcpListener,
+    security: &ServerSecurity,
+    clients: &Clients,
+    cmd_tx: &Sender<Command>,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to accept client: {}", e);
+                continue;
+            }
+        };
+        let (events_tx, events_rx) = flume::bounded(EVENT_QUEUE);
+        let (spectrum_tx, spectrum_rx) = flume::bounded(SPECTRUM_QUEUE);
+        let security = security.clone();
+        let cmd_tx = cmd_tx.clone();
+        let clients = clients.clone();
+        // The handshake happens on the client's thread, so one that stalls
+        // doesn't hold up the ones behind it
+        thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "client".to_string(), |addr| addr.to_string());
+            let mut ws = match open(stream, &security) {
+                Ok(ws) => ws,
+                Err(e) => {
+                    warn!("Turned {} away: {}", peer, e);
+                    return;
+                }
+            };
+            {
+                let mut clients = clients.lock().unwrap_or_else(PoisonError::into_inner);
+                clients.push(Client {
+                    events: events_tx,
+                    spectrum: spectrum_tx,
+                });
+                info!("{} connected, {} clients", peer, clients.len());
+            }
+            if cmd_tx.send(Command::Resync).is_err() {
+                return;
+            }
+            serve_client(&mut ws, &peer, &events_rx, &spectrum_rx, &cmd_tx);
+            let _ = ws.close(None);
+            let _ = ws.flush();
+            info!("{} disconnected", peer);
+        });
+    }
+}
+
+/// Complete the TLS and WebSocket handshakes on `stream`, turning the
+/// client away unless it presents the token `security` asks for.
+fn open(stream: TcpStream, security: &ServerSecurity) -> anyhow::Result<WebSocket<Duplex>> {
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let (reader, writer) = transport::secure(Socket::Tcp(stream), security.tls.as_ref())?;
+    // The callback's error type is tungstenite's
+    #[allow(clippy::result_large_err)]
+    let check = |request: &Request, response: Response| match &security.token {
+        Some(token)
+            if !presented_token(request).is_some_and(|t| transport::same_token(t, token)) =>
+        {
+            let mut refusal = ErrorResponse::new(Some("wrong token".to_string()));
+            *refusal.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(refusal)
+        }
+        _ => Ok(response),
+    };
+    let ws = tungstenite::accept_hdr(Duplex { reader, writer }, check)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    ws.get_ref().reader.set_read_timeout(Some(POLL_INTERVAL))?;
+    Ok(ws)
+}
+
+/// The bearer token in the request's `Authorization` header, if there is
+/// one.
+fn presented_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Feed the client's commands to the engine and send it its queued events,
+/// until either side goes away.
+fn serve_client(
+    ws: &mut WebSocket<Duplex>,
+    peer: &str,
+    events: &Receiver<Text>,
+    spectrum: &Receiver<Text>,
+    cmd_tx: &Sender<Command>,
+) {
+    loop {
+        match ws.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<Command>(&text) {
+                Ok(command) => {
+                    if cmd_tx.send(command).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    debug!("Unreadable command from {}: {}", peer, e);
+                    let error = Event::Error(RustIqError::Protocol {
+                        peer: peer.to_string(),
+                        detail: format!("unreadable command: {e}"),
+                    });
+                    if let Ok(json) = serde_json::to_string(&error)
+                        && ws.write(Message::text(json)).is_err()
+                    {
+                        return;
+                    }
+                }
+            },
+            Ok(Message::Close(_)) => return,
+            // Pings are answered as the next message is written
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => {
+                debug!("Lost {}: {}", peer, e);
+                return;
+            }
+        }
+
+        // Other events go out ahead of queued spectrum frames
+        let queued = events.try_iter().chain(spectrum.try_recv().ok());
+        for text in queued {
+            if let Err(e) = ws.write(Message::Text(text)) {
+                debug!("Failed to send to {}: {}", peer, e);
+                return;
+            }
+        }
+        match ws.flush() {
+            Ok(()) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => {
+                debug!("Failed to send to {}: {}", peer, e);
+                return;
+            }
+        }
+        if events.is_disconnected() {
+            // Dropped for falling behind, or the engine stopped
+            return;
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Running the engine in its own process, connected to the UI over a Unix
+//! domain socket or TCP (see `transport`). Commands and events cross the
+//! socket as wire frames; each side bridges them onto the same flume
+//! channels the in-process mode uses, with spectrum frames split back out
+//! onto their own channel for the UI.
+
+use std::io::{BufReader, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use flume::{Receiver, Sender};
+use log::{debug, error, info};
+use rustiq_engine::{Engine, EngineConfig};
+use rustiq_messages::{
+    Command, CommandSender, Event, EventReceiver, RustIqError, SourceConfig, Transport,
+    WireTransport, read_frame,
+};
+
+use crate::spectators::Spectators;
+use crate::streaming::{Expander, Outlet};
+use crate::transport::{self, Address, ClientSecurity, Listener, Reader, ServerSecurity, Writer};
+use crate::websocket::WebSocketTransport;
+
+/// How long the UI waits for a freshly started engine process to listen.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events and spectrum frames queued for the UI's connection. The engine
+/// waits for room, except for spectrum frames to a remote UI, which are
+/// dropped so its link sets their pace.
+const UI_EVENT_QUEUE: usize = 16;
+const UI_SPECTRUM_QUEUE: usize = 2;
+
+/// Where and how the engine serves its UIs.
+pub struct ServeOptions {
+    pub socket: Address,
+    /// Keep running after the UI disconnects, for the next UI
+    pub keep_running: bool,
+    /// Where read-only spectators can watch
+    pub spectate: Option<Address>,
+    /// What UIs and spectators must present
+    pub security: ServerSecurity,
+}
+
+/// Run the engine for a UI connecting as `options` say (blocking),
+/// journaling the session to `journal` if given. `config` sizes the
+/// channels feeding the socket. The engine stops when the UI disconnects,
+/// unless `keep_running`, when it carries on and serves the next UI to
+/// connect.
+pub fn serve_engine(
+    options: &ServeOptions,
+    source_config: SourceConfig,
+    journal: Option<PathBuf>,
+    config: EngineConfig,
+) -> anyhow::Result<()> {
+    let ServeOptions {
+        socket,
+        keep_running,
+        spectate,
+        security,
+    } = options;
+    let keep_running = *keep_running;
+    security.check(socket);
+    let spectators = match spectate {
+        Some(spectate) => {
+            security.check(spectate);
+            Some(Spectators::listen(spectate, security.clone())?)
+        }
+        None => None,
+    };
+    let listener = Listener::bind(socket).with_context(|| format!("binding {socket}"))?;
+    info!("Engine listening on {}", socket);
+    let stream = transport::accept(&listener, security)?;
+    if !keep_running {
+        socket.remove();
+    }
+
+    let (cmd_tx, cmd_rx) = config.command_channel();
+    let (event_tx, event_rx) = config.event_channel();
+    let (spectrum_tx, spectrum_rx) = config.spectrum_channel();
+
+    let connection = Arc::new(Mutex::new(None));
+    attach(stream, &connection, &cmd_tx, keep_running)?;
+    if keep_running {
+        let connection = connection.clone();
+        let security = security.clone();
+        thread::spawn(move || accept_uis(&listener, &security, &connection, &cmd_tx));
+    } else {
+        // Only the UI's command side stops the engine
+        drop(cmd_tx);
+    }
+    let engine_handle = thread::spawn(move || {
+        let mut engine = Engine::new(cmd_rx, event_tx, source_config)
+            .with_spectrum_channel(spectrum_tx)
+            .with_limits(&config);
+        if let Some(journal) = journal {
+            engine = engine.with_journal(journal);
+        }
+        engine.run()
+    });
+
+    loop {
+        // Other events go out ahead of queued spectrum frames
+        let event = match event_rx.try_recv() {
+            Ok(event) => Ok(event),
+            Err(_) => flume::Selector::new()
+                .recv(&event_rx, |event| event)
+                .recv(&spectrum_rx, |event| event)
+                .wait(),
+        };
+        let Ok(event) = event else {
+            // The engine exited
+            break;
+        };
+        if let Some(spectators) = &spectators {
+            spectators.broadcast(&event);
+        }
+        let mut connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+        // Without a UI attached the event is dropped, so the engine never
+        // waits on one
+        let Some(outlet) = connection.as_ref() else {
+            continue;
+        };
+        if !outlet.send(event) {
+            if !keep_running {
+                // The UI went away; the command side stops the engine
+                break;
+            }
+            info!("UI detached, engine keeps running");
+            *connection = None;
+        }
+    }
+    drop(event_rx);
+    drop(spectrum_rx);
+    let outlet = connection
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(outlet) = outlet {
+        outlet.finish();
+    }
+    if keep_running {
+        socket.remove();
+    }
+    if let Some(spectate) = spectate {
+        spectate.remove();
+    }
+
+    engine_handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("Engine thread panicked"))??;
+    Ok(())
+}
+
+/// The attached UI's side of the socket, for sending it events.
+type Connection = Arc<Mutex<Option<Outlet>>>;
+
+/// Serve each UI connecting to the running engine in turn, bringing it up
+/// to date with the engine's state.
+fn accept_uis(
+    listener: &Listener,
+    security: &ServerSecurity,
+    connection: &Connection,
+    cmd_tx: &Sender<Command>,
+) {
+    loop {
+        let attached = transport::accept(listener, security)
+            .and_then(|stream| attach(stream, connection, cmd_tx, true));
+        match attached {
+            Ok(()) => {
+                if cmd_tx.send(Command::Resync).is_err() {
+                    return;
+                }
+            }
+            Err(e) => error!("Failed to attach UI: {}", e),
+        }
+    }
+}
+
+/// Make `stream` the UI the engine serves, forwarding its commands. Only one
+/// UI is served at a time; a new one takes over from the last.
+fn attach(
+    (reader, writer): (Reader, Writer),
+    connection: &Connection,
+    cmd_tx: &Sender<Command>,
+    keep_running: bool,
+) -> std::io::Result<()> {
+    let cmd_tx = cmd_tx.clone();
+    thread::spawn(move || forward_commands(reader, &cmd_tx, !keep_running));
+    let previous = connection
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Outlet::open(writer, UI_EVENT_QUEUE, UI_SPECTRUM_QUEUE));
+    if let Some(previous) = previous {
+        info!("Another UI connected, detaching the last one");
+        previous.close();
+    }
+    debug!("UI connected");
+    Ok(())
+}
+
+/// Feed commands from the socket to the engine until the UI disconnects,
+/// then stop the engine if `stop_on_disconnect`.
+fn forward_commands(stream: Reader, cmd_tx: &Sender<Command>, stop_on_disconnect: bool) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        match read_frame::<Command>(&mut reader) {
+            Ok(command) => {
+                if cmd_tx.send(command).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                if e.kind() != ErrorKind::UnexpectedEof {
+                    error!("Failed to read command: {}", e);
+                }
+                if stop_on_disconnect {
+                    let _ = cmd_tx.send(Command::Stop);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Channels for the UI: events, spectrum frames and commands.
+pub type UiChannels = (Receiver<Event>, Receiver<Event>, Sender<Command>);
+
+/// Connect to the engine at `address` and return channels for the UI sized
+/// by `config`: a WebSocket to a headless engine for `ws://HOST:PORT` (or
+/// `wss://` with TLS), else the engine's socket (see `Address`), presenting `security`.
+pub fn connect(
+    address: &str,
+    security: &ClientSecurity,
+    config: EngineConfig,
+) -> anyhow::Result<UiChannels> {
+    if address.starts_with("ws://") || address.starts_with("wss://") {
+        let transport = WebSocketTransport::connect(address, security)
+            .with_context(|| format!("connecting to {address}"))?;
+        info!("Connected to headless engine on {}", address);
+        return Ok(bridge(transport, address.to_string(), config));
+    }
+    connect_ui(&Address::parse(address), security, config)
+}
+
+/// Connect to an engine listening on `socket`, presenting `security`, and
+/// return channels for the UI sized by `config`. Waits for the socket to
+/// appear, as a just-spawned engine may not be listening yet.
+pub fn connect_ui(
+    socket: &Address,
+    security: &ClientSecurity,
+    config: EngineConfig,
+) -> anyhow::Result<UiChannels> {
+    let started = Instant::now();
+    let (reader, writer) = loop {
+        match transport::connect(socket, security) {
+            Ok(halves) => break halves,
+            Err(e)
+                if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused)
+                    && started.elapsed() < CONNECT_TIMEOUT =>
+            {
+                debug!("Engine not listening yet: {}", e);
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("connecting to {socket}"));
+            }
+        }
+    };
+    info!("Connected to engine on {}", socket);
+    Ok(bridge(
+        WireTransport { reader, writer },
+        socket.to_string(),
+        config,
+    ))
+}
+
+/// Bridge `transport` to `peer` onto channels for the UI sized by
+/// `config`, with a thread for each direction. Reduced spectrum frames are
+/// expanded back to full ones on the way.
+fn bridge(transport: impl Transport, peer: String, config: EngineConfig) -> UiChannels {
+    let (mut events, mut commands) = transport.split();
+    let (cmd_tx, cmd_rx) = config.command_channel();
+    let (event_tx, event_rx) = config.event_channel();
+    let (spectrum_tx, spectrum_rx) = config.spectrum_channel();
+
+    thread::spawn(move || {
+        let mut expander = Expander::default();
+        loop {
+            let event = match events.recv() {
+                Ok(Event::ReducedSpectrum(reduced)) => match expander.expand(reduced) {
+                    Ok(frame) => Event::SpectrumData(frame),
+                    Err(e) => {
+                        debug!("Failed to expand spectrum frame: {}", e);
+                        continue;
+                    }
+                },
+                Ok(event) => event,
+                Err(e) => {
+                    // The UI keeps running with its last state
+                    error!("Lost connection to engine: {}", e);
+                    let _ = event_tx.send(Event::Error(RustIqError::Protocol {
+                        peer,
+                        detail: format!("lost the connection to the engine: {e}"),
+                    }));
+                    return;
+                }
+            };
+            let sent = match event {
+                Event::SpectrumData(_) => spectrum_tx.send(event),
+                event => event_tx.send(event).is_ok(),
+            };
+            if !sent {
+                return;
+            }
+        }
+    });
+    thread::spawn(move || {
+        for command in cmd_rx.iter() {
+            if let Err(e) = commands.send(&command) {
+                error!("Failed to send command to engine: {}", e);
+                return;
+            }
+        }
+    });
+
+    (event_rx, spectrum_rx, cmd_tx)
+}
@@ -1,9 +1,264 @@
-use rustiq_engine::Engine;
-use rustiq_messages::{Command, Hertz, SourceConfig};
+mod analyze;
+mod headless;
+mod ipc;
+mod record;
+mod spectators;
+mod streaming;
+mod transport;
+mod verify;
+mod websocket;
 
+use rustiq_engine::{Engine, EngineConfig, Overflow, ResourceLimits};
+use rustiq_messages::{Command, Hertz, IqFormat, SourceConfig};
+
+use anyhow::bail;
+use headless::HeadlessOptions;
+use ipc::ServeOptions;
 use log::LevelFilter;
+use rustiq_ui::{Connector, LastSessionFile};
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::Arc;
+use transport::{Address, ClientSecurity, ServerSecurity};
+
+const USAGE: &str = "\
+Usage:
+  rustiq [--engine-process] [FILE]   Run the UI, with the engine on a thread or in a child process
+  rustiq --connect SOCKET [--token-file FILE] [--tls-ca FILE]
+                                     Run the UI against an engine listening on SOCKET,
+                                     or a headless engine at ws://HOST:PORT
+                                     (wss://HOST:PORT with TLS)
+  rustiq --headless HOST:PORT [--token-file FILE] [--tls-cert FILE --tls-key FILE] [FILE]
+                                     Run only the engine, controlled by any number of
+                                     WebSocket clients sending commands and receiving
+                                     events as JSON
+  rustiq engine --socket SOCKET [--keep-running] [--spectate SOCKET]
+                [--token-file FILE] [--tls-cert FILE --tls-key FILE] [FILE]
+                                     Run only the engine, serving one UI on SOCKET;
+                                     with --keep-running, the engine outlives the UI
+                                     and serves the next one to connect; with
+                                     --spectate, any number of UIs can watch
+                                     read-only on the second socket
+  rustiq analyze FILE [OPTIONS]      Summarize a recording (see rustiq analyze --help)
+  rustiq record [OPTIONS] OUTPUT     Capture to SigMF (see rustiq record --help)
+  rustiq verify RECORDING            Check a capture against its checksums
+
+Channel options (all modes):
+  --spectrum-buffer N   Spectrum frames queued for the UI (default 1)
+  --block-spectrum      Hold up the engine when the spectrum queue is full
+                        instead of dropping the oldest frame
+  --event-buffer N      Other events queued for the UI (default 1)
+  --command-buffer N    Commands queued for the engine (default unlimited)
+
+Resource limits (all modes but --connect), e.g. on a single-board computer:
+  --memory-limit MB     Memory for spectrum frames, which caps the FFT size
+  --thread-limit N      Threads for background work, such as the overview
+                        of a file played or the self test
+  --disk-limit MB       Disk space a recording may take before it is stopped
+
+A SOCKET of the form HOST:PORT is TCP, anything else a Unix socket path.
+Remote options (engine, --connect and --headless):
+  --token-file FILE     Token a client must present, read from FILE; WebSocket
+                        clients give it as an Authorization: Bearer header
+  --tls-cert FILE       Serve TLS with the PEM certificate chain in FILE
+  --tls-key FILE        and the PEM private key in FILE
+  --tls-ca FILE         Connect with TLS, trusting the PEM authority in FILE";
+
+/// Where the engine runs relative to the UI.
+enum Mode {
+    /// Engine on a thread of the UI process (the default)
+    InProcess,
+    /// Engine in a child process, so a DSP crash can't take down the UI
+    EngineProcess,
+    /// UI only, connecting to an engine started separately (e.g. as a user
+    /// with access to the radio hardware): an engine's socket, or
+    /// `ws://HOST:PORT` for a headless engine
+    Connect(String, ClientSecurity),
+    /// Engine only, listening for a UI
+    Engine(ServeOptions),
+    /// Engine only, controlled over WebSocket with JSON messages
+    Headless(HeadlessOptions),
+}
+
+struct Args {
+    mode: Mode,
+    /// IQ file to read instead of the signal generator
+    file: Option<PathBuf>,
+    channels: EngineConfig,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut args = args.into_iter().peekable();
+        let engine = args.next_if(|arg| arg == "engine").is_some();
+        let mut socket = None;
+        let mut headless = None;
+        let mut engine_process = false;
+        let mut keep_running = false;
+        let mut spectate = None;
+        let mut token_file = None;
+        let (mut tls_cert, mut tls_key, mut tls_ca) = (None, None, None);
+        let mut file = None;
+        let mut channels = EngineConfig::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--spectrum-buffer" => channels.spectrum_capacity = capacity(&arg, args.next())?,
+                "--block-spectrum" => channels.spectrum_overflow = Overflow::Block,
+                "--event-buffer" => channels.event_capacity = capacity(&arg, args.next())?,
+                "--command-buffer" => {
+                    channels.command_capacity = Some(capacity(&arg, args.next())?);
+                }
+                "--memory-limit" => {
+                    channels.limits.memory = Some(limit::<usize>(&arg, args.next())? * 1_000_000);
+                }
+                "--thread-limit" => channels.limits.threads = Some(limit(&arg, args.next())?),
+                "--disk-limit" => {
+                    channels.limits.disk = Some(limit::<u64>(&arg, args.next())? * 1_000_000);
+                }
+                "--socket" if engine => socket = args.next(),
+                "--keep-running" if engine => keep_running = true,
+                "--spectate" if engine => spectate = args.next().as_deref().map(Address::parse),
+                "--connect" if !engine => socket = args.next(),
+                "--headless" if !engine => headless = Some(args.next()),
+                "--token-file" => token_file = args.next().map(PathBuf::from),
+                "--tls-cert" => tls_cert = args.next().map(PathBuf::from),
+                "--tls-key" => tls_key = args.next().map(PathBuf::from),
+                "--tls-ca" if !engine => tls_ca = args.next().map(PathBuf::from),
+                "--engine-process" if !engine => engine_process = true,
+                "-h" | "--help" => bail!("{USAGE}"),
+                flag if flag.starts_with('-') => bail!("Unknown option {flag}\n\n{USAGE}"),
+                _ if file.is_none() => file = Some(PathBuf::from(arg)),
+                _ => bail!("Unexpected argument {arg}\n\n{USAGE}"),
+            }
+        }
+
+        let mode = match (engine, socket, engine_process) {
+            (false, None, false) if headless.is_some() => {
+                let Some(Some(listen)) = headless else {
+                    bail!("--headless needs HOST:PORT\n\n{USAGE}");
+                };
+                if tls_ca.is_some() {
+                    bail!("--tls-ca is for --connect\n\n{USAGE}");
+                }
+                Mode::Headless(HeadlessOptions {
+                    listen,
+                    security: ServerSecurity::load(
+                        token_file.as_deref(),
+                        tls_cert.as_deref(),
+                        tls_key.as_deref(),
+                    )?,
+                })
+            }
+            _ if headless.is_some() => {
+                bail!("--headless conflicts with --connect and --engine-process")
+            }
+            (true, Some(socket), _) => Mode::Engine(ServeOptions {
+                socket: Address::parse(&socket),
+                keep_running,
+                spectate,
+                security: ServerSecurity::load(
+                    token_file.as_deref(),
+                    tls_cert.as_deref(),
+                    tls_key.as_deref(),
+                )?,
+            }),
+            (true, None, _) => bail!("engine needs --socket\n\n{USAGE}"),
+            (false, Some(_), true) => bail!("--connect and --engine-process conflict"),
+            (false, Some(socket), false) => Mode::Connect(
+                socket,
+                ClientSecurity::load(token_file.as_deref(), tls_ca.as_deref())?,
+            ),
+            (false, None, true) => Mode::EngineProcess,
+            (false, None, false) => Mode::InProcess,
+        };
+        if !matches!(
+            mode,
+            Mode::Engine(_) | Mode::Connect(..) | Mode::Headless(_)
+        ) && (token_file.is_some() || tls_ca.is_some())
+        {
+            bail!("--token-file and --tls-ca are for --connect\n\n{USAGE}");
+        }
+        if !matches!(mode, Mode::Engine(_) | Mode::Headless(_))
+            && (tls_cert.is_some() || tls_key.is_some())
+        {
+            bail!("--tls-cert and --tls-key are for engine and --headless\n\n{USAGE}");
+        }
+        if matches!(mode, Mode::Connect(..)) && file.is_some() {
+            bail!("The engine chooses the source with --connect\n\n{USAGE}");
+        }
+        if matches!(mode, Mode::Connect(..)) && channels.limits != ResourceLimits::default() {
+            bail!("The engine is limited where it runs, not with --connect\n\n{USAGE}");
+        }
+        Ok(Self {
+            mode,
+            file,
+            channels,
+        })
+    }
+
+    /// The channel options, to pass on to an engine process.
+    fn channel_args(&self) -> Vec<String> {
+        let defaults = EngineConfig::default();
+        let mut args = Vec::new();
+        if self.channels.spectrum_capacity != defaults.spectrum_capacity {
+            args.push("--spectrum-buffer".to_string());
+            args.push(self.channels.spectrum_capacity.to_string());
+        }
+        if self.channels.spectrum_overflow == Overflow::Block {
+            args.push("--block-spectrum".to_string());
+        }
+        if self.channels.event_capacity != defaults.event_capacity {
+            args.push("--event-buffer".to_string());
+            args.push(self.channels.event_capacity.to_string());
+        }
+        if let Some(capacity) = self.channels.command_capacity {
+            args.push("--command-buffer".to_string());
+            args.push(capacity.to_string());
+        }
+        let limits = self.channels.limits;
+        if let Some(memory) = limits.memory {
+            args.push("--memory-limit".to_string());
+            args.push((memory / 1_000_000).to_string());
+        }
+        if let Some(threads) = limits.threads {
+            args.push("--thread-limit".to_string());
+            args.push(threads.to_string());
+        }
+        if let Some(disk) = limits.disk {
+            args.push("--disk-limit".to_string());
+            args.push((disk / 1_000_000).to_string());
+        }
+        args
+    }
+
+    /// Source for the engine: the IQ file if given, else the signal generator.
+    fn source_config(&self) -> SourceConfig {
+        self.file
+            .clone()
+            .map(|path| SourceConfig::File {
+                format: IqFormat::from_extension(&path),
+                path,
+                sample_rate: Hertz(3_200_000), // 3.2 MHz sample rate
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Parse the value of a resource limit option.
+fn limit<T: std::str::FromStr>(flag: &str, value: Option<String>) -> anyhow::Result<T> {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(limit)) => Ok(limit),
+        _ => bail!("{flag} needs a number\n\n{USAGE}"),
+    }
+}
+
+/// Parse the value of a channel capacity option.
+fn capacity(flag: &str, value: Option<String>) -> anyhow::Result<usize> {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(capacity)) if capacity > 0 => Ok(capacity),
+        _ => bail!("{flag} needs a positive number\n\n{USAGE}"),
+    }
+}
 
 fn main() -> anyhow::Result<()> {
     env_logger::builder()
@@ -23,27 +278,148 @@ fn main() -> anyhow::Result<()> {
         .filter_module("rustiq_ui", LevelFilter::Trace)
         .init();
 
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "analyze").is_some() {
+        return analyze::run(args);
+    }
+    if args.next_if(|arg| arg == "record").is_some() {
+        return record::run(args);
+    }
+    if args.next_if(|arg| arg == "verify").is_some() {
+        return verify::run(args);
+    }
+    let args = Args::parse(args)?;
+    match &args.mode {
+        Mode::InProcess => run_in_process(
+            args.source_config(),
+            args.channels,
+            last_session(args.file.is_none()),
+        ),
+        Mode::EngineProcess => run_engine_process(&args),
+        Mode::Connect(socket, security) => {
+            let (event_rx, spectrum_rx, cmd_tx) = ipc::connect(socket, security, args.channels)?;
+            // Disconnecting stops the engine, unless it keeps running; it
+            // is left as it is found
+            rustiq_ui::run(
+                event_rx,
+                spectrum_rx,
+                cmd_tx,
+                last_session(false),
+                Some(connector(args.channels, security.clone(), None)),
+            )?;
+            Ok(())
+        }
+        Mode::Engine(options) => ipc::serve_engine(
+            options,
+            args.source_config(),
+            session_journal(),
+            args.channels,
+        ),
+        Mode::Headless(options) => headless::serve(
+            options,
+            args.source_config(),
+            session_journal(),
+            args.channels,
+        ),
+    }
+}
+
+/// Directory for files that outlive a run, like session journals:
+/// `$XDG_STATE_HOME/rustiq`, else `~/.local/state/rustiq`.
+fn state_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+    let dir = base.join("rustiq");
+    match std::fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(e) => {
+            log::warn!("Failed to create {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+/// Directory for settings: `$XDG_CONFIG_HOME/rustiq`, else
+/// `~/.config/rustiq`.
+fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("rustiq"))
+}
+
+/// Where the UI keeps what it was set to between runs, putting the
+/// engine's part back if `restore_engine`.
+fn last_session(restore_engine: bool) -> Option<LastSessionFile> {
+    config_dir().map(|dir| LastSessionFile {
+        path: dir.join("last-session.toml"),
+        restore_engine,
+    })
+}
+
+/// Journal for recovering the engine's session after a crash.
+fn session_journal() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("session.journal"))
+}
+
+/// How the UI switches to another engine while running: connecting with
+/// `security`, the token typed in the UI aside, on channels sized by
+/// `channels`. The engine it started with is stopped once connected, if
+/// it was started for it (`own_engine`).
+fn connector(
+    channels: EngineConfig,
+    security: ClientSecurity,
+    own_engine: Option<flume::Sender<Command>>,
+) -> Connector {
+    Arc::new(move |address, token| {
+        let security = ClientSecurity {
+            token: token.map(str::to_string).or(security.token.clone()),
+            ..security.clone()
+        };
+        let ui = ipc::connect(address, &security, channels)?;
+        if let Some(own_engine) = &own_engine {
+            let _ = own_engine.send(Command::Stop);
+        }
+        Ok(ui)
+    })
+}
+
+fn run_in_process(
+    source_config: SourceConfig,
+    channels: EngineConfig,
+    last_session: Option<LastSessionFile>,
+) -> anyhow::Result<()> {
     // Create flume channels for bidirectional communication
-    let (cmd_tx, cmd_rx) = flume::unbounded();
-    let (event_tx, event_rx) = flume::bounded(1);
-
-    // Parse CLI arguments - if a file path is provided, use FileSource
-    let source_config = std::env::args()
-        .nth(1)
-        .map(|path| SourceConfig::File {
-            path: PathBuf::from(path),
-            sample_rate: Hertz(3_200_000), // 3.2 MHz sample rate
-        })
-        .unwrap_or_default();
+    let (cmd_tx, cmd_rx) = channels.command_channel();
+    let (event_tx, event_rx) = channels.event_channel();
+    let (spectrum_tx, spectrum_rx) = channels.spectrum_channel();
 
     // Spawn engine thread
     let engine_handle = std::thread::spawn(move || {
-        let engine = Engine::new(cmd_rx, event_tx, source_config);
+        let mut engine = Engine::new(cmd_rx, event_tx, source_config)
+            .with_spectrum_channel(spectrum_tx)
+            .with_limits(&channels);
+        if let Some(journal) = session_journal() {
+            engine = engine.with_journal(journal);
+        }
         engine.run().expect("Engine failed");
     });
 
     // Run UI on main thread (blocking)
-    rustiq_ui::run(event_rx, cmd_tx.clone())?;
+    rustiq_ui::run(
+        event_rx,
+        spectrum_rx,
+        cmd_tx.clone(),
+        last_session,
+        Some(connector(
+            channels,
+            ClientSecurity::default(),
+            Some(cmd_tx.clone()),
+        )),
+    )?;
 
     // UI has exited - send stop command to engine
     let _ = cmd_tx.send(Command::Stop);
@@ -55,3 +431,46 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Start this executable as an engine process and run the UI against it.
+fn run_engine_process(args: &Args) -> anyhow::Result<()> {
+    let socket = std::env::temp_dir().join(format!("rustiq-{}.sock", std::process::id()));
+    let mut engine = std::process::Command::new(std::env::current_exe()?);
+    engine.arg("engine").arg("--socket").arg(&socket);
+    engine.args(args.channel_args());
+    if let Some(file) = &args.file {
+        engine.arg(file);
+    }
+    let mut child = engine.spawn()?;
+
+    let address = Address::Unix(socket);
+    let security = ClientSecurity::default();
+    let (event_rx, spectrum_rx, cmd_tx) = match ipc::connect_ui(&address, &security, args.channels)
+    {
+        Ok(channels) => channels,
+        Err(e) => {
+            let _ = child.kill();
+            return Err(e);
+        }
+    };
+    rustiq_ui::run(
+        event_rx,
+        spectrum_rx,
+        cmd_tx.clone(),
+        last_session(args.file.is_none()),
+        Some(connector(
+            args.channels,
+            ClientSecurity::default(),
+            Some(cmd_tx.clone()),
+        )),
+    )?;
+
+    // The engine also stops when the socket closes, should this not arrive
+    let _ = cmd_tx.send(Command::Stop);
+    drop(cmd_tx);
+    let status = child.wait()?;
+    if !status.success() {
+        log::warn!("Engine process exited with {}", status);
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! `rustiq record`: capture a source to a SigMF recording without the GUI.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use log::warn;
+use rustiq_engine::journal::{self, Journal};
+use rustiq_engine::recording::{Recording, RecordingOptions};
+use rustiq_messages::{Decibels, Hertz, IqFormat, SessionRecord, SourceConfig};
+
+pub const USAGE: &str = "\
+Usage: rustiq record [OPTIONS] OUTPUT
+
+Capture to OUTPUT.sigmf-data and OUTPUT.sigmf-meta until the duration is
+reached, the source ends or Ctrl-C is pressed. Checksums of the data go to
+OUTPUT.sigmf-sha256, for rustiq verify.
+
+Options:
+  --device DEVICE    generator (the default), file:PATH to read IQ samples from,
+                     rtl_tcp:HOST:PORT for a dongle served by rtl_tcp, or rtlsdr
+                     when built with the rtlsdr feature
+  --freq HZ          Frequency to tune to; for the metadata only, except on
+                     hardware (default 0)
+  --rate HZ          Sample rate (default 48000 for the generator, 3200000 for
+                     files, 2400000 for rtlsdr and rtl_tcp)
+  --gain DB          Tuner gain for rtlsdr and rtl_tcp (default 20)
+  --duration SECS    Stop after this long (default: until Ctrl-C)
+  --description TEXT Description for the metadata";
+
+struct Args {
+    output: PathBuf,
+    source_config: SourceConfig,
+    options: RecordingOptions,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut args = args.into_iter();
+        let mut output = None;
+        let mut device = "generator".to_string();
+        let mut rate = None;
+        let mut gain = Decibels(20.0);
+        let mut options = RecordingOptions {
+            center_frequency: Hertz(0),
+            duration: None,
+            description: None,
+            hardware: None,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .with_context(|| format!("{name} needs a value\n\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "--device" => device = value(&arg)?,
+                "--freq" => options.center_frequency = Hertz(value(&arg)?.parse()?),
+                "--rate" => rate = Some(Hertz(value(&arg)?.parse()?)),
+                "--gain" => gain = Decibels(value(&arg)?.parse()?),
+                "--duration" => {
+                    let secs: f64 = value(&arg)?.parse()?;
+                    options.duration = Some(
+                        Duration::try_from_secs_f64(secs)
+                            .with_context(|| format!("invalid duration {secs}"))?,
+                    );
+                }
+                "--description" => options.description = Some(value(&arg)?),
+                "-h" | "--help" => bail!("{USAGE}"),
+                flag if flag.starts_with('-') => bail!("Unknown option {flag}\n\n{USAGE}"),
+                _ if output.is_none() => output = Some(PathBuf::from(arg)),
+                _ => bail!("Unexpected argument {arg}\n\n{USAGE}"),
+            }
+        }
+
+        let remote = device
+            .strip_prefix("rtl_tcp:")
+            .and_then(|address| address.rsplit_once(':'));
+        let source_config = match (device.as_str(), device.strip_prefix("file:"), remote) {
+            ("generator", _, _) => match SourceConfig::default() {
+                SourceConfig::SignalGenerator {
+                    sample_rate,
+                    tones,
+                    snr,
+                    sweep,
+                } => SourceConfig::SignalGenerator {
+                    sample_rate: rate.unwrap_or(sample_rate),
+                    tones,
+                    snr,
+                    sweep,
+                },
+                other => other,
+            },
+            (_, Some(path), _) => SourceConfig::File {
+                format: IqFormat::from_extension(Path::new(path)),
+                path: PathBuf::from(path),
+                sample_rate: rate.unwrap_or(Hertz(3_200_000)),
+            },
+            (_, _, Some((host, port))) => SourceConfig::RtlTcp {
+                host: host.to_string(),
+                port: port
+                    .parse()
+                    .with_context(|| format!("invalid rtl_tcp port {port}"))?,
+                sample_rate: rate.unwrap_or(Hertz(2_400_000)),
+                gain,
+            },
+            ("rtlsdr", _, _) if cfg!(feature = "rtlsdr") => SourceConfig::RtlSdr {
+                sample_rate: rate.unwrap_or(Hertz(2_400_000)),
+                gain,
+            },
+            _ => bail!("Unknown device {device}\n\n{USAGE}"),
+        };
+        options.hardware = Some(device);
+        let output = output.with_context(|| format!("No output given\n\n{USAGE}"))?;
+        Ok(Self {
+            output,
+            source_config,
+            options,
+        })
+    }
+}
+
+pub fn run(args: impl IntoIterator<Item = String>) -> anyhow::Result<()> {
+    let args = Args::parse(args)?;
+    let journal_path = crate::state_dir().map(|dir| dir.join("recording.journal"));
+    if let Some(path) = &journal_path {
+        match journal::recover(path) {
+            Ok(Some(SessionRecord {
+                recording: Some(previous),
+                ..
+            })) => eprintln!("Finalized interrupted recording {}", previous.display()),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to read journal {}: {}", path.display(), e),
+        }
+    }
+
+    let recording = Recording::new(args.source_config.clone(), &args.output, &args.options)?;
+    let cancel = recording.cancel_token();
+    ctrlc::set_handler(move || cancel.cancel()).context("installing Ctrl-C handler")?;
+
+    let data_path = recording.data_path().to_path_buf();
+    let sample_rate = recording.sample_rate();
+    // Journal the recording, so a crash leaves it to be finalized next time
+    let journal = journal_path.and_then(|path| {
+        let mut journal = Journal::create(&path)
+            .inspect_err(|e| warn!("Not journaling the recording: {}", e))
+            .ok()?;
+        let record = SessionRecord {
+            source_config: args.source_config,
+            center_frequency: args.options.center_frequency,
+            gain: Decibels(0.0),
+            recording: Some(data_path.clone()),
+        };
+        if let Err(e) = journal.write(&record) {
+            warn!("Failed to journal the recording: {}", e);
+        }
+        Some(journal)
+    });
+    match args.options.duration {
+        Some(duration) => eprintln!(
+            "Recording {:.1} s to {}",
+            duration.as_secs_f64(),
+            data_path.display()
+        ),
+        None => eprintln!("Recording to {}, press Ctrl-C to stop", data_path.display()),
+    }
+    let samples = recording.run()?;
+    if let Some(journal) = journal {
+        journal.close();
+    }
+    eprintln!(
+        "Recorded {samples} samples ({:.3} s at {sample_rate})",
+        samples as f64 / sample_rate.as_hz() as f64
+    );
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Read-only spectators of an engine serving a UI: any number of clients on
+//! a socket of their own, sent the engine's events like the UI but with
+//! their commands ignored, so a class or team can watch one receiver. Each
+//! spectator has its own queues; one whose link can't keep up is sent fewer
+//! spectrum frames instead of holding up the engine or the others.
+
+use std::io::{BufReader, ErrorKind};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use anyhow::Context;
+use flume::TrySendError;
+use log::{debug, error, info};
+use rustiq_messages::{Command, Event, read_frame};
+
+use crate::streaming::{self, Frame, Outlet};
+use crate::transport::{self, Address, Listener, Reader, ServerSecurity, Writer};
+
+/// Spectrum frames queued for each spectator. Frames beyond these are
+/// dropped, so each spectator gets as many as its link carries.
+const SPECTRUM_QUEUE: usize = 2;
+
+/// Other events queued for each spectator. A spectator this far behind is
+/// disconnected.
+const EVENT_QUEUE: usize = 1024;
+
+#[derive(Default)]
+struct Inner {
+    spectators: Vec<Outlet>,
+    /// Latest state snapshot and capabilities, to bring those joining up
+    /// to date
+    state: Option<Frame>,
+    capabilities: Option<Frame>,
+}
+
+/// The spectators watching the engine.
+pub struct Spectators {
+    inner: Mutex<Inner>,
+}
+
+impl Spectators {
+    /// Let spectators presenting `security` join on `socket`.
+    pub fn listen(socket: &Address, security: ServerSecurity) -> anyhow::Result<Arc<Self>> {
+        let listener = Listener::bind(socket).with_context(|| format!("binding {socket}"))?;
+        info!("Spectators can watch on {}", socket);
+        let spectators = Arc::new(Self {
+            inner: Mutex::new(Inner::default()),
+        });
+        let joining = spectators.clone();
+        thread::spawn(move || {
+            loop {
+                match transport::accept(&listener, &security) {
+                    Ok(stream) => joining.join(stream),
+                    Err(e) => error!("Failed to add spectator: {}", e),
+                }
+            }
+        });
+        Ok(spectators)
+    }
+
+    fn join(&self, (reader, writer): (Reader, Writer)) {
+        thread::spawn(move || ignore_commands(reader));
+        let outlet = Outlet::open(writer, EVENT_QUEUE, SPECTRUM_QUEUE);
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        for frame in [&inner.state, &inner.capabilities].into_iter().flatten() {
+            let _ = outlet.offer_event(frame);
+        }
+        inner.spectators.push(outlet);
+        info!("Spectator joined, {} watching", inner.spectators.len());
+    }
+
+    /// Send `event` to every spectator.
+    pub fn broadcast(&self, event: &Event) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Event::SpectrumData(frame) = event {
+            if inner.spectators.is_empty() {
+                return;
+            }
+            let frame = Arc::new(frame.clone());
+            inner
+                .spectators
+                .retain(|spectator| spectator.offer_spectrum(&frame));
+            return;
+        }
+        let catch_up = matches!(event, Event::StateSnapshot(_) | Event::Capabilities(_));
+        if inner.spectators.is_empty() && !catch_up {
+            return;
+        }
+        let frame = match streaming::encode(event) {
+            Ok(frame) => frame,
+            Err(e) => {
+                debug!("Failed to encode event for spectators: {}", e);
+                return;
+            }
+        };
+        match event {
+            Event::StateSnapshot(_) => inner.state = Some(frame.clone()),
+            Event::Capabilities(_) => inner.capabilities = Some(frame.clone()),
+            _ => {}
+        }
+        for spectator in std::mem::take(&mut inner.spectators) {
+            match spectator.offer_event(&frame) {
+                Ok(()) => inner.spectators.push(spectator),
+                Err(TrySendError::Full(_)) => {
+                    info!("Spectator fell too far behind, disconnecting it");
+                    spectator.close();
+                }
+                Err(TrySendError::Disconnected(_)) => info!("Spectator left"),
+            }
+        }
+    }
+}
+
+/// Read and discard a spectator's commands, which a UI sends as usual.
+fn ignore_commands(stream: Reader) {
+    let mut reader = BufReader::new(stream);
+    let mut ignored = false;
+    loop {
+        match read_frame::<Command>(&mut reader) {
+            Ok(command) => {
+                if !ignored {
+                    info!("Ignoring commands from a spectator");
+                    ignored = true;
+                }
+                debug!("Ignored spectator command: {:?}", command);
+            }
+            Err(e) => {
+                if e.kind() != ErrorKind::UnexpectedEof {
+                    debug!("Failed to read spectator command: {}", e);
+                }
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Sending events to one connection from a queue of its own, with spectrum
+//! frames cut down to what a network link carries. Over TCP each frame goes
+//! out as a `ReducedSpectrum`: quantized, delta-coded and compressed, and
+//! with fewer bins and frames the further the link falls behind. The
+//! connection's `Adapter` picks how far to cut from how busy writing keeps
+//! it and whether frames pile up in its queue; the other end's `Expander`
+//! turns them back into full frames.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use flume::{Receiver, Sender, TrySendError};
+use log::{debug, info};
+use rustiq_messages::{Event, ReducedSpectrum, SpectrumFrame, write_frame};
+
+use crate::transport::Writer;
+
+/// Bins merged into one and the least time between frames at each level,
+/// from the full spectrum to the most cut down.
+const LEVELS: [(usize, Duration); 6] = [
+    (1, Duration::ZERO),
+    (2, Duration::ZERO),
+    (4, Duration::from_millis(50)),
+    (8, Duration::from_millis(100)),
+    (16, Duration::from_millis(200)),
+    (32, Duration::from_millis(500)),
+];
+
+/// Resolution of the quantized levels, in dB.
+const STEP_DB: f32 = 0.1;
+
+/// Frames of a source between ones sent whole rather than as a change.
+const KEYFRAME_INTERVAL: u64 = 50;
+
+/// How often the level is reconsidered.
+const ADAPT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Share of the time spent writing above which the link is saturated, and
+/// below which it has room for more.
+const BUSY_HIGH: f64 = 0.8;
+const BUSY_LOW: f64 = 0.3;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// An event encoded as a wire frame, shareable between connections.
+pub type Frame = Arc<Vec<u8>>;
+
+/// Encode `event` as a wire frame.
+pub fn encode(event: &Event) -> io::Result<Frame> {
+    let mut frame = Vec::new();
+    write_frame(&mut frame, event)?;
+    Ok(Arc::new(frame))
+}
+
+/// Queues to one connection, written out by a thread of its own.
+pub struct Outlet {
+    events: Sender<Frame>,
+    spectrum: Sender<Arc<SpectrumFrame>>,
+    /// Spectrum frames dropped for a full queue
+    dropped: Arc<AtomicU64>,
+    /// Spectrum frames are cut down to the link
+    adaptive: bool,
+    /// For closing the connection under the writing thread
+    closer: io::Result<Writer>,
+    thread: JoinHandle<()>,
+}
+
+impl Outlet {
+    /// Write events queued up to `event_queue` deep, and spectrum frames up
+    /// to `spectrum_queue` deep, to `stream`. Spectrum frames to a remote
+    /// stream are cut down to what its link carries.
+    pub fn open(stream: Writer, event_queue: usize, spectrum_queue: usize) -> Self {
+        let (events, events_rx) = flume::bounded(event_queue);
+        let (spectrum, spectrum_rx) = flume::bounded(spectrum_queue);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let adaptive = stream.is_remote();
+        let adapter = adaptive.then(|| Adapter::new(dropped.clone()));
+        let closer = stream.try_clone();
+        let thread = thread::spawn(move || send_events(stream, &events_rx, &spectrum_rx, adapter));
+        Self {
+            events,
+            spectrum,
+            dropped,
+            adaptive,
+            closer,
+            thread,
+        }
+    }
+
+    /// Queue `event`, waiting for room. Spectrum frames for a remote link
+    /// are dropped instead of waiting, as the link sets their pace. False
+    /// once the connection is gone.
+    pub fn send(&self, event: Event) -> bool {
+        if let Event::SpectrumData(frame) = event {
+            if self.adaptive {
+                return self.offer_spectrum(&Arc::new(frame));
+            }
+            return self.spectrum.send(Arc::new(frame)).is_ok();
+        }
+        match encode(&event) {
+            Ok(frame) => self.events.send(frame).is_ok(),
+            Err(e) => {
+                debug!("Failed to encode event: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Queue `frame` if there's room.
+    pub fn offer_event(&self, frame: &Frame) -> Result<(), TrySendError<Frame>> {
+        self.events.try_send(frame.clone())
+    }
+
+    /// Queue `frame` if there's room, otherwise drop it. False once the
+    /// connection is gone.
+    pub fn offer_spectrum(&self, frame: &Arc<SpectrumFrame>) -> bool {
+        match self.spectrum.try_send(frame.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// Close the connection, dropping whatever is still queued.
+    pub fn close(self) {
+        if let Ok(closer) = &self.closer {
+            closer.shutdown();
+        }
+    }
+
+    /// Send what is still queued, then close the connection.
+    pub fn finish(self) {
+        drop(self.events);
+        drop(self.spectrum);
+        let _ = self.thread.join();
+    }
+}
+
+/// Write queued events to `stream` until it disconnects or the queues are
+/// dropped.
+fn send_events(
+    mut stream: Writer,
+    events: &Receiver<Frame>,
+    spectrum: &Receiver<Arc<SpectrumFrame>>,
+    mut adapter: Option<Adapter>,
+) {
+    enum Queued {
+        Event(Frame),
+        Spectrum(Arc<SpectrumFrame>),
+    }
+    loop {
+        // Other events go out ahead of queued spectrum frames
+        let queued = match events.try_recv() {
+            Ok(frame) => Ok(Queued::Event(frame)),
+            Err(_) => flume::Selector::new()
+                .recv(events, |frame| frame.map(Queued::Event))
+                .recv(spectrum, |frame| frame.map(Queued::Spectrum))
+                .wait(),
+        };
+        let frame = match queued {
+            Ok(Queued::Event(frame)) => frame,
+            Ok(Queued::Spectrum(frame)) => {
+                let event = match &mut adapter {
+                    Some(adapter) => match adapter.reduce(&frame) {
+                        Some(reduced) => Event::ReducedSpectrum(reduced),
+                        None => continue,
+                    },
+                    None => Event::SpectrumData(Arc::unwrap_or_clone(frame)),
+                };
+                match encode(&event) {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        debug!("Failed to encode spectrum frame: {}", e);
+                        continue;
+                    }
+                }
+            }
+            Err(_) => break,
+        };
+        let started = Instant::now();
+        if let Err(e) = stream.write_all(&frame) {
+            debug!("Failed to send event: {}", e);
+            break;
+        }
+        if let Some(adapter) = &mut adapter {
+            adapter.wrote(started.elapsed());
+        }
+    }
+    stream.shutdown();
+}
+
+/// What was last sent of one source's spectrum.
+struct SourceState {
+    sent_at: Instant,
+    levels: Vec<i16>,
+    since_keyframe: u64,
+}
+
+/// Cuts spectrum frames down to what one link carries, stepping down a
+/// level while the link can't keep up and back up once it has room.
+struct Adapter {
+    /// Index into `LEVELS`
+    level: usize,
+    sources: HashMap<usize, SourceState>,
+    window_start: Instant,
+    /// Time spent writing since `window_start`
+    busy: Duration,
+    /// Frames dropped for a full queue, counted by the `Outlet`
+    dropped: Arc<AtomicU64>,
+    last_dropped: u64,
+}
+
+impl Adapter {
+    fn new(dropped: Arc<AtomicU64>) -> Self {
+        Self {
+            level: 0,
+            sources: HashMap::new(),
+            window_start: Instant::now(),
+            busy: Duration::ZERO,
+            dropped,
+            last_dropped: 0,
+        }
+    }
+
+    /// `frame` cut down to the current level, or None to skip it to keep
+    /// to the level's frame rate.
+    fn reduce(&mut self, frame: &SpectrumFrame) -> Option<ReducedSpectrum> {
+        let (merge, interval) = LEVELS[self.level];
+        let now = Instant::now();
+        let previous = self.sources.get(&frame.source);
+        if let Some(previous) = previous
+            && now.duration_since(previous.sent_at) < interval
+            && frame.discontinuity.is_none()
+        {
+            return None;
+        }
+        let levels: Vec<i16> = frame
+            .magnitudes
+            .chunks(merge)
+            .map(|group| quantize(group.iter().copied().fold(0.0, f32::max)))
+            .collect();
+        let delta = previous.filter(|previous| {
+            previous.levels.len() == levels.len()
+                && previous.since_keyframe < KEYFRAME_INTERVAL
+                && frame.discontinuity.is_none()
+        });
+        let coded: Vec<u8> = match delta {
+            Some(previous) => levels
+                .iter()
+                .zip(&previous.levels)
+                .flat_map(|(level, previous)| level.wrapping_sub(*previous).to_le_bytes())
+                .collect(),
+            None => levels
+                .iter()
+                .flat_map(|level| level.to_le_bytes())
+                .collect(),
+        };
+        let compressed = match zstd::bulk::compress(&coded, ZSTD_LEVEL) {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                debug!("Failed to compress spectrum frame: {}", e);
+                return None;
+            }
+        };
+        let is_delta = delta.is_some();
+        let since_keyframe = if is_delta {
+            previous.map_or(0, |previous| previous.since_keyframe + 1)
+        } else {
+            0
+        };
+        self.sources.insert(
+            frame.source,
+            SourceState {
+                sent_at: now,
+                levels,
+                since_keyframe,
+            },
+        );
+        Some(ReducedSpectrum {
+            frame: SpectrumFrame {
+                sequence: frame.sequence,
+                sample_time: frame.sample_time,
+                timestamp: frame.timestamp,
+                source: frame.source,
+                discontinuity: frame.discontinuity,
+                center_frequency: frame.center_frequency,
+                sample_rate: frame.sample_rate,
+                magnitudes: Vec::new(),
+                peak_hold: merge_bins(&frame.peak_hold, merge, f32::max),
+                min_hold: merge_bins(&frame.min_hold, merge, f32::min),
+            },
+            bins: frame.magnitudes.len(),
+            merge,
+            delta: is_delta,
+            levels: compressed,
+        })
+    }
+
+    /// Account `elapsed` spent writing a frame, and reconsider the level
+    /// every `ADAPT_INTERVAL`.
+    fn wrote(&mut self, elapsed: Duration) {
+        self.busy += elapsed;
+        let window = self.window_start.elapsed();
+        if window < ADAPT_INTERVAL {
+            return;
+        }
+        let busy = self.busy.as_secs_f64() / window.as_secs_f64();
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        let dropping = dropped > self.last_dropped;
+        let level = if (dropping || busy > BUSY_HIGH) && self.level + 1 < LEVELS.len() {
+            self.level + 1
+        } else if !dropping && busy < BUSY_LOW && self.level > 0 {
+            self.level - 1
+        } else {
+            self.level
+        };
+        if level != self.level {
+            info!(
+                "Link {} at {:.0}% busy, sending spectrum with {} bins merged",
+                if level > self.level {
+                    "behind"
+                } else {
+                    "has room"
+                },
+                busy * 100.0,
+                LEVELS[level].0
+            );
+            self.level = level;
+            // The next frames of each source go out whole, at the new size
+            self.sources.clear();
+        }
+        self.window_start = Instant::now();
+        self.busy = Duration::ZERO;
+        self.last_dropped = dropped;
+    }
+}
+
+/// Turns the `ReducedSpectrum`s from one connection back into full frames,
+/// each merged level repeated across the bins it stands for.
+#[derive(Default)]
+pub struct Expander {
+    /// Levels of each source's last frame, which deltas apply to
+    previous: HashMap<usize, Vec<i16>>,
+}
+
+impl Expander {
+    pub fn expand(&mut self, reduced: ReducedSpectrum) -> io::Result<SpectrumFrame> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        if reduced.merge == 0 {
+            return Err(invalid("spectrum with no bins merged"));
+        }
+        let count = reduced.bins.div_ceil(reduced.merge);
+        let coded = zstd::bulk::decompress(&reduced.levels, count * 2)?;
+        if coded.len() != count * 2 {
+            return Err(invalid("spectrum levels don't match its bins"));
+        }
+        let coded = coded
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]));
+        let source = reduced.frame.source;
+        let levels: Vec<i16> = if reduced.delta {
+            let previous = self
+                .previous
+                .get(&source)
+                .filter(|previous| previous.len() == count)
+                .ok_or_else(|| invalid("spectrum change without a frame to apply it to"))?;
+            coded
+                .zip(previous)
+                .map(|(change, previous)| previous.wrapping_add(change))
+                .collect()
+        } else {
+            coded.collect()
+        };
+        let mut frame = reduced.frame;
+        frame.magnitudes = levels
+            .iter()
+            .flat_map(|level| std::iter::repeat_n(dequantize(*level), reduced.merge))
+            .take(reduced.bins)
+            .collect();
+        let spread = |merged: &[f32]| -> Vec<f32> {
+            merged
+                .iter()
+                .flat_map(|level| std::iter::repeat_n(*level, reduced.merge))
+                .take(reduced.bins)
+                .collect()
+        };
+        frame.peak_hold = frame.peak_hold.as_deref().map(spread);
+        frame.min_hold = frame.min_hold.as_deref().map(spread);
+        self.previous.insert(source, levels);
+        Ok(frame)
+    }
+}
+
+/// Each group of `merge` bins of a hold trace, if any, as the level `keep`
+/// picks of them.
+fn merge_bins(
+    trace: &Option<Vec<f32>>,
+    merge: usize,
+    keep: fn(f32, f32) -> f32,
+) -> Option<Vec<f32>> {
+    let trace = trace.as_ref()?;
+    Some(
+        trace
+            .chunks(merge)
+            .filter_map(|group| group.iter().copied().reduce(keep))
+            .collect(),
+    )
+}
+
+/// `magnitude` in steps of `STEP_DB`.
+fn quantize(magnitude: f32) -> i16 {
+    (20.0 * magnitude.max(f32::MIN_POSITIVE).log10() / STEP_DB).round() as i16
+}
+
+fn dequantize(level: i16) -> f32 {
+    10f32.powf(level as f32 * STEP_DB / 20.0)
+}
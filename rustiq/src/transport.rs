@@ -0,0 +1,480 @@
+//! The sockets the engine and its UIs talk over: a Unix domain socket on
+//! one machine, or TCP across a network. A TCP connection can be wrapped in
+//! TLS, and a server can require a token of each client, as engines often
+//! sit on shared networks.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::ops::Range;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::Context;
+use log::warn;
+use rustiq_messages::{read_frame, write_frame};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+
+/// How long a client has to finish the TLS handshake and present its token.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a server listens: a Unix socket path, or `HOST:PORT` for TCP.
+#[derive(Debug, Clone)]
+pub enum Address {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl Address {
+    /// `HOST:PORT` is TCP, anything else a socket path.
+    pub fn parse(address: &str) -> Self {
+        let tcp = !address.contains('/')
+            && address
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if tcp {
+            Address::Tcp(address.to_string())
+        } else {
+            Address::Unix(PathBuf::from(address))
+        }
+    }
+
+    /// Host part of a TCP address, for checking the server's certificate.
+    fn host(&self) -> Option<&str> {
+        match self {
+            Address::Unix(_) => None,
+            Address::Tcp(address) => address
+                .rsplit_once(':')
+                .map(|(host, _)| host.trim_start_matches('[').trim_end_matches(']')),
+        }
+    }
+
+    /// Remove the socket file a Unix socket leaves behind.
+    pub fn remove(&self) {
+        if let Address::Unix(path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Unix(path) => write!(f, "{}", path.display()),
+            Address::Tcp(address) => write!(f, "tcp {address}"),
+        }
+    }
+}
+
+/// A connected socket of either kind.
+pub enum Socket {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Socket {
+    pub fn connect(address: &Address) -> io::Result<Self> {
+        match address {
+            Address::Unix(path) => UnixStream::connect(path).map(Socket::Unix),
+            Address::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                // Commands and state are small; don't hold them back
+                stream.set_nodelay(true)?;
+                Ok(Socket::Tcp(stream))
+            }
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Socket::Unix(stream) => stream.try_clone().map(Socket::Unix),
+            Socket::Tcp(stream) => stream.try_clone().map(Socket::Tcp),
+        }
+    }
+
+    fn shutdown(&self) {
+        let _ = match self {
+            Socket::Unix(stream) => stream.shutdown(Shutdown::Both),
+            Socket::Tcp(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Socket::Unix(stream) => stream.set_read_timeout(timeout),
+            Socket::Tcp(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Socket::Unix(stream) => stream.read(buf),
+            Socket::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Socket::Unix(stream) => stream.write(buf),
+            Socket::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Socket::Unix(stream) => stream.flush(),
+            Socket::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+/// A listening socket of either kind.
+pub enum Listener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    pub fn bind(address: &Address) -> io::Result<Self> {
+        match address {
+            Address::Unix(path) => {
+                // A socket file left behind by an engine that crashed would
+                // block binding
+                let _ = std::fs::remove_file(path);
+                UnixListener::bind(path).map(Listener::Unix)
+            }
+            Address::Tcp(address) => TcpListener::bind(address).map(Listener::Tcp),
+        }
+    }
+
+    fn accept_socket(&self) -> io::Result<Socket> {
+        match self {
+            Listener::Unix(listener) => listener.accept().map(|(stream, _)| Socket::Unix(stream)),
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Ok(Socket::Tcp(stream))
+            }
+        }
+    }
+}
+
+/// What a server asks of its clients.
+#[derive(Clone, Default)]
+pub struct ServerSecurity {
+    /// Token each client must present
+    pub token: Option<String>,
+    pub tls: Option<Arc<ServerConfig>>,
+}
+
+impl ServerSecurity {
+    /// Security read from a token file and a PEM certificate chain and key.
+    pub fn load(
+        token_file: Option<&Path>,
+        cert: Option<&Path>,
+        key: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let tls = match (cert, key) {
+            (Some(cert), Some(key)) => {
+                let certs = CertificateDer::pem_file_iter(cert)
+                    .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                    .with_context(|| format!("reading {}", cert.display()))?;
+                let key = PrivateKeyDer::from_pem_file(key)
+                    .with_context(|| format!("reading {}", key.display()))?;
+                let config = ServerConfig::builder()
+                    .with_no_client_auth()
+                    .with_single_cert(certs, key)
+                    .context("loading the TLS certificate")?;
+                Some(Arc::new(config))
+            }
+            (None, None) => None,
+            _ => anyhow::bail!("TLS needs both --tls-cert and --tls-key"),
+        };
+        Ok(Self {
+            token: token_file.map(read_token).transpose()?,
+            tls,
+        })
+    }
+
+    /// Warn if `address` is open to the network without protection.
+    pub fn check(&self, address: &Address) {
+        if matches!(address, Address::Tcp(_)) {
+            if self.token.is_none() {
+                warn!("Anyone who can reach {address} can use it; consider --token-file");
+            }
+            if self.tls.is_none() {
+                warn!("Traffic on {address} is unencrypted; consider --tls-cert");
+            }
+        }
+    }
+}
+
+/// What a client presents to its server.
+#[derive(Clone, Default)]
+pub struct ClientSecurity {
+    pub token: Option<String>,
+    pub tls: Option<Arc<ClientConfig>>,
+}
+
+impl ClientSecurity {
+    /// Security read from a token file and the PEM certificate of the
+    /// authority the server's certificate is checked against.
+    pub fn load(token_file: Option<&Path>, ca: Option<&Path>) -> anyhow::Result<Self> {
+        let tls = match ca {
+            Some(ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in CertificateDer::pem_file_iter(ca)
+                    .with_context(|| format!("reading {}", ca.display()))?
+                {
+                    let cert = cert.with_context(|| format!("reading {}", ca.display()))?;
+                    roots.add(cert).context("loading the TLS authority")?;
+                }
+                let config = ClientConfig::builder()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth();
+                Some(Arc::new(config))
+            }
+            None => None,
+        };
+        Ok(Self {
+            token: token_file.map(read_token).transpose()?,
+            tls,
+        })
+    }
+}
+
+/// The token in `path`, less surrounding whitespace.
+fn read_token(path: &Path) -> anyhow::Result<String> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .trim()
+        .to_string();
+    anyhow::ensure!(!token.is_empty(), "{} is empty", path.display());
+    Ok(token)
+}
+
+/// Accept the next client of `listener` that gets through `security`.
+/// Clients that don't are turned away.
+pub fn accept(listener: &Listener, security: &ServerSecurity) -> io::Result<(Reader, Writer)> {
+    loop {
+        let socket = listener.accept_socket()?;
+        match admit(socket, security) {
+            Ok(halves) => return Ok(halves),
+            Err(e) => warn!("Turned a client away: {}", e),
+        }
+    }
+}
+
+fn admit(socket: Socket, security: &ServerSecurity) -> io::Result<(Reader, Writer)> {
+    // A client that stalls mustn't hold up the ones behind it
+    socket.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let (mut reader, writer) = secure(socket, security.tls.as_ref())?;
+    if let Some(token) = &security.token {
+        let presented: String = read_frame(&mut reader)?;
+        if !same_token(&presented, token) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "wrong token",
+            ));
+        }
+    }
+    reader.socket.set_read_timeout(None)?;
+    Ok((reader, writer))
+}
+
+/// Split a client's `socket`, taking it through the TLS handshake first if
+/// `tls` is given.
+pub fn secure(socket: Socket, tls: Option<&Arc<ServerConfig>>) -> io::Result<(Reader, Writer)> {
+    let tls = tls
+        .map(|config| ServerConnection::new(config.clone()).map(rustls::Connection::from))
+        .transpose()
+        .map_err(io::Error::other)?;
+    split(socket, tls)
+}
+
+/// Compare tokens in time independent of where they differ.
+pub fn same_token(presented: &str, token: &str) -> bool {
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |differ, (a, b)| differ | (a ^ b))
+            == 0
+}
+
+/// Connect to the server at `address`, presenting `security`.
+pub fn connect(address: &Address, security: &ClientSecurity) -> io::Result<(Reader, Writer)> {
+    let (reader, mut writer) = open(address, security.tls.as_ref())?;
+    if let Some(token) = &security.token {
+        write_frame(&mut writer, token)?;
+    }
+    Ok((reader, writer))
+}
+
+/// Connect to `address`, over TLS checked against `tls` if given.
+pub fn open(address: &Address, tls: Option<&Arc<ClientConfig>>) -> io::Result<(Reader, Writer)> {
+    let socket = Socket::connect(address)?;
+    let tls = match tls {
+        Some(config) => {
+            let host = address.host().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "TLS needs a TCP address")
+            })?;
+            let name = ServerName::try_from(host.to_string())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let connection =
+                ClientConnection::new(config.clone(), name).map_err(io::Error::other)?;
+            Some(rustls::Connection::from(connection))
+        }
+        None => None,
+    };
+    split(socket, tls)
+}
+
+/// Split `socket` into halves for reading and writing on separate threads,
+/// finishing the TLS handshake first if there is one.
+fn split(mut socket: Socket, tls: Option<rustls::Connection>) -> io::Result<(Reader, Writer)> {
+    let tls = match tls {
+        Some(mut connection) => {
+            while connection.is_handshaking() {
+                connection.complete_io(&mut socket)?;
+            }
+            Some(Arc::new(Mutex::new(connection)))
+        }
+        None => None,
+    };
+    let reader = Reader {
+        socket: socket.try_clone()?,
+        tls: tls.clone(),
+        writer: socket.try_clone()?,
+        received: vec![0; 16 * 1024],
+        pending: 0..0,
+    };
+    Ok((reader, Writer { socket, tls }))
+}
+
+/// Receiving half of a connection.
+pub struct Reader {
+    socket: Socket,
+    tls: Option<Arc<Mutex<rustls::Connection>>>,
+    /// For what TLS sends back as it reads, like alerts
+    writer: Socket,
+    /// Bytes read off the socket for decryption
+    received: Vec<u8>,
+    /// Those of them not yet decrypted
+    pending: Range<usize>,
+}
+
+impl Reader {
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_read_timeout(timeout)
+    }
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(tls) = &self.tls else {
+            return self.socket.read(buf);
+        };
+        loop {
+            {
+                let mut tls = tls.lock().unwrap_or_else(PoisonError::into_inner);
+                match tls.reader().read(buf) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    result => return result,
+                }
+            }
+            if self.pending.is_empty() {
+                // Off the lock, so the writing half isn't held up meanwhile
+                let len = self.socket.read(&mut self.received)?;
+                if len == 0 {
+                    return Ok(0);
+                }
+                self.pending = 0..len;
+            }
+            // A record at a time, taking the plaintext out between them so
+            // it doesn't pile up past rustls' limit
+            let mut tls = tls.lock().unwrap_or_else(PoisonError::into_inner);
+            let len = tls.read_tls(&mut &self.received[self.pending.clone()])?;
+            self.pending.start += len;
+            tls.process_new_packets().map_err(io::Error::other)?;
+            while tls.wants_write() {
+                tls.write_tls(&mut self.writer)?;
+            }
+        }
+    }
+}
+
+/// Sending half of a connection.
+pub struct Writer {
+    socket: Socket,
+    tls: Option<Arc<Mutex<rustls::Connection>>>,
+}
+
+impl Writer {
+    /// Close the connection, which also ends the other half.
+    pub fn shutdown(&self) {
+        self.socket.shutdown();
+    }
+
+    /// The connection crosses a network, rather than a local socket.
+    pub fn is_remote(&self) -> bool {
+        matches!(self.socket, Socket::Tcp(_))
+    }
+
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            socket: self.socket.try_clone()?,
+            tls: self.tls.clone(),
+        })
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(tls) = &self.tls else {
+            return self.socket.write(buf);
+        };
+        let mut tls = tls.lock().unwrap_or_else(PoisonError::into_inner);
+        let len = tls.writer().write(buf)?;
+        while tls.wants_write() {
+            tls.write_tls(&mut self.socket)?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.socket.flush()
+    }
+}
+
+/// Both halves of a connection as one stream, for a WebSocket on top.
+/// Sending needs no reading half, so one that only sends can do with
+/// `io::Empty` for it.
+pub struct Duplex<R = Reader> {
+    pub reader: R,
+    pub writer: Writer,
+}
+
+impl<R: Read> Read for Duplex<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R> Write for Duplex<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
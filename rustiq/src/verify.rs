@@ -0,0 +1,59 @@
+//! `rustiq verify`: check a recording against its checksum manifest.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use rustiq_engine::integrity;
+
+pub const USAGE: &str = "\
+Usage: rustiq verify RECORDING
+
+Check RECORDING.sigmf-data against the checksums `rustiq record` wrote to
+RECORDING.sigmf-sha256, reporting corrupt chunks and truncated or unfinished
+captures. Exits with an error unless the recording is intact.";
+
+pub fn run(args: impl IntoIterator<Item = String>) -> anyhow::Result<()> {
+    let mut recording = None;
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => bail!("{USAGE}"),
+            flag if flag.starts_with('-') => bail!("Unknown option {flag}\n\n{USAGE}"),
+            _ if recording.is_none() => recording = Some(PathBuf::from(arg)),
+            _ => bail!("Unexpected argument {arg}\n\n{USAGE}"),
+        }
+    }
+    let recording = recording.with_context(|| format!("No recording given\n\n{USAGE}"))?;
+    let data_path = recording.with_extension("sigmf-data");
+    let verification = integrity::verify(&data_path)
+        .with_context(|| format!("verifying {}", data_path.display()))?;
+
+    println!("File:    {}", data_path.display());
+    println!("Good:    {} chunks", verification.good_chunks);
+    for chunk in &verification.corrupt_chunks {
+        println!(
+            "Corrupt: chunk {chunk} (bytes {} to {})",
+            chunk * verification.chunk_bytes,
+            (chunk + 1) * verification.chunk_bytes
+        );
+    }
+    if verification.missing_chunks > 0 {
+        println!(
+            "Missing: {} chunks, the data is truncated",
+            verification.missing_chunks
+        );
+    }
+    if verification.unchecked_bytes > 0 {
+        println!(
+            "Unchecked: {} bytes past the last checksum",
+            verification.unchecked_bytes
+        );
+    }
+    if !verification.finished {
+        println!("The recording didn't finish; it was interrupted");
+    }
+    if !verification.passed() {
+        bail!("{} failed verification", data_path.display());
+    }
+    println!("Intact");
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! The UI's side of a headless engine (see `headless`): commands and events
+//! as JSON text messages over a WebSocket, behind the same `Transport` as
+//! wire frames over a socket.
+
+use std::io::{self, ErrorKind};
+
+use log::debug;
+use rustiq_messages::{Command, CommandSender, Event, EventReceiver, Transport};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::error::ProtocolError;
+use tungstenite::http::{HeaderValue, header};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use crate::transport::{self, Address, ClientSecurity, Duplex};
+
+/// A WebSocket to a headless engine, with a second handle on the
+/// connection for writing so commands and events each have a thread of
+/// their own.
+pub struct WebSocketTransport {
+    reader: WebSocket<Duplex>,
+    writer: WebSocket<Duplex<io::Empty>>,
+}
+
+impl WebSocketTransport {
+    /// Open `url` (`ws://HOST:PORT`, or `wss://HOST:PORT` for TLS),
+    /// presenting `security`. The token goes in the `Authorization` header,
+    /// not the URL, which tends to end up in logs.
+    pub fn connect(url: &str, security: &ClientSecurity) -> io::Result<Self> {
+        let (tls, host) = match url.split_once("://") {
+            Some(("ws", rest)) => (None, rest),
+            Some(("wss", rest)) => {
+                let tls = security.tls.as_ref().ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidInput, "wss:// needs --tls-ca")
+                })?;
+                (Some(tls), rest)
+            }
+            _ => (None, ""),
+        };
+        let host = host
+            .split('/')
+            .next()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "not a ws://HOST:PORT URL"))?;
+        let (reader, writer) = transport::open(&Address::Tcp(host.to_string()), tls)?;
+        let mut request = url.into_client_request().map_err(io::Error::other)?;
+        if let Some(token) = &security.token {
+            let bearer = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+            request.headers_mut().insert(header::AUTHORIZATION, bearer);
+        }
+        let stream = Duplex {
+            reader,
+            writer: writer.try_clone()?,
+        };
+        let (reader, _) = tungstenite::client(request, stream).map_err(|e| match e {
+            tungstenite::HandshakeError::Failure(tungstenite::Error::Http(response))
+                if response.status() == 401 =>
+            {
+                io::Error::new(ErrorKind::PermissionDenied, "wrong token")
+            }
+            e => io::Error::other(e.to_string()),
+        })?;
+        let writer = WebSocket::from_raw_socket(
+            Duplex {
+                reader: io::empty(),
+                writer,
+            },
+            Role::Client,
+            None,
+        );
+        Ok(Self { reader, writer })
+    }
+}
+
+impl Transport for WebSocketTransport {
+    type Events = JsonEvents;
+    type Commands = JsonCommands;
+
+    fn split(self) -> (JsonEvents, JsonCommands) {
+        (JsonEvents(self.reader), JsonCommands(self.writer))
+    }
+}
+
+pub struct JsonEvents(WebSocket<Duplex>);
+
+impl EventReceiver for JsonEvents {
+    fn recv(&mut self) -> io::Result<Event> {
+        loop {
+            match self.0.read() {
+                Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                    Ok(event) => return Ok(event),
+                    // Likely from a newer engine; the rest may still be of use
+                    Err(e) => debug!("Skipping unreadable event: {}", e),
+                },
+                Ok(Message::Close(_))
+                | Err(
+                    tungstenite::Error::ConnectionClosed
+                    | tungstenite::Error::Protocol(ProtocolError::ResetWithoutClosingHandshake),
+                ) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "the engine closed the connection",
+                    ));
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) => return Err(e),
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+    }
+}
+
+pub struct JsonCommands(WebSocket<Duplex<io::Empty>>);
+
+impl CommandSender for JsonCommands {
+    fn send(&mut self, command: &Command) -> io::Result<()> {
+        let json = serde_json::to_string(command).map_err(io::Error::other)?;
+        self.0.send(Message::text(json)).map_err(|e| match e {
+            tungstenite::Error::Io(e) => e,
+            e => io::Error::other(e),
+        })
+    }
+}